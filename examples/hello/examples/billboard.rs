@@ -0,0 +1,138 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use massive_geometry::{Camera, Color, Matrix4, Point3, UnitSystem, Vector3};
+use massive_scene::{Position, PositionedShape};
+use massive_shapes::{Billboard, TextWeight};
+use massive_shell::{shell, widgets::LineLayout, ApplicationContext};
+
+/// The radius of the ring of labels, in pixels.
+const RADIUS: f64 = 300.0;
+const LABELS: usize = 8;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let mut yaw: f64 = 0.0;
+    let mut pitch: f64 = 0.0;
+    let camera = orbit_camera(camera_distance, yaw, pitch);
+
+    let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    // A ring of labels on the x / z plane. Even labels face the camera, odd labels only turn
+    // around their vertical axis.
+    let mut labels = Vec::new();
+    for i in 0..LABELS {
+        let angle = i as f64 / LABELS as f64 * std::f64::consts::TAU;
+        let matrix = director.cast(Matrix4::from_translation(Vector3::new(
+            angle.sin() * RADIUS,
+            0.0,
+            angle.cos() * RADIUS,
+        )));
+        let position = director.cast(Position::from(matrix));
+
+        let billboard = if i % 2 == 0 {
+            Billboard::Spherical
+        } else {
+            Billboard::Cylindrical
+        };
+        let (layout, run) = LineLayout::shape(
+            &mut font_system.lock().unwrap(),
+            &format!("{billboard:?} {i}"),
+            32.0,
+            TextWeight::NORMAL,
+            Color::BLACK,
+            (0.0, 0.0, 0.0),
+            32.0,
+        );
+        // Center the label on its position.
+        let mut run = run.with_billboard(billboard);
+        run.translation = Vector3::new(
+            -layout.width() as f64 / 2.0,
+            -layout.height() as f64 / 2.0,
+            0.0,
+        );
+        labels.push(director.cast(PositionedShape::new(position, run)));
+    }
+
+    // A label at the center that keeps its size on the screen.
+    let (layout, run) = LineLayout::shape(
+        &mut font_system.lock().unwrap(),
+        "Center",
+        16.0,
+        TextWeight::BOLD,
+        Color::rgb(0.8, 0.1, 0.1),
+        (0.0, 0.0, 0.0),
+        16.0,
+    );
+    let mut run = run
+        .with_billboard(Billboard::Spherical)
+        .with_constant_screen_size();
+    run.translation = Vector3::new(-layout.width() as f64 / 2.0, 0.0, 0.0);
+    let matrix = director.cast(Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)));
+    let position = director.cast(Position::from(matrix));
+    labels.push(director.cast(PositionedShape::new(position, run)));
+
+    director.action()?;
+
+    loop {
+        match ctx.wait_for_event(&mut renderer).await? {
+            WindowEvent::CloseRequested => return Ok(()),
+            // The arrow keys orbit the camera around the ring.
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                match key {
+                    NamedKey::ArrowLeft => yaw -= 10.0,
+                    NamedKey::ArrowRight => yaw += 10.0,
+                    NamedKey::ArrowUp => pitch = (pitch + 10.0).min(80.0),
+                    NamedKey::ArrowDown => pitch = (pitch - 10.0).max(-80.0),
+                    // F1 cycles through the debug render modes.
+                    NamedKey::F1 => {
+                        renderer.set_debug_mode(renderer.debug_mode().next());
+                        continue;
+                    }
+                    _ => continue,
+                }
+                renderer.update_camera(orbit_camera(camera_distance, yaw, pitch));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A camera looking at the origin from `distance`, rotated by `yaw` and `pitch` degrees.
+fn orbit_camera(distance: f64, yaw: f64, pitch: f64) -> Camera {
+    let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+    let eye = Point3::new(
+        yaw.sin() * pitch.cos() * distance,
+        pitch.sin() * distance,
+        yaw.cos() * pitch.cos() * distance,
+    );
+    Camera::new(eye, (0.0, 0.0, 0.0))
+}
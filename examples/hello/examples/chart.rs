@@ -0,0 +1,190 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use winit::{dpi::LogicalSize, event::WindowEvent};
+
+use massive_geometry::{Bounds, Camera, Color, Matrix4, UnitSystem, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::{Border, Dash, Quad};
+use massive_shell::{
+    chart::{axis_labels, AxisLabelStyle, AxisSide, TickFormat, Ticks},
+    shell, ApplicationContext,
+};
+
+/// The size of the plot area in pixels.
+const PLOT_SIZE: (f64, f64) = (800.0, 400.0);
+/// The range of the x axis.
+const X_RANGE: (f64, f64) = (0.0, 2_000_000.0);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    // Center the plot area.
+    let (width, height) = PLOT_SIZE;
+    let matrix = director.cast(Matrix4::from_translation(Vector3::new(
+        -width / 2.0,
+        -height / 2.0,
+        0.0,
+    )));
+    let position = director.cast(Position::from(matrix));
+
+    let _shapes = plot(&mut director, &mut font_system.lock().unwrap(), &position);
+
+    // A legend in the top left corner of the window, which does not move with the camera.
+    let legend_matrix = director.cast(Matrix4::from_translation(Vector3::new(16.0, 16.0, 0.0)));
+    let legend = director.cast(Position {
+        parent: None,
+        matrix: legend_matrix,
+        pin: None,
+        overlay: true,
+    });
+    let _legend = director.cast(PositionedShape::new(
+        legend,
+        vec![
+            line((0.0, 12.0), (120.0, 12.0), 24.0, Color::rgb(0.9, 0.9, 0.9)),
+            line((8.0, 12.0), (40.0, 12.0), 2.0, Color::rgb(0.2, 0.4, 0.9)),
+        ],
+    ));
+    director.action()?;
+
+    loop {
+        let event = ctx.wait_for_event(&mut renderer).await?;
+        if let WindowEvent::CloseRequested = event {
+            return Ok(());
+        }
+    }
+}
+
+/// A sine wave with dense x ticks, which rotate their labels.
+fn plot(
+    director: &mut Director,
+    font_system: &mut FontSystem,
+    position: &Handle<Position>,
+) -> Vec<Handle<PositionedShape>> {
+    let (width, height) = PLOT_SIZE;
+    let style = AxisLabelStyle::default();
+    let mut shapes = Vec::new();
+
+    // The curve and the axes.
+    let mut quads: Vec<Quad> = (0..400)
+        .map(|i| {
+            let x = |i: usize| i as f64 / 400.0 * width;
+            let y = |i: usize| (1.0 - (i as f64 / 40.0).sin()) / 2.0 * height;
+            line(
+                (x(i), y(i)),
+                (x(i + 1), y(i + 1)),
+                2.0,
+                Color::rgb(0.2, 0.4, 0.9),
+            )
+        })
+        .collect();
+    quads.push(line((0.0, height), (width, height), 1.0, Color::BLACK));
+    quads.push(line((0.0, 0.0), (0.0, height), 1.0, Color::BLACK));
+
+    // The x axis, at the bottom of the plot.
+    let x_axis_matrix = director.cast(Matrix4::from_translation(Vector3::new(0.0, height, 0.0)));
+    let x_axis = director.cast(Position {
+        parent: Some(position.clone()),
+        matrix: x_axis_matrix,
+        pin: None,
+        overlay: false,
+    });
+    let ticks = Ticks::new(X_RANGE.0, X_RANGE.1, 40);
+    let offsets: Vec<f64> = ticks
+        .values
+        .iter()
+        .map(|v| (v - X_RANGE.0) / (X_RANGE.1 - X_RANGE.0) * width)
+        .collect();
+    for offset in &offsets {
+        quads.push(line(
+            (*offset, height),
+            (*offset, height + 4.0),
+            1.0,
+            Color::BLACK,
+        ));
+    }
+    let labels = axis_labels(
+        font_system,
+        offsets.into_iter().zip(ticks.labels(TickFormat::Si)),
+        AxisSide::Bottom,
+        &style,
+    );
+    for label in labels {
+        let position = match label.matrix {
+            Some(matrix) => {
+                let matrix = director.cast(matrix);
+                director.cast(Position {
+                    parent: Some(x_axis.clone()),
+                    matrix,
+                    pin: None,
+                    overlay: false,
+                })
+            }
+            None => x_axis.clone(),
+        };
+        shapes.push(director.cast(PositionedShape::new(position, label.run)));
+    }
+
+    // The y axis, at the left.
+    let ticks = Ticks::new(-1.0, 1.0, 8);
+    let offsets: Vec<f64> = ticks
+        .values
+        .iter()
+        .map(|v| (1.0 - v) / 2.0 * height)
+        .collect();
+    let labels = axis_labels(
+        font_system,
+        offsets.into_iter().zip(ticks.labels(TickFormat::Auto)),
+        AxisSide::Left,
+        &style,
+    );
+    for label in labels {
+        shapes.push(director.cast(PositionedShape::new(position.clone(), label.run)));
+    }
+
+    shapes.push(director.cast(PositionedShape::new(position.clone(), quads)));
+
+    // A selected range of the x axis, marked with marching ants.
+    let selection = Border::new(
+        Bounds::new((width * 0.25, 0.0), (width * 0.5, height)),
+        1.0,
+        Color::BLACK,
+    )
+    .with_dash(Dash::marching(6.0, 4.0, 20.0));
+    shapes.push(director.cast(PositionedShape::new(position.clone(), selection)));
+    shapes
+}
+
+/// A line between two points as a quad.
+fn line(from: (f64, f64), to: (f64, f64), width: f64, color: Color) -> Quad {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+    // Half of the width, perpendicular to the line.
+    let (nx, ny) = (-dy / length * width / 2.0, dx / length * width / 2.0);
+    Quad::new(
+        [
+            Vector3::new(from.0 + nx, from.1 + ny, 0.0),
+            Vector3::new(to.0 + nx, to.1 + ny, 0.0),
+            Vector3::new(to.0 - nx, to.1 - ny, 0.0),
+            Vector3::new(from.0 - nx, from.1 - ny, 0.0),
+        ],
+        color,
+    )
+}
@@ -0,0 +1,180 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use shared::origin_matrix;
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::{ElementState, KeyEvent, MouseButton, TouchPhase, WindowEvent},
+    keyboard::Key,
+};
+
+use massive_geometry::{Camera, Color, UnitSystem};
+use massive_scene::Position;
+use massive_shapes::TextWeight;
+use massive_shell::{
+    shell,
+    widgets::{DocumentView, Paragraph, ParagraphStyle, ScrollPhysics, ScrollbarStyle, SpanStyle},
+    ApplicationContext, FontFallbackCache,
+};
+
+/// The distance of the view from the window's edges, in physical pixels.
+const MARGIN: f64 = 40.0;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    let size = window.inner_size();
+    let matrix = director.cast(origin_matrix(size, MARGIN));
+    let position = director.cast(Position::from(matrix.clone()));
+    let (width, height) = view_size(size);
+    let mut view = DocumentView::new(position, document(), width, height);
+    // Touches pull the content beyond the edges and it bounces back.
+    view.scroll_mut().set_physics(ScrollPhysics::touch());
+    view.set_scrollbar(Some(ScrollbarStyle::default()));
+
+    // Load the fallback fonts the document needed the last time before it's laid out, the view
+    // requests them directly instead of falling back again.
+    let mut fallback_cache =
+        FontFallbackCache::open(std::env::temp_dir().join("massive-document-view.fallback"));
+    fallback_cache.warm(&mut font_system.lock().unwrap());
+    view.set_font_fallback_cache(Some(fallback_cache));
+
+    let mut cursor = (0.0, 0.0);
+
+    loop {
+        let event = ctx.wait_for_event(&mut renderer).await?;
+
+        match &event {
+            WindowEvent::CloseRequested => return Ok(()),
+            WindowEvent::Resized(size) => {
+                matrix.update(origin_matrix(*size, MARGIN));
+                let (width, height) = view_size(*size);
+                view.set_width(width);
+                view.set_viewport_height(height);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                cursor = (position.x - MARGIN, position.y - MARGIN);
+                view.pointer_moved(cursor.0, cursor.1);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => view.pointer_pressed(cursor.0, cursor.1, false),
+                ElementState::Released => view.pointer_released(),
+            },
+            // Touches pan and fling.
+            WindowEvent::Touch(touch) => {
+                let scroll = view.scroll_mut();
+                let now = Instant::now();
+                match touch.phase {
+                    TouchPhase::Started => scroll.pan_started(touch.location.y, now),
+                    TouchPhase::Moved => scroll.pan_moved(touch.location.y, now),
+                    TouchPhase::Ended | TouchPhase::Cancelled => scroll.pan_ended(now),
+                }
+            }
+            // Digits jump to chapters.
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(c),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if c.parse::<u32>().is_ok() => {
+                let chapter = c.parse::<u32>().unwrap().max(1) * 50;
+                view.scroll_to_anchor(&format!("chapter-{chapter}"), true);
+            }
+            _ => {
+                view.handle_event(&event);
+            }
+        }
+
+        let animating = view.update(
+            &mut director,
+            &mut font_system.lock().unwrap(),
+            Instant::now(),
+        );
+        director.action()?;
+        if animating {
+            window.request_redraw();
+        }
+
+        if let Some(anchor) = view.take_anchor_change() {
+            println!("Current section: {:?}", anchor.map(|anchor| &anchor.id));
+        }
+    }
+}
+
+const BODY: &str = ". Scroll with the mouse wheel, the keyboard, or fling with touches. \
+    Press a digit to jump to a chapter. \
+    Resize the window to wrap the paragraphs at a different width.";
+
+fn document() -> Vec<Paragraph> {
+    let heading = ParagraphStyle {
+        font_size: 32.0,
+        ..ParagraphStyle::default()
+    };
+    let bold = SpanStyle {
+        weight: TextWeight::BOLD,
+        ..SpanStyle::default()
+    };
+    let code = SpanStyle {
+        color: Color::rgb(0.6, 0.1, 0.1),
+        monospace: true,
+        ..SpanStyle::default()
+    };
+
+    (1..=500)
+        .flat_map(|chapter| {
+            [
+                Paragraph::new(heading)
+                    .span(format!("Chapter {chapter}"), bold)
+                    .anchor(format!("chapter-{chapter}"), 1),
+                Paragraph::default()
+                    .span(
+                        "Only the visible paragraphs are laid out and rendered. ",
+                        SpanStyle::default(),
+                    )
+                    .span("Drag", bold)
+                    .span(" to select text and copy it with ", SpanStyle::default())
+                    .span("Ctrl+C", code)
+                    .span(BODY, SpanStyle::default()),
+                // Resolved by font fallback.
+                Paragraph::default().span(
+                    "Ελληνικά, Русский, 日本語, 한국어, हिन्दी, ★ ✓ →",
+                    SpanStyle::default(),
+                ),
+            ]
+        })
+        .collect()
+}
+
+fn view_size(size: PhysicalSize<u32>) -> (f64, f64) {
+    (
+        (size.width as f64 - MARGIN * 2.0).max(1.0),
+        (size.height as f64 - MARGIN * 2.0).max(0.0),
+    )
+}
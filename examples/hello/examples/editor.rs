@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use shared::origin_matrix;
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+};
+
+use massive_geometry::{Camera, UnitSystem};
+use massive_scene::Position;
+use massive_shell::{
+    shell,
+    widgets::{Editor, EditorStyle},
+    ApplicationContext,
+};
+
+/// The distance of the editor from the window's edges, in physical pixels.
+const MARGIN: f64 = 20.0;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1280, 800), None)?;
+    window.set_ime_allowed(true);
+
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    let size = window.inner_size();
+    let matrix = director.cast(origin_matrix(size, MARGIN));
+    let position = director.cast(Position::from(matrix.clone()));
+
+    // A large document to show that only the visible lines are laid out and rendered.
+    let text = include_str!("editor.rs").repeat(1000);
+    let mut editor = Editor::new(
+        position,
+        &text,
+        EditorStyle {
+            selection_radius: 4.0,
+            ..EditorStyle::default()
+        },
+        viewport_height(size),
+    );
+    editor.set_focused(true);
+
+    let mut cursor = (0.0, 0.0);
+
+    loop {
+        let event = ctx.wait_for_event(&mut renderer).await?;
+
+        match &event {
+            WindowEvent::CloseRequested => return Ok(()),
+            WindowEvent::Resized(size) => {
+                matrix.update(origin_matrix(*size, MARGIN));
+                editor.set_viewport_height(viewport_height(*size));
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                cursor = (position.x - MARGIN, position.y - MARGIN);
+                editor.pointer_moved(cursor.0, cursor.1);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => editor.pointer_pressed(cursor.0, cursor.1, false),
+                ElementState::Released => editor.pointer_released(),
+            },
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, lines) => {
+                    editor.scroll_by(-*lines as f64 * editor.style().line_height * 3.0)
+                }
+                MouseScrollDelta::PixelDelta(delta) => editor.scroll_by(-delta.y),
+            },
+            _ => {
+                editor.handle_event(&event);
+            }
+        }
+
+        // Keep the caret above a soft keyboard.
+        let covered = window.viewport_insets().bottom - MARGIN;
+        editor.set_bottom_inset(covered.max(0.0));
+
+        editor.update(&mut director, &mut font_system.lock().unwrap());
+        director.action()?;
+
+        let ((x, y), size) = editor.ime_cursor_area();
+        window.set_ime_cursor_area((x + MARGIN, y + MARGIN), size);
+    }
+}
+
+fn viewport_height(size: PhysicalSize<u32>) -> f64 {
+    (size.height as f64 - MARGIN * 2.0).max(0.0)
+}
@@ -14,8 +14,10 @@ use winit::{
     keyboard::{Key, NamedKey},
 };
 
-use massive_geometry::{Camera, Color, Identity, Matrix4, Vector3};
-use massive_shapes::{GlyphRun, GlyphRunMetrics, GlyphRunShape, Shape, TextWeight};
+use massive_geometry::{Camera, Color, Identity, Matrix4, UnitSystem, Vector3};
+use massive_shapes::{
+    GlyphRun, GlyphRunMetrics, GlyphRunShape, Reveal, RevealEffect, Shape, TextWeight,
+};
 use massive_shell::{shell, ApplicationContext};
 
 #[tokio::main]
@@ -29,7 +31,7 @@ async fn application(mut ctx: ApplicationContext) -> Result<()> {
     let font_system = Arc::new(Mutex::new(FontSystem::new()));
 
     let fovy: f64 = 45.0;
-    let camera_distance = 1.0 / (fovy / 2.0).to_radians().tan();
+    let camera_distance = UnitSystem::camera_distance(fovy);
     let mut camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
 
     // camera.eye = Point3::new(0.8999999999999992, 0.0, 0.11421356237309382);
@@ -46,6 +48,8 @@ async fn application(mut ctx: ApplicationContext) -> Result<()> {
     let _positioned_shapes = legacy::into_positioned_shapes(&mut director, shapes);
     director.action()?;
 
+    let mut cursor = (0.0, 0.0);
+
     loop {
         match ctx.wait_for_event(&mut renderer).await? {
             WindowEvent::KeyboardInput {
@@ -68,6 +72,12 @@ async fn application(mut ctx: ApplicationContext) -> Result<()> {
                 renderer.update_camera(camera);
                 println!("eye: {:?}", camera.eye)
             }
+            // Trackpad pinches zoom around the cursor.
+            WindowEvent::CursorMoved { position, .. } => cursor = (position.x, position.y),
+            WindowEvent::PinchGesture { delta, .. } => {
+                renderer.zoom_at(cursor, 1.0 + delta);
+                camera = renderer.camera();
+            }
             WindowEvent::CloseRequested => {
                 return Ok(());
             }
@@ -86,6 +96,8 @@ fn render(font_system: &mut FontSystem, str: &str) -> Vec<Shape> {
     let center_translation = Vector3::new(-center_x as f64, -center_y as f64, 0.0);
 
     glyph_run.translation = center_translation;
+    // The renderer's time starts when it is created, so this reveals the text right after startup.
+    glyph_run.reveal = Some(Reveal::new(RevealEffect::SlideUp { distance: 40.0 }, 0.5));
 
     let shapes = vec![GlyphRunShape {
         model_matrix: Rc::new(Matrix4::identity()),
@@ -0,0 +1,340 @@
+//! A custom shape type rendered by a shape extension.
+//!
+//! The arrow keys rotate the polygons. This only updates the model matrices of the prepared
+//! layers, the polygons are not prepared again.
+
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use cgmath::Deg;
+use cosmic_text::FontSystem;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use massive_geometry::{Bounds3, Camera, Color, Matrix4, Point, UnitSystem};
+use massive_renderer::{PreparationContext, RenderContext, Renderer, ShapeExtension};
+use massive_scene::{CustomShape, Position, PositionedShape, Shape};
+use massive_shell::{shell, ApplicationContext};
+
+/// The distance between the centers of the polygons, in pixels.
+const SPACING: f64 = 120.0;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system, camera, window.inner_size())
+        .await?;
+    renderer.register_extension(PolygonRenderer::new);
+
+    // A row of polygons from a triangle to a nonagon, centered at the origin.
+    let mut angle = 0.0;
+    let matrix = director.cast(Matrix4::from_angle_z(Deg(angle)));
+    let position = director.cast(Position::from(matrix.clone()));
+    let _polygons: Vec<_> = (3..=9)
+        .enumerate()
+        .map(|(i, corners)| {
+            let polygon = Polygon {
+                center: Point::new((i as f64 - 3.0) * SPACING, 0.0),
+                radius: SPACING * 0.4,
+                corners,
+                color: Color::hsv(i as f32 * 360.0 / 7.0, 0.6, 0.9),
+            };
+            director.cast(PositionedShape::new(
+                position.clone(),
+                Shape::custom(polygon),
+            ))
+        })
+        .collect();
+    director.action()?;
+
+    loop {
+        match ctx.wait_for_event(&mut renderer).await? {
+            WindowEvent::CloseRequested => return Ok(()),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                match key {
+                    NamedKey::ArrowLeft => angle -= 5.0,
+                    NamedKey::ArrowRight => angle += 5.0,
+                    _ => continue,
+                }
+                matrix.update(Matrix4::from_angle_z(Deg(angle)));
+                director.action()?;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A filled regular polygon.
+#[derive(Debug, Clone, PartialEq)]
+struct Polygon {
+    center: Point,
+    /// The distance of the corners from the center.
+    radius: f64,
+    corners: u32,
+    color: Color,
+}
+
+impl Polygon {
+    const SERIALIZED_SIZE: usize = 3 * 8 + 4 + 4 * 4;
+
+    /// The corners in the order of their angle, the first corner points up.
+    fn corner_points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..self.corners).map(|i| {
+            let angle = i as f64 / self.corners as f64 * std::f64::consts::TAU;
+            Point::new(
+                self.center.x + angle.sin() * self.radius,
+                self.center.y - angle.cos() * self.radius,
+            )
+        })
+    }
+}
+
+impl CustomShape for Polygon {
+    fn bounds(&self) -> Option<Bounds3> {
+        if self.corners < 3 {
+            return None;
+        }
+        Bounds3::from_points(self.corner_points().map(|p| p.with_z(0.0)))
+    }
+
+    /// Each corner is a triangle.
+    fn upload_cost(&self) -> usize {
+        self.corners as usize
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Prepares a vertex buffer of triangles per shape group and draws it with the model matrix and
+/// the layer uniforms of the group.
+struct PolygonRenderer {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    layers: Vec<PolygonLayer>,
+}
+
+struct PolygonLayer {
+    /// The index of the shape group this layer was prepared from.
+    group: usize,
+    model_matrix: Matrix4,
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+}
+
+/// The position followed by the color.
+const VERTEX_FLOATS: usize = 3 + 4;
+
+impl PolygonRenderer {
+    fn new(renderer: &Renderer) -> Self {
+        let device = &renderer.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Polygon Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("polygons.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Polygon Pipeline Layout"),
+            bind_group_layouts: &[
+                renderer.view_projection_bind_group_layout(),
+                renderer.layer_bind_group_layout(),
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(device, &shader, &pipeline_layout, renderer.render_format());
+
+        Self {
+            pipeline,
+            pipeline_layout,
+            shader,
+            layers: Vec::new(),
+        }
+    }
+
+    fn prepare_polygons<'a>(
+        context: &PreparationContext,
+        polygons: impl Iterator<Item = &'a Polygon>,
+    ) -> Vec<f32> {
+        let mut vertices = Vec::new();
+        for polygon in polygons {
+            let color = match &context.high_contrast {
+                Some(high_contrast) => high_contrast.fill(polygon.color),
+                None => polygon.color,
+            };
+            let color = [color.red, color.green, color.blue, color.alpha];
+            let corners: Vec<_> = polygon.corner_points().collect();
+            for (i, corner) in corners.iter().enumerate() {
+                let next = corners[(i + 1) % corners.len()];
+                for point in [polygon.center, *corner, next] {
+                    vertices.extend([point.x as f32, point.y as f32, 0.0]);
+                    vertices.extend(color);
+                }
+            }
+        }
+        vertices
+    }
+}
+
+impl ShapeExtension for PolygonRenderer {
+    type Shape = Polygon;
+
+    const NAME: &'static str = "polygon";
+
+    fn serialize(shape: &Polygon) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(Polygon::SERIALIZED_SIZE);
+        for value in [shape.center.x, shape.center.y, shape.radius] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(shape.corners.to_le_bytes());
+        let color = shape.color;
+        for value in [color.red, color.green, color.blue, color.alpha] {
+            data.extend(value.to_le_bytes());
+        }
+        Ok(data)
+    }
+
+    fn deserialize(data: &[u8]) -> Result<Polygon> {
+        if data.len() != Polygon::SERIALIZED_SIZE {
+            bail!("Invalid polygon size: {}", data.len());
+        }
+        let f64_at = |i: usize| f64::from_le_bytes(data[i..i + 8].try_into().unwrap());
+        let f32_at = |i: usize| f32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+        Ok(Polygon {
+            center: Point::new(f64_at(0), f64_at(8)),
+            radius: f64_at(16),
+            corners: u32::from_le_bytes(data[24..28].try_into().unwrap()),
+            color: Color::new(f32_at(28), f32_at(32), f32_at(36), f32_at(40)),
+        })
+    }
+
+    fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        groups: &[(Matrix4, Vec<&Polygon>)],
+    ) -> Result<()> {
+        for (group, (matrix, polygons)) in (first_group..).zip(groups) {
+            let vertices = Self::prepare_polygons(context, polygons.iter().copied());
+            if vertices.is_empty() {
+                continue;
+            }
+            let contents: Vec<u8> = vertices.iter().flat_map(|v| v.to_le_bytes()).collect();
+            let vertex_buffer = context.device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Polygon Vertex Buffer"),
+                contents: &contents,
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            self.layers.push(PolygonLayer {
+                group,
+                model_matrix: *matrix,
+                vertex_buffer,
+                vertex_count: (vertices.len() / VERTEX_FLOATS) as u32,
+            });
+        }
+        Ok(())
+    }
+
+    fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for layer in &mut self.layers {
+            layer.model_matrix = matrices[layer.group];
+        }
+    }
+
+    fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        context.pass.set_pipeline(&self.pipeline);
+
+        for layer in &self.layers {
+            if !context.renders_group(layer.group) {
+                continue;
+            }
+            context.queue_model_matrix(&layer.model_matrix);
+
+            let layer_bind_group = context.layer_bind_group(layer.group);
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_group, &[]);
+            pass.set_vertex_buffer(0, layer.vertex_buffer.slice(..));
+            pass.draw(0..layer.vertex_count, 0..1);
+        }
+    }
+
+    fn target_format_changed(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.pipeline = create_pipeline(device, &self.shader, &self.pipeline_layout, format);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    const FLOAT_SIZE: u64 = std::mem::size_of::<f32>() as u64;
+    let vertex_layout = wgpu::VertexBufferLayout {
+        array_stride: VERTEX_FLOATS as u64 * FLOAT_SIZE,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Polygon Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: Default::default(),
+            buffers: &[vertex_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_polygon",
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        // The winding of the triangles depends on the camera.
+        primitive: wgpu::PrimitiveState {
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: Default::default(),
+        multiview: None,
+    })
+}
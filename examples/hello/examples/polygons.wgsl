@@ -0,0 +1,44 @@
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> model_view: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(vertex_input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
+    out.color = vertex_input.color;
+    return out;
+}
+
+// Fragment shader
+
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> layer: Layer;
+
+@fragment
+fn fs_polygon(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * layer.tint;
+}
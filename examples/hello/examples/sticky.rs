@@ -0,0 +1,159 @@
+use std::{
+    f64::consts::FRAC_PI_4,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use winit::{
+    dpi::LogicalSize,
+    event::{MouseScrollDelta, WindowEvent},
+};
+
+use massive_geometry::{Camera, Color, Matrix4, UnitSystem, Vector3};
+use massive_scene::{Axis, Director, Handle, Pin, Position, PositionedShape};
+use massive_shapes::{Pattern, Quad};
+use massive_shell::{shell, ApplicationContext};
+
+/// The size of the scrolled area in pixels.
+const VIEWPORT: (f64, f64) = (600.0, 600.0);
+const TOOLBAR_HEIGHT: f64 = 40.0;
+const HEADER_HEIGHT: f64 = 32.0;
+const SECTION_HEIGHT: f64 = 400.0;
+const SECTIONS: usize = 12;
+/// The distance one mouse wheel step scrolls.
+const LINE_SCROLL: f64 = 40.0;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system, camera, window.inner_size())
+        .await?;
+
+    // The viewport, centered.
+    let (width, height) = VIEWPORT;
+    let viewport_matrix = director.cast(Matrix4::from_translation(Vector3::new(
+        -width / 2.0,
+        -height / 2.0,
+        0.0,
+    )));
+    let viewport = director.cast(Position::from(viewport_matrix));
+
+    // The scrolled world.
+    let scroll_matrix = director.cast(Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)));
+    let scroll = director.cast(Position {
+        parent: Some(viewport),
+        matrix: scroll_matrix.clone(),
+        pin: None,
+        overlay: false,
+    });
+
+    let _shapes = sections(&mut director, &scroll);
+    director.action()?;
+
+    let max_scroll = (TOOLBAR_HEIGHT + SECTIONS as f64 * SECTION_HEIGHT - height).max(0.0);
+    let mut scroll_top = 0.0;
+
+    loop {
+        let event = ctx.wait_for_event(&mut renderer).await?;
+        match event {
+            WindowEvent::CloseRequested => return Ok(()),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y as f64 * LINE_SCROLL,
+                    MouseScrollDelta::PixelDelta(position) => position.y,
+                };
+                scroll_top = (scroll_top - delta).clamp(0.0, max_scroll);
+                scroll_matrix.update(Matrix4::from_translation(Vector3::new(
+                    0.0,
+                    -scroll_top,
+                    0.0,
+                )));
+                director.action()?;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sections with sticky headers below a fixed toolbar.
+fn sections(director: &mut Director, scroll: &Handle<Position>) -> Vec<Handle<PositionedShape>> {
+    let width = VIEWPORT.0;
+    let mut shapes = Vec::new();
+
+    for section in 0..SECTIONS {
+        let top = TOOLBAR_HEIGHT + section as f64 * SECTION_HEIGHT;
+        let shade = if section % 2 == 0 { 0.95 } else { 0.9 };
+        let body = rect(
+            0.0,
+            top,
+            width,
+            top + SECTION_HEIGHT,
+            Color::rgb(shade, shade, shade),
+        );
+        // Placeholder patterns for the content of the sections.
+        let pattern_color = Color::rgb(shade - 0.08, shade - 0.08, shade - 0.08);
+        let body = match section % 4 {
+            1 => body.with_pattern(Pattern::stripes(pattern_color, 16.0).with_rotation(FRAC_PI_4)),
+            2 => body.with_pattern(Pattern::checkerboard(pattern_color, 32.0)),
+            3 => body.with_pattern(Pattern::dots(pattern_color, 12.0)),
+            _ => body,
+        };
+        shapes.push(director.cast(PositionedShape::new(scroll.clone(), vec![body])));
+
+        // The header sticks below the toolbar until the end of its section scrolls by.
+        let matrix = director.cast(Matrix4::from_translation(Vector3::new(0.0, top, 0.0)));
+        let header = director.cast(Position {
+            parent: Some(scroll.clone()),
+            matrix,
+            pin: Some(Pin::Sticky {
+                axis: Axis::Y,
+                at: TOOLBAR_HEIGHT,
+                travel: SECTION_HEIGHT - HEADER_HEIGHT,
+            }),
+            overlay: false,
+        });
+        let hue = section as f32 / SECTIONS as f32;
+        let color = Color::rgb(0.3 + 0.6 * hue, 0.5, 0.9 - 0.6 * hue);
+        let quad = rect(0.0, 0.0, width, HEADER_HEIGHT, color);
+        shapes.push(director.cast(PositionedShape::new(header, vec![quad])));
+    }
+
+    // The toolbar ignores scrolling.
+    let matrix = director.cast(Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)));
+    let toolbar = director.cast(Position {
+        parent: Some(scroll.clone()),
+        matrix,
+        pin: Some(Pin::Fixed(Axis::Y)),
+        overlay: false,
+    });
+    let quad = rect(0.0, 0.0, width, TOOLBAR_HEIGHT, Color::rgb(0.2, 0.2, 0.25));
+    shapes.push(director.cast(PositionedShape::new(toolbar, vec![quad])));
+
+    shapes
+}
+
+fn rect(left: f64, top: f64, right: f64, bottom: f64, color: Color) -> Quad {
+    Quad::new(
+        [
+            Vector3::new(left, top, 0.0),
+            Vector3::new(left, bottom, 0.0),
+            Vector3::new(right, bottom, 0.0),
+            Vector3::new(right, top, 0.0),
+        ],
+        color,
+    )
+}
@@ -0,0 +1,174 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use shared::origin_matrix;
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use massive_geometry::{Camera, UnitSystem};
+use massive_scene::Position;
+use massive_shell::{
+    shell, system_font_system,
+    widgets::{
+        Cell, CellFlags, CellMetrics, CursorStyle, TerminalColor, TerminalGrid, TerminalStyle,
+    },
+    ApplicationContext,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(system_font_system()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    let style = TerminalStyle::default();
+    let metrics = CellMetrics::measure(&mut font_system.lock().unwrap(), style.font_size);
+
+    let size = window.inner_size();
+    let matrix = director.cast(origin_matrix(size, 0.0));
+    let position = director.cast(Position::from(matrix.clone()));
+    let (columns, rows) = grid_size(size, &metrics);
+    let mut grid = TerminalGrid::new(position, style, columns, rows);
+
+    let mut row = show_samples(&mut grid).min(rows - 1);
+    let mut column = grid.write_str(row, 0, "$ ", Cell::default());
+    grid.set_cursor(Some((row, column)));
+
+    loop {
+        let event = ctx.wait_for_event(&mut renderer).await?;
+
+        match &event {
+            WindowEvent::CloseRequested => return Ok(()),
+            WindowEvent::Resized(size) => {
+                matrix.update(origin_matrix(*size, 0.0));
+                let (columns, rows) = grid_size(*size, &metrics);
+                grid.resize(columns, rows);
+                row = row.min(rows.saturating_sub(1));
+                column = column.min(columns.saturating_sub(1));
+            }
+            WindowEvent::Focused(focused) => grid.set_focused(*focused),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        text,
+                        ..
+                    },
+                ..
+            } => match logical_key {
+                // Tab cycles through the cursor styles.
+                Key::Named(NamedKey::Tab) => grid.set_cursor_style(match grid.cursor_style() {
+                    CursorStyle::Block => CursorStyle::Underline,
+                    CursorStyle::Underline => CursorStyle::Bar,
+                    CursorStyle::Bar => CursorStyle::HollowBlock,
+                    CursorStyle::HollowBlock => CursorStyle::Block,
+                }),
+                Key::Named(NamedKey::Enter) => {
+                    if row + 1 < grid.rows() {
+                        row += 1;
+                    } else {
+                        grid.scroll_up(1);
+                    }
+                    column = grid.write_str(row, 0, "$ ", Cell::default());
+                }
+                Key::Named(NamedKey::Backspace) if column > 2 => {
+                    column -= 1;
+                    grid.set_cell(row, column, Cell::default());
+                }
+                _ => {
+                    if let Some(text) = text {
+                        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+                        column = grid.write_str(row, column, &text, Cell::default());
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        grid.set_cursor(Some((row, column.min(grid.columns().saturating_sub(1)))));
+        grid.update(&mut director, &mut font_system.lock().unwrap());
+        director.action()?;
+    }
+}
+
+/// Fill the first rows with the palette, a true color gradient, and the cell attributes.
+///
+/// Returns the next free row.
+fn show_samples(grid: &mut TerminalGrid) -> usize {
+    let bold = Cell {
+        flags: CellFlags::BOLD,
+        ..Cell::default()
+    };
+    grid.write_str(
+        0,
+        0,
+        "256 colors, type to write, Tab changes the cursor",
+        bold,
+    );
+
+    // The 256 color palette, 32 colors per row.
+    for index in 0..=255u8 {
+        let cell = Cell {
+            background: TerminalColor::Indexed(index),
+            ..Cell::default()
+        };
+        let (row, column) = (2 + index as usize / 32, (index as usize % 32) * 2);
+        grid.write_str(row, column, "  ", cell);
+    }
+
+    // A true color gradient.
+    let columns = grid.columns();
+    for column in 0..columns {
+        let t = column as f64 / columns.max(1) as f64;
+        let cell = Cell {
+            c: '▀',
+            foreground: TerminalColor::Rgb((255.0 * t) as u8, 80, (255.0 * (1.0 - t)) as u8),
+            background: TerminalColor::Rgb(0, (255.0 * t) as u8, 128),
+            flags: CellFlags::empty(),
+        };
+        grid.set_cell(11, column, cell);
+    }
+
+    let mut column = 0;
+    for (text, flags) in [
+        ("bold", CellFlags::BOLD),
+        ("italic", CellFlags::ITALIC),
+        ("underline", CellFlags::UNDERLINE),
+        ("strikethrough", CellFlags::STRIKETHROUGH),
+        ("inverse", CellFlags::INVERSE),
+        ("dim", CellFlags::DIM),
+    ] {
+        let cell = Cell {
+            foreground: TerminalColor::Indexed(11),
+            flags,
+            ..Cell::default()
+        };
+        column = grid.write_str(13, column, text, cell) + 1;
+    }
+
+    15
+}
+
+fn grid_size(size: PhysicalSize<u32>, metrics: &CellMetrics) -> (usize, usize) {
+    (
+        ((size.width as f64 / metrics.width) as usize).max(1),
+        ((size.height as f64 / metrics.height) as usize).max(1),
+    )
+}
@@ -0,0 +1,53 @@
+//! Renders a corpus of glyphs offscreen and compares them with the glyphs swash rasterizes on the
+//! CPU. Exits with an error if a glyph deviates more than the tolerance allows.
+
+use anyhow::{anyhow, bail, Result};
+use cosmic_text::FontSystem;
+
+use massive_renderer::{Renderer, TextConformance};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .ok_or_else(|| anyhow!("No adapter found"))?;
+    println!("Adapter: {:?}", adapter.get_info());
+
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await?;
+
+    // Nothing is presented, the surface configuration only selects the format and the size.
+    let surface_config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        width: 1,
+        height: 1,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![],
+        desired_maximum_frame_latency: 1,
+    };
+    let mut renderer = Renderer::new_detached(device, queue, surface_config);
+    let mut font_system = FontSystem::new();
+
+    let report = TextConformance::default().run(&mut renderer, &mut font_system)?;
+
+    for failure in report.failures() {
+        println!("FAILED: {failure}");
+    }
+    let failures = report.failures().count();
+    println!(
+        "{} of {} glyph comparisons passed",
+        report.comparisons.len() - failures,
+        report.comparisons.len()
+    );
+    if !report.passed() {
+        bail!("{failures} glyph comparisons exceeded the tolerance");
+    }
+    Ok(())
+}
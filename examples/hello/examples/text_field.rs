@@ -0,0 +1,171 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use massive_geometry::{Camera, Color, Matrix4, Rect, Size, UnitSystem, Vector3};
+use massive_renderer::{HighContrast, TextPreset};
+use massive_scene::{Position, PositionedShape};
+use massive_shapes::FocusRing;
+use massive_shell::{
+    shell,
+    widgets::{
+        TextField, TextFieldResponse, TextFieldStyle, Tooltip, TooltipDirection, TooltipStyle,
+    },
+    ApplicationContext,
+};
+
+/// The offset of the text field's origin from the center of the window, in physical pixels.
+const ORIGIN: (f64, f64) = (-400.0, -40.0);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    shell::run(application).await
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(LogicalSize::new(1280, 800), None)?;
+    window.set_ime_allowed(true);
+
+    let (mut renderer, mut director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    let matrix = director.cast(Matrix4::from_translation(Vector3::new(
+        ORIGIN.0, ORIGIN.1, 0.0,
+    )));
+    let position = director.cast(Position::from(matrix));
+
+    let style = TextFieldStyle {
+        font_size: 48.0,
+        caret_trail: true,
+        ..TextFieldStyle::default()
+    };
+    let mut text_field = TextField::new(position.clone(), "Edit me", style);
+    text_field.set_focused(true);
+
+    // The renderer computes the ring from the bounds of the text field's shapes.
+    let _focus_ring = director.cast(PositionedShape::new(
+        position,
+        FocusRing::new(Color::rgb(0.2, 0.5, 1.0)),
+    ));
+
+    // Follow the system settings, F2 toggles high contrast.
+    renderer.set_high_contrast(window.forced_colors());
+    // F3 cycles through the text rendering presets.
+    let mut text_preset = TextPreset::default();
+
+    let mut cursor_x = 0.0;
+    let mut tooltip: Option<Tooltip> = None;
+
+    loop {
+        let event = ctx.wait_for_event(&mut renderer).await?;
+
+        match &event {
+            WindowEvent::CloseRequested => return Ok(()),
+            WindowEvent::CursorMoved { position, .. } => {
+                // The camera shows physical pixels 1:1 at z = 0, centered in the window.
+                let width = window.inner_size().width;
+                cursor_x = position.x - width as f64 / 2.0 - ORIGIN.0;
+                text_field.pointer_moved(cursor_x);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::F2),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                let high_contrast = match renderer.high_contrast() {
+                    Some(_) => None,
+                    None => Some(HighContrast::default()),
+                };
+                renderer.set_high_contrast(high_contrast);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::F3),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                text_preset = text_preset.next();
+                println!("Text rendering: {text_preset:?}");
+                renderer.set_text_rendering(text_preset);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => text_field.pointer_pressed(cursor_x, false),
+                ElementState::Released => text_field.pointer_released(),
+            },
+            _ => {
+                if text_field.handle_event(&event) == TextFieldResponse::Submitted {
+                    println!("Submitted: {}", text_field.text());
+                }
+            }
+        }
+
+        // The shell redraws when the caret blinks, and restarts blinking on input.
+        renderer.set_caret_active(text_field.is_focused());
+        text_field.set_caret_visible(renderer.caret_visible());
+        text_field.update(&mut director, &mut font_system.lock().unwrap());
+
+        let ((x, y), size) = text_field.ime_cursor_area();
+        let size_in_window = window.inner_size();
+        let caret = (
+            x + size_in_window.width as f64 / 2.0 + ORIGIN.0,
+            y + size_in_window.height as f64 / 2.0 + ORIGIN.1,
+        );
+        window.set_ime_cursor_area(caret, size);
+
+        // A hint that follows the caret.
+        let anchor = Rect {
+            left: caret.0,
+            top: caret.1,
+            right: caret.0 + size.0,
+            bottom: caret.1 + size.1,
+        };
+        let screen = Size::new(size_in_window.width as f64, size_in_window.height as f64);
+        match &mut tooltip {
+            Some(tooltip) => tooltip.set_anchor(&anchor, screen),
+            None => {
+                tooltip = Some(Tooltip::new(
+                    &mut director,
+                    &mut font_system.lock().unwrap(),
+                    "Press Enter to submit",
+                    &anchor,
+                    TooltipDirection::Above,
+                    screen,
+                    // Frosted glass over the text.
+                    TooltipStyle {
+                        background: Color::new(0.15, 0.15, 0.15, 0.6),
+                        backdrop_blur: 8.0,
+                        ..TooltipStyle::default()
+                    },
+                ))
+            }
+        }
+
+        director.action()?;
+    }
+}
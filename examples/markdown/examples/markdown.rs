@@ -20,7 +20,7 @@ use massive_scene::PositionedShape;
 use massive_shapes::GlyphRun;
 
 use massive_geometry::{Camera, SizeI, Vector3};
-use massive_shell::{shell, ApplicationContext};
+use massive_shell::{deterministic_font_system, shell, ApplicationContext};
 
 use shared::{
     application::{Application, UpdateResponse},
@@ -40,17 +40,9 @@ async fn markdown() -> Result<()> {
 }
 
 async fn application(mut ctx: ApplicationContext) -> Result<()> {
-    let font_system = {
-        // In wasm the system locale can't be acquired. `sys_locale::get_locale()`
-        const DEFAULT_LOCALE: &str = "en-US";
-
-        // Don't load system fonts for now, this way we get the same result on wasm and local runs.
-        let mut font_db = fontdb::Database::new();
-        let montserrat = fonts::MONTSERRAT_REGULAR;
-        let source = fontdb::Source::Binary(Arc::new(montserrat));
-        font_db.load_font_source(source);
-        FontSystem::new_with_locale_and_db(DEFAULT_LOCALE.into(), font_db)
-    };
+    // Don't load system fonts for now, this way we get the same result on wasm and local runs.
+    let font_system =
+        deterministic_font_system([fontdb::Source::Binary(Arc::new(fonts::MONTSERRAT_REGULAR))]);
 
     let scale_factor = ctx
         .primary_monitor()
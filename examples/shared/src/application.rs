@@ -1,12 +1,8 @@
 use std::collections::HashMap;
 
 use massive_geometry::{Matrix4, PointI, SizeI};
-use winit::{
-    event::{
-        DeviceId, ElementState, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, TouchPhase,
-        WindowEvent,
-    },
-    keyboard::{Key, NamedKey},
+use winit::event::{
+    DeviceId, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
 };
 
 enum ActiveGesture {
@@ -67,17 +63,8 @@ impl Application {
     #[must_use]
     pub fn update(&mut self, window_event: WindowEvent) -> UpdateResponse {
         match window_event {
-            // Forward to application for more control?
-            WindowEvent::CloseRequested
-            | WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        state: ElementState::Pressed,
-                        logical_key: Key::Named(NamedKey::Escape),
-                        ..
-                    },
-                ..
-            } => return UpdateResponse::Exit,
+            // Escape is bound to quit in the shell's shortcut map, see `massive_shell::ShortcutMap`.
+            WindowEvent::CloseRequested => return UpdateResponse::Exit,
             WindowEvent::CursorMoved {
                 device_id,
                 position,
@@ -6,6 +6,8 @@ pub mod positioning;
 use std::future::Future;
 
 use anyhow::Result;
+use massive_geometry::{Matrix4, Vector3};
+use winit::dpi::PhysicalSize;
 
 pub fn main<Fut>(main: impl FnOnce() -> Fut + 'static) -> Result<()>
 where
@@ -37,3 +39,14 @@ where
         Ok(())
     }
 }
+
+/// Places an origin `margin` physical pixels from the top left of a window of `size`.
+///
+/// The camera shows physical pixels 1:1 at z = 0, centered in the window.
+pub fn origin_matrix(size: PhysicalSize<u32>, margin: f64) -> Matrix4 {
+    Matrix4::from_translation(Vector3::new(
+        margin - size.width as f64 / 2.0,
+        margin - size.height as f64 / 2.0,
+        0.0,
+    ))
+}
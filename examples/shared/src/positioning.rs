@@ -10,7 +10,8 @@ const RENDER_SUBPIXEL: bool = false;
 
 /// Converts a cosmic_text `LayoutRun` into one or more `GlyphRun`s.
 ///
-/// We split `LayoutRun`s if they contain different metadata which points to a color.
+/// We split `LayoutRun`s if they contain different metadata which points to a color. Adjacent
+/// parts with the same color and weight are merged again.
 pub fn to_attributed_glyph_runs(
     translation: Vector3,
     run: &LayoutRun,
@@ -19,7 +20,8 @@ pub fn to_attributed_glyph_runs(
 ) -> Vec<GlyphRun> {
     let metrics = metrics(run, line_height);
 
-    run.glyphs
+    let runs = run
+        .glyphs
         .iter()
         .group_by(|r| r.metadata)
         .into_iter()
@@ -34,7 +36,8 @@ pub fn to_attributed_glyph_runs(
                 positioned_glyphs.collect(),
             )
         })
-        .collect()
+        .collect::<Vec<_>>();
+    GlyphRun::merge_adjacent(runs)
 }
 
 pub fn to_glyph_run(translation: Vector3, run: &LayoutRun, line_height: f32) -> GlyphRun {
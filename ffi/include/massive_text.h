@@ -0,0 +1,120 @@
+/*
+ * C bindings for the massive text renderer.
+ *
+ * Link against the `massive_text_ffi` library built from the `ffi` crate.
+ *
+ * `massive_run` opens a window and calls back into the application on the same thread. All other
+ * functions must only be called from inside these callbacks.
+ *
+ * Functions returning `int32_t` return 0 on success and -1 on error, functions returning pointers
+ * return NULL on error. `massive_last_error` describes the error.
+ */
+
+#ifndef MASSIVE_TEXT_H
+#define MASSIVE_TEXT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct MassiveContext MassiveContext;
+typedef struct MassiveDirector MassiveDirector;
+typedef struct MassivePosition MassivePosition;
+typedef struct MassiveShape MassiveShape;
+
+typedef struct MassiveVector3 {
+    double x;
+    double y;
+    double z;
+} MassiveVector3;
+
+typedef struct MassiveColor {
+    float red;
+    float green;
+    float blue;
+    float alpha;
+} MassiveColor;
+
+typedef struct MassiveTextStyle {
+    /* The font size in pixels. */
+    float font_size;
+    /* The font weight, for example 400 for normal text. */
+    uint16_t weight;
+    MassiveColor color;
+} MassiveTextStyle;
+
+typedef struct MassiveQuad {
+    MassiveVector3 vertices[4];
+    MassiveColor color;
+} MassiveQuad;
+
+typedef struct MassiveCamera {
+    MassiveVector3 eye;
+    MassiveVector3 target;
+    MassiveVector3 up;
+    /* The vertical field of view in degrees. */
+    double fovy;
+} MassiveCamera;
+
+typedef enum MassiveEventKind {
+    MASSIVE_EVENT_CLOSE_REQUESTED,
+    /* `width` and `height` are set in physical pixels. */
+    MASSIVE_EVENT_RESIZED,
+    /* `character` is the Unicode scalar value of the key's text, 0 for keys without text. */
+    MASSIVE_EVENT_KEY_PRESSED,
+    MASSIVE_EVENT_OTHER,
+} MassiveEventKind;
+
+typedef struct MassiveEvent {
+    MassiveEventKind kind;
+    uint32_t width;
+    uint32_t height;
+    uint32_t character;
+} MassiveEvent;
+
+/* Return false to exit. */
+typedef bool (*MassiveStartFn)(MassiveContext *context, void *user_data);
+typedef bool (*MassiveEventFn)(MassiveContext *context, const MassiveEvent *event, void *user_data);
+
+const char *massive_last_error(void);
+
+/* Runs until the window is closed or a callback returns false. Changes of the context's director
+ * are sent to the renderer after each callback. */
+int32_t massive_run(uint32_t width, uint32_t height, MassiveStartFn on_start,
+                    MassiveEventFn on_event, void *user_data);
+
+/* Owned by the context, must not be freed. */
+MassiveDirector *massive_director(MassiveContext *context);
+int32_t massive_director_action(MassiveDirector *director);
+void massive_load_font(MassiveDirector *director, const uint8_t *data, size_t len);
+
+/* `matrix` is a column major 4x4 matrix, `parent` may be NULL. */
+MassivePosition *massive_position_new(MassiveDirector *director, const double *matrix,
+                                      const MassivePosition *parent);
+void massive_position_set_matrix(MassivePosition *position, const double *matrix);
+void massive_position_free(MassivePosition *position);
+
+/* `text` is UTF-8 and does not need to be null terminated. */
+MassiveShape *massive_text_new(MassiveDirector *director, const MassivePosition *position,
+                               const char *text, size_t len, MassiveTextStyle style,
+                               MassiveVector3 translation);
+MassiveShape *massive_quads_new(MassiveDirector *director, const MassivePosition *position,
+                                const MassiveQuad *quads, size_t count);
+void massive_shape_set_visible(MassiveShape *shape, bool visible);
+void massive_shape_free(MassiveShape *shape);
+
+void massive_camera(MassiveContext *context, MassiveCamera *camera);
+/* If `animation_ms` is not 0, the camera is animated for that duration. */
+void massive_set_camera(MassiveContext *context, const MassiveCamera *camera, uint32_t animation_ms);
+/* Returns 1 if the camera was moved, 0 if the scene is empty, -1 on error. */
+int32_t massive_fit_scene(MassiveContext *context, double margin, uint32_t animation_ms);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MASSIVE_TEXT_H */
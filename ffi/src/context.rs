@@ -0,0 +1,272 @@
+use std::{
+    ffi::c_void,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use cgmath::EuclideanSpace;
+use cosmic_text::FontSystem;
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::Key,
+};
+
+use massive_geometry::{Camera, Point3, UnitSystem, Vector3};
+use massive_shell::{shell, ApplicationContext, WindowRenderer};
+
+use crate::{
+    error::{set_last_error, status},
+    MassiveDirector, MassiveVector3,
+};
+
+/// The window and renderer, passed to the application's callbacks.
+pub struct MassiveContext<'window> {
+    renderer: WindowRenderer<'window>,
+    director: MassiveDirector,
+}
+
+/// Called once after the renderer was created. Return `false` to exit.
+pub type MassiveStartFn = extern "C" fn(*mut MassiveContext<'_>, *mut c_void) -> bool;
+
+/// Called for every window event. Return `false` to exit.
+pub type MassiveEventFn =
+    extern "C" fn(*mut MassiveContext<'_>, *const MassiveEvent, *mut c_void) -> bool;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MassiveEventKind {
+    /// The window should be closed. [`massive_run`] returns after the event was handled.
+    CloseRequested,
+    /// The window was resized to `width` x `height` physical pixels.
+    Resized,
+    /// A key was pressed. `character` is the Unicode scalar value of the key's text, `0` for keys
+    /// without text.
+    KeyPressed,
+    Other,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MassiveEvent {
+    pub kind: MassiveEventKind,
+    pub width: u32,
+    pub height: u32,
+    pub character: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MassiveCamera {
+    pub eye: MassiveVector3,
+    pub target: MassiveVector3,
+    pub up: MassiveVector3,
+    /// The vertical field of view in degrees.
+    pub fovy: f64,
+}
+
+/// Open a window of `width` x `height` logical pixels and run the renderer until the window is
+/// closed or a callback returns `false`.
+///
+/// The scene changes of the context's director are sent to the renderer after each callback.
+/// Returns `0` when the window was closed and `-1` on error.
+#[no_mangle]
+pub extern "C" fn massive_run(
+    width: u32,
+    height: u32,
+    on_start: Option<MassiveStartFn>,
+    on_event: Option<MassiveEventFn>,
+    user_data: *mut c_void,
+) -> i32 {
+    // The application may have initialized logging already.
+    let _ = env_logger::try_init();
+
+    let callbacks = Callbacks {
+        on_start,
+        on_event,
+        user_data,
+    };
+    status(run(LogicalSize::new(width, height), callbacks))
+}
+
+struct Callbacks {
+    on_start: Option<MassiveStartFn>,
+    on_event: Option<MassiveEventFn>,
+    user_data: *mut c_void,
+}
+
+fn run(size: LogicalSize<u32>, callbacks: Callbacks) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(shell::run(move |ctx| application(ctx, size, callbacks)))
+}
+
+async fn application(
+    mut ctx: ApplicationContext,
+    size: LogicalSize<u32>,
+    callbacks: Callbacks,
+) -> Result<()> {
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+    let window = ctx.new_window(size, None)?;
+    let (renderer, director) = window
+        .new_renderer(font_system.clone(), camera, window.inner_size())
+        .await?;
+
+    let mut context = MassiveContext {
+        renderer,
+        director: MassiveDirector::new(director, font_system),
+    };
+
+    if let Some(on_start) = callbacks.on_start {
+        let proceed = on_start(&mut context, callbacks.user_data);
+        context.director.action()?;
+        if !proceed {
+            return Ok(());
+        }
+    }
+
+    loop {
+        let window_event = ctx.wait_for_event(&mut context.renderer).await?;
+        let event = convert_event(&window_event);
+
+        if let Some(on_event) = callbacks.on_event {
+            let proceed = on_event(&mut context, &event, callbacks.user_data);
+            context.director.action()?;
+            if !proceed {
+                return Ok(());
+            }
+        }
+
+        if event.kind == MassiveEventKind::CloseRequested {
+            return Ok(());
+        }
+    }
+}
+
+fn convert_event(event: &WindowEvent) -> MassiveEvent {
+    let mut converted = MassiveEvent {
+        kind: MassiveEventKind::Other,
+        width: 0,
+        height: 0,
+        character: 0,
+    };
+
+    match event {
+        WindowEvent::CloseRequested => converted.kind = MassiveEventKind::CloseRequested,
+        WindowEvent::Resized(size) => {
+            converted.kind = MassiveEventKind::Resized;
+            converted.width = size.width;
+            converted.height = size.height;
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    logical_key,
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } => {
+            converted.kind = MassiveEventKind::KeyPressed;
+            if let Key::Character(text) = logical_key {
+                converted.character = text.chars().next().map(u32::from).unwrap_or_default();
+            }
+        }
+        _ => {}
+    }
+
+    converted
+}
+
+/// The director of the context. It is owned by the context and must not be freed.
+///
+/// # Safety
+///
+/// `context` must be the context passed to the current callback.
+#[no_mangle]
+pub unsafe extern "C" fn massive_director(
+    context: *mut MassiveContext<'_>,
+) -> *mut MassiveDirector {
+    &mut (*context).director
+}
+
+/// Retrieve the current camera.
+///
+/// # Safety
+///
+/// `context` must be the context passed to the current callback, `camera` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn massive_camera(
+    context: *mut MassiveContext<'_>,
+    camera: *mut MassiveCamera,
+) {
+    let current = (*context).renderer.camera();
+    *camera = MassiveCamera {
+        eye: current.eye.to_vec().into(),
+        target: current.target.to_vec().into(),
+        up: current.up.into(),
+        fovy: current.fovy,
+    };
+}
+
+/// Set the camera. If `animation_ms` is not `0`, the camera is animated for that duration.
+///
+/// # Safety
+///
+/// `context` must be the context passed to the current callback, `camera` must point to a valid
+/// camera.
+#[no_mangle]
+pub unsafe extern "C" fn massive_set_camera(
+    context: *mut MassiveContext<'_>,
+    camera: *const MassiveCamera,
+    animation_ms: u32,
+) {
+    let camera = &*camera;
+    let camera = Camera {
+        eye: Point3::from_vec(camera.eye.into()),
+        target: Point3::from_vec(camera.target.into()),
+        up: Vector3::from(camera.up),
+        fovy: camera.fovy,
+    };
+
+    let renderer = &mut (*context).renderer;
+    match animation_ms {
+        0 => renderer.update_camera(camera),
+        ms => renderer.animate_camera(camera, Instant::now() + Duration::from_millis(ms as u64)),
+    }
+}
+
+/// Move the camera so that the whole scene is in view. `margin` is in world units.
+///
+/// Returns `1` if the camera was moved, `0` if the scene is empty, and `-1` on error.
+///
+/// # Safety
+///
+/// `context` must be the context passed to the current callback.
+#[no_mangle]
+pub unsafe extern "C" fn massive_fit_scene(
+    context: *mut MassiveContext<'_>,
+    margin: f64,
+    animation_ms: u32,
+) -> i32 {
+    let context = &mut *context;
+    let animation = (animation_ms != 0).then(|| Duration::from_millis(animation_ms as u64));
+    // Changes made in the current callback were not sent yet.
+    let result = context.director.action().and_then(|_| {
+        context.renderer.apply_pending_changes()?;
+        Ok(context.renderer.fit_scene(margin, animation))
+    });
+    match result {
+        Ok(fitted) => fitted as i32,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
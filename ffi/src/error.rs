@@ -0,0 +1,53 @@
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    ptr,
+};
+
+use anyhow::Result;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The message of the last error that occurred on this thread, null if there was none.
+///
+/// The string is valid until the next function of this library fails.
+#[no_mangle]
+pub extern "C" fn massive_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+pub(crate) fn set_last_error(error: anyhow::Error) {
+    // Interior nul bytes can not be represented in a C string.
+    let message = format!("{error:?}").replace('\0', "");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// Convert a result to the status code returned to C.
+pub(crate) fn status(result: Result<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Box a result for C, a null pointer on error.
+pub(crate) fn into_raw<T>(result: Result<T>) -> *mut T {
+    match result {
+        Ok(value) => Box::into_raw(Box::new(value)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
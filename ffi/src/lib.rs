@@ -0,0 +1,19 @@
+//! C bindings for embedding the renderer in applications written in other languages.
+//!
+//! The C declarations are in `include/massive_text.h`.
+//!
+//! [`massive_run`] opens a window, creates the renderer and calls back into the application on the
+//! same thread. All other functions must only be called from inside these callbacks. Objects are
+//! reference counted on the Rust side, so the shapes and positions the application frees stay
+//! alive as long as other objects refer to them.
+//!
+//! Functions that can fail return `0` on success and `-1` on error, or a null pointer on error. The
+//! error message is then available through [`massive_last_error`].
+
+mod context;
+mod error;
+mod scene;
+
+pub use context::*;
+pub use error::*;
+pub use scene::*;
@@ -0,0 +1,309 @@
+use std::{
+    ffi::c_char,
+    slice, str,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use cosmic_text::{self as text, FontSystem};
+
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_scene::{Director, Handle, Matrix, Position, PositionedShape};
+use massive_shapes::{GlyphRun, GlyphRunMetrics, Quad, RunGlyph, TextWeight};
+
+use crate::error::{into_raw, status};
+
+/// Creates shapes and sends their changes to the renderer.
+pub struct MassiveDirector {
+    director: Director,
+    font_system: Arc<Mutex<FontSystem>>,
+}
+
+impl MassiveDirector {
+    pub(crate) fn new(director: Director, font_system: Arc<Mutex<FontSystem>>) -> Self {
+        Self {
+            director,
+            font_system,
+        }
+    }
+
+    pub(crate) fn action(&mut self) -> Result<()> {
+        self.director.action()
+    }
+}
+
+/// A position in the scene, a matrix relative to an optional parent position.
+pub struct MassivePosition {
+    position: Handle<Position>,
+    matrix: Handle<Matrix>,
+}
+
+pub struct MassiveShape(Handle<PositionedShape>);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MassiveVector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<Vector3> for MassiveVector3 {
+    fn from(v: Vector3) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<MassiveVector3> for Vector3 {
+    fn from(v: MassiveVector3) -> Self {
+        Vector3::new(v.x, v.y, v.z)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MassiveColor {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+impl From<MassiveColor> for Color {
+    fn from(c: MassiveColor) -> Self {
+        Color::new(c.red, c.green, c.blue, c.alpha)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MassiveTextStyle {
+    /// The font size in pixels.
+    pub font_size: f32,
+    /// The font weight, for example `400` for normal text.
+    pub weight: u16,
+    pub color: MassiveColor,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MassiveQuad {
+    pub vertices: [MassiveVector3; 4],
+    pub color: MassiveColor,
+}
+
+/// Send all changes to the renderer now.
+///
+/// This is done automatically after each callback, so it's only needed to make changes visible
+/// before a callback returns.
+///
+/// # Safety
+///
+/// `director` must be the director of the current context.
+#[no_mangle]
+pub unsafe extern "C" fn massive_director_action(director: *mut MassiveDirector) -> i32 {
+    status((*director).action())
+}
+
+/// Load a font file (TrueType, OpenType, or a collection) to make it available for text shaping.
+///
+/// # Safety
+///
+/// `director` must be the director of the current context, `data` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn massive_load_font(
+    director: *mut MassiveDirector,
+    data: *const u8,
+    len: usize,
+) {
+    let data = slice::from_raw_parts(data, len).to_vec();
+    (*director)
+        .font_system
+        .lock()
+        .unwrap()
+        .db_mut()
+        .load_font_data(data);
+}
+
+/// Create a position from a column major 4x4 `matrix`. `parent` may be null.
+///
+/// # Safety
+///
+/// `director` must be the director of the current context, `matrix` must point to 16 values, and
+/// `parent` must be null or a valid position.
+#[no_mangle]
+pub unsafe extern "C" fn massive_position_new(
+    director: *mut MassiveDirector,
+    matrix: *const f64,
+    parent: *const MassivePosition,
+) -> *mut MassivePosition {
+    let director = &mut (*director).director;
+    let matrix = director.cast(read_matrix(matrix));
+    let position = director.cast(Position {
+        parent: parent.as_ref().map(|parent| parent.position.clone()),
+        matrix: matrix.clone(),
+        pin: None,
+        overlay: false,
+    });
+    Box::into_raw(Box::new(MassivePosition { position, matrix }))
+}
+
+/// Replace the matrix of a position.
+///
+/// # Safety
+///
+/// `position` must be a valid position, `matrix` must point to 16 values.
+#[no_mangle]
+pub unsafe extern "C" fn massive_position_set_matrix(
+    position: *mut MassivePosition,
+    matrix: *const f64,
+) {
+    (*position).matrix.update(read_matrix(matrix));
+}
+
+/// Release a position. It is removed from the scene when no shapes or positions refer to it
+/// anymore.
+///
+/// # Safety
+///
+/// `position` must be null or a valid position that is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn massive_position_free(position: *mut MassivePosition) {
+    if !position.is_null() {
+        drop(Box::from_raw(position));
+    }
+}
+
+/// Shape a single line of UTF-8 `text` and add it at `translation` relative to `position`.
+///
+/// Returns null on error.
+///
+/// # Safety
+///
+/// `director` must be the director of the current context, `position` must be a valid position,
+/// and `text` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn massive_text_new(
+    director: *mut MassiveDirector,
+    position: *const MassivePosition,
+    text: *const c_char,
+    len: usize,
+    style: MassiveTextStyle,
+    translation: MassiveVector3,
+) -> *mut MassiveShape {
+    let director = &mut *director;
+    let text = slice::from_raw_parts(text as *const u8, len);
+    let result = str::from_utf8(text)
+        .map_err(anyhow::Error::from)
+        .map(|text| {
+            let run = shape_line(
+                &mut director.font_system.lock().unwrap(),
+                text,
+                &style,
+                translation.into(),
+            );
+            let shape = PositionedShape::new((*position).position.clone(), run);
+            MassiveShape(director.director.cast(shape))
+        });
+    into_raw(result)
+}
+
+/// Add `count` quads relative to `position`.
+///
+/// # Safety
+///
+/// `director` must be the director of the current context, `position` must be a valid position,
+/// and `quads` must point to `count` quads.
+#[no_mangle]
+pub unsafe extern "C" fn massive_quads_new(
+    director: *mut MassiveDirector,
+    position: *const MassivePosition,
+    quads: *const MassiveQuad,
+    count: usize,
+) -> *mut MassiveShape {
+    let quads: Vec<Quad> = slice::from_raw_parts(quads, count)
+        .iter()
+        .map(|quad| Quad::new(quad.vertices.map(Vector3::from), quad.color.into()))
+        .collect();
+    let shape = PositionedShape::new((*position).position.clone(), quads);
+    Box::into_raw(Box::new(MassiveShape((*director).director.cast(shape))))
+}
+
+/// Show or hide a shape.
+///
+/// # Safety
+///
+/// `shape` must be a valid shape.
+#[no_mangle]
+pub unsafe extern "C" fn massive_shape_set_visible(shape: *mut MassiveShape, visible: bool) {
+    (*shape).0.set_visible(visible);
+}
+
+/// Release a shape and remove it from the scene.
+///
+/// # Safety
+///
+/// `shape` must be null or a valid shape that is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn massive_shape_free(shape: *mut MassiveShape) {
+    if !shape.is_null() {
+        drop(Box::from_raw(shape));
+    }
+}
+
+unsafe fn read_matrix(matrix: *const f64) -> Matrix4 {
+    let m = slice::from_raw_parts(matrix, 16);
+    Matrix4::new(
+        m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10], m[11], m[12], m[13],
+        m[14], m[15],
+    )
+}
+
+fn shape_line(
+    font_system: &mut FontSystem,
+    text: &str,
+    style: &MassiveTextStyle,
+    translation: Vector3,
+) -> GlyphRun {
+    let attrs = text::Attrs::new().weight(text::Weight(style.weight));
+    let mut buffer =
+        text::BufferLine::new(text, text::AttrsList::new(attrs), text::Shaping::Advanced);
+    let line = &buffer.layout(
+        font_system,
+        style.font_size,
+        f32::MAX,
+        text::Wrap::None,
+        None,
+    )[0];
+    let metrics = GlyphRunMetrics {
+        max_ascent: line.max_ascent as u32,
+        max_descent: line.max_descent as u32,
+        width: line.w.ceil() as u32,
+    };
+    let glyphs = line
+        .glyphs
+        .iter()
+        .map(|glyph| {
+            let (key, x, y) = text::CacheKey::new(
+                glyph.font_id,
+                glyph.glyph_id,
+                glyph.font_size,
+                (glyph.x.round(), glyph.y.round()),
+                text::CacheKeyFlags::empty(),
+            );
+            RunGlyph::new(key, (x, y), glyph.w)
+        })
+        .collect();
+
+    GlyphRun::new(
+        translation,
+        metrics,
+        style.color.into(),
+        TextWeight(style.weight),
+        glyphs,
+    )
+}
@@ -0,0 +1,124 @@
+//! Feeds arbitrary streams of framed scene messages into a headless renderer.
+//!
+//! Every input is a new connection: The messages are read like a viewer reads them from a client,
+//! decoded, and the changes that are accepted are applied to an empty scene and rendered. Errors
+//! are expected, panics are not. Resource usage is bounded by libFuzzer's limits, for example:
+//!
+//! ```sh
+//! cargo +nightly fuzz run scene_changes -- -rss_limit_mb=2048 -timeout=10
+//! ```
+
+#![no_main]
+
+use std::{cell::RefCell, sync::Arc};
+
+use cosmic_text::{fontdb, FontSystem};
+use libfuzzer_sys::fuzz_target;
+use massive_remote::{ipc::read_message, Decoder};
+use massive_renderer::{Renderer, View};
+
+static FONT: &[u8] =
+    include_bytes!("../../examples/shared/src/fonts/Montserrat/Montserrat-Regular.ttf");
+
+/// The size of the headless surface.
+const SURFACE_SIZE: (u32, u32) = (256, 256);
+
+struct Harness {
+    renderer: Renderer<'static>,
+    target: wgpu::TextureView,
+    max_texture_size: u32,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let instance = wgpu::Instance::default();
+        let adapter = futures::executor::block_on(
+            instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+        )
+        .expect("No adapter found");
+        let (device, queue) = futures::executor::block_on(
+            adapter.request_device(&wgpu::DeviceDescriptor::default(), None),
+        )
+        .expect("Failed to request a device");
+        let max_texture_size = device.limits().max_texture_dimension_2d;
+
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let target = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Fuzz Target"),
+                size: wgpu::Extent3d {
+                    width: SURFACE_SIZE.0,
+                    height: SURFACE_SIZE.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: SURFACE_SIZE.0,
+            height: SURFACE_SIZE.1,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 1,
+        };
+
+        Self {
+            renderer: Renderer::new_detached(device, queue, surface_config),
+            target,
+            max_texture_size,
+        }
+    }
+
+    fn run(&mut self, mut data: &[u8]) {
+        // A new font system for every input, so that fonts embedded in the messages don't
+        // accumulate.
+        let mut font_db = fontdb::Database::new();
+        font_db.load_font_source(fontdb::Source::Binary(Arc::new(FONT)));
+        let mut font_system = FontSystem::new_with_locale_and_db("en-US".into(), font_db);
+
+        // Start with an empty scene, like a viewer does for a new client.
+        self.renderer
+            .bootstrap_changes(&mut font_system, Vec::new())
+            .expect("Failed to reset the scene");
+
+        let mut decoder = Decoder::default();
+        while let Ok(Some(message)) = read_message(&mut data) {
+            // Rejected messages are fine, the stream continues with the next one.
+            let Ok(Some(changes)) = decoder.decode(&mut font_system, message) else {
+                continue;
+            };
+            let _ = self.renderer.apply_changes(&mut font_system, changes);
+        }
+
+        let pixel_matrix = self.renderer.pixel_matrix();
+        self.renderer
+            .render_views_to(&self.target, &[View::new(pixel_matrix)])
+            .expect("Failed to render");
+
+        for atlas in self.renderer.atlases() {
+            let (width, height) = atlas.size;
+            assert!(
+                width <= self.max_texture_size && height <= self.max_texture_size,
+                "Atlas {} grew to {width}x{height}",
+                atlas.name
+            );
+        }
+    }
+}
+
+thread_local! {
+    static HARNESS: RefCell<Option<Harness>> = const { RefCell::new(None) };
+}
+
+fuzz_target!(|data: &[u8]| {
+    HARNESS.with_borrow_mut(|harness| harness.get_or_insert_with(Harness::new).run(data));
+});
@@ -1,6 +1,8 @@
+use serde_tuple::{Deserialize_tuple, Serialize_tuple};
+
 use crate::{Point, Rect};
 
-#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize_tuple, Deserialize_tuple)]
 pub struct Bounds {
     pub min: Point,
     pub max: Point,
@@ -1,4 +1,6 @@
-use crate::{Point3, Size3};
+use cgmath::{EuclideanSpace, Transform};
+
+use crate::{scalar, Matrix4, Point3, Size3, Vector3};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Bounds3 {
@@ -14,8 +16,114 @@ impl Bounds3 {
         }
     }
 
+    /// The smallest bounds that contain all the points, `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Point3>) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, p| {
+            Some(match bounds {
+                Some(bounds) => bounds.with_point(p),
+                None => Self::new(p, p),
+            })
+        })
+    }
+
     pub fn size(&self) -> Size3 {
         let v = self.max - self.min;
         Size3::new((v.x, v.y, v.z).into())
     }
+
+    pub fn center(&self) -> Point3 {
+        self.min.midpoint(self.max)
+    }
+
+    /// The 8 corners of the box.
+    pub fn corners(&self) -> [Point3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    #[must_use]
+    pub fn join(&self, other: &Self) -> Self {
+        Self::new(
+            (
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            (
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        )
+    }
+
+    #[must_use]
+    pub fn with_point(&self, p: Point3) -> Self {
+        self.join(&Self::new(p, p))
+    }
+
+    /// The surface area of the box. Used to estimate the cost of bounding volume hierarchies.
+    pub fn surface_area(&self) -> scalar {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+            && self.min.z <= other.max.z
+            && self.max.z >= other.min.z
+    }
+
+    pub fn contains_point(&self, p: Point3) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    /// The axis aligned bounds of this box transformed by `matrix`.
+    #[must_use]
+    pub fn transformed(&self, matrix: &Matrix4) -> Self {
+        Self::from_points(self.corners().map(|p| matrix.transform_point(p)))
+            .expect("Internal error: No corners")
+    }
+
+    /// The distance along the ray at which it enters the box, `None` if it misses.
+    ///
+    /// `direction` does not need to be normalized, the distance is measured in multiples of it.
+    /// Returns `0.0` if the origin is inside the box.
+    pub fn ray_intersection(&self, origin: Point3, direction: Vector3) -> Option<scalar> {
+        let mut near: scalar = 0.0;
+        let mut far = scalar::INFINITY;
+
+        for axis in 0..3 {
+            let (o, d) = (origin[axis], direction[axis]);
+            let (min, max) = (self.min[axis], self.max[axis]);
+            if d == 0.0 {
+                if o < min || o > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+            if near > far {
+                return None;
+            }
+        }
+
+        Some(near)
+    }
 }
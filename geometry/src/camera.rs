@@ -1,9 +1,12 @@
-use crate::{scalar, Matrix4, Point3, Projection, Vector3};
+use cgmath::{EuclideanSpace, InnerSpace, One, Quaternion, Rotation, VectorSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{scalar, Bounds3, Matrix4, Point3, Projection, Vector3};
 
 // TODO: May use yaw / pitch based camera?
 // <https://sotrh.github.io/learn-wgpu/intermediate/tutorial12-camera/#the-camera>
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub struct Camera {
     pub eye: Point3,
     pub target: Point3,
@@ -36,6 +39,189 @@ impl Camera {
         let projection = Projection::new(width as scalar / height as scalar, z_range.0, z_range.1);
         view_projection_matrix(self, &projection)
     }
+
+    /// Interpolates between this and another camera.
+    ///
+    /// The view direction and the up vector are spherically interpolated. The eye position, the
+    /// distance to the target, and the field of view are interpolated linearly. `t` is expected
+    /// to be in the range `0.0..=1.0` and is not eased.
+    pub fn interpolate(&self, to: &Camera, t: scalar) -> Camera {
+        let from_direction = self.target - self.eye;
+        let to_direction = to.target - to.eye;
+
+        let direction = slerp_vector(from_direction.normalize(), to_direction.normalize(), t);
+        let up = slerp_vector(self.up.normalize(), to.up.normalize(), t);
+
+        let distance = lerp(from_direction.magnitude(), to_direction.magnitude(), t);
+        let eye = Point3::from_vec(self.eye.to_vec().lerp(to.eye.to_vec(), t));
+
+        Camera {
+            eye,
+            target: eye + direction * distance,
+            up,
+            fovy: lerp(self.fovy, to.fovy, t),
+        }
+    }
+
+    /// A camera that looks at the center of `bounds` from a distance at which all of the bounds
+    /// are visible. The view direction, the up vector, and the field of view are kept.
+    ///
+    /// `aspect` is the aspect ratio (width / height) of the surface. `margin` extends the bounds
+    /// on all sides and is in world units.
+    pub fn fit_bounds(&self, bounds: &Bounds3, aspect: scalar, margin: scalar) -> Camera {
+        let (right, up, back) = self.basis();
+        let center = bounds.center();
+        let (tan_x, tan_y) = self.half_fov_tangents(aspect);
+
+        // The distance from the center at which every corner fits into the horizontal and the
+        // vertical field of view.
+        let distance = bounds
+            .corners()
+            .iter()
+            .map(|corner| {
+                let v = corner - center;
+                let (x, y, z) = (v.dot(right), v.dot(up), v.dot(back));
+                (z + (x.abs() + margin) / tan_x).max(z + (y.abs() + margin) / tan_y)
+            })
+            .fold(0.0, scalar::max);
+
+        Camera {
+            eye: center + back * distance,
+            target: center,
+            up,
+            fovy: self.fovy,
+        }
+    }
+
+    /// A camera that is moved parallel to its view plane just so far that `bounds` become
+    /// visible.
+    ///
+    /// If the bounds are already visible, the camera is returned unchanged. If they do not fit
+    /// into the view at the distance of the bounds, the camera is moved back as in
+    /// [`Self::fit_bounds`].
+    ///
+    /// `aspect` is the aspect ratio (width / height) of the surface. `margin` extends the bounds
+    /// on all sides and is in world units.
+    pub fn scroll_into_view(&self, bounds: &Bounds3, aspect: scalar, margin: scalar) -> Camera {
+        let (right, up, back) = self.basis();
+        let (tan_x, tan_y) = self.half_fov_tangents(aspect);
+
+        // The visible half extents at the distance of the bounds' center.
+        let distance = (self.eye - bounds.center()).dot(back);
+        if distance <= 0.0 {
+            return self.fit_bounds(bounds, aspect, margin);
+        }
+        let (half_x, half_y) = (distance * tan_x, distance * tan_y);
+
+        let corners = bounds.corners().map(|c| c - self.target);
+        let extent = |axis: Vector3| {
+            let projected = corners.map(|c| c.dot(axis));
+            let min = projected.into_iter().fold(scalar::INFINITY, scalar::min);
+            let max = projected
+                .into_iter()
+                .fold(scalar::NEG_INFINITY, scalar::max);
+            (min - margin, max + margin)
+        };
+        let (x_range, y_range) = (extent(right), extent(up));
+
+        let (Some(dx), Some(dy)) = (
+            scroll_offset(x_range, half_x),
+            scroll_offset(y_range, half_y),
+        ) else {
+            return self.fit_bounds(bounds, aspect, margin);
+        };
+
+        let offset = right * dx + up * dy;
+        Camera {
+            eye: self.eye + offset,
+            target: self.target + offset,
+            ..*self
+        }
+    }
+
+    /// Zoom by `scale` so that the content under `focal` stays where it is, for example under the
+    /// fingers of a pinch gesture.
+    ///
+    /// `focal` is in normalized device coordinates, from -1 to 1 with y pointing up. The content
+    /// is considered to lie on the plane through the target that faces the camera. A `scale`
+    /// above 1 zooms in. `aspect` is the aspect ratio (width / height) of the surface.
+    pub fn zoom_at(&self, focal: (scalar, scalar), scale: scalar, aspect: scalar) -> Camera {
+        let (right, up, back) = self.basis();
+        let (tan_x, tan_y) = self.half_fov_tangents(aspect);
+        let distance = (self.eye - self.target).dot(back);
+        let point = self.target + (right * focal.0 * tan_x + up * focal.1 * tan_y) * distance;
+
+        // Scaling the camera around the point keeps the point on the same ray through the eye.
+        let scale = 1.0 / scale;
+        Camera {
+            eye: point + (self.eye - point) * scale,
+            target: point + (self.target - point) * scale,
+            ..*self
+        }
+    }
+
+    /// The bounds of the part of the plane at height `z` that faces the z axis and is visible
+    /// through the camera, for example the visible area of an infinite canvas.
+    ///
+    /// `None` if the plane does not fill the view, because it's seen up to the horizon or is
+    /// behind the camera. `aspect` is the aspect ratio (width / height) of the surface.
+    pub fn visible_bounds(&self, z: scalar, aspect: scalar) -> Option<Bounds3> {
+        let (right, up, back) = self.basis();
+        let (tan_x, tan_y) = self.half_fov_tangents(aspect);
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| {
+            let ray = right * x * tan_x + up * y * tan_y - back;
+            let t = (z - self.eye.z) / ray.z;
+            (t > 0.0 && t.is_finite()).then(|| self.eye + ray * t)
+        });
+        Bounds3::from_points(corners.into_iter().collect::<Option<Vec<_>>>()?)
+    }
+
+    /// The number of pixels a world unit covers on the plane through the target that faces the
+    /// camera, for a surface that is `height` pixels high.
+    pub fn pixels_per_unit(&self, height: scalar) -> scalar {
+        let (_, tan_y) = self.half_fov_tangents(1.0);
+        height / (2.0 * (self.eye - self.target).magnitude() * tan_y)
+    }
+
+    /// The normalized right, up, and back (pointing from the target to the eye) vectors of the
+    /// camera.
+    fn basis(&self) -> (Vector3, Vector3, Vector3) {
+        let back = (self.eye - self.target).normalize();
+        let right = self.up.cross(back).normalize();
+        let up = back.cross(right);
+        (right, up, back)
+    }
+
+    /// The tangents of the half horizontal and vertical field of view.
+    fn half_fov_tangents(&self, aspect: scalar) -> (scalar, scalar) {
+        let tan_y = (self.fovy / 2.0).to_radians().tan();
+        (tan_y * aspect, tan_y)
+    }
+}
+
+/// The offset the center of a view with the half extent `half` needs to be moved so that `range`
+/// becomes visible. `None` if the range does not fit.
+fn scroll_offset((min, max): (scalar, scalar), half: scalar) -> Option<scalar> {
+    if max - min > half * 2.0 {
+        return None;
+    }
+    Some(if min < -half {
+        min + half
+    } else if max > half {
+        max - half
+    } else {
+        0.0
+    })
+}
+
+/// Spherically interpolate between two normalized vectors.
+fn slerp_vector(from: Vector3, to: Vector3, t: scalar) -> Vector3 {
+    let arc = Quaternion::from_arc(from, to, None);
+    Quaternion::one().slerp(arc, t).rotate_vector(from)
+}
+
+fn lerp(from: scalar, to: scalar, t: scalar) -> scalar {
+    from + (to - from) * t
 }
 
 pub fn view_projection_matrix(camera: &Camera, projection: &Projection) -> Matrix4 {
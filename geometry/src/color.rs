@@ -1,7 +1,10 @@
-use std::ops::{Add, Div, Mul};
+use core::ops::{Add, Div, Mul};
 
 use serde_tuple::{Deserialize_tuple, Serialize_tuple};
 
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+
 // TODO: WGPU uses f64 for colors, should we do the same?
 #[derive(Copy, Clone, PartialEq, Debug, Serialize_tuple, Deserialize_tuple)]
 pub struct Color {
@@ -1,37 +1,61 @@
 //! Geometry primitives, taken from the BrainSharper project at 20230701
+//!
+//! Without the default `std` feature, this is a `no_std` crate that contains the 2D types:
+//! colors, points, sizes, rectangles, and bounds. The 3D types, the camera, and the curves are
+//! based on `cgmath` and `flo_curves` and require `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
 mod bezier_algorithms;
 mod bounds;
+#[cfg(feature = "std")]
 mod bounds3;
+#[cfg(feature = "std")]
 mod camera;
 mod color;
+#[cfg(feature = "std")]
 mod cubic_bezier;
+#[cfg(feature = "std")]
 mod flo_curves;
+#[cfg(not(feature = "std"))]
+mod float;
 mod line;
 mod point;
 mod point_i;
+#[cfg(feature = "std")]
 mod projection;
 mod rect;
 mod size;
+#[cfg(feature = "std")]
 mod size3;
 mod size_i;
 mod unit_interval;
+#[cfg(feature = "std")]
+mod units;
 
 pub use bounds::*;
+#[cfg(feature = "std")]
 pub use bounds3::*;
+#[cfg(feature = "std")]
 pub use camera::*;
+#[cfg(feature = "std")]
 use cgmath::One;
 pub use color::*;
+#[cfg(feature = "std")]
 pub use cubic_bezier::*;
 pub use line::*;
 pub use point::*;
 pub use point_i::*;
+#[cfg(feature = "std")]
 pub use projection::*;
 pub use rect::*;
 pub use size::*;
+#[cfg(feature = "std")]
 pub use size3::*;
 pub use size_i::*;
 pub use unit_interval::*;
+#[cfg(feature = "std")]
+pub use units::*;
 
 #[allow(non_camel_case_types)]
 pub type scalar = f64;
@@ -44,14 +68,18 @@ pub trait Contains<Other> {
     fn contains(&self, other: Other) -> bool;
 }
 
+#[cfg(feature = "std")]
 pub type Matrix4 = cgmath::Matrix4<f64>;
+#[cfg(feature = "std")]
 pub type Point3 = cgmath::Point3<f64>;
+#[cfg(feature = "std")]
 pub type Vector3 = cgmath::Vector3<f64>;
 
 pub trait Identity {
     fn identity() -> Self;
 }
 
+#[cfg(feature = "std")]
 impl Identity for Matrix4 {
     fn identity() -> Self {
         cgmath::Matrix4::one()
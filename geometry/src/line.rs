@@ -1,4 +1,6 @@
 use super::Point;
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
 
 #[derive(Copy, Clone, PartialEq, Debug, Default)]
 pub struct Line {
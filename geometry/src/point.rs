@@ -1,7 +1,10 @@
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use core::ops::{Add, Div, Mul, Neg, Sub};
 
 use serde_tuple::{Deserialize_tuple, Serialize_tuple};
 
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+#[cfg(feature = "std")]
 use crate::Point3;
 
 #[derive(Copy, Clone, PartialEq, Debug, Default, Serialize_tuple, Deserialize_tuple)]
@@ -41,6 +44,7 @@ impl Point {
         self.x * self.x + self.y * self.y
     }
 
+    #[cfg(feature = "std")]
     pub fn with_z(self, z: f64) -> Point3 {
         Point3::new(self.x, self.y, z)
     }
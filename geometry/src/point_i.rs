@@ -1,7 +1,10 @@
-use std::ops::{Add, Neg, Sub};
+use core::ops::{Add, Neg, Sub};
 
 use serde_tuple::{Deserialize_tuple, Serialize_tuple};
 
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+
 #[derive(Copy, Clone, PartialEq, Debug, Default, Serialize_tuple, Deserialize_tuple)]
 pub struct PointI {
     pub x: i64,
@@ -1,6 +1,8 @@
 //! Taken from skia-safe at 20230701
-use std::ops::{Add, Sub};
+use core::ops::{Add, Sub};
 
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
 use crate::{scalar, Centered, Contains, Point, Size, Vector};
 
 /// A basic rectangle representation. Meant to be sorted and with finite values only.
@@ -0,0 +1,127 @@
+//! The unit systems used to size and place content.
+//!
+//! - Physical pixels are the pixels of the surface.
+//! - Logical pixels are physical pixels divided by the scale factor of the window.
+//! - World units are the units of the 3D scene. With a camera at
+//!   [`UnitSystem::camera_distance`], the surface height spans two world units at z = 0.
+
+use std::ops;
+
+use crate::{scalar, Matrix4};
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct PhysicalPx(pub scalar);
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct LogicalPx(pub scalar);
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct WorldUnits(pub scalar);
+
+macro_rules! unit_ops {
+    ($unit:ident) => {
+        impl ops::Add for $unit {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl ops::Sub for $unit {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl ops::Mul<scalar> for $unit {
+            type Output = Self;
+
+            fn mul(self, rhs: scalar) -> Self::Output {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl ops::Div<scalar> for $unit {
+            type Output = Self;
+
+            fn div(self, rhs: scalar) -> Self::Output {
+                Self(self.0 / rhs)
+            }
+        }
+    };
+}
+
+unit_ops!(PhysicalPx);
+unit_ops!(LogicalPx);
+unit_ops!(WorldUnits);
+
+/// Converts between the unit systems of a surface.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct UnitSystem {
+    /// The ratio of physical to logical pixels.
+    pub scale_factor: scalar,
+    /// The height of the surface in physical pixels.
+    pub surface_height: u32,
+}
+
+impl UnitSystem {
+    pub fn new(scale_factor: scalar, surface_height: u32) -> Self {
+        Self {
+            scale_factor,
+            surface_height,
+        }
+    }
+
+    pub fn to_physical(&self, logical: LogicalPx) -> PhysicalPx {
+        PhysicalPx(logical.0 * self.scale_factor)
+    }
+
+    pub fn to_logical(&self, physical: PhysicalPx) -> LogicalPx {
+        LogicalPx(physical.0 / self.scale_factor)
+    }
+
+    pub fn physical_to_world(&self, physical: PhysicalPx) -> WorldUnits {
+        WorldUnits(physical.0 * self.world_units_per_physical_px())
+    }
+
+    pub fn world_to_physical(&self, world: WorldUnits) -> PhysicalPx {
+        PhysicalPx(world.0 / self.world_units_per_physical_px())
+    }
+
+    pub fn logical_to_world(&self, logical: LogicalPx) -> WorldUnits {
+        self.physical_to_world(self.to_physical(logical))
+    }
+
+    pub fn world_to_logical(&self, world: WorldUnits) -> LogicalPx {
+        self.to_logical(self.world_to_physical(world))
+    }
+
+    /// A matrix that converts from physical pixels to world units. Also flips y, so that y points
+    /// downwards in pixel coordinates.
+    pub fn physical_pixel_matrix(&self) -> Matrix4 {
+        Self::pixel_matrix(self.world_units_per_physical_px())
+    }
+
+    /// A matrix that converts from logical pixels to world units. Also flips y, so that y points
+    /// downwards in pixel coordinates.
+    pub fn logical_pixel_matrix(&self) -> Matrix4 {
+        Self::pixel_matrix(self.world_units_per_physical_px() * self.scale_factor)
+    }
+
+    /// The distance of the camera to the z = 0 plane at which the surface height spans two world
+    /// units.
+    pub fn camera_distance(fovy: scalar) -> scalar {
+        1.0 / (fovy / 2.0).to_radians().tan()
+    }
+
+    fn world_units_per_physical_px(&self) -> scalar {
+        2.0 / self.surface_height.max(1) as scalar
+    }
+
+    fn pixel_matrix(scale: scalar) -> Matrix4 {
+        Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0) * Matrix4::from_scale(scale)
+    }
+}
@@ -0,0 +1,445 @@
+//! Referential integrity of decoded scene changes.
+//!
+//! Locally, the [`massive_scene::Director`] guarantees that changes only refer to objects that
+//! exist. Changes received from another process may not, and the renderer would panic on them, so
+//! the [`crate::Decoder`] tracks the objects the stream created and rejects transactions that
+//! break the integrity.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+
+use massive_scene::{Change, Id, SceneChange};
+
+/// The largest id accepted.
+///
+/// The renderer stores the objects in tables indexed by their ids, so this bounds their size.
+pub const MAX_ID: usize = 1 << 20;
+
+/// The objects that exist on the receiving side.
+#[derive(Debug, Default)]
+pub struct SceneIntegrity {
+    matrices: HashSet<Id>,
+    positions: HashMap<Id, PositionRefs>,
+    /// The position of each shape.
+    shapes: HashMap<Id, Id>,
+    /// The number of positions that refer to each matrix.
+    matrix_refs: HashMap<Id, usize>,
+    /// The number of positions and shapes that refer to each position.
+    position_refs: HashMap<Id, usize>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct PositionRefs {
+    matrix: Id,
+    parent: Option<Id>,
+}
+
+/// The previous state of an object, to undo a rejected transaction.
+#[derive(Debug)]
+enum Undo {
+    Matrix(Id, bool),
+    Position(Id, Option<PositionRefs>),
+    Shape(Id, Option<Id>),
+}
+
+/// The objects a transaction touched, to verify them after it was applied.
+#[derive(Debug, Default)]
+struct Touched {
+    positions: Vec<Id>,
+    shapes: Vec<Id>,
+    deleted_matrices: Vec<Id>,
+    deleted_positions: Vec<Id>,
+}
+
+impl SceneIntegrity {
+    /// Apply the changes of a transaction.
+    ///
+    /// Fails if a change refers to an object that does not exist at the end of the transaction,
+    /// if it creates an object that exists, or updates or deletes one that does not. A rejected
+    /// transaction leaves the tracked objects unchanged.
+    pub fn apply(&mut self, changes: &[SceneChange]) -> Result<()> {
+        let mut undo = Vec::new();
+        let result = self.apply_changes(changes, &mut undo);
+        if result.is_err() {
+            for undo in undo.into_iter().rev() {
+                match undo {
+                    Undo::Matrix(id, exists) => {
+                        self.set_matrix(id, exists);
+                    }
+                    Undo::Position(id, refs) => {
+                        self.set_position(id, refs);
+                    }
+                    Undo::Shape(id, position) => {
+                        self.set_shape(id, position);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn apply_changes(&mut self, changes: &[SceneChange], undo: &mut Vec<Undo>) -> Result<()> {
+        let mut touched = Touched::default();
+
+        for change in changes {
+            match change {
+                SceneChange::Matrix(change) => {
+                    let (id, value) = target(change, self.matrices.contains(&change.id()))?;
+                    if value.is_none() {
+                        touched.deleted_matrices.push(id);
+                    }
+                    undo.push(Undo::Matrix(id, self.set_matrix(id, value.is_some())));
+                }
+                SceneChange::Position(change) => {
+                    let exists = self.positions.contains_key(&change.id());
+                    let (id, value) = target(change, exists)?;
+                    let refs = value.map(|position| PositionRefs {
+                        matrix: position.matrix,
+                        parent: position.parent,
+                    });
+                    match refs {
+                        Some(_) => touched.positions.push(id),
+                        None => touched.deleted_positions.push(id),
+                    }
+                    undo.push(Undo::Position(id, self.set_position(id, refs)));
+                }
+                SceneChange::PositionedShape(change) => {
+                    let (id, value) = target(change, self.shapes.contains_key(&change.id()))?;
+                    let position = value.map(|shape| shape.position);
+                    if position.is_some() {
+                        touched.shapes.push(id);
+                    }
+                    undo.push(Undo::Shape(id, self.set_shape(id, position)));
+                }
+                SceneChange::ShapeVisibility(id, _) => {
+                    if !self.shapes.contains_key(id) {
+                        bail!("Shape {} does not exist", **id);
+                    }
+                }
+            }
+        }
+
+        self.verify(&touched)
+    }
+
+    /// Verify the references of the objects a transaction touched.
+    fn verify(&self, touched: &Touched) -> Result<()> {
+        for id in &touched.deleted_matrices {
+            if !self.matrices.contains(id) && refs(&self.matrix_refs, *id) > 0 {
+                bail!("Matrix {} was deleted, but is still in use", **id);
+            }
+        }
+        for id in &touched.deleted_positions {
+            if !self.positions.contains_key(id) && refs(&self.position_refs, *id) > 0 {
+                bail!("Position {} was deleted, but is still in use", **id);
+            }
+        }
+
+        for id in &touched.positions {
+            let Some(refs) = self.positions.get(id) else {
+                continue;
+            };
+            if !self.matrices.contains(&refs.matrix) {
+                bail!(
+                    "Position {} refers to the missing matrix {}",
+                    **id,
+                    *refs.matrix
+                );
+            }
+            if let Some(parent) = refs.parent {
+                if !self.positions.contains_key(&parent) {
+                    bail!("Position {} refers to the missing parent {}", **id, *parent);
+                }
+            }
+            self.verify_ancestors(*id)?;
+        }
+
+        for id in &touched.shapes {
+            let Some(position) = self.shapes.get(id) else {
+                continue;
+            };
+            if !self.positions.contains_key(position) {
+                bail!(
+                    "Shape {} refers to the missing position {}",
+                    **id,
+                    **position
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fail if the parents of a position lead back to it.
+    fn verify_ancestors(&self, id: Id) -> Result<()> {
+        let mut current = id;
+        // A chain without cycles can't be longer than the number of positions.
+        for _ in 0..self.positions.len() {
+            match self.positions.get(&current).and_then(|refs| refs.parent) {
+                Some(parent) if parent == id => bail!("Position {} is its own ancestor", *id),
+                Some(parent) => current = parent,
+                None => return Ok(()),
+            }
+        }
+        bail!("The ancestors of position {} form a cycle", *id)
+    }
+
+    /// Returns whether the matrix existed before.
+    fn set_matrix(&mut self, id: Id, exists: bool) -> bool {
+        if exists {
+            !self.matrices.insert(id)
+        } else {
+            self.matrices.remove(&id)
+        }
+    }
+
+    /// Returns the previous references of the position.
+    fn set_position(&mut self, id: Id, refs: Option<PositionRefs>) -> Option<PositionRefs> {
+        let previous = match refs {
+            Some(refs) => self.positions.insert(id, refs),
+            None => self.positions.remove(&id),
+        };
+        if let Some(previous) = previous {
+            release(&mut self.matrix_refs, previous.matrix);
+            if let Some(parent) = previous.parent {
+                release(&mut self.position_refs, parent);
+            }
+        }
+        if let Some(refs) = refs {
+            *self.matrix_refs.entry(refs.matrix).or_default() += 1;
+            if let Some(parent) = refs.parent {
+                *self.position_refs.entry(parent).or_default() += 1;
+            }
+        }
+        previous
+    }
+
+    /// Returns the previous position of the shape.
+    fn set_shape(&mut self, id: Id, position: Option<Id>) -> Option<Id> {
+        let previous = match position {
+            Some(position) => self.shapes.insert(id, position),
+            None => self.shapes.remove(&id),
+        };
+        if let Some(previous) = previous {
+            release(&mut self.position_refs, previous);
+        }
+        if let Some(position) = position {
+            *self.position_refs.entry(position).or_default() += 1;
+        }
+        previous
+    }
+}
+
+/// Check that the change is valid for an object that `exists` and return its id and new value,
+/// `None` if it is deleted.
+fn target<T>(change: &Change<T>, exists: bool) -> Result<(Id, Option<&T>)> {
+    let id = change.id();
+    if *id > MAX_ID {
+        bail!("Id {} exceeds the maximum of {MAX_ID}", *id);
+    }
+    match change {
+        Change::Create(_, value) if !exists => Ok((id, Some(value))),
+        Change::Create(..) => bail!("Object {} was created, but exists already", *id),
+        Change::Update(_, value) if exists => Ok((id, Some(value))),
+        Change::Delete(_) if exists => Ok((id, None)),
+        Change::Update(..) | Change::Delete(_) => bail!("Object {} does not exist", *id),
+    }
+}
+
+fn refs(counts: &HashMap<Id, usize>, id: Id) -> usize {
+    counts.get(&id).copied().unwrap_or_default()
+}
+
+fn release(counts: &mut HashMap<Id, usize>, id: Id) {
+    if let Some(count) = counts.get_mut(&id) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use massive_geometry::Matrix4;
+    use massive_scene::{PositionRenderObj, PositionedRenderShape, Shape};
+
+    use super::*;
+
+    fn id(raw: usize) -> Id {
+        Id::from_raw(raw)
+    }
+
+    fn matrix(change: fn(Id, Matrix4) -> Change<Matrix4>, raw: usize) -> SceneChange {
+        SceneChange::Matrix(change(id(raw), Matrix4::from_scale(1.0)))
+    }
+
+    fn position(
+        change: fn(Id, PositionRenderObj) -> Change<PositionRenderObj>,
+        raw: usize,
+        matrix: usize,
+        parent: Option<usize>,
+    ) -> SceneChange {
+        SceneChange::Position(change(
+            id(raw),
+            PositionRenderObj {
+                parent: parent.map(id),
+                matrix: id(matrix),
+                pin: None,
+                overlay: false,
+            },
+        ))
+    }
+
+    fn shape(raw: usize, position: usize) -> SceneChange {
+        SceneChange::PositionedShape(Change::Create(
+            id(raw),
+            PositionedRenderShape {
+                position: id(position),
+                shape: Shape::Quads(Vec::new()),
+            },
+        ))
+    }
+
+    /// A matrix, a position and a shape, each with id 0.
+    fn scene() -> SceneIntegrity {
+        let mut integrity = SceneIntegrity::default();
+        integrity
+            .apply(&[
+                matrix(Change::Create, 0),
+                position(Change::Create, 0, 0, None),
+                shape(0, 0),
+            ])
+            .unwrap();
+        integrity
+    }
+
+    #[test]
+    fn references_may_be_created_later_in_the_transaction() {
+        let mut integrity = SceneIntegrity::default();
+        integrity
+            .apply(&[
+                shape(0, 0),
+                position(Change::Create, 0, 0, None),
+                matrix(Change::Create, 0),
+            ])
+            .unwrap();
+    }
+
+    #[test]
+    fn missing_references_are_rejected() {
+        let mut integrity = scene();
+        assert!(integrity.apply(&[shape(1, 1)]).is_err());
+        assert!(integrity
+            .apply(&[position(Change::Create, 1, 1, None)])
+            .is_err());
+        assert!(integrity
+            .apply(&[position(Change::Create, 1, 0, Some(2))])
+            .is_err());
+        assert!(integrity
+            .apply(&[SceneChange::ShapeVisibility(id(1), false)])
+            .is_err());
+        integrity
+            .apply(&[SceneChange::ShapeVisibility(id(0), false)])
+            .unwrap();
+    }
+
+    #[test]
+    fn creating_existing_and_changing_missing_objects_is_rejected() {
+        let mut integrity = scene();
+        assert!(integrity.apply(&[matrix(Change::Create, 0)]).is_err());
+        assert!(integrity.apply(&[matrix(Change::Update, 1)]).is_err());
+        assert!(integrity
+            .apply(&[SceneChange::Matrix(Change::Delete(id(1)))])
+            .is_err());
+        integrity.apply(&[matrix(Change::Update, 0)]).unwrap();
+    }
+
+    #[test]
+    fn objects_in_use_can_not_be_deleted() {
+        let mut integrity = scene();
+        assert!(integrity
+            .apply(&[SceneChange::Matrix(Change::Delete(id(0)))])
+            .is_err());
+        assert!(integrity
+            .apply(&[SceneChange::Position(Change::Delete(id(0)))])
+            .is_err());
+
+        // Deleting the users first releases the references.
+        integrity
+            .apply(&[
+                SceneChange::PositionedShape(Change::Delete(id(0))),
+                SceneChange::Position(Change::Delete(id(0))),
+                SceneChange::Matrix(Change::Delete(id(0))),
+            ])
+            .unwrap();
+        assert!(integrity.matrices.is_empty());
+        assert!(integrity.matrix_refs.is_empty());
+        assert!(integrity.position_refs.is_empty());
+    }
+
+    #[test]
+    fn a_deleted_object_may_be_recreated_in_the_same_transaction() {
+        let mut integrity = scene();
+        integrity
+            .apply(&[
+                SceneChange::Matrix(Change::Delete(id(0))),
+                matrix(Change::Create, 0),
+            ])
+            .unwrap();
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut integrity = scene();
+        assert!(integrity
+            .apply(&[position(Change::Update, 0, 0, Some(0))])
+            .is_err());
+
+        integrity
+            .apply(&[
+                position(Change::Create, 1, 0, Some(0)),
+                position(Change::Create, 2, 0, Some(1)),
+            ])
+            .unwrap();
+        assert!(integrity
+            .apply(&[position(Change::Update, 0, 0, Some(2))])
+            .is_err());
+    }
+
+    #[test]
+    fn ids_beyond_the_maximum_are_rejected() {
+        let mut integrity = SceneIntegrity::default();
+        assert!(integrity
+            .apply(&[matrix(Change::Create, MAX_ID + 1)])
+            .is_err());
+        integrity.apply(&[matrix(Change::Create, MAX_ID)]).unwrap();
+    }
+
+    #[test]
+    fn rejected_transactions_are_undone() {
+        let mut integrity = scene();
+        // Valid until the last change.
+        assert!(integrity
+            .apply(&[
+                matrix(Change::Create, 1),
+                position(Change::Update, 0, 1, None),
+                SceneChange::Matrix(Change::Delete(id(0))),
+                shape(1, 1),
+            ])
+            .is_err());
+
+        assert_eq!(integrity.matrices, HashSet::from([id(0)]));
+        assert_eq!(
+            integrity.positions[&id(0)],
+            PositionRefs {
+                matrix: id(0),
+                parent: None
+            }
+        );
+        assert_eq!(integrity.matrix_refs, HashMap::from([(id(0), 1)]));
+        assert_eq!(integrity.position_refs, HashMap::from([(id(0), 1)]));
+        assert_eq!(integrity.shapes, HashMap::from([(id(0), id(0))]));
+    }
+}
@@ -0,0 +1,234 @@
+//! Transfers scene messages between processes.
+//!
+//! Messages are framed with their length as a little endian `u32` followed by the postcard
+//! serialized [`Message`].
+//!
+//! On unix, unix domain sockets are supported. TCP works everywhere and is meant for Windows and
+//! for connections between machines.
+
+use std::{
+    fmt,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use anyhow::{bail, Result};
+use cosmic_text::FontSystem;
+use massive_scene::{CustomShapeCodecs, Director, SceneChange};
+
+use crate::{Decoder, Encoder, Message};
+
+/// The largest message accepted, to detect corrupt streams early.
+const MAX_MESSAGE_SIZE: usize = 1 << 30;
+
+/// Where a viewer listens and clients connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+impl FromStr for Endpoint {
+    type Err = anyhow::Error;
+
+    /// Parses `unix:<path>` or `tcp:<address>`.
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(Self::Unix(path.into()));
+        }
+        if let Some(address) = s.strip_prefix("tcp:") {
+            return Ok(Self::Tcp(address.parse()?));
+        }
+        bail!("Invalid endpoint `{s}`, expected `unix:<path>` or `tcp:<address>`")
+    }
+}
+
+impl Default for Endpoint {
+    /// A unix socket in the temporary directory on unix, TCP on localhost otherwise.
+    fn default() -> Self {
+        if cfg!(unix) {
+            Endpoint::Unix(std::env::temp_dir().join("massive-viewer.sock"))
+        } else {
+            Endpoint::Tcp(SocketAddr::from(([127, 0, 0, 1], 7817)))
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+            Endpoint::Tcp(address) => write!(f, "tcp:{address}"),
+        }
+    }
+}
+
+pub trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+impl Endpoint {
+    pub fn connect(&self) -> Result<Box<dyn Stream>> {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Ok(Box::new(UnixStream::connect(path)?)),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => bail!("Unix sockets are not supported on this platform"),
+            Endpoint::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    pub fn listen(&self) -> Result<Listener> {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                // Remove a stale socket from a previous run.
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(Listener::Unix(UnixListener::bind(path)?))
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => bail!("Unix sockets are not supported on this platform"),
+            Endpoint::Tcp(address) => Ok(Listener::Tcp(TcpListener::bind(address)?)),
+        }
+    }
+}
+
+pub enum Listener {
+    #[cfg(unix)]
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    /// Wait for the next client.
+    pub fn accept(&self) -> Result<Box<dyn Stream>> {
+        match self {
+            #[cfg(unix)]
+            Listener::Unix(listener) => Ok(Box::new(listener.accept()?.0)),
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Message) -> Result<()> {
+    let bytes = postcard::to_stdvec(message)?;
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Read the next message. Returns `None` if the stream was closed between two messages.
+pub fn read_message(reader: &mut impl Read) -> Result<Option<Message>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        bail!("Message of {len} bytes exceeds the maximum size");
+    }
+    // Grow the buffer with the bytes actually received, so that a corrupt length does not
+    // allocate up to the maximum size.
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(Some(postcard::from_bytes(&bytes)?))
+}
+
+/// Sends scene changes to a viewer in another process.
+pub struct SceneSender {
+    writer: BufWriter<Box<dyn Stream>>,
+    encoder: Encoder,
+    font_system: Arc<Mutex<FontSystem>>,
+}
+
+impl SceneSender {
+    pub fn connect(endpoint: &Endpoint, font_system: Arc<Mutex<FontSystem>>) -> Result<Self> {
+        Ok(Self::new(endpoint.connect()?, font_system))
+    }
+
+    pub fn new(stream: Box<dyn Stream>, font_system: Arc<Mutex<FontSystem>>) -> Self {
+        Self {
+            writer: BufWriter::new(stream),
+            encoder: Encoder::default(),
+            font_system,
+        }
+    }
+
+    /// Set the codecs used to serialize custom shapes.
+    pub fn set_codecs(&mut self, codecs: CustomShapeCodecs) {
+        self.encoder.set_codecs(codecs);
+    }
+
+    /// Send a transaction of changes.
+    pub fn send(&mut self, changes: Vec<SceneChange>) -> Result<()> {
+        let messages = {
+            let font_system = self.font_system.lock().unwrap();
+            self.encoder.encode(&font_system, changes)?
+        };
+        for message in &messages {
+            write_message(&mut self.writer, message)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// A director that sends all its changes to the viewer.
+    pub fn into_director(mut self) -> Director {
+        Director::new(move |changes| self.send(changes))
+    }
+}
+
+/// Receives scene changes from a client in another process.
+pub struct SceneReceiver {
+    reader: BufReader<Box<dyn Stream>>,
+    decoder: Decoder,
+    font_system: Arc<Mutex<FontSystem>>,
+}
+
+impl SceneReceiver {
+    pub fn new(stream: Box<dyn Stream>, font_system: Arc<Mutex<FontSystem>>) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+            decoder: Decoder::default(),
+            font_system,
+        }
+    }
+
+    /// Set the codecs used to deserialize custom shapes.
+    pub fn set_codecs(&mut self, codecs: CustomShapeCodecs) {
+        self.decoder.set_codecs(codecs);
+    }
+
+    /// Receive the next transaction of changes. Returns `None` if the client disconnected.
+    pub fn receive(&mut self) -> Result<Option<Vec<SceneChange>>> {
+        loop {
+            let Some(message) = read_message(&mut self.reader)? else {
+                return Ok(None);
+            };
+            let mut font_system = self.font_system.lock().unwrap();
+            if let Some(changes) = self.decoder.decode(&mut font_system, message)? {
+                return Ok(Some(changes));
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Streams scenes to a renderer in another process.
+//!
+//! [`wire`] defines the serialized scene format, [`ipc`] transfers it between processes.
+//! [`websocket_server`] streams scenes to browsers, which receive them with
+//! `websocket_client::WebSocketReceiver`. [`snapshot`] stores the state of a renderer in a file.
+
+mod integrity;
+pub mod ipc;
+pub mod snapshot;
+#[cfg(target_arch = "wasm32")]
+pub mod websocket_client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod websocket_server;
+pub mod wire;
+
+pub use ipc::{Endpoint, SceneReceiver, SceneSender};
+pub use snapshot::Snapshot;
+#[cfg(target_arch = "wasm32")]
+pub use websocket_client::WebSocketReceiver;
+#[cfg(not(target_arch = "wasm32"))]
+pub use websocket_server::WebSocketServer;
+pub use wire::{Decoder, Encoder, Message};
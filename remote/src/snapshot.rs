@@ -0,0 +1,147 @@
+//! Snapshots of the retained state of a renderer, to reproduce rendering issues.
+//!
+//! A snapshot contains the scene, the renderer configuration, and the camera in one file. Fonts
+//! are embedded, so that the snapshot renders the same on other machines. Snapshots are loaded
+//! with `massive-viewer snapshot:<path>`.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use cosmic_text::FontSystem;
+use massive_geometry::{Camera, Point3, Vector3};
+use massive_renderer::{AtlasMetadata, RendererConfig, RendererState};
+use massive_scene::{CustomShapeCodecs, SceneChange};
+use serde::{Deserialize, Serialize};
+
+use crate::{Decoder, Encoder, Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The version of the snapshot format.
+    pub version: u32,
+    pub camera: WireCamera,
+    pub config: RendererConfig,
+    /// The atlases at the time of the snapshot, for information only.
+    pub atlases: Vec<AtlasMetadata>,
+    /// The font declarations followed by one transaction that creates the scene.
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WireCamera {
+    pub eye: [f64; 3],
+    pub target: [f64; 3],
+    pub up: [f64; 3],
+    pub fovy: f64,
+}
+
+impl From<Camera> for WireCamera {
+    fn from(camera: Camera) -> Self {
+        Self {
+            eye: camera.eye.into(),
+            target: camera.target.into(),
+            up: camera.up.into(),
+            fovy: camera.fovy,
+        }
+    }
+}
+
+impl From<WireCamera> for Camera {
+    fn from(camera: WireCamera) -> Self {
+        Self {
+            eye: Point3::from(camera.eye),
+            target: Point3::from(camera.target),
+            up: Vector3::from(camera.up),
+            fovy: camera.fovy,
+        }
+    }
+}
+
+impl Snapshot {
+    pub const VERSION: u32 = 17;
+
+    /// Capture the state of a renderer.
+    ///
+    /// Custom shapes are serialized with `codecs`.
+    pub fn capture(
+        state: &RendererState,
+        camera: Camera,
+        font_system: &FontSystem,
+        codecs: &CustomShapeCodecs,
+    ) -> Result<Self> {
+        let mut encoder = Encoder::embedding_fonts();
+        encoder.set_codecs(codecs.clone());
+        Ok(Self {
+            version: Self::VERSION,
+            camera: camera.into(),
+            config: state.config.clone(),
+            atlases: state.atlases.clone(),
+            messages: encoder.encode_state(font_system, state)?,
+        })
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = postcard::to_stdvec(self)?;
+        fs::write(path, bytes).with_context(|| format!("Failed to write snapshot {path:?}"))
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("Failed to read snapshot {path:?}"))?;
+        // The version comes first, so that snapshots of other versions are rejected before the
+        // rest fails to decode.
+        let (version, _) = postcard::take_from_bytes::<u32>(&bytes)?;
+        if version != Self::VERSION {
+            bail!(
+                "Snapshot version {version} is not supported, expected {}",
+                Self::VERSION
+            );
+        }
+        Ok(postcard::from_bytes(&bytes)?)
+    }
+
+    /// The scene changes that recreate the scene, to be applied to an empty scene.
+    ///
+    /// Loads the embedded fonts that are not available in `font_system`.
+    pub fn scene_changes(
+        &self,
+        font_system: &mut FontSystem,
+        codecs: &CustomShapeCodecs,
+    ) -> Result<Vec<SceneChange>> {
+        let mut decoder = Decoder::default();
+        decoder.set_codecs(codecs.clone());
+        let mut changes = Vec::new();
+        for message in &self.messages {
+            if let Some(decoded) = decoder.decode(font_system, message.clone())? {
+                changes.extend(decoded);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_rejects_other_versions_before_decoding() {
+        let mut bytes = postcard::to_stdvec(&(Snapshot::VERSION + 1)).unwrap();
+        // Not a snapshot of this version.
+        bytes.extend([0xff; 16]);
+        let path = std::env::temp_dir().join(format!("massive-snapshot-{}", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+
+        let error = Snapshot::read(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Snapshot version {} is not supported, expected {}",
+                Snapshot::VERSION + 1,
+                Snapshot::VERSION
+            )
+        );
+    }
+}
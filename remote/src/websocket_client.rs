@@ -0,0 +1,99 @@
+//! Receives scenes from a `WebSocketServer` in the browser.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use cosmic_text::FontSystem;
+use js_sys::{ArrayBuffer, Uint8Array};
+use log::{error, info};
+use massive_scene::SceneChange;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+use crate::{Decoder, Message};
+
+/// Receives scene changes from a WebSocket server.
+pub struct WebSocketReceiver {
+    socket: WebSocket,
+    receiver: UnboundedReceiver<Vec<SceneChange>>,
+    // Kept alive as long as the socket is used.
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+impl WebSocketReceiver {
+    /// Connect to the server at `url`, for example `ws://localhost:7818`.
+    pub fn connect(url: &str, font_system: Arc<Mutex<FontSystem>>) -> Result<Self> {
+        let socket = WebSocket::new(url).map_err(|e| anyhow!("Failed to connect: {e:?}"))?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let (sender, receiver) = unbounded_channel();
+        // The close handler drops the sender, so that `receive` returns `None` afterwards.
+        let sender = Rc::new(RefCell::new(Some(sender)));
+
+        let on_message = {
+            let sender = sender.clone();
+            let mut decoder = Decoder::default();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Err(e) = receive(&font_system, &mut decoder, &sender, event) {
+                    error!("Failed to receive scene changes: {e:?}");
+                }
+            })
+        };
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+            info!("WebSocket closed: {}", event.reason());
+            sender.borrow_mut().take();
+        });
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            receiver,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    /// Receive the next transaction of changes. Returns `None` if the connection was closed.
+    pub async fn receive(&mut self) -> Option<Vec<SceneChange>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for WebSocketReceiver {
+    fn drop(&mut self) {
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+fn receive(
+    font_system: &Mutex<FontSystem>,
+    decoder: &mut Decoder,
+    sender: &RefCell<Option<UnboundedSender<Vec<SceneChange>>>>,
+    event: MessageEvent,
+) -> Result<()> {
+    let buffer: ArrayBuffer = event
+        .data()
+        .dyn_into()
+        .map_err(|_| anyhow!("Expected a binary message"))?;
+    let bytes = Uint8Array::new(&buffer).to_vec();
+    let message: Message = postcard::from_bytes(&bytes)?;
+
+    let mut font_system = font_system.lock().unwrap();
+    if let Some(changes) = decoder.decode(&mut font_system, message)? {
+        if let Some(sender) = sender.borrow().as_ref() {
+            // The receiver may be gone already, in which case there is no one interested anymore.
+            let _ = sender.send(changes);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,283 @@
+//! Streams scenes to browsers over WebSocket.
+//!
+//! Each [`Message`] is sent as one binary WebSocket message, serialized with postcard. All clients
+//! see the same scene: Changes are broadcast, and clients that connect later receive the font
+//! declarations and a snapshot of the current scene first.
+//!
+//! No network I/O happens while the scene is sent: Handshakes run on their own threads, and each
+//! client has a writer thread that is fed by a bounded queue. Clients that don't keep up are
+//! disconnected.
+
+use std::{
+    collections::BTreeMap,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use log::{error, info, warn};
+use massive_scene::{CustomShapeCodecs, Director, SceneChange};
+use tungstenite::WebSocket;
+
+use crate::{
+    wire::{WireChange, WireSceneChange},
+    Encoder, Message,
+};
+
+/// The number of transactions that may be queued for a client before it is disconnected.
+const QUEUE_CAPACITY: usize = 64;
+/// How long a handshake or a write may block before the client is disconnected.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Broadcasts scene changes to all connected WebSocket clients.
+pub struct WebSocketServer {
+    address: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+    font_system: Arc<Mutex<FontSystem>>,
+}
+
+struct Shared {
+    encoder: Encoder,
+    /// All font declarations sent so far.
+    fonts: Vec<Message>,
+    snapshot: Snapshot,
+    clients: Vec<Client>,
+}
+
+/// The serialized messages of a transaction.
+type Frames = Arc<Vec<Vec<u8>>>;
+
+/// A connected client, served by its writer thread.
+struct Client {
+    peer: SocketAddr,
+    queue: SyncSender<Frames>,
+}
+
+impl WebSocketServer {
+    /// Listen for WebSocket clients at `address`.
+    ///
+    /// Clients are accepted on a background thread, the handshake of each client runs on its own
+    /// thread.
+    pub fn bind(address: SocketAddr, font_system: Arc<Mutex<FontSystem>>) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared {
+            // Browsers have no access to our fonts.
+            encoder: Encoder::embedding_fonts(),
+            fonts: Vec::new(),
+            snapshot: Snapshot::default(),
+            clients: Vec::new(),
+        }));
+
+        {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            error!("Failed to accept WebSocket client: {e:?}");
+                            continue;
+                        }
+                    };
+                    let shared = shared.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = accept(&shared, stream) {
+                            error!("Failed to accept WebSocket client: {e:?}");
+                        }
+                    });
+                }
+            });
+        }
+
+        Ok(Self {
+            address,
+            shared,
+            font_system,
+        })
+    }
+
+    /// The address the server listens at.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The number of clients currently connected.
+    pub fn client_count(&self) -> usize {
+        self.shared.lock().unwrap().clients.len()
+    }
+
+    /// Set the codecs used to serialize custom shapes.
+    pub fn set_codecs(&mut self, codecs: CustomShapeCodecs) {
+        self.shared.lock().unwrap().encoder.set_codecs(codecs);
+    }
+
+    /// Broadcast a transaction of changes.
+    ///
+    /// The changes are queued for each client and sent by its writer thread. Clients that failed
+    /// to receive earlier changes, or have [`QUEUE_CAPACITY`] transactions queued already, are
+    /// disconnected.
+    pub fn send(&mut self, changes: Vec<SceneChange>) -> Result<()> {
+        let mut shared = self.shared.lock().unwrap();
+        let messages = {
+            let font_system = self.font_system.lock().unwrap();
+            shared.encoder.encode(&font_system, changes)?
+        };
+
+        for message in &messages {
+            match message {
+                Message::Font { .. } => shared.fonts.push(message.clone()),
+                Message::Changes(changes) => shared.snapshot.apply(changes),
+            }
+        }
+
+        let frames: Frames = Arc::new(
+            messages
+                .iter()
+                .map(|message| Ok(postcard::to_stdvec(message)?))
+                .collect::<Result<_>>()?,
+        );
+
+        shared
+            .clients
+            .retain(|client| match client.queue.try_send(frames.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("Disconnecting WebSocket client {}: Too slow", client.peer);
+                    false
+                }
+                // The writer has already logged why.
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        Ok(())
+    }
+
+    /// A director that broadcasts all its changes.
+    pub fn into_director(mut self) -> Director {
+        Director::new(move |changes| self.send(changes))
+    }
+}
+
+fn accept(shared: &Mutex<Shared>, stream: TcpStream) -> Result<()> {
+    let peer = stream.peer_addr()?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let client = tungstenite::accept(stream)?;
+
+    let (queue, frames) = mpsc::sync_channel::<Frames>(QUEUE_CAPACITY);
+
+    {
+        // Hold the lock until the client is registered, so that it does not miss any changes.
+        let mut shared = shared.lock().unwrap();
+        let snapshot = Message::Changes(shared.snapshot.changes());
+        let initial_frames: Vec<_> = shared
+            .fonts
+            .iter()
+            .chain([&snapshot])
+            .map(|message| Ok(postcard::to_stdvec(message)?))
+            .collect::<Result<_>>()?;
+        // The queue is empty, so this can't fail.
+        queue.try_send(Arc::new(initial_frames))?;
+        shared.clients.push(Client { peer, queue });
+    }
+
+    info!("WebSocket client {peer} connected");
+    write_frames(client, frames, peer);
+    Ok(())
+}
+
+/// Send the queued frames to the client until it disconnects or is disconnected.
+fn write_frames(
+    mut client: WebSocket<TcpStream>,
+    frames: mpsc::Receiver<Frames>,
+    peer: SocketAddr,
+) {
+    for frames in frames {
+        if let Err(e) = send_frames(&mut client, frames.iter().cloned()) {
+            warn!("Disconnecting WebSocket client {peer}: {e:?}");
+            return;
+        }
+    }
+    info!("WebSocket client {peer} disconnected");
+}
+
+fn send_frames(
+    client: &mut WebSocket<TcpStream>,
+    frames: impl IntoIterator<Item = Vec<u8>>,
+) -> Result<()> {
+    for frame in frames {
+        client.write(tungstenite::Message::Binary(frame))?;
+    }
+    client.flush()?;
+    Ok(())
+}
+
+/// The current state of the scene in its serialized form.
+#[derive(Debug, Default)]
+struct Snapshot {
+    matrices: BTreeMap<usize, WireSceneChange>,
+    positions: BTreeMap<usize, WireSceneChange>,
+    shapes: BTreeMap<usize, WireSceneChange>,
+    hidden: BTreeMap<usize, WireSceneChange>,
+}
+
+impl Snapshot {
+    fn apply(&mut self, changes: &[WireSceneChange]) {
+        for change in changes {
+            match change {
+                WireSceneChange::Matrix(change) => {
+                    apply(&mut self.matrices, change, WireSceneChange::Matrix)
+                }
+                WireSceneChange::Position(change) => {
+                    apply(&mut self.positions, change, WireSceneChange::Position)
+                }
+                WireSceneChange::PositionedShape(change) => {
+                    if let WireChange::Delete(id) = change {
+                        self.hidden.remove(id);
+                    }
+                    apply(&mut self.shapes, change, WireSceneChange::PositionedShape)
+                }
+                WireSceneChange::ShapeVisibility(id, visible) => {
+                    if *visible {
+                        self.hidden.remove(id);
+                    } else {
+                        self.hidden.insert(*id, change.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// The changes that create the current scene.
+    fn changes(&self) -> Vec<WireSceneChange> {
+        self.matrices
+            .values()
+            .chain(self.positions.values())
+            .chain(self.shapes.values())
+            .chain(self.hidden.values())
+            .cloned()
+            .collect()
+    }
+}
+
+fn apply<T: Clone>(
+    objects: &mut BTreeMap<usize, WireSceneChange>,
+    change: &WireChange<T>,
+    promote: fn(WireChange<T>) -> WireSceneChange,
+) {
+    match change {
+        WireChange::Create(id, value) | WireChange::Update(id, value) => {
+            objects.insert(*id, promote(WireChange::Create(*id, value.clone())));
+        }
+        WireChange::Delete(id) => {
+            objects.remove(id);
+        }
+    }
+}
@@ -0,0 +1,715 @@
+//! The serialized scene format.
+//!
+//! Scene changes are transferred as a sequence of [`Message`]s, serialized with postcard.
+//!
+//! Font ids are only valid inside the process that loaded the fonts. So glyphs refer to fonts by
+//! a connection local index that is declared with a [`Message::Font`] before it is used. The
+//! receiver resolves the font by its PostScript name, so the font must be available to the
+//! receiver's font system, too. Receivers without access to the sender's fonts, like browsers, get
+//! the font data embedded in the declaration (see [`Encoder::embedding_fonts`]).
+//!
+//! Custom shapes are serialized by the codecs registered with [`Encoder::set_codecs`] and
+//! [`Decoder::set_codecs`].
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use cosmic_text::{fontdb, CacheKey, CacheKeyFlags, FontSystem, SubpixelBin};
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_renderer::RendererState;
+use massive_scene::{
+    Axis, Change, CustomShapeCodecs, Id, Pin, PositionRenderObj, PositionedRenderShape,
+    SceneChange, Shape,
+};
+use massive_shapes::{
+    Backdrop, Billboard, Border, Caret, FocusRing, GlyphRun, GlyphRunMetrics, Pattern, Quad,
+    Reveal, RoundedRect, RunGlyph, Shadow, TextWeight,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::integrity::SceneIntegrity;
+
+/// The largest font size of a received glyph. Larger glyphs would take too much memory to
+/// rasterize.
+const MAX_FONT_SIZE: f32 = 1024.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    /// Declares the font glyphs refer to with the index `font`.
+    ///
+    /// If `data` is set, it contains the font file, which is loaded if the receiver does not have a
+    /// font with this name.
+    Font {
+        font: u32,
+        post_script_name: String,
+        data: Option<Vec<u8>>,
+    },
+    /// A number of scene changes that are applied as one transaction.
+    Changes(Vec<WireSceneChange>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireSceneChange {
+    /// Column major.
+    Matrix(WireChange<[[f64; 4]; 4]>),
+    Position(WireChange<WirePosition>),
+    PositionedShape(WireChange<WirePositionedShape>),
+    ShapeVisibility(usize, bool),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireChange<T> {
+    Create(usize, T),
+    Delete(usize),
+    Update(usize, T),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WirePosition {
+    pub parent: Option<usize>,
+    pub matrix: usize,
+    pub pin: Option<WirePin>,
+    pub overlay: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum WirePin {
+    Fixed {
+        vertical: bool,
+    },
+    Sticky {
+        vertical: bool,
+        at: f64,
+        travel: f64,
+    },
+}
+
+impl From<Pin> for WirePin {
+    fn from(pin: Pin) -> Self {
+        let vertical = pin.axis() == Axis::Y;
+        match pin {
+            Pin::Fixed(_) => Self::Fixed { vertical },
+            Pin::Sticky { at, travel, .. } => Self::Sticky {
+                vertical,
+                at,
+                travel,
+            },
+        }
+    }
+}
+
+impl From<WirePin> for Pin {
+    fn from(pin: WirePin) -> Self {
+        let axis = |vertical| if vertical { Axis::Y } else { Axis::X };
+        match pin {
+            WirePin::Fixed { vertical } => Pin::Fixed(axis(vertical)),
+            WirePin::Sticky {
+                vertical,
+                at,
+                travel,
+            } => Pin::Sticky {
+                axis: axis(vertical),
+                at,
+                travel,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WirePositionedShape {
+    pub position: usize,
+    pub shape: WireShape,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireShape {
+    GlyphRun(WireGlyphRun),
+    Quads(Vec<WireQuad>),
+    /// A custom shape, serialized by the codec registered with `name`.
+    Custom {
+        name: String,
+        data: Vec<u8>,
+    },
+    Caret(Caret),
+    FocusRing(FocusRing),
+    Shadow(Shadow),
+    Backdrop(Backdrop),
+    Border(Border),
+    RoundedRect(RoundedRect),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireGlyphRun {
+    pub translation: [f64; 3],
+    pub max_ascent: u32,
+    pub max_descent: u32,
+    pub width: u32,
+    pub text_color: Color,
+    pub text_weight: TextWeight,
+    pub glyphs: Vec<WireGlyph>,
+    pub billboard: Option<Billboard>,
+    pub constant_screen_size: bool,
+    pub reveal: Option<Reveal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireGlyph {
+    /// The font index declared with [`Message::Font`].
+    pub font: u32,
+    pub glyph_id: u16,
+    pub font_size_bits: u32,
+    /// The subpixel bins, `0..=3`.
+    pub x_bin: u8,
+    pub y_bin: u8,
+    pub flags: u32,
+    pub hitbox_pos: (i32, i32),
+    pub hitbox_width: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireQuad {
+    pub vertices: [[f64; 3]; 4],
+    pub color: Color,
+    pub pattern: Option<Pattern>,
+}
+
+/// Converts scene changes to messages. Keeps track of the fonts declared to the receiver.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    fonts: HashMap<fontdb::ID, u32>,
+    embed_fonts: bool,
+    codecs: CustomShapeCodecs,
+}
+
+impl Encoder {
+    /// An encoder that embeds the font data in the font declarations.
+    pub fn embedding_fonts() -> Self {
+        Self {
+            embed_fonts: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_codecs(&mut self, codecs: CustomShapeCodecs) {
+        self.codecs = codecs;
+    }
+
+    /// Encode a transaction of changes.
+    ///
+    /// Returns the font declarations the receiver does not know yet, followed by the changes.
+    pub fn encode(
+        &mut self,
+        font_system: &FontSystem,
+        changes: Vec<SceneChange>,
+    ) -> Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        let changes = changes
+            .into_iter()
+            .map(|change| self.encode_change(font_system, change, &mut messages))
+            .collect::<Result<_>>()?;
+        messages.push(Message::Changes(changes));
+        Ok(messages)
+    }
+
+    /// Encode the scene of a renderer as one transaction that creates all of its objects.
+    ///
+    /// Ids are kept, so that the receiver renders the shapes in the same order.
+    pub fn encode_state(
+        &mut self,
+        font_system: &FontSystem,
+        state: &RendererState,
+    ) -> Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        let mut changes: Vec<WireSceneChange> = state
+            .matrices
+            .iter()
+            .map(|(id, matrix)| {
+                WireSceneChange::Matrix(WireChange::Create(**id, (**matrix).into()))
+            })
+            .collect();
+        changes.extend(state.positions.iter().map(|(id, position)| {
+            WireSceneChange::Position(WireChange::Create(
+                **id,
+                WirePosition {
+                    parent: position.parent.map(|id| *id),
+                    matrix: *position.matrix,
+                    pin: position.pin.map(WirePin::from),
+                    overlay: position.overlay,
+                },
+            ))
+        }));
+        for (id, shape, visible) in &state.shapes {
+            let wire_shape = WirePositionedShape {
+                position: *shape.position,
+                shape: self.encode_shape(font_system, &shape.shape, &mut messages)?,
+            };
+            changes.push(WireSceneChange::PositionedShape(WireChange::Create(
+                **id, wire_shape,
+            )));
+            if !visible {
+                changes.push(WireSceneChange::ShapeVisibility(**id, false));
+            }
+        }
+        messages.push(Message::Changes(changes));
+        Ok(messages)
+    }
+
+    fn encode_change(
+        &mut self,
+        font_system: &FontSystem,
+        change: SceneChange,
+        messages: &mut Vec<Message>,
+    ) -> Result<WireSceneChange> {
+        Ok(match change {
+            SceneChange::Matrix(change) => {
+                WireSceneChange::Matrix(encode(change, |m| Ok(m.into()))?)
+            }
+            SceneChange::Position(change) => WireSceneChange::Position(encode(change, |p| {
+                Ok(WirePosition {
+                    parent: p.parent.map(|id| *id),
+                    matrix: *p.matrix,
+                    pin: p.pin.map(WirePin::from),
+                    overlay: p.overlay,
+                })
+            })?),
+            SceneChange::PositionedShape(change) => {
+                WireSceneChange::PositionedShape(encode(change, |shape| {
+                    Ok(WirePositionedShape {
+                        position: *shape.position,
+                        shape: self.encode_shape(font_system, &shape.shape, messages)?,
+                    })
+                })?)
+            }
+            SceneChange::ShapeVisibility(id, visible) => {
+                WireSceneChange::ShapeVisibility(*id, visible)
+            }
+        })
+    }
+
+    fn encode_shape(
+        &mut self,
+        font_system: &FontSystem,
+        shape: &Shape,
+        messages: &mut Vec<Message>,
+    ) -> Result<WireShape> {
+        Ok(match shape {
+            Shape::GlyphRun(run) => {
+                let glyphs = run
+                    .glyphs
+                    .iter()
+                    .map(|glyph| {
+                        let key = glyph.key;
+                        Ok(WireGlyph {
+                            font: self.font_index(font_system, key.font_id, messages)?,
+                            glyph_id: key.glyph_id,
+                            font_size_bits: key.font_size_bits,
+                            x_bin: key.x_bin as u8,
+                            y_bin: key.y_bin as u8,
+                            flags: key.flags.bits(),
+                            hitbox_pos: glyph.hitbox_pos,
+                            hitbox_width: glyph.hitbox_width,
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+
+                WireShape::GlyphRun(WireGlyphRun {
+                    translation: run.translation.into(),
+                    max_ascent: run.metrics.max_ascent,
+                    max_descent: run.metrics.max_descent,
+                    width: run.metrics.width,
+                    text_color: run.text_color,
+                    text_weight: run.text_weight,
+                    glyphs,
+                    billboard: run.billboard,
+                    constant_screen_size: run.constant_screen_size,
+                    reveal: run.reveal,
+                })
+            }
+            Shape::Quads(quads) => WireShape::Quads(
+                quads
+                    .iter()
+                    .map(|quad| WireQuad {
+                        vertices: quad.vertices.map(|v| v.into()),
+                        color: quad.color,
+                        pattern: quad.pattern,
+                    })
+                    .collect(),
+            ),
+            Shape::Caret(caret) => WireShape::Caret(*caret),
+            Shape::FocusRing(ring) => WireShape::FocusRing(*ring),
+            Shape::Shadow(shadow) => WireShape::Shadow(*shadow),
+            Shape::Backdrop(backdrop) => WireShape::Backdrop(*backdrop),
+            Shape::Border(border) => WireShape::Border(*border),
+            Shape::RoundedRect(rect) => WireShape::RoundedRect(*rect),
+            Shape::Custom(shape) => {
+                let codec = self
+                    .codecs
+                    .by_type(shape.shape_type())
+                    .ok_or_else(|| anyhow!("No codec registered for custom shape {shape:?}"))?;
+                WireShape::Custom {
+                    name: codec.name().into(),
+                    data: codec.serialize(shape.as_ref())?,
+                }
+            }
+        })
+    }
+
+    fn font_index(
+        &mut self,
+        font_system: &FontSystem,
+        font_id: fontdb::ID,
+        messages: &mut Vec<Message>,
+    ) -> Result<u32> {
+        if let Some(index) = self.fonts.get(&font_id) {
+            return Ok(*index);
+        }
+
+        let face = font_system
+            .db()
+            .face(font_id)
+            .ok_or_else(|| anyhow!("Font {font_id:?} not found"))?;
+        let data = if self.embed_fonts {
+            let data = font_system
+                .db()
+                .with_face_data(font_id, |data, _index| data.to_vec())
+                .ok_or_else(|| anyhow!("Data of font {font_id:?} not available"))?;
+            Some(data)
+        } else {
+            None
+        };
+        let index = self.fonts.len() as u32;
+        self.fonts.insert(font_id, index);
+        messages.push(Message::Font {
+            font: index,
+            post_script_name: face.post_script_name.clone(),
+            data,
+        });
+        Ok(index)
+    }
+}
+
+/// Converts messages back to scene changes.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    fonts: HashMap<u32, fontdb::ID>,
+    codecs: CustomShapeCodecs,
+    integrity: SceneIntegrity,
+}
+
+impl Decoder {
+    pub fn set_codecs(&mut self, codecs: CustomShapeCodecs) {
+        self.codecs = codecs;
+    }
+
+    /// Decode a message.
+    ///
+    /// Returns the changes if the message contained some. Changes that would break the
+    /// referential integrity of the scene are rejected as a whole.
+    pub fn decode(
+        &mut self,
+        font_system: &mut FontSystem,
+        message: Message,
+    ) -> Result<Option<Vec<SceneChange>>> {
+        match message {
+            Message::Font {
+                font,
+                post_script_name,
+                data,
+            } => {
+                let mut font_id = find_font(font_system, &post_script_name);
+                if let (None, Some(data)) = (font_id, data) {
+                    font_system.db_mut().load_font_data(data);
+                    font_id = find_font(font_system, &post_script_name);
+                }
+                let font_id =
+                    font_id.ok_or_else(|| anyhow!("Font `{post_script_name}` is not available"))?;
+                self.fonts.insert(font, font_id);
+                Ok(None)
+            }
+            Message::Changes(changes) => {
+                let changes = changes
+                    .into_iter()
+                    .map(|change| self.decode_change(change))
+                    .collect::<Result<Vec<_>>>()?;
+                self.integrity.apply(&changes)?;
+                Ok(Some(changes))
+            }
+        }
+    }
+
+    fn decode_change(&self, change: WireSceneChange) -> Result<SceneChange> {
+        Ok(match change {
+            WireSceneChange::Matrix(change) => {
+                SceneChange::Matrix(decode(change, |m| Ok(Matrix4::from(m)))?)
+            }
+            WireSceneChange::Position(change) => SceneChange::Position(decode(change, |p| {
+                Ok(PositionRenderObj {
+                    parent: p.parent.map(Id::from_raw),
+                    matrix: Id::from_raw(p.matrix),
+                    pin: p.pin.map(Pin::from),
+                    overlay: p.overlay,
+                })
+            })?),
+            WireSceneChange::PositionedShape(change) => {
+                SceneChange::PositionedShape(decode(change, |shape| {
+                    Ok(PositionedRenderShape {
+                        position: Id::from_raw(shape.position),
+                        shape: self.decode_shape(shape.shape)?,
+                    })
+                })?)
+            }
+            WireSceneChange::ShapeVisibility(id, visible) => {
+                SceneChange::ShapeVisibility(Id::from_raw(id), visible)
+            }
+        })
+    }
+
+    fn decode_shape(&self, shape: WireShape) -> Result<Shape> {
+        Ok(match shape {
+            WireShape::GlyphRun(run) => {
+                let glyphs = run
+                    .glyphs
+                    .into_iter()
+                    .map(|glyph| {
+                        let font_id = *self
+                            .fonts
+                            .get(&glyph.font)
+                            .ok_or_else(|| anyhow!("Font {} was not declared", glyph.font))?;
+                        let font_size = f32::from_bits(glyph.font_size_bits);
+                        if !(font_size > 0.0 && font_size <= MAX_FONT_SIZE) {
+                            bail!("Invalid font size {font_size}");
+                        }
+                        let key = CacheKey {
+                            font_id,
+                            glyph_id: glyph.glyph_id,
+                            font_size_bits: glyph.font_size_bits,
+                            x_bin: subpixel_bin(glyph.x_bin)?,
+                            y_bin: subpixel_bin(glyph.y_bin)?,
+                            flags: CacheKeyFlags::from_bits_truncate(glyph.flags),
+                        };
+                        Ok(RunGlyph::new(key, glyph.hitbox_pos, glyph.hitbox_width))
+                    })
+                    .collect::<Result<_>>()?;
+
+                let mut glyph_run = GlyphRun::new(
+                    Vector3::from(run.translation),
+                    GlyphRunMetrics {
+                        max_ascent: run.max_ascent,
+                        max_descent: run.max_descent,
+                        width: run.width,
+                    },
+                    run.text_color,
+                    run.text_weight,
+                    glyphs,
+                );
+                glyph_run.billboard = run.billboard;
+                glyph_run.constant_screen_size = run.constant_screen_size;
+                glyph_run.reveal = run.reveal;
+                Shape::GlyphRun(glyph_run)
+            }
+            WireShape::Quads(quads) => Shape::Quads(
+                quads
+                    .into_iter()
+                    .map(|quad| Quad {
+                        vertices: quad.vertices.map(Vector3::from),
+                        color: quad.color,
+                        pattern: quad.pattern,
+                    })
+                    .collect(),
+            ),
+            WireShape::Caret(caret) => Shape::Caret(caret),
+            WireShape::FocusRing(ring) => Shape::FocusRing(ring),
+            WireShape::Shadow(shadow) => Shape::Shadow(shadow),
+            WireShape::Backdrop(backdrop) => Shape::Backdrop(backdrop),
+            WireShape::Border(border) => Shape::Border(border),
+            WireShape::RoundedRect(rect) => Shape::RoundedRect(rect),
+            WireShape::Custom { name, data } => {
+                let codec = self
+                    .codecs
+                    .by_name(&name)
+                    .ok_or_else(|| anyhow!("No codec registered for custom shape `{name}`"))?;
+                Shape::Custom(codec.deserialize(&data)?)
+            }
+        })
+    }
+}
+
+fn find_font(font_system: &FontSystem, post_script_name: &str) -> Option<fontdb::ID> {
+    font_system
+        .db()
+        .faces()
+        .find(|face| face.post_script_name == post_script_name)
+        .map(|face| face.id)
+}
+
+fn encode<T, W>(change: Change<T>, f: impl FnOnce(T) -> Result<W>) -> Result<WireChange<W>> {
+    Ok(match change {
+        Change::Create(id, value) => WireChange::Create(*id, f(value)?),
+        Change::Delete(id) => WireChange::Delete(*id),
+        Change::Update(id, value) => WireChange::Update(*id, f(value)?),
+    })
+}
+
+fn decode<W, T>(change: WireChange<W>, f: impl FnOnce(W) -> Result<T>) -> Result<Change<T>> {
+    Ok(match change {
+        WireChange::Create(id, value) => Change::Create(Id::from_raw(id), f(value)?),
+        WireChange::Delete(id) => Change::Delete(Id::from_raw(id)),
+        WireChange::Update(id, value) => Change::Update(Id::from_raw(id), f(value)?),
+    })
+}
+
+fn subpixel_bin(bin: u8) -> Result<SubpixelBin> {
+    Ok(match bin {
+        0 => SubpixelBin::Zero,
+        1 => SubpixelBin::One,
+        2 => SubpixelBin::Two,
+        3 => SubpixelBin::Three,
+        bin => return Err(anyhow!("Invalid subpixel bin {bin}")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use massive_shapes::Quads;
+
+    use super::*;
+
+    static FONT: &[u8] =
+        include_bytes!("../../examples/shared/src/fonts/Montserrat/Montserrat-Regular.ttf");
+
+    fn font_system(with_font: bool) -> FontSystem {
+        let mut db = fontdb::Database::new();
+        if with_font {
+            db.load_font_source(fontdb::Source::Binary(Arc::new(FONT)));
+        }
+        FontSystem::new_with_locale_and_db("en-US".into(), db)
+    }
+
+    fn scene_changes(font_id: fontdb::ID) -> Vec<SceneChange> {
+        let key = CacheKey {
+            font_id,
+            glyph_id: 42,
+            font_size_bits: 16f32.to_bits(),
+            x_bin: SubpixelBin::Two,
+            y_bin: SubpixelBin::Zero,
+            flags: CacheKeyFlags::empty(),
+        };
+        let run = GlyphRun::new(
+            (10.0, 20.0, 0.0),
+            GlyphRunMetrics {
+                max_ascent: 12,
+                max_descent: 4,
+                width: 9,
+            },
+            Color::rgb(0.1, 0.2, 0.3),
+            TextWeight::BOLD,
+            vec![RunGlyph::new(key, (1, -12), 9.0)],
+        );
+        let quads: Quads = vec![Quad {
+            vertices: [
+                (0.0, 0.0, 0.0),
+                (0.0, 1.0, 0.0),
+                (1.0, 1.0, 0.0),
+                (1.0, 0.0, 0.0),
+            ]
+            .map(Vector3::from),
+            color: Color::WHITE,
+            pattern: None,
+        }];
+
+        vec![
+            SceneChange::Matrix(Change::Create(
+                Id::from_raw(0),
+                Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0)),
+            )),
+            SceneChange::Position(Change::Create(
+                Id::from_raw(0),
+                PositionRenderObj {
+                    parent: None,
+                    matrix: Id::from_raw(0),
+                    pin: None,
+                    overlay: false,
+                },
+            )),
+            SceneChange::PositionedShape(Change::Create(
+                Id::from_raw(0),
+                PositionedRenderShape {
+                    position: Id::from_raw(0),
+                    shape: Shape::GlyphRun(run),
+                },
+            )),
+            SceneChange::PositionedShape(Change::Create(
+                Id::from_raw(1),
+                PositionedRenderShape {
+                    position: Id::from_raw(0),
+                    shape: Shape::Quads(quads),
+                },
+            )),
+            SceneChange::ShapeVisibility(Id::from_raw(1), false),
+        ]
+    }
+
+    fn first_font(font_system: &FontSystem) -> fontdb::ID {
+        font_system.db().faces().next().unwrap().id
+    }
+
+    /// Serialize and deserialize a message like a connection does.
+    fn transfer(message: &Message) -> Message {
+        postcard::from_bytes(&postcard::to_stdvec(message).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn changes_survive_a_round_trip() {
+        let sender_fonts = font_system(true);
+        let messages = Encoder::embedding_fonts()
+            .encode(&sender_fonts, scene_changes(first_font(&sender_fonts)))
+            .unwrap();
+        assert!(matches!(messages[0], Message::Font { font: 0, .. }));
+        assert_eq!(messages.len(), 2);
+
+        // The receiver loads the embedded font.
+        let mut receiver_fonts = font_system(false);
+        let mut decoder = Decoder::default();
+        let mut changes = Vec::new();
+        for message in &messages {
+            changes.extend(
+                decoder
+                    .decode(&mut receiver_fonts, transfer(message))
+                    .unwrap()
+                    .unwrap_or_default(),
+            );
+        }
+
+        let expected = scene_changes(first_font(&receiver_fonts));
+        assert_eq!(format!("{changes:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn fonts_are_declared_once() {
+        let fonts = font_system(true);
+        let font_id = first_font(&fonts);
+        let mut encoder = Encoder::default();
+        let first = encoder.encode(&fonts, scene_changes(font_id)).unwrap();
+        assert!(matches!(&first[0], Message::Font { data: None, .. }));
+
+        let second = encoder.encode(&fonts, scene_changes(font_id)).unwrap();
+        assert_eq!(second.len(), 1);
+        assert!(matches!(second[0], Message::Changes(_)));
+    }
+
+    #[test]
+    fn glyphs_of_undeclared_fonts_are_rejected() {
+        let sender_fonts = font_system(true);
+        let messages = Encoder::embedding_fonts()
+            .encode(&sender_fonts, scene_changes(first_font(&sender_fonts)))
+            .unwrap();
+
+        // Skip the font declaration.
+        let mut decoder = Decoder::default();
+        let result = decoder.decode(&mut font_system(false), transfer(&messages[1]));
+        assert!(result.is_err());
+    }
+}
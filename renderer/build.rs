@@ -0,0 +1,118 @@
+//! Validates all WGSL shaders, so that shader errors fail the build instead of the creation of
+//! the renderer.
+//!
+//! With the `precompiled-shaders` feature, the shaders are also compiled to SPIR-V, see
+//! `src/tools/shader_module.rs`, and translated to MSL, so that shaders the Metal backend can't
+//! translate fail the build, too. The MSL is written next to the SPIR-V for inspection.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use naga::{
+    back::{msl, spv},
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+
+fn main() {
+    let src = Path::new("src");
+    println!("cargo:rerun-if-changed={}", src.display());
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let precompile = env::var_os("CARGO_FEATURE_PRECOMPILED_SHADERS").is_some();
+
+    let mut shaders = Vec::new();
+    find_shaders(src, &mut shaders);
+    shaders.sort();
+
+    let mut failed = false;
+    for path in &shaders {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = fs::read_to_string(path).expect("Failed to read shader");
+        let label = path.strip_prefix(src).unwrap().to_string_lossy();
+
+        let module = match naga::front::wgsl::parse_str(&source) {
+            Ok(module) => module,
+            Err(e) => {
+                eprintln!("{}", e.emit_to_string_with_path(&source, &*label));
+                failed = true;
+                continue;
+            }
+        };
+
+        // The capabilities are checked against the device when the pipelines are created.
+        let info =
+            match Validator::new(ValidationFlags::all(), Capabilities::all()).validate(&module) {
+                Ok(info) => info,
+                Err(e) => {
+                    eprintln!("{}", e.emit_to_string_with_path(&source, &*label));
+                    failed = true;
+                    continue;
+                }
+            };
+
+        if precompile {
+            let spirv = spv::write_vec(&module, &info, &spirv_options(), None)
+                .unwrap_or_else(|e| panic!("Failed to compile {label} to SPIR-V: {e}"));
+            let target = out_dir.join("shaders").join(format!("{label}.spv"));
+            fs::create_dir_all(target.parent().unwrap())
+                .expect("Failed to create shader directory");
+            fs::write(&target, bytemuck::cast_slice::<u32, u8>(&spirv))
+                .expect("Failed to write SPIR-V");
+
+            let (metal, translation) = msl::write_string(
+                &module,
+                &info,
+                &msl_options(),
+                &msl::PipelineOptions::default(),
+            )
+            .unwrap_or_else(|e| panic!("Failed to translate {label} to MSL: {e}"));
+            for entry_point in translation.entry_point_names {
+                if let Err(e) = entry_point {
+                    panic!("Failed to translate an entry point of {label} to MSL: {e}");
+                }
+            }
+            fs::write(target.with_extension("metal"), metal).expect("Failed to write MSL");
+        }
+    }
+
+    if failed {
+        panic!("Shader validation failed");
+    }
+}
+
+/// The options wgpu's Vulkan backend uses for WGSL shaders.
+///
+/// The y axis is not flipped in the shader, because the backend flips the viewport.
+fn spirv_options() -> spv::Options<'static> {
+    spv::Options {
+        flags: spv::WriterFlags::LABEL_VARYINGS
+            | spv::WriterFlags::CLAMP_FRAG_DEPTH
+            | spv::WriterFlags::FORCE_POINT_SIZE,
+        ..spv::Options::default()
+    }
+}
+
+/// The options for the translation to MSL.
+///
+/// The bindings are assigned by wgpu's Metal backend when the pipelines are created, so missing
+/// ones are faked here.
+fn msl_options() -> msl::Options {
+    msl::Options {
+        lang_version: (2, 1),
+        fake_missing_bindings: true,
+        ..msl::Options::default()
+    }
+}
+
+fn find_shaders(dir: &Path, shaders: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).expect("Failed to read source directory") {
+        let path = entry.expect("Failed to read directory entry").path();
+        if path.is_dir() {
+            find_shaders(&path, shaders);
+        } else if path.extension().is_some_and(|ext| ext == "wgsl") {
+            shaders.push(path);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> model_view: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    // The position relative to the center of the region.
+    @location(1) local: vec2<f32>,
+    @location(2) half_size: vec2<f32>,
+    @location(3) radius: f32,
+    @location(4) tint: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) half_size: vec2<f32>,
+    @location(2) @interpolate(flat) radius: f32,
+    @location(3) @interpolate(flat) tint: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    vertex_input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
+    out.local = vertex_input.local;
+    out.half_size = vertex_input.half_size;
+    out.radius = vertex_input.radius;
+    out.tint = vertex_input.tint;
+    return out;
+}
+
+// Fragment shader
+
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> layer: Layer;
+
+// See `BlurUniforms`.
+struct Blur {
+    step: vec2<f32>,
+    sigma: f32,
+    taps: f32,
+    scene_size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+// The blurred scene.
+@group(2) @binding(0)
+var t_blurred: texture_2d<f32>;
+@group(2) @binding(1)
+var s_blurred: sampler;
+@group(2) @binding(2)
+var<uniform> blur: Blur;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+@fragment
+fn fs_backdrop(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The signed distance to the rounded rectangle, anti-aliased over a pixel.
+    let q = abs(in.local) - (in.half_size - vec2<f32>(in.radius));
+    let distance = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - in.radius;
+    let coverage = clamp(0.5 - distance / max(fwidth(distance), 0.0001), 0.0, 1.0);
+
+    // The blurred scene at the same pixel.
+    let blurred = textureSample(t_blurred, s_blurred, in.clip_position.xy / blur.scene_size);
+    let color = mix(blurred.rgb, in.tint.rgb, in.tint.a);
+    return layer_color(vec4<f32>(color, coverage));
+}
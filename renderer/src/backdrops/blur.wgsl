@@ -0,0 +1,56 @@
+// Full screen passes that blur the scene and copy it to the target.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+// A triangle that covers the viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 2u), f32((index << 1u) & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// See `BlurUniforms`.
+struct Blur {
+    // The distance between the taps in texture coordinates of the source.
+    step: vec2<f32>,
+    // The standard deviation in taps.
+    sigma: f32,
+    // The number of taps on each side.
+    taps: f32,
+    // The size of the scene in pixels.
+    scene_size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> blur: Blur;
+
+@fragment
+fn fs_blur(in: VertexOutput) -> @location(0) vec4<f32> {
+    let taps = i32(blur.taps);
+    var sum = vec4<f32>(0.0);
+    var weights = 0.0;
+    for (var i = -taps; i <= taps; i = i + 1) {
+        let x = f32(i);
+        let weight = exp(-x * x / (2.0 * blur.sigma * blur.sigma));
+        sum += weight * textureSampleLevel(t_source, s_source, in.tex_coords + blur.step * x, 0.0);
+        weights += weight;
+    }
+    return sum / weights;
+}
+
+// Copies the scene to the same pixels of the target, regardless of the viewport.
+@fragment
+fn fs_blit(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(t_source, s_source, in.clip_position.xy / blur.scene_size, 0.0);
+}
@@ -0,0 +1,523 @@
+use std::{collections::HashMap, mem::size_of};
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_scene::Shape;
+use massive_shapes::Backdrop;
+use static_assertions::const_assert_eq;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BufferUsages,
+};
+
+use crate::{
+    bind_group_entries,
+    pods::RoundedRectVertex,
+    renderer::{PreparationContext, RenderContext},
+    tools::{create_pipeline, texture_sampler, BindGroupLayoutBuilder, QuadIndexBuffer},
+};
+
+/// Blur radii are clamped to this, to limit the number of taps.
+const MAX_BLUR: f64 = 64.0;
+
+/// The parameters of a blur pass, and the size of the scene.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Pod, Zeroable)]
+struct BlurUniforms {
+    /// The distance between the taps in texture coordinates of the source.
+    step: [f32; 2],
+    /// The standard deviation in taps.
+    sigma: f32,
+    /// The number of taps on each side.
+    taps: f32,
+    scene_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+// WebGL uniform requirement
+const_assert_eq!(size_of::<BlurUniforms>() % 16, 0);
+
+/// Renders [`Backdrop`]s.
+///
+/// When there are backdrops, the world is rendered into an intermediate texture instead of the
+/// target. The texture is blurred at half the resolution once for each blur radius and copied to
+/// the target. The backdrops are then rendered with the blurred textures, before the overlay.
+pub struct BackdropRenderer {
+    pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    blit_pipeline: wgpu::RenderPipeline,
+    /// A texture, a sampler, and the blur uniforms.
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_sampler: wgpu::Sampler,
+    index_buffer: QuadIndexBuffer,
+    target_format: wgpu::TextureFormat,
+
+    targets: Option<Targets>,
+    layers: Vec<BackdropLayer>,
+}
+
+struct BackdropLayer {
+    /// The index of the shape group this layer was prepared from.
+    group: usize,
+    /// The blur radius in pixels.
+    blur: u32,
+    model_matrix: Matrix4,
+    vertex_buffer: wgpu::Buffer,
+    quad_count: usize,
+}
+
+/// The textures the world is rendered to and blurred into, for one size of the target.
+struct Targets {
+    size: (u32, u32),
+    scene: wgpu::TextureView,
+    /// Binds the scene to copy it to the target.
+    blit: wgpu::BindGroup,
+    /// The horizontally blurred scene at half the size, shared by all blurs.
+    horizontal: wgpu::TextureView,
+    blurs: HashMap<u32, Blur>,
+}
+
+/// The scene blurred with one radius.
+struct Blur {
+    /// Binds the scene to blur it horizontally.
+    horizontal: wgpu::BindGroup,
+    /// Binds the horizontally blurred scene to blur it vertically.
+    vertical: wgpu::BindGroup,
+    blurred: wgpu::TextureView,
+    /// Binds the blurred scene to the backdrops.
+    bind_group: wgpu::BindGroup,
+}
+
+impl BackdropRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let texture_bind_group_layout = BindGroupLayoutBuilder::fragment()
+            .texture()
+            .sampler()
+            .uniform()
+            .build("Backdrop Bind Group Layout", device);
+
+        let shader = &crate::shader_module!(device, "backdrops/backdrops.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Backdrops Pipeline Layout"),
+            bind_group_layouts: &[
+                view_projection_bind_group_layout,
+                layer_bind_group_layout,
+                &texture_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let pipeline = create_pipeline(
+            "Backdrops Pipeline",
+            device,
+            shader,
+            "fs_backdrop",
+            &[RoundedRectVertex::layout()],
+            &pipeline_layout,
+            &targets,
+        );
+
+        let blur_shader = &crate::shader_module!(device, "backdrops/blur.wgsl");
+
+        let blur_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blur Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let replace = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let blur_pipeline = create_pipeline(
+            "Blur Pipeline",
+            device,
+            blur_shader,
+            "fs_blur",
+            &[],
+            &blur_pipeline_layout,
+            &replace,
+        );
+
+        let blit_pipeline = create_pipeline(
+            "Blit Pipeline",
+            device,
+            blur_shader,
+            "fs_blit",
+            &[],
+            &blur_pipeline_layout,
+            &replace,
+        );
+
+        Self {
+            pipeline,
+            blur_pipeline,
+            blit_pipeline,
+            texture_bind_group_layout,
+            texture_sampler: texture_sampler::linear_clamping(device),
+            index_buffer: QuadIndexBuffer::new(device),
+            target_format,
+            targets: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Drop all prepared layers.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Prepare shape groups and add them to the prepared layers.
+    ///
+    /// `first_group` is the index of the first group in `shapes` among all groups prepared since
+    /// the last [`Self::clear`].
+    #[tracing::instrument(name = "prepare_backdrops", skip_all)]
+    pub fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        shapes: &[(Matrix4, &[&Shape])],
+    ) -> Result<()> {
+        let mut max_quads = 0;
+
+        for (group, (matrix, shapes)) in (first_group..).zip(shapes) {
+            // Batch by blur radius.
+            let mut batches: Vec<(u32, Vec<RoundedRectVertex>)> = Vec::new();
+            for backdrop in shapes.iter().filter_map(|s| match s {
+                Shape::Backdrop(backdrop) => Some(backdrop),
+                _ => None,
+            }) {
+                let blur = backdrop.blur.clamp(0.0, MAX_BLUR).round() as u32;
+                // The blurred content would reduce the contrast, so the backdrops are opaque in
+                // high contrast mode.
+                let tint = match &context.high_contrast {
+                    Some(high_contrast) => high_contrast.background.with_alpha(1.0),
+                    None => backdrop.tint,
+                };
+                let index = match batches.iter().position(|(b, _)| *b == blur) {
+                    Some(index) => index,
+                    None => {
+                        batches.push((blur, Vec::new()));
+                        batches.len() - 1
+                    }
+                };
+                push_rounded_rect(&mut batches[index].1, backdrop, tint);
+            }
+
+            for (blur, vertices) in batches {
+                if vertices.is_empty() {
+                    continue;
+                }
+                let vertex_buffer = context.device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Backdrops Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: BufferUsages::VERTEX,
+                });
+                let quad_count = vertices.len() >> 2;
+                max_quads = max_quads.max(quad_count);
+                self.layers.push(BackdropLayer {
+                    group,
+                    blur,
+                    model_matrix: *matrix,
+                    vertex_buffer,
+                    quad_count,
+                });
+            }
+        }
+
+        self.index_buffer
+            .ensure_can_index_num_quads(context.device, max_quads);
+
+        Ok(())
+    }
+
+    /// Update the model matrices of the prepared layers without preparing them again.
+    ///
+    /// `matrices` must be in the order of the shape groups passed to [`Self::prepare`].
+    pub fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for layer in &mut self.layers {
+            layer.model_matrix = matrices[layer.group];
+        }
+    }
+
+    /// Create the textures for the prepared backdrops and a target of `size`.
+    ///
+    /// Without backdrops, the textures are released.
+    pub fn prepare_targets(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        if self.layers.is_empty() {
+            self.targets = None;
+            return;
+        }
+
+        if self.targets.as_ref().map(|t| t.size) != Some(size) {
+            self.targets = Some(self.create_targets(device, size));
+        }
+        let Some(targets) = &mut self.targets else {
+            return;
+        };
+
+        targets
+            .blurs
+            .retain(|blur, _| self.layers.iter().any(|l| l.blur == *blur));
+        for layer in &self.layers {
+            if !targets.blurs.contains_key(&layer.blur) {
+                let blur = Self::create_blur(
+                    device,
+                    &self.texture_bind_group_layout,
+                    &self.texture_sampler,
+                    self.target_format,
+                    targets,
+                    layer.blur,
+                );
+                targets.blurs.insert(layer.blur, blur);
+            }
+        }
+    }
+
+    /// Release the textures, they are created again with the next [`Self::prepare_targets`].
+    pub fn release_targets(&mut self) {
+        self.targets = None;
+    }
+
+    /// The texture the world must be rendered to instead of the target, `None` if there are no
+    /// backdrops.
+    pub fn scene_target(&self) -> Option<&wgpu::TextureView> {
+        self.targets.as_ref().map(|targets| &targets.scene)
+    }
+
+    /// Blur the scene once for each blur radius of the backdrops.
+    pub fn blur(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        for blur in targets.blurs.values() {
+            for (target, bind_group) in [
+                (&targets.horizontal, &blur.horizontal),
+                (&blur.blurred, &blur.vertical),
+            ] {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Blur Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: target,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                pass.set_pipeline(&self.blur_pipeline);
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    /// Copy the scene into the viewport of the pass.
+    pub fn blit<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        pass.set_pipeline(&self.blit_pipeline);
+        pass.set_bind_group(0, &targets.blit, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    pub fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        if !context.debug_mode.renders_scene() {
+            return;
+        }
+
+        let pass = &mut context.pass;
+        pass.set_pipeline(&self.pipeline);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        for BackdropLayer {
+            group,
+            blur,
+            model_matrix,
+            vertex_buffer,
+            quad_count,
+        } in &self.layers
+        {
+            if !context.renders_group(*group) {
+                continue;
+            }
+            let Some(blur) = targets.blurs.get(blur) else {
+                continue;
+            };
+
+            context.queue_model_matrix(model_matrix);
+
+            let layer_bind_group = context.layer_bind_group(*group);
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_group, &[]);
+            pass.set_bind_group(2, &blur.bind_group, &[]);
+
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+            pass.draw_indexed(
+                0..(QuadIndexBuffer::INDICES_PER_QUAD * quad_count) as u32,
+                0,
+                0..1,
+            )
+        }
+    }
+
+    fn create_targets(&self, device: &wgpu::Device, size: (u32, u32)) -> Targets {
+        let scene = create_texture(device, "Backdrop Scene", size, self.target_format);
+        let blit = create_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            &self.texture_sampler,
+            &scene,
+            BlurUniforms {
+                scene_size: [size.0 as f32, size.1 as f32],
+                ..BlurUniforms::default()
+            },
+        );
+        let horizontal = create_texture(
+            device,
+            "Backdrop Horizontal Blur",
+            half_size(size),
+            self.target_format,
+        );
+        Targets {
+            size,
+            scene,
+            blit,
+            horizontal,
+            blurs: HashMap::new(),
+        }
+    }
+
+    /// Create the textures and bind groups of a blur with a radius of `radius` pixels.
+    fn create_blur(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        format: wgpu::TextureFormat,
+        targets: &Targets,
+        radius: u32,
+    ) -> Blur {
+        let (width, height) = targets.size;
+        let (_, half_height) = half_size(targets.size);
+        let scene_size = [width as f32, height as f32];
+        // The blur radius is two standard deviations, and the taps are one pixel of the half
+        // size apart.
+        let sigma = (radius as f32 / 4.0).max(0.01);
+        let taps = (radius as f32 / 2.0).ceil();
+
+        let bind_group = |view: &wgpu::TextureView, step: [f32; 2]| {
+            let uniforms = BlurUniforms {
+                step,
+                sigma,
+                taps,
+                scene_size,
+                _padding: [0.0; 2],
+            };
+            create_bind_group(device, layout, sampler, view, uniforms)
+        };
+
+        let blurred = create_texture(device, "Backdrop Blur", half_size(targets.size), format);
+        Blur {
+            horizontal: bind_group(&targets.scene, [2.0 / width as f32, 0.0]),
+            vertical: bind_group(&targets.horizontal, [0.0, 1.0 / half_height as f32]),
+            bind_group: bind_group(&blurred, [0.0; 2]),
+            blurred,
+        }
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    view: &wgpu::TextureView,
+    uniforms: BlurUniforms,
+) -> wgpu::BindGroup {
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Blur Uniform Buffer"),
+        contents: bytemuck::bytes_of(&uniforms),
+        usage: BufferUsages::UNIFORM,
+    });
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Backdrop Bind Group"),
+        layout,
+        entries: bind_group_entries!(0 => view, 1 => sampler, 2 => &buffer),
+    })
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    label: &str,
+    (width, height): (u32, u32),
+    format: wgpu::TextureFormat,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn half_size((width, height): (u32, u32)) -> (u32, u32) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+fn push_rounded_rect(vertices: &mut Vec<RoundedRectVertex>, backdrop: &Backdrop, tint: Color) {
+    let bounds = backdrop.bounds;
+    let half_size = [
+        ((bounds.max.x - bounds.min.x) / 2.0) as f32,
+        ((bounds.max.y - bounds.min.y) / 2.0) as f32,
+    ];
+    if half_size[0] <= 0.0 || half_size[1] <= 0.0 {
+        return;
+    }
+    let radius = (backdrop.radius as f32).clamp(0.0, half_size[0].min(half_size[1]));
+    let corners = [
+        (bounds.min.x, bounds.min.y, -1.0, -1.0),
+        (bounds.min.x, bounds.max.y, -1.0, 1.0),
+        (bounds.max.x, bounds.max.y, 1.0, 1.0),
+        (bounds.max.x, bounds.min.y, 1.0, -1.0),
+    ];
+    vertices.extend(corners.map(|(x, y, sx, sy)| RoundedRectVertex {
+        position: Vector3::new(x, y, 0.0).into(),
+        local: [sx * half_size[0], sy * half_size[1]],
+        half_size,
+        radius,
+        color: tint.into(),
+    }));
+}
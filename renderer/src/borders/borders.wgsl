@@ -0,0 +1,85 @@
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> model_view: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    // position in dash periods, dash ratio, periods per second
+    @location(2) dash: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) color: vec4<f32>,
+    @location(1) position: f32,
+    @location(2) @interpolate(flat) ratio: f32,
+    @location(3) @interpolate(flat) speed: f32,
+}
+
+@vertex
+fn vs_main(
+    vertex_input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
+    out.color = vertex_input.color;
+    out.position = vertex_input.dash.x;
+    out.ratio = vertex_input.dash.y;
+    out.speed = vertex_input.dash.z;
+    return out;
+}
+
+// Fragment shader
+
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> layer: Layer;
+
+// Frame uniforms, see `FrameUniforms`.
+
+struct Frame {
+    // time, unused, unused, unused
+    parameters: vec4<f32>,
+    text: vec4<f32>,
+}
+
+@group(2) @binding(0)
+var<uniform> frame: Frame;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+@fragment
+fn fs_border(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Moving clockwise means that the pattern moves towards larger positions.
+    let t = fract(in.position - frame.parameters.x * in.speed);
+    // Anti-alias both ends of the dash over one pixel.
+    let aa = max(fwidth(in.position), 1e-5);
+    var coverage = clamp(min(t, in.ratio - t) / aa + 0.5, 0.0, 1.0);
+    // Solid borders.
+    if (in.ratio >= 1.0) {
+        coverage = 1.0;
+    }
+    return layer_color(vec4<f32>(in.color.rgb, in.color.a * coverage));
+}
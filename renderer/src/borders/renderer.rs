@@ -0,0 +1,281 @@
+use anyhow::Result;
+use massive_geometry::Matrix4;
+use massive_scene::Shape;
+use massive_shapes::Border;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BufferUsages,
+};
+
+use crate::{
+    debug::DebugPipelines,
+    pods::DashVertex,
+    renderer::{PreparationContext, RenderContext},
+    tools::{create_pipeline, QuadIndexBuffer},
+};
+
+/// Renders solid and dashed borders.
+///
+/// Every border is drawn as four non-overlapping quads, one per edge. The vertices carry the
+/// position along the perimeter in dash periods, so that the fragment shader can compute the
+/// dashes and move them with the frame time.
+pub struct BorderRenderer {
+    pipeline: wgpu::RenderPipeline,
+    index_buffer: QuadIndexBuffer,
+    debug: DebugPipelines,
+
+    layers: Vec<BordersLayer>,
+}
+
+struct BordersLayer {
+    /// The index of the shape group this layer was prepared from.
+    group: usize,
+    model_matrix: Matrix4,
+    vertex_buffer: wgpu::Buffer,
+    quad_count: usize,
+    /// At least one of the borders' dashes is moving.
+    animated: bool,
+}
+
+impl BorderRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+        frame_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = &crate::shader_module!(device, "borders/borders.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Borders Pipeline Layout"),
+            bind_group_layouts: &[
+                view_projection_bind_group_layout,
+                layer_bind_group_layout,
+                frame_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let vertex_layout = [DashVertex::layout()];
+
+        let pipeline = create_pipeline(
+            "Borders Pipeline",
+            device,
+            shader,
+            "fs_border",
+            &vertex_layout,
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            pipeline,
+            index_buffer: QuadIndexBuffer::new(device),
+            debug: DebugPipelines::new(
+                device,
+                target_format,
+                view_projection_bind_group_layout,
+                DashVertex::layout().array_stride,
+            ),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Drop all prepared layers.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Returns `true` if a prepared border has marching dashes.
+    pub fn is_animating(&self) -> bool {
+        self.layers.iter().any(|l| l.animated)
+    }
+
+    /// Prepare shape groups and add them to the prepared layers.
+    ///
+    /// `first_group` is the index of the first group in `shapes` among all groups prepared since
+    /// the last [`Self::clear`].
+    #[tracing::instrument(name = "prepare_borders", skip_all)]
+    pub fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        shapes: &[(Matrix4, &[&Shape])],
+    ) -> Result<()> {
+        let mut max_quads = 0;
+
+        for (group, (matrix, shapes)) in (first_group..).zip(shapes) {
+            if let Some(layer) = self.prepare_borders(
+                context,
+                group,
+                matrix,
+                shapes.iter().filter_map(|s| match s {
+                    Shape::Border(border) => Some(border),
+                    _ => None,
+                }),
+            ) {
+                max_quads = max_quads.max(layer.quad_count);
+                self.layers.push(layer)
+            }
+        }
+
+        self.index_buffer
+            .ensure_can_index_num_quads(context.device, max_quads);
+        self.debug.prepare(context.device, max_quads);
+
+        Ok(())
+    }
+
+    /// Update the model matrices of the prepared layers without preparing them again.
+    ///
+    /// `matrices` must be in the order of the shape groups passed to [`Self::prepare`].
+    pub fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for layer in &mut self.layers {
+            layer.model_matrix = matrices[layer.group];
+        }
+    }
+
+    pub fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        if context.debug_mode.renders_scene() {
+            self.render_layers(context);
+        }
+
+        let batches: Vec<_> = self
+            .layers
+            .iter()
+            .filter(|l| context.renders_group(l.group))
+            .map(|l| (l.model_matrix, l.vertex_buffer.slice(..), l.quad_count))
+            .collect();
+        self.debug.render(context, &self.index_buffer, &batches);
+    }
+
+    fn render_layers<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        let pass = &mut context.pass;
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+        pass.set_bind_group(2, context.frame_bind_group, &[]);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        for BordersLayer {
+            group,
+            model_matrix,
+            vertex_buffer,
+            quad_count,
+            ..
+        } in &self.layers
+        {
+            if !context.renders_group(*group) {
+                continue;
+            }
+            context.queue_model_matrix(model_matrix);
+
+            let layer_bind_group = context.layer_bind_group(*group);
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_group, &[]);
+
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+            pass.draw_indexed(
+                0..(QuadIndexBuffer::INDICES_PER_QUAD * quad_count) as u32,
+                0,
+                0..1,
+            )
+        }
+    }
+
+    fn prepare_borders<'a>(
+        &mut self,
+        context: &mut PreparationContext,
+        group: usize,
+        model_matrix: &Matrix4,
+        borders: impl Iterator<Item = &'a Border>,
+    ) -> Option<BordersLayer> {
+        let mut vertices = Vec::new();
+        let mut animated = false;
+
+        for border in borders {
+            let color = match &context.high_contrast {
+                Some(high_contrast) => high_contrast.fill(border.color),
+                None => border.color,
+            };
+            animated |= border_vertices(border, color, &mut vertices);
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+
+        let vertex_buffer = context.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Borders Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+
+        Some(BordersLayer {
+            group,
+            model_matrix: *model_matrix,
+            vertex_buffer,
+            quad_count: vertices.len() >> 2,
+            animated,
+        })
+    }
+}
+
+/// Pushes the four edge quads of a border and returns `true` if its dashes are moving.
+fn border_vertices(
+    border: &Border,
+    color: massive_geometry::Color,
+    vertices: &mut Vec<DashVertex>,
+) -> bool {
+    let (l, t) = (border.bounds.min.x, border.bounds.min.y);
+    let (r, b) = (border.bounds.max.x, border.bounds.max.y);
+    let (w, h) = (r - l, b - t);
+    let bw = border.width.min(w / 2.0).min(h / 2.0);
+    if bw <= 0.0 {
+        return false;
+    }
+
+    // The distances along the perimeter are measured clockwise from the top left corner.
+    let perimeter = 2.0 * (w + h);
+
+    // The period is adjusted so that a whole number of dashes fits the perimeter.
+    let (period, ratio, speed) = match border.dash {
+        Some(dash) if dash.length > 0.0 && dash.gap > 0.0 => {
+            let count = (perimeter / (dash.length + dash.gap)).round().max(1.0);
+            let period = perimeter / count;
+            let ratio = dash.length / (dash.length + dash.gap);
+            (period, ratio, dash.speed / period)
+        }
+        _ => (perimeter, 1.0, 0.0),
+    };
+
+    let mut edge = |x0: f64, y0: f64, x1: f64, y1: f64, d: [f64; 4]| {
+        let corners = [(x0, y0), (x0, y1), (x1, y1), (x1, y0)];
+        vertices.extend(corners.iter().zip(d).map(|(&(x, y), d)| {
+            DashVertex::new(
+                (x as f32, y as f32, 0.0),
+                color,
+                [(d / period) as f32, ratio as f32, speed as f32],
+            )
+        }));
+    };
+
+    // Top, right, bottom, left. The distances are in the order of the corners: top left, bottom
+    // left, bottom right, top right.
+    edge(l, t, r, t + bw, [0.0, 0.0, w, w]);
+    edge(r - bw, t + bw, r, b, [w + bw, w + h, w + h, w + bw]);
+    let (bl, br) = (w + h + w, w + h + bw);
+    edge(l, b - bw, r - bw, b, [bl, bl, br, br]);
+    let (lt, lb) = (perimeter - bw, perimeter - h + bw);
+    edge(l, t + bw, l + bw, b - bw, [lt, lb, lb, lt]);
+
+    speed != 0.0
+}
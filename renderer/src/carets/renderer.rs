@@ -0,0 +1,306 @@
+use std::{collections::BTreeMap, mem};
+
+use massive_geometry::{Bounds, Color, Matrix4, Point};
+use massive_scene::{Id, PositionedRenderShape, Shape};
+use massive_shapes::Caret;
+
+use crate::{
+    pods::ColorVertex,
+    renderer::RenderContext,
+    tools::{create_pipeline, QuadIndexBuffer},
+    ColorRole, HighContrast, LayerBindGroups,
+};
+
+/// Renders and animates the carets.
+///
+/// Carets are not part of the prepared scene. They are tracked by their shape ids, so that an
+/// update can move a caret from where it is, and their vertices are written every frame.
+pub struct CaretRenderer {
+    pipeline: wgpu::RenderPipeline,
+    index_buffer: QuadIndexBuffer,
+    carets: BTreeMap<Id, AnimatedCaret>,
+    vertex_buffer: Option<wgpu::Buffer>,
+    /// The draw calls prepared for the next frame.
+    draws: Vec<CaretDraw>,
+}
+
+struct AnimatedCaret {
+    shape: PositionedRenderShape,
+    caret: Caret,
+    /// The bounds the caret moves from.
+    from: Bounds,
+    /// The time the movement started.
+    start: f32,
+}
+
+struct CaretDraw {
+    position: Id,
+    model_matrix: Matrix4,
+    overlay: bool,
+    first_quad: usize,
+    quad_count: usize,
+}
+
+/// The opacity of the trail relative to the caret when the movement starts.
+const TRAIL_OPACITY: f32 = 0.35;
+
+impl CaretRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        // Carets are quads and share their shader.
+        let shader = &crate::shader_module!(device, "quads/quads.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Caret Pipeline Layout"),
+            bind_group_layouts: &[view_projection_bind_group_layout, layer_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let pipeline = create_pipeline(
+            "Caret Pipeline",
+            device,
+            shader,
+            "fs_quad",
+            &[ColorVertex::layout()],
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            pipeline,
+            index_buffer: QuadIndexBuffer::new(device),
+            carets: BTreeMap::new(),
+            vertex_buffer: None,
+            draws: Vec::new(),
+        }
+    }
+
+    /// Recreate the pipeline for a new target format and keep the carets.
+    pub fn target_format_changed(
+        &mut self,
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) {
+        let carets = mem::take(&mut self.carets);
+        *self = Self::new(
+            device,
+            target_format,
+            view_projection_bind_group_layout,
+            layer_bind_group_layout,
+        );
+        self.carets = carets;
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.carets.contains_key(&id)
+    }
+
+    /// Create or update a caret at `time`.
+    ///
+    /// An updated caret moves from its current bounds to the new ones. New carets and carets that
+    /// changed their position appear at their bounds right away.
+    pub fn set(&mut self, id: Id, position: Id, caret: Caret, time: f32) {
+        let from = match self.carets.get(&id) {
+            Some(current) if current.shape.position == position => current.bounds(time),
+            _ => caret.bounds,
+        };
+        self.carets.insert(
+            id,
+            AnimatedCaret {
+                shape: PositionedRenderShape {
+                    position,
+                    shape: Shape::Caret(caret),
+                },
+                caret,
+                from,
+                start: time,
+            },
+        );
+    }
+
+    /// Remove a caret, returns `false` if there is no caret with this id.
+    pub fn remove(&mut self, id: Id) -> bool {
+        self.carets.remove(&id).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.carets.clear();
+        self.draws.clear();
+    }
+
+    /// All carets, ordered by their ids.
+    pub fn shapes(&self) -> impl Iterator<Item = (Id, &PositionedRenderShape)> {
+        self.carets.iter().map(|(id, caret)| (*id, &caret.shape))
+    }
+
+    /// Whether a caret is still moving or its trail is still visible at `time`.
+    pub fn is_animating(&self, time: f32) -> bool {
+        self.carets
+            .values()
+            .any(|caret| time < caret.start + caret.caret.motion)
+    }
+
+    /// Write the vertices of all carets at `time`.
+    ///
+    /// `model_matrix` returns the model matrix of a position and whether it's placed in the
+    /// overlay. With `high_contrast`, carets take the text color of the palette.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        time: f32,
+        high_contrast: Option<&HighContrast>,
+        model_matrix: impl Fn(Id) -> (Matrix4, bool),
+    ) {
+        self.draws.clear();
+        let mut vertices = Vec::new();
+        for caret in self.carets.values() {
+            let first_quad = vertices.len() / 4;
+            let progress = caret.progress(time);
+            let bounds = caret.bounds(time);
+            let color = match high_contrast {
+                Some(high_contrast) => high_contrast.color(ColorRole::Text, caret.caret.color),
+                None => caret.caret.color,
+            };
+
+            if caret.caret.trail && progress < 1.0 {
+                // The tail follows linearly and so lags behind the eased caret.
+                let tail = lerp_bounds(&caret.from, &caret.caret.bounds, progress);
+                let trail = Bounds::new(
+                    (bounds.min.x.min(tail.min.x), bounds.min.y.min(tail.min.y)),
+                    (bounds.max.x.max(tail.max.x), bounds.max.y.max(tail.max.y)),
+                );
+                let opacity = TRAIL_OPACITY * (1.0 - progress);
+                let trail_color = Color {
+                    alpha: color.alpha * opacity,
+                    ..color
+                };
+                vertices.extend(quad_vertices(&trail, trail_color));
+            }
+            vertices.extend(quad_vertices(&bounds, color));
+
+            let (matrix, overlay) = model_matrix(caret.shape.position);
+            self.draws.push(CaretDraw {
+                position: caret.shape.position,
+                model_matrix: matrix,
+                overlay,
+                first_quad,
+                quad_count: vertices.len() / 4 - first_quad,
+            });
+        }
+
+        if vertices.is_empty() {
+            return;
+        }
+
+        let contents: &[u8] = bytemuck::cast_slice(&vertices);
+        let size = contents.len() as wgpu::BufferAddress;
+        if self.vertex_buffer.as_ref().map_or(0, |b| b.size()) < size {
+            // Grow in powers of two, carets come and go with focus changes.
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Caret Vertex Buffer"),
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        queue.write_buffer(self.vertex_buffer.as_ref().unwrap(), 0, contents);
+
+        let max_quads = self.draws.iter().map(|d| d.quad_count).max();
+        self.index_buffer
+            .ensure_can_index_num_quads(device, max_quads.unwrap_or_default());
+    }
+
+    /// Whether one of the prepared carets is placed in the overlay.
+    pub fn has_overlay(&self) -> bool {
+        self.draws.iter().any(|draw| draw.overlay)
+    }
+
+    pub fn render<'rpass>(
+        &'rpass self,
+        context: &mut RenderContext<'_, 'rpass>,
+        layer_bind_groups: &'rpass LayerBindGroups,
+    ) {
+        let Some(vertex_buffer) = &self.vertex_buffer else {
+            return;
+        };
+        if self.draws.is_empty() || !context.debug_mode.renders_scene() {
+            return;
+        }
+
+        let pass = &mut context.pass;
+        pass.set_pipeline(&self.pipeline);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+        for CaretDraw {
+            position,
+            model_matrix,
+            overlay,
+            first_quad,
+            quad_count,
+        } in &self.draws
+        {
+            if *overlay != context.is_overlay_pass() || !context.renders_position(*position) {
+                continue;
+            }
+            context.queue_model_matrix(model_matrix);
+
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_groups.bind_group(*position), &[]);
+
+            pass.draw_indexed(
+                0..(QuadIndexBuffer::INDICES_PER_QUAD * quad_count) as u32,
+                (first_quad * 4) as i32,
+                0..1,
+            )
+        }
+    }
+}
+
+impl AnimatedCaret {
+    /// The linear progress of the movement at `time`, from 0 to 1.
+    fn progress(&self, time: f32) -> f32 {
+        if self.caret.motion <= 0.0 {
+            return 1.0;
+        }
+        ((time - self.start) / self.caret.motion).clamp(0.0, 1.0)
+    }
+
+    /// The bounds of the caret at `time`.
+    fn bounds(&self, time: f32) -> Bounds {
+        // Ease out cubic.
+        let eased = 1.0 - (1.0 - self.progress(time)).powi(3);
+        lerp_bounds(&self.from, &self.caret.bounds, eased)
+    }
+}
+
+fn lerp_bounds(from: &Bounds, to: &Bounds, t: f32) -> Bounds {
+    let lerp = |from: Point, to: Point| from + (to - from) * t as f64;
+    Bounds::new(lerp(from.min, to.min), lerp(from.max, to.max))
+}
+
+/// The vertices of a quad covering `bounds`, in the order left top, left bottom, right bottom,
+/// right top.
+fn quad_vertices(bounds: &Bounds, color: Color) -> [ColorVertex; 4] {
+    let (min, max) = (bounds.min, bounds.max);
+    [
+        ColorVertex::new(min.with_z(0.0), color),
+        ColorVertex::new(Point::new(min.x, max.y).with_z(0.0), color),
+        ColorVertex::new(max.with_z(0.0), color),
+        ColorVertex::new(Point::new(max.x, min.y).with_z(0.0), color),
+    ]
+}
@@ -0,0 +1,504 @@
+//! Conformance of the GPU text rendering with a reference rasterizer.
+//!
+//! The glyphs of a corpus are rendered through the renderer into an offscreen texture and compared
+//! with the coverage swash rasterizes for the same glyphs on the CPU. The comparison catches
+//! regressions in the distance field generation and in the sampling math of the glyph shaders.
+//!
+//! Each glyph is rendered into its own cell. With a scale other than 1, the glyph is magnified by
+//! its model matrix and compared with a reference that is rasterized at the magnified size, so
+//! that the distance field interpolation is covered, too.
+
+use std::{cell::RefCell, fmt, rc::Rc, sync::mpsc};
+
+use anyhow::{bail, Result};
+use cosmic_text as text;
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape, SceneChange};
+use massive_shapes::{GlyphRun, GlyphRunMetrics, RunGlyph, TextWeight};
+use swash::{scale::ScaleContext, Weight};
+use text::SwashContent;
+
+use crate::{
+    glyph::{glyph_rasterization::rasterize_glyph, SwashRasterizationParam},
+    Quality, Renderer, TextRendering, View,
+};
+
+/// The number of glyph cells in a row of the rendered texture.
+const COLUMNS: usize = 16;
+
+/// A text rendering conformance suite.
+///
+/// Every glyph of `text` is compared at each font size and scale. Hinted glyphs are compared at
+/// scale 1 only, because hinting does not survive magnification.
+#[derive(Debug, Clone)]
+pub struct TextConformance {
+    pub text: String,
+    pub font_sizes: Vec<f32>,
+    pub scales: Vec<f64>,
+    pub tolerance: Tolerance,
+}
+
+impl Default for TextConformance {
+    fn default() -> Self {
+        Self {
+            text: "aegkmosyABGHQRW0123&@%?/".into(),
+            font_sizes: vec![10.0, 14.0, 20.0, 32.0, 48.0],
+            scales: vec![1.0, 2.0, 3.5],
+            tolerance: Tolerance::default(),
+        }
+    }
+}
+
+/// The deviations from the reference a glyph may have, in coverage from 0 to 1.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    /// The mean absolute error over the pixels that are covered by either rendering.
+    pub mean_error: f32,
+    /// The largest absolute error of a single pixel.
+    pub max_error: f32,
+    /// How much the total coverage may deviate relatively, so that glyphs don't get lighter or
+    /// heavier.
+    pub ink: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            mean_error: 0.08,
+            max_error: 0.75,
+            ink: 0.15,
+        }
+    }
+}
+
+/// The result of comparing one glyph with its reference.
+#[derive(Debug, Clone)]
+pub struct GlyphComparison {
+    /// The text the glyph was shaped from.
+    pub text: String,
+    pub glyph_id: u16,
+    pub font_size: f32,
+    pub scale: f64,
+    pub hinted: bool,
+    pub mean_error: f32,
+    pub max_error: f32,
+    /// The total coverage of the rendered glyph relative to the reference.
+    pub ink_ratio: f32,
+    pub passed: bool,
+}
+
+impl fmt::Display for GlyphComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} (glyph {}) at {}px x{}{}: mean error {:.3}, max error {:.3}, ink {:.3}",
+            self.text,
+            self.glyph_id,
+            self.font_size,
+            self.scale,
+            if self.hinted { " hinted" } else { "" },
+            self.mean_error,
+            self.max_error,
+            self.ink_ratio
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    pub comparisons: Vec<GlyphComparison>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.comparisons.iter().all(|c| c.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &GlyphComparison> {
+        self.comparisons.iter().filter(|c| !c.passed)
+    }
+}
+
+/// A glyph of the corpus.
+#[derive(Debug)]
+struct CorpusGlyph {
+    text: String,
+    font_id: text::fontdb::ID,
+    glyph_id: u16,
+}
+
+impl TextConformance {
+    /// Render the corpus with `renderer` and compare it with the reference.
+    ///
+    /// The renderer should be detached and used for nothing else, this changes its text rendering,
+    /// quality, and surface size. Its surface format must be one of the 8 bit RGBA or BGRA
+    /// formats.
+    pub fn run(
+        &self,
+        renderer: &mut Renderer,
+        font_system: &mut text::FontSystem,
+    ) -> Result<ConformanceReport> {
+        let format = renderer.surface_config.format;
+        let srgb = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Bgra8Unorm => false,
+            wgpu::TextureFormat::Rgba8UnormSrgb | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            _ => bail!("Unsupported format for text conformance: {format:?}"),
+        };
+
+        renderer.set_quality(Quality::FULL);
+        renderer.set_high_contrast(None);
+        renderer.set_min_contrast(None);
+
+        let mut report = ConformanceReport::default();
+        for &font_size in &self.font_sizes {
+            let glyphs = shape_corpus(font_system, &self.text, font_size);
+            for &scale in &self.scales {
+                let hinting = if scale == 1.0 {
+                    &[false, true][..]
+                } else {
+                    &[false][..]
+                };
+                for &hinted in hinting {
+                    let case = Case {
+                        font_size,
+                        scale,
+                        hinted,
+                    };
+                    let coverage = case.render(renderer, font_system, &glyphs, srgb)?;
+                    report.comparisons.extend(case.compare(
+                        font_system,
+                        &glyphs,
+                        &coverage,
+                        &self.tolerance,
+                    ));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// One font size, scale, and hinting of the corpus.
+#[derive(Debug, Copy, Clone)]
+struct Case {
+    font_size: f32,
+    scale: f64,
+    hinted: bool,
+}
+
+impl Case {
+    /// The size of a square glyph cell in pixels.
+    fn cell_size(&self) -> usize {
+        (self.font_size as f64 * self.scale * 2.0).ceil() as usize + 2
+    }
+
+    /// The origin of a glyph inside its cell, the left end of its baseline.
+    fn origin(&self) -> (usize, usize) {
+        let cell = self.cell_size();
+        (cell / 4, cell * 2 / 3)
+    }
+
+    fn texture_size(&self, glyphs: usize) -> (usize, usize) {
+        let cell = self.cell_size();
+        let rows = glyphs.div_ceil(COLUMNS).max(1);
+        (COLUMNS * cell, rows * cell)
+    }
+
+    /// The pixel the origin of the glyph at `index` is placed at.
+    fn glyph_origin(&self, index: usize) -> (usize, usize) {
+        let cell = self.cell_size();
+        let (x, y) = self.origin();
+        ((index % COLUMNS) * cell + x, (index / COLUMNS) * cell + y)
+    }
+
+    fn key(&self, glyph: &CorpusGlyph, font_size: f32) -> text::CacheKey {
+        text::CacheKey::new(
+            glyph.font_id,
+            glyph.glyph_id,
+            font_size,
+            (0.0, 0.0),
+            text::CacheKeyFlags::empty(),
+        )
+        .0
+    }
+
+    /// Render all glyphs and return the coverage of the texture's pixels.
+    fn render(
+        &self,
+        renderer: &mut Renderer,
+        font_system: &mut text::FontSystem,
+        glyphs: &[CorpusGlyph],
+        srgb: bool,
+    ) -> Result<Vec<f32>> {
+        renderer.set_text_rendering(TextRendering {
+            hinting: self.hinted,
+            pixel_snapping: false,
+            sharpness: 1.0,
+            dilation: 0.0,
+            gamma: 1.0,
+        });
+
+        let (width, height) = self.texture_size(glyphs.len());
+        renderer.resize_surface((width as u32, height as u32));
+
+        let changes = Rc::new(RefCell::new(Vec::<SceneChange>::new()));
+        let mut director = Director::new({
+            let changes = changes.clone();
+            move |c| {
+                changes.borrow_mut().extend(c);
+                Ok(())
+            }
+        });
+
+        let ascent = self.font_size.ceil() as u32;
+        let shapes: Vec<Handle<PositionedShape>> = glyphs
+            .iter()
+            .enumerate()
+            .map(|(index, glyph)| {
+                let (x, y) = self.glyph_origin(index);
+                let matrix = director.cast(
+                    Matrix4::from_translation(Vector3::new(x as f64, y as f64, 0.0))
+                        * Matrix4::from_scale(self.scale),
+                );
+                let position = director.cast(Position {
+                    parent: None,
+                    matrix,
+                    pin: None,
+                    overlay: true,
+                });
+                // The baseline of the run is at y = 0 of the model.
+                let run = GlyphRun::new(
+                    (0.0, -(ascent as f64), 0.0),
+                    GlyphRunMetrics {
+                        max_ascent: ascent,
+                        max_descent: ascent / 2,
+                        width: ascent * 2,
+                    },
+                    Color::BLACK,
+                    TextWeight::NORMAL,
+                    vec![RunGlyph::new(
+                        self.key(glyph, self.font_size),
+                        (0, 0),
+                        self.font_size,
+                    )],
+                );
+                director.cast(PositionedShape::new(position, run))
+            })
+            .collect();
+        director.action()?;
+        renderer.apply_changes(font_system, changes.take())?;
+
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Text Conformance Texture"),
+            size: wgpu::Extent3d {
+                width: width as u32,
+                height: height as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: renderer.surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        renderer.render_views_to(&view, &[View::new(renderer.pixel_matrix())])?;
+        let coverage = read_coverage(&renderer.device, &renderer.queue, &texture, srgb)?;
+
+        // Remove the glyphs from the scene again.
+        drop(shapes);
+        director.action()?;
+        renderer.apply_changes(font_system, changes.take())?;
+
+        Ok(coverage)
+    }
+
+    /// Compare the glyphs of the rendered `coverage` with the coverage of the reference.
+    fn compare(
+        &self,
+        font_system: &mut text::FontSystem,
+        glyphs: &[CorpusGlyph],
+        coverage: &[f32],
+        tolerance: &Tolerance,
+    ) -> Vec<GlyphComparison> {
+        let (width, _) = self.texture_size(glyphs.len());
+        let cell = self.cell_size();
+        let (origin_x, origin_y) = self.origin();
+        let mut context = ScaleContext::new();
+        let param = SwashRasterizationParam {
+            hinted: self.hinted,
+            weight: Weight(TextWeight::NORMAL.0),
+        };
+
+        glyphs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, glyph)| {
+                let key = self.key(glyph, self.font_size * self.scale as f32);
+                let image = rasterize_glyph(font_system, &mut context, key, param)?;
+                // Color glyphs are not rendered with distance fields.
+                if image.content != SwashContent::Mask {
+                    return None;
+                }
+
+                // The reference coverage of the cell.
+                let mut reference = vec![0.0f32; cell * cell];
+                let placement = image.placement;
+                for y in 0..placement.height as usize {
+                    for x in 0..placement.width as usize {
+                        let cx = origin_x as i32 + placement.left + x as i32;
+                        let cy = origin_y as i32 - placement.top + y as i32;
+                        if (0..cell as i32).contains(&cx) && (0..cell as i32).contains(&cy) {
+                            reference[cy as usize * cell + cx as usize] =
+                                image.data[y * placement.width as usize + x] as f32 / 255.0;
+                        }
+                    }
+                }
+
+                let (cell_x, cell_y) = ((index % COLUMNS) * cell, (index / COLUMNS) * cell);
+                let rendered = (0..cell * cell)
+                    .map(|i| coverage[(cell_y + i / cell) * width + cell_x + i % cell]);
+
+                let mut error_sum = 0.0;
+                let mut max_error: f32 = 0.0;
+                let mut inked = 0;
+                let (mut rendered_ink, mut reference_ink) = (0.0, 0.0);
+                for (rendered, reference) in rendered.zip(&reference) {
+                    if rendered <= 0.0 && *reference <= 0.0 {
+                        continue;
+                    }
+                    let error = (rendered - reference).abs();
+                    error_sum += error;
+                    max_error = max_error.max(error);
+                    inked += 1;
+                    rendered_ink += rendered;
+                    reference_ink += reference;
+                }
+
+                let mean_error = if inked > 0 {
+                    error_sum / inked as f32
+                } else {
+                    0.0
+                };
+                let ink_ratio = if reference_ink > 0.0 {
+                    rendered_ink / reference_ink
+                } else {
+                    1.0
+                };
+
+                Some(GlyphComparison {
+                    text: glyph.text.clone(),
+                    glyph_id: glyph.glyph_id,
+                    font_size: self.font_size,
+                    scale: self.scale,
+                    hinted: self.hinted,
+                    mean_error,
+                    max_error,
+                    ink_ratio,
+                    passed: mean_error <= tolerance.mean_error
+                        && max_error <= tolerance.max_error
+                        && (ink_ratio - 1.0).abs() <= tolerance.ink,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Shape the corpus and return its visible glyphs.
+fn shape_corpus(
+    font_system: &mut text::FontSystem,
+    corpus: &str,
+    font_size: f32,
+) -> Vec<CorpusGlyph> {
+    let mut line = text::BufferLine::new(
+        corpus,
+        text::AttrsList::new(text::Attrs::new()),
+        text::Shaping::Advanced,
+    );
+    let Some(layout) = line
+        .layout(font_system, font_size, f32::MAX, text::Wrap::None, None)
+        .first()
+    else {
+        return Vec::new();
+    };
+
+    layout
+        .glyphs
+        .iter()
+        .filter(|glyph| !corpus[glyph.start..glyph.end].trim().is_empty())
+        .map(|glyph| CorpusGlyph {
+            text: corpus[glyph.start..glyph.end].to_string(),
+            font_id: glyph.font_id,
+            glyph_id: glyph.glyph_id,
+        })
+        .collect()
+}
+
+/// Read back the texture the black glyphs were rendered to on white and convert its pixels to
+/// coverage.
+fn read_coverage(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    srgb: bool,
+) -> Result<Vec<f32>> {
+    let (width, height) = (texture.width() as usize, texture.height() as usize);
+    let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Text Conformance Readback Buffer"),
+        size: (bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Text Conformance Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row as u32),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    let _ = device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let data = slice.get_mapped_range();
+    // Black on white, so all color channels are the same.
+    let coverage = (0..height)
+        .flat_map(|y| (0..width).map(move |x| y * bytes_per_row + x * 4))
+        .map(|offset| {
+            let value = data[offset] as f32 / 255.0;
+            let linear = if srgb { srgb_to_linear(value) } else { value };
+            1.0 - linear
+        })
+        .collect();
+    drop(data);
+    buffer.unmap();
+
+    Ok(coverage)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,148 @@
+//! A high contrast mode for visually impaired users.
+//!
+//! While a [`HighContrast`] palette is set, the colors of the scene are replaced by the colors of
+//! the palette when shapes are prepared. The role of a color is derived from the shape it belongs
+//! to, see [`ColorRole`].
+//!
+//! Less intrusively, a minimum contrast ratio moves the colors of text runs just far enough away
+//! from their backgrounds, see [`with_min_contrast`].
+
+use massive_geometry::Color;
+use serde::{Deserialize, Serialize};
+
+/// The colors that replace the colors of the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HighContrast {
+    /// Text, carets, and everything else in the foreground.
+    pub text: Color,
+    /// The clear color and the unsaturated quads behind the text.
+    pub background: Color,
+    /// Saturated quads like selections and highlights, and focus rings.
+    pub accent: Color,
+}
+
+/// What a color is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRole {
+    Text,
+    Background,
+    Accent,
+}
+
+/// Quads above this saturation are treated as accents.
+const ACCENT_SATURATION: f32 = 0.25;
+
+impl HighContrast {
+    /// White text on black, the default of most operating systems.
+    pub const DARK: Self = Self {
+        text: Color::WHITE,
+        background: Color::BLACK,
+        accent: Color::rgb(0.0, 1.0, 1.0),
+    };
+
+    /// Black text on white.
+    pub const LIGHT: Self = Self {
+        text: Color::BLACK,
+        background: Color::WHITE,
+        accent: Color::rgb(0.0, 0.2, 0.8),
+    };
+
+    /// The palette's color for `role`. The alpha of `color` is kept, so translucent shapes stay
+    /// translucent.
+    pub fn color(&self, role: ColorRole, color: Color) -> Color {
+        let replacement = match role {
+            ColorRole::Text => self.text,
+            ColorRole::Background => self.background,
+            ColorRole::Accent => self.accent,
+        };
+        replacement.with_alpha(replacement.alpha * color.alpha)
+    }
+
+    /// Replace the color of a filled area, for example of a quad.
+    ///
+    /// There are no roles attached to the quads of a scene, so saturated colors are considered
+    /// accents and all others backgrounds.
+    pub fn fill(&self, color: Color) -> Color {
+        self.color(ColorRole::of_fill(color), color)
+    }
+}
+
+impl Default for HighContrast {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+impl ColorRole {
+    /// The role of the color of a filled area.
+    pub fn of_fill(color: Color) -> Self {
+        let max = color.red.max(color.green).max(color.blue);
+        let min = color.red.min(color.green).min(color.blue);
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        if saturation > ACCENT_SATURATION {
+            ColorRole::Accent
+        } else {
+            ColorRole::Background
+        }
+    }
+}
+
+/// The contrast ratio between two colors as defined by WCAG 2, from 1 to 21. Alpha is ignored.
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Move `text` towards black or white until its contrast ratio to `background` reaches `ratio`.
+///
+/// Returns `text` unchanged if it has enough contrast already. If the ratio can't be reached, the
+/// extreme with the higher contrast is returned. The alpha of `text` is kept.
+pub fn with_min_contrast(text: Color, background: Color, ratio: f32) -> Color {
+    if contrast_ratio(text, background) >= ratio {
+        return text;
+    }
+    let (black, white) = (Color::BLACK, Color::WHITE);
+    let target = if contrast_ratio(black, background) >= contrast_ratio(white, background) {
+        black
+    } else {
+        white
+    };
+    let mix = |t: f32| (text * (1.0 - t) + target * t).with_alpha(text.alpha);
+    if contrast_ratio(target, background) < ratio {
+        return mix(1.0);
+    }
+
+    // The contrast grows monotonically towards the target, so the smallest change is found by
+    // bisection.
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..CONTRAST_ITERATIONS {
+        let t = (low + high) / 2.0;
+        if contrast_ratio(mix(t), background) >= ratio {
+            high = t;
+        } else {
+            low = t;
+        }
+    }
+    mix(high)
+}
+
+/// Compose `over` over an opaque `under`.
+pub(crate) fn compose(over: Color, under: Color) -> Color {
+    let alpha = over.alpha;
+    (over * alpha + under * (1.0 - alpha)).with_alpha(under.alpha)
+}
+
+/// The number of bisection steps [`with_min_contrast`] takes.
+const CONTRAST_ITERATIONS: usize = 12;
+
+/// The relative luminance of a color in sRGB as defined by WCAG 2.
+fn relative_luminance(color: Color) -> f32 {
+    let linear = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.red) + 0.7152 * linear(color.green) + 0.0722 * linear(color.blue)
+}
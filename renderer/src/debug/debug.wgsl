@@ -0,0 +1,46 @@
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> view_model: mat4x4<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) color: vec3<f32>,
+}
+
+// The batch index is passed as the instance index.
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @builtin(instance_index) batch: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = view_model * vec4<f32>(position, 1.0);
+    out.color = batch_color(batch);
+    return out;
+}
+
+// Distinct hues for neighboring batches, spaced by the golden ratio.
+fn batch_color(batch: u32) -> vec3<f32> {
+    let hue = fract(f32(batch) * 0.61803398875);
+    let rgb = clamp(abs(fract(hue + vec3(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0, vec3(0.0), vec3(1.0));
+    return mix(vec3(1.0), rgb, 0.8) * 0.9;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
+
+// Blended additively, so the brightness counts how often a pixel is drawn.
+@fragment
+fn fs_overdraw(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.12, 0.05, 0.02, 1.0);
+}
+
+@fragment
+fn fs_batches(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 0.35);
+}
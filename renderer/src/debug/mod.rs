@@ -0,0 +1,192 @@
+//! Debug render modes that visualize the quads the renderers draw.
+
+use std::mem;
+
+use massive_geometry::Matrix4;
+use serde::{Deserialize, Serialize};
+
+use crate::{renderer::RenderContext, tools::QuadIndexBuffer};
+
+/// A visualization of how the scene is rendered, to find out why a scene is slow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum DebugMode {
+    #[default]
+    Off,
+    /// The outlines of all quads, drawn over the scene.
+    Wireframe,
+    /// A heat map of how often each pixel is drawn, the scene is not rendered.
+    Overdraw,
+    /// Every batch filled with its own color, drawn over the scene. Each batch is one draw call.
+    Batches,
+}
+
+impl DebugMode {
+    /// Whether the scene is rendered below the visualization.
+    pub fn renders_scene(self) -> bool {
+        self != DebugMode::Overdraw
+    }
+
+    /// The next mode, for cycling through all modes with a key.
+    pub fn next(self) -> Self {
+        match self {
+            DebugMode::Off => DebugMode::Wireframe,
+            DebugMode::Wireframe => DebugMode::Overdraw,
+            DebugMode::Overdraw => DebugMode::Batches,
+            DebugMode::Batches => DebugMode::Off,
+        }
+    }
+}
+
+/// The debug pipelines for the vertex buffers of one renderer.
+///
+/// Only the position at location 0 of the vertices is used.
+pub struct DebugPipelines {
+    wireframe: wgpu::RenderPipeline,
+    overdraw: wgpu::RenderPipeline,
+    batches: wgpu::RenderPipeline,
+    outline_index_buffer: QuadIndexBuffer,
+}
+
+impl DebugPipelines {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        vertex_stride: wgpu::BufferAddress,
+    ) -> Self {
+        let shader = &crate::shader_module!(device, "debug/debug.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Pipeline Layout"),
+            bind_group_layouts: &[view_projection_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let attributes = wgpu::vertex_attr_array![0 => Float32x3];
+        let vertex_layout = [wgpu::VertexBufferLayout {
+            array_stride: vertex_stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &attributes,
+        }];
+
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        let pipeline = |label, fs_entry, topology, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    compilation_options: Default::default(),
+                    buffers: &vertex_layout,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: fs_entry,
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: target_format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    // Back faces are drawn, too, so that flipped quads show up.
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+            })
+        };
+
+        Self {
+            wireframe: pipeline(
+                "Debug Wireframe Pipeline",
+                "fs_wireframe",
+                wgpu::PrimitiveTopology::LineList,
+                wgpu::BlendState::REPLACE,
+            ),
+            overdraw: pipeline(
+                "Debug Overdraw Pipeline",
+                "fs_overdraw",
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::BlendState {
+                    color: additive,
+                    alpha: additive,
+                },
+            ),
+            batches: pipeline(
+                "Debug Batches Pipeline",
+                "fs_batches",
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::BlendState::ALPHA_BLENDING,
+            ),
+            outline_index_buffer: QuadIndexBuffer::outlines(device),
+        }
+    }
+
+    /// Make sure that the outlines of batches with `quad_count` quads can be drawn.
+    ///
+    /// Must be called for every batch that is prepared, because the render pass can't grow the
+    /// index buffer.
+    pub fn prepare(&mut self, device: &wgpu::Device, quad_count: usize) {
+        self.outline_index_buffer
+            .ensure_can_index_num_quads(device, quad_count);
+    }
+
+    /// Render the batches in the current debug mode.
+    ///
+    /// `batches` are the model matrices, vertex buffers, and quad counts of the batches.
+    /// `quad_index_buffer` is the index buffer the batches are regularly rendered with.
+    pub fn render<'rpass>(
+        &'rpass self,
+        context: &mut RenderContext<'_, 'rpass>,
+        quad_index_buffer: &'rpass QuadIndexBuffer,
+        batches: &[(Matrix4, wgpu::BufferSlice<'rpass>, usize)],
+    ) {
+        let (pipeline, index_buffer) = match context.debug_mode {
+            DebugMode::Off => return,
+            DebugMode::Wireframe => (&self.wireframe, &self.outline_index_buffer),
+            DebugMode::Overdraw => (&self.overdraw, quad_index_buffer),
+            DebugMode::Batches => (&self.batches, quad_index_buffer),
+        };
+
+        let max_quads = batches.iter().map(|b| b.2).max().unwrap_or_default();
+        // `set_index_buffer` will fail with empty buffers.
+        if max_quads == 0 {
+            return;
+        }
+
+        let indices_per_quad = index_buffer.indices_per_quad();
+        let pass = &mut context.pass;
+        pass.set_pipeline(pipeline);
+        pass.set_index_buffer(
+            index_buffer.slice(..(max_quads * indices_per_quad * mem::size_of::<u16>()) as u64),
+            wgpu::IndexFormat::Uint16,
+        );
+
+        for (model_matrix, vertex_buffer, quad_count) in batches {
+            let batch = context.next_debug_batch();
+            context.queue_model_matrix(model_matrix);
+
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_vertex_buffer(0, *vertex_buffer);
+
+            // The batch index is passed as the instance to color the batch.
+            pass.draw_indexed(
+                0..(quad_count * indices_per_quad) as u32,
+                0,
+                batch..batch + 1,
+            )
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{error, fmt, time::Duration};
+
+/// An error of the render path.
+///
+/// Most errors are recoverable: The scene stays intact and the next frame may succeed. Use
+/// [`Self::is_recoverable`] to decide whether to continue rendering.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The surface texture could not be acquired.
+    Surface(wgpu::SurfaceError),
+    /// A view projection matrix contains values that are not finite.
+    InvalidMatrix,
+    /// The scene could not be prepared for rendering.
+    Preparation(anyhow::Error),
+    /// Presenting took too long or timed out repeatedly, see [`crate::PresentWatchdog`]. The
+    /// surface should be recreated.
+    PresentStalled { duration: Duration, timeouts: usize },
+}
+
+impl RenderError {
+    /// `false` if rendering can not continue as is, for example when the system is out of
+    /// memory.
+    pub fn is_recoverable(&self) -> bool {
+        !self.is_out_of_memory()
+    }
+
+    /// Out of memory is often transient, releasing the caches of the renderer with
+    /// [`crate::Renderer::release_memory`] may resolve it.
+    pub fn is_out_of_memory(&self) -> bool {
+        matches!(self, Self::Surface(wgpu::SurfaceError::OutOfMemory))
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Surface(e) => write!(f, "Surface error: {e}"),
+            Self::InvalidMatrix => write!(f, "View projection matrix is not finite"),
+            Self::Preparation(e) => write!(f, "Preparation failed: {e}"),
+            Self::PresentStalled { duration, timeouts } => write!(
+                f,
+                "Presentation stalled for {duration:?} after {timeouts} consecutive timeouts"
+            ),
+        }
+    }
+}
+
+impl error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Surface(e) => Some(e),
+            Self::InvalidMatrix | Self::PresentStalled { .. } => None,
+            Self::Preparation(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<wgpu::SurfaceError> for RenderError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        Self::Surface(e)
+    }
+}
+
+impl From<anyhow::Error> for RenderError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Preparation(e)
+    }
+}
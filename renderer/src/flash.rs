@@ -0,0 +1,159 @@
+//! A color that covers the whole target and fades out, for example to acknowledge a screenshot.
+//!
+//! The flash is rendered on top of all views and is not part of the scene.
+
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use massive_geometry::Color;
+
+use crate::{
+    bind_group_entries,
+    tools::{create_pipeline, BindGroupLayoutBuilder},
+};
+
+pub struct FlashRenderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    flash: Option<Flash>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Flash {
+    color: Color,
+    /// The time the flash started, in the time base of the renderer.
+    start: f32,
+    duration: f32,
+}
+
+impl Flash {
+    /// The color at `time`, `None` if the flash is over.
+    fn color_at(&self, time: f32) -> Option<Color> {
+        let t = (time - self.start) / self.duration.max(f32::EPSILON);
+        if !(0.0..1.0).contains(&t) {
+            return None;
+        }
+        // ease out quadratic
+        let opacity = (1.0 - t) * (1.0 - t);
+        Some(Color {
+            alpha: self.color.alpha * opacity,
+            ..self.color
+        })
+    }
+}
+
+impl FlashRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bind_group_layout = BindGroupLayoutBuilder::fragment()
+            .uniform()
+            .build("Flash Bind Group Layout", device);
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Flash Uniform Buffer"),
+            contents: bytemuck::bytes_of(&[0f32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Flash Bind Group"),
+            layout: &bind_group_layout,
+            entries: bind_group_entries!(0 => &buffer),
+        });
+        let pipeline = Self::create_pipeline(device, format, &bind_group_layout);
+        Self {
+            pipeline,
+            bind_group_layout,
+            buffer,
+            bind_group,
+            flash: None,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let shader = &crate::shader_module!(device, "flash/flash.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Flash Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        create_pipeline(
+            "Flash Pipeline",
+            device,
+            shader,
+            "fs_flash",
+            &[],
+            &pipeline_layout,
+            &targets,
+        )
+    }
+
+    pub fn target_format_changed(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.pipeline = Self::create_pipeline(device, format, &self.bind_group_layout);
+    }
+
+    /// Start a flash with `color` at `time` that fades out over `duration` seconds. Replaces a
+    /// running flash.
+    pub fn start(&mut self, color: Color, time: f32, duration: f32) {
+        self.flash = Some(Flash {
+            color,
+            start: time,
+            duration,
+        });
+    }
+
+    /// Whether the flash is visible at `time`.
+    pub fn is_animating(&self, time: f32) -> bool {
+        self.flash
+            .is_some_and(|flash| flash.color_at(time).is_some())
+    }
+
+    /// Render the flash at `time` on top of `target`.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        time: f32,
+    ) {
+        let Some(color) = self.flash.and_then(|flash| flash.color_at(time)) else {
+            return;
+        };
+        let color = [color.red, color.green, color.blue, color.alpha];
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&color));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Flash Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Flash Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+    }
+}
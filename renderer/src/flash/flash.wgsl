@@ -0,0 +1,23 @@
+// A color that covers the target, see `flash.rs`.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A triangle that covers the target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 2u), f32((index << 1u) & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+// The color with the opacity at the current time.
+@group(0) @binding(0)
+var<uniform> color: vec4<f32>;
+
+@fragment
+fn fs_flash(in: VertexOutput) -> @location(0) vec4<f32> {
+    return color;
+}
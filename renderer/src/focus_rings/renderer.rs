@@ -0,0 +1,365 @@
+use std::{collections::BTreeMap, f64::consts::FRAC_PI_2, mem};
+
+use massive_geometry::{Bounds, Color, Matrix4, Point};
+use massive_scene::{Id, PositionedRenderShape, Shape};
+use massive_shapes::FocusRing;
+
+use crate::{
+    pods::ColorVertex,
+    renderer::RenderContext,
+    tools::{create_pipeline, QuadIndexBuffer},
+    ColorRole, HighContrast, LayerBindGroups,
+};
+
+/// Renders and animates the focus rings.
+///
+/// Like carets, focus rings are not part of the prepared scene. Their outlines depend on the
+/// bounds of the shapes they surround, so their vertices are written every frame.
+pub struct FocusRingRenderer {
+    pipeline: wgpu::RenderPipeline,
+    index_buffer: QuadIndexBuffer,
+    rings: BTreeMap<Id, AnimatedRing>,
+    vertex_buffer: Option<wgpu::Buffer>,
+    /// The draw calls prepared for the next frame.
+    draws: Vec<RingDraw>,
+}
+
+struct AnimatedRing {
+    shape: PositionedRenderShape,
+    ring: FocusRing,
+    /// The bounds the ring moves from, `None` if it grows in.
+    from: Option<Bounds>,
+    /// The bounds the ring was drawn with the last time.
+    current: Option<Bounds>,
+    /// The time the movement started.
+    start: f32,
+}
+
+struct RingDraw {
+    position: Id,
+    overlay: bool,
+    first_quad: usize,
+    quad_count: usize,
+}
+
+/// The number of segments a corner is approximated with.
+const ARC_SEGMENTS: usize = 6;
+
+/// The distance in pixels a new ring shrinks while it appears.
+const GROW_DISTANCE: f64 = 8.0;
+
+impl FocusRingRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        // Focus rings are made of quads and share their shader.
+        let shader = &crate::shader_module!(device, "quads/quads.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Focus Ring Pipeline Layout"),
+            bind_group_layouts: &[view_projection_bind_group_layout, layer_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let pipeline = create_pipeline(
+            "Focus Ring Pipeline",
+            device,
+            shader,
+            "fs_quad",
+            &[ColorVertex::layout()],
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            pipeline,
+            index_buffer: QuadIndexBuffer::new(device),
+            rings: BTreeMap::new(),
+            vertex_buffer: None,
+            draws: Vec::new(),
+        }
+    }
+
+    /// Recreate the pipeline for a new target format and keep the rings.
+    pub fn target_format_changed(
+        &mut self,
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) {
+        let rings = mem::take(&mut self.rings);
+        *self = Self::new(
+            device,
+            target_format,
+            view_projection_bind_group_layout,
+            layer_bind_group_layout,
+        );
+        self.rings = rings;
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.rings.contains_key(&id)
+    }
+
+    /// Create or update a focus ring at `time`.
+    ///
+    /// A ring that is moved to another position moves from where it was drawn last to the shapes
+    /// of the new position. New rings grow in.
+    pub fn set(&mut self, id: Id, position: Id, ring: FocusRing, time: f32) {
+        let (from, current, start) = match self.rings.get(&id) {
+            // Same position, only the style changed, keep the running animation.
+            Some(existing) if existing.shape.position == position => {
+                (existing.from, existing.current, existing.start)
+            }
+            Some(existing) => (existing.current, existing.current, time),
+            None => (None, None, time),
+        };
+        self.rings.insert(
+            id,
+            AnimatedRing {
+                shape: PositionedRenderShape {
+                    position,
+                    shape: Shape::FocusRing(ring),
+                },
+                ring,
+                from,
+                current,
+                start,
+            },
+        );
+    }
+
+    /// Remove a focus ring, returns `false` if there is no ring with this id.
+    pub fn remove(&mut self, id: Id) -> bool {
+        self.rings.remove(&id).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.rings.clear();
+        self.draws.clear();
+    }
+
+    /// All focus rings, ordered by their ids.
+    pub fn shapes(&self) -> impl Iterator<Item = (Id, &PositionedRenderShape)> {
+        self.rings.iter().map(|(id, ring)| (*id, &ring.shape))
+    }
+
+    /// Whether a ring is still moving or growing in at `time`.
+    pub fn is_animating(&self, time: f32) -> bool {
+        self.rings
+            .values()
+            .any(|ring| time < ring.start + ring.ring.motion)
+    }
+
+    /// Write the vertices of all rings at `time`.
+    ///
+    /// `bounds` returns the bounds of the shapes at a position in scene coordinates, and whether
+    /// the position is placed in the overlay. Rings around positions without visible shapes are
+    /// not drawn. With `high_contrast`, rings take the accent color of the palette.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        time: f32,
+        high_contrast: Option<&HighContrast>,
+        bounds: impl Fn(Id) -> Option<(Bounds, bool)>,
+    ) {
+        self.draws.clear();
+        let mut vertices = Vec::new();
+        for ring in self.rings.values_mut() {
+            let position = ring.shape.position;
+            let Some((target, overlay)) = bounds(position) else {
+                ring.current = None;
+                continue;
+            };
+            let style = &ring.ring;
+            let target = inflate(&target, style.inflate + style.width);
+
+            let eased = ring.eased(time);
+            let (outline, alpha) = match &ring.from {
+                Some(from) => (lerp_bounds(from, &target, eased), 1.0),
+                None => (
+                    inflate(&target, GROW_DISTANCE * (1.0 - eased as f64)),
+                    eased,
+                ),
+            };
+            ring.current = Some(outline);
+
+            let color = match high_contrast {
+                Some(high_contrast) => high_contrast.color(ColorRole::Accent, style.color),
+                None => style.color,
+            };
+            let color = color.with_alpha(color.alpha * alpha);
+            let first_quad = vertices.len() / 4;
+            outline_vertices(&outline, style.width, style.radius, color, &mut vertices);
+            self.draws.push(RingDraw {
+                position,
+                overlay,
+                first_quad,
+                quad_count: vertices.len() / 4 - first_quad,
+            });
+        }
+
+        if vertices.is_empty() {
+            return;
+        }
+
+        let contents: &[u8] = bytemuck::cast_slice(&vertices);
+        let size = contents.len() as wgpu::BufferAddress;
+        if self.vertex_buffer.as_ref().map_or(0, |b| b.size()) < size {
+            // Grow in powers of two, rings come and go with focus changes.
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Focus Ring Vertex Buffer"),
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        queue.write_buffer(self.vertex_buffer.as_ref().unwrap(), 0, contents);
+
+        let max_quads = self.draws.iter().map(|d| d.quad_count).max();
+        self.index_buffer
+            .ensure_can_index_num_quads(device, max_quads.unwrap_or_default());
+    }
+
+    /// Whether one of the prepared rings is placed in the overlay.
+    pub fn has_overlay(&self) -> bool {
+        self.draws.iter().any(|draw| draw.overlay)
+    }
+
+    /// Render the rings. The vertices are in scene coordinates, so `pixel_matrix` is the model
+    /// matrix of all of them.
+    pub fn render<'rpass>(
+        &'rpass self,
+        context: &mut RenderContext<'_, 'rpass>,
+        layer_bind_groups: &'rpass LayerBindGroups,
+        pixel_matrix: &Matrix4,
+    ) {
+        let Some(vertex_buffer) = &self.vertex_buffer else {
+            return;
+        };
+        if self.draws.is_empty() || !context.debug_mode.renders_scene() {
+            return;
+        }
+
+        let pass = &mut context.pass;
+        pass.set_pipeline(&self.pipeline);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+        for RingDraw {
+            position,
+            overlay,
+            first_quad,
+            quad_count,
+        } in &self.draws
+        {
+            if *overlay != context.is_overlay_pass() || !context.renders_position(*position) {
+                continue;
+            }
+            context.queue_model_matrix(pixel_matrix);
+
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_groups.bind_group(*position), &[]);
+
+            pass.draw_indexed(
+                0..(QuadIndexBuffer::INDICES_PER_QUAD * quad_count) as u32,
+                (first_quad * 4) as i32,
+                0..1,
+            )
+        }
+    }
+}
+
+impl AnimatedRing {
+    /// The eased progress of the animation at `time`, from 0 to 1.
+    fn eased(&self, time: f32) -> f32 {
+        if self.ring.motion <= 0.0 {
+            return 1.0;
+        }
+        let progress = ((time - self.start) / self.ring.motion).clamp(0.0, 1.0);
+        // Ease out cubic.
+        1.0 - (1.0 - progress).powi(3)
+    }
+}
+
+fn inflate(bounds: &Bounds, by: f64) -> Bounds {
+    Bounds::new(
+        (bounds.min.x - by, bounds.min.y - by),
+        (bounds.max.x + by, bounds.max.y + by),
+    )
+}
+
+fn lerp_bounds(from: &Bounds, to: &Bounds, t: f32) -> Bounds {
+    let lerp = |from: Point, to: Point| from + (to - from) * t as f64;
+    Bounds::new(lerp(from.min, to.min), lerp(from.max, to.max))
+}
+
+/// Append the quads of a rounded outline of `width` inside of `outer`.
+///
+/// The outline is walked clockwise, and each step connects the outer and the inner edge with one
+/// quad. Where the inner radius is zero, the quads degenerate to triangles.
+fn outline_vertices(
+    outer: &Bounds,
+    width: f64,
+    radius: f64,
+    color: Color,
+    vertices: &mut Vec<ColorVertex>,
+) {
+    let (w, h) = (outer.max.x - outer.min.x, outer.max.y - outer.min.y);
+    let width = width.min(w / 2.0).min(h / 2.0).max(0.0);
+    let outer_radius = radius.min(w / 2.0).min(h / 2.0).max(0.0);
+    let inner_radius = (outer_radius - width).max(0.0);
+    let inner = inflate(outer, -width);
+
+    let outer_edge = rounded_rect(outer, outer_radius);
+    let inner_edge = rounded_rect(&inner, inner_radius);
+    let vertex = |p: Point| ColorVertex::new(p.with_z(0.0), color);
+    for i in 0..outer_edge.len() {
+        let next = (i + 1) % outer_edge.len();
+        vertices.extend([
+            vertex(outer_edge[i]),
+            vertex(inner_edge[i]),
+            vertex(inner_edge[next]),
+            vertex(outer_edge[next]),
+        ]);
+    }
+}
+
+/// The points of a rounded rectangle clockwise, starting at the top left corner.
+///
+/// Every corner has the same number of points, so that the edges of rectangles with different
+/// radii can be connected point by point.
+fn rounded_rect(bounds: &Bounds, radius: f64) -> Vec<Point> {
+    let (min, max) = (bounds.min, bounds.max);
+    let centers = [
+        Point::new(min.x + radius, min.y + radius),
+        Point::new(max.x - radius, min.y + radius),
+        Point::new(max.x - radius, max.y - radius),
+        Point::new(min.x + radius, max.y - radius),
+    ];
+    let mut points = Vec::with_capacity(4 * (ARC_SEGMENTS + 1));
+    for (corner, center) in centers.into_iter().enumerate() {
+        // The top left corner starts at the left, y points down.
+        let start = FRAC_PI_2 * (corner + 2) as f64;
+        points.extend((0..=ARC_SEGMENTS).map(|i| {
+            let angle = start + FRAC_PI_2 * i as f64 / ARC_SEGMENTS as f64;
+            Point::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        }));
+    }
+    points
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+};
+
+use anyhow::{anyhow, Result};
+use cosmic_text::{fontdb, Font, FontSystem};
+
+/// Resolves the fonts glyphs are rasterized with.
+///
+/// Rasterization only needs the font data, not the shaping state of a [`FontSystem`].
+pub trait FontSource {
+    fn font(&mut self, id: fontdb::ID) -> Option<Arc<Font>>;
+}
+
+impl FontSource for FontSystem {
+    fn font(&mut self, id: fontdb::ID) -> Option<Arc<Font>> {
+        self.get_font(id)
+    }
+}
+
+/// The fonts shared between layout in the application and rasterization in the renderer.
+///
+/// Layout locks the [`FontSystem`] for shaping. The renderer resolves fonts through a read-mostly
+/// cache and locks the font system only when it sees a font for the first time, so preparation
+/// does not wait for layout and layout does not wait for preparation. The scaling context stays
+/// with the renderer.
+///
+/// Clones share the same font system and cache.
+#[derive(Clone)]
+pub struct FontService {
+    font_system: Arc<Mutex<FontSystem>>,
+    fonts: Arc<RwLock<HashMap<fontdb::ID, Arc<Font>>>>,
+}
+
+impl From<FontSystem> for FontService {
+    fn from(font_system: FontSystem) -> Self {
+        Arc::new(Mutex::new(font_system)).into()
+    }
+}
+
+impl From<Arc<Mutex<FontSystem>>> for FontService {
+    fn from(font_system: Arc<Mutex<FontSystem>>) -> Self {
+        Self {
+            font_system,
+            fonts: Default::default(),
+        }
+    }
+}
+
+impl FontService {
+    /// The font system for layout.
+    pub fn font_system(&self) -> &Arc<Mutex<FontSystem>> {
+        &self.font_system
+    }
+
+    pub fn lock(&self) -> Result<MutexGuard<'_, FontSystem>> {
+        self.font_system
+            .lock()
+            .map_err(|_| anyhow!("Font system lock is poisoned"))
+    }
+
+    /// Drop the cached fonts, for example after they were removed from the font database.
+    pub fn forget_fonts<'a>(&self, ids: impl IntoIterator<Item = &'a fontdb::ID>) {
+        let mut fonts = self.fonts.write().unwrap_or_else(|e| e.into_inner());
+        for id in ids {
+            fonts.remove(id);
+        }
+    }
+}
+
+impl FontSource for FontService {
+    fn font(&mut self, id: fontdb::ID) -> Option<Arc<Font>> {
+        if let Some(font) = self
+            .fonts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+        {
+            return Some(font.clone());
+        }
+
+        let font = self.lock().ok()?.get_font(id)?;
+        self.fonts
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, font.clone());
+        Some(font)
+    }
+}
@@ -0,0 +1,198 @@
+//! Rendering to targets in formats the pipelines can't render to directly.
+//!
+//! The pipelines blend into their target, which some surface formats don't support, for example
+//! `Rgba32Float`. For these, the scene is rendered into an intermediate texture of a format that
+//! supports blending, which is then converted to the target's format.
+
+use log::info;
+
+use crate::{
+    bind_group_entries,
+    tools::{create_pipeline, BindGroupLayoutBuilder},
+};
+
+/// Whether the pipelines can render to targets of `format` directly with a device that has the
+/// `device_features`.
+pub fn renders_directly(format: wgpu::TextureFormat, device_features: wgpu::Features) -> bool {
+    let features = format.guaranteed_format_features(device_features);
+    features
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        && features
+            .flags
+            .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE)
+}
+
+/// The format the pipelines render to for targets of `format`.
+///
+/// This is `format` itself if it is rendered to directly, or if it can't be converted to,
+/// because it does not store floats.
+pub fn render_format(
+    format: wgpu::TextureFormat,
+    device_features: wgpu::Features,
+) -> wgpu::TextureFormat {
+    if renders_directly(format, device_features) || !can_convert_to(format) {
+        return format;
+    }
+    if format.is_srgb() {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    } else if format.block_copy_size(None).unwrap_or_default() > 4 {
+        // Keep the precision of wide formats.
+        wgpu::TextureFormat::Rgba16Float
+    } else {
+        wgpu::TextureFormat::Rgba8Unorm
+    }
+}
+
+/// The conversion shader writes floats.
+fn can_convert_to(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format.sample_type(None, None),
+        Some(wgpu::TextureSampleType::Float { .. })
+    )
+}
+
+/// Renders through an intermediate texture and converts it to the format of the target.
+pub struct FormatConversion {
+    render_format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    intermediate: Option<Intermediate>,
+}
+
+/// The texture the scene is rendered to, for one size of the target.
+struct Intermediate {
+    size: (u32, u32),
+    view: wgpu::TextureView,
+    /// Binds the texture to convert it.
+    bind_group: wgpu::BindGroup,
+}
+
+impl FormatConversion {
+    /// Returns `None` if the pipelines render to targets of `target_format` directly.
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Option<Self> {
+        let render_format = render_format(target_format, device.features());
+        if render_format == target_format {
+            return None;
+        }
+        info!("Rendering to {render_format:?} and converting to {target_format:?}");
+
+        let bind_group_layout = BindGroupLayoutBuilder::fragment()
+            .texture()
+            .build("Format Conversion Bind Group Layout", device);
+
+        let shader = &crate::shader_module!(device, "format_conversion/convert.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Format Conversion Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let pipeline = create_pipeline(
+            "Format Conversion Pipeline",
+            device,
+            shader,
+            "fs_convert",
+            &[],
+            &pipeline_layout,
+            &targets,
+        );
+
+        Some(Self {
+            render_format,
+            pipeline,
+            bind_group_layout,
+            intermediate: None,
+        })
+    }
+
+    pub fn render_format(&self) -> wgpu::TextureFormat {
+        self.render_format
+    }
+
+    /// Create the intermediate texture for a target of `size`.
+    pub fn prepare_target(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        if self.intermediate.as_ref().map(|i| i.size) == Some(size) {
+            return;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Format Conversion Target"),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.render_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Format Conversion Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: bind_group_entries!(0 => &view),
+        });
+
+        self.intermediate = Some(Intermediate {
+            size,
+            view,
+            bind_group,
+        });
+    }
+
+    /// Release the intermediate texture, it is created again with the next
+    /// [`Self::prepare_target`].
+    pub fn release_target(&mut self) {
+        self.intermediate = None;
+    }
+
+    /// The texture to render to instead of the target, `None` before [`Self::prepare_target`].
+    pub fn target(&self) -> Option<&wgpu::TextureView> {
+        self.intermediate
+            .as_ref()
+            .map(|intermediate| &intermediate.view)
+    }
+
+    /// Convert the intermediate texture to `target`, which must have the size it was prepared
+    /// for.
+    pub fn convert(&self, device: &wgpu::Device, queue: &wgpu::Queue, target: &wgpu::TextureView) {
+        let Some(intermediate) = &self.intermediate else {
+            return;
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Format Conversion Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Format Conversion Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &intermediate.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+    }
+}
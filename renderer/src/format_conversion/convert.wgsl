@@ -0,0 +1,23 @@
+// Copies the intermediate target to a target in a format the pipelines can't render to.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A triangle that covers the target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 2u), f32((index << 1u) & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+// The intermediate target has the size of the target, so its pixels are copied one to one.
+@fragment
+fn fs_convert(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(t_source, vec2<i32>(in.clip_position.xy), 0);
+}
@@ -0,0 +1,167 @@
+//! Reading rendered frames back from the GPU, for example for screenshots.
+
+use anyhow::{bail, Context, Result};
+use futures::channel::oneshot;
+
+/// The pixels of a captured frame.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA with 8 bits per channel and straight alpha, row by row without padding. The colors
+    /// are encoded like the surface presents them, which is sRGB for the common formats.
+    pub data: Vec<u8>,
+}
+
+/// A frame that is rendered and copied into a buffer, but may not be readable yet.
+///
+/// Use [`Self::wait`] to read it.
+#[derive(Debug)]
+pub struct FrameCapture {
+    buffer: wgpu::Buffer,
+    size: (u32, u32),
+    bytes_per_row: usize,
+    format: wgpu::TextureFormat,
+    alpha_mode: wgpu::CompositeAlphaMode,
+    mapped: oneshot::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+/// Whether frames rendered to `format` can be captured.
+fn can_capture(format: wgpu::TextureFormat) -> bool {
+    use wgpu::TextureFormat::*;
+    matches!(
+        format,
+        Rgba8Unorm | Rgba8UnormSrgb | Bgra8Unorm | Bgra8UnormSrgb | Rgb10a2Unorm
+    )
+}
+
+impl FrameCapture {
+    /// Copy `texture` into a buffer and map it.
+    ///
+    /// The texture must be created with [`wgpu::TextureUsages::COPY_SRC`].
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        alpha_mode: wgpu::CompositeAlphaMode,
+    ) -> Result<Self> {
+        let format = texture.format();
+        if !can_capture(format) {
+            bail!("Capturing frames of the format {format:?} is not supported");
+        }
+        let (width, height) = (texture.width(), texture.height());
+        let bytes_per_row =
+            (width as usize * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Capture Buffer"),
+            size: (bytes_per_row * height as usize) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Frame Capture Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row as u32),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit([encoder.finish()]);
+
+        let (sender, mapped) = oneshot::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+
+        Ok(Self {
+            buffer,
+            size: (width, height),
+            bytes_per_row,
+            format,
+            alpha_mode,
+            mapped,
+        })
+    }
+
+    /// Wait until the GPU has rendered the frame and read it.
+    ///
+    /// On native targets, this blocks until the GPU is done. In the browser, the buffer is mapped
+    /// asynchronously while the event loop continues.
+    pub async fn wait(self, device: &wgpu::Device) -> Result<CapturedFrame> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = device.poll(wgpu::Maintain::Wait);
+        #[cfg(target_arch = "wasm32")]
+        let _ = device.poll(wgpu::Maintain::Poll);
+
+        self.mapped
+            .await
+            .context("The frame capture was dropped before it was mapped")??;
+
+        let (width, height) = (self.size.0 as usize, self.size.1 as usize);
+        let mut data = Vec::with_capacity(width * height * 4);
+        {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks(self.bytes_per_row).take(height) {
+                for pixel in row[..width * 4].chunks_exact(4) {
+                    data.extend(to_rgba(self.format, pixel.try_into().unwrap()));
+                }
+            }
+        }
+        self.buffer.unmap();
+
+        match self.alpha_mode {
+            wgpu::CompositeAlphaMode::PreMultiplied => unpremultiply(&mut data),
+            wgpu::CompositeAlphaMode::PostMultiplied => {}
+            // The compositor ignores the alpha channel.
+            _ => data.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255),
+        }
+
+        Ok(CapturedFrame {
+            width: self.size.0,
+            height: self.size.1,
+            data,
+        })
+    }
+}
+
+/// Convert a pixel of one of the formats supported by [`can_capture`] to RGBA.
+fn to_rgba(format: wgpu::TextureFormat, pixel: [u8; 4]) -> [u8; 4] {
+    use wgpu::TextureFormat::*;
+    match format {
+        Bgra8Unorm | Bgra8UnormSrgb => [pixel[2], pixel[1], pixel[0], pixel[3]],
+        Rgb10a2Unorm => {
+            let value = u32::from_le_bytes(pixel);
+            let channel = |shift: u32| (((value >> shift) & 0x3ff) >> 2) as u8;
+            [
+                channel(0),
+                channel(10),
+                channel(20),
+                ((value >> 30) * 85) as u8,
+            ]
+        }
+        _ => pixel,
+    }
+}
+
+fn unpremultiply(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use static_assertions::const_assert_eq;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::{bind_group_entries, tools::BindGroupLayoutBuilder, TextRendering};
+
+/// Uniforms that are shared by all draw calls of a frame.
+///
+/// The text pipelines bind them at group 3 to evaluate the reveal animations of the glyph runs and
+/// to shade the glyphs.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Pod, Zeroable)]
+pub struct FrameUniforms {
+    /// The time in seconds, unused, unused, unused.
+    pub parameters: [f32; 4],
+    /// The shader parameters of the renderer's [`crate::TextRendering`].
+    pub text: [f32; 4],
+}
+
+// WebGL uniform requirement
+const_assert_eq!(size_of::<FrameUniforms>() % 16, 0);
+
+pub struct FrameBindGroup {
+    /// A copy of the uniforms in the buffer.
+    uniforms: FrameUniforms,
+    layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl FrameBindGroup {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = BindGroupLayoutBuilder::vertex_fragment()
+            .uniform()
+            .build("Frame Bind Group Layout", device);
+        let uniforms = FrameUniforms {
+            text: TextRendering::default().shader_parameters(),
+            ..FrameUniforms::default()
+        };
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Frame Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniforms),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Frame Bind Group"),
+            layout: &layout,
+            entries: bind_group_entries!(0 => &buffer),
+        });
+        Self {
+            uniforms,
+            layout,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn set_time(&mut self, queue: &wgpu::Queue, time: f32) {
+        self.uniforms.parameters[0] = time;
+        self.write(queue);
+    }
+
+    pub fn set_text_rendering(&mut self, queue: &wgpu::Queue, text_rendering: &TextRendering) {
+        self.uniforms.text = text_rendering.shader_parameters();
+        self.write(queue);
+    }
+
+    fn write(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniforms));
+    }
+}
@@ -2,10 +2,17 @@ mod distance_field_gen;
 pub mod glyph_atlas;
 mod glyph_cache;
 mod glyph_classifier;
+#[cfg(not(target_arch = "wasm32"))]
+mod glyph_disk_cache;
 mod glyph_param;
 pub mod glyph_rasterization;
+mod msdf_gen;
+mod skyline_packer;
 
-pub use glyph_atlas::GlyphAtlas;
+pub use glyph_atlas::{GlyphAtlas, MaskAtlasFormat};
 pub use glyph_cache::*;
 pub use glyph_classifier::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use glyph_disk_cache::*;
 pub use glyph_param::*;
+pub use skyline_packer::PackingStats;
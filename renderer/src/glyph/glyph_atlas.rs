@@ -1,11 +1,17 @@
 //! A  wgpu glyph atlas for u8 textures. Inspired by glyphon's TextAtlas.
-use std::collections::HashMap;
+//!
+//! Monochrome images can also be packed into the four channels of an RGBA texture, see
+//! [`MaskAtlasFormat`].
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
 
 use anyhow::{bail, Result};
-use cosmic_text::{SwashContent, SwashImage};
+use cosmic_text::{fontdb, SwashContent, SwashImage};
 pub use etagere::Rectangle;
-use etagere::{Allocation, BucketedAtlasAllocator, Point};
-use euclid::size2;
+use etagere::Size;
+use euclid::{size2, vec2};
 
 use tracing::instrument;
 use wgpu::{
@@ -14,142 +20,511 @@ use wgpu::{
     TextureViewDescriptor,
 };
 
-use super::RasterizedGlyphKey;
+use super::{skyline_packer::SkylinePacker, PackingStats, RasterizedGlyphKey};
+
+/// How the monochrome images of an atlas, like the distance fields of glyphs, are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaskAtlasFormat {
+    /// One image per texel in an R8 texture.
+    #[default]
+    R8,
+    /// Up to four images per texel, one in each channel of an RGBA texture.
+    ///
+    /// For backends where R8 textures are slow to sample or to write to, like WebGL2. This
+    /// quadruples the capacity of an atlas of the same texture size.
+    ChannelPacked,
+}
+
+impl MaskAtlasFormat {
+    /// The format that works best on `backend`.
+    pub fn for_backend(backend: wgpu::Backend) -> Self {
+        match backend {
+            wgpu::Backend::Gl => Self::ChannelPacked,
+            _ => Self::R8,
+        }
+    }
+}
+
+/// Where an image is stored in an atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasSlot {
+    /// The index of the page the image is stored in.
+    pub page: usize,
+    pub rect: Rectangle,
+    /// The color channel of a channel packed atlas, `0` otherwise.
+    pub channel: usize,
+}
 
+impl AtlasSlot {
+    /// The weights of the texture's channels that select the image's channel. Selects red in
+    /// atlases that are not channel packed.
+    pub fn channel_mask(&self) -> [f32; 4] {
+        let mut mask = [0.0; 4];
+        mask[self.channel] = 1.0;
+        mask
+    }
+}
+
+/// Stores images in one or more texture pages.
+///
+/// Images are stored in the first page they fit in. If they fit in none, the last page grows, and
+/// when it reached the maximum texture dimension of the device, a new page is added. Quads that
+/// sample the atlas must therefore be batched by the page of their slots.
 pub struct GlyphAtlas {
+    texture_format: TextureFormat,
+    /// The number of channels images are stored in.
+    channels: usize,
+    /// Never empty.
+    pages: Vec<AtlasPage>,
+    /// Storage of the available and (padded) Images and their slots in the pages.
+    images: HashMap<RasterizedGlyphKey, (AtlasSlot, SwashImage)>,
+    /// A lazily allocated block of fully covered pixels, used to render solid quads.
+    solid: Option<AtlasSlot>,
+    /// The id of the next texture that is created.
+    next_texture_id: u32,
+    /// The number of bytes written to the textures.
+    uploaded_bytes: u64,
+}
+
+/// A texture of an atlas and the allocations in it.
+struct AtlasPage {
     texture: AtlasTexture,
-    allocator: BucketedAtlasAllocator,
-    /// Storage of the available and (padded) Images.
-    images: HashMap<RasterizedGlyphKey, (Allocation, SwashImage)>,
+    /// Identifies the texture, unique among all textures the atlas created.
+    texture_id: u32,
+    /// One packer for each channel images are stored in.
+    packers: Vec<SkylinePacker>,
+    /// The regions of the texture that changed since the last [`GlyphAtlas::flush`].
+    dirty: Vec<Rectangle>,
 }
 
 impl GlyphAtlas {
     // TODO: Measure what we usually need and make this a arg to new.
     const INITIAL_SIZE: u32 = 128;
     const GROWTH_FACTOR: u32 = 2;
+    /// The size of the solid block. Texture coordinates are kept one pixel away from its borders,
+    /// so that linear sampling never reaches into neighboring allocations.
+    const SOLID_DIM: i32 = 4;
+    /// Dirty regions are merged if the merged region is at most this much larger than both, so
+    /// that neighboring glyphs are uploaded with one write, without uploading much that did not
+    /// change.
+    const MERGE_SLACK: f64 = 1.25;
+    /// A page is repacked if more of its occupied space is wasted than this.
+    const REPACK_FRAGMENTATION: f64 = 0.3;
+    /// Only pages that are occupied more than this are repacked, because they would need to grow
+    /// soon.
+    const REPACK_OCCUPANCY: f64 = 0.75;
 
     pub fn new(device: &Device, texture_format: TextureFormat) -> Self {
         assert!(
             texture_format == TextureFormat::R8Unorm || texture_format == TextureFormat::Rgba8Unorm
         );
+        Self::with_channels(device, texture_format, 1, 0)
+    }
 
-        let max_texture_dimension_2d = device.limits().max_texture_dimension_2d;
-        let dim = Self::INITIAL_SIZE.min(max_texture_dimension_2d);
-        let allocator = BucketedAtlasAllocator::new(size2(dim as i32, dim as i32));
-        let texture = AtlasTexture::new(device, texture_format, dim);
+    /// An atlas for monochrome images.
+    pub fn new_masks(device: &Device, format: MaskAtlasFormat) -> Self {
+        Self::masks(device, format, 0)
+    }
 
-        Self {
-            texture,
-            allocator,
-            images: HashMap::default(),
+    fn masks(device: &Device, format: MaskAtlasFormat, first_texture_id: u32) -> Self {
+        match format {
+            MaskAtlasFormat::R8 => {
+                Self::with_channels(device, TextureFormat::R8Unorm, 1, first_texture_id)
+            }
+            MaskAtlasFormat::ChannelPacked => {
+                Self::with_channels(device, TextureFormat::Rgba8Unorm, 4, first_texture_id)
+            }
         }
     }
 
-    #[allow(unused)]
+    fn with_channels(
+        device: &Device,
+        texture_format: TextureFormat,
+        channels: usize,
+        first_texture_id: u32,
+    ) -> Self {
+        let mut atlas = Self {
+            texture_format,
+            channels,
+            pages: Vec::new(),
+            images: HashMap::default(),
+            solid: None,
+            next_texture_id: first_texture_id,
+            uploaded_bytes: 0,
+        };
+        atlas.add_page(device);
+        atlas
+    }
+
+    /// Add an empty page of the initial size.
+    fn add_page(&mut self, device: &Device) {
+        let dim = Self::INITIAL_SIZE.min(device.limits().max_texture_dimension_2d);
+        let packers = (0..self.channels)
+            .map(|_| SkylinePacker::new(size2(dim as i32, dim as i32)))
+            .collect();
+        self.pages.push(AtlasPage {
+            texture: AtlasTexture::new(device, self.texture_format, dim),
+            texture_id: self.next_texture_id,
+            packers,
+            dirty: Vec::new(),
+        });
+        self.next_texture_id += 1;
+    }
+
+    /// The size of the largest page.
     pub fn size(&self) -> (u32, u32) {
-        let dim = self.texture.dim();
+        let dim = self
+            .pages
+            .iter()
+            .map(|page| page.texture.dim())
+            .max()
+            .unwrap_or_default();
         (dim, dim)
     }
 
-    pub fn texture_view(&self) -> &TextureView {
-        self.texture.view()
+    /// The number of texture pages.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
     }
 
-    pub fn get(&self, key: &RasterizedGlyphKey) -> Option<(Rectangle, &SwashImage)> {
-        self.images.get(key).map(|(a, image)| {
-            let image_size = size2(image.placement.width as i32, image.placement.height as i32);
-            (
-                Rectangle::new(a.rectangle.min, a.rectangle.min + image_size),
-                image,
-            )
-        })
+    /// The number of glyph images stored.
+    pub fn glyph_count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Whether monochrome images are packed into the channels of an RGBA texture.
+    pub fn is_channel_packed(&self) -> bool {
+        self.channels > 1
     }
 
-    /// Makes room and stores a SwashImage in the texture atlas. May reallocate / grow it.
+    /// Drop all images and store the following ones in `format`.
+    ///
+    /// The textures are replaced, so this must only be called when no prepared quads refer to
+    /// the atlas.
+    pub fn set_mask_format(&mut self, device: &Device, format: MaskAtlasFormat) {
+        *self = Self::masks(device, format, self.next_texture_id);
+    }
+
+    /// Drop all images and pages and shrink the first page to its initial size, for example to
+    /// release memory.
+    ///
+    /// Like [`Self::set_mask_format`], this must only be called when no prepared quads refer to
+    /// the atlas.
+    pub fn clear(&mut self, device: &Device) {
+        *self = Self::with_channels(
+            device,
+            self.texture_format,
+            self.channels,
+            self.next_texture_id,
+        );
+    }
+
+    /// Free the images of the glyphs of the given fonts.
+    ///
+    /// The textures are not cleared, the space is reused for new glyphs after the pages were
+    /// repacked.
+    pub fn remove_fonts(&mut self, fonts: &HashSet<fontdb::ID>) {
+        let pages = &mut self.pages;
+        self.images.retain(|key, (slot, _)| {
+            let keep = !fonts.contains(&key.text.font_id);
+            if !keep {
+                pages[slot.page].packers[slot.channel].deallocate(&slot.rect);
+            }
+            keep
+        });
+    }
+
+    /// How well the space of the textures is used, summed over all pages and channels.
+    pub fn packing_stats(&self) -> PackingStats {
+        packing_stats(self.pages.iter().flat_map(|page| &page.packers))
+    }
+
+    /// The number of bytes written to the textures since the atlas was created.
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes
+    }
+
+    /// Identifies the texture of a page. Bind groups created for the same texture id are
+    /// interchangeable.
+    pub fn texture_id(&self, page: usize) -> u32 {
+        self.pages[page].texture_id
+    }
+
+    pub fn texture_view(&self, page: usize) -> &TextureView {
+        self.pages[page].texture.view()
+    }
+
+    pub fn get(&self, key: &RasterizedGlyphKey) -> Option<(AtlasSlot, &SwashImage)> {
+        self.images.get(key).map(|(slot, image)| (*slot, image))
+    }
+
+    /// Makes room and stores a SwashImage in the texture atlas. May grow the last page or add a
+    /// new one.
+    ///
+    /// The image is uploaded with the next [`Self::flush`].
     pub fn store(
         &mut self,
         device: &Device,
-        queue: &Queue,
         key: &RasterizedGlyphKey,
         image: SwashImage,
-    ) -> Result<Rectangle> {
+    ) -> Result<AtlasSlot> {
         debug_assert!(!self.images.contains_key(key));
+        debug_assert!(!self.is_channel_packed() || image.content == SwashContent::Mask);
 
         let size = size2(image.placement.width as i32, image.placement.height as i32);
+        let slot = self.allocate(device, size)?;
+        self.mark_dirty(slot.page, slot.rect);
+        // commit
+        self.images.insert(key.clone(), (slot, image));
+        Ok(slot)
+    }
+
+    /// Returns a rectangle in the atlas that is completely covered.
+    ///
+    /// Allocates the solid block on first use. It's uploaded with the next [`Self::flush`].
+    pub fn solid_rect(&mut self, device: &Device) -> Result<AtlasSlot> {
+        let solid = match self.solid {
+            Some(solid) => solid,
+            None => {
+                let solid = self.allocate(device, size2(Self::SOLID_DIM, Self::SOLID_DIM))?;
+                self.mark_dirty(solid.page, solid.rect);
+                self.solid = Some(solid);
+                solid
+            }
+        };
+
+        let min = solid.rect.min;
+        Ok(AtlasSlot {
+            rect: Rectangle::new(
+                min + vec2(1, 1),
+                min + vec2(Self::SOLID_DIM - 1, Self::SOLID_DIM - 1),
+            ),
+            ..solid
+        })
+    }
 
+    /// Allocate `size` in the first page it fits in, growing the atlas until it does.
+    fn allocate(&mut self, device: &Device, size: Size) -> Result<AtlasSlot> {
         loop {
-            let allocation = self.allocator.allocate(size);
-            if let Some(allocation) = allocation {
-                // Allocation might be larger, so we can't return the rectangles directly.
-                let allocated_size = allocation.rectangle.size();
-                debug_assert!(allocated_size.width >= size.width);
-                debug_assert!(allocated_size.height >= size.height);
-                self.upload(queue, &image, allocation.rectangle.min);
-                // commit
-                self.images.insert(key.clone(), (allocation, image));
-                let final_rect =
-                    Rectangle::new(allocation.rectangle.min, allocation.rectangle.min + size);
-                return Ok(final_rect);
+            let slot = self
+                .pages
+                .iter_mut()
+                .enumerate()
+                .find_map(|(index, page)| allocate(&mut page.packers, index, size));
+            if let Some(slot) = slot {
+                return Ok(slot);
             }
 
-            self.grow(device, queue)?
+            self.grow(device, size)?
         }
     }
 
-    fn grow(&mut self, device: &Device, queue: &Queue) -> Result<()> {
-        // TODO: allocate additional textures, if this fails. TODO: try to copy from texture to
-        // texture when growing (COPY_SRC). Does this cost performance, measure on all backends?
+    /// Grow the last page, or add a new page if it can't grow anymore.
+    fn grow(&mut self, device: &Device, size: Size) -> Result<()> {
+        // TODO: try to copy from texture to texture when growing (COPY_SRC). Does this cost
+        // performance, measure on all backends?
 
-        let current_dim = self.texture.dim();
+        let index = self.pages.len() - 1;
+        let page = &mut self.pages[index];
+        let current_dim = page.texture.dim();
 
         let new_dim =
             (current_dim * Self::GROWTH_FACTOR).min(device.limits().max_texture_dimension_2d);
 
         if new_dim == current_dim {
-            // TODO: Support multiple atlas textures.
-            bail!("Atlas reached its maximum size of {current_dim}x{current_dim}");
+            if packing_stats(&page.packers).occupied == 0 {
+                bail!(
+                    "An image of {}x{} does not fit into an empty atlas page",
+                    size.width,
+                    size.height
+                );
+            }
+            log::info!(
+                "Glyph atlas page {index} reached its maximum size of {current_dim}x{current_dim}, \
+                 adding a page"
+            );
+            self.add_page(device);
+            return Ok(());
         }
 
-        log::info!("Growing glyph atlas from {current_dim} to {new_dim}");
+        log::info!("Growing glyph atlas page {index} from {current_dim} to {new_dim}");
 
         // TODO: This allocates the new texture alongside the old for a short period of time.
         // If we won't use COPY_SRC, this should be avoided.
-        self.texture = AtlasTexture::new(device, self.texture.format(), new_dim);
+        page.texture = AtlasTexture::new(device, self.texture_format, new_dim);
+        page.texture_id = self.next_texture_id;
+        self.next_texture_id += 1;
         // After growing, the allocated rectangles retain their position.
-        self.allocator.grow(size2(new_dim as i32, new_dim as i32));
+        for packer in &mut page.packers {
+            packer.grow(size2(new_dim as i32, new_dim as i32));
+        }
 
-        self.upload_all(queue);
+        // The new texture is empty, everything that was stored needs to be uploaded again.
+        self.mark_all_dirty(index);
 
         Ok(())
     }
 
-    #[instrument(skip_all)]
-    fn upload_all(&self, queue: &Queue) {
-        for (allocation, image) in self.images.values() {
-            self.upload(queue, image, allocation.rectangle.min)
+    /// Repack the pages where too much space is wasted by freed glyphs or by gaps between them.
+    /// Returns `true` if a page was repacked.
+    ///
+    /// This moves the stored glyphs inside their pages, so it must only be called when no
+    /// prepared quads refer to their rectangles.
+    pub fn repack_if_fragmented(&mut self) -> bool {
+        let mut repacked = false;
+        for index in 0..self.pages.len() {
+            repacked |= self.repack_page_if_fragmented(index);
         }
+        repacked
     }
 
-    /// Upload the image to the GPU into the atlas texture at the given position.
+    fn repack_page_if_fragmented(&mut self, index: usize) -> bool {
+        let stats = packing_stats(&self.pages[index].packers);
+        if stats.fragmentation() <= Self::REPACK_FRAGMENTATION
+            || stats.occupancy() <= Self::REPACK_OCCUPANCY
+        {
+            return false;
+        }
+
+        // Placing the highest images first leaves the fewest gaps below the skyline.
+        let mut keys: Vec<_> = self
+            .images
+            .iter()
+            .filter(|(_, (slot, _))| slot.page == index)
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort_by_key(|key| {
+            let size = self.images[key].0.rect.size();
+            Reverse((size.height, size.width))
+        });
+
+        let mut packers: Vec<_> = self.pages[index]
+            .packers
+            .iter()
+            .map(|packer| SkylinePacker::new(packer.size()))
+            .collect();
+        let solid = match self.solid.filter(|solid| solid.page == index) {
+            Some(solid) => {
+                let Some(slot) = allocate(&mut packers, index, solid.rect.size()) else {
+                    return false;
+                };
+                Some(slot)
+            }
+            None => None,
+        };
+        let mut slots = Vec::with_capacity(keys.len());
+        for key in keys {
+            let Some(slot) = allocate(&mut packers, index, self.images[&key].0.rect.size()) else {
+                // Unlikely, skyline packing does not always improve with sorting.
+                log::warn!("Failed to repack glyph atlas page {index}, keeping its layout");
+                return false;
+            };
+            slots.push((key, slot));
+        }
+
+        let before = stats.efficiency();
+        for (key, slot) in slots {
+            if let Some((stored, _)) = self.images.get_mut(&key) {
+                *stored = slot;
+            }
+        }
+        if solid.is_some() {
+            self.solid = solid;
+        }
+        self.pages[index].packers = packers;
+        log::info!(
+            "Repacked glyph atlas page {index}, efficiency {:.2} -> {:.2}",
+            before,
+            packing_stats(&self.pages[index].packers).efficiency()
+        );
+
+        self.mark_all_dirty(index);
+        true
+    }
+
+    /// Upload the regions of the textures that changed since the last flush.
+    ///
+    /// Call this after storing glyphs and before rendering.
     #[instrument(skip_all)]
-    fn upload(&self, queue: &Queue, image: &SwashImage, pos: Point) {
-        let (x, y) = (pos.x as u32, pos.y as u32);
-        let (width, height) = (image.placement.width, image.placement.height);
-
-        let bytes_per_pixel = match image.content {
-            SwashContent::Mask => 1,
-            SwashContent::SubpixelMask => panic!("Unsupported Subpixel Mask Image"),
-            SwashContent::Color => 4,
+    pub fn flush(&mut self, queue: &Queue) {
+        for index in 0..self.pages.len() {
+            for rect in std::mem::take(&mut self.pages[index].dirty) {
+                let data = self.compose(index, &rect);
+                self.upload_data(queue, index, &data, &rect);
+            }
+        }
+    }
+
+    /// Mark the region of all rectangles stored in a page as dirty.
+    fn mark_all_dirty(&mut self, page: usize) {
+        self.pages[page].dirty.clear();
+        let stored = self
+            .images
+            .values()
+            .map(|(slot, _)| slot)
+            .chain(&self.solid)
+            .filter(|slot| slot.page == page)
+            .map(|slot| slot.rect);
+        if let Some(bounds) = stored.reduce(|a, b| a.union(&b)) {
+            self.pages[page].dirty.push(bounds);
+        }
+    }
+
+    /// Add a region to the dirty regions of a page, merging it with the regions it's near.
+    fn mark_dirty(&mut self, page: usize, rect: Rectangle) {
+        if rect.is_empty() {
+            return;
+        }
+        let dirty = &mut self.pages[page].dirty;
+        let mut rect = rect;
+        // A merged region may be near regions it was not near before.
+        while let Some(index) = dirty
+            .iter()
+            .position(|dirty| should_merge(dirty, &rect, Self::MERGE_SLACK))
+        {
+            rect = rect.union(&dirty.swap_remove(index));
+        }
+        dirty.push(rect);
+    }
+
+    /// The pixels of the images and the solid block stored inside `rect` of a page. Free space is
+    /// zero.
+    ///
+    /// In channel packed atlases, each image is written to its channel only.
+    fn compose(&self, page: usize, rect: &Rectangle) -> Vec<u8> {
+        let texel = Texel {
+            bytes_per_pixel: self.pages[page].texture.bytes_per_pixel() as usize,
+            channel_packed: self.is_channel_packed(),
         };
+        let mut data = vec![0u8; rect.area() as usize * texel.bytes_per_pixel];
+
+        for (slot, image) in self.images.values().filter(|(slot, _)| slot.page == page) {
+            debug_assert_eq!(image_bytes_per_pixel(image), texel.image_bytes_per_pixel());
+            blit(&mut data, rect, slot, &image.data, texel);
+        }
+        if let Some(solid) = self.solid.filter(|solid| solid.page == page) {
+            let pixels = vec![0xffu8; solid.rect.area() as usize * texel.image_bytes_per_pixel()];
+            blit(&mut data, rect, &solid, &pixels, texel);
+        }
+
+        data
+    }
+
+    fn upload_data(&mut self, queue: &Queue, page: usize, data: &[u8], rect: &Rectangle) {
+        let texture = &self.pages[page].texture;
+        let (x, y) = (rect.min.x as u32, rect.min.y as u32);
+        let (width, height) = (rect.width() as u32, rect.height() as u32);
+        let bytes_per_pixel = texture.bytes_per_pixel();
+        self.uploaded_bytes += data.len() as u64;
 
         queue.write_texture(
             ImageCopyTexture {
-                texture: &self.texture.texture,
+                texture: &texture.texture,
                 mip_level: 0,
                 origin: Origin3d { x, y, z: 0 },
                 aspect: TextureAspect::All,
             },
-            &image.data,
+            data,
             ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(width * bytes_per_pixel),
@@ -164,6 +539,97 @@ impl GlyphAtlas {
     }
 }
 
+/// Whether merging two regions uploads at most `slack` times the pixels of uploading them
+/// separately.
+fn should_merge(a: &Rectangle, b: &Rectangle, slack: f64) -> bool {
+    let merged = a.union(b).area() as f64;
+    merged <= (a.area() + b.area()) as f64 * slack
+}
+
+/// Allocate `size` in the first channel of a page it fits in.
+fn allocate(packers: &mut [SkylinePacker], page: usize, size: Size) -> Option<AtlasSlot> {
+    packers
+        .iter_mut()
+        .enumerate()
+        .find_map(|(channel, packer)| {
+            Some(AtlasSlot {
+                page,
+                rect: packer.allocate(size)?,
+                channel,
+            })
+        })
+}
+
+/// The stats of the packers, summed.
+fn packing_stats<'a>(packers: impl IntoIterator<Item = &'a SkylinePacker>) -> PackingStats {
+    packers
+        .into_iter()
+        .map(|packer| packer.stats())
+        .fold(PackingStats::default(), |sum, stats| PackingStats {
+            capacity: sum.capacity + stats.capacity,
+            occupied: sum.occupied + stats.occupied,
+            used: sum.used + stats.used,
+        })
+}
+
+/// The pixel layout of an atlas texture.
+#[derive(Debug, Clone, Copy)]
+struct Texel {
+    bytes_per_pixel: usize,
+    /// Images are stored in a single channel of the texture.
+    channel_packed: bool,
+}
+
+impl Texel {
+    fn image_bytes_per_pixel(&self) -> usize {
+        if self.channel_packed {
+            1
+        } else {
+            self.bytes_per_pixel
+        }
+    }
+}
+
+/// Copy the part of the image at `slot` that is inside `target`.
+///
+/// `pixels` are the rows of the image, and `data` the rows of `target`, without padding.
+fn blit(data: &mut [u8], target: &Rectangle, slot: &AtlasSlot, pixels: &[u8], texel: Texel) {
+    let source = &slot.rect;
+    let Some(section) = source.intersection(target) else {
+        return;
+    };
+    let source_bytes_per_pixel = texel.image_bytes_per_pixel();
+    let source_stride = source.width() as usize * source_bytes_per_pixel;
+    let target_stride = target.width() as usize * texel.bytes_per_pixel;
+    let from_x = (section.min.x - source.min.x) as usize * source_bytes_per_pixel;
+    let to_x = (section.min.x - target.min.x) as usize * texel.bytes_per_pixel;
+    let width = section.width() as usize;
+    for y in section.min.y..section.max.y {
+        let from = (y - source.min.y) as usize * source_stride + from_x;
+        let to = (y - target.min.y) as usize * target_stride + to_x;
+        if texel.channel_packed {
+            let row = &mut data[to..to + width * texel.bytes_per_pixel];
+            for (pixel, value) in row
+                .chunks_exact_mut(texel.bytes_per_pixel)
+                .zip(&pixels[from..from + width])
+            {
+                pixel[slot.channel] = *value;
+            }
+        } else {
+            let len = width * source_bytes_per_pixel;
+            data[to..to + len].copy_from_slice(&pixels[from..from + len]);
+        }
+    }
+}
+
+fn image_bytes_per_pixel(image: &SwashImage) -> usize {
+    match image.content {
+        SwashContent::Mask => 1,
+        SwashContent::SubpixelMask => panic!("Unsupported Subpixel Mask Image"),
+        SwashContent::Color => 4,
+    }
+}
+
 #[derive(Debug)]
 struct AtlasTexture {
     texture: Texture,
@@ -196,6 +662,13 @@ impl AtlasTexture {
         self.texture.format()
     }
 
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self.format() {
+            TextureFormat::R8Unorm => 1,
+            _ => 4,
+        }
+    }
+
     pub fn dim(&self) -> u32 {
         self.texture.width()
     }
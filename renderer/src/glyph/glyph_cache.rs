@@ -8,8 +8,8 @@ use text::SwashContent;
 
 use super::{glyph_param::GlyphRasterizationParam, glyph_rasterization::render_sdf};
 use crate::{
-    glyph::glyph_rasterization::{pad_image, rasterize_glyph},
-    texture,
+    glyph::glyph_rasterization::{pad_image, rasterize_glyph, rasterize_msdf},
+    texture, FontSource,
 };
 
 #[derive(Default)]
@@ -26,7 +26,7 @@ impl GlyphCache {
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        font_system: &mut text::FontSystem,
+        fonts: &mut dyn FontSource,
         key: RasterizedGlyphKey,
     ) -> Option<&RenderGlyph> {
         self.retainer.insert(key.clone());
@@ -34,7 +34,7 @@ impl GlyphCache {
         match self.cache.entry(key) {
             Entry::Occupied(e) => e.into_mut().as_ref(),
             Entry::Vacant(e) => {
-                let glyph = render_glyph(device, queue, font_system, &mut self.scaler, e.key());
+                let glyph = render_glyph(device, queue, fonts, &mut self.scaler, e.key());
                 e.insert(glyph).as_ref()
             }
         }
@@ -62,12 +62,12 @@ pub struct RenderGlyph {
 fn render_glyph(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
-    font_system: &mut text::FontSystem,
+    fonts: &mut dyn FontSource,
     scale_context: &mut ScaleContext,
     key: &RasterizedGlyphKey,
 ) -> Option<RenderGlyph> {
     // TODO: use rasterize_padded_glyph()!
-    let image = rasterize_glyph(font_system, scale_context, key.text, key.param.swash)?;
+    let image = rasterize_glyph(fonts, scale_context, key.text, key.param.swash)?;
     if image.placement.width == 0 || image.placement.height == 0 {
         return None;
     }
@@ -76,6 +76,20 @@ fn render_glyph(
         return None;
     }
 
+    if key.param.prefer_msdf {
+        let msdf = rasterize_msdf(fonts, scale_context, key.text, key.param.swash)?;
+        let placement = msdf.placement;
+        return Some(RenderGlyph {
+            placement,
+            texture_view: texture::View::from_rgba_data(
+                device,
+                queue,
+                &msdf.data,
+                (placement.width, placement.height),
+            ),
+        });
+    }
+
     // TODO: propagate errors.
     let (placement, texture_view) =
         image_to_gpu_texture(device, queue, &image, key.param.prefer_sdf).ok()?;
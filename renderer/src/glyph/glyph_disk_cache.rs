@@ -0,0 +1,558 @@
+//! A persistent cache of rasterized glyph images.
+//!
+//! The cache is a single append-only file that is memory-mapped when it's opened. New images are
+//! collected in memory and appended when the cache is persisted.
+//!
+//! File layout: The magic bytes followed by a sequence of entries. Each entry consists of a fixed
+//! size header (see [`Entry::HEADER_SIZE`]) and the image data. All numbers are little endian.
+//!
+//! Because font ids are not stable between runs, glyphs are keyed by a hash of the font's data
+//! instead.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+use cosmic_text::{self as text, fontdb};
+use log::{info, warn};
+use memmap2::Mmap;
+use swash::scale::Source;
+use text::SwashContent;
+
+use super::RasterizedGlyphKey;
+use crate::FontSource;
+
+const MAGIC: &[u8; 8] = b"MGLYPH02";
+
+/// A persistent glyph cache backed by a memory-mapped file.
+///
+/// The file must be used by one cache only: The mapping is valid only as long as the file is not
+/// truncated or rewritten, which this cache never does while the file is mapped. Other caches or
+/// processes writing to the same file concurrently are not supported.
+#[derive(Debug)]
+pub struct GlyphDiskCache {
+    path: PathBuf,
+    mmap: Option<Mmap>,
+    /// The entries in the memory-mapped file and their data offsets.
+    entries: HashMap<DiskKey, (Entry, usize)>,
+    /// Images that are not persisted yet.
+    pending: HashMap<DiskKey, (Entry, Vec<u8>)>,
+    font_hashes: HashMap<fontdb::ID, u64>,
+}
+
+/// A glyph key that is stable between runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct DiskKey {
+    font_hash: u64,
+    glyph_id: u16,
+    font_size_bits: u32,
+    x_bin: u8,
+    y_bin: u8,
+    flags: u32,
+    hinted: bool,
+    prefer_sdf: bool,
+    prefer_msdf: bool,
+    weight: u16,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Entry {
+    placement: text::Placement,
+    content: SwashContent,
+    data_len: usize,
+}
+
+impl GlyphDiskCache {
+    /// Open the cache at `path` or start a new one if it does not exist.
+    ///
+    /// A cache file of another format or version, or a truncated one, is removed and a new one is
+    /// written when the cache is persisted. Returns an error if the file exists but can't be read.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut cache = Self {
+            path,
+            mmap: None,
+            entries: HashMap::new(),
+            pending: HashMap::new(),
+            font_hashes: HashMap::new(),
+        };
+        cache.map()?;
+        Ok(cache)
+    }
+
+    /// Memory-map the cache file, if it exists, and read its entries.
+    ///
+    /// If the entries can't be read, the file is removed.
+    fn map(&mut self) -> Result<()> {
+        self.entries.clear();
+        self.mmap = None;
+
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.path)?;
+        // SAFETY: The mapping stays valid as long as the file is not truncated or modified in
+        // place. This cache only appends to the file and maps it again afterwards, and the file is
+        // removed only after the mapping was dropped. See [`GlyphDiskCache`] for why other writers
+        // are not supported.
+        let mmap = unsafe { Mmap::map(&file)? };
+        match read_entries(&mmap) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.mmap = Some(mmap);
+            }
+            Err(e) => {
+                warn!(
+                    "Ignoring glyph cache {}, it will be rewritten: {e:?}",
+                    self.path.display()
+                );
+                drop(mmap);
+                // A file that can't be removed is ignored again when it is mapped the next time.
+                let _ = fs::remove_file(&self.path);
+                return Ok(());
+            }
+        }
+        info!(
+            "Mapped {} cached glyphs from {}",
+            self.entries.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+
+    /// Returns the cached image, `None` if it is not cached.
+    pub fn get(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        key: &RasterizedGlyphKey,
+    ) -> Option<text::SwashImage> {
+        let disk_key = self.disk_key(fonts, key)?;
+
+        if let Some((entry, offset)) = self.entries.get(&disk_key) {
+            let mmap = self.mmap.as_ref()?;
+            return Some(entry.image(mmap[*offset..*offset + entry.data_len].to_vec()));
+        }
+
+        self.pending
+            .get(&disk_key)
+            .map(|(entry, data)| entry.image(data.clone()))
+    }
+
+    /// Add an image to the cache. It is written with the next [`Self::persist`].
+    pub fn insert(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        key: &RasterizedGlyphKey,
+        image: &text::SwashImage,
+    ) {
+        let Some(disk_key) = self.disk_key(fonts, key) else {
+            return;
+        };
+        let entry = Entry {
+            placement: image.placement,
+            content: image.content,
+            data_len: image.data.len(),
+        };
+        self.pending.insert(disk_key, (entry, image.data.clone()));
+    }
+
+    /// Forget the content hashes of fonts whose data was replaced.
+    pub fn forget_fonts(&mut self, fonts: &HashSet<fontdb::ID>) {
+        self.font_hashes.retain(|id, _| !fonts.contains(id));
+    }
+
+    /// Append all new images to the cache file and map it again.
+    pub fn persist(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let is_new = !self.path.exists();
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?,
+        );
+        if is_new {
+            writer.write_all(MAGIC)?;
+        }
+        for (key, (entry, data)) in &self.pending {
+            writer.write_all(&entry.header(key))?;
+            writer.write_all(data)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        info!(
+            "Persisted {} glyphs to {}",
+            self.pending.len(),
+            self.path.display()
+        );
+        self.pending.clear();
+        self.map()
+    }
+
+    fn disk_key(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        key: &RasterizedGlyphKey,
+    ) -> Option<DiskKey> {
+        let cache_key = key.text;
+        let font_hash = match self.font_hashes.get(&cache_key.font_id) {
+            Some(hash) => *hash,
+            None => {
+                let font = fonts.font(cache_key.font_id)?;
+                let hash = fnv1a(font.data());
+                self.font_hashes.insert(cache_key.font_id, hash);
+                hash
+            }
+        };
+
+        Some(DiskKey {
+            font_hash,
+            glyph_id: cache_key.glyph_id,
+            font_size_bits: cache_key.font_size_bits,
+            x_bin: cache_key.x_bin as u8,
+            y_bin: cache_key.y_bin as u8,
+            flags: cache_key.flags.bits(),
+            hinted: key.param.swash.hinted,
+            prefer_sdf: key.param.prefer_sdf,
+            prefer_msdf: key.param.prefer_msdf,
+            weight: key.param.swash.weight.0,
+        })
+    }
+}
+
+impl Drop for GlyphDiskCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.persist() {
+            warn!(
+                "Failed to persist glyph cache {}: {e:?}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+impl Entry {
+    const HEADER_SIZE: usize = 8 + 2 + 4 + 1 + 1 + 4 + 1 + 1 + 1 + 2 + 4 * 4 + 1 + 4;
+
+    fn image(&self, data: Vec<u8>) -> text::SwashImage {
+        text::SwashImage {
+            // The source is not persisted and not used for rendering.
+            source: Source::Outline,
+            content: self.content,
+            placement: self.placement,
+            data,
+        }
+    }
+
+    fn header(&self, key: &DiskKey) -> Vec<u8> {
+        let mut header = Vec::with_capacity(Self::HEADER_SIZE);
+        header.extend(key.font_hash.to_le_bytes());
+        header.extend(key.glyph_id.to_le_bytes());
+        header.extend(key.font_size_bits.to_le_bytes());
+        header.extend([key.x_bin, key.y_bin]);
+        header.extend(key.flags.to_le_bytes());
+        header.extend([
+            key.hinted as u8,
+            key.prefer_sdf as u8,
+            key.prefer_msdf as u8,
+        ]);
+        header.extend(key.weight.to_le_bytes());
+        header.extend(self.placement.left.to_le_bytes());
+        header.extend(self.placement.top.to_le_bytes());
+        header.extend(self.placement.width.to_le_bytes());
+        header.extend(self.placement.height.to_le_bytes());
+        header.push(match self.content {
+            SwashContent::Mask => 0,
+            SwashContent::SubpixelMask => 1,
+            SwashContent::Color => 2,
+        });
+        header.extend((self.data_len as u32).to_le_bytes());
+        debug_assert_eq!(header.len(), Self::HEADER_SIZE);
+        header
+    }
+}
+
+fn read_entries(data: &[u8]) -> Result<HashMap<DiskKey, (Entry, usize)>> {
+    if !data.starts_with(MAGIC) {
+        bail!("Invalid magic or version");
+    }
+
+    let mut entries = HashMap::new();
+    let mut reader = Reader {
+        data,
+        pos: MAGIC.len(),
+    };
+
+    while reader.pos < data.len() {
+        if data.len() - reader.pos < Entry::HEADER_SIZE {
+            bail!("Truncated entry header at {}", reader.pos);
+        }
+        let key = DiskKey {
+            font_hash: u64::from_le_bytes(reader.bytes()),
+            glyph_id: u16::from_le_bytes(reader.bytes()),
+            font_size_bits: u32::from_le_bytes(reader.bytes()),
+            x_bin: reader.byte(),
+            y_bin: reader.byte(),
+            flags: u32::from_le_bytes(reader.bytes()),
+            hinted: reader.byte() != 0,
+            prefer_sdf: reader.byte() != 0,
+            prefer_msdf: reader.byte() != 0,
+            weight: u16::from_le_bytes(reader.bytes()),
+        };
+        let placement = text::Placement {
+            left: i32::from_le_bytes(reader.bytes()),
+            top: i32::from_le_bytes(reader.bytes()),
+            width: u32::from_le_bytes(reader.bytes()),
+            height: u32::from_le_bytes(reader.bytes()),
+        };
+        let content = match reader.byte() {
+            0 => SwashContent::Mask,
+            1 => SwashContent::SubpixelMask,
+            2 => SwashContent::Color,
+            content => bail!("Invalid content type {content}"),
+        };
+        let data_len = u32::from_le_bytes(reader.bytes()) as usize;
+        if data.len() - reader.pos < data_len {
+            bail!("Truncated entry data at {}", reader.pos);
+        }
+
+        let entry = Entry {
+            placement,
+            content,
+            data_len,
+        };
+        entries.insert(key, (entry, reader.pos));
+        reader.pos += data_len;
+    }
+
+    Ok(entries)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.data[self.pos..self.pos + N].try_into().unwrap();
+        self.pos += N;
+        bytes
+    }
+
+    fn byte(&mut self) -> u8 {
+        let [byte] = self.bytes();
+        byte
+    }
+}
+
+/// A hash that is stable between runs and platforms.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glyph::{GlyphRasterizationParam, SwashRasterizationParam};
+
+    fn key(glyph_id: u16) -> DiskKey {
+        DiskKey {
+            font_hash: 0x0123_4567_89ab_cdef,
+            glyph_id,
+            font_size_bits: 16f32.to_bits(),
+            x_bin: 2,
+            y_bin: 0,
+            flags: 1,
+            hinted: true,
+            prefer_sdf: false,
+            prefer_msdf: true,
+            weight: 700,
+        }
+    }
+
+    fn entry(content: SwashContent, data_len: usize) -> Entry {
+        Entry {
+            placement: text::Placement {
+                left: -3,
+                top: 12,
+                width: 7,
+                height: 9,
+            },
+            content,
+            data_len,
+        }
+    }
+
+    /// The magic bytes followed by the entries.
+    fn file(entries: &[(DiskKey, Entry, Vec<u8>)]) -> Vec<u8> {
+        let mut file = MAGIC.to_vec();
+        for (key, entry, data) in entries {
+            file.extend(entry.header(key));
+            file.extend(data);
+        }
+        file
+    }
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "massive-glyph-cache-{name}-{}.cache",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn entries_survive_a_round_trip() {
+        let written = [
+            (key(1), entry(SwashContent::Mask, 3), vec![1, 2, 3]),
+            (key(2), entry(SwashContent::SubpixelMask, 0), vec![]),
+            (key(3), entry(SwashContent::Color, 4), vec![4, 5, 6, 7]),
+        ];
+        let file = file(&written);
+        assert_eq!(file.len(), MAGIC.len() + 3 * Entry::HEADER_SIZE + 3 + 4);
+
+        let entries = read_entries(&file).unwrap();
+        assert_eq!(entries.len(), 3);
+        for (key, written, data) in &written {
+            let (entry, offset) = entries[key];
+            let (placement, expected) = (entry.placement, written.placement);
+            assert_eq!(
+                (
+                    placement.left,
+                    placement.top,
+                    placement.width,
+                    placement.height
+                ),
+                (expected.left, expected.top, expected.width, expected.height)
+            );
+            assert_eq!(entry.content, written.content);
+            assert_eq!(entry.data_len, written.data_len);
+            assert_eq!(&file[offset..offset + entry.data_len], data.as_slice());
+        }
+    }
+
+    #[test]
+    fn empty_files_have_no_entries() {
+        assert!(read_entries(MAGIC).unwrap().is_empty());
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        assert!(read_entries(b"").is_err());
+        assert!(read_entries(b"MGLYPH01").is_err());
+        let mut file = file(&[(key(1), entry(SwashContent::Mask, 1), vec![0])]);
+        file[..MAGIC.len()].copy_from_slice(b"MGLYPH01");
+        assert!(read_entries(&file).is_err());
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let file = file(&[(key(1), entry(SwashContent::Mask, 4), vec![1, 2, 3, 4])]);
+        // In the header and in the data.
+        for len in [MAGIC.len() + 1, MAGIC.len() + Entry::HEADER_SIZE + 2] {
+            assert!(read_entries(&file[..len]).is_err(), "{len}");
+        }
+        assert!(read_entries(&file).is_ok());
+    }
+
+    #[test]
+    fn invalid_content_types_are_rejected() {
+        let mut file = file(&[(key(1), entry(SwashContent::Mask, 0), vec![])]);
+        // The content type precedes the data length.
+        let content = MAGIC.len() + Entry::HEADER_SIZE - 5;
+        assert_eq!(file[content], 0);
+        file[content] = 3;
+        assert!(read_entries(&file).is_err());
+    }
+
+    /// A font system with one font, which gets the same id in every font system.
+    fn font_system(font: &'static [u8]) -> text::FontSystem {
+        let mut db = fontdb::Database::new();
+        db.load_font_source(fontdb::Source::Binary(std::sync::Arc::new(font)));
+        text::FontSystem::new_with_locale_and_db("en-US".into(), db)
+    }
+
+    fn first_font(font_system: &text::FontSystem) -> fontdb::ID {
+        font_system.db().faces().next().unwrap().id
+    }
+
+    #[test]
+    fn forgotten_fonts_are_hashed_again() {
+        let mut montserrat = font_system(include_bytes!(
+            "../../../examples/shared/src/fonts/Montserrat/Montserrat-Regular.ttf"
+        ));
+        let mut jetbrains = font_system(include_bytes!(
+            "../../../examples/shared/src/fonts/JetBrainsMono-2.304/fonts/variable/JetBrainsMono[wght].ttf"
+        ));
+        let font_id = first_font(&montserrat);
+        assert_eq!(first_font(&jetbrains), font_id);
+
+        let glyph_key = RasterizedGlyphKey {
+            text: text::CacheKey::new(font_id, 42, 16.0, (0.0, 0.0), text::CacheKeyFlags::empty())
+                .0,
+            param: GlyphRasterizationParam {
+                prefer_sdf: false,
+                prefer_msdf: false,
+                swash: SwashRasterizationParam {
+                    hinted: false,
+                    weight: swash::Weight::NORMAL,
+                },
+            },
+        };
+
+        let mut cache = GlyphDiskCache::open(cache_path("forget")).unwrap();
+        let before = cache.disk_key(&mut montserrat, &glyph_key).unwrap();
+        // The data of the font changes, but its hash is still known.
+        assert_eq!(cache.disk_key(&mut jetbrains, &glyph_key), Some(before));
+
+        cache.forget_fonts(&HashSet::from([font_id]));
+        let after = cache.disk_key(&mut jetbrains, &glyph_key).unwrap();
+        assert_ne!(after.font_hash, before.font_hash);
+        assert_eq!(
+            after.font_hash,
+            fnv1a(jetbrains.get_font(font_id).unwrap().data())
+        );
+        assert_eq!(
+            DiskKey {
+                font_hash: before.font_hash,
+                ..after
+            },
+            before
+        );
+    }
+
+    #[test]
+    fn invalid_files_are_removed_when_opened() {
+        let path = cache_path("invalid");
+        let valid = file(&[(key(1), entry(SwashContent::Mask, 4), vec![1, 2, 3, 4])]);
+        fs::write(&path, &valid[..valid.len() - 1]).unwrap();
+
+        let cache = GlyphDiskCache::open(&path).unwrap();
+        assert!(cache.entries.is_empty());
+        assert!(!path.exists());
+        drop(cache);
+
+        fs::write(&path, &valid).unwrap();
+        let cache = GlyphDiskCache::open(&path).unwrap();
+        assert_eq!(cache.entries.len(), 1);
+        drop(cache);
+        fs::remove_file(&path).unwrap();
+    }
+}
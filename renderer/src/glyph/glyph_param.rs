@@ -1,10 +1,17 @@
 use super::GlyphClass;
 use crate::primitives::Pipeline;
 
+/// Zoom factors above this are rendered with multi-channel SDFs, which keep the corners of glyphs
+/// sharp.
+const MSDF_ZOOM_THRESHOLD: f64 = 4.0;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct GlyphRasterizationParam {
     // Prefer SDF rasterization if the glyph is monochrome.
     pub prefer_sdf: bool,
+    // Prefer multi-channel SDF rasterization if the glyph is monochrome. Takes precedence over
+    // `prefer_sdf`.
+    pub prefer_msdf: bool,
     pub swash: SwashRasterizationParam,
 }
 
@@ -17,7 +24,9 @@ pub struct SwashRasterizationParam {
 
 impl GlyphRasterizationParam {
     pub fn pipeline(&self) -> Pipeline {
-        if self.prefer_sdf {
+        if self.prefer_msdf {
+            Pipeline::MsdfGlyph
+        } else if self.prefer_sdf {
             Pipeline::SdfGlyph
         } else {
             Pipeline::PlanarGlyph
@@ -29,12 +38,21 @@ impl From<GlyphClass> for GlyphRasterizationParam {
     fn from(class: GlyphClass) -> Self {
         use GlyphClass::*;
         match class {
+            Zoomed(zoom) if zoom > MSDF_ZOOM_THRESHOLD => GlyphRasterizationParam {
+                swash: SwashRasterizationParam {
+                    hinted: true,
+                    weight: Default::default(),
+                },
+                prefer_sdf: false,
+                prefer_msdf: true,
+            },
             Zoomed(_) | PixelPerfect { .. } => GlyphRasterizationParam {
                 swash: SwashRasterizationParam {
                     hinted: true,
                     weight: Default::default(),
                 },
                 prefer_sdf: false,
+                prefer_msdf: false,
             },
             Distorted(_) => GlyphRasterizationParam {
                 swash: SwashRasterizationParam {
@@ -42,6 +60,7 @@ impl From<GlyphClass> for GlyphRasterizationParam {
                     weight: Default::default(),
                 },
                 prefer_sdf: true,
+                prefer_msdf: false,
             },
         }
     }
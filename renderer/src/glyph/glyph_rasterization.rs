@@ -1,27 +1,38 @@
 use cosmic_text as text;
 use swash::{
-    scale::{Render, ScaleContext, Source, StrikeWith},
+    scale::{Render, ScaleContext, Scaler, Source, StrikeWith},
     zeno::{Format, Vector},
 };
 use text::SwashContent;
 
 use super::{
     distance_field_gen::{generate_distance_field_from_image, DISTANCE_FIELD_PAD},
+    msdf_gen::generate_msdf,
     RasterizedGlyphKey, SwashRasterizationParam,
 };
+use crate::FontSource;
 
-/// Rasterize a glyph into [`SwashImage`] as either monochrome, colored, or SDF, with appropriate
-/// padding prepared to be used as a texture.
+/// Rasterize a glyph into [`SwashImage`] as either monochrome, colored, SDF, or MSDF, with
+/// appropriate padding prepared to be used as a texture.
+///
+/// Blank glyphs, like whitespace, are returned without padding, see [`is_blank`].
 ///
 /// TODO: Using this for SDF and non-SDF glyphs may duplicate rasterization of the non-sdf
 /// [`SwashImage`]s that  are the basis for the SDF generation.
 pub fn rasterize_glyph_with_padding(
-    font_system: &mut text::FontSystem,
+    fonts: &mut dyn FontSource,
     context: &mut ScaleContext,
     key: &RasterizedGlyphKey,
 ) -> Option<text::SwashImage> {
     let param = key.param;
-    let without_padding = rasterize_glyph(font_system, context, key.text, param.swash)?;
+    let without_padding = rasterize_glyph(fonts, context, key.text, param.swash)?;
+    if is_blank(&without_padding) {
+        return Some(without_padding);
+    }
+    if without_padding.content == SwashContent::Mask && param.prefer_msdf {
+        // MSDF rendering adds its own padding, too.
+        return rasterize_msdf(fonts, context, key.text, param.swash);
+    }
     if without_padding.content == SwashContent::Mask && param.prefer_sdf {
         // SDF rendering adds its own padding.
         return render_sdf(&without_padding);
@@ -32,16 +43,71 @@ pub fn rasterize_glyph_with_padding(
 }
 
 pub fn rasterize_glyph(
-    font_system: &mut text::FontSystem,
+    fonts: &mut dyn FontSource,
     context: &mut ScaleContext,
     cache_key: text::CacheKey,
     param: SwashRasterizationParam,
 ) -> Option<text::SwashImage> {
+    // Compute the fractional offset -- you'll likely want to quantize this
+    // in a real renderer
+    //
+    // TODO: Is this used? It seems that it's only relevant for subpixel rendering.
+    let offset = Vector::new(cache_key.x_bin.as_float(), cache_key.y_bin.as_float());
+
+    with_scaler(fonts, context, cache_key, param, |scaler| {
+        // Select our source order
+        Render::new(&[
+            // Color outline with the first palette
+            Source::ColorOutline(0),
+            // Color bitmap with best fit selection mode
+            Source::ColorBitmap(StrikeWith::BestFit),
+            // Standard scalable outline
+            Source::Outline,
+        ])
+        // Select a subpixel format
+        .format(Format::Alpha)
+        // Apply the fractional offset
+        .offset(offset)
+        // Render the image
+        .render(scaler, cache_key.glyph_id)
+    })?
+}
+
+/// Rasterize a glyph's outline into an RGBA multi-channel SDF, padded by [`DISTANCE_FIELD_PAD`].
+///
+/// Returns `None` for glyphs without an outline, like bitmap emojis, and for blank glyphs. The
+/// image's content is [`SwashContent::Color`], because it has four channels.
+pub fn rasterize_msdf(
+    fonts: &mut dyn FontSource,
+    context: &mut ScaleContext,
+    cache_key: text::CacheKey,
+    param: SwashRasterizationParam,
+) -> Option<text::SwashImage> {
+    let outline = with_scaler(fonts, context, cache_key, param, |scaler| {
+        scaler.scale_outline(cache_key.glyph_id)
+    })??;
+    let offset = (cache_key.x_bin.as_float(), cache_key.y_bin.as_float());
+    let (data, placement) = generate_msdf(&outline, offset, DISTANCE_FIELD_PAD)?;
+    Some(text::SwashImage {
+        source: Source::Outline,
+        content: SwashContent::Color,
+        placement,
+        data,
+    })
+}
+
+fn with_scaler<R>(
+    fonts: &mut dyn FontSource,
+    context: &mut ScaleContext,
+    cache_key: text::CacheKey,
+    param: SwashRasterizationParam,
+    f: impl FnOnce(&mut Scaler) -> R,
+) -> Option<R> {
     // Copied from cosmic_text/swash.rs, because we might need finer control and don't need a cache.
     // TODO: Find a way to prevent excessive locking of the font system here. Note that it needs to
     // be mutable for font caching (can we implement our own)
 
-    let font = match font_system.get_font(cache_key.font_id) {
+    let font = match fonts.font(cache_key.font_id) {
         Some(some) => some,
         None => {
             log::warn!("did not find font {:?}", cache_key.font_id);
@@ -57,27 +123,12 @@ pub fn rasterize_glyph(
         .variations(&[("wght", param.weight.0 as f32)])
         .build();
 
-    // Compute the fractional offset -- you'll likely want to quantize this
-    // in a real renderer
-    //
-    // TODO: Is this used? It seems that it's only relevant for subpixel rendering.
-    let offset = Vector::new(cache_key.x_bin.as_float(), cache_key.y_bin.as_float());
+    Some(f(&mut scaler))
+}
 
-    // Select our source order
-    Render::new(&[
-        // Color outline with the first palette
-        Source::ColorOutline(0),
-        // Color bitmap with best fit selection mode
-        Source::ColorBitmap(StrikeWith::BestFit),
-        // Standard scalable outline
-        Source::Outline,
-    ])
-    // Select a subpixel format
-    .format(Format::Alpha)
-    // Apply the fractional offset
-    .offset(offset)
-    // Render the image
-    .render(&mut scaler, cache_key.glyph_id)
+/// `true` if the image has no pixels, which is the case for whitespace at all sizes.
+pub fn is_blank(image: &text::SwashImage) -> bool {
+    image.placement.width == 0 || image.placement.height == 0
 }
 
 pub fn render_sdf(image: &text::SwashImage) -> Option<text::SwashImage> {
@@ -0,0 +1,460 @@
+//! Multi-channel signed distance fields (MSDF) generated from glyph outlines.
+//!
+//! A simplified variant of the approach of Viktor Chlumsky's msdfgen: The edges of each contour
+//! are colored so that the two edges that meet at a corner never share all channels. Each channel
+//! stores the distance to the nearest edge of its color, and the median of the three channels
+//! reconstructs the corner, even though every single channel is smooth.
+//!
+//! Curves are flattened, and corners are only detected at the joins of the outline's segments.
+//! Texels where the median disagrees with the fill of the outline are replaced by the true
+//! distance in all channels, which removes most artifacts.
+
+use bitflags::bitflags;
+use swash::{
+    scale::outline::Outline,
+    zeno::{Placement, Verb},
+};
+
+/// The distance in pixels covered on each side of an edge. Matches the encoding of the single
+/// channel distance fields, see `DISTANCE_FIELD_MAGNITUDE`.
+const MSDF_RANGE: f32 = 4.0;
+
+/// Joins of segments with an angle between their directions larger than this (in radians) are
+/// corners.
+const CORNER_ANGLE: f32 = 0.15;
+
+/// The number of lines a curve is flattened to.
+const CURVE_SEGMENTS: usize = 12;
+
+bitflags! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    struct Channels: u8 {
+        const RED = 0b001;
+        const GREEN = 0b010;
+        const BLUE = 0b100;
+        const YELLOW = Self::RED.bits() | Self::GREEN.bits();
+        const MAGENTA = Self::RED.bits() | Self::BLUE.bits();
+        const CYAN = Self::GREEN.bits() | Self::BLUE.bits();
+        const WHITE = Self::RED.bits() | Self::GREEN.bits() | Self::BLUE.bits();
+    }
+}
+
+const CHANNELS: [Channels; 3] = [Channels::RED, Channels::GREEN, Channels::BLUE];
+const CORNER_COLORS: [Channels; 3] = [Channels::CYAN, Channels::MAGENTA, Channels::YELLOW];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Vec2 {
+    x: f32,
+    y: f32,
+}
+
+impl Vec2 {
+    fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+
+    fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    fn cross(self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    fn normalized(self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return Self::new(0.0, 0.0);
+        }
+        Self::new(self.x / length, self.y / length)
+    }
+}
+
+/// A segment of the outline, flattened to a polyline.
+#[derive(Debug)]
+struct Edge {
+    points: Vec<Vec2>,
+    /// The direction at the start of the segment.
+    start_direction: Vec2,
+    /// The direction at the end of the segment.
+    end_direction: Vec2,
+    color: Channels,
+}
+
+impl Edge {
+    fn line(from: Vec2, to: Vec2) -> Self {
+        let direction = to.sub(from);
+        Self {
+            points: vec![from, to],
+            start_direction: direction,
+            end_direction: direction,
+            color: Channels::WHITE,
+        }
+    }
+
+    /// A curve with its control points, including the start and the end point.
+    fn curve(control: &[Vec2]) -> Self {
+        let points = (0..=CURVE_SEGMENTS)
+            .map(|i| de_casteljau(control, i as f32 / CURVE_SEGMENTS as f32))
+            .collect();
+        let (first, last) = (control[0], control[control.len() - 1]);
+        // Control points may coincide with the end points.
+        let start = control[1..].iter().find(|p| **p != first).unwrap_or(&last);
+        let end = control[..control.len() - 1]
+            .iter()
+            .rfind(|p| **p != last)
+            .unwrap_or(&first);
+        Self {
+            points,
+            start_direction: start.sub(first),
+            end_direction: last.sub(*end),
+            color: Channels::WHITE,
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.points.windows(2).map(|w| (w[0], w[1]))
+    }
+}
+
+fn de_casteljau(control: &[Vec2], t: f32) -> Vec2 {
+    let mut points = control.to_vec();
+    for n in (1..points.len()).rev() {
+        for i in 0..n {
+            points[i] = points[i].lerp(points[i + 1], t);
+        }
+    }
+    points[0]
+}
+
+/// The distance to a line, and how the line is oriented relative to the point.
+#[derive(Debug, Copy, Clone)]
+struct LineDistance {
+    /// The unsigned distance to the nearest point of the line.
+    distance: f32,
+    /// `|cos|` of the angle between the line and the direction to the nearest point. Smaller is
+    /// more orthogonal and wins if two lines are at the same distance.
+    obliqueness: f32,
+    /// Positive if the point is left of the line.
+    side: f32,
+    /// The position of the nearest point along the line, unclamped.
+    t: f32,
+}
+
+impl LineDistance {
+    fn new(p: Vec2, (a, b): (Vec2, Vec2)) -> Self {
+        let ab = b.sub(a);
+        let ap = p.sub(a);
+        let length_sq = ab.dot(ab);
+        let t = if length_sq > 0.0 {
+            ap.dot(ab) / length_sq
+        } else {
+            0.0
+        };
+        let nearest = a.lerp(b, t.clamp(0.0, 1.0));
+        let to_point = p.sub(nearest);
+        let obliqueness = if (0.0..=1.0).contains(&t) {
+            0.0
+        } else {
+            ab.normalized().dot(to_point.normalized()).abs()
+        };
+        Self {
+            distance: to_point.length(),
+            obliqueness,
+            side: ab.cross(ap),
+            t,
+        }
+    }
+
+    fn is_closer_than(&self, other: &Self) -> bool {
+        const EPSILON: f32 = 1e-5;
+        if (self.distance - other.distance).abs() <= EPSILON {
+            self.obliqueness < other.obliqueness
+        } else {
+            self.distance < other.distance
+        }
+    }
+}
+
+/// Generate an RGBA MSDF of an outline given in pixels with the y axis pointing up.
+///
+/// The red, green, and blue channels contain the multi-channel distances and the alpha channel
+/// the true distance, all encoded like the single channel distance fields. `pad` pixels are added
+/// on each side.
+///
+/// Returns the image's data and its placement, or `None` if the outline is empty.
+pub fn generate_msdf(
+    outline: &Outline,
+    offset: (f32, f32),
+    pad: usize,
+) -> Option<(Vec<u8>, Placement)> {
+    let contours = contours(outline, offset);
+    let points = contours.iter().flatten().flat_map(|e| e.points.iter());
+
+    let (mut min, mut max) = (Vec2::new(f32::MAX, f32::MAX), Vec2::new(f32::MIN, f32::MIN));
+    for p in points {
+        min = Vec2::new(min.x.min(p.x), min.y.min(p.y));
+        max = Vec2::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    if min.x > max.x || min.y > max.y {
+        return None;
+    }
+
+    let pad = pad as i32;
+    let left = min.x.floor() as i32 - pad;
+    let top = max.y.ceil() as i32 + pad;
+    let width = (max.x.ceil() as i32 + pad - left) as usize;
+    let height = (top - (min.y.floor() as i32 - pad)) as usize;
+
+    // Outer contours of TrueType fonts are clockwise, and of CFF fonts counterclockwise. The
+    // orientation with the larger area is considered filled.
+    let orientation = contours
+        .iter()
+        .map(|c| signed_area(c))
+        .sum::<f32>()
+        .signum();
+
+    let mut data = vec![0u8; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let p = Vec2::new(left as f32 + x as f32 + 0.5, top as f32 - y as f32 - 0.5);
+            let distances = texel_distances(&contours, p, orientation);
+            let texel = &mut data[(y * width + x) * 4..][..4];
+            for (value, distance) in texel.iter_mut().zip(distances) {
+                *value = encode(distance);
+            }
+        }
+    }
+
+    let placement = Placement {
+        left,
+        top,
+        width: width as u32,
+        height: height as u32,
+    };
+    Some((data, placement))
+}
+
+/// The signed distances of a texel center to the colored edges and the true distance, positive
+/// inside.
+fn texel_distances(contours: &[Vec<Edge>], p: Vec2, orientation: f32) -> [f32; 4] {
+    let mut nearest: [Option<(LineDistance, &Edge, usize)>; 3] = [None; 3];
+    let mut true_distance = f32::MAX;
+
+    for edge in contours.iter().flatten() {
+        for (i, line) in edge.lines().enumerate() {
+            let distance = LineDistance::new(p, line);
+            true_distance = true_distance.min(distance.distance);
+            for (channel, nearest) in CHANNELS.iter().zip(nearest.iter_mut()) {
+                if !edge.color.contains(*channel) {
+                    continue;
+                }
+                let closer = match nearest {
+                    Some((n, ..)) => distance.is_closer_than(n),
+                    None => true,
+                };
+                if closer {
+                    *nearest = Some((distance, edge, i));
+                }
+            }
+        }
+    }
+
+    let inside = winding_number(contours, p) != 0;
+    let true_distance = if inside {
+        true_distance
+    } else {
+        -true_distance
+    };
+
+    let [r, g, b] = nearest.map(|nearest| match nearest {
+        Some((distance, edge, i)) => pseudo_distance(p, edge, i, distance) * orientation,
+        // No edge of this color, for example if all edges are white.
+        None => true_distance,
+    });
+
+    // Correct texels where the channels disagree with the fill, for example where edges of
+    // different contours come close to each other.
+    if (median(r, g, b) > 0.0) != inside {
+        return [true_distance; 4];
+    }
+    [r, g, b, true_distance]
+}
+
+/// The signed distance to the nearest line `i` of an edge. Beyond the ends of an edge the
+/// distance is measured to the extension of the edge's first or last line, which keeps the
+/// corners sharp.
+fn pseudo_distance(p: Vec2, edge: &Edge, i: usize, distance: LineDistance) -> f32 {
+    let last = edge.points.len() - 2;
+    let extended = (i == 0 && distance.t < 0.0) || (i == last && distance.t > 1.0);
+    let side = distance.side.signum();
+    if !extended {
+        return side * distance.distance;
+    }
+    let (a, b) = (edge.points[i], edge.points[i + 1]);
+    let direction = b.sub(a).normalized();
+    let perpendicular = direction.cross(p.sub(a)).abs();
+    side * perpendicular
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// Encode a distance in pixels like `generate_distance_field_from_image` does, so that the glyph
+/// shaders can share their constants.
+fn encode(distance: f32) -> u8 {
+    (128.0 + distance * 128.0 / MSDF_RANGE)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
+
+/// The non-zero winding number of the flattened contours around `p`.
+fn winding_number(contours: &[Vec<Edge>], p: Vec2) -> i32 {
+    let mut winding = 0;
+    for (a, b) in contours.iter().flatten().flat_map(|e| e.lines()) {
+        if a.y <= p.y {
+            if b.y > p.y && b.sub(a).cross(p.sub(a)) > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= p.y && b.sub(a).cross(p.sub(a)) < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+fn signed_area(contour: &[Edge]) -> f32 {
+    contour
+        .iter()
+        .flat_map(|e| e.lines())
+        .map(|(a, b)| a.cross(b))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Convert the outline to contours of colored edges.
+fn contours(outline: &Outline, (offset_x, offset_y): (f32, f32)) -> Vec<Vec<Edge>> {
+    let mut points = outline
+        .points()
+        .iter()
+        .map(|p| Vec2::new(p.x + offset_x, p.y + offset_y));
+    let mut next = || points.next().unwrap_or(Vec2::new(0.0, 0.0));
+
+    let mut contours = Vec::new();
+    let mut contour = Vec::new();
+    let mut start = Vec2::new(0.0, 0.0);
+    let mut current = start;
+
+    let mut close = |contour: &mut Vec<Edge>, current: Vec2, start: Vec2| {
+        if current != start {
+            contour.push(Edge::line(current, start));
+        }
+        if !contour.is_empty() {
+            contours.push(color_edges(std::mem::take(contour)));
+        }
+    };
+
+    for verb in outline.verbs() {
+        match verb {
+            Verb::MoveTo => {
+                close(&mut contour, current, start);
+                start = next();
+                current = start;
+            }
+            Verb::LineTo => {
+                let to = next();
+                if to != current {
+                    contour.push(Edge::line(current, to));
+                }
+                current = to;
+            }
+            Verb::QuadTo => {
+                let (control, to) = (next(), next());
+                contour.push(Edge::curve(&[current, control, to]));
+                current = to;
+            }
+            Verb::CurveTo => {
+                let (control1, control2, to) = (next(), next(), next());
+                contour.push(Edge::curve(&[current, control1, control2, to]));
+                current = to;
+            }
+            Verb::Close => {
+                close(&mut contour, current, start);
+                current = start;
+            }
+        }
+    }
+    close(&mut contour, current, start);
+
+    contours
+}
+
+/// Color the edges of a contour, so that the edges at each corner have different colors that
+/// share one channel.
+fn color_edges(mut edges: Vec<Edge>) -> Vec<Edge> {
+    let count = edges.len();
+    let corners: Vec<usize> = (0..count)
+        .filter(|&i| {
+            let previous = &edges[(i + count - 1) % count];
+            is_corner(previous.end_direction, edges[i].start_direction)
+        })
+        .collect();
+
+    match corners.len() {
+        0 => {}
+        1 => {
+            // A teardrop: Split the contour into three parts, starting at the corner.
+            let corner = corners[0];
+            let colors = [Channels::MAGENTA, Channels::WHITE, Channels::YELLOW];
+            for i in 0..count {
+                let part = match count {
+                    1 => 1,
+                    2 => i * 2,
+                    _ => i * 3 / count,
+                };
+                edges[(corner + i) % count].color = colors[part];
+            }
+        }
+        corner_count => {
+            for (n, &corner) in corners.iter().enumerate() {
+                let mut color = CORNER_COLORS[n % 3];
+                // The last part must not have the color of the first.
+                if n == corner_count - 1 && color == CORNER_COLORS[0] {
+                    color = CORNER_COLORS[1];
+                }
+                let end = corners[(n + 1) % corner_count];
+                let mut i = corner;
+                loop {
+                    edges[i].color = color;
+                    i = (i + 1) % count;
+                    if i == end {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+fn is_corner(a: Vec2, b: Vec2) -> bool {
+    let (a, b) = (a.normalized(), b.normalized());
+    a.dot(b) <= 0.0 || a.cross(b).abs() > CORNER_ANGLE.sin()
+}
@@ -0,0 +1,322 @@
+//! A skyline packer for the glyph atlases.
+//!
+//! Glyph images are of similar heights and are rarely freed, which is where skyline packing
+//! wastes the least space. Freed space is not reused until the atlas is repacked, the
+//! [`PackingStats`] tell when that is worth it.
+use etagere::{Rectangle, Size};
+use euclid::point2;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub struct SkylinePacker {
+    size: Size,
+    /// The top edges of the occupied area from left to right. The segments cover the full width
+    /// and neighbors are never at the same height.
+    skyline: Vec<Segment>,
+    /// The area of the allocated rectangles.
+    used: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x: i32,
+    y: i32,
+    width: i32,
+}
+
+/// How well the space of an atlas is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PackingStats {
+    /// The area of the atlas in pixels.
+    pub capacity: u64,
+    /// The area below the skyline, which is not available for new allocations.
+    pub occupied: u64,
+    /// The area of the allocated rectangles.
+    pub used: u64,
+}
+
+impl PackingStats {
+    /// The part of the occupied area that is allocated. `1.0` for an empty atlas.
+    pub fn efficiency(&self) -> f64 {
+        if self.occupied == 0 {
+            return 1.0;
+        }
+        self.used as f64 / self.occupied as f64
+    }
+
+    /// The part of the occupied area that is wasted, either by gaps below the skyline or by
+    /// freed allocations.
+    pub fn fragmentation(&self) -> f64 {
+        1.0 - self.efficiency()
+    }
+
+    /// The part of the atlas that is occupied.
+    pub fn occupancy(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.occupied as f64 / self.capacity as f64
+    }
+}
+
+impl SkylinePacker {
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            skyline: vec![Segment {
+                x: 0,
+                y: 0,
+                width: size.width,
+            }],
+            used: 0,
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Allocate a rectangle of exactly `size`, as low as possible, and then as far left as
+    /// possible. Returns `None` if it does not fit.
+    ///
+    /// Empty sizes are placed at the origin without occupying space.
+    pub fn allocate(&mut self, size: Size) -> Option<Rectangle> {
+        if size.is_empty() {
+            return Some(Rectangle::new(point2(0, 0), point2(0, 0)));
+        }
+
+        let (index, y) = (0..self.skyline.len())
+            .filter_map(|index| Some((index, self.fit(index, size)?)))
+            .min_by_key(|(index, y)| (y + size.height, self.skyline[*index].x))?;
+
+        let min = point2(self.skyline[index].x, y);
+        let rect = Rectangle::new(min, min + size);
+        self.place(index, &rect);
+        self.used += rect.area() as u64;
+        Some(rect)
+    }
+
+    /// Free an allocated rectangle.
+    ///
+    /// The space is reused only after all rectangles are allocated again in a new packer.
+    pub fn deallocate(&mut self, rect: &Rectangle) {
+        self.used -= rect.area() as u64;
+    }
+
+    /// Enlarge the area. Allocated rectangles keep their positions.
+    pub fn grow(&mut self, size: Size) {
+        debug_assert!(size.width >= self.size.width && size.height >= self.size.height);
+        if size.width > self.size.width {
+            self.skyline.push(Segment {
+                x: self.size.width,
+                y: 0,
+                width: size.width - self.size.width,
+            });
+            self.merge_segments();
+        }
+        self.size = size;
+    }
+
+    pub fn stats(&self) -> PackingStats {
+        PackingStats {
+            capacity: self.size.area() as u64,
+            occupied: self
+                .skyline
+                .iter()
+                .map(|segment| segment.width as u64 * segment.y as u64)
+                .sum(),
+            used: self.used,
+        }
+    }
+
+    /// The top of a rectangle of `size` placed at the left of the segment at `index`, if it fits.
+    fn fit(&self, index: usize, size: Size) -> Option<i32> {
+        let x = self.skyline[index].x;
+        if x + size.width > self.size.width {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = size.width;
+        // The segments cover the full width, so this does not run past the last one.
+        for segment in &self.skyline[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment.y);
+            if y + size.height > self.size.height {
+                return None;
+            }
+            remaining -= segment.width;
+        }
+        Some(y)
+    }
+
+    /// Raise the skyline to the top of `rect`, which starts at the segment at `index`.
+    fn place(&mut self, index: usize, rect: &Rectangle) {
+        self.skyline.insert(
+            index,
+            Segment {
+                x: rect.min.x,
+                y: rect.max.y,
+                width: rect.width(),
+            },
+        );
+
+        // Remove or shorten the segments below the rectangle.
+        let right = rect.max.x;
+        let next = index + 1;
+        while next < self.skyline.len() {
+            let segment = &mut self.skyline[next];
+            let segment_right = segment.x + segment.width;
+            if segment_right <= right {
+                self.skyline.remove(next);
+                continue;
+            }
+            if segment.x < right {
+                segment.width = segment_right - right;
+                segment.x = right;
+            }
+            break;
+        }
+
+        self.merge_segments();
+    }
+
+    fn merge_segments(&mut self) {
+        self.skyline.dedup_by(|next, previous| {
+            let same_height = previous.y == next.y;
+            if same_height {
+                previous.width += next.width;
+            }
+            same_height
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::size2;
+
+    use super::*;
+
+    /// Check that the segments cover the full width, and that neighbors differ in height.
+    fn assert_skyline(packer: &SkylinePacker) {
+        let mut x = 0;
+        for (index, segment) in packer.skyline.iter().enumerate() {
+            assert_eq!(segment.x, x);
+            assert!(segment.width > 0);
+            if index > 0 {
+                assert_ne!(packer.skyline[index - 1].y, segment.y);
+            }
+            x += segment.width;
+        }
+        assert_eq!(x, packer.size.width);
+    }
+
+    #[test]
+    fn allocates_lowest_then_leftmost() {
+        let mut packer = SkylinePacker::new(size2(100, 100));
+        let a = packer.allocate(size2(10, 10)).unwrap();
+        let b = packer.allocate(size2(20, 5)).unwrap();
+        let c = packer.allocate(size2(10, 10)).unwrap();
+        assert_eq!(a.min, point2(0, 0));
+        assert_eq!(b.min, point2(10, 0));
+        assert_eq!(c.min, point2(30, 0));
+
+        let d = packer.allocate(size2(40, 10)).unwrap();
+        assert_eq!(d.min, point2(40, 0));
+        // Wider than the dip above `b`, so it rests on top of `a`.
+        let e = packer.allocate(size2(30, 10)).unwrap();
+        assert_eq!(e.min, point2(0, 10));
+        assert_skyline(&packer);
+    }
+
+    #[test]
+    fn allocations_do_not_overlap_and_stay_inside() {
+        let mut packer = SkylinePacker::new(size2(128, 128));
+        let mut rects: Vec<Rectangle> = Vec::new();
+        // A deterministic sequence of glyph like sizes.
+        let mut seed = 7u32;
+        let mut next = |max: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) % max + 1
+        };
+        while let Some(rect) = packer.allocate(size2(next(16) as i32, next(24) as i32)) {
+            assert!(rect.min.x >= 0 && rect.min.y >= 0);
+            assert!(rect.max.x <= 128 && rect.max.y <= 128);
+            for other in &rects {
+                assert!(!rect.intersects(other), "{rect:?} overlaps {other:?}");
+            }
+            rects.push(rect);
+            assert_skyline(&packer);
+        }
+        assert!(rects.len() > 50);
+
+        let used: i32 = rects.iter().map(|rect| rect.area()).sum();
+        let stats = packer.stats();
+        assert_eq!(stats.used, used as u64);
+        assert!(stats.occupied >= stats.used);
+        assert!(stats.occupied <= stats.capacity);
+    }
+
+    #[test]
+    fn rejects_what_does_not_fit() {
+        let mut packer = SkylinePacker::new(size2(32, 32));
+        assert!(packer.allocate(size2(33, 1)).is_none());
+        assert!(packer.allocate(size2(1, 33)).is_none());
+        packer.allocate(size2(32, 30)).unwrap();
+        assert!(packer.allocate(size2(1, 3)).is_none());
+        assert!(packer.allocate(size2(32, 2)).is_some());
+    }
+
+    #[test]
+    fn empty_sizes_do_not_occupy_space() {
+        let mut packer = SkylinePacker::new(size2(32, 32));
+        let rect = packer.allocate(size2(0, 10)).unwrap();
+        assert!(rect.is_empty());
+        assert_eq!(packer.stats().occupied, 0);
+        assert_eq!(packer.stats().used, 0);
+    }
+
+    #[test]
+    fn deallocation_fragments_without_freeing() {
+        let mut packer = SkylinePacker::new(size2(64, 64));
+        let a = packer.allocate(size2(32, 16)).unwrap();
+        packer.allocate(size2(32, 16)).unwrap();
+        assert_eq!(packer.stats().efficiency(), 1.0);
+
+        packer.deallocate(&a);
+        let stats = packer.stats();
+        assert_eq!(stats.used, 32 * 16);
+        assert_eq!(stats.occupied, 64 * 16);
+        assert_eq!(stats.fragmentation(), 0.5);
+        assert_eq!(stats.occupancy(), 0.25);
+        // The freed space is not reused.
+        assert_eq!(packer.allocate(size2(32, 16)).unwrap().min.y, 16);
+    }
+
+    #[test]
+    fn growing_keeps_allocations_and_adds_space() {
+        let mut packer = SkylinePacker::new(size2(32, 32));
+        let a = packer.allocate(size2(32, 32)).unwrap();
+        assert!(packer.allocate(size2(8, 8)).is_none());
+
+        packer.grow(size2(64, 64));
+        assert_skyline(&packer);
+        let b = packer.allocate(size2(32, 64)).unwrap();
+        assert_eq!(b.min, point2(32, 0));
+        let c = packer.allocate(size2(32, 32)).unwrap();
+        assert_eq!(c.min, point2(0, 32));
+        assert!(!a.intersects(&b) && !a.intersects(&c));
+        assert_eq!(packer.stats().occupancy(), 1.0);
+    }
+
+    #[test]
+    fn stats_of_an_empty_packer() {
+        let stats = SkylinePacker::new(size2(16, 16)).stats();
+        assert_eq!(stats.capacity, 256);
+        assert_eq!(stats.efficiency(), 1.0);
+        assert_eq!(stats.occupancy(), 0.0);
+        assert_eq!(PackingStats::default().occupancy(), 0.0);
+    }
+}
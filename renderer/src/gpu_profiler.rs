@@ -0,0 +1,196 @@
+//! GPU zones for the Tracy profiler.
+//!
+//! With the `tracy` feature, a running Tracy client, and a device that supports timestamp
+//! queries, the render passes of every frame are measured and reported as GPU zones. The
+//! timestamps are read back asynchronously, so the zones of a frame appear with a delay of one
+//! or more frames. Frames rendered while the timestamps of a previous frame are not read back
+//! yet are not measured.
+//!
+//! The CPU zones of the preparation stages are `tracing` spans, use `tracing-tracy` to forward
+//! them to the same timeline.
+
+#[cfg(feature = "tracy")]
+use std::{
+    cell::RefCell,
+    mem::size_of,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "tracy")]
+use log::{info, warn};
+#[cfg(feature = "tracy")]
+use tracy_client::{Client, GpuContext, GpuContextType, GpuSpan};
+
+/// The features the device needs to report GPU zones.
+///
+/// Empty if the renderer was built without the `tracy` feature. Request the features the
+/// adapter supports, the renderer does not measure the passes without them.
+pub fn profiling_features() -> wgpu::Features {
+    if cfg!(feature = "tracy") {
+        wgpu::Features::TIMESTAMP_QUERY
+    } else {
+        wgpu::Features::empty()
+    }
+}
+
+/// The outcome of mapping the read buffer, `None` while it's pending.
+#[cfg(feature = "tracy")]
+type Readback = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+#[cfg(feature = "tracy")]
+pub struct GpuProfiler {
+    context: GpuContext,
+    query_set: wgpu::QuerySet,
+    /// The resolved timestamps.
+    resolve_buffer: wgpu::Buffer,
+    /// A copy of the resolved timestamps, mapped to read them back.
+    read_buffer: wgpu::Buffer,
+    /// The zones of the frame that is recorded. `None` if the frame is not measured.
+    frame: RefCell<Option<Vec<GpuSpan>>>,
+    /// The zones of the frame whose timestamps are read back.
+    pending: Vec<GpuSpan>,
+    readback: Readback,
+}
+
+#[cfg(feature = "tracy")]
+impl GpuProfiler {
+    /// The most render passes measured per frame.
+    const MAX_PASSES: u32 = 64;
+
+    /// A profiler that reports to the running Tracy client.
+    ///
+    /// `None` if no client is running or the device does not support timestamp queries.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(profiling_features()) {
+            return None;
+        }
+        let client = Client::running()?;
+        // The zones are aligned by the Tracy server, the first timestamp is unknown here.
+        let context = client
+            .new_gpu_context(
+                Some("massive"),
+                GpuContextType::Invalid,
+                0,
+                queue.get_timestamp_period(),
+            )
+            .map_err(|e| warn!("Failed to create a Tracy GPU context: {e:?}"))
+            .ok()?;
+
+        let count = Self::MAX_PASSES * 2;
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Profiler Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count,
+        });
+        let size = count as u64 * size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Profiler Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Profiler Read Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        info!("Reporting GPU zones to Tracy");
+        Some(Self {
+            context,
+            query_set,
+            resolve_buffer,
+            read_buffer,
+            frame: RefCell::new(None),
+            pending: Vec::new(),
+            readback: Readback::default(),
+        })
+    }
+
+    /// Report the zones of a previous frame if their timestamps are available, and measure the
+    /// next frame if the timestamps can be read back.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        if !self.pending.is_empty() {
+            device.poll(wgpu::Maintain::Poll);
+            let readback = self.readback.lock().ok().and_then(|mut r| r.take());
+            match readback {
+                Some(Ok(())) => self.report_pending(),
+                Some(Err(e)) => {
+                    warn!("Failed to read back timestamps: {e}");
+                    self.pending.clear();
+                }
+                None => {
+                    // Still waiting for the timestamps of a previous frame.
+                    *self.frame.get_mut() = None;
+                    return;
+                }
+            }
+        }
+        *self.frame.get_mut() = Some(Vec::new());
+    }
+
+    /// Allocate a zone for a render pass of the frame, `None` if the frame is not measured.
+    pub fn pass(&self, name: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let mut frame = self.frame.borrow_mut();
+        let zones = frame.as_mut()?;
+        let index = zones.len() as u32;
+        if index == Self::MAX_PASSES {
+            return None;
+        }
+        let mut span = self
+            .context
+            .span_alloc(name, "render_pass", file!(), line!())
+            .ok()?;
+        // The passes are recorded sequentially, the GPU times are uploaded later.
+        span.end_zone();
+        zones.push(span);
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index * 2),
+            end_of_pass_write_index: Some(index * 2 + 1),
+        })
+    }
+
+    /// Resolve the timestamps of the frame and start reading them back.
+    pub fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Some(zones) = self.frame.get_mut().take() else {
+            return;
+        };
+        if zones.is_empty() {
+            return;
+        }
+        let count = zones.len() as u32 * 2;
+        let size = count as u64 * size_of::<u64>() as u64;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Profiler Encoder"),
+        });
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.read_buffer, 0, size);
+        queue.submit([encoder.finish()]);
+
+        let readback = self.readback.clone();
+        self.read_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if let Ok(mut readback) = readback.lock() {
+                    *readback = Some(result);
+                }
+            });
+        self.pending = zones;
+    }
+
+    fn report_pending(&mut self) {
+        let size = self.pending.len() as u64 * 2 * size_of::<u64>() as u64;
+        {
+            let data = self.read_buffer.slice(..size).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            for (span, times) in self.pending.iter().zip(timestamps.chunks_exact(2)) {
+                span.upload_timestamp_start(times[0] as i64);
+                span.upload_timestamp_end(times[1] as i64);
+            }
+        }
+        self.read_buffer.unmap();
+        self.pending.clear();
+    }
+}
@@ -0,0 +1,204 @@
+//! Caching of static layers in textures.
+//!
+//! The world shapes below a cached position are rendered into a texture of the target's size
+//! once per view, and the texture is composited behind the other shapes of the world with every
+//! frame. The texture is rendered again when the view, the matrices of the cached groups, or the
+//! prepared batches change.
+
+use std::mem;
+
+use massive_geometry::Matrix4;
+use massive_scene::Id;
+
+use crate::{
+    bind_group_entries,
+    renderer::Viewport,
+    tools::{create_pipeline, BindGroupLayoutBuilder},
+};
+
+pub struct LayerCache {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    target_format: wgpu::TextureFormat,
+    /// The positions whose world shapes are cached, including the ones of their descendants.
+    roots: Vec<Id>,
+    /// The textures by the index of the view they were rendered for.
+    views: Vec<Option<CachedView>>,
+}
+
+/// What the texture of a view was rendered with. If any of it changes, it's rendered again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheKey {
+    pub size: (u32, u32),
+    pub view_projection_matrix: Matrix4,
+    pub viewport: Option<Viewport>,
+    pub scissor: Option<Viewport>,
+    pub root: Option<Id>,
+    /// The matrices of the cached groups.
+    pub matrices: Vec<Matrix4>,
+}
+
+struct CachedView {
+    key: CacheKey,
+    /// Whether the texture was rendered since it was invalidated.
+    valid: bool,
+    view: wgpu::TextureView,
+    /// Binds the texture to composite it.
+    bind_group: wgpu::BindGroup,
+}
+
+impl LayerCache {
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let bind_group_layout = BindGroupLayoutBuilder::fragment()
+            .texture()
+            .build("Layer Cache Bind Group Layout", device);
+
+        let shader = &crate::shader_module!(device, "layer_cache/composite.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Layer Cache Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The layers are rendered over transparent black, so the texture holds premultiplied
+        // colors.
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let pipeline = create_pipeline(
+            "Layer Cache Pipeline",
+            device,
+            shader,
+            "fs_composite",
+            &[],
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            target_format,
+            roots: Vec::new(),
+            views: Vec::new(),
+        }
+    }
+
+    /// Recreate the pipeline for `target_format`, the cached positions are kept.
+    pub fn target_format_changed(
+        &mut self,
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+    ) {
+        let roots = mem::take(&mut self.roots);
+        *self = Self::new(device, target_format);
+        self.roots = roots;
+    }
+
+    /// Cache the world shapes of `position` and its descendants, or stop caching them. Returns
+    /// `true` if this changed.
+    pub fn set_cached(&mut self, position: Id, cached: bool) -> bool {
+        let index = self.roots.iter().position(|root| *root == position);
+        match (index, cached) {
+            (None, true) => self.roots.push(position),
+            (Some(index), false) => {
+                self.roots.remove(index);
+                if self.roots.is_empty() {
+                    self.release();
+                }
+            }
+            _ => return false,
+        }
+        self.invalidate();
+        true
+    }
+
+    pub fn roots(&self) -> &[Id] {
+        &self.roots
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Render all textures again before they are composited the next time.
+    pub fn invalidate(&mut self) {
+        for view in self.views.iter_mut().flatten() {
+            view.valid = false;
+        }
+    }
+
+    /// Drop the textures.
+    pub fn release(&mut self) {
+        self.views.clear();
+    }
+
+    /// Make sure there is a texture for the view at `index` that was rendered with `key`.
+    ///
+    /// Returns `true` if the texture needs to be rendered.
+    pub fn prepare(&mut self, device: &wgpu::Device, index: usize, key: CacheKey) -> bool {
+        if self.views.len() <= index {
+            self.views.resize_with(index + 1, || None);
+        }
+        let slot = &mut self.views[index];
+        if let Some(view) = slot {
+            if view.key.size == key.size {
+                let current = view.valid && view.key == key;
+                view.key = key;
+                view.valid = true;
+                return !current;
+            }
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Layer Cache"),
+            size: wgpu::Extent3d {
+                width: key.size.0,
+                height: key.size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.target_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Layer Cache Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: bind_group_entries!(0 => &view),
+        });
+        *slot = Some(CachedView {
+            key,
+            valid: true,
+            view,
+            bind_group,
+        });
+        true
+    }
+
+    /// The texture of the view at `index`, `None` before [`Self::prepare`].
+    pub fn target(&self, index: usize) -> Option<&wgpu::TextureView> {
+        self.views
+            .get(index)
+            .and_then(Option::as_ref)
+            .map(|view| &view.view)
+    }
+
+    /// Draw the texture of the view at `index` to the same pixels of the pass, regardless of the
+    /// viewport.
+    pub fn composite<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, index: usize) {
+        let Some(view) = self.views.get(index).and_then(Option::as_ref) else {
+            return;
+        };
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &view.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
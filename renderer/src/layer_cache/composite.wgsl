@@ -0,0 +1,24 @@
+// Composites the texture of cached layers behind the rest of the world.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A triangle that covers the viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 2u), f32((index << 1u) & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var t_cache: texture_2d<f32>;
+
+// The texture has the size of the target, so its pixels are copied to the same pixels of the
+// target, regardless of the viewport.
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(t_cache, vec2<i32>(in.clip_position.xy), 0);
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use static_assertions::const_assert_eq;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use massive_geometry::Color;
+use massive_scene::Id;
+
+use crate::{bind_group_entries, tools::BindGroupLayoutBuilder};
+
+/// Shader parameters of a layer, the shapes placed at one position.
+///
+/// The built-in pipelines bind them at group 1 and apply the tint and the highlight to the colors
+/// of all quads and glyphs of the layer. Changing them does not prepare the layer again, so they
+/// can be animated every frame.
+///
+/// Pipelines of shape extensions can bind the same block with
+/// [`crate::Renderer::layer_bind_group_layout`] and [`crate::RenderContext::layer_bind_group`].
+/// The WGSL declaration is:
+///
+/// ```wgsl
+/// struct Layer {
+///     tint: vec4<f32>,
+///     highlight: vec4<f32>,
+///     // highlight factor, time, pulse frequency, unused
+///     parameters: vec4<f32>,
+///     user: vec4<f32>,
+///     // sharpness, dilation, gamma, set
+///     text: vec4<f32>,
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+pub struct LayerUniforms {
+    /// Multiplied with the colors.
+    pub tint: [f32; 4],
+    /// Mixed into the colors, weighted by its alpha and the highlight factor.
+    pub highlight: [f32; 4],
+    /// The highlight factor, the time in seconds, and the frequency in Hz the highlight pulses
+    /// with. A frequency of zero does not pulse.
+    pub parameters: [f32; 4],
+    /// Not used by the built-in pipelines.
+    pub user: [f32; 4],
+    /// The text rendering of the layer, see [`crate::Renderer::set_layer_text_rendering`]. All
+    /// zero uses the text rendering of the renderer.
+    pub text: [f32; 4],
+}
+
+// WebGL uniform requirement
+const_assert_eq!(size_of::<LayerUniforms>() % 16, 0);
+
+impl Default for LayerUniforms {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 4],
+            highlight: [0.0; 4],
+            parameters: [0.0; 4],
+            user: [0.0; 4],
+            text: [0.0; 4],
+        }
+    }
+}
+
+impl LayerUniforms {
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = color(tint);
+        self
+    }
+
+    /// Mix `highlight` into the colors by `factor`.
+    pub fn with_highlight(mut self, highlight: Color, factor: f32) -> Self {
+        self.highlight = color(highlight);
+        self.parameters[0] = factor;
+        self
+    }
+
+    /// Pulse the highlight with `frequency` in Hz, `time` is the current time in seconds.
+    pub fn with_pulse(mut self, time: f32, frequency: f32) -> Self {
+        self.parameters[1] = time;
+        self.parameters[2] = frequency;
+        self
+    }
+
+    pub fn with_user(mut self, user: [f32; 4]) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Whether the highlight pulses, so that the layer renders differently with every frame.
+    pub fn is_pulsing(&self) -> bool {
+        self.parameters[0] != 0.0 && self.parameters[2] != 0.0
+    }
+}
+
+fn color(color: Color) -> [f32; 4] {
+    [color.red, color.green, color.blue, color.alpha]
+}
+
+/// The uniform buffers and bind groups of the layers that have uniforms set.
+pub struct LayerBindGroups {
+    layout: wgpu::BindGroupLayout,
+    /// Bound for layers without uniforms.
+    default: wgpu::BindGroup,
+    layers: HashMap<Id, Layer>,
+}
+
+impl LayerBindGroups {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = BindGroupLayoutBuilder::fragment()
+            .uniform()
+            .build("Layer Bind Group Layout", device);
+        let (_, default) = Self::create(device, &layout, &LayerUniforms::default());
+        Self {
+            layout,
+            default,
+            layers: HashMap::new(),
+        }
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn set(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        position: Id,
+        uniforms: &LayerUniforms,
+    ) {
+        match self.layers.get_mut(&position) {
+            Some(layer) => {
+                queue.write_buffer(&layer.buffer, 0, bytemuck::bytes_of(uniforms));
+                layer.uniforms = *uniforms;
+            }
+            None => {
+                let (buffer, bind_group) = Self::create(device, &self.layout, uniforms);
+                let layer = Layer {
+                    uniforms: *uniforms,
+                    buffer,
+                    bind_group,
+                };
+                self.layers.insert(position, layer);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, position: Id) {
+        self.layers.remove(&position);
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// The uniforms of a layer, `None` if they are not set.
+    pub fn get(&self, position: Id) -> Option<&LayerUniforms> {
+        self.layers.get(&position).map(|layer| &layer.uniforms)
+    }
+
+    /// The uniforms of all layers that have uniforms set.
+    pub fn uniforms(&self) -> impl Iterator<Item = (Id, &LayerUniforms)> {
+        self.layers.iter().map(|(id, layer)| (*id, &layer.uniforms))
+    }
+
+    pub fn bind_group(&self, position: Id) -> &wgpu::BindGroup {
+        self.layers
+            .get(&position)
+            .map_or(&self.default, |layer| &layer.bind_group)
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniforms: &LayerUniforms,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Layer Uniform Buffer"),
+            contents: bytemuck::bytes_of(uniforms),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Layer Bind Group"),
+            layout,
+            entries: bind_group_entries!(0 => &buffer),
+        });
+        (buffer, bind_group)
+    }
+}
+
+struct Layer {
+    /// A copy of the uniforms in the buffer.
+    uniforms: LayerUniforms,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
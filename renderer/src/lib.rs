@@ -1,21 +1,66 @@
+mod backdrops;
+mod borders;
+mod carets;
 mod color_buffer;
+#[cfg(not(target_arch = "wasm32"))]
+mod conformance;
+mod contrast;
+mod debug;
+mod error;
+mod flash;
+mod focus_rings;
+mod font_service;
+mod format_conversion;
+mod frame_capture;
+mod frame_uniforms;
 mod glyph;
+mod gpu_profiler;
+mod layer_cache;
+mod layer_uniforms;
 mod pipelines;
 mod pods;
+mod present_watchdog;
 mod primitives;
 mod quads;
+mod quality;
 mod renderer;
+mod resample;
+mod rounded_rects;
 mod scene;
+mod shadows;
 mod shape;
+mod shape_extension;
 mod shape_renderer;
 mod size_buffer;
+mod state;
 mod text_layer;
+mod text_rendering;
 mod texture;
 mod tools;
+mod upload_submission;
 
 pub use color_buffer::*;
-pub use renderer::Renderer;
+#[cfg(not(target_arch = "wasm32"))]
+pub use conformance::*;
+pub use contrast::*;
+pub use debug::DebugMode;
+pub use error::*;
+pub use font_service::*;
+pub use format_conversion::{render_format, renders_directly};
+pub use frame_capture::{CapturedFrame, FrameCapture};
+pub use glyph::{MaskAtlasFormat, PackingStats};
+pub use gpu_profiler::profiling_features;
+pub(crate) use layer_uniforms::LayerBindGroups;
+pub use layer_uniforms::LayerUniforms;
+pub use present_watchdog::PresentWatchdog;
+pub use quality::*;
+pub use renderer::{PreparationContext, PreparationStats, RenderContext, Renderer, View, Viewport};
+pub use shape_extension::ShapeExtension;
 pub use shape_renderer::*;
 pub use size_buffer::*;
+pub use state::*;
+pub use text_rendering::*;
+pub use tools::precompiled_shader_features;
+pub use upload_submission::UploadSubmission;
 
 pub use cosmic_text as text;
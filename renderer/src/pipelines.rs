@@ -14,7 +14,8 @@ pub fn create(
     shape_bind_group_layout: &wgpu::BindGroupLayout,
     targets: &[Option<wgpu::ColorTargetState>],
 ) -> Vec<(Pipeline, wgpu::RenderPipeline)> {
-    let glyph_shader = &device.create_shader_module(wgpu::include_wgsl!("texture/glyph.wgsl"));
+    let glyph_shader = &crate::shader_module!(device, "texture/glyph.wgsl");
+    let msdf_glyph_shader = &crate::shader_module!(device, "texture/atlas_msdf.wgsl");
 
     let glyph_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Glyph Pipeline Layout"),
@@ -22,7 +23,7 @@ pub fn create(
         push_constant_ranges: &[],
     });
 
-    let shape_shader = &device.create_shader_module(wgpu::include_wgsl!("shape/shape.wgsl"));
+    let shape_shader = &crate::shader_module!(device, "shape/shape.wgsl");
 
     let shape_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Shape Pipeline Layout"),
@@ -58,6 +59,18 @@ pub fn create(
                 targets,
             ),
         ),
+        (
+            Pipeline::MsdfGlyph,
+            create_pipeline(
+                "MSDF Glyph Pipeline",
+                device,
+                msdf_glyph_shader,
+                "fs_msdf_glyph",
+                &texture_vertex_layout,
+                &glyph_pipeline_layout,
+                targets,
+            ),
+        ),
         (
             Pipeline::Circle,
             create_pipeline(
@@ -4,6 +4,7 @@ use bytemuck::{Pod, Zeroable};
 use static_assertions::const_assert_eq;
 
 use massive_geometry::{Point3, Vector3};
+use massive_shapes::{Pattern, PatternKind, Reveal, RevealEffect};
 use wgpu::{BufferAddress, VertexAttribute, VertexBufferLayout, VertexStepMode};
 
 // We need this for Rust to store our data correctly for the shaders
@@ -15,6 +16,14 @@ pub struct Matrix4(pub [[f32; 4]; 4]);
 // WebGL uniform requirement
 const_assert_eq!(size_of::<Matrix4>() % 16, 0);
 
+/// Values out of the range of `f32` become infinite.
+impl From<&massive_geometry::Matrix4> for Matrix4 {
+    fn from(m: &massive_geometry::Matrix4) -> Self {
+        let m: &[[f64; 4]; 4] = m.as_ref();
+        Self(m.map(|column| column.map(|v| v as f32)))
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
 pub struct TextureSize(pub [f32; 2], pub [u32; 2]);
@@ -69,15 +78,13 @@ impl From<(f32, f32, f32)> for Vertex {
 
 impl From<Point3> for Vertex {
     fn from(v: Point3) -> Self {
-        let v = v.cast::<f32>().expect("Failed to cast Point3 to f32");
-        Self::new(v.x, v.y, v.z)
+        Self::new(v.x as f32, v.y as f32, v.z as f32)
     }
 }
 
 impl From<Vector3> for Vertex {
     fn from(v: Vector3) -> Self {
-        let v = v.cast::<f32>().expect("Failed to cast Point3 to f32");
-        Self::new(v.x, v.y, v.z)
+        Self::new(v.x as f32, v.y as f32, v.z as f32)
     }
 }
 
@@ -136,6 +143,94 @@ impl ColorVertex {
     }
 }
 
+/// A vertex of a quad that may be filled with a [`Pattern`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct PatternVertex {
+    pub position: Vertex,
+    pub color: Color,
+    /// The position in the coordinate system of the pattern, in cells.
+    pub pattern_position: [f32; 2],
+    /// The kind of the pattern, zero for none.
+    pub pattern_kind: f32,
+    pub pattern_color: Color,
+}
+
+impl PatternVertex {
+    pub fn new(position: Vector3, color: impl Into<Color>, pattern: Option<&Pattern>) -> Self {
+        let Some(pattern) = pattern else {
+            return Self {
+                position: position.into(),
+                color: color.into(),
+                pattern_position: [0.0; 2],
+                pattern_kind: 0.0,
+                pattern_color: Color([0.0; 4]),
+            };
+        };
+
+        let (sin, cos) = (-pattern.rotation).sin_cos();
+        let scale = pattern.scale.max(f64::EPSILON);
+        let x = (position.x * cos - position.y * sin) / scale;
+        let y = (position.x * sin + position.y * cos) / scale;
+        let kind = match pattern.kind {
+            PatternKind::Stripes => 1.0,
+            PatternKind::Checkerboard => 2.0,
+            PatternKind::Dots => 3.0,
+        };
+        Self {
+            position: position.into(),
+            color: color.into(),
+            pattern_position: [x as f32, y as f32],
+            pattern_kind: kind,
+            pattern_color: pattern.color.into(),
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [VertexAttribute; 5] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x4, 2 => Float32x2, 3 => Float32, 4 => Float32x4
+        ];
+
+        VertexBufferLayout {
+            array_stride: size_of::<PatternVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
+/// A vertex of a quad of a dashed line.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct DashVertex {
+    pub position: Vertex,
+    pub color: Color,
+    /// The position along the line in dash periods, the part of a period that is covered by the
+    /// dash, and the periods the dashes move per second.
+    pub dash: [f32; 3],
+}
+
+impl DashVertex {
+    pub fn new(position: impl Into<Vertex>, color: impl Into<Color>, dash: [f32; 3]) -> Self {
+        Self {
+            position: position.into(),
+            color: color.into(),
+            dash,
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [VertexAttribute; 3] =
+            wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4, 2 => Float32x3];
+
+        VertexBufferLayout {
+            array_stride: size_of::<DashVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct TextureColorVertex {
@@ -166,6 +261,173 @@ impl TextureColorVertex {
     }
 }
 
+/// A vertex of an SDF glyph quad.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct SdfGlyphVertex {
+    pub position: Vertex,
+    pub tex_coords: [f32; 2],
+    pub color: Color3,
+    /// Selects the channel of the atlas texture the distance field is stored in, see
+    /// `AtlasSlot::channel_mask`.
+    pub channel: [f32; 4],
+}
+
+impl SdfGlyphVertex {
+    pub fn new(
+        position: impl Into<Vertex>,
+        uv: (f32, f32),
+        color: impl Into<Color3>,
+        channel: [f32; 4],
+    ) -> Self {
+        Self {
+            position: position.into(),
+            tex_coords: [uv.0, uv.1],
+            color: color.into(),
+            channel,
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        // Locations 3 and 4 are used by the `RevealVertex`.
+        const ATTRS: [VertexAttribute; 4] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 5 => Float32x4
+        ];
+
+        VertexBufferLayout {
+            array_stride: size_of::<SdfGlyphVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
+/// A vertex of a textured quad with an alpha-blended color, used for shadows.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct TextureAlphaColorVertex {
+    pub position: Vertex,
+    pub tex_coords: [f32; 2],
+    pub color: Color,
+}
+
+impl TextureAlphaColorVertex {
+    pub fn new(position: impl Into<Vertex>, uv: (f32, f32), color: impl Into<Color>) -> Self {
+        Self {
+            position: position.into(),
+            tex_coords: [uv.0, uv.1],
+            color: color.into(),
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [VertexAttribute; 3] =
+            wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4];
+
+        VertexBufferLayout {
+            array_stride: size_of::<TextureAlphaColorVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
+/// A vertex of a rounded rectangle whose edges are computed in the fragment shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct RoundedRectVertex {
+    pub position: Vertex,
+    /// The position relative to the center of the rectangle.
+    pub local: [f32; 2],
+    pub half_size: [f32; 2],
+    pub radius: f32,
+    pub color: Color,
+}
+
+impl RoundedRectVertex {
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [VertexAttribute; 5] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x2, 2 => Float32x2, 3 => Float32, 4 => Float32x4
+        ];
+
+        VertexBufferLayout {
+            array_stride: size_of::<RoundedRectVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
+/// A vertex of a [`massive_shapes::RoundedRect`] with per-corner radii and a border, whose edges
+/// are computed in the fragment shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct PanelVertex {
+    pub position: Vertex,
+    /// The position relative to the center of the rectangle.
+    pub local: [f32; 2],
+    pub half_size: [f32; 2],
+    /// Top left, top right, bottom right, bottom left.
+    pub radii: [f32; 4],
+    pub fill: Color,
+    pub border_color: Color,
+    pub border_width: f32,
+}
+
+impl PanelVertex {
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [VertexAttribute; 7] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x2, 2 => Float32x2, 3 => Float32x4, 4 => Float32x4,
+            5 => Float32x4, 6 => Float32
+        ];
+
+        VertexBufferLayout {
+            array_stride: size_of::<PanelVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
+/// The reveal animation of a glyph quad, passed in a second vertex buffer of the text pipelines.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct RevealVertex {
+    /// The start time, the duration, the effect, and the slide distance. An effect of zero is not
+    /// animated.
+    pub reveal: [f32; 4],
+    /// The center of the glyph, scale-in grows from here.
+    pub center: [f32; 2],
+}
+
+impl RevealVertex {
+    pub fn new(reveal: Option<&Reveal>, index: usize, center: Point3) -> Self {
+        let Some(reveal) = reveal else {
+            return Self::default();
+        };
+        let (effect, distance) = match reveal.effect {
+            RevealEffect::Fade => (1.0, 0.0),
+            RevealEffect::SlideUp { distance } => (2.0, distance),
+            RevealEffect::ScaleIn => (3.0, 0.0),
+        };
+        Self {
+            reveal: [reveal.glyph_start(index), reveal.duration, effect, distance],
+            center: [center.x as f32, center.y as f32],
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [VertexAttribute; 2] =
+            wgpu::vertex_attr_array![3 => Float32x4, 4 => Float32x2];
+
+        VertexBufferLayout {
+            array_stride: size_of::<RevealVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &ATTRS,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
 pub struct Color3(pub [f32; 3]);
@@ -0,0 +1,74 @@
+//! Detection of stalled surface presentation.
+//!
+//! On some drivers, acquiring the surface texture blocks for seconds or times out on every
+//! frame. The [`PresentWatchdog`] is fed with the outcome and the duration of each frame and
+//! tells when the surface should be recreated.
+
+use std::time::Duration;
+
+use log::warn;
+
+use crate::RenderError;
+
+#[derive(Debug, Clone)]
+pub struct PresentWatchdog {
+    /// Frames that take longer than this to render and present are considered stalled.
+    stall_after: Duration,
+    /// Number of consecutive timeouts until presentation is considered stalled.
+    max_timeouts: usize,
+    timeouts: usize,
+    /// The number of stalls detected so far.
+    stalls: usize,
+}
+
+impl Default for PresentWatchdog {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(2), 3)
+    }
+}
+
+impl PresentWatchdog {
+    pub fn new(stall_after: Duration, max_timeouts: usize) -> Self {
+        Self {
+            stall_after,
+            max_timeouts: max_timeouts.max(1),
+            timeouts: 0,
+            stalls: 0,
+        }
+    }
+
+    /// Record a frame with its `result` and the time it took to render and present it, if it
+    /// was measured.
+    ///
+    /// Returns [`RenderError::PresentStalled`] if the surface should be recreated.
+    pub fn record(
+        &mut self,
+        result: &Result<(), RenderError>,
+        duration: Option<Duration>,
+    ) -> Option<RenderError> {
+        if matches!(
+            result,
+            Err(RenderError::Surface(wgpu::SurfaceError::Timeout))
+        ) {
+            self.timeouts += 1;
+        } else {
+            self.timeouts = 0;
+        }
+
+        let duration = duration.unwrap_or_default();
+        if self.timeouts < self.max_timeouts && duration <= self.stall_after {
+            return None;
+        }
+
+        let timeouts = self.timeouts;
+        self.timeouts = 0;
+        self.stalls += 1;
+        warn!("Presentation stalled: {duration:?}, {timeouts} consecutive timeouts");
+        Some(RenderError::PresentStalled { duration, timeouts })
+    }
+
+    /// The number of stalls detected since the watchdog was created.
+    pub fn stalls(&self) -> usize {
+        self.stalls
+    }
+}
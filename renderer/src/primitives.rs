@@ -25,6 +25,7 @@ impl Primitive {
 pub enum Pipeline {
     PlanarGlyph,
     SdfGlyph,
+    MsdfGlyph,
     TextLayer,
     Circle,
     RoundedRect,
@@ -6,11 +6,19 @@ var<uniform> model_view: mat4x4<f32>;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec4<f32>,
+    // The position in cells of the pattern.
+    @location(2) pattern_position: vec2<f32>,
+    // 0: none, 1: stripes, 2: checkerboard, 3: dots.
+    @location(3) pattern_kind: f32,
+    @location(4) pattern_color: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(1) @interpolate(flat) color: vec4<f32>,
+    @location(2) pattern_position: vec2<f32>,
+    @location(3) @interpolate(flat) pattern_kind: f32,
+    @location(4) @interpolate(flat) pattern_color: vec4<f32>,
 }
 
 @vertex
@@ -20,12 +28,65 @@ fn vs_main(
     var out: VertexOutput;
     out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
     out.color = vertex_input.color;
+    out.pattern_position = vertex_input.pattern_position;
+    out.pattern_kind = vertex_input.pattern_kind;
+    out.pattern_color = vertex_input.pattern_color;
     return out;
 }
 
 // Fragment shader
 
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> layer: Layer;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+// 1 in the middle half of each cell along an axis, anti-aliased over a pixel.
+fn stripe(x: f32) -> f32 {
+    let edge = abs(fract(x) - 0.5) * 2.0;
+    return clamp((0.5 - edge) / max(2.0 * fwidth(x), 0.0001) + 0.5, 0.0, 1.0);
+}
+
+// The coverage of the pattern's color.
+fn pattern_coverage(kind: f32, p: vec2<f32>) -> f32 {
+    // All patterns are computed, because derivatives require uniform control flow.
+    let stripes = stripe(p.x);
+    let sy = stripe(p.y);
+    let checkerboard = stripes + sy - 2.0 * stripes * sy;
+    let distance = length(fract(p) - vec2<f32>(0.5));
+    let dots = clamp((0.25 - distance) / max(fwidth(distance), 0.0001) + 0.5, 0.0, 1.0);
+
+    switch i32(kind) {
+        case 1: { return stripes; }
+        case 2: { return checkerboard; }
+        case 3: { return dots; }
+        default: { return 0.0; }
+    }
+}
+
 @fragment
 fn fs_quad(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    let coverage = pattern_coverage(in.pattern_kind, in.pattern_position);
+    return layer_color(mix(in.color, in.pattern_color, coverage));
 }
\ No newline at end of file
@@ -8,7 +8,8 @@ use wgpu::{
 };
 
 use crate::{
-    pods::ColorVertex,
+    debug::DebugPipelines,
+    pods::PatternVertex,
     renderer::{PreparationContext, RenderContext},
     tools::{create_pipeline, QuadIndexBuffer},
 };
@@ -16,11 +17,14 @@ use crate::{
 pub struct QuadsRenderer {
     pipeline: wgpu::RenderPipeline,
     index_buffer: QuadIndexBuffer,
+    debug: DebugPipelines,
 
     layers: Vec<QuadsLayer>,
 }
 
 struct QuadsLayer {
+    /// The index of the shape group this layer was prepared from.
+    group: usize,
     model_matrix: Matrix4,
     vertex_buffer: wgpu::Buffer,
     quad_count: usize,
@@ -31,12 +35,13 @@ impl QuadsRenderer {
         device: &wgpu::Device,
         target_format: wgpu::TextureFormat,
         view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Self {
-        let shader = &device.create_shader_module(wgpu::include_wgsl!("quads.wgsl"));
+        let shader = &crate::shader_module!(device, "quads/quads.wgsl");
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Quads Pipeline Layout"),
-            bind_group_layouts: &[view_projection_bind_group_layout],
+            bind_group_layouts: &[view_projection_bind_group_layout, layer_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -46,7 +51,7 @@ impl QuadsRenderer {
             write_mask: wgpu::ColorWrites::ALL,
         })];
 
-        let vertex_layout = [ColorVertex::layout()];
+        let vertex_layout = [PatternVertex::layout()];
 
         let pipeline = create_pipeline(
             "Quads Pipeline",
@@ -61,26 +66,42 @@ impl QuadsRenderer {
         Self {
             pipeline,
             index_buffer: QuadIndexBuffer::new(device),
+            debug: DebugPipelines::new(
+                device,
+                target_format,
+                view_projection_bind_group_layout,
+                PatternVertex::layout().array_stride,
+            ),
             layers: Vec::new(),
         }
     }
 
+    /// Drop all prepared layers.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Prepare shape groups and add them to the prepared layers.
+    ///
+    /// `first_group` is the index of the first group in `shapes` among all groups prepared since
+    /// the last [`Self::clear`].
+    #[tracing::instrument(name = "prepare_quads", skip_all)]
     pub fn prepare(
         &mut self,
         context: &mut PreparationContext,
+        first_group: usize,
         shapes: &[(Matrix4, &[&Shape])],
     ) -> Result<()> {
-        self.layers.clear();
-
         let mut max_quads = 0;
 
-        for (matrix, shapes) in shapes {
+        for (group, (matrix, shapes)) in (first_group..).zip(shapes) {
             if let Some(quads_layer) = self.prepare_quads(
                 context,
+                group,
                 matrix,
                 shapes.iter().filter_map(|s| match s {
-                    Shape::GlyphRun(_) => None,
                     Shape::Quads(quads) => Some(quads),
+                    _ => None,
                 }),
             )? {
                 max_quads = max_quads.max(quads_layer.quad_count);
@@ -90,11 +111,35 @@ impl QuadsRenderer {
 
         self.index_buffer
             .ensure_can_index_num_quads(context.device, max_quads);
+        self.debug.prepare(context.device, max_quads);
 
         Ok(())
     }
 
+    /// Update the model matrices of the prepared layers without preparing them again.
+    ///
+    /// `matrices` must be in the order of the shape groups passed to [`Self::prepare`].
+    pub fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for layer in &mut self.layers {
+            layer.model_matrix = matrices[layer.group];
+        }
+    }
+
     pub fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        if context.debug_mode.renders_scene() {
+            self.render_layers(context);
+        }
+
+        let batches: Vec<_> = self
+            .layers
+            .iter()
+            .filter(|l| context.renders_group(l.group))
+            .map(|l| (l.model_matrix, l.vertex_buffer.slice(..), l.quad_count))
+            .collect();
+        self.debug.render(context, &self.index_buffer, &batches);
+    }
+
+    fn render_layers<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
         let pass = &mut context.pass;
         pass.set_pipeline(&self.pipeline);
         // DI: May do this inside this renderer and pass a Matrix to prepare?.
@@ -103,18 +148,22 @@ impl QuadsRenderer {
         pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
         for QuadsLayer {
+            group,
             model_matrix,
             vertex_buffer,
             quad_count,
         } in &self.layers
         {
-            let text_layer_matrix = context.view_projection_matrix * model_matrix;
-
+            if !context.renders_group(*group) {
+                continue;
+            }
             // OO: Set bind group only once and update the buffer?
-            context.queue_view_projection_matrix(&text_layer_matrix);
+            context.queue_model_matrix(model_matrix);
 
+            let layer_bind_group = context.layer_bind_group(*group);
             let pass = &mut context.pass;
             pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_group, &[]);
 
             pass.set_vertex_buffer(0, vertex_buffer.slice(..));
 
@@ -129,6 +178,7 @@ impl QuadsRenderer {
     fn prepare_quads<'a>(
         &mut self,
         context: &mut PreparationContext,
+        group: usize,
         model_matrix: &Matrix4,
         // TODO: this double reference is quite unusual here
         // TODO: flatten!
@@ -144,14 +194,16 @@ impl QuadsRenderer {
             for Quad {
                 vertices: qv,
                 color,
+                pattern,
             } in quads
             {
-                vertices.extend([
-                    ColorVertex::new(qv[0], *color),
-                    ColorVertex::new(qv[1], *color),
-                    ColorVertex::new(qv[2], *color),
-                    ColorVertex::new(qv[3], *color),
-                ]);
+                // Patterns reduce the contrast of the text on top of them, so they are left out
+                // in high contrast mode.
+                let (color, pattern) = match &context.high_contrast {
+                    Some(high_contrast) => (high_contrast.fill(*color), None),
+                    None => (*color, pattern.as_ref()),
+                };
+                vertices.extend(qv.map(|v| PatternVertex::new(v, color, pattern)));
             }
         }
 
@@ -168,6 +220,7 @@ impl QuadsRenderer {
         });
 
         let quads_layer = QuadsLayer {
+            group,
             model_matrix: *model_matrix,
             vertex_buffer,
             quad_count: vertices.len() >> 2,
@@ -0,0 +1,261 @@
+//! Adaptive rendering quality.
+//!
+//! The [`QualityController`] is fed with frame times and steps through the quality levels of a
+//! [`QualityPolicy`]: It degrades when frames repeatedly miss the target frame time and restores
+//! quality when there is enough headroom again.
+//!
+//! Multisampling is not one of the quality settings: The renderer does not multisample, text is
+//! anti-aliased by its distance fields and coverage masks and the other shapes by their shaders.
+//! So there is no MSAA a quality level could turn off, and adding it only to turn it off under
+//! load would make every pipeline depend on the sample count.
+
+use std::time::Duration;
+
+use massive_geometry::scalar;
+use serde::{Deserialize, Serialize};
+
+/// Quality settings the renderer honors while preparing a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quality {
+    /// Glyph runs that are smaller than this height in physical pixels are not rasterized glyph by
+    /// glyph, they are rendered as a single bar instead ("greeking").
+    ///
+    /// The height is measured with the camera of the last rendered view, see
+    /// [`crate::Renderer::render_views_and_present`].
+    pub greeking_threshold: Option<scalar>,
+    /// Glyphs are rasterized at this fraction of their size and scaled up when they are rendered.
+    ///
+    /// This saves rasterization time and atlas space. Distance fields keep the outlines smooth
+    /// when they are scaled up, but fine details get lost. Color glyphs are scaled up, too. `1`
+    /// rasterizes glyphs at their size.
+    pub sdf_downscale: u32,
+}
+
+impl Quality {
+    pub const FULL: Self = Self {
+        greeking_threshold: None,
+        sdf_downscale: 1,
+    };
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QualityPolicy {
+    /// The frame time to maintain.
+    pub target_frame_time: Duration,
+    /// The fraction of the target frame time below which a frame is considered to have
+    /// headroom.
+    pub headroom: f64,
+    /// Number of consecutive frames over the target until quality is degraded.
+    pub degrade_after: usize,
+    /// Number of consecutive frames with headroom until quality is restored.
+    pub restore_after: usize,
+    /// The quality levels, ordered from the best to the lowest.
+    pub levels: Vec<Quality>,
+}
+
+impl Default for QualityPolicy {
+    fn default() -> Self {
+        Self {
+            target_frame_time: Duration::from_micros(16_667),
+            headroom: 0.6,
+            degrade_after: 5,
+            restore_after: 60,
+            levels: vec![
+                Quality::FULL,
+                Quality {
+                    greeking_threshold: Some(4.0),
+                    sdf_downscale: 1,
+                },
+                Quality {
+                    greeking_threshold: Some(8.0),
+                    sdf_downscale: 2,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct QualityController {
+    policy: QualityPolicy,
+    level: usize,
+    frames_over: usize,
+    frames_with_headroom: usize,
+}
+
+impl QualityController {
+    pub fn new(policy: QualityPolicy) -> Self {
+        assert!(
+            !policy.levels.is_empty(),
+            "A quality policy needs at least one level"
+        );
+        Self {
+            policy,
+            level: 0,
+            frames_over: 0,
+            frames_with_headroom: 0,
+        }
+    }
+
+    pub fn policy(&self) -> &QualityPolicy {
+        &self.policy
+    }
+
+    /// The index of the current quality level, `0` is the best.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.policy.levels[self.level]
+    }
+
+    /// Record the time it took to produce a frame.
+    ///
+    /// Returns the new quality if the level changed.
+    pub fn record_frame(&mut self, frame_time: Duration) -> Option<Quality> {
+        let target = self.policy.target_frame_time;
+
+        if frame_time > target {
+            self.frames_over += 1;
+            self.frames_with_headroom = 0;
+        } else if frame_time.as_secs_f64() < target.as_secs_f64() * self.policy.headroom {
+            self.frames_with_headroom += 1;
+            self.frames_over = 0;
+        } else {
+            self.frames_over = 0;
+            self.frames_with_headroom = 0;
+        }
+
+        if self.frames_over >= self.policy.degrade_after
+            && self.level + 1 < self.policy.levels.len()
+        {
+            return Some(self.switch_to(self.level + 1));
+        }
+
+        if self.frames_with_headroom >= self.policy.restore_after && self.level > 0 {
+            return Some(self.switch_to(self.level - 1));
+        }
+
+        None
+    }
+
+    fn switch_to(&mut self, level: usize) -> Quality {
+        log::info!("Switching quality level from {} to {level}", self.level);
+        self.level = level;
+        self.frames_over = 0;
+        self.frames_with_headroom = 0;
+        self.quality()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Duration = Duration::from_millis(10);
+    const SLOW: Duration = Duration::from_millis(12);
+    /// Below the headroom of 60% of the target.
+    const FAST: Duration = Duration::from_millis(5);
+    /// Neither over the target nor with headroom.
+    const STEADY: Duration = Duration::from_millis(8);
+
+    fn controller() -> QualityController {
+        QualityController::new(QualityPolicy {
+            target_frame_time: TARGET,
+            headroom: 0.6,
+            degrade_after: 3,
+            restore_after: 4,
+            ..QualityPolicy::default()
+        })
+    }
+
+    /// Record `count` frames and return the level changes.
+    fn record(controller: &mut QualityController, frame_time: Duration, count: usize) -> usize {
+        (0..count)
+            .filter(|_| controller.record_frame(frame_time).is_some())
+            .count()
+    }
+
+    #[test]
+    fn degrades_after_consecutive_slow_frames() {
+        let mut controller = controller();
+        assert_eq!(record(&mut controller, SLOW, 2), 0);
+        assert_eq!(
+            controller.record_frame(SLOW),
+            Some(controller.policy().levels[1])
+        );
+        assert_eq!(controller.level(), 1);
+
+        // The count starts over after a change.
+        assert_eq!(record(&mut controller, SLOW, 2), 0);
+        assert_eq!(controller.level(), 1);
+    }
+
+    #[test]
+    fn frames_within_the_target_reset_the_count() {
+        let mut controller = controller();
+        for frame_time in [SLOW, SLOW, STEADY, SLOW, SLOW, FAST, SLOW, SLOW] {
+            assert_eq!(controller.record_frame(frame_time), None);
+        }
+        assert_eq!(controller.level(), 0);
+        // Exactly the target is not over it.
+        assert_eq!(record(&mut controller, TARGET, 10), 0);
+    }
+
+    #[test]
+    fn restores_after_consecutive_frames_with_headroom() {
+        let mut controller = controller();
+        record(&mut controller, SLOW, 6);
+        assert_eq!(controller.level(), 2);
+
+        assert_eq!(record(&mut controller, FAST, 3), 0);
+        assert_eq!(
+            controller.record_frame(FAST),
+            Some(controller.policy().levels[1])
+        );
+        // Steady frames neither restore nor degrade.
+        assert_eq!(record(&mut controller, FAST, 3), 0);
+        assert_eq!(record(&mut controller, STEADY, 1), 0);
+        assert_eq!(record(&mut controller, FAST, 3), 0);
+        assert_eq!(controller.level(), 1);
+        assert_eq!(record(&mut controller, FAST, 1), 1);
+        assert_eq!(controller.quality(), Quality::FULL);
+    }
+
+    #[test]
+    fn stays_within_the_levels() {
+        let mut controller = controller();
+        assert_eq!(record(&mut controller, FAST, 100), 0);
+        assert_eq!(controller.level(), 0);
+
+        assert_eq!(record(&mut controller, SLOW, 100), 2);
+        assert_eq!(controller.level(), 2);
+        assert_eq!(controller.quality(), controller.policy().levels[2]);
+    }
+
+    #[test]
+    fn a_single_level_never_changes() {
+        let mut controller = QualityController::new(QualityPolicy {
+            levels: vec![Quality::FULL],
+            ..QualityPolicy::default()
+        });
+        assert_eq!(record(&mut controller, Duration::from_secs(1), 100), 0);
+        assert_eq!(record(&mut controller, Duration::ZERO, 100), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn a_policy_needs_levels() {
+        QualityController::new(QualityPolicy {
+            levels: Vec::new(),
+            ..QualityPolicy::default()
+        });
+    }
+}
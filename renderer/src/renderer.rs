@@ -1,31 +1,88 @@
 use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
     mem::{self},
     result,
+    time::Duration,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use cgmath::{InnerSpace, SquareMatrix, Transform};
 use log::info;
-use massive_geometry::Matrix4;
-use massive_scene::SceneChange;
-use wgpu::StoreOp;
+use massive_geometry::{Bounds, Bounds3, Color, Matrix4, Point3, UnitSystem, Vector3};
+use massive_scene::{Change, Id, PositionedRenderShape, SceneChange, Shape};
+use massive_shapes::GlyphRun;
+use wgpu::{util::RenderEncoder, StoreOp};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::glyph::GlyphDiskCache;
+#[cfg(feature = "tracy")]
+use crate::gpu_profiler::GpuProfiler;
 use crate::{
-    pipelines, pods, quads::QuadsRenderer, scene::Scene, text, text_layer::TextLayerRenderer,
-    texture,
+    backdrops::BackdropRenderer,
+    borders::BorderRenderer,
+    carets::CaretRenderer,
+    contrast,
+    flash::FlashRenderer,
+    focus_rings::FocusRingRenderer,
+    format_conversion::FormatConversion,
+    frame_uniforms::FrameBindGroup,
+    layer_cache::{CacheKey, LayerCache},
+    pipelines, pods,
+    quads::QuadsRenderer,
+    resample::Resampler,
+    rounded_rects::RoundedRectRenderer,
+    scene::{Scene, Transaction},
+    shadows::ShadowRenderer,
+    shape_extension::{Extension, ShapeExtension},
+    text,
+    text_layer::TextLayerRenderer,
+    texture, AtlasMetadata, DebugMode, FontSource, FrameCapture, HighContrast, LayerBindGroups,
+    LayerUniforms, MaskAtlasFormat, Quality, RenderError, RendererConfig, RendererState,
+    TextRendering, UploadSubmission,
 };
 
 pub struct Renderer<'window> {
-    surface: wgpu::Surface<'window>,
+    /// The surface to render to. `None` if the surface is detached, for example while the
+    /// application is suspended.
+    surface: Option<wgpu::Surface<'window>>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
 
     scene: Scene,
+    quality: Quality,
+    /// The palette that replaces the colors of the scene, see [`HighContrast`].
+    high_contrast: Option<HighContrast>,
+    /// The minimum contrast ratio of text to its background, see [`Self::set_min_contrast`].
+    min_contrast: Option<f32>,
+    text_rendering: TextRendering,
+    /// The layers that override the text rendering, see [`Self::set_layer_text_rendering`].
+    layer_text_rendering: HashMap<Id, TextRendering>,
+    /// The state the scene was prepared with, `None` if it needs to be prepared.
+    prepared: Option<Prepared>,
+    /// The maximum number of glyphs and quads to prepare per frame, `None` prepares everything at
+    /// once.
+    upload_budget: Option<usize>,
+    /// How the uploads of the preparation are submitted, see [`Self::set_upload_submission`].
+    upload_submission: UploadSubmission,
+    preparation_stats: PreparationStats,
 
     // DI: Type this.
     view_projection_buffer: wgpu::Buffer,
     // DI: Type this.
     view_projection_bind_group: wgpu::BindGroup,
+    // Kept to recreate the pipelines when the surface format changes.
+    view_projection_bind_group_layout: wgpu::BindGroupLayout,
+    layer_bind_groups: LayerBindGroups,
+    frame_bind_group: FrameBindGroup,
+    /// The time in seconds the reveal animations are evaluated at.
+    time: f32,
+    /// The time at which all reveal animations applied so far are completed.
+    reveals_end: f32,
+    debug_mode: DebugMode,
+    /// Fonts whose data was replaced, mapped to their replacements.
+    font_replacements: HashMap<text::fontdb::ID, text::fontdb::ID>,
 
     // TODO: this doesn't belong here and is used only for specific pipelines. We need some
     // per-pipeline information types.
@@ -33,13 +90,270 @@ pub struct Renderer<'window> {
 
     text_layer_renderer: TextLayerRenderer,
     quads_renderer: QuadsRenderer,
+    border_renderer: BorderRenderer,
+    /// Rounded rectangles are rendered behind quads and text, see [`RoundedRectRenderer`].
+    rounded_rect_renderer: RoundedRectRenderer,
+    shadow_renderer: ShadowRenderer,
+    /// Backdrops are rendered between the world and the overlay, see [`BackdropRenderer`].
+    backdrop_renderer: BackdropRenderer,
+    /// Set if the pipelines can't render to the format of the surface directly.
+    format_conversion: Option<FormatConversion>,
+    /// The factor the surface size is scaled with to get the size the scene is rendered at, see
+    /// [`Self::set_render_scale`].
+    render_scale: f64,
+    /// Set if the render scale is not 1.
+    resampler: Option<Resampler>,
+    /// Static layers that are rendered into textures, see [`Self::set_layer_cached`].
+    layer_cache: LayerCache,
+    /// The carets are not part of the scene, see [`CaretRenderer`].
+    caret_renderer: CaretRenderer,
+    /// The focus rings are not part of the scene either, see [`FocusRingRenderer`].
+    focus_ring_renderer: FocusRingRenderer,
+    /// Covers the views and fades out, see [`Self::flash`].
+    flash_renderer: FlashRenderer,
+    /// Renderers for custom shapes, rendered after the built-in shapes in the order they were
+    /// registered.
+    extensions: Vec<Box<dyn Extension>>,
+    /// Whether the draw calls of the static shapes are recorded into bundles, see
+    /// [`Self::set_render_bundles`].
+    render_bundles: bool,
+    /// The recorded draw calls of the static shapes. `None` if they can't be replayed, because
+    /// they depend on the camera.
+    bundles: HashMap<BundleKey, Option<RecordedBundle>>,
+    /// Whether something changed since the last frame was presented, see [`Self::is_idle`].
+    frame_dirty: bool,
+    /// The views the last frame was presented with.
+    presented_views: Vec<View>,
+    /// Whether fragmented glyph atlases are left as they are, see
+    /// [`Self::set_defer_cache_maintenance`].
+    defer_cache_maintenance: bool,
+    /// Measures the render passes if a Tracy client is running.
+    #[cfg(feature = "tracy")]
+    gpu_profiler: Option<GpuProfiler>,
+}
+
+/// A view of the scene, for example one eye of a stereo pair or one pane of a split editor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub view_projection_matrix: Matrix4,
+    /// The area of the target the view is rendered to. `None` renders to the whole target.
+    pub viewport: Option<Viewport>,
+    /// The area of the target the view may draw to. `None` restricts it to the viewport.
+    pub scissor: Option<Viewport>,
+    /// Render only the shapes of this position and its descendants. `None` renders the whole
+    /// scene.
+    pub root: Option<Id>,
+}
+
+impl View {
+    pub fn new(view_projection_matrix: Matrix4) -> Self {
+        Self {
+            view_projection_matrix,
+            viewport: None,
+            scissor: None,
+            root: None,
+        }
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    pub fn with_scissor(mut self, scissor: Viewport) -> Self {
+        self.scissor = Some(scissor);
+        self
+    }
+
+    /// Render only the part of the scene below the position `root`, so that views can show
+    /// different scenes side by side.
+    pub fn with_root(mut self, root: Id) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    /// `true` if the view projection matrix is finite when converted for the GPU.
+    pub fn is_finite(&self) -> bool {
+        pods::Matrix4::from(&self.view_projection_matrix)
+            .0
+            .iter()
+            .flatten()
+            .all(|v| v.is_finite())
+    }
+}
+
+/// A rectangular area of a render target in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    fn scaled(&self, factor: f32) -> Self {
+        Self {
+            x: self.x * factor,
+            y: self.y * factor,
+            width: self.width * factor,
+            height: self.height * factor,
+        }
+    }
+
+    /// The scissor rectangle of the pixels the viewport covers, clipped to `target_size`.
+    ///
+    /// Returns `None` if no pixel is covered.
+    fn scissor_rect(&self, target_size: (u32, u32)) -> Option<(u32, u32, u32, u32)> {
+        let clamp = |v: f32, max: u32| (v.round().max(0.0) as u32).min(max);
+        let (left, top) = (clamp(self.x, target_size.0), clamp(self.y, target_size.1));
+        let right = clamp(self.x + self.width, target_size.0);
+        let bottom = clamp(self.y + self.height, target_size.1);
+        (right > left && bottom > top).then(|| (left, top, right - left, bottom - top))
+    }
+}
+
+/// Counts how calls to [`Renderer::apply_changes`] were processed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PreparationStats {
+    /// All shapes were prepared, which rebuilds vertex buffers and may rasterize glyphs. With an
+    /// upload budget, this counts the frames in which a new preparation started.
+    pub preparations: u64,
+    /// Only the model matrices of the prepared batches were updated.
+    pub matrix_updates: u64,
+    /// Nothing needed to be prepared, for example because only the camera changed.
+    pub skipped: u64,
+    /// The uploads that were submitted separately from the frames, see
+    /// [`UploadSubmission::Separate`].
+    pub upload_submissions: u64,
+}
+
+impl PreparationStats {
+    /// Count how a call to [`Renderer::apply_changes`] was processed.
+    ///
+    /// Keeping the prepared shapes counts as skipped only if no preparation is pending, because
+    /// otherwise the call continues the pending one.
+    fn record(&mut self, step: PreparationStep, preparation_pending: bool) {
+        match step {
+            PreparationStep::Prepare => self.preparations += 1,
+            PreparationStep::UpdateMatrices => self.matrix_updates += 1,
+            PreparationStep::Keep if !preparation_pending => self.skipped += 1,
+            PreparationStep::Keep => {}
+        }
+    }
+}
+
+/// What needs to be done with the prepared shapes after a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreparationStep {
+    /// Prepare all shapes again.
+    Prepare,
+    /// Only update the model matrices of the prepared batches.
+    UpdateMatrices,
+    /// The prepared shapes are current.
+    Keep,
+}
+
+impl PreparationStep {
+    /// The step after `transaction` for shapes that were prepared with `prepared`, `None` if
+    /// nothing was prepared yet.
+    fn new(
+        prepared: Option<&PreparationSettings>,
+        current: &PreparationSettings,
+        transaction: Transaction,
+    ) -> Self {
+        let prepared_is_current = prepared.is_some_and(|prepared| {
+            !transaction.shapes_changed
+                && prepared.quality == current.quality
+                && prepared.high_contrast == current.high_contrast
+                && prepared.min_contrast == current.min_contrast
+                && prepared.surface_size == current.surface_size
+                // Greeking depends on the size of the runs on the surface.
+                && !(current.quality.greeking_threshold.is_some()
+                    && (transaction.matrices_changed
+                        || !similar_view_scale(prepared.view_scale, current.view_scale)))
+                // Moved runs may be in front of other backgrounds.
+                && !(transaction.matrices_changed && current.min_contrast.is_some())
+        });
+
+        if !prepared_is_current {
+            Self::Prepare
+        } else if transaction.matrices_changed {
+            Self::UpdateMatrices
+        } else {
+            Self::Keep
+        }
+    }
+}
+
+/// The settings that affect the prepared shapes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PreparationSettings {
+    quality: Quality,
+    high_contrast: Option<HighContrast>,
+    min_contrast: Option<f32>,
+    surface_size: (u32, u32),
+    /// The scale of the view greeking is measured with, see [`Renderer::view_scale`].
+    view_scale: Option<f64>,
+}
+
+#[derive(Debug)]
+struct Prepared {
+    settings: PreparationSettings,
+    /// The position ids of the shape groups in the order they were passed to the renderers.
+    groups: Vec<Id>,
+    /// The number of groups at the start of `groups` that are prepared. If this is less than the
+    /// number of groups, preparation continues with the next frames.
+    prepared_groups: usize,
+    /// The number of shapes of the group at `prepared_groups` that are prepared already. Groups
+    /// that exceed the upload budget are split.
+    prepared_shapes: usize,
+}
+
+/// Identifies the bundle of a pass of the views with the same root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BundleKey {
+    root: Option<Id>,
+    overlay: bool,
+}
+
+/// The draw calls of the static shapes of a pass.
+struct RecordedBundle {
+    bundle: wgpu::RenderBundle,
+    /// The model matrix of the last view projection matrix the draw calls wrote.
+    model_matrix: Option<Matrix4>,
+}
+
+/// The prepared shape groups as seen from a view.
+struct ViewState<'rpass> {
+    root: Option<Id>,
+    /// The layer bind group of every shape group, indexed by the group indices the renderers
+    /// track their batches with.
+    layer_bind_groups: Vec<&'rpass wgpu::BindGroup>,
+    overlay_groups: Vec<bool>,
+    view_groups: Vec<bool>,
+    /// The blend order of every shape group, see [`RenderContext::blend_order`].
+    blend_orders: Vec<u32>,
+    /// The number of batches rendered in the current debug mode.
+    debug_batches: Cell<u32>,
 }
 
 /// The context provided to `prepare()` middleware functions.
 pub struct PreparationContext<'a> {
     pub device: &'a wgpu::Device,
     pub queue: &'a wgpu::Queue,
-    pub font_system: &'a mut text::FontSystem,
+    /// Resolves the fonts of the glyphs to rasterize.
+    pub fonts: &'a mut dyn FontSource,
+    pub quality: Quality,
+    /// The palette that replaces the colors of the shapes, if set.
+    pub high_contrast: Option<HighContrast>,
+    /// The text rendering of each group passed to `prepare()`, in the same order.
+    pub text_rendering: Vec<TextRendering>,
+    /// The size the scene is rendered at in physical pixels, see [`Renderer::render_size`].
+    pub surface_size: (u32, u32),
+    /// The view projection matrix of the first view that was presented last, `None` if nothing
+    /// was presented yet.
+    pub view_projection_matrix: Option<Matrix4>,
 }
 
 pub struct RenderContext<'a, 'rpass> {
@@ -47,7 +361,33 @@ pub struct RenderContext<'a, 'rpass> {
     view_projection_buffer: &'a wgpu::Buffer,
     pub view_projection_matrix: Matrix4,
     pub view_projection_bind_group: &'rpass wgpu::BindGroup,
-    pub pass: &'a mut wgpu::RenderPass<'rpass>,
+    /// The layer bind group of each prepared shape group.
+    layer_bind_groups: &'a [&'rpass wgpu::BindGroup],
+    /// Whether each prepared shape group is placed in the overlay.
+    overlay_groups: &'a [bool],
+    /// The overlay is rendered in this pass, see [`Self::renders_group`].
+    overlay: bool,
+    /// Whether each prepared shape group is below the root of the view.
+    view_groups: &'a [bool],
+    /// The blend order of each prepared shape group.
+    blend_orders: &'a [u32],
+    scene: &'a Scene,
+    /// The root of the view, see [`View::root`].
+    root: Option<Id>,
+    /// The bind group of the uniforms shared by all draw calls of the frame.
+    pub frame_bind_group: &'rpass wgpu::BindGroup,
+    /// The size of the area the view is rendered to in physical pixels.
+    pub view_size: (f32, f32),
+    pub debug_mode: DebugMode,
+    /// The number of batches rendered in the current debug mode.
+    debug_batches: &'a Cell<u32>,
+    /// The model matrix of the last matrix written with [`Self::queue_model_matrix`].
+    model_matrix: Option<Matrix4>,
+    /// Whether a matrix was written that depends on the camera, see
+    /// [`Self::queue_view_projection_matrix`].
+    camera_dependent: bool,
+    /// A render pass, or a bundle encoder when the draw calls are recorded.
+    pub pass: &'a mut dyn RenderEncoder<'rpass>,
 }
 
 impl<'window> Renderer<'window> {
@@ -57,6 +397,21 @@ impl<'window> Renderer<'window> {
         queue: wgpu::Queue,
         surface: wgpu::Surface<'window>,
         surface_config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let mut renderer = Self::new_detached(device, queue, surface_config);
+        renderer.attach_surface(surface);
+        renderer
+    }
+
+    /// Creates a new renderer without a surface.
+    ///
+    /// The pipelines are created for the format in `surface_config`. Scene changes can be applied
+    /// right away, but nothing is rendered until a surface is attached with
+    /// [`Self::attach_surface`].
+    pub fn new_detached(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_config: wgpu::SurfaceConfiguration,
     ) -> Self {
         let view_projection_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("View Projection Matrix Buffer"),
@@ -70,98 +425,1450 @@ impl<'window> Renderer<'window> {
 
         let texture_bind_group_layout = texture::BindGroupLayout::new(&device);
 
-        let format = surface_config.format;
+        let layer_bind_groups = LayerBindGroups::new(&device);
+        let frame_bind_group = FrameBindGroup::new(&device);
+
+        let format_conversion = FormatConversion::new(&device, surface_config.format);
+        let format = format_conversion
+            .as_ref()
+            .map_or(surface_config.format, |conversion| {
+                conversion.render_format()
+            });
+
+        let text_layer_renderer = TextLayerRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+            frame_bind_group.layout(),
+        );
+
+        let quads_renderer = QuadsRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+        );
+
+        let border_renderer = BorderRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+            frame_bind_group.layout(),
+        );
+
+        let rounded_rect_renderer = RoundedRectRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+        );
+
+        let shadow_renderer = ShadowRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+        );
 
-        let text_layer_renderer =
-            TextLayerRenderer::new(&device, format, &view_projection_bind_group_layout);
+        let backdrop_renderer = BackdropRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+        );
 
-        let quads_renderer =
-            QuadsRenderer::new(&device, format, &view_projection_bind_group_layout);
+        let caret_renderer = CaretRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+        );
 
-        let mut renderer = Self {
+        let focus_ring_renderer = FocusRingRenderer::new(
+            &device,
+            format,
+            &view_projection_bind_group_layout,
+            layer_bind_groups.layout(),
+        );
+
+        let layer_cache = LayerCache::new(&device, format);
+        let flash_renderer = FlashRenderer::new(&device, format);
+        #[cfg(feature = "tracy")]
+        let gpu_profiler = GpuProfiler::new(&device, &queue);
+
+        Self {
             device,
             queue,
-            surface,
+            surface: None,
             surface_config,
             scene: Scene::default(),
+            quality: Quality::default(),
+            high_contrast: None,
+            min_contrast: None,
+            text_rendering: TextRendering::default(),
+            layer_text_rendering: HashMap::new(),
+            prepared: None,
+            upload_budget: None,
+            upload_submission: UploadSubmission::default(),
+            preparation_stats: PreparationStats::default(),
             view_projection_buffer,
             view_projection_bind_group,
+            view_projection_bind_group_layout,
+            layer_bind_groups,
+            frame_bind_group,
+            time: 0.0,
+            reveals_end: 0.0,
+            debug_mode: DebugMode::default(),
+            font_replacements: HashMap::new(),
             texture_bind_group_layout,
             text_layer_renderer,
             quads_renderer,
+            border_renderer,
+            rounded_rect_renderer,
+            shadow_renderer,
+            backdrop_renderer,
+            format_conversion,
+            render_scale: 1.0,
+            resampler: None,
+            layer_cache,
+            caret_renderer,
+            focus_ring_renderer,
+            flash_renderer,
+            extensions: Vec::new(),
+            render_bundles: true,
+            bundles: HashMap::new(),
+            frame_dirty: true,
+            presented_views: Vec::new(),
+            defer_cache_maintenance: false,
+            #[cfg(feature = "tracy")]
+            gpu_profiler,
+        }
+    }
+
+    /// Register an extension that prepares and renders custom shapes of the type
+    /// `E::Shape`.
+    ///
+    /// Replaces an extension registered for the same shape type. Custom shapes without an
+    /// extension are not rendered.
+    pub fn register_extension<E: ShapeExtension>(&mut self, extension: E) {
+        let extension: Box<dyn Extension> = Box::new(extension);
+        self.extensions
+            .retain(|registered| registered.shape_type() != extension.shape_type());
+        self.extensions.push(extension);
+        // Shapes of this type may be in the scene already.
+        self.prepared = None;
+    }
+
+    /// The layout of the bind group that contains the view projection matrix.
+    ///
+    /// Extensions use this to create pipelines compatible with [`RenderContext`].
+    pub fn view_projection_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.view_projection_bind_group_layout
+    }
+
+    /// The layout of the bind group that contains the [`LayerUniforms`].
+    ///
+    /// Extensions use this to create pipelines that bind [`RenderContext::layer_bind_group`].
+    pub fn layer_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        self.layer_bind_groups.layout()
+    }
+
+    /// Set the uniforms of the layer that renders the shapes at `position`.
+    ///
+    /// Shapes at nested positions are separate layers. This takes effect with the next frame and
+    /// does not prepare the scene again.
+    ///
+    /// The text rendering of the layer is kept, see [`Self::set_layer_text_rendering`].
+    pub fn set_layer_uniforms(&mut self, position: Id, uniforms: &LayerUniforms) {
+        let mut uniforms = *uniforms;
+        if let Some(text_rendering) = self.layer_text_rendering.get(&position) {
+            uniforms.text = text_rendering.shader_parameters();
+        }
+        self.frame_dirty = true;
+        // The bundles refer to the default bind group.
+        if self.layer_bind_groups.get(position).is_none() {
+            self.invalidate_bundles();
+        }
+        if self.is_cached(position) {
+            self.layer_cache.invalidate();
+        }
+        self.layer_bind_groups
+            .set(&self.device, &self.queue, position, &uniforms);
+    }
+
+    /// Render the world shapes at `position` and its descendants into a texture once and
+    /// composite it with every frame, until they or the view change.
+    ///
+    /// This makes scenes with a heavy static background and a small dynamic part cheaper. The
+    /// cached shapes are composited behind all other shapes of the world, so this is meant for
+    /// backgrounds. Shapes in the overlay, carets, and focus rings are not cached.
+    ///
+    /// The texture is rendered again when the scene is prepared, for example when shapes change,
+    /// when the matrices of the cached positions or their layer uniforms change, and when the
+    /// view moves. Reveal animations and pulsing highlights of cached shapes don't advance, use
+    /// [`Self::invalidate_layer_caches`] to render them again.
+    pub fn set_layer_cached(&mut self, position: Id, cached: bool) {
+        if self.layer_cache.set_cached(position, cached) {
+            // The bundles of the world don't contain the cached groups.
+            self.invalidate_bundles();
+        }
+    }
+
+    /// Render the cached layers again with the next frame, see [`Self::set_layer_cached`].
+    pub fn invalidate_layer_caches(&mut self) {
+        self.layer_cache.invalidate();
+        self.frame_dirty = true;
+    }
+
+    /// Whether the shapes at `position` are rendered from the texture of a cached layer.
+    fn is_cached(&self, position: Id) -> bool {
+        !self.layer_cache.is_empty()
+            && !self.scene.is_overlay(position)
+            && self
+                .layer_cache
+                .roots()
+                .iter()
+                .any(|root| self.scene.is_below(position, *root))
+    }
+
+    /// Reset the uniforms of a layer to their defaults.
+    ///
+    /// Uniforms are not removed when their position is dropped, so call this when a position with
+    /// uniforms is not used anymore.
+    pub fn remove_layer_uniforms(&mut self, position: Id) {
+        if self.layer_text_rendering.contains_key(&position) {
+            self.set_layer_uniforms(position, &LayerUniforms::default());
+        } else {
+            self.layer_bind_groups.remove(position);
+            self.invalidate_bundles();
+        }
+    }
+
+    pub fn text_rendering(&self) -> TextRendering {
+        self.text_rendering
+    }
+
+    /// Set how text is rendered, for example with [`crate::TextPreset::settings`].
+    ///
+    /// The shader parameters take effect with the next frame. Hinting and pixel snapping take
+    /// effect with the next invocation of [`Self::apply_changes`], which prepares all shapes again.
+    pub fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        if text_rendering.prepares_differently(&self.text_rendering) {
+            self.prepared = None;
+        }
+        self.text_rendering = text_rendering;
+        self.frame_bind_group
+            .set_text_rendering(&self.queue, &text_rendering);
+    }
+
+    /// Store the distance fields of glyphs in `format`.
+    ///
+    /// Changing the format drops the rasterized glyphs, they are rasterized again with the next
+    /// preparation.
+    pub fn set_mask_atlas_format(&mut self, format: MaskAtlasFormat) {
+        if self
+            .text_layer_renderer
+            .set_mask_atlas_format(&self.device, format)
+        {
+            self.prepared = None;
+        }
+    }
+
+    pub fn mask_atlas_format(&self) -> MaskAtlasFormat {
+        self.text_layer_renderer.mask_atlas_format()
+    }
+
+    /// Override the text rendering for the layer that renders the shapes at `position`. `None`
+    /// uses the text rendering of the renderer again.
+    ///
+    /// Like uniforms, this is not removed when the position is dropped.
+    pub fn set_layer_text_rendering(
+        &mut self,
+        position: Id,
+        text_rendering: Option<TextRendering>,
+    ) {
+        let previous = match text_rendering {
+            Some(text_rendering) => self.layer_text_rendering.insert(position, text_rendering),
+            None => self.layer_text_rendering.remove(&position),
+        };
+        if previous
+            .unwrap_or(self.text_rendering)
+            .prepares_differently(&text_rendering.unwrap_or(self.text_rendering))
+        {
+            self.prepared = None;
+        }
+
+        // The text parameters of the layer are added again if it still has them.
+        let uniforms = LayerUniforms {
+            text: [0.0; 4],
+            ..self
+                .layer_bind_groups
+                .get(position)
+                .copied()
+                .unwrap_or_default()
+        };
+        self.set_layer_uniforms(position, &uniforms);
+    }
+
+    /// The text rendering of the layer of `position`.
+    fn text_rendering_of(&self, position: Id) -> TextRendering {
+        self.layer_text_rendering
+            .get(&position)
+            .copied()
+            .unwrap_or(self.text_rendering)
+    }
+
+    /// The time in seconds the reveal animations of glyph runs are evaluated at.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Set the time the reveal animations are evaluated at, usually once per frame.
+    ///
+    /// The time base is up to the application, the start times of the reveals must use the same.
+    /// This takes effect with the next frame and does not prepare the scene again.
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+        self.frame_bind_group.set_time(&self.queue, time);
+    }
+
+    /// Whether a reveal animation, a caret movement, a focus ring movement, or a flash is still
+    /// running at the current time, or a border's dashes are marching, and so the next frame
+    /// renders differently.
+    pub fn is_animating(&self) -> bool {
+        self.time < self.reveals_end
+            || self.caret_renderer.is_animating(self.time)
+            || self.focus_ring_renderer.is_animating(self.time)
+            || self.border_renderer.is_animating()
+            || self.flash_renderer.is_animating(self.time)
+    }
+
+    /// Cover the views with `color` and fade it out over `duration`, starting at the current
+    /// [`Self::time`]. Replaces a running flash.
+    ///
+    /// The flash is not part of the scene and is not captured, see [`Self::capture_frame`].
+    pub fn flash(&mut self, color: Color, duration: Duration) {
+        self.flash_renderer
+            .start(color, self.time, duration.as_secs_f32());
+        self.frame_dirty = true;
+    }
+
+    /// Attach a surface and configure it with the current configuration.
+    ///
+    /// Returns the previously attached surface. The scene is kept, so rendering continues where
+    /// it left off. If the new surface does not support the current format, use
+    /// [`Self::reconfigure_surface_format`] afterwards.
+    pub fn attach_surface(
+        &mut self,
+        surface: wgpu::Surface<'window>,
+    ) -> Option<wgpu::Surface<'window>> {
+        let previous = self.surface.replace(surface);
+        self.reconfigure_surface();
+        previous
+    }
+
+    /// Detach the surface. The scene and all GPU resources besides the surface are kept.
+    pub fn detach_surface(&mut self) -> Option<wgpu::Surface<'window>> {
+        self.surface.take()
+    }
+
+    pub fn has_surface(&self) -> bool {
+        self.surface.is_some()
+    }
+
+    /// Forget everything known and bootstrap a new set of initial changes.
+    ///
+    /// This is for legacy support an should be removed.
+    pub fn bootstrap_changes(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        changes: impl IntoIterator<Item = SceneChange>,
+    ) -> result::Result<(), RenderError> {
+        // Reset the scene.
+        self.scene = Scene::default();
+        self.caret_renderer.clear();
+        self.focus_ring_renderer.clear();
+        self.prepared = None;
+        self.apply_changes(fonts, changes)
+    }
+
+    /// Apply changes to the scene and prepare it for rendering.
+    ///
+    /// Shapes are only prepared again when they were changed, or when the quality or the surface
+    /// size changed. If only matrices or positions were changed, the model matrices of the
+    /// already prepared batches are updated. Camera updates don't need any preparation at all.
+    ///
+    /// If an upload budget is set, preparation may be spread over several calls, see
+    /// [`Self::set_upload_budget`].
+    ///
+    /// Use [`Self::preparation_stats`] to verify which path was taken.
+    ///
+    /// The changes are applied to the scene even if preparation fails. Preparation is attempted
+    /// again with the next invocation.
+    #[tracing::instrument(skip_all)]
+    pub fn apply_changes(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        changes: impl IntoIterator<Item = SceneChange>,
+    ) -> result::Result<(), RenderError> {
+        let mut received_changes = false;
+        let replacements = &self.font_replacements;
+        let reveals_end = &mut self.reveals_end;
+        let carets = &mut self.caret_renderer;
+        let focus_rings = &mut self.focus_ring_renderer;
+        let time = self.time;
+        let changes = changes
+            .into_iter()
+            .inspect(|_| received_changes = true)
+            .filter_map(|change| route_caret_change(carets, change, time))
+            .filter_map(|change| route_focus_ring_change(focus_rings, change, time));
+        let transaction = self.scene.transact(changes.map(|mut change| {
+            if let SceneChange::PositionedShape(
+                Change::Create(_, shape) | Change::Update(_, shape),
+            ) = &mut change
+            {
+                // Shapes may have been laid out with caches that still refer to replaced fonts.
+                if !replacements.is_empty() {
+                    replace_fonts(&mut shape.shape, replacements);
+                }
+                if let Shape::GlyphRun(run) = &shape.shape {
+                    if let Some(reveal) = &run.reveal {
+                        *reveals_end = reveals_end.max(reveal.end(run.glyphs.len()));
+                    }
+                }
+            }
+            change
+        }));
+        if received_changes {
+            self.frame_dirty = true;
+        }
+        let step = PreparationStep::new(
+            self.prepared.as_ref().map(|prepared| &prepared.settings),
+            &self.preparation_settings(),
+            transaction,
+        );
+        let preparation_pending = self.is_preparation_pending();
+        match step {
+            PreparationStep::Prepare => self.begin_preparation(),
+            PreparationStep::UpdateMatrices => self.update_matrices(),
+            PreparationStep::Keep => {}
+        }
+        self.preparation_stats.record(step, preparation_pending);
+
+        Ok(self.continue_preparation(fonts)?)
+    }
+
+    /// Release the GPU memory that can be recreated: The glyph atlases, the vertex buffers of the
+    /// text, the intermediate textures, and the recorded bundles.
+    ///
+    /// Use this when the device runs out of memory. The scene is kept and prepared again with the
+    /// next call to [`Self::apply_changes`], which rasterizes the visible glyphs again.
+    pub fn release_memory(&mut self) {
+        info!("Releasing caches and intermediate textures");
+        self.text_layer_renderer.release_memory(&self.device);
+        self.backdrop_renderer.release_targets();
+        if let Some(conversion) = &mut self.format_conversion {
+            conversion.release_target();
+        }
+        if let Some(resampler) = &mut self.resampler {
+            resampler.release_target();
+        }
+        self.layer_cache.release();
+        self.invalidate_bundles();
+        self.prepared = None;
+    }
+
+    /// Drop all prepared batches and schedule all visible shapes of the scene for preparation.
+    fn begin_preparation(&mut self) {
+        self.invalidate_bundles();
+        self.text_layer_renderer.clear();
+        if !self.defer_cache_maintenance {
+            self.text_layer_renderer.repack_atlases();
+        }
+        self.quads_renderer.clear();
+        self.border_renderer.clear();
+        self.rounded_rect_renderer.clear();
+        self.shadow_renderer.clear();
+        self.backdrop_renderer.clear();
+        for extension in &mut self.extensions {
+            extension.clear();
+        }
+
+        self.prepared = Some(Prepared {
+            settings: self.preparation_settings(),
+            groups: self.scene.grouped_shapes().map(|(id, ..)| id).collect(),
+            prepared_groups: 0,
+            prepared_shapes: 0,
+        });
+    }
+
+    fn preparation_settings(&self) -> PreparationSettings {
+        PreparationSettings {
+            quality: self.quality,
+            high_contrast: self.high_contrast,
+            min_contrast: self.min_contrast,
+            surface_size: self.render_size(),
+            view_scale: self.view_scale(),
+        }
+    }
+
+    /// Prepare the shapes that are not prepared yet, as many as the upload budget allows.
+    ///
+    /// A group that does not fit into the remaining budget is split and continued with the next
+    /// call. At least one shape is prepared per call, so that preparation always makes progress.
+    #[tracing::instrument(skip_all)]
+    fn continue_preparation(&mut self, fonts: &mut dyn FontSource) -> Result<()> {
+        let Some(prepared) = &self.prepared else {
+            return Ok(());
+        };
+        let first_group = prepared.prepared_groups;
+        if first_group == prepared.groups.len() {
+            return Ok(());
+        }
+
+        // OO: Lot's of allocations here.
+        let mut grouped_shapes: HashMap<Id, (Matrix4, Vec<&Shape>)> = self
+            .scene
+            .grouped_shapes()
+            .map(|(id, matrix, shapes)| (id, (matrix, shapes)))
+            .collect();
+
+        let pixel_matrix = self.pixel_matrix();
+        let budget = self.upload_budget.unwrap_or(usize::MAX);
+        let mut spent = 0;
+
+        // The shapes of the first group that were prepared by the previous call.
+        let mut first_shape = prepared.prepared_shapes;
+        // The number of shapes of the last group that are prepared if it was split.
+        let mut split_at = None;
+
+        let mut grouped_by_matrix = Vec::new();
+        for id in &prepared.groups[first_group..] {
+            // Groups can't change while they are pending, because shape changes begin a new
+            // preparation.
+            let Some((matrix, mut shapes)) = grouped_shapes.remove(id) else {
+                bail!("Internal error: Pending shape group vanished");
+            };
+            shapes.drain(..first_shape);
+
+            let mut end = shapes.len();
+            for (index, shape) in shapes.iter().enumerate() {
+                let cost = upload_cost(shape);
+                if spent > 0 && spent + cost > budget {
+                    end = index;
+                    break;
+                }
+                spent += cost;
+            }
+
+            if end == shapes.len() {
+                grouped_by_matrix.push((*id, matrix, shapes));
+                first_shape = 0;
+                continue;
+            }
+            if end > 0 {
+                shapes.truncate(end);
+                grouped_by_matrix.push((*id, matrix, shapes));
+                split_at = Some(first_shape + end);
+            }
+            break;
+        }
+
+        let text_rendering = grouped_by_matrix
+            .iter()
+            .map(|(id, ..)| self.text_rendering_of(*id))
+            .collect();
+
+        let adjusted_runs = self.contrast_adjusted_runs(&grouped_by_matrix);
+        for (group, index, run) in &adjusted_runs {
+            grouped_by_matrix[*group].2[*index] = run;
+        }
+
+        // Group by matrix and apply the pixel matrix.
+        let grouped_by_matrix: Vec<_> = grouped_by_matrix
+            .iter()
+            .map(|(_, matrix, shapes)| (pixel_matrix * matrix, shapes.as_slice()))
+            .collect();
+
+        let mut context = PreparationContext {
+            device: &self.device,
+            queue: &self.queue,
+            fonts,
+            quality: self.quality,
+            high_contrast: self.high_contrast,
+            text_rendering,
+            surface_size: self.render_size(),
+            view_projection_matrix: self
+                .presented_views
+                .first()
+                .map(|v| v.view_projection_matrix),
         };
 
-        renderer.reconfigure_surface();
-        renderer
+        // OO: parallelize?
+        self.text_layer_renderer
+            .prepare(&mut context, first_group, &grouped_by_matrix)?;
+        self.quads_renderer
+            .prepare(&mut context, first_group, &grouped_by_matrix)?;
+        self.border_renderer
+            .prepare(&mut context, first_group, &grouped_by_matrix)?;
+        self.rounded_rect_renderer
+            .prepare(&mut context, first_group, &grouped_by_matrix)?;
+        self.shadow_renderer
+            .prepare(&mut context, first_group, &grouped_by_matrix)?;
+        self.backdrop_renderer
+            .prepare(&mut context, first_group, &grouped_by_matrix)?;
+        for extension in &mut self.extensions {
+            extension.prepare(&mut context, first_group, &grouped_by_matrix)?;
+        }
+
+        let prepared_count = grouped_by_matrix.len();
+        if let Some(prepared) = &mut self.prepared {
+            match split_at {
+                Some(prepared_shapes) => {
+                    prepared.prepared_groups += prepared_count - 1;
+                    prepared.prepared_shapes = prepared_shapes;
+                }
+                None => {
+                    prepared.prepared_groups += prepared_count;
+                    prepared.prepared_shapes = 0;
+                }
+            }
+        }
+        self.invalidate_bundles();
+        self.submit_uploads();
+
+        Ok(())
+    }
+
+    /// Copies of the glyph runs that don't reach the minimum contrast to the quads behind them,
+    /// with adjusted colors, by group and shape index.
+    ///
+    /// The background is sampled at the center of a run.
+    fn contrast_adjusted_runs(
+        &self,
+        groups: &[(Id, Matrix4, Vec<&Shape>)],
+    ) -> Vec<(usize, usize, Shape)> {
+        // The high contrast palette replaces the colors anyway.
+        let Some(ratio) = self.min_contrast.filter(|_| self.high_contrast.is_none()) else {
+            return Vec::new();
+        };
+
+        let mut adjusted = Vec::new();
+        for (group, (position, matrix, shapes)) in groups.iter().enumerate() {
+            let overlay = self.scene.is_overlay(*position);
+            for (index, shape) in shapes.iter().enumerate() {
+                let Shape::GlyphRun(run) = shape else {
+                    continue;
+                };
+                let center = matrix.transform_point(run.bounds().center());
+                let background = self
+                    .scene
+                    .background_at(center, overlay, self.clear_color());
+                let text_color = contrast::with_min_contrast(run.text_color, background, ratio);
+                if text_color != run.text_color {
+                    let run = GlyphRun {
+                        text_color,
+                        ..run.clone()
+                    };
+                    adjusted.push((group, index, Shape::GlyphRun(run)));
+                }
+            }
+        }
+        adjusted
+    }
+
+    /// Returns `true` if some shapes are not prepared yet, because they exceeded the upload
+    /// budget.
+    ///
+    /// While this is the case, render frames (and call [`Self::apply_changes`], even without
+    /// changes) until the whole scene is prepared.
+    pub fn is_preparation_pending(&self) -> bool {
+        self.prepared
+            .as_ref()
+            .is_some_and(|prepared| prepared.prepared_groups < prepared.groups.len())
+    }
+
+    /// Rasterize the glyphs of the pending shape groups that are visible through
+    /// `view_projection_matrix` and upload them to the atlases ahead of their preparation.
+    ///
+    /// This is a hint for a view that is expected to be rendered in a few frames, for example
+    /// where an animated camera is heading. Preparation still continues in the order of the
+    /// groups, but reaches the prefetched ones without rasterization hitches. At most the upload
+    /// budget of glyphs is rasterized. Returns the number of rasterized glyphs.
+    pub fn prefetch_glyphs(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        view_projection_matrix: &Matrix4,
+    ) -> Result<usize> {
+        let Some(prepared) = &self.prepared else {
+            return Ok(0);
+        };
+        let pending: HashSet<Id> = prepared.groups[prepared.prepared_groups..]
+            .iter()
+            .copied()
+            .collect();
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let clip_matrix = view_projection_matrix * self.pixel_matrix();
+        let mut runs = Vec::new();
+        for (id, matrix, shapes) in self.scene.grouped_shapes() {
+            if !pending.contains(&id) {
+                continue;
+            }
+            let text_rendering = self.text_rendering_of(id);
+            let matrix = clip_matrix * matrix;
+            for shape in shapes {
+                let Shape::GlyphRun(run) = shape else {
+                    continue;
+                };
+                if shape.bounds().is_some_and(|b| is_in_view(&b, &matrix)) {
+                    runs.push((run, text_rendering));
+                }
+            }
+        }
+
+        let mut context = PreparationContext {
+            device: &self.device,
+            queue: &self.queue,
+            fonts,
+            quality: self.quality,
+            high_contrast: self.high_contrast,
+            text_rendering: Vec::new(),
+            surface_size: self.render_size(),
+            view_projection_matrix: Some(*view_projection_matrix),
+        };
+        let budget = self.upload_budget.unwrap_or(usize::MAX);
+        let prefetched = self
+            .text_layer_renderer
+            .prefetch(&mut context, runs, budget)?;
+        self.submit_uploads();
+        Ok(prefetched)
+    }
+
+    /// Submit the uploads staged on the queue, if they are submitted separately from the frames.
+    fn submit_uploads(&mut self) {
+        if self.upload_submission == UploadSubmission::Separate {
+            self.queue.submit([]);
+            self.preparation_stats.upload_submissions += 1;
+        }
+    }
+
+    /// Use a persistent cache at `path` for rasterized glyphs.
+    ///
+    /// The cache file is memory-mapped and glyphs found there are not rasterized again. Newly
+    /// rasterized glyphs are appended with [`Self::persist_glyph_cache`] and when the renderer is
+    /// dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_glyph_cache(&mut self, path: impl Into<std::path::PathBuf>) -> Result<()> {
+        let cache = GlyphDiskCache::open(path)?;
+        self.text_layer_renderer.set_disk_cache(Some(cache));
+        Ok(())
+    }
+
+    /// Write all newly rasterized glyphs to the glyph cache, if one is open.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn persist_glyph_cache(&mut self) -> Result<()> {
+        match self.text_layer_renderer.disk_cache_mut() {
+            Some(cache) => cache.persist(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn upload_budget(&self) -> Option<usize> {
+        self.upload_budget
+    }
+
+    /// Limit the number of glyphs and quads that are prepared per frame.
+    ///
+    /// When a huge scene is submitted at once, this spreads the uploads across frames and renders
+    /// the already prepared shapes in the meantime. `None` prepares everything at once.
+    pub fn set_upload_budget(&mut self, budget: Option<usize>) {
+        self.upload_budget = budget;
+    }
+
+    pub fn upload_submission(&self) -> UploadSubmission {
+        self.upload_submission
+    }
+
+    /// Submit the glyphs and vertices a preparation step uploads separately from the frames, see
+    /// [`UploadSubmission`].
+    ///
+    /// Use [`UploadSubmission::for_backend`] to select the submission for the adapter.
+    pub fn set_upload_submission(&mut self, submission: UploadSubmission) {
+        self.upload_submission = submission;
+    }
+
+    /// Leave fragmented glyph atlases as they are instead of repacking them when the scene is
+    /// prepared again. They are repacked with the first preparation after this is disabled.
+    ///
+    /// Repacking moves all stored glyphs on the GPU, which is wasted work while nobody is looking.
+    pub fn set_defer_cache_maintenance(&mut self, defer: bool) {
+        self.defer_cache_maintenance = defer;
+    }
+
+    pub fn defer_cache_maintenance(&self) -> bool {
+        self.defer_cache_maintenance
+    }
+
+    pub fn debug_mode(&self) -> DebugMode {
+        self.debug_mode
+    }
+
+    /// Visualize how the scene is rendered, starting with the next frame.
+    pub fn set_debug_mode(&mut self, mode: DebugMode) {
+        self.debug_mode = mode;
+        self.invalidate_bundles();
+    }
+
+    pub fn render_bundles(&self) -> bool {
+        self.render_bundles
+    }
+
+    /// Record the draw calls of the shapes into render bundles and replay them as long as the
+    /// scene does not change. Enabled by default.
+    ///
+    /// This saves most of the encoding time of large static scenes while only the camera moves.
+    /// Carets, focus rings, backdrops, and shapes of extensions are encoded every frame.
+    /// Billboards and text of constant screen size depend on the camera, so passes with them
+    /// are encoded every frame, too.
+    pub fn set_render_bundles(&mut self, enabled: bool) {
+        self.render_bundles = enabled;
+        self.invalidate_bundles();
+    }
+
+    fn invalidate_bundles(&mut self) {
+        self.bundles.clear();
+        // The cached layers are rendered from the same batches.
+        self.layer_cache.invalidate();
+        self.frame_dirty = true;
+    }
+
+    /// Whether presenting a frame with `views` would show the same as the last presented frame:
+    /// Nothing changed since then, nothing is animating, and the views are the same.
+    ///
+    /// Renderers may skip such frames to reduce the load while idle. Changes to the scene, to
+    /// the settings, and to the surface render the next frame again, as does
+    /// [`Self::invalidate_frame`].
+    pub fn is_idle(&self, views: &[View]) -> bool {
+        !self.frame_dirty
+            && self.presented_views == views
+            && !self.is_animating()
+            && !self.is_preparation_pending()
+            && !self
+                .layer_bind_groups
+                .uniforms()
+                .any(|(_, uniforms)| uniforms.is_pulsing())
+    }
+
+    /// Render the next frame even if [`Self::is_idle`], for example when the contents of the
+    /// surface may have been lost.
+    pub fn invalidate_frame(&mut self) {
+        self.frame_dirty = true;
+    }
+
+    /// Replace fonts in all glyph runs, for example after the data of fonts was reloaded.
+    ///
+    /// `replacements` maps the ids of the fonts that were removed from the font system to the ids
+    /// of the fonts that replace them. The glyphs of the removed fonts are evicted, and all runs
+    /// are rendered with the replacements, including runs that are added later. The runs keep
+    /// their layout, so applications should lay out their text again to pick up changed metrics.
+    pub fn replace_fonts(
+        &mut self,
+        fonts: &mut dyn FontSource,
+        replacements: &HashMap<text::fontdb::ID, text::fontdb::ID>,
+    ) -> Result<()> {
+        if replacements.is_empty() {
+            return Ok(());
+        }
+
+        // Resolve chains of replacements.
+        for replacement in self.font_replacements.values_mut() {
+            if let Some(new) = replacements.get(replacement) {
+                *replacement = *new;
+            }
+        }
+        self.font_replacements.extend(replacements);
+
+        for shape in self.scene.shapes_mut() {
+            replace_fonts(shape, &self.font_replacements);
+        }
+
+        let removed: HashSet<_> = replacements.keys().copied().collect();
+        self.text_layer_renderer.evict_fonts(&removed);
+
+        self.begin_preparation();
+        self.preparation_stats.preparations += 1;
+        self.continue_preparation(fonts)
+    }
+
+    /// The retained state of the renderer, for example to write a snapshot for a bug report.
+    pub fn state(&self) -> RendererState<'_> {
+        let carets = self.caret_renderer.shapes();
+        let focus_rings = self.focus_ring_renderer.shapes();
+        let mut shapes: Vec<_> = self
+            .scene
+            .shapes()
+            .chain(carets.map(|(id, shape)| (id, shape, true)))
+            .chain(focus_rings.map(|(id, shape)| (id, shape, true)))
+            .collect();
+        shapes.sort_by_key(|(id, ..)| *id);
+
+        RendererState {
+            matrices: self.scene.matrices().collect(),
+            positions: self.scene.positions().collect(),
+            shapes,
+            config: self.config(),
+            atlases: self.atlases(),
+        }
+    }
+
+    pub fn config(&self) -> RendererConfig {
+        let mut layer_uniforms: Vec<(usize, LayerUniforms)> = self
+            .layer_bind_groups
+            .uniforms()
+            .map(|(id, uniforms)| (*id, *uniforms))
+            .collect();
+        layer_uniforms.sort_by_key(|(id, _)| *id);
+        let mut layer_text_rendering: Vec<(usize, TextRendering)> = self
+            .layer_text_rendering
+            .iter()
+            .map(|(id, text_rendering)| (**id, *text_rendering))
+            .collect();
+        layer_text_rendering.sort_by_key(|(id, _)| *id);
+
+        RendererConfig {
+            quality: self.quality,
+            upload_budget: self.upload_budget,
+            debug_mode: self.debug_mode,
+            high_contrast: self.high_contrast,
+            min_contrast: self.min_contrast,
+            text_rendering: self.text_rendering,
+            layer_text_rendering,
+            surface_size: self.surface_size(),
+            layer_uniforms,
+        }
+    }
+
+    /// Apply the settings of a [`RendererConfig`], except the surface size, which is up to the
+    /// window.
+    ///
+    /// Replaces all layer uniforms.
+    pub fn apply_config(&mut self, config: &RendererConfig) {
+        self.quality = config.quality;
+        self.upload_budget = config.upload_budget;
+        self.debug_mode = config.debug_mode;
+        self.high_contrast = config.high_contrast;
+        self.min_contrast = config.min_contrast;
+        self.set_text_rendering(config.text_rendering);
+        self.layer_text_rendering = config
+            .layer_text_rendering
+            .iter()
+            .map(|(id, text_rendering)| (Id::from_raw(*id), *text_rendering))
+            .collect();
+        // The layer text rendering may differ.
+        self.prepared = None;
+        self.invalidate_bundles();
+        self.layer_bind_groups.clear();
+        for (id, uniforms) in &config.layer_uniforms {
+            self.layer_bind_groups
+                .set(&self.device, &self.queue, Id::from_raw(*id), uniforms);
+        }
+    }
+
+    pub fn atlases(&self) -> Vec<AtlasMetadata> {
+        self.text_layer_renderer.atlases()
+    }
+
+    /// Update the model matrices of all prepared batches.
+    fn update_matrices(&mut self) {
+        self.invalidate_bundles();
+        let Some(prepared) = &self.prepared else {
+            return;
+        };
+
+        let pixel_matrix = self.pixel_matrix();
+        // Including the group that is prepared partially.
+        let started_groups = prepared.prepared_groups + usize::from(prepared.prepared_shapes > 0);
+        let matrices: Vec<_> = prepared.groups[..started_groups]
+            .iter()
+            .map(|position_id| pixel_matrix * self.scene.position_matrix(*position_id))
+            .collect();
+
+        self.text_layer_renderer.update_matrices(&matrices);
+        self.quads_renderer.update_matrices(&matrices);
+        self.border_renderer.update_matrices(&matrices);
+        self.rounded_rect_renderer.update_matrices(&matrices);
+        self.shadow_renderer.update_matrices(&matrices);
+        self.backdrop_renderer.update_matrices(&matrices);
+        for extension in &mut self.extensions {
+            extension.update_matrices(&matrices);
+        }
+    }
+
+    /// The bounds of all visible shapes in world units, `None` if there are none.
+    pub fn scene_bounds(&self) -> Option<Bounds3> {
+        let pixel_matrix = self.pixel_matrix();
+        self.scene.bounds().map(|b| b.transformed(&pixel_matrix))
+    }
+
+    /// The bounds of a visible shape in world units.
+    ///
+    /// `None` if the shape does not exist, is invisible, or empty.
+    pub fn shape_bounds(&self, shape_id: Id) -> Option<Bounds3> {
+        let pixel_matrix = self.pixel_matrix();
+        self.scene
+            .shape_bounds(shape_id)
+            .map(|b| b.transformed(&pixel_matrix))
+    }
+
+    /// The ids of all visible shapes whose bounds intersect `bounds`, which is in world units.
+    pub fn shapes_intersecting(&self, bounds: &Bounds3) -> Vec<Id> {
+        let inverse = self.inverse_pixel_matrix();
+        self.scene
+            .shapes_intersecting(&bounds.transformed(&inverse))
+    }
+
+    /// The ids of all visible shapes whose bounds are in the view of `view_projection_matrix`,
+    /// for example to cull or pick the content of a large canvas.
+    ///
+    /// Branches of the bounds tree that are out of view are skipped, so the cost depends on the
+    /// shapes in view, not on the size of the scene.
+    pub fn shapes_in_view(&self, view_projection_matrix: &Matrix4) -> Vec<Id> {
+        let clip_matrix = view_projection_matrix * self.pixel_matrix();
+        self.scene
+            .shapes_passing(|bounds| is_in_view(bounds, &clip_matrix))
+    }
+
+    /// The ids of all visible shapes whose bounds are hit by a ray in world units, ordered from
+    /// the nearest to the farthest.
+    ///
+    /// The distances are in multiples of `direction`.
+    pub fn shapes_hit_by_ray(&self, origin: Point3, direction: Vector3) -> Vec<(Id, f64)> {
+        let inverse = self.inverse_pixel_matrix();
+        self.scene.shapes_hit_by_ray(
+            inverse.transform_point(origin),
+            inverse.transform_vector(direction),
+        )
+    }
+
+    /// The color the target is cleared with, the background of the scene.
+    fn clear_color(&self) -> Color {
+        match &self.high_contrast {
+            Some(high_contrast) => high_contrast.background,
+            None => Color::WHITE,
+        }
+    }
+
+    fn inverse_pixel_matrix(&self) -> Matrix4 {
+        self.pixel_matrix()
+            .invert()
+            .expect("Internal error: Pixel matrix is not invertible")
+    }
+
+    pub fn preparation_stats(&self) -> PreparationStats {
+        self.preparation_stats
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// Set the quality used for preparing the next frames.
+    ///
+    /// Takes effect with the next invocation of [`Self::apply_changes`].
+    pub fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+    }
+
+    pub fn high_contrast(&self) -> Option<HighContrast> {
+        self.high_contrast
+    }
+
+    /// Replace the colors of the scene with a high contrast palette, or restore them with `None`.
+    ///
+    /// Takes effect with the next invocation of [`Self::apply_changes`], which prepares all
+    /// shapes again.
+    pub fn set_high_contrast(&mut self, high_contrast: Option<HighContrast>) {
+        self.high_contrast = high_contrast;
+    }
+
+    pub fn min_contrast(&self) -> Option<f32> {
+        self.min_contrast
+    }
+
+    /// Adjust the colors of text runs so that they reach a minimum contrast ratio to the quads
+    /// behind them, for example 4.5 for WCAG AA. `None` renders the colors as they are.
+    ///
+    /// This is meant for color schemes that can't be verified up front, like user generated
+    /// ones. Takes effect with the next invocation of [`Self::apply_changes`].
+    pub fn set_min_contrast(&mut self, ratio: Option<f32>) {
+        self.min_contrast = ratio;
+    }
+
+    /// Render the scene and present it.
+    ///
+    /// Does nothing if no surface is attached.
+    // TODO: Can't we handle SurfaceError::Lost here by just reconfiguring the surface and trying
+    // again?
+    #[tracing::instrument(skip_all)]
+    pub fn render_and_present(
+        &mut self,
+        view_projection_matrix: &Matrix4,
+    ) -> result::Result<(), RenderError> {
+        self.render_views_and_present(&[View::new(*view_projection_matrix)])
+    }
+
+    /// Render the scene once for each view and present it.
+    ///
+    /// Use views with viewports to render side by side stereo images, for example.
+    ///
+    /// Does nothing if no surface is attached. Views with matrices that are not finite are
+    /// rejected before the surface texture is acquired.
+    #[tracing::instrument(skip_all)]
+    pub fn render_views_and_present(&mut self, views: &[View]) -> result::Result<(), RenderError> {
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
+        check_views(views)?;
+        let surface_texture = surface.get_current_texture()?;
+        let surface_view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.prepare_animated_shapes();
+        self.backdrop_renderer
+            .prepare_targets(&self.device, self.render_size());
+        self.render_views(&surface_view, views, true);
+
+        surface_texture.present();
+        #[cfg(feature = "tracy")]
+        if let Some(client) = tracy_client::Client::running() {
+            client.frame_mark();
+        }
+        // The frame after the end of an animation shows its final state.
+        self.frame_dirty = self.is_animating();
+        self.presented_views = views.to_vec();
+        Ok(())
+    }
+
+    /// Render the scene once for each view into an external target.
+    ///
+    /// This is meant for hosts that provide their own render targets, like OpenXR swapchain
+    /// images. To render into texture array layers, create a view for each layer and invoke this
+    /// function for each of them. The target's format must match the format of the surface
+    /// configuration, and with backdrops, a format conversion, or a render scale, its size must
+    /// match the size of the surface, too.
+    #[tracing::instrument(skip_all)]
+    pub fn render_views_to(
+        &mut self,
+        target: &wgpu::TextureView,
+        views: &[View],
+    ) -> result::Result<(), RenderError> {
+        check_views(views)?;
+        self.prepare_animated_shapes();
+        self.backdrop_renderer
+            .prepare_targets(&self.device, self.render_size());
+        self.render_views(target, views, true);
+        Ok(())
+    }
+
+    /// Render the scene once for each view into a texture of the surface's format and size and
+    /// copy it into a buffer, for example to take a screenshot. Nothing is presented, and a
+    /// running flash is left out.
+    ///
+    /// Works without an attached surface, too. Read the frame with [`FrameCapture::wait`].
+    #[tracing::instrument(skip_all)]
+    pub fn capture_frame(&mut self, views: &[View]) -> Result<FrameCapture> {
+        check_views(views)?;
+        let (width, height) = self.surface_size();
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Frame Capture Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.prepare_animated_shapes();
+        self.backdrop_renderer
+            .prepare_targets(&self.device, self.render_size());
+        self.render_views(&view, views, false);
+
+        FrameCapture::new(
+            &self.device,
+            &self.queue,
+            &texture,
+            self.surface_config.alpha_mode,
+        )
+    }
+
+    /// Write the vertices of the carets and focus rings at the current time.
+    fn prepare_animated_shapes(&mut self) {
+        let pixel_matrix = self.pixel_matrix();
+        let scene = &self.scene;
+        let high_contrast = self.high_contrast.as_ref();
+        self.caret_renderer.prepare(
+            &self.device,
+            &self.queue,
+            self.time,
+            high_contrast,
+            |position| {
+                (
+                    pixel_matrix * scene.position_matrix(position),
+                    scene.is_overlay(position),
+                )
+            },
+        );
+        self.focus_ring_renderer.prepare(
+            &self.device,
+            &self.queue,
+            self.time,
+            high_contrast,
+            |position| {
+                let bounds = scene.position_bounds(position)?;
+                let bounds =
+                    Bounds::new((bounds.min.x, bounds.min.y), (bounds.max.x, bounds.max.y));
+                Some((bounds, scene.is_overlay(position)))
+            },
+        );
+    }
+
+    fn render_views(&mut self, target: &wgpu::TextureView, views: &[View], flash: bool) {
+        #[cfg(feature = "tracy")]
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.begin_frame(&self.device);
+        }
+        if self.render_bundles {
+            self.record_bundles(views);
+        }
+        // The cached layers are rendered before the target is resolved, because the target
+        // borrows the resampler or the format conversion.
+        for (i, view) in views.iter().enumerate() {
+            self.render_layer_cache(i, view);
+        }
+        let surface_size = self.surface_size();
+        let render_size = self.render_size();
+        // The resampler converts the format, too.
+        if let Some(resampler) = &mut self.resampler {
+            resampler.prepare_target(&self.device, render_size, surface_size);
+        } else if let Some(conversion) = &mut self.format_conversion {
+            conversion.prepare_target(&self.device, surface_size);
+        }
+        let render_target = match (&self.resampler, &self.format_conversion) {
+            (Some(resampler), _) => resampler.target(),
+            (None, Some(conversion)) => conversion.target(),
+            (None, None) => None,
+        }
+        .unwrap_or(target);
+
+        for (i, view) in views.iter().enumerate() {
+            // Only the first view clears the target, the following ones are rendered on top of
+            // it.
+            let load = if i == 0 {
+                // The overdraw heat map adds up on black.
+                wgpu::LoadOp::Clear(match self.debug_mode {
+                    DebugMode::Overdraw => wgpu::Color::BLACK,
+                    _ => to_wgpu_color(self.clear_color()),
+                })
+            } else {
+                wgpu::LoadOp::Load
+            };
+            self.render_view(render_target, i, view, load);
+        }
+
+        if flash {
+            self.flash_renderer
+                .render(&self.device, &self.queue, render_target, self.time);
+        }
+
+        if let Some(resampler) = &self.resampler {
+            resampler.resample(&self.device, &self.queue, target);
+        } else if let Some(conversion) = &self.format_conversion {
+            conversion.convert(&self.device, &self.queue, target);
+        }
+
+        #[cfg(feature = "tracy")]
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.end_frame(&self.device, &self.queue);
+        }
+    }
+
+    /// The timestamps to write for the render pass `name` if the frame is measured, see
+    /// [`crate::profiling_features`].
+    #[cfg(feature = "tracy")]
+    fn pass_timestamps(&self, name: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.gpu_profiler.as_ref()?.pass(name)
+    }
+
+    #[cfg(not(feature = "tracy"))]
+    fn pass_timestamps(&self, _name: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        None
     }
 
-    /// Forget everything known and bootstrap a new set of initial changes.
+    /// Record the bundles of the passes of the views that are not recorded yet.
     ///
-    /// This is for legacy support an should be removed.
-    pub fn bootstrap_changes(
-        &mut self,
-        font_system: &mut text::FontSystem,
-        changes: impl IntoIterator<Item = SceneChange>,
-    ) -> Result<()> {
-        // Reset the scene.
-        self.scene = Scene::default();
-        self.apply_changes(font_system, changes)
+    /// The debug visualizations are always encoded.
+    fn record_bundles(&mut self, views: &[View]) {
+        if self.debug_mode != DebugMode::Off {
+            return;
+        }
+        for view in views {
+            for overlay in [false, true] {
+                let key = BundleKey {
+                    root: view.root,
+                    overlay,
+                };
+                if !self.bundles.contains_key(&key) {
+                    let bundle = self.record_bundle(key);
+                    self.bundles.insert(key, bundle);
+                }
+            }
+        }
     }
 
-    #[tracing::instrument(skip_all)]
-    pub fn apply_changes(
-        &mut self,
-        font_system: &mut text::FontSystem,
-        changes: impl IntoIterator<Item = SceneChange>,
-    ) -> Result<()> {
-        self.scene.transact(changes);
-
-        let mut context = PreparationContext {
-            device: &self.device,
-            queue: &self.queue,
-            font_system,
-        };
+    /// Record the draw calls of the static shapes of a pass.
+    ///
+    /// Returns `None` if they depend on the camera.
+    fn record_bundle(&self, key: BundleKey) -> Option<RecordedBundle> {
+        let state = self.view_state(key.root);
+        let mut encoder =
+            self.device
+                .create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: Some("Static Shapes Bundle"),
+                    color_formats: &[Some(self.render_format())],
+                    depth_stencil: None,
+                    sample_count: 1,
+                    multiview: None,
+                });
 
-        // OO: Lot's of allocations here.
-        let grouped_shapes: Vec<_> = self.scene.grouped_shapes().collect();
+        let (width, height) = self.surface_size();
+        let mut context = self.render_context(
+            &mut encoder,
+            &state,
+            Matrix4::identity(),
+            (width as f32, height as f32),
+            key.overlay,
+        );
+        self.render_static_shapes(&mut context);
+        let (model_matrix, camera_dependent) = (context.model_matrix, context.camera_dependent);
 
-        let pixel_matrix = self.pixel_matrix();
+        if camera_dependent {
+            return None;
+        }
+        let bundle = encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("Static Shapes Bundle"),
+        });
+        Some(RecordedBundle {
+            bundle,
+            model_matrix,
+        })
+    }
 
-        // OO: Lot's of allocations here.
-        // Group by matrix and apply the pixel matrix.
-        let grouped_by_matrix: Vec<_> = grouped_shapes
-            .iter()
-            .map(|(m, v)| (pixel_matrix * *m, v.as_slice()))
-            .collect();
+    /// Render the cached layers of the view at `index` into their texture, unless it's current.
+    fn render_layer_cache(&mut self, index: usize, view: &View) {
+        if self.layer_cache.is_empty() {
+            return;
+        }
+        let key = CacheKey {
+            size: self.render_size(),
+            view_projection_matrix: view.view_projection_matrix,
+            viewport: view.viewport,
+            scissor: view.scissor,
+            root: view.root,
+            matrices: self
+                .prepared
+                .iter()
+                .flat_map(|prepared| &prepared.groups)
+                .filter(|id| self.is_cached(**id))
+                .map(|id| self.scene.position_matrix(*id))
+                .collect(),
+        };
+        if !self.layer_cache.prepare(&self.device, index, key) {
+            return;
+        }
+        let Some(target) = self.layer_cache.target(index) else {
+            return;
+        };
 
-        // OO: parallelize?
-        self.text_layer_renderer
-            .prepare(&mut context, &grouped_by_matrix)?;
-        self.quads_renderer
-            .prepare(&mut context, &grouped_by_matrix)?;
-        Ok(())
+        Self::queue_view_projection_matrix(
+            &self.queue,
+            &self.view_projection_buffer,
+            &view.view_projection_matrix,
+        );
+        let state = self.view_state_of(view.root, true);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Layer Cache Encoder"),
+            });
+        {
+            let mut render_pass = begin_render_pass(
+                &mut encoder,
+                target,
+                wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                self.pass_timestamps("Layer Cache"),
+            );
+            let view_size = self.set_viewport(&mut render_pass, view);
+            let mut context = self.render_context(
+                &mut render_pass,
+                &state,
+                view.view_projection_matrix,
+                view_size,
+                false,
+            );
+            self.render_static_shapes(&mut context);
+            for extension in &self.extensions {
+                extension.render(&mut context);
+            }
+        }
+        self.queue.submit([encoder.finish()]);
     }
 
-    // TODO: Can't we handle SurfaceError::Lost here by just reconfiguring the surface and trying
-    // again?
-    #[tracing::instrument(skip_all)]
-    pub fn render_and_present(
-        &mut self,
-        view_projection_matrix: &Matrix4,
-    ) -> result::Result<(), wgpu::SurfaceError> {
-        let surface_texture = self.surface.get_current_texture()?;
-        let surface_view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// Render one view.
+    ///
+    /// Each view is submitted separately, because the view projection matrix is written to a
+    /// uniform buffer that is shared by all views.
+    ///
+    /// `index` is the index of the view in the views of the frame, which selects the texture of
+    /// the cached layers.
+    fn render_view(
+        &self,
+        target: &wgpu::TextureView,
+        index: usize,
+        view: &View,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let view_projection_matrix = view.view_projection_matrix;
 
         // OO: This should not be needed anymore, because every renderer is now responsible for
         // setting up the view projection.
         Self::queue_view_projection_matrix(
             &self.queue,
             &self.view_projection_buffer,
-            view_projection_matrix,
+            &view_projection_matrix,
         );
 
+        let state = self.view_state(view.root);
+        let has_overlay = state.overlay_groups.contains(&true)
+            || self.caret_renderer.has_overlay()
+            || self.focus_ring_renderer.has_overlay();
+
+        // With backdrops, the world is rendered into an intermediate texture that is blurred and
+        // then copied to the target.
+        let scene_target = self.backdrop_renderer.scene_target();
+
         let command_buffer = {
             let mut encoder = self
                 .device
@@ -170,66 +1877,265 @@ impl<'window> Renderer<'window> {
                 });
 
             {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &surface_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
-                            store: StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    timestamp_writes: None,
-                    occlusion_query_set: None,
-                });
+                let mut render_pass = begin_render_pass(
+                    &mut encoder,
+                    scene_target.unwrap_or(target),
+                    load,
+                    self.pass_timestamps("World"),
+                );
+                let view_size = self.set_viewport(&mut render_pass, view);
+                // The cached layers are behind the rest of the world.
+                self.layer_cache.composite(&mut render_pass, index);
 
-                // DI: There is a lot of view_projection stuff going on.
-                let mut render_context = RenderContext {
-                    queue: &self.queue,
-                    view_projection_buffer: &self.view_projection_buffer,
-                    pass: &mut render_pass,
-                    view_projection_matrix: *view_projection_matrix,
-                    view_projection_bind_group: &self.view_projection_bind_group,
-                };
+                self.render_pass(
+                    &mut render_pass,
+                    &state,
+                    view_projection_matrix,
+                    view_size,
+                    false,
+                );
+
+                // The overlay is drawn on top of the world, in pixels of the view.
+                if has_overlay && scene_target.is_none() {
+                    let overlay_matrix = self.overlay_projection_matrix(view_size);
+                    self.render_pass(&mut render_pass, &state, overlay_matrix, view_size, true);
+                }
+            }
+
+            if scene_target.is_some() {
+                self.backdrop_renderer.blur(&mut encoder);
 
-                self.text_layer_renderer.render(&mut render_context);
-                self.quads_renderer.render(&mut render_context);
-
-                // for pipeline in &self.pipelines {
-                //     let kind = pipeline.0;
-                //     let pipeline = &pipeline.1;
-                //     render_pass.set_pipeline(pipeline);
-                //     render_pass.set_bind_group(0, &self.view_projection_bind_group, &[]);
-                //     render_pass
-                //         .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-
-                //     for primitive in primitives.iter().filter(|p| p.pipeline() == kind) {
-                //         match primitive {
-                //             Primitive::Texture(Texture {
-                //                 bind_group,
-                //                 vertex_buffer,
-                //                 ..
-                //             }) => {
-                //                 render_pass.set_bind_group(1, bind_group, &[]);
-                //                 render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                //                 render_pass.draw_indexed(
-                //                     0..QuadIndexBuffer::QUAD_INDICES_COUNT as u32,
-                //                     0,
-                //                     0..1,
-                //                 );
-                //             }
-                //         }
-                //     }
-                // }
+                let mut render_pass = begin_render_pass(
+                    &mut encoder,
+                    target,
+                    wgpu::LoadOp::Load,
+                    self.pass_timestamps("Backdrops"),
+                );
+                let view_size = self.set_viewport(&mut render_pass, view);
+                self.backdrop_renderer.blit(&mut render_pass);
+
+                // The backdrops of the world are drawn over it, and the ones of the overlay
+                // before the rest of the overlay.
+                self.backdrop_renderer.render(&mut self.render_context(
+                    &mut render_pass,
+                    &state,
+                    view_projection_matrix,
+                    view_size,
+                    false,
+                ));
+                if has_overlay {
+                    let overlay_matrix = self.overlay_projection_matrix(view_size);
+                    self.backdrop_renderer.render(&mut self.render_context(
+                        &mut render_pass,
+                        &state,
+                        overlay_matrix,
+                        view_size,
+                        true,
+                    ));
+                    self.render_pass(&mut render_pass, &state, overlay_matrix, view_size, true);
+                }
             }
+
             encoder.finish()
         };
 
         self.queue.submit([command_buffer]);
-        surface_texture.present();
-        Ok(())
+    }
+
+    /// The prepared shape groups as seen from a view with the given root.
+    ///
+    /// The groups of the cached layers are not rendered, their texture is composited instead.
+    fn view_state(&self, root: Option<Id>) -> ViewState<'_> {
+        self.view_state_of(root, false)
+    }
+
+    /// The prepared shape groups as seen from a view with the given root, either only the ones of
+    /// the cached layers, or only the others.
+    fn view_state_of(&self, root: Option<Id>, cached: bool) -> ViewState<'_> {
+        let groups = self.prepared.iter().flat_map(|prepared| &prepared.groups);
+        ViewState {
+            root,
+            layer_bind_groups: groups
+                .clone()
+                .map(|id| self.layer_bind_groups.bind_group(*id))
+                .collect(),
+            overlay_groups: groups
+                .clone()
+                .map(|id| self.scene.is_overlay(*id))
+                .collect(),
+            view_groups: groups
+                .clone()
+                .map(|id| {
+                    root.map_or(true, |root| self.scene.is_below(*id, root))
+                        && self.is_cached(*id) == cached
+                })
+                .collect(),
+            blend_orders: self.blend_orders(groups),
+            debug_batches: Cell::new(0),
+        }
+    }
+
+    /// Translucent layers are blended over the groups before them, so their batches can't be
+    /// reordered with the batches of other groups.
+    fn blend_orders<'a>(&self, groups: impl Iterator<Item = &'a Id>) -> Vec<u32> {
+        let mut order = 0;
+        groups
+            .map(|id| {
+                let translucent = self
+                    .layer_bind_groups
+                    .get(*id)
+                    .is_some_and(|uniforms| uniforms.tint[3] < 1.0);
+                if !translucent {
+                    return order;
+                }
+                order += 2;
+                order - 1
+            })
+            .collect()
+    }
+
+    fn render_context<'a, 'rpass>(
+        &'rpass self,
+        pass: &'a mut dyn RenderEncoder<'rpass>,
+        state: &'a ViewState<'rpass>,
+        view_projection_matrix: Matrix4,
+        view_size: (f32, f32),
+        overlay: bool,
+    ) -> RenderContext<'a, 'rpass> {
+        // DI: There is a lot of view_projection stuff going on.
+        RenderContext {
+            queue: &self.queue,
+            view_projection_buffer: &self.view_projection_buffer,
+            view_projection_matrix,
+            view_projection_bind_group: &self.view_projection_bind_group,
+            layer_bind_groups: &state.layer_bind_groups,
+            overlay_groups: &state.overlay_groups,
+            overlay,
+            view_groups: &state.view_groups,
+            blend_orders: &state.blend_orders,
+            scene: &self.scene,
+            root: state.root,
+            frame_bind_group: self.frame_bind_group.bind_group(),
+            view_size,
+            debug_mode: self.debug_mode,
+            debug_batches: &state.debug_batches,
+            model_matrix: None,
+            camera_dependent: false,
+            pass,
+        }
+    }
+
+    /// Render the groups of the world or the overlay.
+    ///
+    /// The static shapes are replayed from their bundle if one was recorded.
+    fn render_pass<'rpass>(
+        &'rpass self,
+        pass: &mut wgpu::RenderPass<'rpass>,
+        state: &ViewState<'rpass>,
+        view_projection_matrix: Matrix4,
+        view_size: (f32, f32),
+        overlay: bool,
+    ) {
+        let key = BundleKey {
+            root: state.root,
+            overlay,
+        };
+        let bundle = self
+            .bundles
+            .get(&key)
+            .and_then(Option::as_ref)
+            .filter(|_| self.render_bundles && self.debug_mode == DebugMode::Off);
+        if let Some(RecordedBundle {
+            bundle,
+            model_matrix,
+        }) = bundle
+        {
+            pass.execute_bundles([bundle]);
+            // Leave the matrix in the state the recorded draw calls would have.
+            if let Some(model_matrix) = model_matrix {
+                Self::queue_view_projection_matrix(
+                    &self.queue,
+                    &self.view_projection_buffer,
+                    &(view_projection_matrix * model_matrix),
+                );
+            }
+        }
+
+        let mut context =
+            self.render_context(pass, state, view_projection_matrix, view_size, overlay);
+        if bundle.is_none() {
+            self.render_static_shapes(&mut context);
+        }
+        // Carets are drawn over the text.
+        self.caret_renderer
+            .render(&mut context, &self.layer_bind_groups);
+        // Focus rings surround everything else of their position.
+        self.focus_ring_renderer.render(
+            &mut context,
+            &self.layer_bind_groups,
+            &self.pixel_matrix(),
+        );
+        for extension in &self.extensions {
+            extension.render(&mut context);
+        }
+    }
+
+    /// Render the shapes that don't change between frames, which can be recorded into bundles.
+    fn render_static_shapes<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        // Shadows first, they are behind the panels that cast them.
+        self.shadow_renderer.render(context);
+        // Then the panels the other shapes are placed on.
+        self.rounded_rect_renderer.render(context);
+        // Then quads: Without a depth buffer, backgrounds and selections must be drawn before
+        // the text they are behind.
+        self.quads_renderer.render(context);
+        self.text_layer_renderer.render(context);
+        // Borders mark the content they surround, like selection marquees.
+        self.border_renderer.render(context);
+    }
+
+    /// Restrict the pass to the viewport and the scissor rectangle of `view` and return the size
+    /// of the area the view is rendered to.
+    ///
+    /// Viewports are in pixels of the surface and are scaled to the render size. The returned
+    /// size is in pixels of the surface.
+    fn set_viewport(&self, pass: &mut wgpu::RenderPass, view: &View) -> (f32, f32) {
+        let surface_size = self.surface_size();
+        let render_size = self.render_size();
+        let scale = self.render_scale as f32;
+        let view_size = match view.viewport {
+            Some(viewport) => {
+                let Viewport {
+                    x,
+                    y,
+                    width,
+                    height,
+                } = viewport.scaled(scale);
+                pass.set_viewport(x, y, width, height, 0.0, 1.0);
+                (viewport.width, viewport.height)
+            }
+            None => (surface_size.0 as f32, surface_size.1 as f32),
+        };
+        if let Some(scissor) = view.scissor.or(view.viewport) {
+            // Views outside of the target draw nothing.
+            let (x, y, width, height) = scissor
+                .scaled(scale)
+                .scissor_rect(render_size)
+                .unwrap_or((0, 0, 0, 0));
+            pass.set_scissor_rect(x, y, width, height);
+        }
+        view_size
+    }
+
+    /// The projection of the overlay, which maps pixels of a view of `view_size` to its clip
+    /// space, with the origin at the top left.
+    ///
+    /// The model matrices of the batches include the pixel matrix, which is reverted here.
+    fn overlay_projection_matrix(&self, view_size: (f32, f32)) -> Matrix4 {
+        let (width, height) = (view_size.0 as f64, view_size.1 as f64);
+        let orthographic = Matrix4::from_translation(cgmath::Vector3::new(-1.0, 1.0, 0.5))
+            * Matrix4::from_nonuniform_scale(2.0 / width, -2.0 / height, 0.0);
+        orthographic * self.inverse_pixel_matrix()
     }
 
     fn queue_view_projection_matrix(
@@ -237,12 +2143,7 @@ impl<'window> Renderer<'window> {
         view_projection_buffer: &wgpu::Buffer,
         view_projection_matrix: &Matrix4,
     ) {
-        let view_projection_uniform = {
-            let m: cgmath::Matrix4<f32> = view_projection_matrix
-                .cast()
-                .expect("matrix casting to f32 failed");
-            pods::Matrix4(m.into())
-        };
+        let view_projection_uniform = pods::Matrix4::from(view_projection_matrix);
 
         queue.write_buffer(
             view_projection_buffer,
@@ -254,9 +2155,23 @@ impl<'window> Renderer<'window> {
     /// A Matrix that translates from pixels (0,0)-(width,height) to screen space, which is -1.0 to
     /// 1.0 in each axis. Also flips y.
     pub fn pixel_matrix(&self) -> Matrix4 {
+        self.unit_system(1.0).physical_pixel_matrix()
+    }
+
+    /// The scale of the first view that was presented last at the origin of the scene.
+    ///
+    /// Greeked runs are prepared again when this changes noticeably, for example when the camera
+    /// moves closer.
+    fn view_scale(&self) -> Option<f64> {
+        let view_projection_matrix = self.presented_views.first()?.view_projection_matrix;
+        let matrix = view_projection_matrix * self.pixel_matrix();
+        Some(matrix.y.truncate().truncate().magnitude() / matrix.w.w.abs().max(f64::EPSILON))
+    }
+
+    /// The unit system of the surface with the given scale factor.
+    pub fn unit_system(&self, scale_factor: f64) -> UnitSystem {
         let (_, surface_height) = self.surface_size();
-        Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0)
-            * Matrix4::from_scale(1.0 / surface_height as f64 * 2.0)
+        UnitSystem::new(scale_factor, surface_height)
     }
 
     // A Matrix that projects from normalized view coordinates -1.0 to 1.0 (3D, all axis, Z from 0.1
@@ -291,14 +2206,604 @@ impl<'window> Renderer<'window> {
         (config.width, config.height)
     }
 
+    /// Render the scene at the surface size scaled by `scale` and filter it to the surface.
+    ///
+    /// Scales above 1 supersample the scene, for example for crisp screenshots, scales below 1
+    /// save fill rate on large surfaces. The scale is clamped to the range from 0.25 to 4 and
+    /// the render size to the maximum texture size. A scale of 1 renders to the surface
+    /// directly.
+    ///
+    /// Glyphs are rasterized for the render size, so changing the scale prepares the scene again
+    /// with the next invocation of [`Self::apply_changes`].
+    pub fn set_render_scale(&mut self, scale: f64) {
+        let scale = scale.clamp(0.25, 4.0);
+        if scale == self.render_scale {
+            return;
+        }
+        info!("Render scale: {scale}");
+        self.render_scale = scale;
+        self.resampler = (scale != 1.0).then(|| {
+            Resampler::new(
+                &self.device,
+                self.render_format(),
+                self.surface_config.format,
+            )
+        });
+        self.prepared = None;
+        self.invalidate_bundles();
+    }
+
+    pub fn render_scale(&self) -> f64 {
+        self.render_scale
+    }
+
+    /// The size the scene is rendered at, which is the surface size scaled by the render scale.
+    pub fn render_size(&self) -> (u32, u32) {
+        let (width, height) = self.surface_size();
+        if self.render_scale == 1.0 {
+            return (width, height);
+        }
+        let max = self.device.limits().max_texture_dimension_2d;
+        let scale = |v: u32| ((v as f64 * self.render_scale).round() as u32).clamp(1, max);
+        (scale(width), scale(height))
+    }
+
+    /// The capabilities of the attached surface, `None` if no surface is attached.
+    pub fn surface_capabilities(
+        &self,
+        adapter: &wgpu::Adapter,
+    ) -> Option<wgpu::SurfaceCapabilities> {
+        self.surface
+            .as_ref()
+            .map(|surface| surface.get_capabilities(adapter))
+    }
+
+    /// The format the pipelines render to. This is the format of the surface, unless it needs to
+    /// be converted, see [`crate::renders_directly`].
+    ///
+    /// Extensions must create their pipelines for this format.
+    pub fn render_format(&self) -> wgpu::TextureFormat {
+        self.format_conversion
+            .as_ref()
+            .map_or(self.surface_config.format, |conversion| {
+                conversion.render_format()
+            })
+    }
+
+    /// Changes the format and the alpha mode of the surface and reconfigures it.
+    ///
+    /// If the format the pipelines render to changes, all pipelines are recreated. This drops
+    /// all cached glyphs, but keeps the scene, so that the next call to [`Self::apply_changes`]
+    /// prepares it again.
+    pub fn reconfigure_surface_format(
+        &mut self,
+        surface_format: wgpu::TextureFormat,
+        alpha_mode: wgpu::CompositeAlphaMode,
+    ) {
+        let previous_format = self.render_format();
+        if surface_format != self.surface_config.format {
+            self.format_conversion = FormatConversion::new(&self.device, surface_format);
+            self.surface_config.format = surface_format;
+            if self.resampler.is_some() {
+                self.resampler = Some(Resampler::new(
+                    &self.device,
+                    self.render_format(),
+                    surface_format,
+                ));
+            }
+        }
+        let format = self.render_format();
+        if format != previous_format {
+            let mask_atlas_format = self.text_layer_renderer.mask_atlas_format();
+            self.text_layer_renderer = TextLayerRenderer::new(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+                self.frame_bind_group.layout(),
+            );
+            self.text_layer_renderer
+                .set_mask_atlas_format(&self.device, mask_atlas_format);
+            self.quads_renderer = QuadsRenderer::new(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+            );
+            self.border_renderer = BorderRenderer::new(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+                self.frame_bind_group.layout(),
+            );
+            self.rounded_rect_renderer = RoundedRectRenderer::new(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+            );
+            self.shadow_renderer = ShadowRenderer::new(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+            );
+            self.backdrop_renderer = BackdropRenderer::new(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+            );
+            self.caret_renderer.target_format_changed(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+            );
+            self.focus_ring_renderer.target_format_changed(
+                &self.device,
+                format,
+                &self.view_projection_bind_group_layout,
+                self.layer_bind_groups.layout(),
+            );
+            self.flash_renderer
+                .target_format_changed(&self.device, format);
+            self.layer_cache.target_format_changed(&self.device, format);
+            for extension in &mut self.extensions {
+                extension.target_format_changed(&self.device, format);
+            }
+            self.prepared = None;
+            self.invalidate_bundles();
+        }
+
+        self.surface_config.alpha_mode = alpha_mode;
+        self.reconfigure_surface();
+    }
+
+    /// Present with `present_mode`, which must be supported by the surface.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        if present_mode == self.surface_config.present_mode {
+            return;
+        }
+        self.surface_config.present_mode = present_mode;
+        self.reconfigure_surface();
+    }
+
+    /// Configures the attached surface with the current configuration.
     pub fn reconfigure_surface(&mut self) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
         info!("Reconfiguring surface {:?}", self.surface_config);
-        self.surface.configure(&self.device, &self.surface_config)
+        surface.configure(&self.device, &self.surface_config);
+        self.frame_dirty = true;
     }
 }
 
-impl RenderContext<'_, '_> {
-    pub fn queue_view_projection_matrix(&self, matrix: &Matrix4) {
+impl<'rpass> RenderContext<'_, 'rpass> {
+    /// The bind group of the [`LayerUniforms`] of a prepared shape group.
+    pub fn layer_bind_group(&self, group: usize) -> &'rpass wgpu::BindGroup {
+        self.layer_bind_groups[group]
+    }
+
+    /// Whether the batches of a prepared shape group are rendered in this pass.
+    ///
+    /// Each view is rendered in two passes, first the world through the camera, and then the
+    /// overlay on top of it. Renderers must skip the batches of the groups that belong to the
+    /// other pass.
+    ///
+    /// Groups that are not below the root of the view are skipped in both passes.
+    pub fn renders_group(&self, group: usize) -> bool {
+        self.overlay_groups[group] == self.overlay && self.view_groups[group]
+    }
+
+    /// The batches of groups with the same blend order may be drawn in any order to reduce state
+    /// changes. A group of a translucent layer has a blend order of its own.
+    pub fn blend_order(&self, group: usize) -> u32 {
+        self.blend_orders[group]
+    }
+
+    /// Whether shapes that are not part of a prepared group, like carets, are rendered in the
+    /// view if they are placed at `position`.
+    pub fn renders_position(&self, position: Id) -> bool {
+        self.root
+            .map_or(true, |root| self.scene.is_below(position, root))
+    }
+
+    /// `true` if the overlay is rendered in this pass.
+    pub fn is_overlay_pass(&self) -> bool {
+        self.overlay
+    }
+
+    /// The index of the next batch rendered in a debug mode, unique in the view.
+    pub(crate) fn next_debug_batch(&mut self) -> u32 {
+        let batch = self.debug_batches.get();
+        self.debug_batches.set(batch + 1);
+        batch
+    }
+
+    /// Write the view projection matrix multiplied with `model_matrix` for the following draw
+    /// calls.
+    ///
+    /// Prefer this over [`Self::queue_view_projection_matrix`], because the draw calls can then
+    /// be replayed with another camera.
+    pub fn queue_model_matrix(&mut self, model_matrix: &Matrix4) {
+        Renderer::queue_view_projection_matrix(
+            self.queue,
+            self.view_projection_buffer,
+            &(self.view_projection_matrix * model_matrix),
+        );
+        self.model_matrix = Some(*model_matrix);
+    }
+
+    /// Write a matrix for the following draw calls.
+    ///
+    /// The draw calls of the pass are assumed to depend on the camera then, so they are not
+    /// replayed from a bundle.
+    pub fn queue_view_projection_matrix(&mut self, matrix: &Matrix4) {
         Renderer::queue_view_projection_matrix(self.queue, self.view_projection_buffer, matrix);
+        self.mark_camera_dependent();
+    }
+
+    /// Prevent that the draw calls of the pass are replayed from a bundle.
+    pub(crate) fn mark_camera_dependent(&mut self) {
+        self.camera_dependent = true;
+        self.model_matrix = None;
+    }
+}
+
+/// Whether `bounds` overlap the clip space volume after being transformed by `clip_matrix`.
+fn is_in_view(bounds: &Bounds3, clip_matrix: &Matrix4) -> bool {
+    let clip = bounds.transformed(clip_matrix);
+    clip.min.x <= 1.0 && clip.max.x >= -1.0 && clip.min.y <= 1.0 && clip.max.y >= -1.0
+}
+
+/// Whether greeking measured with the view scale `prepared` is still valid for `current`.
+///
+/// Greeking thresholds are coarse, so changes of up to a quarter are tolerated. Otherwise an
+/// animated camera would prepare the scene again with every frame.
+fn similar_view_scale(prepared: Option<f64>, current: Option<f64>) -> bool {
+    match (prepared, current) {
+        (Some(prepared), Some(current)) => {
+            let ratio = current / prepared;
+            (0.8..=1.25).contains(&ratio)
+        }
+        (prepared, current) => prepared.is_none() && current.is_none(),
+    }
+}
+
+fn check_views(views: &[View]) -> result::Result<(), RenderError> {
+    if !views.iter().all(View::is_finite) {
+        return Err(RenderError::InvalidMatrix);
+    }
+    Ok(())
+}
+
+fn replace_fonts(shape: &mut Shape, replacements: &HashMap<text::fontdb::ID, text::fontdb::ID>) {
+    if let Shape::GlyphRun(run) = shape {
+        for glyph in &mut run.glyphs {
+            if let Some(font_id) = replacements.get(&glyph.key.font_id) {
+                glyph.key.font_id = *font_id;
+            }
+        }
+    }
+}
+
+/// Route the changes of carets to the caret renderer.
+///
+/// Returns the change if it's meant for the scene.
+fn route_caret_change(
+    carets: &mut CaretRenderer,
+    change: SceneChange,
+    time: f32,
+) -> Option<SceneChange> {
+    match change {
+        SceneChange::PositionedShape(Change::Create(
+            id,
+            PositionedRenderShape {
+                position,
+                shape: Shape::Caret(caret),
+            },
+        )) => {
+            carets.set(id, position, caret, time);
+            None
+        }
+        SceneChange::PositionedShape(Change::Update(
+            id,
+            PositionedRenderShape {
+                position,
+                shape: Shape::Caret(caret),
+            },
+        )) => {
+            let was_caret = carets.contains(id);
+            carets.set(id, position, caret, time);
+            // The shape was something else before, so it leaves the scene.
+            (!was_caret).then_some(SceneChange::PositionedShape(Change::Delete(id)))
+        }
+        // The shape was a caret before, so it's new to the scene.
+        SceneChange::PositionedShape(Change::Update(id, shape)) if carets.remove(id) => {
+            Some(SceneChange::PositionedShape(Change::Create(id, shape)))
+        }
+        SceneChange::PositionedShape(Change::Delete(id)) if carets.remove(id) => None,
+        // Carets are always visible.
+        SceneChange::ShapeVisibility(id, _) if carets.contains(id) => None,
+        change => Some(change),
+    }
+}
+
+/// Route the changes of focus rings to the focus ring renderer.
+///
+/// Returns the change if it's meant for the scene.
+fn route_focus_ring_change(
+    rings: &mut FocusRingRenderer,
+    change: SceneChange,
+    time: f32,
+) -> Option<SceneChange> {
+    match change {
+        SceneChange::PositionedShape(Change::Create(
+            id,
+            PositionedRenderShape {
+                position,
+                shape: Shape::FocusRing(ring),
+            },
+        )) => {
+            rings.set(id, position, ring, time);
+            None
+        }
+        SceneChange::PositionedShape(Change::Update(
+            id,
+            PositionedRenderShape {
+                position,
+                shape: Shape::FocusRing(ring),
+            },
+        )) => {
+            let was_ring = rings.contains(id);
+            rings.set(id, position, ring, time);
+            // The shape was something else before, so it leaves the scene.
+            (!was_ring).then_some(SceneChange::PositionedShape(Change::Delete(id)))
+        }
+        // The shape was a focus ring before, so it's new to the scene.
+        SceneChange::PositionedShape(Change::Update(id, shape)) if rings.remove(id) => {
+            Some(SceneChange::PositionedShape(Change::Create(id, shape)))
+        }
+        SceneChange::PositionedShape(Change::Delete(id)) if rings.remove(id) => None,
+        // Focus rings are always visible.
+        SceneChange::ShapeVisibility(id, _) if rings.contains(id) => None,
+        change => Some(change),
+    }
+}
+
+fn begin_render_pass<'encoder>(
+    encoder: &'encoder mut wgpu::CommandEncoder,
+    target: &'encoder wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+    timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'encoder>>,
+) -> wgpu::RenderPass<'encoder> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes,
+        occlusion_query_set: None,
+    })
+}
+
+fn to_wgpu_color(color: Color) -> wgpu::Color {
+    wgpu::Color {
+        r: color.red as f64,
+        g: color.green as f64,
+        b: color.blue as f64,
+        a: color.alpha as f64,
+    }
+}
+
+/// The cost of uploading a shape, in glyphs or quads.
+fn upload_cost(shape: &Shape) -> usize {
+    match shape {
+        Shape::GlyphRun(run) => run.glyphs.len(),
+        Shape::Quads(quads) => quads.len(),
+        // The nine slices of a shadow.
+        Shape::Shadow(_) => 9,
+        Shape::Backdrop(_) => 1,
+        // The four edges of a border.
+        Shape::Border(_) => 4,
+        Shape::RoundedRect(_) => 1,
+        Shape::Caret(_) | Shape::FocusRing(_) => 0,
+        Shape::Custom(shape) => shape.upload_cost(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use massive_scene::PositionRenderObj;
+
+    use super::*;
+
+    /// Drives transactions through a scene and counts the preparation steps like
+    /// [`Renderer::apply_changes`].
+    #[derive(Default)]
+    struct Preparations {
+        scene: Scene,
+        prepared: Option<PreparationSettings>,
+        stats: PreparationStats,
+    }
+
+    impl Preparations {
+        fn apply(
+            &mut self,
+            settings: PreparationSettings,
+            changes: impl IntoIterator<Item = SceneChange>,
+        ) -> PreparationStep {
+            let transaction = self.scene.transact(changes);
+            let step = PreparationStep::new(self.prepared.as_ref(), &settings, transaction);
+            if step == PreparationStep::Prepare {
+                self.prepared = Some(settings);
+            }
+            self.stats.record(step, false);
+            step
+        }
+    }
+
+    fn settings() -> PreparationSettings {
+        PreparationSettings {
+            quality: Quality::FULL,
+            high_contrast: None,
+            min_contrast: None,
+            surface_size: (800, 600),
+            view_scale: Some(1.0),
+        }
+    }
+
+    fn greeking() -> PreparationSettings {
+        PreparationSettings {
+            quality: Quality {
+                greeking_threshold: Some(4.0),
+                ..Quality::FULL
+            },
+            ..settings()
+        }
+    }
+
+    fn id() -> Id {
+        Id::from_raw(0)
+    }
+
+    fn create_shape() -> Vec<SceneChange> {
+        vec![
+            SceneChange::Matrix(Change::Create(id(), Matrix4::identity())),
+            SceneChange::Position(Change::Create(
+                id(),
+                PositionRenderObj {
+                    parent: None,
+                    matrix: id(),
+                    pin: None,
+                    overlay: false,
+                },
+            )),
+            SceneChange::PositionedShape(Change::Create(
+                id(),
+                PositionedRenderShape {
+                    position: id(),
+                    shape: Shape::Quads(Vec::new()),
+                },
+            )),
+        ]
+    }
+
+    fn move_shape(x: f64) -> SceneChange {
+        SceneChange::Matrix(Change::Update(
+            id(),
+            Matrix4::from_translation(Vector3::new(x, 0.0, 0.0)),
+        ))
+    }
+
+    fn stats(preparations: u64, matrix_updates: u64, skipped: u64) -> PreparationStats {
+        PreparationStats {
+            preparations,
+            matrix_updates,
+            skipped,
+            ..PreparationStats::default()
+        }
+    }
+
+    #[test]
+    fn matrix_changes_only_update_matrices() {
+        let mut preparations = Preparations::default();
+        assert_eq!(
+            preparations.apply(settings(), create_shape()),
+            PreparationStep::Prepare
+        );
+        for x in 1..=3 {
+            assert_eq!(
+                preparations.apply(settings(), [move_shape(x as f64)]),
+                PreparationStep::UpdateMatrices
+            );
+        }
+        assert_eq!(preparations.stats, stats(1, 3, 0));
+    }
+
+    #[test]
+    fn empty_transactions_are_skipped() {
+        let mut preparations = Preparations::default();
+        preparations.apply(settings(), create_shape());
+        // Camera changes don't change the scene.
+        preparations.apply(settings(), []);
+        preparations.apply(settings(), []);
+        assert_eq!(preparations.stats, stats(1, 0, 2));
+    }
+
+    #[test]
+    fn pending_preparations_are_not_skipped() {
+        let mut stats = PreparationStats::default();
+        stats.record(PreparationStep::Keep, true);
+        assert_eq!(stats, PreparationStats::default());
+    }
+
+    #[test]
+    fn shape_changes_prepare_again() {
+        let mut preparations = Preparations::default();
+        preparations.apply(settings(), create_shape());
+        let update = SceneChange::PositionedShape(Change::Update(
+            id(),
+            PositionedRenderShape {
+                position: id(),
+                shape: Shape::Quads(Vec::new()),
+            },
+        ));
+        assert_eq!(
+            preparations.apply(settings(), [update]),
+            PreparationStep::Prepare
+        );
+        assert_eq!(
+            preparations.apply(settings(), [SceneChange::ShapeVisibility(id(), false)]),
+            PreparationStep::Prepare
+        );
+        assert_eq!(preparations.stats, stats(3, 0, 0));
+    }
+
+    #[test]
+    fn setting_changes_prepare_again() {
+        let mut preparations = Preparations::default();
+        preparations.apply(settings(), create_shape());
+        let resized = PreparationSettings {
+            surface_size: (1024, 768),
+            ..settings()
+        };
+        assert_eq!(preparations.apply(resized, []), PreparationStep::Prepare);
+        assert_eq!(preparations.apply(resized, []), PreparationStep::Keep);
+        assert_eq!(preparations.stats, stats(2, 0, 1));
+    }
+
+    #[test]
+    fn greeking_prepares_moved_and_zoomed_shapes_again() {
+        let mut preparations = Preparations::default();
+        preparations.apply(greeking(), create_shape());
+        assert_eq!(
+            preparations.apply(greeking(), [move_shape(1.0)]),
+            PreparationStep::Prepare
+        );
+
+        let slightly_zoomed = PreparationSettings {
+            view_scale: Some(1.1),
+            ..greeking()
+        };
+        assert_eq!(
+            preparations.apply(slightly_zoomed, []),
+            PreparationStep::Keep
+        );
+        let zoomed = PreparationSettings {
+            view_scale: Some(2.0),
+            ..greeking()
+        };
+        assert_eq!(preparations.apply(zoomed, []), PreparationStep::Prepare);
+        assert_eq!(preparations.stats, stats(3, 0, 1));
     }
 }
@@ -0,0 +1,194 @@
+//! Rendering at a different resolution than the target.
+//!
+//! With a render scale other than 1, the scene is rendered into an intermediate texture of the
+//! scaled size, which is then filtered to the target: Above 1, this supersamples the scene, for
+//! example for screenshots, below 1, it saves fill rate on large surfaces.
+
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use log::info;
+use static_assertions::const_assert_eq;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::{
+    bind_group_entries,
+    tools::{create_pipeline, texture_sampler, BindGroupLayoutBuilder},
+};
+
+/// The sizes the intermediate texture is filtered with.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Pod, Zeroable)]
+struct ResampleUniforms {
+    source_size: [f32; 2],
+    target_size: [f32; 2],
+}
+
+// WebGL uniform requirement
+const_assert_eq!(size_of::<ResampleUniforms>() % 16, 0);
+
+/// Renders through an intermediate texture of a scaled size and filters it to the target.
+///
+/// This writes the target without blending, so it also converts to formats the pipelines can't
+/// render to, like the format conversion does.
+pub struct Resampler {
+    render_format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    /// A texture, a sampler, and the resample uniforms.
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    intermediate: Option<Intermediate>,
+}
+
+/// The texture the scene is rendered to, for one size of the target.
+struct Intermediate {
+    size: (u32, u32),
+    target_size: (u32, u32),
+    view: wgpu::TextureView,
+    /// Binds the texture to filter it.
+    bind_group: wgpu::BindGroup,
+}
+
+impl Resampler {
+    /// A resampler that renders to `render_format` and filters to targets of `target_format`.
+    pub fn new(
+        device: &wgpu::Device,
+        render_format: wgpu::TextureFormat,
+        target_format: wgpu::TextureFormat,
+    ) -> Self {
+        info!("Resampling from {render_format:?} to {target_format:?}");
+
+        let bind_group_layout = BindGroupLayoutBuilder::fragment()
+            .texture()
+            .sampler()
+            .uniform()
+            .build("Resample Bind Group Layout", device);
+
+        let shader = &crate::shader_module!(device, "resample/resample.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Resample Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let pipeline = create_pipeline(
+            "Resample Pipeline",
+            device,
+            shader,
+            "fs_resample",
+            &[],
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            render_format,
+            pipeline,
+            bind_group_layout,
+            sampler: texture_sampler::linear_clamping(device),
+            intermediate: None,
+        }
+    }
+
+    /// Create the intermediate texture of `size` for a target of `target_size`.
+    pub fn prepare_target(
+        &mut self,
+        device: &wgpu::Device,
+        size: (u32, u32),
+        target_size: (u32, u32),
+    ) {
+        if self.intermediate.as_ref().map(|i| (i.size, i.target_size)) == Some((size, target_size))
+        {
+            return;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Resample Source"),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.render_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let uniforms = ResampleUniforms {
+            source_size: [size.0 as f32, size.1 as f32],
+            target_size: [target_size.0 as f32, target_size.1 as f32],
+        };
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Resample Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniforms),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Resample Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: bind_group_entries!(0 => &view, 1 => &self.sampler, 2 => &uniform_buffer),
+        });
+
+        self.intermediate = Some(Intermediate {
+            size,
+            target_size,
+            view,
+            bind_group,
+        });
+    }
+
+    /// Release the intermediate texture, it is created again with the next
+    /// [`Self::prepare_target`].
+    pub fn release_target(&mut self) {
+        self.intermediate = None;
+    }
+
+    /// The texture to render to instead of the target, `None` before [`Self::prepare_target`].
+    pub fn target(&self) -> Option<&wgpu::TextureView> {
+        self.intermediate
+            .as_ref()
+            .map(|intermediate| &intermediate.view)
+    }
+
+    /// Filter the intermediate texture to `target`, which must have the size it was prepared
+    /// for.
+    pub fn resample(&self, device: &wgpu::Device, queue: &wgpu::Queue, target: &wgpu::TextureView) {
+        let Some(intermediate) = &self.intermediate else {
+            return;
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Resample Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Resample Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &intermediate.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+    }
+}
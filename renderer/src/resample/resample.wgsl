@@ -0,0 +1,50 @@
+// Filters the scene rendered at a scaled size to the target.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A triangle that covers the target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 2u), f32((index << 1u) & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+// See `ResampleUniforms`.
+struct Resample {
+    source_size: vec2<f32>,
+    target_size: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> resample: Resample;
+
+// The most taps per axis when downscaling.
+const MAX_TAPS: f32 = 4.0;
+
+// Upscaled pixels are interpolated bilinearly. Downscaled pixels average bilinear taps spread
+// over the source pixels they cover, so that no source pixel is skipped up to a scale of 4.
+@fragment
+fn fs_resample(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Source pixels per target pixel.
+    let footprint = resample.source_size / resample.target_size;
+    let center = in.clip_position.xy * footprint;
+    let taps = min(ceil(max(footprint, vec2<f32>(1.0))), vec2<f32>(MAX_TAPS));
+
+    var sum = vec4<f32>(0.0);
+    for (var y = 0.0; y < taps.y; y = y + 1.0) {
+        for (var x = 0.0; x < taps.x; x = x + 1.0) {
+            let offset = (vec2<f32>(x, y) + 0.5) / taps - 0.5;
+            let position = center + offset * footprint;
+            sum += textureSampleLevel(t_source, s_source, position / resample.source_size, 0.0);
+        }
+    }
+    return sum / (taps.x * taps.y);
+}
@@ -0,0 +1,262 @@
+use anyhow::Result;
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_scene::Shape;
+use massive_shapes::RoundedRect;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BufferUsages,
+};
+
+use crate::{
+    debug::DebugPipelines,
+    pods::PanelVertex,
+    renderer::{PreparationContext, RenderContext},
+    tools::{create_pipeline, QuadIndexBuffer},
+};
+
+/// Renders filled rounded rectangles with borders.
+///
+/// Every rectangle is drawn as one quad. The fragment shader computes the signed distance to the
+/// rounded outline from the position relative to the center, and derives the fill, the border,
+/// and the anti-aliased edge from it.
+pub struct RoundedRectRenderer {
+    pipeline: wgpu::RenderPipeline,
+    index_buffer: QuadIndexBuffer,
+    debug: DebugPipelines,
+
+    layers: Vec<RoundedRectsLayer>,
+}
+
+struct RoundedRectsLayer {
+    /// The index of the shape group this layer was prepared from.
+    group: usize,
+    model_matrix: Matrix4,
+    vertex_buffer: wgpu::Buffer,
+    quad_count: usize,
+}
+
+impl RoundedRectRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = &crate::shader_module!(device, "rounded_rects/rounded_rects.wgsl");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Rounded Rects Pipeline Layout"),
+            bind_group_layouts: &[view_projection_bind_group_layout, layer_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let vertex_layout = [PanelVertex::layout()];
+
+        let pipeline = create_pipeline(
+            "Rounded Rects Pipeline",
+            device,
+            shader,
+            "fs_rounded_rect",
+            &vertex_layout,
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            pipeline,
+            index_buffer: QuadIndexBuffer::new(device),
+            debug: DebugPipelines::new(
+                device,
+                target_format,
+                view_projection_bind_group_layout,
+                PanelVertex::layout().array_stride,
+            ),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Drop all prepared layers.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Prepare shape groups and add them to the prepared layers.
+    ///
+    /// `first_group` is the index of the first group in `shapes` among all groups prepared since
+    /// the last [`Self::clear`].
+    #[tracing::instrument(name = "prepare_rounded_rects", skip_all)]
+    pub fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        shapes: &[(Matrix4, &[&Shape])],
+    ) -> Result<()> {
+        let mut max_quads = 0;
+
+        for (group, (matrix, shapes)) in (first_group..).zip(shapes) {
+            if let Some(layer) = self.prepare_rects(
+                context,
+                group,
+                matrix,
+                shapes.iter().filter_map(|s| match s {
+                    Shape::RoundedRect(rect) => Some(rect),
+                    _ => None,
+                }),
+            ) {
+                max_quads = max_quads.max(layer.quad_count);
+                self.layers.push(layer)
+            }
+        }
+
+        self.index_buffer
+            .ensure_can_index_num_quads(context.device, max_quads);
+        self.debug.prepare(context.device, max_quads);
+
+        Ok(())
+    }
+
+    /// Update the model matrices of the prepared layers without preparing them again.
+    ///
+    /// `matrices` must be in the order of the shape groups passed to [`Self::prepare`].
+    pub fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for layer in &mut self.layers {
+            layer.model_matrix = matrices[layer.group];
+        }
+    }
+
+    pub fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        if context.debug_mode.renders_scene() {
+            self.render_layers(context);
+        }
+
+        let batches: Vec<_> = self
+            .layers
+            .iter()
+            .filter(|l| context.renders_group(l.group))
+            .map(|l| (l.model_matrix, l.vertex_buffer.slice(..), l.quad_count))
+            .collect();
+        self.debug.render(context, &self.index_buffer, &batches);
+    }
+
+    fn render_layers<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        let pass = &mut context.pass;
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        for RoundedRectsLayer {
+            group,
+            model_matrix,
+            vertex_buffer,
+            quad_count,
+        } in &self.layers
+        {
+            if !context.renders_group(*group) {
+                continue;
+            }
+            context.queue_model_matrix(model_matrix);
+
+            let layer_bind_group = context.layer_bind_group(*group);
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_group, &[]);
+
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+            pass.draw_indexed(
+                0..(QuadIndexBuffer::INDICES_PER_QUAD * quad_count) as u32,
+                0,
+                0..1,
+            )
+        }
+    }
+
+    fn prepare_rects<'a>(
+        &mut self,
+        context: &mut PreparationContext,
+        group: usize,
+        model_matrix: &Matrix4,
+        rects: impl Iterator<Item = &'a RoundedRect>,
+    ) -> Option<RoundedRectsLayer> {
+        let mut vertices = Vec::new();
+
+        for rect in rects {
+            let (fill, border_color) = match &context.high_contrast {
+                Some(high_contrast) => (
+                    high_contrast.fill(rect.fill),
+                    rect.border.map(|b| high_contrast.fill(b.color)),
+                ),
+                None => (rect.fill, rect.border.map(|b| b.color)),
+            };
+            push_rounded_rect(&mut vertices, rect, fill, border_color);
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+
+        let vertex_buffer = context.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Rounded Rects Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+
+        Some(RoundedRectsLayer {
+            group,
+            model_matrix: *model_matrix,
+            vertex_buffer,
+            quad_count: vertices.len() >> 2,
+        })
+    }
+}
+
+fn push_rounded_rect(
+    vertices: &mut Vec<PanelVertex>,
+    rect: &RoundedRect,
+    fill: Color,
+    border_color: Option<Color>,
+) {
+    let bounds = rect.bounds;
+    let half_size = [
+        ((bounds.max.x - bounds.min.x) / 2.0) as f32,
+        ((bounds.max.y - bounds.min.y) / 2.0) as f32,
+    ];
+    if half_size[0] <= 0.0 || half_size[1] <= 0.0 {
+        return;
+    }
+    let max_radius = half_size[0].min(half_size[1]);
+    let radii = &rect.radii;
+    let radii = [
+        radii.top_left,
+        radii.top_right,
+        radii.bottom_right,
+        radii.bottom_left,
+    ]
+    .map(|r| (r as f32).clamp(0.0, max_radius));
+    let border_width = rect
+        .border
+        .map_or(0.0, |b| (b.width as f32).clamp(0.0, max_radius));
+    let border_color = border_color.unwrap_or(Color::new(0.0, 0.0, 0.0, 0.0));
+
+    let corners = [
+        (bounds.min.x, bounds.min.y, -1.0, -1.0),
+        (bounds.min.x, bounds.max.y, -1.0, 1.0),
+        (bounds.max.x, bounds.max.y, 1.0, 1.0),
+        (bounds.max.x, bounds.min.y, 1.0, -1.0),
+    ];
+    vertices.extend(corners.map(|(x, y, sx, sy)| PanelVertex {
+        position: Vector3::new(x, y, 0.0).into(),
+        local: [sx * half_size[0], sy * half_size[1]],
+        half_size,
+        radii,
+        fill: fill.into(),
+        border_color: border_color.into(),
+        border_width,
+    }));
+}
@@ -0,0 +1,103 @@
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> model_view: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    // The position relative to the center of the rectangle.
+    @location(1) local: vec2<f32>,
+    @location(2) half_size: vec2<f32>,
+    // top left, top right, bottom right, bottom left
+    @location(3) radii: vec4<f32>,
+    @location(4) fill: vec4<f32>,
+    @location(5) border_color: vec4<f32>,
+    @location(6) border_width: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) half_size: vec2<f32>,
+    @location(2) @interpolate(flat) radii: vec4<f32>,
+    @location(3) @interpolate(flat) fill: vec4<f32>,
+    @location(4) @interpolate(flat) border_color: vec4<f32>,
+    @location(5) @interpolate(flat) border_width: f32,
+}
+
+@vertex
+fn vs_main(
+    vertex_input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
+    out.local = vertex_input.local;
+    out.half_size = vertex_input.half_size;
+    out.radii = vertex_input.radii;
+    out.fill = vertex_input.fill;
+    out.border_color = vertex_input.border_color;
+    out.border_width = vertex_input.border_width;
+    return out;
+}
+
+// Fragment shader
+
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> layer: Layer;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+// The radius of the corner of the quadrant `p` is in. y points down.
+fn corner_radius(p: vec2<f32>, radii: vec4<f32>) -> f32 {
+    if (p.y < 0.0) {
+        return select(radii.y, radii.x, p.x < 0.0);
+    }
+    return select(radii.z, radii.w, p.x < 0.0);
+}
+
+@fragment
+fn fs_rounded_rect(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The signed distance to the rounded rectangle, negative inside.
+    let radius = corner_radius(in.local, in.radii);
+    let q = abs(in.local) - (in.half_size - vec2<f32>(radius));
+    let distance = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+
+    // Anti-alias the outer edge and the inner edge of the border over a pixel.
+    let aa = max(fwidth(distance), 0.0001);
+    let coverage = clamp(0.5 - distance / aa, 0.0, 1.0);
+    var inside = 1.0;
+    if (in.border_width > 0.0) {
+        inside = clamp(0.5 - (distance + in.border_width) / aa, 0.0, 1.0);
+    }
+
+    // Blend the border over the fill with premultiplied colors.
+    let fill = vec4<f32>(in.fill.rgb * in.fill.a, in.fill.a) * inside;
+    let border = vec4<f32>(in.border_color.rgb * in.border_color.a, in.border_color.a)
+        * (1.0 - inside);
+    let color = (fill + border) * coverage;
+    if (color.a <= 0.0) {
+        discard;
+    }
+    return layer_color(vec4<f32>(color.rgb / color.a, color.a));
+}
@@ -0,0 +1,252 @@
+//! A dynamic bounding volume hierarchy.
+//!
+//! Leaves can be inserted, moved, and removed individually. The tree is not rebalanced, insertion
+//! picks the sibling that minimizes the growth of the surface areas along the path, which keeps
+//! the tree reasonably compact for the mostly static scenes we render.
+
+use massive_geometry::{scalar, Bounds3, Point3, Vector3};
+
+/// Refers to a leaf in the tree. Stable until the leaf is removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LeafId(usize);
+
+#[derive(Debug)]
+pub struct BoundsTree<T> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    root: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Node<T> {
+    bounds: Bounds3,
+    parent: Option<usize>,
+    kind: NodeKind<T>,
+}
+
+#[derive(Debug)]
+enum NodeKind<T> {
+    Leaf(T),
+    Branch(usize, usize),
+    Free,
+}
+
+impl<T> Default for BoundsTree<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: None,
+        }
+    }
+}
+
+impl<T> BoundsTree<T> {
+    /// The bounds of all leaves, `None` if the tree is empty.
+    pub fn bounds(&self) -> Option<Bounds3> {
+        self.root.map(|root| self.nodes[root].bounds)
+    }
+
+    pub fn leaf_bounds(&self, leaf: LeafId) -> Bounds3 {
+        self.nodes[leaf.0].bounds
+    }
+
+    pub fn insert(&mut self, bounds: Bounds3, value: T) -> LeafId {
+        let leaf = self.allocate(Node {
+            bounds,
+            parent: None,
+            kind: NodeKind::Leaf(value),
+        });
+        self.attach(leaf);
+        LeafId(leaf)
+    }
+
+    pub fn remove(&mut self, leaf: LeafId) -> T {
+        self.detach(leaf.0);
+        let node = &mut self.nodes[leaf.0];
+        let NodeKind::Leaf(value) = std::mem::replace(&mut node.kind, NodeKind::Free) else {
+            panic!("Internal error: Removing a node that is not a leaf");
+        };
+        self.free.push(leaf.0);
+        value
+    }
+
+    /// Change the bounds of a leaf.
+    pub fn update(&mut self, leaf: LeafId, bounds: Bounds3) {
+        if self.nodes[leaf.0].bounds == bounds {
+            return;
+        }
+        self.detach(leaf.0);
+        self.nodes[leaf.0].bounds = bounds;
+        self.attach(leaf.0);
+    }
+
+    /// Visit all leaves whose bounds intersect `bounds`.
+    pub fn intersecting(&self, bounds: &Bounds3, f: impl FnMut(&T, &Bounds3)) {
+        self.visit(|b| b.intersects(bounds), f)
+    }
+
+    /// Visit all leaves hit by a ray together with the distance at which the ray enters their
+    /// bounds.
+    pub fn hit_by_ray(
+        &self,
+        origin: Point3,
+        direction: Vector3,
+        mut f: impl FnMut(&T, &Bounds3, scalar),
+    ) {
+        self.visit(
+            |b| b.ray_intersection(origin, direction).is_some(),
+            |value, b| {
+                if let Some(distance) = b.ray_intersection(origin, direction) {
+                    f(value, b, distance)
+                }
+            },
+        )
+    }
+
+    /// Visit all leaves that pass `filter`. Branches that don't pass are skipped entirely.
+    pub fn visit(&self, mut filter: impl FnMut(&Bounds3) -> bool, mut f: impl FnMut(&T, &Bounds3)) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !filter(&node.bounds) {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Leaf(value) => f(value, &node.bounds),
+                NodeKind::Branch(a, b) => stack.extend([*a, *b]),
+                NodeKind::Free => panic!("Internal error: Visiting a free node"),
+            }
+        }
+    }
+
+    fn allocate(&mut self, node: Node<T>) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Attach a detached leaf to the tree.
+    fn attach(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.nodes[leaf].parent = None;
+            self.root = Some(leaf);
+            return;
+        };
+
+        let bounds = self.nodes[leaf].bounds;
+        let sibling = self.find_sibling(root, &bounds);
+
+        let old_parent = self.nodes[sibling].parent;
+        let parent = self.allocate(Node {
+            bounds: self.nodes[sibling].bounds.join(&bounds),
+            parent: old_parent,
+            kind: NodeKind::Branch(sibling, leaf),
+        });
+        self.nodes[sibling].parent = Some(parent);
+        self.nodes[leaf].parent = Some(parent);
+
+        match old_parent {
+            Some(old_parent) => {
+                self.replace_child(old_parent, sibling, parent);
+                self.refit(old_parent);
+            }
+            None => self.root = Some(parent),
+        }
+    }
+
+    /// Find the node that should become the sibling of a leaf with `bounds`.
+    fn find_sibling(&self, root: usize, bounds: &Bounds3) -> usize {
+        let mut index = root;
+        loop {
+            let node = &self.nodes[index];
+            let NodeKind::Branch(a, b) = node.kind else {
+                return index;
+            };
+
+            let area = node.bounds.surface_area();
+            let combined_area = node.bounds.join(bounds).surface_area();
+
+            // Cost of making a new parent for this node and the leaf.
+            let cost = 2.0 * combined_area;
+            // Minimum cost of pushing the leaf further down the tree.
+            let inheritance_cost = 2.0 * (combined_area - area);
+
+            let child_cost = |child: usize| {
+                let child = &self.nodes[child];
+                let joined = child.bounds.join(bounds).surface_area();
+                match child.kind {
+                    NodeKind::Leaf(_) => joined + inheritance_cost,
+                    _ => joined - child.bounds.surface_area() + inheritance_cost,
+                }
+            };
+
+            let (cost_a, cost_b) = (child_cost(a), child_cost(b));
+            if cost < cost_a && cost < cost_b {
+                return index;
+            }
+            index = if cost_a < cost_b { a } else { b };
+        }
+    }
+
+    /// Detach a leaf from the tree and free its parent.
+    fn detach(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+
+        let parent = self.nodes[leaf]
+            .parent
+            .expect("Internal error: Leaf without parent");
+        let NodeKind::Branch(a, b) = self.nodes[parent].kind else {
+            panic!("Internal error: Parent is not a branch");
+        };
+        let sibling = if a == leaf { b } else { a };
+        let grand_parent = self.nodes[parent].parent;
+
+        self.nodes[sibling].parent = grand_parent;
+        match grand_parent {
+            Some(grand_parent) => {
+                self.replace_child(grand_parent, parent, sibling);
+                self.refit(grand_parent);
+            }
+            None => self.root = Some(sibling),
+        }
+
+        self.nodes[parent].kind = NodeKind::Free;
+        self.free.push(parent);
+        self.nodes[leaf].parent = None;
+    }
+
+    fn replace_child(&mut self, branch: usize, old: usize, new: usize) {
+        let NodeKind::Branch(a, b) = &mut self.nodes[branch].kind else {
+            panic!("Internal error: Node is not a branch");
+        };
+        if *a == old {
+            *a = new;
+        } else {
+            debug_assert_eq!(*b, old);
+            *b = new;
+        }
+    }
+
+    /// Recompute the bounds of `index` and all its ancestors.
+    fn refit(&mut self, index: usize) {
+        let mut current = Some(index);
+        while let Some(index) = current {
+            let NodeKind::Branch(a, b) = self.nodes[index].kind else {
+                panic!("Internal error: Refitting a node that is not a branch");
+            };
+            self.nodes[index].bounds = self.nodes[a].bounds.join(&self.nodes[b].bounds);
+            current = self.nodes[index].parent;
+        }
+    }
+}
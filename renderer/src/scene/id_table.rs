@@ -45,6 +45,17 @@ impl<T> IdTable<T> {
         &self.rows[index]
     }
 
+    /// Returns a reference to the value at `id`, `None` if the table does not reach that far.
+    pub fn get(&self, id: Id) -> Option<&T> {
+        self.rows.get(*id)
+    }
+
+    /// Returns a mutable reference to the value at `id`, `None` if the table does not reach that
+    /// far.
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut T> {
+        self.rows.get_mut(*id)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.rows.iter()
     }
@@ -1,11 +1,19 @@
-use std::{cell::RefCell, collections::HashMap};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+};
 
+use bounds_tree::{BoundsTree, LeafId};
+use cgmath::Transform;
 use euclid::num::Zero;
 use id_table::IdTable;
-use massive_geometry::Matrix4;
+use massive_geometry::{scalar, Bounds3, Color, Matrix4, Point3, Vector3};
 use massive_scene::{Change, Id, PositionRenderObj, PositionedRenderShape, SceneChange, Shape};
 use versioning::{Computed, Version, Versioned};
 
+use crate::contrast::compose;
+
+mod bounds_tree;
 mod id_table;
 mod versioning;
 
@@ -16,7 +24,12 @@ pub struct Scene {
 
     matrices: IdTable<Option<Versioned<Matrix4>>>,
     positions: IdTable<Option<Versioned<PositionRenderObj>>>,
-    shapes: IdTable<Option<PositionedRenderShape>>,
+    shapes: IdTable<Option<SceneShape>>,
+
+    /// The bounds of all visible shapes in scene coordinates, with the position matrices applied.
+    bounds_tree: BoundsTree<Id>,
+    /// The dependents of matrices and positions, to find the shapes that move.
+    dependents: Dependents,
 
     caches: RefCell<SceneCaches>,
 }
@@ -26,28 +39,278 @@ impl Scene {
     ///
     /// The transaction is given a new version number, which is then treated as the most recent
     /// version and the current version of the whole scene.
-    pub fn transact(&mut self, changes: impl IntoIterator<Item = SceneChange>) {
+    ///
+    /// Returns a summary of what the transaction changed.
+    pub fn transact(&mut self, changes: impl IntoIterator<Item = SceneChange>) -> Transaction {
         self.current_version += 1;
+        let mut transaction = Transaction::default();
+        let mut changed_shapes = Vec::new();
+        let mut moved_positions = HashSet::new();
         for change in changes {
+            match &change {
+                SceneChange::Matrix(change) => {
+                    transaction.matrices_changed = true;
+                    if let Some(positions) = self.dependents.matrix_positions.get(&change.id()) {
+                        moved_positions.extend(positions);
+                    }
+                }
+                SceneChange::Position(change) => {
+                    transaction.matrices_changed = true;
+                    moved_positions.insert(change.id());
+                }
+                SceneChange::PositionedShape(change) => {
+                    transaction.shapes_changed = true;
+                    changed_shapes.push(change.id());
+                }
+                SceneChange::ShapeVisibility(id, _) => {
+                    transaction.shapes_changed = true;
+                    changed_shapes.push(*id);
+                }
+            }
             self.apply(change, self.current_version)
         }
+        self.update_bounds(&changed_shapes, &moved_positions);
+        transaction
     }
 
     fn apply(&mut self, change: SceneChange, version: Version) {
         match change {
             SceneChange::Matrix(change) => self.matrices.apply_versioned(change, version),
-            SceneChange::Position(change) => self.positions.apply_versioned(change, version),
-            SceneChange::PositionedShape(change) => self.shapes.apply(change),
+            SceneChange::Position(change) => {
+                let current = self.positions.get(change.id()).and_then(Option::as_ref);
+                self.dependents
+                    .remove_position(change.id(), current.map(|p| &**p));
+                if let Change::Create(id, position) | Change::Update(id, position) = &change {
+                    self.dependents.add_position(*id, position);
+                }
+                self.positions.apply_versioned(change, version)
+            }
+            SceneChange::PositionedShape(change) => self.apply_shape(change),
+            SceneChange::ShapeVisibility(id, visible) => {
+                // Visibility changes of unknown shapes are ignored.
+                if let Some(Some(shape)) = self.shapes.get_mut(id) {
+                    shape.visible = visible;
+                }
+            }
+        }
+    }
+
+    fn apply_shape(&mut self, change: Change<PositionedRenderShape>) {
+        let id = change.id();
+        let current_position = self
+            .shapes
+            .get(id)
+            .and_then(Option::as_ref)
+            .map(|scene_shape| scene_shape.shape.position);
+        if let Some(position) = current_position {
+            self.dependents.remove_shape(id, position);
+        }
+        match &change {
+            Change::Create(_, shape) => self.dependents.add_shape(id, shape.position),
+            // Updates of unknown shapes are ignored below.
+            Change::Update(_, shape) if current_position.is_some() => {
+                self.dependents.add_shape(id, shape.position)
+            }
+            _ => {}
+        }
+
+        match change {
+            Change::Create(id, shape) => self.shapes.put(
+                id,
+                Some(SceneShape {
+                    local_bounds: shape.shape.bounds(),
+                    shape,
+                    visible: true,
+                    bounds: None,
+                }),
+            ),
+            Change::Delete(id) => {
+                if let Some(ShapeBounds { leaf, .. }) =
+                    self.shapes[id].as_mut().and_then(|s| s.bounds.take())
+                {
+                    self.bounds_tree.remove(leaf);
+                }
+                self.shapes.put(id, None)
+            }
+            Change::Update(id, shape) => {
+                // Updating the shape retains its visibility.
+                if let Some(scene_shape) = &mut self.shapes[id] {
+                    scene_shape.local_bounds = shape.shape.bounds();
+                    scene_shape.shape = shape;
+                }
+            }
+        }
+    }
+
+    /// Bring the bounds of the changed shapes and of the shapes that moved with
+    /// `moved_positions` or their descendants up to date.
+    fn update_bounds(&mut self, changed_shapes: &[Id], moved_positions: &HashSet<Id>) {
+        for id in changed_shapes {
+            self.update_shape_bounds(*id, true);
+        }
+
+        for id in self.dependents.shapes_below(moved_positions) {
+            self.update_shape_bounds(id, false);
+        }
+    }
+
+    /// Update the bounds of a shape in the bounds tree.
+    ///
+    /// If `shape_changed` is `false`, the bounds are only recomputed if the position matrix of the
+    /// shape changed since the bounds were computed.
+    fn update_shape_bounds(&mut self, id: Id, shape_changed: bool) {
+        let Some(Some(scene_shape)) = self.shapes.get(id) else {
+            return;
+        };
+
+        let world_bounds = match (scene_shape.visible, scene_shape.local_bounds) {
+            (true, Some(local_bounds)) => {
+                let position_id = scene_shape.shape.position;
+                let (matrix, matrix_version) = {
+                    let mut caches = self.caches.borrow_mut();
+                    self.resolve_positioned_matrix(position_id, &mut caches);
+                    let computed = &caches.positions_matrix[position_id];
+                    (computed.value, computed.max_deps_version)
+                };
+                if !shape_changed
+                    && scene_shape
+                        .bounds
+                        .is_some_and(|b| b.matrix_version >= matrix_version)
+                {
+                    return;
+                }
+                Some((local_bounds.transformed(&matrix), matrix_version))
+            }
+            _ => None,
+        };
+
+        let scene_shape = self.shapes[id].as_mut().unwrap();
+        scene_shape.bounds = match (scene_shape.bounds, world_bounds) {
+            (Some(current), Some((bounds, matrix_version))) => {
+                self.bounds_tree.update(current.leaf, bounds);
+                Some(ShapeBounds {
+                    leaf: current.leaf,
+                    matrix_version,
+                })
+            }
+            (None, Some((bounds, matrix_version))) => Some(ShapeBounds {
+                leaf: self.bounds_tree.insert(bounds, id),
+                matrix_version,
+            }),
+            (Some(current), None) => {
+                self.bounds_tree.remove(current.leaf);
+                None
+            }
+            (None, None) => None,
+        };
+    }
+
+    /// The bounds of all visible shapes in scene coordinates, `None` if there are none.
+    pub fn bounds(&self) -> Option<Bounds3> {
+        self.bounds_tree.bounds()
+    }
+
+    /// The bounds of a visible shape in scene coordinates.
+    ///
+    /// `None` if the shape does not exist, is invisible, or empty.
+    pub fn shape_bounds(&self, id: Id) -> Option<Bounds3> {
+        let scene_shape = self.shapes.get(id)?.as_ref()?;
+        scene_shape
+            .bounds
+            .map(|b| self.bounds_tree.leaf_bounds(b.leaf))
+    }
+
+    /// The union of the bounds of the visible shapes at a position in scene coordinates.
+    ///
+    /// `None` if there are none.
+    ///
+    /// OO: This visits all shapes.
+    pub fn position_bounds(&self, position_id: Id) -> Option<Bounds3> {
+        self.shapes
+            .iter_some()
+            .filter(|s| s.shape.position == position_id)
+            .filter_map(|s| s.bounds)
+            .map(|b| self.bounds_tree.leaf_bounds(b.leaf))
+            .reduce(|a, b| a.join(&b))
+    }
+
+    /// The ids of all visible shapes whose bounds intersect `bounds`.
+    pub fn shapes_intersecting(&self, bounds: &Bounds3) -> Vec<Id> {
+        let mut ids = Vec::new();
+        self.bounds_tree.intersecting(bounds, |id, _| ids.push(*id));
+        ids
+    }
+
+    /// The ids of all visible shapes whose bounds pass `filter`.
+    ///
+    /// `filter` is also applied to the bounds of the branches of the bounds tree, so it must pass
+    /// all bounds that contain bounds that pass.
+    pub fn shapes_passing(&self, filter: impl FnMut(&Bounds3) -> bool) -> Vec<Id> {
+        let mut ids = Vec::new();
+        self.bounds_tree.visit(filter, |id, _| ids.push(*id));
+        ids
+    }
+
+    /// The color of the quads at `point` in scene coordinates, composed over `base` in the order
+    /// they are rendered.
+    ///
+    /// Only the quads in the world, or in the overlay if `overlay` is set, are composed.
+    ///
+    /// OO: Transforms all quads of the shapes at `point`.
+    pub fn background_at(&self, point: Point3, overlay: bool, base: Color) -> Color {
+        let mut below: Vec<_> = self
+            .shapes_intersecting(&Bounds3::new(point, point))
+            .into_iter()
+            .filter_map(|id| {
+                let positioned = &self.shapes.get(id)?.as_ref()?.shape;
+                let Shape::Quads(quads) = &positioned.shape else {
+                    return None;
+                };
+                let position = positioned.position;
+                (self.is_overlay(position) == overlay).then_some((position, id, quads))
+            })
+            .collect();
+        // Groups are rendered by their position ids, and the shapes of a group by their ids.
+        below.sort_by_key(|(position, id, _)| (*position, *id));
+
+        let mut color = base;
+        for (position, _, quads) in below {
+            let matrix = self.position_matrix(position);
+            for quad in quads {
+                let [a, b, c, d] = quad
+                    .vertices
+                    .map(|v| matrix.transform_point(Point3::new(v.x, v.y, v.z)));
+                if in_triangle(point, a, b, c) || in_triangle(point, a, c, d) {
+                    color = compose(quad.color, color);
+                }
+            }
         }
+        color
+    }
+
+    /// The ids of all visible shapes whose bounds are hit by a ray, ordered by the distance at
+    /// which the ray enters their bounds.
+    pub fn shapes_hit_by_ray(&self, origin: Point3, direction: Vector3) -> Vec<(Id, scalar)> {
+        let mut hits = Vec::new();
+        self.bounds_tree
+            .hit_by_ray(origin, direction, |id, _, distance| {
+                hits.push((*id, distance))
+            });
+        hits.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        hits
     }
 
     /// Returns a set of grouped shape by matrix.
     ///
+    /// The groups are ordered by their position ids, so that the same scene is always rendered
+    /// in the same order.
+    ///
     /// TODO: This should not be &mut self, because it updates computed values only.
-    pub fn grouped_shapes(&self) -> impl Iterator<Item = (Matrix4, Vec<&Shape>)> {
-        let mut map: HashMap<Id, Vec<&Shape>> = HashMap::new();
+    pub fn grouped_shapes(&self) -> impl Iterator<Item = (Id, Matrix4, Vec<&Shape>)> {
+        let mut map: BTreeMap<Id, Vec<&Shape>> = BTreeMap::new();
 
-        for positioned in self.shapes.iter_some() {
+        for scene_shape in self.shapes.iter_some().filter(|s| s.visible) {
+            let positioned = &scene_shape.shape;
             let position_id = positioned.position;
             map.entry(position_id).or_default().push(&positioned.shape);
         }
@@ -68,10 +331,69 @@ impl Scene {
             // Ensure the matrix is up2date.
             // We can't return a reference to matrix, because this would also borrow `caches``.
             let matrix = *caches.positions_matrix[position_id];
-            (matrix, shapes)
+            (position_id, matrix, shapes)
         })
     }
 
+    /// All matrices, ordered by their ids.
+    pub fn matrices(&self) -> impl Iterator<Item = (Id, &Matrix4)> {
+        enumerate_some(&self.matrices).map(|(id, matrix)| (id, &**matrix))
+    }
+
+    /// All positions, ordered by their ids.
+    pub fn positions(&self) -> impl Iterator<Item = (Id, &PositionRenderObj)> {
+        enumerate_some(&self.positions).map(|(id, position)| (id, &**position))
+    }
+
+    /// All shapes and their visibility, ordered by their ids.
+    pub fn shapes(&self) -> impl Iterator<Item = (Id, &PositionedRenderShape, bool)> {
+        enumerate_some(&self.shapes).map(|(id, shape)| (id, &shape.shape, shape.visible))
+    }
+
+    /// All shapes for modifications that don't change their bounds.
+    pub fn shapes_mut(&mut self) -> impl Iterator<Item = &mut Shape> {
+        self.shapes
+            .rows_mut()
+            .iter_mut()
+            .flatten()
+            .map(|scene_shape| &mut scene_shape.shape.shape)
+    }
+
+    /// Whether a position or one of its ancestors is placed in the overlay.
+    pub fn is_overlay(&self, position_id: Id) -> bool {
+        let mut position = self.positions.unwrapped(position_id);
+        loop {
+            if position.overlay {
+                return true;
+            }
+            match position.parent {
+                Some(parent) => position = self.positions.unwrapped(parent),
+                None => return false,
+            }
+        }
+    }
+
+    /// Whether a position is `root` or one of its descendants.
+    pub fn is_below(&self, position_id: Id, root: Id) -> bool {
+        let mut position_id = position_id;
+        loop {
+            if position_id == root {
+                return true;
+            }
+            match self.positions.unwrapped(position_id).parent {
+                Some(parent) => position_id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns the up to date matrix of a position.
+    pub fn position_matrix(&self, position_id: Id) -> Matrix4 {
+        let mut caches = self.caches.borrow_mut();
+        self.resolve_positioned_matrix(position_id, &mut caches);
+        *caches.positions_matrix[position_id]
+    }
+
     /// Compute - if needed - the matrix of a position.
     ///
     /// When this function returns the matrix at `position_id` is up to date with the current
@@ -137,7 +459,20 @@ impl Scene {
         let local_matrix = &**self.matrices.unwrapped(matrix);
         let new_value = parent_id.map_or_else(
             || *local_matrix,
-            |parent_id| *caches.positions_matrix[parent_id] * local_matrix,
+            |parent_id| {
+                let parent_matrix = *caches.positions_matrix[parent_id];
+                match &position.pin {
+                    Some(pin) => {
+                        // The parent's own matrix, relative to the viewport.
+                        let parent_local = &**self
+                            .matrices
+                            .unwrapped(self.positions.unwrapped(parent_id).matrix);
+                        let offset = pin.offset(parent_local, local_matrix);
+                        parent_matrix * offset * local_matrix
+                    }
+                    None => parent_matrix * local_matrix,
+                }
+            },
         );
 
         caches.positions_matrix[position_id] = Computed {
@@ -148,23 +483,19 @@ impl Scene {
     }
 }
 
+fn enumerate_some<T>(table: &IdTable<Option<T>>) -> impl Iterator<Item = (Id, &T)> {
+    table
+        .iter()
+        .enumerate()
+        .filter_map(|(index, v)| Some((Id::from_raw(index), v.as_ref()?)))
+}
+
 impl<T> IdTable<Option<T>> {
     /// Iterate through all existing (non-`None`) values.
     pub fn iter_some(&self) -> impl Iterator<Item = &T> {
         self.iter().filter_map(|v| v.as_ref())
     }
 
-    pub fn apply(&mut self, change: Change<T>) {
-        match change {
-            Change::Create(id, value) => self.put(id, Some(value)),
-            Change::Delete(id) => self.put(id, None),
-            Change::Update(id, value) => {
-                // Already know that this index must exist, so use rows() here.
-                self.rows_mut()[*id] = Some(value)
-            }
-        }
-    }
-
     /// Returns a reference to the object at `id`.
     ///
     /// Panics if it does not exist.
@@ -197,8 +528,110 @@ impl Default for Computed<Matrix4> {
     }
 }
 
+/// What a transaction changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Transaction {
+    /// Matrices or positions were changed, which may move shapes.
+    pub matrices_changed: bool,
+    /// Shapes were added, removed, updated, or their visibility changed.
+    pub shapes_changed: bool,
+}
+
+#[derive(Debug)]
+struct SceneShape {
+    shape: PositionedRenderShape,
+    visible: bool,
+    /// The bounds of the shape in the coordinate system of its position.
+    local_bounds: Option<Bounds3>,
+    /// Where the shape is in the bounds tree. `None` if it's invisible or empty.
+    bounds: Option<ShapeBounds>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ShapeBounds {
+    leaf: LeafId,
+    /// The version of the position matrix the bounds were computed with.
+    matrix_version: Version,
+}
+
+/// The reverse dependencies of the scene objects.
+#[derive(Debug, Default)]
+struct Dependents {
+    /// The positions that use a matrix.
+    matrix_positions: HashMap<Id, HashSet<Id>>,
+    /// The children of a position.
+    position_children: HashMap<Id, HashSet<Id>>,
+    /// The shapes at a position.
+    position_shapes: HashMap<Id, HashSet<Id>>,
+}
+
+impl Dependents {
+    fn add_position(&mut self, id: Id, position: &PositionRenderObj) {
+        self.matrix_positions
+            .entry(position.matrix)
+            .or_default()
+            .insert(id);
+        if let Some(parent) = position.parent {
+            self.position_children.entry(parent).or_default().insert(id);
+        }
+    }
+
+    fn remove_position(&mut self, id: Id, position: Option<&PositionRenderObj>) {
+        let Some(position) = position else {
+            return;
+        };
+        remove_dependent(&mut self.matrix_positions, position.matrix, id);
+        if let Some(parent) = position.parent {
+            remove_dependent(&mut self.position_children, parent, id);
+        }
+    }
+
+    fn add_shape(&mut self, id: Id, position: Id) {
+        self.position_shapes.entry(position).or_default().insert(id);
+    }
+
+    fn remove_shape(&mut self, id: Id, position: Id) {
+        remove_dependent(&mut self.position_shapes, position, id);
+    }
+
+    /// The shapes at `positions` and at their descendants.
+    fn shapes_below(&self, positions: &HashSet<Id>) -> Vec<Id> {
+        let mut visited = HashSet::new();
+        let mut pending: Vec<Id> = positions.iter().copied().collect();
+        let mut shapes = Vec::new();
+        while let Some(position) = pending.pop() {
+            if !visited.insert(position) {
+                continue;
+            }
+            if let Some(position_shapes) = self.position_shapes.get(&position) {
+                shapes.extend(position_shapes);
+            }
+            if let Some(children) = self.position_children.get(&position) {
+                pending.extend(children);
+            }
+        }
+        shapes
+    }
+}
+
+fn remove_dependent(dependents: &mut HashMap<Id, HashSet<Id>>, of: Id, id: Id) {
+    if let Some(set) = dependents.get_mut(&of) {
+        set.remove(&id);
+        if set.is_empty() {
+            dependents.remove(&of);
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct SceneCaches {
     // The result of a positioned computation.
     positions_matrix: IdTable<Computed<Matrix4>>,
 }
+
+/// Whether `p` is inside of the triangle `a`, `b`, `c` in the xy plane, including its edges.
+fn in_triangle(p: Point3, a: Point3, b: Point3, c: Point3) -> bool {
+    let side = |a: Point3, b: Point3| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use massive_geometry::{Matrix4, Vector3};
+use massive_scene::Shape;
+use massive_shapes::Shadow;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BufferUsages,
+};
+
+use crate::{
+    bind_group_entries,
+    debug::DebugPipelines,
+    pods::TextureAlphaColorVertex,
+    renderer::{PreparationContext, RenderContext},
+    texture,
+    tools::{create_pipeline, texture_sampler, BindGroupLayoutBuilder, QuadIndexBuffer},
+};
+
+/// Radius, blur, and spread are rounded to this fraction of a pixel, so that shadows that look
+/// the same share their textures.
+const KEY_PRECISION: f64 = 4.0;
+/// Radii and blurs are clamped to this, to limit the size of the textures.
+const MAX_EXTENT: f64 = 256.0;
+/// The number of cached textures above which textures that are not used anymore are dropped.
+const MAX_CACHED_TEXTURES: usize = 64;
+
+/// Renders [`Shadow`]s.
+///
+/// For each combination of radius, blur, and spread, the renderer generates a texture with the
+/// blurred corners and edges of a rounded rectangle once, and renders each shadow as nine quads
+/// that stretch its edges. Shadows are batched per layer and texture.
+pub struct ShadowRenderer {
+    pipeline: wgpu::RenderPipeline,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_sampler: wgpu::Sampler,
+    index_buffer: QuadIndexBuffer,
+    debug: DebugPipelines,
+
+    textures: HashMap<ShadowKey, ShadowTexture>,
+    layers: Vec<ShadowLayer>,
+}
+
+/// The quantized radius, blur, and spread of a shadow.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct ShadowKey {
+    radius: i32,
+    blur: i32,
+    spread: i32,
+}
+
+impl ShadowKey {
+    fn new(shadow: &Shadow) -> Self {
+        let quantize = |v: f64, min: f64| (v.clamp(min, MAX_EXTENT) * KEY_PRECISION).round() as i32;
+        Self {
+            radius: quantize(shadow.radius, 0.0),
+            blur: quantize(shadow.blur, 0.0),
+            spread: quantize(shadow.spread, -MAX_EXTENT),
+        }
+    }
+
+    /// The corner radius of the rectangle grown by the spread.
+    fn spread_radius(&self) -> f64 {
+        ((self.radius + self.spread) as f64 / KEY_PRECISION).max(0.0)
+    }
+
+    fn blur(&self) -> f64 {
+        self.blur as f64 / KEY_PRECISION
+    }
+
+    fn spread(&self) -> f64 {
+        self.spread as f64 / KEY_PRECISION
+    }
+}
+
+struct ShadowTexture {
+    bind_group: wgpu::BindGroup,
+    metrics: TextureMetrics,
+    /// Used by the layers prepared since the last [`ShadowRenderer::clear`].
+    used: bool,
+}
+
+/// The layout of a shadow texture, in pixels.
+///
+/// The texture is square. The spread rectangle starts `margin` pixels from its edges, and the
+/// corners, including their blur, fit into `corner` pixels. The row and column in the middle are
+/// stretched to the size of the shadow.
+#[derive(Debug, Copy, Clone)]
+struct TextureMetrics {
+    size: u32,
+    margin: u32,
+    corner: u32,
+}
+
+impl TextureMetrics {
+    fn new(key: &ShadowKey) -> Self {
+        let half_blur = (key.blur() / 2.0).ceil() as u32;
+        let margin = half_blur + 1;
+        // One more blur and pixel, so that the middle is not affected by the corners.
+        let corner = margin + key.spread_radius().ceil() as u32 + half_blur + 2;
+        Self {
+            size: corner * 2 + 1,
+            margin,
+            corner,
+        }
+    }
+}
+
+struct ShadowLayer {
+    /// The index of the shape group this layer was prepared from.
+    group: usize,
+    key: ShadowKey,
+    model_matrix: Matrix4,
+    vertex_buffer: wgpu::Buffer,
+    quad_count: usize,
+}
+
+impl ShadowRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = &crate::shader_module!(device, "shadows/shadows.wgsl");
+
+        let texture_bind_group_layout = BindGroupLayoutBuilder::fragment()
+            .texture()
+            .sampler()
+            .build("Shadow Bind Group Layout", device);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadows Pipeline Layout"),
+            bind_group_layouts: &[
+                view_projection_bind_group_layout,
+                layer_bind_group_layout,
+                &texture_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let targets = [Some(wgpu::ColorTargetState {
+            format: target_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let vertex_layout = [TextureAlphaColorVertex::layout()];
+
+        let pipeline = create_pipeline(
+            "Shadows Pipeline",
+            device,
+            shader,
+            "fs_shadow",
+            &vertex_layout,
+            &pipeline_layout,
+            &targets,
+        );
+
+        Self {
+            pipeline,
+            texture_bind_group_layout,
+            texture_sampler: texture_sampler::linear_clamping(device),
+            index_buffer: QuadIndexBuffer::new(device),
+            debug: DebugPipelines::new(
+                device,
+                target_format,
+                view_projection_bind_group_layout,
+                TextureAlphaColorVertex::layout().array_stride,
+            ),
+            textures: HashMap::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Drop all prepared layers.
+    ///
+    /// The textures are kept for the next preparation. When there are too many, the ones the
+    /// dropped layers did not use are released.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+        if self.textures.len() > MAX_CACHED_TEXTURES {
+            self.textures.retain(|_, texture| texture.used);
+        }
+        for texture in self.textures.values_mut() {
+            texture.used = false;
+        }
+    }
+
+    /// Prepare shape groups and add them to the prepared layers.
+    ///
+    /// `first_group` is the index of the first group in `shapes` among all groups prepared since
+    /// the last [`Self::clear`].
+    #[tracing::instrument(name = "prepare_shadows", skip_all)]
+    pub fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        shapes: &[(Matrix4, &[&Shape])],
+    ) -> Result<()> {
+        // Shadows are decoration and reduce the contrast, so they are left out in high contrast
+        // mode.
+        if context.high_contrast.is_some() {
+            return Ok(());
+        }
+
+        let mut max_quads = 0;
+
+        for (group, (matrix, shapes)) in (first_group..).zip(shapes) {
+            // Batch by texture, in the order of first appearance.
+            let mut batches: Vec<(ShadowKey, Vec<TextureAlphaColorVertex>)> = Vec::new();
+            for shadow in shapes.iter().filter_map(|s| match s {
+                Shape::Shadow(shadow) => Some(shadow),
+                _ => None,
+            }) {
+                let key = ShadowKey::new(shadow);
+                let metrics = self.texture(context, key);
+                let index = match batches.iter().position(|(k, _)| *k == key) {
+                    Some(index) => index,
+                    None => {
+                        batches.push((key, Vec::new()));
+                        batches.len() - 1
+                    }
+                };
+                push_nine_slices(&mut batches[index].1, shadow, &key, &metrics);
+            }
+
+            for (key, vertices) in batches {
+                let vertex_buffer = context.device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Shadows Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: BufferUsages::VERTEX,
+                });
+                let quad_count = vertices.len() >> 2;
+                max_quads = max_quads.max(quad_count);
+                self.layers.push(ShadowLayer {
+                    group,
+                    key,
+                    model_matrix: *matrix,
+                    vertex_buffer,
+                    quad_count,
+                });
+            }
+        }
+
+        self.index_buffer
+            .ensure_can_index_num_quads(context.device, max_quads);
+        self.debug.prepare(context.device, max_quads);
+
+        Ok(())
+    }
+
+    /// Update the model matrices of the prepared layers without preparing them again.
+    ///
+    /// `matrices` must be in the order of the shape groups passed to [`Self::prepare`].
+    pub fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for layer in &mut self.layers {
+            layer.model_matrix = matrices[layer.group];
+        }
+    }
+
+    pub fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        if context.debug_mode.renders_scene() {
+            self.render_layers(context);
+        }
+
+        let batches: Vec<_> = self
+            .layers
+            .iter()
+            .filter(|l| context.renders_group(l.group))
+            .map(|l| (l.model_matrix, l.vertex_buffer.slice(..), l.quad_count))
+            .collect();
+        self.debug.render(context, &self.index_buffer, &batches);
+    }
+
+    fn render_layers<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        if self.layers.is_empty() {
+            return;
+        }
+
+        let pass = &mut context.pass;
+        pass.set_pipeline(&self.pipeline);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        for ShadowLayer {
+            group,
+            key,
+            model_matrix,
+            vertex_buffer,
+            quad_count,
+        } in &self.layers
+        {
+            if !context.renders_group(*group) {
+                continue;
+            }
+            let texture = &self.textures[key];
+
+            context.queue_model_matrix(model_matrix);
+
+            let layer_bind_group = context.layer_bind_group(*group);
+            let pass = &mut context.pass;
+            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+            pass.set_bind_group(1, layer_bind_group, &[]);
+            pass.set_bind_group(2, &texture.bind_group, &[]);
+
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+            pass.draw_indexed(
+                0..(QuadIndexBuffer::INDICES_PER_QUAD * quad_count) as u32,
+                0,
+                0..1,
+            )
+        }
+    }
+
+    /// The metrics of the texture of `key`, generated and uploaded if it's not cached.
+    fn texture(&mut self, context: &PreparationContext, key: ShadowKey) -> TextureMetrics {
+        let texture = self.textures.entry(key).or_insert_with(|| {
+            let metrics = TextureMetrics::new(&key);
+            let data = shadow_coverage(&key, &metrics);
+            let view = texture::View::from_data(
+                context.device,
+                context.queue,
+                &data,
+                (metrics.size, metrics.size),
+            );
+            let bind_group = context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Shadow Bind Group"),
+                    layout: &self.texture_bind_group_layout,
+                    entries: bind_group_entries!(0 => &view, 1 => &self.texture_sampler),
+                });
+            ShadowTexture {
+                bind_group,
+                metrics,
+                used: false,
+            }
+        });
+        texture.used = true;
+        texture.metrics
+    }
+}
+
+/// Add the nine quads of a shadow: the corners, the stretched edges, and the stretched middle.
+///
+/// Shadows smaller than the corners of their texture show the parts of the corners that fit.
+fn push_nine_slices(
+    vertices: &mut Vec<TextureAlphaColorVertex>,
+    shadow: &Shadow,
+    key: &ShadowKey,
+    metrics: &TextureMetrics,
+) {
+    let outset = key.spread() + metrics.margin as f64;
+    let left = shadow.bounds.min.x + shadow.offset.x - outset;
+    let top = shadow.bounds.min.y + shadow.offset.y - outset;
+    let right = shadow.bounds.max.x + shadow.offset.x + outset;
+    let bottom = shadow.bounds.max.y + shadow.offset.y + outset;
+    if right <= left || bottom <= top {
+        return;
+    }
+
+    let size = metrics.size as f64;
+    let corner = metrics.corner as f64;
+    let cx = corner.min((right - left) / 2.0);
+    let cy = corner.min((bottom - top) / 2.0);
+    let xs = [left, left + cx, right - cx, right];
+    let ys = [top, top + cy, bottom - cy, bottom];
+    let us = [0.0, cx / size, 1.0 - cx / size, 1.0];
+    let vs = [0.0, cy / size, 1.0 - cy / size, 1.0];
+
+    let color = shadow.color;
+    for row in 0..3 {
+        for column in 0..3 {
+            let (l, r) = (xs[column], xs[column + 1]);
+            let (t, b) = (ys[row], ys[row + 1]);
+            let (lu, ru) = (us[column] as f32, us[column + 1] as f32);
+            let (tv, bv) = (vs[row] as f32, vs[row + 1] as f32);
+            vertices.extend([
+                TextureAlphaColorVertex::new(Vector3::new(l, t, 0.0), (lu, tv), color),
+                TextureAlphaColorVertex::new(Vector3::new(l, b, 0.0), (lu, bv), color),
+                TextureAlphaColorVertex::new(Vector3::new(r, b, 0.0), (ru, bv), color),
+                TextureAlphaColorVertex::new(Vector3::new(r, t, 0.0), (ru, tv), color),
+            ]);
+        }
+    }
+}
+
+/// Render the blurred coverage of the spread rectangle into a texture of `metrics`.
+fn shadow_coverage(key: &ShadowKey, metrics: &TextureMetrics) -> Vec<u8> {
+    let size = metrics.size as usize;
+    let radius = key.spread_radius();
+    let margin = metrics.margin as f64;
+    let half_extent = metrics.size as f64 / 2.0 - margin;
+
+    // The anti-aliased coverage of the rounded rectangle.
+    let center = metrics.size as f64 / 2.0;
+    let mut coverage: Vec<f32> = (0..size * size)
+        .map(|i| {
+            let x = (i % size) as f64 + 0.5 - center;
+            let y = (i / size) as f64 + 0.5 - center;
+            let qx = x.abs() - (half_extent - radius);
+            let qy = y.abs() - (half_extent - radius);
+            let outside = qx.max(0.0).hypot(qy.max(0.0));
+            let distance = outside + qx.max(qy).min(0.0) - radius;
+            (0.5 - distance).clamp(0.0, 1.0) as f32
+        })
+        .collect();
+
+    // A separable gaussian blur, with the edge fading over the blur width, which is 4 sigma.
+    let sigma = key.blur() / 4.0;
+    if sigma >= 0.25 {
+        let kernel = gaussian_kernel(sigma);
+        coverage = blur(&coverage, size, &kernel, 1, size);
+        coverage = blur(&coverage, size, &kernel, size, 1);
+    }
+
+    coverage
+        .into_iter()
+        .map(|c| (c * 255.0).round() as u8)
+        .collect()
+}
+
+/// A normalized gaussian kernel that reaches 2 sigma.
+fn gaussian_kernel(sigma: f64) -> Vec<f32> {
+    let reach = (sigma * 2.0).ceil() as i32;
+    let weights: Vec<f64> = (-reach..=reach)
+        .map(|i| (-(i * i) as f64 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| (w / sum) as f32).collect()
+}
+
+/// Blur the lines of a square image of `size`. `step` is the distance between the pixels of a
+/// line and `line_step` between the lines.
+fn blur(image: &[f32], size: usize, kernel: &[f32], step: usize, line_step: usize) -> Vec<f32> {
+    let reach = (kernel.len() / 2) as isize;
+    let mut blurred = vec![0.0; image.len()];
+    for line in 0..size {
+        for i in 0..size {
+            let mut sum = 0.0;
+            for (k, weight) in kernel.iter().enumerate() {
+                let j = i as isize + k as isize - reach;
+                // The texture's margins are empty, so outside is zero.
+                if (0..size as isize).contains(&j) {
+                    sum += image[line * line_step + j as usize * step] * weight;
+                }
+            }
+            blurred[line * line_step + i * step] = sum;
+        }
+    }
+    blurred
+}
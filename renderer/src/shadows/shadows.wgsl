@@ -0,0 +1,67 @@
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> model_view: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    vertex_input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
+    out.tex_coords = vertex_input.tex_coords;
+    out.color = vertex_input.color;
+    return out;
+}
+
+// Fragment shader
+
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> layer: Layer;
+
+// The blurred coverage of the shadow, see `ShadowTexture`.
+@group(2) @binding(0)
+var t_shadow: texture_2d<f32>;
+@group(2) @binding(1)
+var s_shadow: sampler;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+@fragment
+fn fs_shadow(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(t_shadow, s_shadow, in.tex_coords).r;
+    return layer_color(vec4<f32>(in.color.rgb, in.color.a * coverage));
+}
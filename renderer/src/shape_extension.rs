@@ -0,0 +1,143 @@
+use std::{any::TypeId, marker::PhantomData, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use massive_geometry::Matrix4;
+use massive_scene::{CustomShape, CustomShapeCodec, Shape};
+
+use crate::renderer::{PreparationContext, RenderContext};
+
+/// Adds a custom shape type to the renderer.
+///
+/// An extension serializes its shapes for remote scenes, prepares GPU resources for them, and
+/// renders them. Register it with [`crate::Renderer::register_extension`]. Shapes are passed to
+/// the extension in the same groups and order the built-in renderers use, so matrix updates work
+/// the same way.
+pub trait ShapeExtension: 'static {
+    type Shape: CustomShape;
+
+    /// The name that identifies the shape type in serialized scenes.
+    const NAME: &'static str;
+
+    fn serialize(shape: &Self::Shape) -> Result<Vec<u8>>;
+    fn deserialize(data: &[u8]) -> Result<Self::Shape>;
+
+    /// Drop all prepared resources.
+    fn clear(&mut self);
+
+    /// Prepare shape groups and add them to the prepared resources.
+    ///
+    /// `first_group` is the index of the first group in `groups` among all groups prepared since
+    /// the last [`Self::clear`]. Groups without shapes of this extension are passed, too.
+    fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        groups: &[(Matrix4, Vec<&Self::Shape>)],
+    ) -> Result<()>;
+
+    /// Update the model matrices of the prepared groups.
+    ///
+    /// `matrices` is indexed by group, see [`Self::prepare`].
+    fn update_matrices(&mut self, matrices: &[Matrix4]);
+
+    /// Render the prepared groups.
+    ///
+    /// Called twice per view, for the world and for the overlay. Skip the groups for which
+    /// [`RenderContext::renders_group`] returns `false`.
+    fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>);
+
+    /// Called when the format of the render target changes. Pipelines that depend on the format
+    /// must be recreated.
+    fn target_format_changed(&mut self, _device: &wgpu::Device, _format: wgpu::TextureFormat) {}
+
+    /// The codec that serializes the shapes of this extension, for use with remote scenes.
+    fn codec() -> Arc<dyn CustomShapeCodec>
+    where
+        Self: Sized,
+    {
+        Arc::new(ExtensionCodec::<Self>(PhantomData))
+    }
+}
+
+struct ExtensionCodec<E>(PhantomData<fn() -> E>);
+
+impl<E: ShapeExtension> CustomShapeCodec for ExtensionCodec<E> {
+    fn name(&self) -> &'static str {
+        E::NAME
+    }
+
+    fn shape_type(&self) -> TypeId {
+        TypeId::of::<E::Shape>()
+    }
+
+    fn serialize(&self, shape: &dyn CustomShape) -> Result<Vec<u8>> {
+        let shape = shape
+            .downcast_ref::<E::Shape>()
+            .ok_or_else(|| anyhow!("Shape is not a `{}`", E::NAME))?;
+        E::serialize(shape)
+    }
+
+    fn deserialize(&self, data: &[u8]) -> Result<Box<dyn CustomShape>> {
+        Ok(Box::new(E::deserialize(data)?))
+    }
+}
+
+/// A type erased [`ShapeExtension`].
+pub(crate) trait Extension {
+    fn shape_type(&self) -> TypeId;
+    fn clear(&mut self);
+    fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        groups: &[(Matrix4, &[&Shape])],
+    ) -> Result<()>;
+    fn update_matrices(&mut self, matrices: &[Matrix4]);
+    fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>);
+    fn target_format_changed(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat);
+}
+
+impl<E: ShapeExtension> Extension for E {
+    fn shape_type(&self) -> TypeId {
+        TypeId::of::<E::Shape>()
+    }
+
+    fn clear(&mut self) {
+        ShapeExtension::clear(self)
+    }
+
+    fn prepare(
+        &mut self,
+        context: &mut PreparationContext,
+        first_group: usize,
+        groups: &[(Matrix4, &[&Shape])],
+    ) -> Result<()> {
+        let groups: Vec<_> = groups
+            .iter()
+            .map(|(matrix, shapes)| {
+                let shapes = shapes
+                    .iter()
+                    .filter_map(|shape| match shape {
+                        Shape::Custom(shape) => shape.downcast_ref::<E::Shape>(),
+                        _ => None,
+                    })
+                    .collect();
+                (*matrix, shapes)
+            })
+            .collect();
+
+        ShapeExtension::prepare(self, context, first_group, &groups)
+    }
+
+    fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        ShapeExtension::update_matrices(self, matrices)
+    }
+
+    fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        ShapeExtension::render(self, context)
+    }
+
+    fn target_format_changed(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        ShapeExtension::target_format_changed(self, device, format)
+    }
+}
@@ -0,0 +1,54 @@
+//! The retained state of a renderer, to reproduce rendering issues elsewhere.
+
+use massive_geometry::Matrix4;
+use massive_scene::{Id, PositionRenderObj, PositionedRenderShape};
+use serde::{Deserialize, Serialize};
+
+use crate::{DebugMode, HighContrast, LayerUniforms, PackingStats, Quality, TextRendering};
+
+/// Everything a renderer retains between frames, borrowed from the renderer.
+///
+/// All objects are ordered by their ids, so that the same scene always results in the same
+/// state. Prepared GPU resources are not included, they are recreated from the scene.
+#[derive(Debug)]
+pub struct RendererState<'a> {
+    pub matrices: Vec<(Id, &'a Matrix4)>,
+    pub positions: Vec<(Id, &'a PositionRenderObj)>,
+    /// The shapes and their visibility.
+    pub shapes: Vec<(Id, &'a PositionedRenderShape, bool)>,
+    pub config: RendererConfig,
+    /// Informational, atlases are filled again when the scene is prepared.
+    pub atlases: Vec<AtlasMetadata>,
+}
+
+/// The settings of a renderer that influence how the scene is rendered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RendererConfig {
+    pub quality: Quality,
+    pub upload_budget: Option<usize>,
+    pub debug_mode: DebugMode,
+    pub high_contrast: Option<HighContrast>,
+    pub min_contrast: Option<f32>,
+    pub text_rendering: TextRendering,
+    /// The text rendering of the layers that override it, ordered by the raw ids of their
+    /// positions.
+    pub layer_text_rendering: Vec<(usize, TextRendering)>,
+    /// The size of the surface in physical pixels.
+    pub surface_size: (u32, u32),
+    /// The layer uniforms by the raw ids of their positions, ordered by the ids.
+    pub layer_uniforms: Vec<(usize, LayerUniforms)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasMetadata {
+    pub name: String,
+    /// The size of the largest texture page in pixels.
+    pub size: (u32, u32),
+    /// The number of texture pages.
+    pub pages: usize,
+    /// The number of glyph images stored.
+    pub glyphs: usize,
+    pub packing: PackingStats,
+    /// The number of bytes written to the textures since the atlas was created.
+    pub uploaded_bytes: u64,
+}
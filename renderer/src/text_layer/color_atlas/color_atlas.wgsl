@@ -6,12 +6,46 @@ var<uniform> view_model: mat4x4<f32>;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(3) reveal: vec4<f32>,
+    @location(4) center: vec2<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     // Unnormalized texture pixel coordinates.
     @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) opacity: f32,
+}
+
+// Frame uniforms, see `FrameUniforms`.
+
+struct Frame {
+    // time, unused, unused, unused
+    parameters: vec4<f32>,
+    // The text rendering of the renderer: sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(3) @binding(0)
+var<uniform> frame: Frame;
+
+// The position and the opacity of a vertex of a revealed glyph, see `RevealVertex`.
+fn reveal_vertex(position: vec3<f32>, reveal: vec4<f32>, center: vec2<f32>) -> vec4<f32> {
+    let effect = reveal.z;
+    if (effect == 0.0) {
+        return vec4<f32>(position, 1.0);
+    }
+    let t = clamp((frame.parameters.x - reveal.x) / max(reveal.y, 0.0001), 0.0, 1.0);
+    // ease out cubic
+    let e = 1.0 - pow(1.0 - t, 3.0);
+    var p = position;
+    if (effect == 2.0) {
+        // y points down.
+        p.y += (1.0 - e) * reveal.w;
+    } else if (effect == 3.0) {
+        p = vec3<f32>(center + (p.xy - center) * e, p.z);
+    }
+    return vec4<f32>(p, e);
 }
 
 @vertex
@@ -20,19 +54,48 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = vertex_input.tex_coords;
-    out.clip_position = view_model * vec4<f32>(vertex_input.position, 1.0);
+    let revealed = reveal_vertex(vertex_input.position, vertex_input.reveal, vertex_input.center);
+    out.clip_position = view_model * vec4<f32>(revealed.xyz, 1.0);
+    out.opacity = revealed.w;
     return out;
 }
 
 // Fragment shader
 
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
 @group(1) @binding(0)
+var<uniform> layer: Layer;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+@group(2) @binding(0)
 var t_texture: texture_2d<f32>;
-@group(1) @binding(1)
+@group(2) @binding(1)
 var s_sampler: sampler;
 
 @fragment
 fn fs_color(in: VertexOutput) -> @location(0) vec4<f32> {
     let texture_size = vec2<f32>(textureDimensions(t_texture));
-    return textureSample(t_texture, s_sampler, in.tex_coords / texture_size);
+    let color = textureSample(t_texture, s_sampler, in.tex_coords / texture_size);
+    return layer_color(vec4<f32>(color.rgb, color.a * in.opacity));
 }
@@ -7,19 +7,36 @@ pub use bind_group::*;
 use massive_geometry::{Matrix4, Point3};
 pub use renderer::*;
 
-use crate::glyph::glyph_atlas;
+use super::ViewAdjustment;
+use crate::{glyph::glyph_atlas, pods::RevealVertex, tools::RingRange};
 
 pub struct QuadBatch {
     // Matrix is not prepared as a buffer, because it is combined with the camera matrix before
     // uploading to the shader.
     model_matrix: Matrix4,
+    adjustment: ViewAdjustment,
     fs_bind_group: wgpu::BindGroup,
-    vertex_buffer: wgpu::Buffer,
+    /// The texture of the atlas page `fs_bind_group` was created for, see
+    /// [`GlyphAtlas::texture_id`](crate::glyph::GlyphAtlas::texture_id).
+    texture_id: u32,
+    vertices: RingRange,
+    reveals: RingRange,
     quad_count: usize,
 }
 
+impl QuadBatch {
+    pub fn set_model_matrix(&mut self, model_matrix: Matrix4) {
+        self.model_matrix = model_matrix;
+    }
+
+    pub fn set_adjustment(&mut self, adjustment: ViewAdjustment) {
+        self.adjustment = adjustment;
+    }
+}
+
 #[derive(Debug)]
 pub struct QuadInstance {
-    pub atlas_rect: glyph_atlas::Rectangle,
+    pub slot: glyph_atlas::AtlasSlot,
     pub vertices: [Point3; 4],
+    pub reveal: RevealVertex,
 }
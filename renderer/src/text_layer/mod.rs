@@ -2,4 +2,75 @@ mod color_atlas;
 mod renderer;
 mod sdf_atlas;
 
+use cgmath::InnerSpace;
+use massive_geometry::{Matrix4, Vector3};
+use massive_shapes::{Billboard, GlyphRun};
 pub use renderer::*;
+
+use crate::renderer::RenderContext;
+
+/// How the model matrix of a batch is adjusted to the view it is rendered in.
+///
+/// Runs are batched by their adjustment, because it's applied to the model matrix of a batch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ViewAdjustment {
+    billboard: Option<Billboard>,
+    constant_screen_size: bool,
+}
+
+impl ViewAdjustment {
+    fn of(run: &GlyphRun) -> Self {
+        Self {
+            billboard: run.billboard,
+            constant_screen_size: run.constant_screen_size,
+        }
+    }
+
+    fn apply(&self, model_matrix: &Matrix4, context: &RenderContext) -> Matrix4 {
+        let view_projection = &context.view_projection_matrix;
+        let model_matrix = match self.billboard {
+            Some(billboard) => billboard.orient(model_matrix, view_projection),
+            None => *model_matrix,
+        };
+        if !self.constant_screen_size {
+            return model_matrix;
+        }
+
+        // The clip space w of the model's origin is its view depth. A model unit along x covers
+        // the length of the projected x axis divided by it, in normalized device coordinates.
+        let mvp = view_projection * model_matrix;
+        let depth = mvp.w.w;
+        let vp = view_projection;
+        let x_scale = Vector3::new(vp.x.x, vp.y.x, vp.z.x).magnitude();
+        let unit = model_matrix.x.truncate().magnitude();
+        let pixels = unit * x_scale / depth * context.view_size.0 as f64 / 2.0;
+        if depth <= 0.0 || pixels <= 0.0 || !pixels.is_finite() {
+            return model_matrix;
+        }
+        model_matrix * Matrix4::from_scale(1.0 / pixels)
+    }
+
+    /// Apply the adjustment to `model_matrix` and write it for the following draw calls.
+    fn queue_model_matrix(&self, model_matrix: &Matrix4, context: &mut RenderContext) {
+        let adjusted = self.apply(model_matrix, context);
+        context.queue_model_matrix(&adjusted);
+        // Adjusted batches can't be replayed with another camera.
+        if *self != Self::default() {
+            context.mark_camera_dependent();
+        }
+    }
+}
+
+/// The atlas pages of `pages`, each once, in ascending order.
+///
+/// Quads are batched by the page they sample, because each page is bound separately.
+fn atlas_pages(pages: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut distinct = Vec::new();
+    for page in pages {
+        if !distinct.contains(&page) {
+            distinct.push(page);
+        }
+    }
+    distinct.sort_unstable();
+    distinct
+}
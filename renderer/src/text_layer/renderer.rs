@@ -1,6 +1,7 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
+use cgmath::{EuclideanSpace, InnerSpace};
 use cosmic_text as text;
 use massive_geometry::{Matrix4, Point, Point3};
 use massive_scene::Shape;
@@ -12,13 +13,20 @@ use wgpu::Device;
 use super::{
     color_atlas::{self, ColorAtlasRenderer},
     sdf_atlas::{self, SdfAtlasRenderer},
+    ViewAdjustment,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::glyph::GlyphDiskCache;
 use crate::{
     glyph::{
-        glyph_atlas, glyph_rasterization::rasterize_glyph_with_padding, GlyphRasterizationParam,
-        RasterizedGlyphKey, SwashRasterizationParam,
+        glyph_atlas,
+        glyph_rasterization::{is_blank, rasterize_glyph_with_padding},
+        GlyphRasterizationParam, MaskAtlasFormat, RasterizedGlyphKey, SwashRasterizationParam,
     },
+    pods::RevealVertex,
     renderer::{PreparationContext, RenderContext},
+    tools::DrawScheduler,
+    AtlasMetadata, ColorRole, TextRendering,
 };
 
 pub struct TextLayerRenderer {
@@ -28,14 +36,30 @@ pub struct TextLayerRenderer {
     // would allow further optimizations I guess (e.g. an own scratch buffer, etc.).
     scale_context: ScaleContext,
     empty_glyphs: HashSet<RasterizedGlyphKey>,
+    /// Glyphs without pixels at any size, like whitespace, by font and glyph id. They are skipped
+    /// before their keys are even built.
+    blank_glyphs: HashSet<(text::fontdb::ID, u16)>,
+    /// The glyphs located in the atlases during the current preparation, so that repeated
+    /// glyphs are looked up only once.
+    prepared_glyphs: HashMap<RasterizedGlyphKey, Option<LocatedGlyph>>,
+    /// Rasterized glyphs persisted between runs.
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<GlyphDiskCache>,
 
     sdf_renderer: SdfAtlasRenderer,
     sdf_batches: Vec<sdf_atlas::QuadBatch>,
+    /// The index of the shape group each batch in `sdf_batches` was prepared from.
+    sdf_batch_groups: Vec<usize>,
 
     color_renderer: ColorAtlasRenderer,
     color_batches: Vec<color_atlas::QuadBatch>,
+    /// The index of the shape group each batch in `color_batches` was prepared from.
+    color_batch_groups: Vec<usize>,
 }
 
+/// The slot of a glyph in an atlas, its placement, and the atlas it's stored in.
+type LocatedGlyph = (glyph_atlas::AtlasSlot, text::Placement, AtlasKind);
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum AtlasKind {
     Sdf,
@@ -47,108 +71,389 @@ impl TextLayerRenderer {
         device: &Device,
         target_format: wgpu::TextureFormat,
         view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+        frame_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Self {
         Self {
             scale_context: ScaleContext::default(),
             empty_glyphs: HashSet::new(),
+            blank_glyphs: HashSet::new(),
+            prepared_glyphs: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: None,
 
             sdf_renderer: SdfAtlasRenderer::new(
                 device,
                 target_format,
                 view_projection_bind_group_layout,
+                layer_bind_group_layout,
+                frame_bind_group_layout,
             ),
             sdf_batches: Vec::new(),
+            sdf_batch_groups: Vec::new(),
 
             color_renderer: ColorAtlasRenderer::new(
                 device,
                 target_format,
                 view_projection_bind_group_layout,
+                layer_bind_group_layout,
+                frame_bind_group_layout,
             ),
             color_batches: Vec::new(),
+            color_batch_groups: Vec::new(),
         }
     }
 
+    /// Drop all prepared batches.
+    pub fn clear(&mut self) {
+        self.sdf_batches.clear();
+        self.sdf_batch_groups.clear();
+        self.color_batches.clear();
+        self.color_batch_groups.clear();
+        self.sdf_renderer.clear();
+        self.color_renderer.clear();
+    }
+
+    /// Repack the fragmented atlases.
+    ///
+    /// Must only be called after [`Self::clear`], when no prepared quads refer to the atlases
+    /// anymore.
+    pub fn repack_atlases(&mut self) {
+        self.sdf_renderer.atlas.repack_if_fragmented();
+        self.color_renderer.atlas.repack_if_fragmented();
+    }
+
+    /// Prepare shape groups and add them to the prepared batches.
+    ///
+    /// `first_group` is the index of the first group in `shapes` among all groups prepared since
+    /// the last [`Self::clear`].
+    #[tracing::instrument(name = "prepare_text", skip_all)]
     pub fn prepare(
         &mut self,
         context: &mut PreparationContext,
+        first_group: usize,
         shapes: &[(Matrix4, &[&Shape])],
     ) -> Result<()> {
-        self.sdf_batches.clear();
-        self.color_batches.clear();
-
-        for (matrix, shapes) in shapes {
-            // NB: could deref the pointer here using unsafe.
-            let (sdf_batch, color_batch) = self.prepare_runs(
-                context,
-                matrix,
-                // DI: Move this filter up (callers should just pass here what's needed).
-                shapes.iter().filter_map(|s| match s {
+        self.prepared_glyphs.clear();
+        for (index, (matrix, shapes)) in shapes.iter().enumerate() {
+            let group = first_group + index;
+            let text_rendering = context.text_rendering[index];
+            // DI: Move this filter up (callers should just pass here what's needed).
+            let runs: Vec<&GlyphRun> = shapes
+                .iter()
+                .filter_map(|s| match s {
                     Shape::GlyphRun(run) => Some(run),
-                    Shape::Quads(_) => None,
-                }),
-            )?;
-            self.sdf_batches.extend(sdf_batch.into_iter());
-            self.color_batches.extend(color_batch.into_iter());
+                    _ => None,
+                })
+                .collect();
+
+            // Runs are adjusted to the view per batch, so each adjustment needs its own.
+            let mut adjustments: Vec<ViewAdjustment> = Vec::new();
+            for run in &runs {
+                let adjustment = ViewAdjustment::of(run);
+                if !adjustments.contains(&adjustment) {
+                    adjustments.push(adjustment);
+                }
+            }
+
+            for adjustment in adjustments {
+                let (sdf_batches, color_batches) = self.prepare_runs(
+                    context,
+                    matrix,
+                    &text_rendering,
+                    runs.iter()
+                        .copied()
+                        .filter(|run| ViewAdjustment::of(run) == adjustment),
+                )?;
+                for mut sdf_batch in sdf_batches {
+                    sdf_batch.set_adjustment(adjustment);
+                    self.sdf_batches.push(sdf_batch);
+                    self.sdf_batch_groups.push(group);
+                }
+                for mut color_batch in color_batches {
+                    color_batch.set_adjustment(adjustment);
+                    self.color_batches.push(color_batch);
+                    self.color_batch_groups.push(group);
+                }
+            }
         }
 
+        self.flush_atlases(context);
         Ok(())
     }
 
+    /// Upload the glyphs that were stored in the atlases since the last flush.
+    fn flush_atlases(&mut self, context: &PreparationContext) {
+        self.sdf_renderer.atlas.flush(context.queue);
+        self.color_renderer.atlas.flush(context.queue);
+    }
+
+    /// Use a persistent cache for rasterized glyphs. `None` disables it.
+    ///
+    /// Returns the previous cache.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_disk_cache(&mut self, cache: Option<GlyphDiskCache>) -> Option<GlyphDiskCache> {
+        std::mem::replace(&mut self.disk_cache, cache)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn disk_cache_mut(&mut self) -> Option<&mut GlyphDiskCache> {
+        self.disk_cache.as_mut()
+    }
+
+    /// Evict all rasterized glyphs of the given fonts, for example after their data was replaced.
+    pub fn evict_fonts(&mut self, fonts: &HashSet<text::fontdb::ID>) {
+        self.sdf_renderer.atlas.remove_fonts(fonts);
+        self.color_renderer.atlas.remove_fonts(fonts);
+        self.empty_glyphs
+            .retain(|key| !fonts.contains(&key.text.font_id));
+        self.blank_glyphs
+            .retain(|(font_id, _)| !fonts.contains(font_id));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = &mut self.disk_cache {
+            cache.forget_fonts(fonts);
+        }
+    }
+
+    /// Drop all prepared batches, the glyphs in the atlases, and release the vertex buffers.
+    ///
+    /// Glyphs are rasterized again, or loaded from the disk cache, when they are prepared the
+    /// next time.
+    pub fn release_memory(&mut self, device: &Device) {
+        self.clear();
+        self.prepared_glyphs.clear();
+        self.sdf_renderer.release_memory(device);
+        self.color_renderer.release_memory(device);
+    }
+
+    /// Store the distance fields of the following glyphs in `format`. Returns `true` if the
+    /// format changed, which drops the glyphs in the SDF atlas.
+    ///
+    /// Must be called before the batches are prepared again.
+    pub fn set_mask_atlas_format(&mut self, device: &Device, format: MaskAtlasFormat) -> bool {
+        let atlas = &mut self.sdf_renderer.atlas;
+        let channel_packed = format == MaskAtlasFormat::ChannelPacked;
+        if atlas.is_channel_packed() == channel_packed {
+            return false;
+        }
+        atlas.set_mask_format(device, format);
+        true
+    }
+
+    pub fn mask_atlas_format(&self) -> MaskAtlasFormat {
+        if self.sdf_renderer.atlas.is_channel_packed() {
+            MaskAtlasFormat::ChannelPacked
+        } else {
+            MaskAtlasFormat::R8
+        }
+    }
+
+    pub fn atlases(&self) -> Vec<AtlasMetadata> {
+        [
+            ("SDF", &self.sdf_renderer.atlas),
+            ("Color", &self.color_renderer.atlas),
+        ]
+        .into_iter()
+        .map(|(name, atlas)| AtlasMetadata {
+            name: name.into(),
+            size: atlas.size(),
+            pages: atlas.page_count(),
+            glyphs: atlas.glyph_count(),
+            packing: atlas.packing_stats(),
+            uploaded_bytes: atlas.uploaded_bytes(),
+        })
+        .collect()
+    }
+
+    /// Update the model matrices of the prepared batches without preparing them again.
+    ///
+    /// `matrices` must be in the order of the shape groups passed to [`Self::prepare`].
+    pub fn update_matrices(&mut self, matrices: &[Matrix4]) {
+        for (batch, group) in self.sdf_batches.iter_mut().zip(&self.sdf_batch_groups) {
+            batch.set_model_matrix(matrices[*group]);
+        }
+        for (batch, group) in self.color_batches.iter_mut().zip(&self.color_batch_groups) {
+            batch.set_model_matrix(matrices[*group]);
+        }
+    }
+
     pub fn render<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
-        self.sdf_renderer.render(context, &self.sdf_batches);
-        self.color_renderer.render(context, &self.color_batches);
+        if context.debug_mode.renders_scene() {
+            self.render_batches(context);
+        }
+        self.sdf_renderer
+            .render_debug(context, &self.sdf_batches, &self.sdf_batch_groups);
+        self.color_renderer
+            .render_debug(context, &self.color_batches, &self.color_batch_groups);
+    }
+
+    /// Draw the SDF and color batches of all groups, ordered to bind as few pipelines, layers and
+    /// atlas pages as possible.
+    fn render_batches<'rpass>(&'rpass self, context: &mut RenderContext<'_, 'rpass>) {
+        let mut scheduler = DrawScheduler::default();
+        for (index, (batch, group)) in self
+            .sdf_batches
+            .iter()
+            .zip(&self.sdf_batch_groups)
+            .enumerate()
+        {
+            if context.renders_group(*group) {
+                let state = self.sdf_renderer.draw_state(context, batch, *group);
+                scheduler.push(context.blend_order(*group), state, index);
+            }
+        }
+        for (index, (batch, group)) in self
+            .color_batches
+            .iter()
+            .zip(&self.color_batch_groups)
+            .enumerate()
+        {
+            if context.renders_group(*group) {
+                let state = self.color_renderer.draw_state(context, batch, *group);
+                scheduler.push(context.blend_order(*group), state, index);
+            }
+        }
+
+        for draw in scheduler.schedule() {
+            if draw.state.pipeline == SdfAtlasRenderer::PIPELINE {
+                if draw.pipeline_changed {
+                    self.sdf_renderer.begin(context, &self.sdf_batches);
+                }
+                let (batch, group) = (
+                    &self.sdf_batches[draw.index],
+                    self.sdf_batch_groups[draw.index],
+                );
+                self.sdf_renderer.draw(context, batch, group, &draw);
+            } else {
+                if draw.pipeline_changed {
+                    self.color_renderer.begin(context, &self.color_batches);
+                }
+                let (batch, group) = (
+                    &self.color_batches[draw.index],
+                    self.color_batch_groups[draw.index],
+                );
+                self.color_renderer.draw(context, batch, group, &draw);
+            }
+        }
     }
 
-    /// Prepare a number of glyph runs and produce a TextLayer.
+    /// Prepare a number of glyph runs and produce the SDF and color batches, one for each atlas
+    /// page the glyphs are stored in.
     ///
     /// All of the runs use the same model matrix.
     fn prepare_runs<'a>(
         &mut self,
         context: &mut PreparationContext,
         model_matrix: &Matrix4,
+        text_rendering: &TextRendering,
         // TODO: this double reference is quite unusual here
         runs: impl Iterator<Item = &'a GlyphRun>,
-    ) -> Result<(Option<sdf_atlas::QuadBatch>, Option<color_atlas::QuadBatch>)> {
+    ) -> Result<(Vec<sdf_atlas::QuadBatch>, Vec<color_atlas::QuadBatch>)> {
         // Step 1: Get all instance data.
         // OO: Compute a conservative capacity?
         let mut sdf_glyphs = Vec::new();
         let mut color_glyphs = Vec::new();
 
+        let greeking_threshold = context
+            .quality
+            .greeking_threshold
+            .map(|threshold| threshold / Self::physical_pixel_scale(context, model_matrix));
+
         for run in runs {
-            let translation = run.translation;
-            for glyph in &run.glyphs {
-                if let Some((rect, placement, kind)) =
-                    self.rasterized_glyph_atlas_rect(context, run.text_weight, glyph)?
+            let mut translation = run.translation;
+            if text_rendering.pixel_snapping {
+                translation.x = translation.x.round();
+                translation.y = translation.y.round();
+            }
+            // Color glyphs, like emojis, keep their colors.
+            let text_color = match &context.high_contrast {
+                Some(high_contrast) => high_contrast.color(ColorRole::Text, run.text_color),
+                None => run.text_color,
+            };
+
+            // The size of runs with a constant screen size does not depend on the model matrix.
+            if let Some(threshold) = greeking_threshold.filter(|_| !run.constant_screen_size) {
+                let (_, height) = run.metrics.size();
+                if (height as f64) < threshold {
+                    let slot = self.sdf_renderer.atlas.solid_rect(context.device)?;
+                    let vertices = Self::greeked_vertices(run).map(|p| p + translation);
+                    sdf_glyphs.push(sdf_atlas::QuadInstance {
+                        slot,
+                        vertices,
+                        color: text_color,
+                        reveal: Self::reveal_vertex(run, 0, &vertices),
+                    });
+                    continue;
+                }
+            }
+
+            for (index, glyph) in run.glyphs.iter().enumerate() {
+                // Indentation of code consists of lots of them.
+                if self
+                    .blank_glyphs
+                    .contains(&(glyph.key.font_id, glyph.key.glyph_id))
                 {
+                    continue;
+                }
+                if let Some((slot, placement, kind)) = self.rasterized_glyph_atlas_rect(
+                    context,
+                    run.text_weight,
+                    text_rendering.hinting,
+                    glyph,
+                )? {
+                    let placement = Self::upscaled(placement, context.quality.sdf_downscale.max(1));
                     // OO: translation might be applied to two points only (lt, rb)
                     let vertices =
                         Self::glyph_vertices(run, glyph, &placement).map(|p| p + translation);
+                    let reveal = Self::reveal_vertex(run, index, &vertices);
 
                     match kind {
                         AtlasKind::Sdf => {
                             sdf_glyphs.push(sdf_atlas::QuadInstance {
-                                atlas_rect: rect,
+                                slot,
                                 vertices,
                                 // OO: Text color is changing per run only.
-                                color: run.text_color,
+                                color: text_color,
+                                reveal,
                             })
                         }
                         AtlasKind::Color => color_glyphs.push(color_atlas::QuadInstance {
-                            atlas_rect: rect,
+                            slot,
                             vertices,
+                            reveal,
                         }),
                     }
                 } // else: Glyph is empty: Not rendered.
             }
         }
 
-        let sdf_batch = self.sdf_renderer.batch(context, model_matrix, &sdf_glyphs);
+        let sdf_batches = self.sdf_renderer.batch(context, model_matrix, &sdf_glyphs);
 
-        let color_batch = self
+        let color_batches = self
             .color_renderer
             .batch(context, model_matrix, &color_glyphs);
 
-        Ok((sdf_batch, color_batch))
+        Ok((sdf_batches, color_batches))
+    }
+
+    /// Rasterize a glyph, or load it from the disk cache.
+    fn rasterize(
+        &mut self,
+        context: &mut PreparationContext,
+        glyph_key: &RasterizedGlyphKey,
+    ) -> Option<text::SwashImage> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(disk_cache) = &mut self.disk_cache {
+            if let Some(image) = disk_cache.get(context.fonts, glyph_key) {
+                return Some(image);
+            }
+            let image =
+                rasterize_glyph_with_padding(context.fonts, &mut self.scale_context, glyph_key)?;
+            disk_cache.insert(context.fonts, glyph_key, &image);
+            return Some(image);
+        }
+
+        rasterize_glyph_with_padding(context.fonts, &mut self.scale_context, glyph_key)
     }
 
     // This makes sure that there is a rasterized glyph in the atlas and returns the rectangle.
@@ -156,67 +461,187 @@ impl TextLayerRenderer {
         &mut self,
         context: &mut PreparationContext,
         weight: TextWeight,
+        hinted: bool,
+        glyph: &RunGlyph,
+    ) -> Result<Option<LocatedGlyph>> {
+        let glyph_key = Self::glyph_key(weight, hinted, context.quality.sdf_downscale, glyph);
+
+        // Repetitive content, like logs, shows the same glyphs over and over again.
+        if let Some(located) = self.prepared_glyphs.get(&glyph_key) {
+            return Ok(*located);
+        }
+        let located = self.locate_glyph(context, &glyph_key)?;
+        self.prepared_glyphs.insert(glyph_key, located);
+        Ok(located)
+    }
+
+    /// The key of a glyph, rasterized `downscale` times smaller than its size, see
+    /// [`crate::Quality::sdf_downscale`].
+    fn glyph_key(
+        weight: TextWeight,
+        hinted: bool,
+        downscale: u32,
         glyph: &RunGlyph,
-    ) -> Result<Option<(glyph_atlas::Rectangle, text::Placement, AtlasKind)>> {
-        let glyph_key = RasterizedGlyphKey {
-            text: glyph.key,
+    ) -> RasterizedGlyphKey {
+        let mut text = glyph.key;
+        if downscale > 1 {
+            let size = f32::from_bits(text.font_size_bits) / downscale as f32;
+            text.font_size_bits = size.to_bits();
+        }
+        RasterizedGlyphKey {
+            text,
             param: GlyphRasterizationParam {
                 prefer_sdf: true,
+                prefer_msdf: false,
                 swash: SwashRasterizationParam {
-                    hinted: true,
+                    hinted,
                     weight: Weight(weight.0),
                 },
             },
-        };
+        }
+    }
 
-        if let Some((rect, image)) = self.sdf_renderer.atlas.get(&glyph_key) {
-            return Ok(Some((rect, image.placement, AtlasKind::Sdf)));
+    /// Rasterize the glyphs of runs that are about to be prepared and store them in the atlases.
+    ///
+    /// Stops after `budget` glyphs were rasterized. Returns the number of rasterized glyphs.
+    pub fn prefetch<'a>(
+        &mut self,
+        context: &mut PreparationContext,
+        runs: impl IntoIterator<Item = (&'a GlyphRun, TextRendering)>,
+        budget: usize,
+    ) -> Result<usize> {
+        let mut rasterized = 0;
+        'runs: for (run, text_rendering) in runs {
+            for glyph in &run.glyphs {
+                if rasterized == budget {
+                    break 'runs;
+                }
+                if self
+                    .blank_glyphs
+                    .contains(&(glyph.key.font_id, glyph.key.glyph_id))
+                {
+                    continue;
+                }
+                let glyph_key = Self::glyph_key(
+                    run.text_weight,
+                    text_rendering.hinting,
+                    context.quality.sdf_downscale,
+                    glyph,
+                );
+                let resident = self.sdf_renderer.atlas.get(&glyph_key).is_some()
+                    || self.color_renderer.atlas.get(&glyph_key).is_some()
+                    || self.empty_glyphs.contains(&glyph_key);
+                if !resident {
+                    self.locate_glyph(context, &glyph_key)?;
+                    rasterized += 1;
+                }
+            }
         }
+        self.flush_atlases(context);
+        Ok(rasterized)
+    }
 
-        if let Some((rect, image)) = self.color_renderer.atlas.get(&glyph_key) {
-            return Ok(Some((rect, image.placement, AtlasKind::Color)));
+    /// Find a glyph in the atlases, or rasterize and store it.
+    fn locate_glyph(
+        &mut self,
+        context: &mut PreparationContext,
+        glyph_key: &RasterizedGlyphKey,
+    ) -> Result<Option<LocatedGlyph>> {
+        if let Some((slot, image)) = self.sdf_renderer.atlas.get(glyph_key) {
+            return Ok(Some((slot, image.placement, AtlasKind::Sdf)));
+        }
+
+        if let Some((slot, image)) = self.color_renderer.atlas.get(glyph_key) {
+            return Ok(Some((slot, image.placement, AtlasKind::Color)));
         }
 
         // Atlas / cache miss, empty cached glyph?.
-        if self.empty_glyphs.contains(&glyph_key) {
+        if self.empty_glyphs.contains(glyph_key) {
             return Ok(None);
         }
 
         // Not yet in an atlas and not empty. Now rasterize.
-        let Some(image) =
-            rasterize_glyph_with_padding(context.font_system, &mut self.scale_context, &glyph_key)
-        else {
-            self.empty_glyphs.insert(glyph_key);
+        let Some(image) = self.rasterize(context, glyph_key) else {
+            self.empty_glyphs.insert(glyph_key.clone());
             return Ok(None);
         };
+        if is_blank(&image) {
+            self.blank_glyphs
+                .insert((glyph_key.text.font_id, glyph_key.text.glyph_id));
+            return Ok(None);
+        }
 
         let image_placement = image.placement;
 
         match image.content {
             SwashContent::Mask => {
-                let rect_in_atlas = self.sdf_renderer.atlas.store(
-                    context.device,
-                    context.queue,
-                    &glyph_key,
-                    image,
-                )?;
+                let slot = self
+                    .sdf_renderer
+                    .atlas
+                    .store(context.device, glyph_key, image)?;
 
-                Ok(Some((rect_in_atlas, image_placement, AtlasKind::Sdf)))
+                Ok(Some((slot, image_placement, AtlasKind::Sdf)))
             }
             SwashContent::Color => {
-                let rect_in_atlas = self.color_renderer.atlas.store(
-                    context.device,
-                    context.queue,
-                    &glyph_key,
-                    image,
-                )?;
+                let slot = self
+                    .color_renderer
+                    .atlas
+                    .store(context.device, glyph_key, image)?;
 
-                Ok(Some((rect_in_atlas, image_placement, AtlasKind::Color)))
+                Ok(Some((slot, image_placement, AtlasKind::Color)))
             }
             SwashContent::SubpixelMask => panic!("Unsupported Subpixel Mask"),
         }
     }
 
+    /// Approximate number of physical pixels a pixel of a run covers when rendered with the
+    /// `model_matrix`.
+    ///
+    /// The scale is measured at the origin of the model in the last presented view, so it
+    /// includes the distance to the camera. Without a view, this assumes that the camera is placed
+    /// so that a pixel at z = 0 maps to a physical pixel.
+    fn physical_pixel_scale(context: &PreparationContext, model_matrix: &Matrix4) -> f64 {
+        let (_, surface_height) = context.surface_size;
+        let Some(view_projection_matrix) = context.view_projection_matrix else {
+            return model_matrix.y.truncate().magnitude() * surface_height as f64 / 2.0;
+        };
+        let matrix = view_projection_matrix * model_matrix;
+        // Perspective divides by w, which grows with the distance to the camera.
+        let w = matrix.w.w.abs().max(f64::EPSILON);
+        matrix.y.truncate().truncate().magnitude() / w * surface_height as f64 / 2.0
+    }
+
+    /// The placement of a glyph that was rasterized `downscale` times smaller than its size.
+    fn upscaled(placement: text::Placement, downscale: u32) -> text::Placement {
+        let factor = downscale as i32;
+        text::Placement {
+            left: placement.left * factor,
+            top: placement.top * factor,
+            width: placement.width * downscale,
+            height: placement.height * downscale,
+        }
+    }
+
+    /// The vertices of the bar that replaces a greeked run.
+    ///
+    /// It covers the lower half of the ascent, which roughly resembles the x-height.
+    fn greeked_vertices(run: &GlyphRun) -> [Point3; 4] {
+        let max_ascent = run.metrics.max_ascent as f64;
+        let left = 0.0;
+        let right = run.metrics.width as f64;
+        let top = max_ascent / 2.0;
+        let bottom = max_ascent;
+
+        let points: [Point; 4] = [
+            (left, top).into(),
+            (left, bottom).into(),
+            (right, bottom).into(),
+            (right, top).into(),
+        ];
+
+        points.map(|f| f.with_z(0.0))
+    }
+
     fn glyph_vertices(
         run: &GlyphRun,
         glyph: &RunGlyph,
@@ -240,4 +665,10 @@ impl TextLayerRenderer {
 
         points.map(|f| f.with_z(0.0))
     }
+
+    /// The reveal animation of the glyph at `index` with the quad `vertices`.
+    fn reveal_vertex(run: &GlyphRun, index: usize, vertices: &[Point3; 4]) -> RevealVertex {
+        let center = vertices[0].midpoint(vertices[2]);
+        RevealVertex::new(run.reveal.as_ref(), index, center)
+    }
 }
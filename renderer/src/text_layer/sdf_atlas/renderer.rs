@@ -1,17 +1,16 @@
 use std::mem;
 
-use wgpu::{
-    util::{BufferInitDescriptor, DeviceExt},
-    TextureFormat,
-};
-
 use massive_geometry::Matrix4;
 
 use crate::{
-    glyph::GlyphAtlas,
-    pods::TextureColorVertex,
+    debug::DebugPipelines,
+    glyph::{GlyphAtlas, MaskAtlasFormat},
+    pods::{RevealVertex, SdfGlyphVertex},
     renderer::{PreparationContext, RenderContext},
-    tools::{create_pipeline, texture_sampler, QuadIndexBuffer},
+    text_layer::atlas_pages,
+    tools::{
+        create_pipeline, texture_sampler, DrawState, QuadIndexBuffer, ScheduledDraw, VertexRing,
+    },
 };
 
 use super::{BindGroupLayout, QuadBatch, QuadInstance};
@@ -23,21 +22,34 @@ pub struct SdfAtlasRenderer {
     fs_bind_group_layout: BindGroupLayout,
     // OO: Share this sucker.
     index_buffer: QuadIndexBuffer,
+    /// The vertices and reveals of all batches.
+    vertex_ring: VertexRing,
+    debug: DebugPipelines,
 }
 
 impl SdfAtlasRenderer {
+    /// The pipeline in the [`DrawState`] of the batches, SDF glyphs are drawn before color glyphs.
+    pub const PIPELINE: usize = 0;
+
     pub fn new(
         device: &wgpu::Device,
         target_format: wgpu::TextureFormat,
         view_projection_bind_group_layout: &wgpu::BindGroupLayout,
+        layer_bind_group_layout: &wgpu::BindGroupLayout,
+        frame_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Self {
         let fs_bind_group_layout = BindGroupLayout::new(device);
 
-        let shader = &device.create_shader_module(wgpu::include_wgsl!("sdf_atlas.wgsl"));
+        let shader = &crate::shader_module!(device, "text_layer/sdf_atlas/sdf_atlas.wgsl");
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Atlas SDF Pipeline Layout"),
-            bind_group_layouts: &[view_projection_bind_group_layout, &fs_bind_group_layout],
+            bind_group_layouts: &[
+                view_projection_bind_group_layout,
+                layer_bind_group_layout,
+                &fs_bind_group_layout,
+                frame_bind_group_layout,
+            ],
             push_constant_ranges: &[],
         });
 
@@ -47,7 +59,7 @@ impl SdfAtlasRenderer {
             write_mask: wgpu::ColorWrites::ALL,
         })];
 
-        let vertex_layout = [TextureColorVertex::layout()];
+        let vertex_layout = [SdfGlyphVertex::layout(), RevealVertex::layout()];
 
         let pipeline = create_pipeline(
             "Atlas SDF Pipeline",
@@ -60,29 +72,63 @@ impl SdfAtlasRenderer {
         );
 
         Self {
-            atlas: GlyphAtlas::new(device, TextureFormat::R8Unorm),
+            atlas: GlyphAtlas::new_masks(device, MaskAtlasFormat::default()),
             texture_sampler: texture_sampler::linear_clamping(device),
             fs_bind_group_layout,
             pipeline,
             index_buffer: QuadIndexBuffer::new(device),
+            vertex_ring: VertexRing::new("SDF Atlas Vertex Ring"),
+            debug: DebugPipelines::new(
+                device,
+                target_format,
+                view_projection_bind_group_layout,
+                SdfGlyphVertex::layout().array_stride,
+            ),
         }
     }
 
-    // Convert a number of instances to a batch.
+    /// Release the vertices of all batches.
+    pub fn clear(&mut self) {
+        self.vertex_ring.clear();
+    }
+
+    /// Drop the glyphs and release the vertex buffers. There must be no prepared batches.
+    pub fn release_memory(&mut self, device: &wgpu::Device) {
+        self.atlas.clear(device);
+        self.vertex_ring.release();
+    }
+
+    /// Convert a number of instances to batches, one for each atlas page they are stored in.
     pub fn batch(
         &mut self,
         context: &PreparationContext,
         model_matrix: &Matrix4,
         instances: &[QuadInstance],
-    ) -> Option<QuadBatch> {
-        if instances.is_empty() {
-            return None;
-        }
+    ) -> Vec<QuadBatch> {
+        atlas_pages(instances.iter().map(|instance| instance.slot.page))
+            .into_iter()
+            .map(|page| {
+                let instances = instances
+                    .iter()
+                    .filter(|instance| instance.slot.page == page);
+                self.page_batch(context, model_matrix, page, instances)
+            })
+            .collect()
+    }
 
-        let mut vertices = Vec::with_capacity(instances.len() * 4);
+    /// Convert the instances stored in an atlas page to a batch.
+    fn page_batch<'a>(
+        &mut self,
+        context: &PreparationContext,
+        model_matrix: &Matrix4,
+        page: usize,
+        instances: impl Iterator<Item = &'a QuadInstance>,
+    ) -> QuadBatch {
+        let mut vertices = Vec::new();
+        let mut reveals = Vec::new();
 
         for instance in instances {
-            let r = instance.atlas_rect;
+            let r = instance.slot.rect;
             // ADR: u/v normalization is dont in the shader, for once, its probably free, and scondly
             // we don't have to care about the atlas texture growing as long the rects stay the same.
             let (ltx, lty) = (r.min.x as f32, r.min.y as f32);
@@ -90,56 +136,98 @@ impl SdfAtlasRenderer {
 
             let v = &instance.vertices;
             let color = instance.color;
+            let channel = instance.slot.channel_mask();
             vertices.extend([
-                TextureColorVertex::new(v[0], (ltx, lty), color),
-                TextureColorVertex::new(v[1], (ltx, rby), color),
-                TextureColorVertex::new(v[2], (rbx, rby), color),
-                TextureColorVertex::new(v[3], (rbx, lty), color),
+                SdfGlyphVertex::new(v[0], (ltx, lty), color, channel),
+                SdfGlyphVertex::new(v[1], (ltx, rby), color, channel),
+                SdfGlyphVertex::new(v[2], (rbx, rby), color, channel),
+                SdfGlyphVertex::new(v[3], (rbx, lty), color, channel),
             ]);
+            reveals.extend([instance.reveal; 4]);
         }
 
-        let device = context.device;
-
-        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Text Layer Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let quad_count = reveals.len() / 4;
+        let (device, queue) = (context.device, context.queue);
+        let vertices = self
+            .vertex_ring
+            .allocate(device, queue, bytemuck::cast_slice(&vertices));
+        let reveals = self
+            .vertex_ring
+            .allocate(device, queue, bytemuck::cast_slice(&reveals));
 
         let bind_group = self.fs_bind_group_layout.create_bind_group(
             context.device,
-            self.atlas.texture_view(),
+            self.atlas.texture_view(page),
             &self.texture_sampler,
         );
 
         // Grow index buffer as needed.
 
-        let quad_count = instances.len();
         self.index_buffer
             .ensure_can_index_num_quads(context.device, quad_count);
+        self.debug.prepare(context.device, quad_count);
 
-        Some(QuadBatch {
+        QuadBatch {
             model_matrix: *model_matrix,
+            adjustment: Default::default(),
             fs_bind_group: bind_group,
-            vertex_buffer,
+            texture_id: self.atlas.texture_id(page),
+            vertices,
+            reveals,
             quad_count,
-        })
+        }
     }
 
-    pub fn render<'rpass>(
+    pub fn render_debug<'rpass>(
         &'rpass self,
         context: &mut RenderContext<'_, 'rpass>,
         batches: &'rpass [QuadBatch],
+        groups: &[usize],
     ) {
-        // `set_index_buffer` will fail with empty buffers, so exit early if there is nothing to do.
-        if batches.is_empty() {
-            return;
-        }
+        let debug_batches: Vec<_> = batches
+            .iter()
+            .zip(groups)
+            .filter(|(_, group)| context.renders_group(**group))
+            .map(|(b, _)| {
+                let model_matrix = b.adjustment.apply(&b.model_matrix, context);
+                (
+                    model_matrix,
+                    self.vertex_ring.slice(&b.vertices),
+                    b.quad_count,
+                )
+            })
+            .collect();
+        self.debug
+            .render(context, &self.index_buffer, &debug_batches);
+    }
+
+    /// The state [`Self::draw`] binds for a batch.
+    pub fn draw_state(
+        &self,
+        context: &RenderContext,
+        batch: &QuadBatch,
+        group: usize,
+    ) -> DrawState {
+        DrawState::new(
+            Self::PIPELINE,
+            context.layer_bind_group(group),
+            batch.texture_id,
+        )
+    }
 
+    /// Set the pipeline and the state shared by all batches.
+    ///
+    /// `batches` must not be empty, `set_index_buffer` fails with empty buffers.
+    pub fn begin<'rpass>(
+        &'rpass self,
+        context: &mut RenderContext<'_, 'rpass>,
+        batches: &[QuadBatch],
+    ) {
         let pass = &mut context.pass;
         pass.set_pipeline(&self.pipeline);
         // DI: May do this inside this renderer and pass a Matrix to prepare?.
         pass.set_bind_group(0, context.view_projection_bind_group, &[]);
+        pass.set_bind_group(3, context.frame_bind_group, &[]);
         // DI: May share index buffers between renderers?
         //
         // OO: Don't pass the full index buffer here, only what's actully needed (it is growing
@@ -157,30 +245,36 @@ impl SdfAtlasRenderer {
             ),
             wgpu::IndexFormat::Uint16,
         );
+    }
 
-        for QuadBatch {
-            model_matrix,
-            fs_bind_group,
-            vertex_buffer,
-            quad_count,
-        } in batches
-        {
-            let text_layer_matrix = context.view_projection_matrix * model_matrix;
-
-            // OO: Set bind group only once and update the buffer?
-            context.queue_view_projection_matrix(&text_layer_matrix);
-
-            let pass = &mut context.pass;
-            pass.set_bind_group(0, context.view_projection_bind_group, &[]);
-
-            pass.set_bind_group(1, fs_bind_group, &[]);
-            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+    /// Draw a batch after [`Self::begin`], binding only the state that changed since the
+    /// previous draw.
+    pub fn draw<'rpass>(
+        &'rpass self,
+        context: &mut RenderContext<'_, 'rpass>,
+        batch: &'rpass QuadBatch,
+        group: usize,
+        draw: &ScheduledDraw,
+    ) {
+        batch
+            .adjustment
+            .queue_model_matrix(&batch.model_matrix, context);
 
-            pass.draw_indexed(
-                0..(quad_count * QuadIndexBuffer::INDICES_PER_QUAD) as u32,
-                0,
-                0..1,
-            )
+        let layer_bind_group = context.layer_bind_group(group);
+        let pass = &mut context.pass;
+        if draw.layer_changed {
+            pass.set_bind_group(1, layer_bind_group, &[]);
         }
+        if draw.page_changed {
+            pass.set_bind_group(2, &batch.fs_bind_group, &[]);
+        }
+        pass.set_vertex_buffer(0, self.vertex_ring.slice(&batch.vertices));
+        pass.set_vertex_buffer(1, self.vertex_ring.slice(&batch.reveals));
+
+        pass.draw_indexed(
+            0..(batch.quad_count * QuadIndexBuffer::INDICES_PER_QUAD) as u32,
+            0,
+            0..1,
+        )
     }
 }
@@ -7,6 +7,10 @@ struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) color: vec3<f32>,
+    @location(3) reveal: vec4<f32>,
+    @location(4) center: vec2<f32>,
+    // Selects the channel of a channel packed atlas, see `AtlasSlot::channel_mask`.
+    @location(5) channel: vec4<f32>,
 }
 
 struct VertexOutput {
@@ -14,6 +18,39 @@ struct VertexOutput {
     // Unnormalized texture pixel coordinates.
     @location(0) tex_coords: vec2<f32>,
     @location(1) @interpolate(flat) color: vec3<f32>,
+    @location(2) @interpolate(flat) opacity: f32,
+    @location(3) @interpolate(flat) channel: vec4<f32>,
+}
+
+// Frame uniforms, see `FrameUniforms`.
+
+struct Frame {
+    // time, unused, unused, unused
+    parameters: vec4<f32>,
+    // The text rendering of the renderer: sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
+@group(3) @binding(0)
+var<uniform> frame: Frame;
+
+// The position and the opacity of a vertex of a revealed glyph, see `RevealVertex`.
+fn reveal_vertex(position: vec3<f32>, reveal: vec4<f32>, center: vec2<f32>) -> vec4<f32> {
+    let effect = reveal.z;
+    if (effect == 0.0) {
+        return vec4<f32>(position, 1.0);
+    }
+    let t = clamp((frame.parameters.x - reveal.x) / max(reveal.y, 0.0001), 0.0, 1.0);
+    // ease out cubic
+    let e = 1.0 - pow(1.0 - t, 3.0);
+    var p = position;
+    if (effect == 2.0) {
+        // y points down.
+        p.y += (1.0 - e) * reveal.w;
+    } else if (effect == 3.0) {
+        p = vec3<f32>(center + (p.xy - center) * e, p.z);
+    }
+    return vec4<f32>(p, e);
 }
 
 @vertex
@@ -22,16 +59,45 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = vertex_input.tex_coords;
-    out.clip_position = view_model * vec4<f32>(vertex_input.position, 1.0);
+    let revealed = reveal_vertex(vertex_input.position, vertex_input.reveal, vertex_input.center);
+    out.clip_position = view_model * vec4<f32>(revealed.xyz, 1.0);
     out.color = vertex_input.color;
+    out.opacity = revealed.w;
+    out.channel = vertex_input.channel;
     return out;
 }
 
 // Fragment shader
 
+// Layer uniforms, see `LayerUniforms`.
+
+struct Layer {
+    tint: vec4<f32>,
+    highlight: vec4<f32>,
+    // highlight factor, time, pulse frequency, unused
+    parameters: vec4<f32>,
+    user: vec4<f32>,
+    // sharpness, dilation, gamma, set
+    text: vec4<f32>,
+}
+
 @group(1) @binding(0)
+var<uniform> layer: Layer;
+
+const tau = 6.28318530718;
+
+fn layer_color(color: vec4<f32>) -> vec4<f32> {
+    let tinted = color * layer.tint;
+    var amount = layer.highlight.a * layer.parameters.x;
+    if (layer.parameters.z != 0.0) {
+        amount *= 0.5 + 0.5 * sin(layer.parameters.y * layer.parameters.z * tau);
+    }
+    return vec4<f32>(mix(tinted.rgb, layer.highlight.rgb, amount), tinted.a);
+}
+
+@group(2) @binding(0)
 var t_texture: texture_2d<f32>;
-@group(1) @binding(1)
+@group(2) @binding(1)
 var s_sampler: sampler;
 
 // For the fragment shader:
@@ -48,10 +114,15 @@ const df_epsilon = 0.0001;
 
 @fragment
 fn fs_sdf(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The text rendering of the layer, or of the renderer if the layer has none, see
+    // `TextRendering`.
+    let text = select(frame.text, layer.text, layer.text.w != 0.0);
+
     // fetch the SDF value from the texture
     // OO: Use 1 / texture_size and multiply.
     let texture_size = vec2<f32>(textureDimensions(t_texture));
-    let distance = (textureSample(t_texture, s_sampler, in.tex_coords / texture_size).r - df_threshold) * df_multiplier;
+    let texel = textureSample(t_texture, s_sampler, in.tex_coords / texture_size);
+    let distance = (dot(texel, in.channel) - df_threshold) * df_multiplier + text.y;
 
     // apply anti-aliasing
     var dist_grad: vec2<f32> = vec2(dpdx(distance), dpdy(distance));
@@ -75,11 +146,11 @@ fn fs_sdf(in: VertexOutput) -> @location(0) vec4<f32> {
     );
 
     // let afwidth = length(grad) * half_sqrt2;
-    let afwidth = df_aa_factor * length(grad);
+    let afwidth = df_aa_factor * length(grad) / max(text.x, df_epsilon);
 
     // gamma correct
     // let val = saturate((distance + afwidth) / (2.0 * afwidth));
-    let val = smoothstep(-afwidth, afwidth, distance);
+    let val = pow(smoothstep(-afwidth, afwidth, distance), 1.0 / max(text.z, df_epsilon));
 
-    return vec4<f32>(in.color, val);
+    return layer_color(vec4<f32>(in.color, val * in.opacity));
 }
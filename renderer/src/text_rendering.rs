@@ -0,0 +1,99 @@
+//! Presets for the appearance of text.
+//!
+//! A [`TextRendering`] bundles the settings that influence how glyphs are rasterized, placed, and
+//! shaded. They can be set for the whole renderer, and overridden per layer, see
+//! [`crate::Renderer::set_text_rendering`] and [`crate::Renderer::set_layer_text_rendering`].
+
+use serde::{Deserialize, Serialize};
+
+/// Named text rendering settings that resemble the appearance of the native platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum TextPreset {
+    /// Hinted glyphs snapped to pixels with sharp edges, like Windows.
+    Crisp,
+    /// Hinted glyphs with soft edges.
+    #[default]
+    Smooth,
+    /// Unhinted glyphs that keep the shapes of the font and are slightly heavier, like macOS.
+    Print,
+}
+
+impl TextPreset {
+    pub fn settings(self) -> TextRendering {
+        match self {
+            TextPreset::Crisp => TextRendering {
+                hinting: true,
+                pixel_snapping: true,
+                sharpness: 1.4,
+                dilation: 0.0,
+                gamma: 1.0,
+            },
+            TextPreset::Smooth => TextRendering {
+                hinting: true,
+                pixel_snapping: false,
+                sharpness: 1.0,
+                dilation: 0.0,
+                gamma: 1.0,
+            },
+            TextPreset::Print => TextRendering {
+                hinting: false,
+                pixel_snapping: false,
+                sharpness: 1.0,
+                dilation: 0.05,
+                gamma: 1.2,
+            },
+        }
+    }
+
+    /// The next preset, for cycling through all presets with a key.
+    pub fn next(self) -> Self {
+        match self {
+            TextPreset::Crisp => TextPreset::Smooth,
+            TextPreset::Smooth => TextPreset::Print,
+            TextPreset::Print => TextPreset::Crisp,
+        }
+    }
+}
+
+/// Settings for the appearance of text.
+///
+/// `hinting` and `pixel_snapping` are applied when shapes are prepared, so changing them prepares
+/// the scene again. The others are shader parameters of SDF glyphs and take effect with the next
+/// frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TextRendering {
+    /// Fit the outlines of the glyphs to the pixel grid when they are rasterized.
+    pub hinting: bool,
+    /// Place the glyph runs at whole pixels of their positions.
+    pub pixel_snapping: bool,
+    /// Divides the width of the antialiased edges. Higher is sharper.
+    pub sharpness: f32,
+    /// Moves the edges outwards in pixels of the distance field. Positive values are heavier.
+    pub dilation: f32,
+    /// Applied to the coverage of the edges. Values above 1 are heavier.
+    pub gamma: f32,
+}
+
+impl TextRendering {
+    /// The shader parameters: sharpness, dilation, gamma, and 1 to mark them as set.
+    pub(crate) fn shader_parameters(&self) -> [f32; 4] {
+        [self.sharpness, self.dilation, self.gamma, 1.0]
+    }
+
+    /// Whether `other` needs the shapes to be prepared again.
+    pub(crate) fn prepares_differently(&self, other: &Self) -> bool {
+        self.hinting != other.hinting || self.pixel_snapping != other.pixel_snapping
+    }
+}
+
+impl Default for TextRendering {
+    fn default() -> Self {
+        TextPreset::default().settings()
+    }
+}
+
+impl From<TextPreset> for TextRendering {
+    fn from(preset: TextPreset) -> Self {
+        preset.settings()
+    }
+}
@@ -0,0 +1,69 @@
+// Multi-channel SDF glyphs, see `msdf_gen.rs`.
+//
+// Bindings and vertices are the same as in `glyph.wgsl`.
+
+// Vertex shader
+
+@group(0) @binding(0)
+var<uniform> model_view: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    vertex_input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = vertex_input.tex_coords;
+    out.clip_position = model_view * vec4<f32>(vertex_input.position, 1.0);
+    return out;
+}
+
+// Fragment shader
+
+struct TextureSize {
+    value: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+@group(1) @binding(0)
+var t_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var<uniform> texture_size: TextureSize;
+@group(1) @binding(2)
+var s_sampler: sampler;
+@group(1) @binding(3)
+var<uniform> color: vec4<f32>;
+
+// The distances are encoded like the single channel distance fields, see `glyph.wgsl`.
+const df_multiplier = 7.96875;
+const df_threshold = 0.50196078431;
+const df_epsilon = 0.0001;
+
+fn median(r: f32, g: f32, b: f32) -> f32 {
+    return max(min(r, g), min(max(r, g), b));
+}
+
+@fragment
+fn fs_msdf_glyph(in: VertexOutput) -> @location(0) vec4<f32> {
+    let sample = textureSample(t_texture, s_sampler, in.tex_coords);
+
+    // The distance to the edge in texels.
+    let distance = (median(sample.r, sample.g, sample.b) - df_threshold) * df_multiplier;
+
+    // How many texels are covered by one fragment.
+    let texels = fwidth(in.tex_coords * texture_size.value);
+    let texels_per_fragment = max(0.5 * (texels.x + texels.y), df_epsilon);
+
+    let val = clamp(distance / texels_per_fragment + 0.5, 0.0, 1.0);
+
+    return vec4<f32>(color.rgb, val);
+}
@@ -9,10 +9,39 @@ pub struct View {
 impl View {
     /// Creates a texture and uploads the image's content to the GPU.
     pub fn from_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        size: (u32, u32),
+    ) -> Self {
+        Self::from_data_with_format(device, queue, data, size, wgpu::TextureFormat::R8Unorm, 1)
+    }
+
+    /// Creates a texture with four channels, like multi-channel SDFs, and uploads the image's
+    /// content to the GPU.
+    pub fn from_rgba_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        size: (u32, u32),
+    ) -> Self {
+        Self::from_data_with_format(
+            device,
+            queue,
+            data,
+            size,
+            wgpu::TextureFormat::Rgba8Unorm,
+            4,
+        )
+    }
+
+    fn from_data_with_format(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         data: &[u8],
         (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+        bytes_per_pixel: u32,
     ) -> Self {
         let size = wgpu::Extent3d {
             width,
@@ -26,7 +55,7 @@ impl View {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R8Unorm,
+            format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
@@ -41,7 +70,7 @@ impl View {
             data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(width),
+                bytes_per_row: Some(width * bytes_per_pixel),
                 rows_per_image: None,
             },
             size,
@@ -12,6 +12,10 @@ impl BindGroupLayoutBuilder {
         Self::new(wgpu::ShaderStages::FRAGMENT)
     }
 
+    pub fn vertex_fragment() -> Self {
+        Self::new(wgpu::ShaderStages::VERTEX_FRAGMENT)
+    }
+
     fn new(shader_stages: wgpu::ShaderStages) -> Self {
         Self {
             shader_stages,
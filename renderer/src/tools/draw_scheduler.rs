@@ -0,0 +1,71 @@
+/// The state a draw call binds before it is issued, ordered by the cost of changing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DrawState {
+    /// The pipeline, numbered by the renderer that issues the draw calls.
+    pub pipeline: usize,
+    /// The identity of the layer bind group.
+    pub layer: usize,
+    /// The texture page, for example the texture id of an atlas page.
+    pub page: u32,
+}
+
+impl DrawState {
+    pub fn new(pipeline: usize, layer: &wgpu::BindGroup, page: u32) -> Self {
+        Self {
+            pipeline,
+            layer: layer as *const wgpu::BindGroup as usize,
+            page,
+        }
+    }
+}
+
+/// A draw call in the order the scheduler chose, together with the state that needs to be bound
+/// before it.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduledDraw {
+    /// The index the draw call was pushed with.
+    pub index: usize,
+    pub state: DrawState,
+    pub pipeline_changed: bool,
+    pub layer_changed: bool,
+    pub page_changed: bool,
+}
+
+/// Orders draw calls so that the ones that bind the same state follow each other.
+///
+/// Without a depth buffer, everything is alpha blended in the order it is drawn. Draw calls are
+/// therefore only reordered inside the same blend order: All draw calls with a lower blend order
+/// are issued before the ones with a higher order, see [`crate::RenderContext::blend_order`].
+/// Inside a blend order, the order of pushing is kept for draw calls that bind the same state.
+#[derive(Debug, Default)]
+pub struct DrawScheduler {
+    draws: Vec<(u32, DrawState, usize)>,
+}
+
+impl DrawScheduler {
+    pub fn push(&mut self, blend_order: u32, state: DrawState, index: usize) {
+        self.draws.push((blend_order, state, index));
+    }
+
+    /// Sort the pushed draw calls and return them with the state changes they need.
+    pub fn schedule(mut self) -> impl Iterator<Item = ScheduledDraw> {
+        self.draws
+            .sort_by_key(|(blend_order, state, _)| (*blend_order, *state));
+
+        let mut previous: Option<DrawState> = None;
+        self.draws.into_iter().map(move |(_, state, index)| {
+            let pipeline_changed = previous.map_or(true, |p| p.pipeline != state.pipeline);
+            let draw = ScheduledDraw {
+                index,
+                state,
+                pipeline_changed,
+                // Rebind everything after a pipeline change, the layouts may not be compatible.
+                layer_changed: pipeline_changed
+                    || previous.map_or(true, |p| p.layer != state.layer),
+                page_changed: pipeline_changed || previous.map_or(true, |p| p.page != state.page),
+            };
+            previous = Some(state);
+            draw
+        })
+    }
+}
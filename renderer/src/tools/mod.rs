@@ -1,11 +1,17 @@
 mod bind_group_layout_builder;
+mod draw_scheduler;
 mod pipeline;
 mod quad_index_buffer;
+mod shader_module;
 pub mod texture_sampler;
+mod vertex_ring;
 
 pub use bind_group_layout_builder::*;
+pub use draw_scheduler::*;
 pub use pipeline::*;
 pub use quad_index_buffer::*;
+pub use shader_module::*;
+pub use vertex_ring::*;
 
 use wgpu::BindingResource;
 
@@ -4,17 +4,40 @@ use log::debug;
 use wgpu::util::DeviceExt;
 
 #[derive(Debug, derive_more::Deref)]
-pub struct QuadIndexBuffer(wgpu::Buffer);
+pub struct QuadIndexBuffer {
+    #[deref]
+    buffer: wgpu::Buffer,
+    /// The indices of one quad.
+    pattern: &'static [u16],
+}
 
 impl QuadIndexBuffer {
+    /// An index buffer for drawing quads as triangle lists.
     pub fn new(device: &wgpu::Device) -> Self {
+        Self::with_pattern(device, Self::QUAD_INDICES)
+    }
+
+    /// An index buffer for drawing the outlines of quads as line lists.
+    pub fn outlines(device: &wgpu::Device) -> Self {
+        Self::with_pattern(device, Self::OUTLINE_INDICES)
+    }
+
+    fn with_pattern(device: &wgpu::Device, pattern: &'static [u16]) -> Self {
         // OO: Provide a good initial size.
         const NO_INDICES: [u16; 0] = [];
-        Self(Self::create_buffer(device, &NO_INDICES))
+        Self {
+            buffer: Self::create_buffer(device, &NO_INDICES),
+            pattern,
+        }
     }
 
     pub fn quads(&self) -> usize {
-        (self.0.size() as usize) / size_of_val(Self::QUAD_INDICES)
+        (self.buffer.size() as usize) / size_of_val(self.pattern)
+    }
+
+    /// The number of indices per quad.
+    pub fn indices_per_quad(&self) -> usize {
+        self.pattern.len()
     }
 
     pub fn ensure_can_index_num_quads(
@@ -39,18 +62,14 @@ impl QuadIndexBuffer {
         debug!("Growing index buffer from {current} to {proposed_quad_capacity} quads, required: {required_quad_count}");
 
         let indices = Self::generate_array(self, proposed_quad_capacity);
-        self.0 = Self::create_buffer(device, &indices);
+        self.buffer = Self::create_buffer(device, &indices);
     }
 
     fn generate_array(&self, quads: usize) -> Vec<u16> {
-        let mut v = Vec::with_capacity(Self::QUAD_INDICES.len() * quads);
+        let mut v = Vec::with_capacity(self.pattern.len() * quads);
 
         (0..quads).for_each(|quad_index| {
-            v.extend(
-                Self::QUAD_INDICES
-                    .iter()
-                    .map(|i| *i + (quad_index << 2) as u16),
-            )
+            v.extend(self.pattern.iter().map(|i| *i + (quad_index << 2) as u16))
         });
 
         v
@@ -58,6 +77,7 @@ impl QuadIndexBuffer {
 
     pub const QUAD_INDICES: &'static [u16] = &[0, 1, 2, 0, 2, 3];
     pub const INDICES_PER_QUAD: usize = Self::QUAD_INDICES.len();
+    pub const OUTLINE_INDICES: &'static [u16] = &[0, 1, 1, 2, 2, 3, 3, 0];
 
     fn create_buffer(device: &wgpu::Device, indices: &[u16]) -> wgpu::Buffer {
         device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -0,0 +1,79 @@
+use std::borrow::Cow;
+
+/// The features a device needs to use the shaders that were compiled to SPIR-V when the renderer
+/// was built.
+///
+/// Empty if the renderer was built without the `precompiled-shaders` feature. Request the
+/// features the adapter supports, the renderer falls back to WGSL without them.
+pub fn precompiled_shader_features() -> wgpu::Features {
+    if cfg!(feature = "precompiled-shaders") {
+        wgpu::Features::SPIRV_SHADER_PASSTHROUGH
+    } else {
+        wgpu::Features::empty()
+    }
+}
+
+/// Create a shader module from the SPIR-V that was compiled at build time if the device supports
+/// passing it through, and from WGSL otherwise.
+///
+/// All shaders are validated at build time, see `build.rs`. Precompiled modules skip parsing,
+/// validation, and translation when the renderer is created. Use [`crate::shader_module`]
+/// instead of calling this directly.
+pub fn create_shader_module(
+    device: &wgpu::Device,
+    label: &str,
+    wgsl: &'static str,
+    spirv: Option<&'static [u8]>,
+) -> wgpu::ShaderModule {
+    if let Some(spirv) = spirv {
+        if device
+            .features()
+            .contains(wgpu::Features::SPIRV_SHADER_PASSTHROUGH)
+        {
+            // Safety: The module was generated and validated by naga at build time.
+            return unsafe {
+                device.create_shader_module_spirv(&wgpu::ShaderModuleDescriptorSpirV {
+                    label: Some(label),
+                    source: wgpu::util::make_spirv_raw(spirv),
+                })
+            };
+        }
+    }
+
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(wgsl)),
+    })
+}
+
+/// Create a shader module from a WGSL file, given relative to the `src` directory of the
+/// renderer.
+#[macro_export]
+macro_rules! shader_module {
+    ($device:expr, $path:literal) => {
+        $crate::tools::create_shader_module(
+            $device,
+            $path,
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/", $path)),
+            $crate::precompiled_spirv!($path),
+        )
+    };
+}
+
+#[cfg(feature = "precompiled-shaders")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! precompiled_spirv {
+    ($path:literal) => {
+        Some(include_bytes!(concat!(env!("OUT_DIR"), "/shaders/", $path, ".spv")).as_slice())
+    };
+}
+
+#[cfg(not(feature = "precompiled-shaders"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! precompiled_spirv {
+    ($path:literal) => {
+        None
+    };
+}
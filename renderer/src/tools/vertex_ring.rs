@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use log::debug;
+
+/// A persistent vertex buffer that batches are allocated from.
+///
+/// Vertices are written with `write_buffer` at the head of the ring. All allocations are released
+/// at once with [`Self::clear`], and the next allocations continue behind the released ones, so
+/// that the regions the GPU may still read from the previous preparation are not the first ones
+/// overwritten.
+///
+/// If the ring is full, a larger buffer replaces it. The replaced buffers are kept until the
+/// next [`Self::clear`], because prepared batches still refer to them.
+pub struct VertexRing {
+    label: &'static str,
+    /// The current buffer is the last one.
+    buffers: Vec<wgpu::Buffer>,
+    /// The offset at which the allocations since the last clear start.
+    tail: u64,
+    /// The offset of the next allocation.
+    head: u64,
+    /// The allocations reached the end of the buffer and continued at its start.
+    wrapped: bool,
+}
+
+/// The location of an allocation in a [`VertexRing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingRange {
+    buffer: usize,
+    range: Range<u64>,
+}
+
+impl VertexRing {
+    const INITIAL_SIZE: u64 = 64 * 1024;
+
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            buffers: Vec::new(),
+            tail: 0,
+            head: 0,
+            wrapped: false,
+        }
+    }
+
+    /// Release all allocations.
+    pub fn clear(&mut self) {
+        if self.buffers.len() > 1 {
+            self.buffers.drain(..self.buffers.len() - 1);
+        }
+        self.tail = self.head;
+        self.wrapped = false;
+    }
+
+    /// Release all allocations and the buffers, the next allocation starts with a small buffer
+    /// again.
+    pub fn release(&mut self) {
+        self.buffers.clear();
+        self.tail = 0;
+        self.head = 0;
+        self.wrapped = false;
+    }
+
+    /// Write `contents` to the ring and return where they are placed.
+    pub fn allocate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        contents: &[u8],
+    ) -> RingRange {
+        let size = (contents.len() as u64).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        let offset = match self.free_offset(size) {
+            Some(offset) => offset,
+            None => {
+                self.grow(device, size);
+                0
+            }
+        };
+        self.head = offset + size;
+        queue.write_buffer(self.current(), offset, contents);
+        RingRange {
+            buffer: self.buffers.len() - 1,
+            range: offset..offset + contents.len() as u64,
+        }
+    }
+
+    pub fn slice(&self, range: &RingRange) -> wgpu::BufferSlice<'_> {
+        self.buffers[range.buffer].slice(range.range.clone())
+    }
+
+    /// The offset at which `size` bytes fit into the current buffer without overwriting other
+    /// allocations.
+    fn free_offset(&mut self, size: u64) -> Option<u64> {
+        let capacity = self.buffers.last()?.size();
+        if self.wrapped {
+            return (self.head + size <= self.tail).then_some(self.head);
+        }
+        if self.head + size <= capacity {
+            return Some(self.head);
+        }
+        // Nothing is allocated, so the ring can start over.
+        if self.head == self.tail && size <= capacity {
+            self.tail = 0;
+            return Some(0);
+        }
+        if size <= self.tail {
+            self.wrapped = true;
+            return Some(0);
+        }
+        None
+    }
+
+    fn grow(&mut self, device: &wgpu::Device, required: u64) {
+        let current = self.buffers.last().map_or(0, |buffer| buffer.size());
+        let mut size = (current * 2).max(Self::INITIAL_SIZE);
+        while size < required {
+            size *= 2;
+        }
+        debug!(
+            "Growing {} from {current} to {size} bytes, required: {required}",
+            self.label
+        );
+        self.buffers
+            .push(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(self.label),
+                size,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        self.tail = 0;
+        self.head = 0;
+        self.wrapped = false;
+    }
+
+    fn current(&self) -> &wgpu::Buffer {
+        self.buffers.last().expect("Internal error: No ring buffer")
+    }
+}
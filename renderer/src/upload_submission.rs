@@ -0,0 +1,30 @@
+//! Submission of the uploads of the preparation.
+//!
+//! wgpu exposes a single queue per device, so glyph uploads can't run on a dedicated transfer
+//! queue. But the writes the preparation stages on the queue can be submitted on their own,
+//! instead of with the next frame's commands.
+
+/// How the uploads of a preparation step, like newly rasterized glyphs, are submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UploadSubmission {
+    /// The uploads are submitted with the commands of the next frame.
+    #[default]
+    WithFrame,
+    /// The uploads are submitted as soon as a preparation step staged them, so that the GPU
+    /// copies them while the next glyphs are rasterized or the frame is recorded, and the
+    /// submission of the frame does not wait for them.
+    Separate,
+}
+
+impl UploadSubmission {
+    /// The submission that works best on `backend`.
+    ///
+    /// On GL, submissions are executed synchronously on the context, so additional submissions
+    /// only add overhead.
+    pub fn for_backend(backend: wgpu::Backend) -> Self {
+        match backend {
+            wgpu::Backend::Gl => Self::WithFrame,
+            _ => Self::Separate,
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::{any::TypeId, mem};
 
 use massive_geometry as geometry;
 
-use crate::{Id, Object, PositionRenderObj, PositionedRenderShape, PositionedShape};
+use crate::{Id, Object, Position, PositionRenderObj, PositionedRenderShape, PositionedShape};
 
 #[derive(Debug)]
 pub enum Change<T> {
@@ -11,11 +11,22 @@ pub enum Change<T> {
     Update(Id, T),
 }
 
+impl<T> Change<T> {
+    /// The id of the object that is changed.
+    pub fn id(&self) -> Id {
+        match self {
+            Change::Create(id, _) | Change::Delete(id) | Change::Update(id, _) => *id,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SceneChange {
     Matrix(Change<geometry::Matrix4>),
     Position(Change<PositionRenderObj>),
     PositionedShape(Change<PositionedRenderShape>),
+    /// Show or hide an existing positioned shape without removing it from the scene.
+    ShapeVisibility(Id, bool),
 }
 
 impl SceneChange {
@@ -24,6 +35,7 @@ impl SceneChange {
             SceneChange::Matrix(Change::Delete(id)) => {
                 Some((TypeId::of::<geometry::Matrix4>(), *id))
             }
+            SceneChange::Position(Change::Delete(id)) => Some((TypeId::of::<Position>(), *id)),
             SceneChange::PositionedShape(Change::Delete(id)) => {
                 Some((TypeId::of::<PositionedShape>(), *id))
             }
@@ -48,6 +60,10 @@ impl ChangeTracker {
         self.push::<T>(Change::Delete(id))
     }
 
+    pub fn set_shape_visibility(&mut self, id: Id, visible: bool) {
+        self.0.push(SceneChange::ShapeVisibility(id, visible))
+    }
+
     fn push<T: Object>(&mut self, change: Change<T::Change>) {
         self.0.push(T::promote_change(change));
     }
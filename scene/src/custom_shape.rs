@@ -0,0 +1,82 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use massive_geometry::Bounds3;
+
+/// A shape type that is not built into the renderer.
+///
+/// Custom shapes are stored in [`crate::Shape::Custom`] and identified by their type id. To render
+/// them, a matching shape extension must be registered in the renderer. Scene changes are sent to
+/// the renderer through channels, so custom shapes must be `Send` and `Sync`.
+pub trait CustomShape: Any + fmt::Debug + Send + Sync {
+    /// The bounds in the coordinate system of the shape's position, `None` if the shape is empty.
+    fn bounds(&self) -> Option<Bounds3>;
+
+    /// The cost of uploading the shape, comparable to the number of glyphs or quads.
+    fn upload_cost(&self) -> usize {
+        1
+    }
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl dyn CustomShape {
+    /// The type id of the concrete shape.
+    pub fn shape_type(&self) -> TypeId {
+        self.as_any().type_id()
+    }
+
+    pub fn downcast_ref<T: CustomShape>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
+/// Serializes custom shapes of one type.
+pub trait CustomShapeCodec: Send + Sync {
+    /// The name that identifies the shape type in serialized scenes.
+    fn name(&self) -> &'static str;
+    fn shape_type(&self) -> TypeId;
+    fn serialize(&self, shape: &dyn CustomShape) -> Result<Vec<u8>>;
+    fn deserialize(&self, data: &[u8]) -> Result<Box<dyn CustomShape>>;
+}
+
+/// The codecs of all custom shape types that can be serialized.
+#[derive(Default, Clone)]
+pub struct CustomShapeCodecs {
+    codecs: Vec<Arc<dyn CustomShapeCodec>>,
+}
+
+impl fmt::Debug for CustomShapeCodecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.codecs.iter().map(|codec| codec.name()))
+            .finish()
+    }
+}
+
+impl CustomShapeCodecs {
+    /// Register a codec. Replaces a codec registered for the same shape type.
+    pub fn register(&mut self, codec: Arc<dyn CustomShapeCodec>) {
+        self.codecs
+            .retain(|registered| registered.shape_type() != codec.shape_type());
+        self.codecs.push(codec);
+    }
+
+    pub fn by_type(&self, shape_type: TypeId) -> Option<&dyn CustomShapeCodec> {
+        self.codecs
+            .iter()
+            .find(|codec| codec.shape_type() == shape_type)
+            .map(|codec| codec.as_ref())
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&dyn CustomShapeCodec> {
+        self.codecs
+            .iter()
+            .find(|codec| codec.name() == name)
+            .map(|codec| codec.as_ref())
+    }
+}
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, fmt, rc::Rc};
 
-use crate::{Change, ChangeTracker, Id, SceneChange};
+use crate::{Change, ChangeTracker, Id, PositionedShape, SceneChange};
 
 pub trait Object: Sized {
     /// The stuff from Self that needs to be stored locally to keep the referential integrity. These
@@ -17,11 +17,20 @@ pub trait Object: Sized {
     fn split(self) -> (Self::Keep, Self::Change);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Handle<T: Object> {
     inner: Rc<InnerHandle<T>>,
 }
 
+/// Cloning a handle refers to the same object, so `T` does not need to be `Clone`.
+impl<T: Object> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 impl<T: Object> Handle<T> {
     pub(crate) fn new(id: Id, value: T, change_tracker: Rc<RefCell<ChangeTracker>>) -> Self {
         let (pinned, uploaded) = T::split(value);
@@ -47,6 +56,19 @@ impl<T: Object> Handle<T> {
     }
 }
 
+impl Handle<PositionedShape> {
+    /// Show or hide the shape.
+    ///
+    /// Hidden shapes stay in the renderer, so showing them again does not require uploading them
+    /// again.
+    pub fn set_visible(&self, visible: bool) {
+        self.inner
+            .change_tracker
+            .borrow_mut()
+            .set_shape_visibility(self.inner.id, visible)
+    }
+}
+
 /// Internal representation of the object handle.
 #[derive(Debug)]
 struct InnerHandle<T: Object> {
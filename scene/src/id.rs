@@ -2,9 +2,19 @@ use derive_more::Deref;
 
 /// An identifier that can be used to index into rows to allow fast id associative storage and
 /// retrieval of objects.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deref)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deref)]
 pub struct Id(usize);
 
+impl Id {
+    /// Reconstruct an id from its raw value.
+    ///
+    /// Ids are generated by the director, use this only to restore ids of changes that were
+    /// transferred, for example from another process.
+    pub fn from_raw(raw: usize) -> Self {
+        Self(raw)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct IdGen {
     next_id: usize,
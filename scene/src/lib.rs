@@ -31,14 +31,20 @@ use tokio::sync::mpsc;
 use id::*;
 
 mod change_tracker;
+mod custom_shape;
 mod handle;
 mod id;
 mod objects;
+mod pin;
+mod shape_group;
 
 pub use change_tracker::*;
+pub use custom_shape::*;
 pub use handle::*;
 pub use id::Id;
 pub use objects::*;
+pub use pin::*;
+pub use shape_group::*;
 
 /// A director is the only direct connection to the renderer. It tracks all the changes to scene
 /// graph and uploads it on demand.
@@ -1,13 +1,65 @@
 use derive_more::From;
 
-use crate::{Change, Handle, Id, Object, SceneChange};
+use crate::{Change, CustomShape, Handle, Id, Object, Pin, SceneChange};
 use massive_geometry as geometry;
-use massive_shapes::{GlyphRun, Quads};
+use massive_shapes::{Backdrop, Border, Caret, FocusRing, GlyphRun, Quads, RoundedRect, Shadow};
 
 #[derive(Debug, From)]
 pub enum Shape {
     GlyphRun(GlyphRun),
     Quads(Quads),
+    /// Shadows are rendered before all other shapes of their position, see [`Shadow`].
+    Shadow(Shadow),
+    /// Backdrops blur the world behind them, see [`Backdrop`].
+    Backdrop(Backdrop),
+    Border(Border),
+    /// Rounded rectangles are rendered behind the quads and the text of their position, see
+    /// [`RoundedRect`].
+    RoundedRect(RoundedRect),
+    /// Carets are animated by the renderer, see [`Caret`].
+    Caret(Caret),
+    /// Focus rings are computed and animated by the renderer, see [`FocusRing`].
+    FocusRing(FocusRing),
+    Custom(Box<dyn CustomShape>),
+}
+
+impl Shape {
+    pub fn custom(shape: impl CustomShape) -> Self {
+        Self::Custom(Box::new(shape))
+    }
+
+    /// The bounds of the shape in the coordinate system of its position.
+    ///
+    /// `None` if the shape is empty.
+    pub fn bounds(&self) -> Option<geometry::Bounds3> {
+        match self {
+            Shape::GlyphRun(run) => Some(run.bounds()),
+            Shape::Quads(quads) => massive_shapes::quads_bounds(quads),
+            Shape::Shadow(shadow) => {
+                let bounds = shadow.shadow_bounds();
+                Some(geometry::Bounds3::new(
+                    bounds.min.with_z(0.0),
+                    bounds.max.with_z(0.0),
+                ))
+            }
+            Shape::Backdrop(Backdrop { bounds, .. })
+            | Shape::Border(Border { bounds, .. })
+            | Shape::RoundedRect(RoundedRect { bounds, .. }) => Some(geometry::Bounds3::new(
+                bounds.min.with_z(0.0),
+                bounds.max.with_z(0.0),
+            )),
+            Shape::Caret(caret) => {
+                let bounds = caret.bounds;
+                Some(geometry::Bounds3::new(
+                    bounds.min.with_z(0.0),
+                    bounds.max.with_z(0.0),
+                ))
+            }
+            // The ring surrounds the other shapes and does not contribute to the bounds.
+            Shape::FocusRing(_) => None,
+            Shape::Custom(shape) => shape.bounds(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -55,6 +107,14 @@ impl PositionedShape {
 pub struct Position {
     pub parent: Option<Handle<Position>>,
     pub matrix: Handle<Matrix>,
+    /// Counteracts the translation of the parent on one axis, see [`Pin`].
+    pub pin: Option<Pin>,
+    /// Place the position and all its descendants in the screen-space overlay.
+    ///
+    /// Overlay positions are not seen through the camera. Their coordinates are pixels of the view,
+    /// with the origin at its top left corner, and they are rendered on top of the world. This is
+    /// meant for HUDs, tooltips, and context menus.
+    pub overlay: bool,
 }
 
 impl From<Handle<Matrix>> for Position {
@@ -62,6 +122,8 @@ impl From<Handle<Matrix>> for Position {
         Position {
             parent: None,
             matrix,
+            pin: None,
+            overlay: false,
         }
     }
 }
@@ -77,7 +139,16 @@ impl Object for Position {
     fn split(self) -> (Self::Keep, Self::Change) {
         let parent = self.parent.as_ref().map(|p| p.id());
         let matrix = self.matrix.id();
-        (self, PositionRenderObj { parent, matrix })
+        let (pin, overlay) = (self.pin, self.overlay);
+        (
+            self,
+            PositionRenderObj {
+                parent,
+                matrix,
+                pin,
+                overlay,
+            },
+        )
     }
 }
 
@@ -85,6 +156,8 @@ impl Object for Position {
 pub struct PositionRenderObj {
     pub parent: Option<Id>,
     pub matrix: Id,
+    pub pin: Option<Pin>,
+    pub overlay: bool,
 }
 
 pub type Matrix = geometry::Matrix4;
@@ -104,7 +177,7 @@ impl Object for Matrix {
 
 pub mod legacy {
     use super::Handle;
-    use crate::{Director, Position, PositionedShape, SceneChange};
+    use crate::{Director, Matrix, Position, PositionedShape, SceneChange};
     use anyhow::Result;
     use massive_geometry::Matrix4;
     use massive_shapes::{GlyphRunShape, QuadsShape, Shape};
@@ -131,7 +204,38 @@ pub mod legacy {
         director: &mut Director,
         shapes: Vec<Shape>,
     ) -> Vec<Handle<PositionedShape>> {
+        into_positioned_shapes_with_matrices(director, shapes).shapes
+    }
+
+    /// Positioned shapes and the matrix handles that were created for the shared model matrices.
+    #[derive(Debug)]
+    pub struct PositionedShapes {
+        pub shapes: Vec<Handle<PositionedShape>>,
+        /// One matrix handle for each distinct model matrix `Rc` found in the shapes.
+        pub matrices: Vec<(Rc<Matrix4>, Handle<Matrix>)>,
+    }
+
+    impl PositionedShapes {
+        /// Returns the matrix handle that was created for the model matrix `matrix`.
+        ///
+        /// Updating this handle repositions all shapes that shared this model matrix.
+        pub fn matrix(&self, matrix: &Rc<Matrix4>) -> Option<&Handle<Matrix>> {
+            self.matrices
+                .iter()
+                .find(|(m, _)| Rc::ptr_eq(m, matrix))
+                .map(|(_, handle)| handle)
+        }
+    }
+
+    /// Converts legacy shapes to positioned shapes.
+    ///
+    /// Shapes sharing the same model matrix `Rc` share one matrix and one position.
+    pub fn into_positioned_shapes_with_matrices(
+        director: &mut Director,
+        shapes: Vec<Shape>,
+    ) -> PositionedShapes {
         let mut position_handles: HashMap<*const Matrix4, Handle<Position>> = HashMap::new();
+        let mut matrices = Vec::new();
         let mut positioned_shapes = Vec::with_capacity(shapes.len());
 
         for shape in shapes {
@@ -142,8 +246,9 @@ pub mod legacy {
 
             let position = position_handles.entry(Rc::as_ptr(matrix)).or_insert_with(
                 || -> Handle<Position> {
-                    let matrix = director.cast(**matrix);
-                    director.cast(matrix.into())
+                    let matrix_handle = director.cast(**matrix);
+                    matrices.push((matrix.clone(), matrix_handle.clone()));
+                    director.cast(matrix_handle.into())
                 },
             );
 
@@ -159,6 +264,9 @@ pub mod legacy {
             positioned_shapes.push(director.cast(positioned));
         }
 
-        positioned_shapes
+        PositionedShapes {
+            shapes: positioned_shapes,
+            matrices,
+        }
     }
 }
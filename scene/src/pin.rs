@@ -0,0 +1,58 @@
+use massive_geometry::{Matrix4, Vector3};
+
+/// Pins a position on one axis, so that it does not follow the translation of its parent, for
+/// example the scroll offset of a scrolled world.
+///
+/// The parent's parent acts as the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pin {
+    /// Ignore the translation of the parent on the axis, like a fixed toolbar.
+    Fixed(Axis),
+    /// Scroll with the parent until the position reaches `at` in the viewport, then stay there
+    /// for at most `travel`, like a section header.
+    Sticky { axis: Axis, at: f64, travel: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+impl Pin {
+    pub fn axis(&self) -> Axis {
+        match self {
+            Pin::Fixed(axis) => *axis,
+            Pin::Sticky { axis, .. } => *axis,
+        }
+    }
+
+    /// The translation that is applied in the coordinates of the parent.
+    ///
+    /// `parent` is the parent's own matrix, relative to the viewport, and `local` the matrix of
+    /// the position.
+    pub fn offset(&self, parent: &Matrix4, local: &Matrix4) -> Matrix4 {
+        let axis = self.axis();
+        let offset = match *self {
+            Pin::Fixed(_) => -translation(parent, axis),
+            Pin::Sticky { at, travel, .. } => {
+                let current = translation(&(parent * local), axis);
+                (at - current).clamp(0.0, travel.max(0.0))
+            }
+        };
+        let unit = match axis {
+            Axis::X => Vector3::new(1.0, 0.0, 0.0),
+            Axis::Y => Vector3::new(0.0, 1.0, 0.0),
+        };
+        Matrix4::from_translation(unit * offset)
+    }
+}
+
+/// Where `matrix` moves the origin to on `axis`.
+fn translation(matrix: &Matrix4, axis: Axis) -> f64 {
+    let origin = matrix.w / matrix.w.w;
+    match axis {
+        Axis::X => origin.x,
+        Axis::Y => origin.y,
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{Director, Handle, PositionedShape};
+
+/// A group of shapes whose content can be replaced atomically.
+///
+/// New content is staged hidden, possibly over several calls to [`Director::action`], and then
+/// swapped with the current content. The swap is a single set of changes, so the renderer never
+/// shows a partially built group: It either renders the old or the new content.
+#[derive(Debug, Default)]
+pub struct ShapeGroup {
+    current: Vec<Handle<PositionedShape>>,
+    staged: Vec<Handle<PositionedShape>>,
+}
+
+impl ShapeGroup {
+    pub fn new(shapes: Vec<Handle<PositionedShape>>) -> Self {
+        Self {
+            current: shapes,
+            staged: Vec::new(),
+        }
+    }
+
+    /// The shapes that are currently shown.
+    pub fn shapes(&self) -> &[Handle<PositionedShape>] {
+        &self.current
+    }
+
+    /// The shapes that are staged and are shown after the next [`Self::swap`].
+    pub fn staged(&self) -> &[Handle<PositionedShape>] {
+        &self.staged
+    }
+
+    /// Add a shape to the staged content.
+    ///
+    /// The shape is created hidden. Both changes are recorded before the director can send them,
+    /// so the shape never becomes visible before the swap.
+    pub fn stage(
+        &mut self,
+        director: &mut Director,
+        shape: PositionedShape,
+    ) -> Handle<PositionedShape> {
+        let handle = director.cast(shape);
+        handle.set_visible(false);
+        self.staged.push(handle.clone());
+        handle
+    }
+
+    /// Add a number of shapes to the staged content.
+    pub fn stage_all(
+        &mut self,
+        director: &mut Director,
+        shapes: impl IntoIterator<Item = PositionedShape>,
+    ) {
+        for shape in shapes {
+            self.stage(director, shape);
+        }
+    }
+
+    /// Drop all staged shapes.
+    pub fn discard_staged(&mut self) {
+        self.staged.clear();
+    }
+
+    /// Show the staged shapes and drop the current ones.
+    ///
+    /// The changes are sent to the renderer with the next [`Director::action`]. Shapes that are
+    /// still referenced by other handles are hidden instead of removed.
+    pub fn swap(&mut self) {
+        for shape in &self.staged {
+            shape.set_visible(true);
+        }
+        for shape in &self.current {
+            shape.set_visible(false);
+        }
+        self.current = std::mem::take(&mut self.staged);
+    }
+}
@@ -1,3 +1,12 @@
+//! The shapes of a scene.
+//!
+//! Without the default `std` feature, this is a `no_std` crate that contains the 2D shapes and
+//! their styles, like rounded rectangles, shadows, borders, carets, patterns, and reveals, so that
+//! they can be created and serialized on targets without `std`. Glyph runs and quads, and with them
+//! `Shape`, need `cgmath` for their matrices and vectors and `cosmic-text` for the glyph keys,
+//! which both require `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
 mod shapes;
 
 pub use shapes::*;
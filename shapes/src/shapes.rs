@@ -1,19 +1,46 @@
+#[cfg(feature = "std")]
 use std::rc::Rc;
 
-use cgmath::Point2;
+#[cfg(feature = "std")]
+use cgmath::{EuclideanSpace, InnerSpace, Point2};
+#[cfg(feature = "std")]
 use cosmic_text as text;
-use massive_geometry::{Color, Vector3};
+use massive_geometry::{Color, Vector};
+#[cfg(feature = "std")]
+use massive_geometry::{Point3, Vector3};
 use serde::{Deserialize, Serialize};
 
-use crate::geometry::{Bounds, Matrix4};
+use crate::geometry::Bounds;
+#[cfg(feature = "std")]
+use crate::geometry::{Bounds3, Matrix4};
 
+#[cfg(feature = "std")]
 #[derive(Debug, derive_more::From)]
 pub enum Shape {
     GlyphRun(GlyphRunShape),
     Quads(QuadsShape),
 }
 
+#[cfg(feature = "std")]
+impl Shape {
+    /// The bounds of the shape with its model matrix applied.
+    ///
+    /// `None` if the shape is empty.
+    pub fn bounds(&self) -> Option<Bounds3> {
+        match self {
+            Shape::GlyphRun(GlyphRunShape { model_matrix, run }) => {
+                Some(run.bounds().transformed(model_matrix))
+            }
+            Shape::Quads(QuadsShape {
+                model_matrix,
+                quads,
+            }) => quads_bounds(quads).map(|b| b.transformed(model_matrix)),
+        }
+    }
+}
+
 /// A number of glyphs to be rendered with same model matrix and an additional translation per run.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct GlyphRunShape {
     // Model transformation
@@ -21,6 +48,7 @@ pub struct GlyphRunShape {
     pub run: GlyphRun,
 }
 
+#[cfg(feature = "std")]
 #[derive(Debug, Clone)]
 pub struct GlyphRun {
     // Local translation This is separated from the view transformation because full matrix changes
@@ -30,8 +58,17 @@ pub struct GlyphRun {
     pub text_color: Color,
     pub text_weight: TextWeight,
     pub glyphs: Vec<RunGlyph>,
+    /// Orient the run to face the camera. `None` renders it in the plane of its model matrix.
+    pub billboard: Option<Billboard>,
+    /// Render a pixel of the run as a physical pixel, independent of the distance to the camera.
+    ///
+    /// The run is scaled around the origin of its model matrix.
+    pub constant_screen_size: bool,
+    /// Animate the glyphs in when they are rendered. `None` shows them right away.
+    pub reveal: Option<Reveal>,
 }
 
+#[cfg(feature = "std")]
 impl GlyphRun {
     pub fn new(
         translation: impl Into<Vector3>,
@@ -46,9 +83,71 @@ impl GlyphRun {
             text_color,
             text_weight,
             glyphs,
+            billboard: None,
+            constant_screen_size: false,
+            reveal: None,
         }
     }
 
+    pub fn with_billboard(mut self, billboard: Billboard) -> Self {
+        self.billboard = Some(billboard);
+        self
+    }
+
+    pub fn with_constant_screen_size(mut self) -> Self {
+        self.constant_screen_size = true;
+        self
+    }
+
+    pub fn with_reveal(mut self, reveal: Reveal) -> Self {
+        self.reveal = Some(reveal);
+        self
+    }
+
+    /// The bounds of the run in the coordinate system of its model matrix.
+    ///
+    /// This is computed from the metrics and not from the rasterized glyphs, so glyphs that extend
+    /// over their advance or the maximum ascent / descent may not be covered.
+    pub fn bounds(&self) -> Bounds3 {
+        let (width, height) = self.metrics.size();
+        let min = Point3::from_vec(self.translation);
+        Bounds3::new(min, min + Vector3::new(width as f64, height as f64, 0.0))
+    }
+
+    /// Whether the glyphs of `other` can be appended to this run without changing how they are
+    /// rendered.
+    ///
+    /// Runs with reveal animations are never merged, because they are animated by glyph index.
+    pub fn can_merge(&self, other: &GlyphRun) -> bool {
+        self.translation == other.translation
+            && self.metrics.max_ascent == other.metrics.max_ascent
+            && self.metrics.max_descent == other.metrics.max_descent
+            && self.text_color == other.text_color
+            && self.text_weight == other.text_weight
+            && self.billboard == other.billboard
+            && self.constant_screen_size == other.constant_screen_size
+            && self.reveal.is_none()
+            && other.reveal.is_none()
+    }
+
+    /// Merge adjacent runs that can be merged, see [`Self::can_merge`].
+    ///
+    /// Applications that emit a run per word or per syntax token end up with fewer shapes, which
+    /// are faster to prepare and to send. The merged runs are as wide as the widest of them.
+    pub fn merge_adjacent(runs: impl IntoIterator<Item = GlyphRun>) -> Vec<GlyphRun> {
+        let mut merged: Vec<GlyphRun> = Vec::new();
+        for run in runs {
+            match merged.last_mut() {
+                Some(last) if last.can_merge(&run) => {
+                    last.metrics.width = last.metrics.width.max(run.metrics.width);
+                    last.glyphs.extend(run.glyphs);
+                }
+                _ => merged.push(run),
+            }
+        }
+        merged
+    }
+
     /// Translate a rasterized glyph's position to the coordinate system of the run.
     pub fn place_glyph(
         &self,
@@ -67,19 +166,558 @@ impl GlyphRun {
     }
 }
 
+/// How a billboarded [`GlyphRun`] faces the camera.
+///
+/// The run rotates around the origin of its model matrix, so it keeps its position in the world.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Billboard {
+    /// Rotate around the model's y axis only, for example for labels standing on the ground.
+    Cylindrical,
+    /// Always face the camera upright.
+    Spherical,
+}
+
+#[cfg(feature = "std")]
+impl Billboard {
+    /// Orient a model matrix so that its x / y plane faces the camera.
+    ///
+    /// The origin and the scale of the model matrix are kept. The camera's orientation is taken
+    /// from the view projection matrix. As with all text, y points down on the screen.
+    pub fn orient(self, model_matrix: &Matrix4, view_projection_matrix: &Matrix4) -> Matrix4 {
+        let m = model_matrix;
+        let vp = view_projection_matrix;
+        // The first two rows of the view projection matrix compute clip space x and y, so they
+        // point to the right and up of the camera.
+        let right = Vector3::new(vp.x.x, vp.y.x, vp.z.x);
+        let up = Vector3::new(vp.x.y, vp.y.y, vp.z.y);
+        let back = right.cross(up);
+        if back.magnitude2() == 0.0 {
+            return *m;
+        }
+        let back = back.normalize();
+
+        let (right, down, back) = match self {
+            Billboard::Spherical => {
+                let right = right.normalize();
+                (right, right.cross(back), back)
+            }
+            Billboard::Cylindrical => {
+                let down = m.y.truncate();
+                if down.magnitude2() == 0.0 {
+                    return *m;
+                }
+                let down = down.normalize();
+                // Facing the camera is ambiguous when looking along the axis.
+                let facing = back - down * back.dot(down);
+                if facing.magnitude2() < 1e-12 {
+                    return *m;
+                }
+                let back = facing.normalize();
+                (back.cross(down), down, back)
+            }
+        };
+
+        Matrix4::from_cols(
+            (right * m.x.truncate().magnitude()).extend(0.0),
+            (down * m.y.truncate().magnitude()).extend(0.0),
+            (back * m.z.truncate().magnitude()).extend(0.0),
+            m.w,
+        )
+    }
+}
+
+/// An animation that reveals the glyphs of a [`GlyphRun`] one after another.
+///
+/// The animation is evaluated in the shaders from the renderer's time, so it does not need any
+/// updates while it runs.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reveal {
+    pub effect: RevealEffect,
+    /// The renderer time in seconds at which the first glyph starts to appear.
+    pub start: f32,
+    /// The time in seconds each glyph takes to appear.
+    pub duration: f32,
+    /// The delay in seconds between the starts of two consecutive glyphs.
+    pub stagger: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RevealEffect {
+    /// Fade in.
+    Fade,
+    /// Fade in while moving up by `distance` font pixels.
+    SlideUp { distance: f32 },
+    /// Fade in while growing from the glyph's center.
+    ScaleIn,
+}
+
+impl Reveal {
+    pub fn new(effect: RevealEffect, start: f32) -> Self {
+        Self {
+            effect,
+            start,
+            duration: 0.4,
+            stagger: 0.03,
+        }
+    }
+
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_stagger(mut self, stagger: f32) -> Self {
+        self.stagger = stagger;
+        self
+    }
+
+    /// The time at which the glyph at `index` starts to appear.
+    pub fn glyph_start(&self, index: usize) -> f32 {
+        self.start + index as f32 * self.stagger
+    }
+
+    /// The time at which all of `glyphs` glyphs are fully visible.
+    pub fn end(&self, glyphs: usize) -> f32 {
+        self.glyph_start(glyphs.saturating_sub(1)) + self.duration
+    }
+}
+
+/// A text caret.
+///
+/// When a caret is updated, the renderer moves it smoothly from where it is to its new bounds, so
+/// editors send only the new position.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Caret {
+    pub bounds: Bounds,
+    pub color: Color,
+    /// The time in seconds the caret takes to move to new bounds. Zero jumps.
+    pub motion: f32,
+    /// Draw a glow that trails behind the moving caret.
+    pub trail: bool,
+}
+
+impl Caret {
+    pub fn new(bounds: Bounds, color: Color) -> Self {
+        Self {
+            bounds,
+            color,
+            motion: 0.1,
+            trail: false,
+        }
+    }
+
+    pub fn with_motion(mut self, motion: f32) -> Self {
+        self.motion = motion;
+        self
+    }
+
+    pub fn with_trail(mut self) -> Self {
+        self.trail = true;
+        self
+    }
+}
+
+/// An outline around the other shapes at the same position, to show the keyboard focus.
+///
+/// The renderer computes the outline from the bounds of the shapes. When a focus ring is updated
+/// with another position, it moves smoothly from the shapes it surrounded to the new ones. New
+/// rings grow in.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusRing {
+    pub color: Color,
+    /// The distance between the bounds of the shapes and the inner edge of the outline.
+    pub inflate: f64,
+    pub width: f64,
+    /// The corner radius of the outer edge.
+    pub radius: f64,
+    /// The time in seconds the ring takes to move or to appear. Zero jumps.
+    pub motion: f32,
+}
+
+impl FocusRing {
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            inflate: 3.0,
+            width: 2.0,
+            radius: 6.0,
+            motion: 0.15,
+        }
+    }
+
+    pub fn with_inflate(mut self, inflate: f64) -> Self {
+        self.inflate = inflate;
+        self
+    }
+
+    pub fn with_width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_motion(mut self, motion: f32) -> Self {
+        self.motion = motion;
+        self
+    }
+}
+
+/// A soft shadow of a rounded rectangle, for example to show the elevation of a panel.
+///
+/// The renderer caches a blurred texture for each combination of radius, blur, and spread, so
+/// shadows of panels that share a style are cheap, regardless of their size.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shadow {
+    /// The bounds of the rectangle that casts the shadow.
+    pub bounds: Bounds,
+    /// The corner radius of the rectangle.
+    pub radius: f64,
+    /// The offset of the shadow from the rectangle.
+    pub offset: Vector,
+    /// The width of the edge over which the shadow fades out, centered on the edge of the spread
+    /// rectangle.
+    pub blur: f64,
+    /// The distance the shadow grows beyond the rectangle before it is blurred.
+    pub spread: f64,
+    pub color: Color,
+}
+
+impl Shadow {
+    pub fn new(bounds: Bounds, radius: f64, color: Color) -> Self {
+        Self {
+            bounds,
+            radius,
+            offset: Vector::default(),
+            blur: 8.0,
+            spread: 0.0,
+            color,
+        }
+    }
+
+    /// A material style shadow of a panel at `elevation`, which roughly corresponds to its
+    /// distance from the surface below in pixels.
+    ///
+    /// Elevations are rounded to whole pixels, so that panels at the same elevation share their
+    /// shadow textures.
+    pub fn elevation(bounds: Bounds, radius: f64, elevation: f64) -> Self {
+        let elevation = libm::round(elevation).max(0.0);
+        Self {
+            bounds,
+            radius,
+            offset: Vector::new(0.0, libm::round(elevation / 2.0)),
+            blur: elevation * 2.0,
+            spread: 0.0,
+            color: Color::new(0.0, 0.0, 0.0, (0.14 + elevation * 0.01).min(0.3) as f32),
+        }
+    }
+
+    pub fn with_offset(mut self, offset: impl Into<Vector>) -> Self {
+        self.offset = offset.into();
+        self
+    }
+
+    pub fn with_blur(mut self, blur: f64) -> Self {
+        self.blur = blur;
+        self
+    }
+
+    pub fn with_spread(mut self, spread: f64) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    /// The bounds of the visible shadow.
+    pub fn shadow_bounds(&self) -> Bounds {
+        let extent = self.spread + self.blur.max(0.0) / 2.0;
+        let offset = self.offset;
+        Bounds::new(
+            (
+                self.bounds.min.x + offset.x - extent,
+                self.bounds.min.y + offset.y - extent,
+            ),
+            (
+                self.bounds.max.x + offset.x + extent,
+                self.bounds.max.y + offset.y + extent,
+            ),
+        )
+    }
+}
+
+/// A translucent region that blurs the world behind it, like frosted glass.
+///
+/// Backdrops are drawn after the world and before the overlay: They blur everything of the world
+/// below them, and the overlay is drawn over them. This makes them the background of translucent
+/// panels in the overlay.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backdrop {
+    pub bounds: Bounds,
+    /// The corner radius of the region.
+    pub radius: f64,
+    /// The blur radius in pixels of the view. Rounded to whole pixels.
+    pub blur: f64,
+    /// The color mixed into the blurred content, by its alpha.
+    pub tint: Color,
+}
+
+impl Backdrop {
+    pub fn new(bounds: Bounds, blur: f64, tint: Color) -> Self {
+        Self {
+            bounds,
+            radius: 0.0,
+            blur,
+            tint,
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+}
+
+/// A solid or dashed outline along the inner edges of a rectangle.
+///
+/// With an animated [`Dash`], the dashes run around the rectangle, like the "marching ants" of a
+/// selection marquee.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Border {
+    pub bounds: Bounds,
+    pub width: f64,
+    pub color: Color,
+    /// `None` draws a solid border.
+    pub dash: Option<Dash>,
+}
+
+impl Border {
+    pub fn new(bounds: Bounds, width: f64, color: Color) -> Self {
+        Self {
+            bounds,
+            width,
+            color,
+            dash: None,
+        }
+    }
+
+    pub fn with_dash(mut self, dash: Dash) -> Self {
+        self.dash = Some(dash);
+        self
+    }
+}
+
+/// The dash pattern of a [`Border`].
+///
+/// The renderer adjusts the length of the dashes and the gaps slightly, so that the pattern
+/// closes seamlessly around the rectangle.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dash {
+    pub length: f64,
+    pub gap: f64,
+    /// The speed the dashes move clockwise around the rectangle, in units per second. Negative
+    /// speeds move counterclockwise, zero does not animate.
+    pub speed: f64,
+}
+
+impl Dash {
+    pub fn new(length: f64, gap: f64) -> Self {
+        Self {
+            length,
+            gap,
+            speed: 0.0,
+        }
+    }
+
+    /// Dashes that march around the rectangle at `speed` units per second.
+    pub fn marching(length: f64, gap: f64, speed: f64) -> Self {
+        Self { length, gap, speed }
+    }
+}
+
+/// A filled rectangle with rounded corners and an optional border, for example the background of
+/// a panel behind text.
+///
+/// The renderer computes the corners from their distance in the fragment shader, so they stay
+/// smooth at every zoom level and the rectangle is drawn as a single quad.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundedRect {
+    pub bounds: Bounds,
+    pub fill: Color,
+    /// Radii larger than half of the rectangle's smaller side are reduced to it.
+    pub radii: CornerRadii,
+    /// A solid border along the inner edges, which follows the rounded corners.
+    pub border: Option<RectBorder>,
+}
+
+impl RoundedRect {
+    pub fn new(bounds: Bounds, fill: Color) -> Self {
+        Self {
+            bounds,
+            fill,
+            radii: CornerRadii::default(),
+            border: None,
+        }
+    }
+
+    /// Round all corners with the same radius.
+    pub fn with_radius(mut self, radius: f64) -> Self {
+        self.radii = CornerRadii::uniform(radius);
+        self
+    }
+
+    pub fn with_radii(mut self, radii: CornerRadii) -> Self {
+        self.radii = radii;
+        self
+    }
+
+    pub fn with_border(mut self, width: f64, color: Color) -> Self {
+        self.border = Some(RectBorder { width, color });
+        self
+    }
+}
+
+/// The radii of the corners of a [`RoundedRect`], clockwise from the top left.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CornerRadii {
+    pub top_left: f64,
+    pub top_right: f64,
+    pub bottom_right: f64,
+    pub bottom_left: f64,
+}
+
+impl CornerRadii {
+    pub fn new(top_left: f64, top_right: f64, bottom_right: f64, bottom_left: f64) -> Self {
+        Self {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        }
+    }
+
+    pub fn uniform(radius: f64) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+
+    /// Round the top corners only, for example for tabs.
+    pub fn top(radius: f64) -> Self {
+        Self::new(radius, radius, 0.0, 0.0)
+    }
+
+    /// Round the bottom corners only, for example for drop-down menus.
+    pub fn bottom(radius: f64) -> Self {
+        Self::new(0.0, 0.0, radius, radius)
+    }
+}
+
+impl From<f64> for CornerRadii {
+    fn from(radius: f64) -> Self {
+        Self::uniform(radius)
+    }
+}
+
+/// The border of a [`RoundedRect`].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RectBorder {
+    pub width: f64,
+    pub color: Color,
+}
+
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct QuadsShape {
     pub model_matrix: Rc<Matrix4>,
     pub quads: Quads,
 }
 
+#[cfg(feature = "std")]
 pub type Quads = Vec<Quad>;
 
+/// The bounds of all quads, `None` if there are none.
+#[cfg(feature = "std")]
+pub fn quads_bounds(quads: &[Quad]) -> Option<Bounds3> {
+    Bounds3::from_points(quads.iter().flat_map(|q| q.vertices.map(Point3::from_vec)))
+}
+
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct Quad {
     /// A three vertices. Visible from both sides.
     pub vertices: [Vector3; 4],
     pub color: Color,
+    /// A pattern of the color and the pattern's color. `None` fills the quad with its color.
+    pub pattern: Option<Pattern>,
+}
+
+#[cfg(feature = "std")]
+impl Quad {
+    pub fn new(vertices: [Vector3; 4], color: Color) -> Self {
+        Self {
+            vertices,
+            color,
+            pattern: None,
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+}
+
+/// A procedural fill of a [`Quad`], for example for placeholders and diff backgrounds.
+///
+/// The pattern is aligned to the coordinate system of the quad's position, so it moves with the
+/// quad and does not depend on the quad's vertices.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    /// The color of the stripes, the odd cells, or the dots. The quad's color is the background.
+    pub color: Color,
+    /// The size of a cell of the pattern.
+    pub scale: f64,
+    /// The counterclockwise rotation in radians.
+    pub rotation: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatternKind {
+    /// Vertical stripes, half of each cell wide.
+    Stripes,
+    /// Squares half of each cell wide.
+    Checkerboard,
+    /// A dot in the center of each cell, half of the cell wide.
+    Dots,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, color: Color, scale: f64) -> Self {
+        Self {
+            kind,
+            color,
+            scale,
+            rotation: 0.0,
+        }
+    }
+
+    pub fn stripes(color: Color, scale: f64) -> Self {
+        Self::new(PatternKind::Stripes, color, scale)
+    }
+
+    pub fn checkerboard(color: Color, scale: f64) -> Self {
+        Self::new(PatternKind::Checkerboard, color, scale)
+    }
+
+    pub fn dots(color: Color, scale: f64) -> Self {
+        Self::new(PatternKind::Dots, color, scale)
+    }
+
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.rotation = rotation;
+        self
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -96,7 +734,7 @@ impl GlyphRunMetrics {
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TextWeight(pub u16);
 
 impl TextWeight {
@@ -112,6 +750,7 @@ impl TextWeight {
 }
 
 /// A glyph inside a [`GlyphRun`].
+#[cfg(feature = "std")]
 #[derive(Debug, Clone)]
 pub struct RunGlyph {
     // This is for rendering the image of the glyph.
@@ -120,6 +759,7 @@ pub struct RunGlyph {
     pub hitbox_width: f32,
 }
 
+#[cfg(feature = "std")]
 impl RunGlyph {
     pub fn new(key: text::CacheKey, hitbox_pos: (i32, i32), hitbox_width: f32) -> Self {
         Self {
@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+
+use massive_geometry::{scalar, Camera};
+use web_time::Instant;
+
+/// Interpolates the camera towards target states that should be reached at specific times.
+///
+/// This decouples the rate the application updates the camera from the display's refresh rate:
+/// The application submits targets whenever it wants to, and the camera is interpolated for each
+/// frame.
+#[derive(Debug)]
+pub struct CameraInterpolator {
+    camera: Camera,
+    /// The start of the current segment: its time and the camera at that time.
+    segment_start: Option<(Instant, Camera)>,
+    /// The targets and the times they should be reached, ordered by time.
+    targets: VecDeque<(Instant, Camera)>,
+}
+
+impl CameraInterpolator {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            segment_start: None,
+            targets: VecDeque::new(),
+        }
+    }
+
+    /// The most recently interpolated camera.
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// Set the camera immediately and drop all targets.
+    pub fn set(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.segment_start = None;
+        self.targets.clear();
+    }
+
+    /// Add a target camera that should be reached at `at`.
+    ///
+    /// Targets that were scheduled at or after `at` are dropped.
+    pub fn push_target(&mut self, now: Instant, camera: Camera, at: Instant) {
+        while self.targets.back().is_some_and(|(time, _)| *time >= at) {
+            self.targets.pop_back();
+        }
+
+        if self.targets.is_empty() {
+            self.segment_start = Some((now, self.camera));
+        }
+
+        self.targets.push_back((at, camera));
+    }
+
+    /// The camera the interpolation ends at, the current one if it is not animating.
+    pub fn final_camera(&self) -> Camera {
+        self.targets
+            .back()
+            .map_or(self.camera, |(_, camera)| *camera)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.targets.is_empty()
+    }
+
+    /// The camera at `at` if no other targets are pushed, without advancing the interpolation.
+    pub fn predict(&self, at: Instant) -> Camera {
+        let mut start = self.segment_start;
+        for &(time, target) in &self.targets {
+            if time > at {
+                return interpolate(start.unwrap_or((at, self.camera)), (time, target), at);
+            }
+            start = Some((time, target));
+        }
+        self.targets
+            .back()
+            .map_or(self.camera, |(_, camera)| *camera)
+    }
+
+    /// Interpolate the camera at `now`.
+    pub fn advance(&mut self, now: Instant) -> Camera {
+        while let Some(&(at, target)) = self.targets.front() {
+            if at > now {
+                let start = self.segment_start.unwrap_or((now, self.camera));
+                self.camera = interpolate(start, (at, target), now);
+                return self.camera;
+            }
+
+            // Target reached, continue with the next segment.
+            self.targets.pop_front();
+            self.camera = target;
+            self.segment_start = Some((at, target));
+        }
+
+        self.segment_start = None;
+        self.camera
+    }
+}
+
+/// Interpolate the camera between the start and the end of a segment at `now`.
+fn interpolate(
+    (start_time, start_camera): (Instant, Camera),
+    (end_time, end_camera): (Instant, Camera),
+    now: Instant,
+) -> Camera {
+    let duration = end_time.saturating_duration_since(start_time).as_secs_f64();
+    let elapsed = now.saturating_duration_since(start_time).as_secs_f64();
+    let t = if duration > 0.0 {
+        elapsed / duration
+    } else {
+        1.0
+    };
+    start_camera.interpolate(&end_camera, smoothstep(t.clamp(0.0, 1.0)))
+}
+
+fn smoothstep(t: scalar) -> scalar {
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+/// How text carets blink, see [`crate::WindowRenderer::set_caret_blink`].
+///
+/// The shell keeps the phase: Input restarts it with a visible caret, and while a caret is
+/// active, the window wakes up to redraw when the phase changes. Widgets query the phase with
+/// [`crate::WindowRenderer::caret_visible`] when they update their shapes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaretBlink {
+    /// How long the caret is visible and how long it is hidden.
+    pub interval: Duration,
+    /// Stop blinking and keep the caret visible when there was no input for this long, so that
+    /// idle windows don't wake up. `None` blinks forever.
+    pub stop_after: Option<Duration>,
+}
+
+impl Default for CaretBlink {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(530),
+            stop_after: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+impl CaretBlink {
+    /// Whether the caret is visible `elapsed` after the phase was restarted.
+    pub(crate) fn is_visible(&self, elapsed: Duration) -> bool {
+        if self.has_stopped(elapsed) || self.interval.is_zero() {
+            return true;
+        }
+        (elapsed.as_nanos() / self.interval.as_nanos()) % 2 == 0
+    }
+
+    /// The time after the restart of the phase at which the caret appears or disappears next,
+    /// `None` if it does not blink anymore.
+    pub(crate) fn next_change(&self, elapsed: Duration) -> Option<Duration> {
+        if self.has_stopped(elapsed) || self.interval.is_zero() {
+            return None;
+        }
+        let changes = elapsed.as_nanos() / self.interval.as_nanos() + 1;
+        let next = self.interval * changes as u32;
+        match self.stop_after {
+            // The last change shows the caret for good.
+            Some(stop_after) if next >= stop_after => Some(stop_after),
+            _ => Some(next),
+        }
+    }
+
+    fn has_stopped(&self, elapsed: Duration) -> bool {
+        self.stop_after
+            .is_some_and(|stop_after| elapsed >= stop_after)
+    }
+}
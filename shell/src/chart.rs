@@ -0,0 +1,444 @@
+//! Text helpers for charts: tick values, tick label formatting, and axis label runs.
+//!
+//! Plots are drawn with quads. The labels produced here are positioned in the plot's coordinate
+//! system, where the origin is the start of the axis.
+
+use cgmath::Deg;
+use cosmic_text as text;
+
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_shapes::{GlyphRun, TextWeight};
+
+use crate::widgets::LineLayout;
+
+/// Evenly spaced tick values at "nice" steps of 1, 2, or 5 times a power of ten.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ticks {
+    pub values: Vec<f64>,
+    pub step: f64,
+}
+
+impl Ticks {
+    /// Ticks covering `min..=max`, about `target_count` of them.
+    pub fn new(min: f64, max: f64, target_count: usize) -> Self {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        let range = max - min;
+        if !range.is_finite() || range == 0.0 || target_count == 0 {
+            return Self {
+                values: vec![min],
+                step: 0.0,
+            };
+        }
+
+        let step = nice_step(range / target_count as f64);
+        let first = (min / step).ceil() as i64;
+        let last = (max / step).floor() as i64;
+        Self {
+            // Multiplying avoids accumulating rounding errors.
+            values: (first..=last).map(|i| i as f64 * step).collect(),
+            step,
+        }
+    }
+
+    /// The formatted labels of the ticks.
+    pub fn labels(&self, format: TickFormat) -> Vec<String> {
+        self.values
+            .iter()
+            .map(|value| format.format(*value, self.step))
+            .collect()
+    }
+}
+
+/// The smallest step of 1, 2, or 5 times a power of ten that is at least `rough_step`.
+fn nice_step(rough_step: f64) -> f64 {
+    let magnitude = 10f64.powf(rough_step.log10().floor());
+    let fraction = rough_step / magnitude;
+    let nice = match fraction {
+        f if f <= 1.0 => 1.0,
+        f if f <= 2.0 => 2.0,
+        f if f <= 5.0 => 5.0,
+        _ => 10.0,
+    };
+    nice * magnitude
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TickFormat {
+    /// As many decimals as the step between ticks needs.
+    #[default]
+    Auto,
+    Decimals(usize),
+    /// Fractions as percentages, `0.25` is shown as `25%`.
+    Percent,
+    /// With SI prefixes, `1500` is shown as `1.5k`.
+    Si,
+}
+
+impl TickFormat {
+    pub fn format(self, value: f64, step: f64) -> String {
+        match self {
+            TickFormat::Auto => format_decimals(value, step_decimals(step)),
+            TickFormat::Decimals(decimals) => format_decimals(value, decimals),
+            TickFormat::Percent => {
+                format!(
+                    "{}%",
+                    format_decimals(value * 100.0, step_decimals(step * 100.0))
+                )
+            }
+            TickFormat::Si => {
+                const PREFIXES: [(f64, &str); 4] =
+                    [(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k")];
+                // The prefix is chosen by the step, so that all labels of an axis share it.
+                match PREFIXES.iter().find(|(factor, _)| step.abs() >= *factor) {
+                    Some((factor, prefix)) => format!(
+                        "{}{prefix}",
+                        format_decimals(value / factor, step_decimals(step / factor))
+                    ),
+                    None => format_decimals(value, step_decimals(step)),
+                }
+            }
+        }
+    }
+}
+
+/// The number of decimals needed to tell ticks `step` apart.
+fn step_decimals(step: f64) -> usize {
+    if step <= 0.0 || !step.is_finite() {
+        return 0;
+    }
+    // The epsilon catches steps like 0.1 that are slightly above their power of ten.
+    (-(step.log10() + 1e-9).floor()).clamp(0.0, 10.0) as usize
+}
+
+fn format_decimals(value: f64, decimals: usize) -> String {
+    let text = format!("{value:.decimals$}");
+    // Don't show "-0".
+    match text.strip_prefix('-') {
+        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => rest.to_string(),
+        _ => text,
+    }
+}
+
+/// The side of the plot an axis is on. Labels are placed outside of the plot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisSide {
+    Bottom,
+    Top,
+    Left,
+    Right,
+}
+
+impl AxisSide {
+    fn is_horizontal(self) -> bool {
+        matches!(self, AxisSide::Bottom | AxisSide::Top)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AxisLabelStyle {
+    pub font_size: f32,
+    pub color: Color,
+    pub weight: TextWeight,
+    /// The distance between the axis and the labels.
+    pub padding: f64,
+    /// The minimum distance between two labels.
+    pub spacing: f64,
+    /// The angle labels of horizontal axes are rotated by when they don't fit side by side, in
+    /// degrees.
+    pub rotation: f64,
+}
+
+impl Default for AxisLabelStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            color: Color::BLACK,
+            weight: TextWeight::NORMAL,
+            padding: 6.0,
+            spacing: 8.0,
+            rotation: 45.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AxisLabel {
+    /// The index of the tick the label belongs to.
+    pub tick: usize,
+    pub run: GlyphRun,
+    /// The matrix of a rotated label, relative to the axis.
+    ///
+    /// `None` if the label is not rotated. Then the run's translation places it relative to the
+    /// axis and all unrotated labels can share the axis' position.
+    pub matrix: Option<Matrix4>,
+}
+
+/// Shape and place the labels of an axis.
+///
+/// `ticks` are the offsets of the ticks along the axis and their labels, in ascending order.
+/// Labels are centered on their ticks. Labels of horizontal axes are rotated when neighbors would
+/// overlap. Labels that still don't fit are dropped, keeping every n-th one.
+pub fn axis_labels(
+    font_system: &mut text::FontSystem,
+    ticks: impl IntoIterator<Item = (f64, impl AsRef<str>)>,
+    side: AxisSide,
+    style: &AxisLabelStyle,
+) -> Vec<AxisLabel> {
+    let shaped: Vec<(f64, LineLayout, GlyphRun)> = ticks
+        .into_iter()
+        .map(|(offset, label)| {
+            let (layout, run) = LineLayout::shape(
+                font_system,
+                label.as_ref(),
+                style.font_size,
+                style.weight,
+                style.color,
+                (0.0, 0.0, 0.0),
+                style.font_size,
+            );
+            (offset, layout, run)
+        })
+        .collect();
+
+    // Whether the labels of every n-th tick fit, given a test for neighbors at a distance.
+    let fits = |every: usize, fit: &dyn Fn(f64, &LineLayout, &LineLayout) -> bool| {
+        shaped
+            .iter()
+            .step_by(every)
+            .zip(shaped.iter().step_by(every).skip(1))
+            .all(|((a, a_layout, _), (b, b_layout, _))| fit((b - a).abs(), a_layout, b_layout))
+    };
+    let side_by_side = |distance: f64, a: &LineLayout, b: &LineLayout| {
+        let extent = if side.is_horizontal() {
+            a.width() + b.width()
+        } else {
+            a.height() + b.height()
+        };
+        distance >= extent as f64 / 2.0 + style.spacing
+    };
+    // Rotated labels are parallel, their distance is perpendicular to the text.
+    let sin = style.rotation.to_radians().sin().abs();
+    let rotated = |distance: f64, a: &LineLayout, b: &LineLayout| {
+        distance * sin >= a.height().max(b.height()) as f64 + style.spacing
+    };
+
+    let rotate = side.is_horizontal() && sin > 0.0 && !fits(1, &side_by_side);
+    let fit: &dyn Fn(f64, &LineLayout, &LineLayout) -> bool = match rotate {
+        true => &rotated,
+        false => &side_by_side,
+    };
+    let every = (1..=shaped.len().max(1))
+        .find(|every| fits(*every, fit))
+        .unwrap_or(1);
+
+    shaped
+        .into_iter()
+        .enumerate()
+        .step_by(every)
+        .map(|(tick, (offset, layout, mut run))| {
+            let (width, height) = (layout.width() as f64, layout.height() as f64);
+            if rotate {
+                // The end of the label points to the tick.
+                let (y, angle) = match side {
+                    AxisSide::Bottom => (style.padding, -style.rotation),
+                    _ => (-style.padding, style.rotation),
+                };
+                run.translation = Vector3::new(-width, -height / 2.0, 0.0);
+                let matrix = Matrix4::from_translation(Vector3::new(offset, y, 0.0))
+                    * Matrix4::from_angle_z(Deg(angle));
+                return AxisLabel {
+                    tick,
+                    run,
+                    matrix: Some(matrix),
+                };
+            }
+
+            let (x, y) = match side {
+                AxisSide::Bottom => (offset - width / 2.0, style.padding),
+                AxisSide::Top => (offset - width / 2.0, -style.padding - height),
+                AxisSide::Left => (-style.padding - width, offset - height / 2.0),
+                AxisSide::Right => (style.padding, offset - height / 2.0),
+            };
+            run.translation = Vector3::new(x, y, 0.0);
+            AxisLabel {
+                tick,
+                run,
+                matrix: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() <= e.abs() * 1e-9,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    fn font_system() -> text::FontSystem {
+        let mut db = text::fontdb::Database::new();
+        db.load_font_source(text::fontdb::Source::Binary(Arc::new(include_bytes!(
+            "../../examples/shared/src/fonts/Montserrat/Montserrat-Regular.ttf"
+        ))));
+        text::FontSystem::new_with_locale_and_db("en-US".into(), db)
+    }
+
+    #[test]
+    fn steps_are_one_two_or_five_times_a_power_of_ten() {
+        for (rough, nice) in [
+            (0.7, 1.0),
+            (1.0, 1.0),
+            (1.3, 2.0),
+            (3.5, 5.0),
+            (7.0, 10.0),
+            (15.0, 20.0),
+            (0.003, 0.005),
+            (7e-5, 1e-4),
+            (4.2e11, 5e11),
+        ] {
+            assert_close(&[nice_step(rough)], &[nice]);
+        }
+    }
+
+    #[test]
+    fn ticks_cover_the_range() {
+        let ticks = Ticks::new(0.0, 10.0, 5);
+        assert_eq!(ticks.step, 2.0);
+        assert_eq!(ticks.values, [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        // Reversed ranges are the same.
+        assert_eq!(Ticks::new(10.0, 0.0, 5), ticks);
+
+        // Ticks are multiples of the step, inside the range.
+        let ticks = Ticks::new(0.3, 9.7, 5);
+        assert_eq!(ticks.values, [2.0, 4.0, 6.0, 8.0]);
+    }
+
+    #[test]
+    fn negative_ranges() {
+        assert_eq!(Ticks::new(-7.0, 7.0, 4).values, [-5.0, 0.0, 5.0]);
+        let ticks = Ticks::new(-0.95, -0.05, 6);
+        assert_close(&ticks.values, &[-0.8, -0.6, -0.4, -0.2]);
+    }
+
+    #[test]
+    fn tiny_and_huge_ranges() {
+        let tiny = Ticks::new(0.0, 0.00035, 5);
+        assert_close(&[tiny.step], &[1e-4]);
+        assert_close(&tiny.values, &[0.0, 1e-4, 2e-4, 3e-4]);
+
+        let huge = Ticks::new(0.0, 3.5e12, 5);
+        assert_close(&[huge.step], &[1e12]);
+        assert_close(&huge.values, &[0.0, 1e12, 2e12, 3e12]);
+    }
+
+    #[test]
+    fn empty_ranges_have_a_single_tick() {
+        for ticks in [
+            Ticks::new(3.0, 3.0, 5),
+            Ticks::new(3.0, 8.0, 0),
+            Ticks::new(3.0, f64::INFINITY, 5),
+        ] {
+            assert_eq!(ticks.values, [3.0]);
+            assert_eq!(ticks.step, 0.0);
+        }
+        assert_eq!(Ticks::new(0.0, 0.0, 5).labels(TickFormat::Auto), ["0"]);
+    }
+
+    #[test]
+    fn auto_labels_have_the_decimals_of_the_step() {
+        assert_eq!(
+            Ticks::new(0.0, 1.0, 5).labels(TickFormat::Auto),
+            ["0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]
+        );
+        assert_eq!(
+            Ticks::new(0.0, 0.035, 3).labels(TickFormat::Auto),
+            ["0.00", "0.02"]
+        );
+        assert_eq!(
+            Ticks::new(-20.0, 20.0, 2).labels(TickFormat::Auto),
+            ["-20", "0", "20"]
+        );
+    }
+
+    #[test]
+    fn labels_in_other_formats() {
+        assert_eq!(TickFormat::Decimals(2).format(1.5, 1.0), "1.50");
+        assert_eq!(TickFormat::Percent.format(0.25, 0.05), "25%");
+        assert_eq!(TickFormat::Percent.format(0.125, 0.005), "12.5%");
+        assert_eq!(TickFormat::Si.format(3e9, 1e9), "3G");
+        assert_eq!(TickFormat::Si.format(-2000.0, 1000.0), "-2k");
+        // The prefix follows the step, not the value.
+        assert_eq!(TickFormat::Si.format(2.5e6, 5e5), "2500k");
+        assert_eq!(TickFormat::Si.format(750.0, 250.0), "750");
+    }
+
+    #[test]
+    fn negative_zero_is_shown_without_sign() {
+        assert_eq!(TickFormat::Auto.format(-0.0, 1.0), "0");
+        assert_eq!(TickFormat::Decimals(2).format(-0.001, 1.0), "0.00");
+        assert_eq!(TickFormat::Decimals(2).format(-0.01, 1.0), "-0.01");
+    }
+
+    #[test]
+    fn labels_of_spaced_ticks_are_centered_and_unrotated() {
+        let mut font_system = font_system();
+        let style = AxisLabelStyle::default();
+        let ticks = [(0.0, "0"), (100.0, "50"), (200.0, "100")];
+        let labels = axis_labels(&mut font_system, ticks, AxisSide::Bottom, &style);
+
+        assert_eq!(labels.len(), 3);
+        for (label, (offset, _)) in labels.iter().zip(ticks) {
+            assert!(label.matrix.is_none());
+            let width = label.run.metrics.width as f64;
+            let center = label.run.translation.x + width / 2.0;
+            assert!((center - offset).abs() <= 1.0, "{center} {offset}");
+            assert_eq!(label.run.translation.y, style.padding);
+        }
+
+        let labels = axis_labels(&mut font_system, ticks, AxisSide::Left, &style);
+        for label in &labels {
+            let width = label.run.metrics.width as f64;
+            assert!(label.run.translation.x + width <= -style.padding + 1.0);
+        }
+    }
+
+    #[test]
+    fn crowded_labels_of_horizontal_axes_are_rotated() {
+        let mut font_system = font_system();
+        let style = AxisLabelStyle::default();
+        let ticks: Vec<_> = (0..10)
+            .map(|i| (i as f64 * 30.0, format!("{}.00", i * 1000)))
+            .collect();
+        let labels = axis_labels(&mut font_system, ticks, AxisSide::Bottom, &style);
+
+        assert!(!labels.is_empty());
+        assert!(labels.iter().all(|label| label.matrix.is_some()));
+        assert_eq!(labels[0].tick, 0);
+    }
+
+    #[test]
+    fn crowded_labels_of_vertical_axes_are_thinned_out() {
+        let mut font_system = font_system();
+        let style = AxisLabelStyle::default();
+        let ticks: Vec<_> = (0..20).map(|i| (i as f64 * 5.0, i.to_string())).collect();
+        let labels = axis_labels(&mut font_system, ticks, AxisSide::Left, &style);
+
+        assert!(labels.iter().all(|label| label.matrix.is_none()));
+        assert!(labels.len() > 1 && labels.len() < 20);
+        let every = labels[1].tick;
+        assert!(labels
+            .iter()
+            .enumerate()
+            .all(|(index, label)| label.tick == index * every));
+    }
+}
@@ -0,0 +1,186 @@
+//! A font system that lays out text the same way on every run and platform, for golden tests and
+//! replays.
+//!
+//! Given the same fonts, cosmic-text shapes deterministically: The advances are computed from the
+//! integer units of the fonts with IEEE arithmetic, and the hash maps of the font system are only
+//! used for lookups, so their random iteration order does not reach the layout. The widgets round
+//! all values that end up in glyph runs by a fixed policy: Glyph positions are rounded to whole
+//! pixels, hit box widths to 1/64 pixel, and the widths of runs up to whole pixels.
+//!
+//! What differs between runs is the environment: the fonts installed on the host, the order they
+//! are found in, which decides the font ids in the glyph runs and the faces fallback tries first,
+//! and the locale, which decides the fallback scripts. The wasm build does not know the locale at
+//! all. A deterministic font system contains only the fonts it is given, in the given order. To
+//! load the fonts of a directory, use [`font_files`], which orders them by their path instead of
+//! the order of the file system.
+//!
+//! Fallback tries the fonts in the order they were loaded, after the faces cosmic-text names for
+//! the platform. To be independent of the platform, the loaded fonts should cover the text
+//! without relying on these names. Don't use a [`crate::FontFallbackCache`] with deterministic
+//! font systems, it changes the faces that are requested.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use cosmic_text::{fontdb, FontSystem};
+
+/// The locale of deterministic font systems.
+pub const DETERMINISTIC_LOCALE: &str = "en-US";
+
+/// The extensions of the files [`font_files`] returns, compared case-insensitively.
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+/// A font system that contains only `fonts`, loaded in the given order, and ignores the fonts and
+/// the locale of the host.
+///
+/// All generic families resolve to the family of the first font.
+pub fn deterministic_font_system(fonts: impl IntoIterator<Item = fontdb::Source>) -> FontSystem {
+    let mut db = fontdb::Database::new();
+    for source in fonts {
+        db.load_font_source(source);
+    }
+
+    // The default generic families name fonts that may or may not be loaded.
+    let first_family = db
+        .faces()
+        .next()
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone());
+    if let Some(family) = first_family {
+        db.set_serif_family(&family);
+        db.set_sans_serif_family(&family);
+        db.set_monospace_family(&family);
+        db.set_cursive_family(&family);
+        db.set_fantasy_family(&family);
+    }
+
+    FontSystem::new_with_locale_and_db(DETERMINISTIC_LOCALE.into(), db)
+}
+
+/// The font files in `dir` and its subdirectories, ordered by their path.
+///
+/// Unlike [`fontdb::Database::load_fonts_dir`], which loads the files in the order the file
+/// system lists them, the order does not depend on the platform.
+pub fn font_files(dir: impl AsRef<Path>) -> io::Result<Vec<fontdb::Source>> {
+    let mut files = Vec::new();
+    collect_font_files(dir.as_ref(), &mut files)?;
+    files.sort();
+    Ok(files.into_iter().map(fontdb::Source::File).collect())
+}
+
+fn collect_font_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_font_files(&path, files)?;
+            continue;
+        }
+        let is_font = path.extension().is_some_and(|extension| {
+            FONT_EXTENSIONS
+                .iter()
+                .any(|font| extension.eq_ignore_ascii_case(font))
+        });
+        if is_font {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use massive_geometry::Color;
+    use massive_shapes::{GlyphRun, TextWeight};
+
+    use super::*;
+    use crate::widgets::LineLayout;
+
+    /// Ligatures, kerning, accents, and a box drawing character only JetBrains Mono covers.
+    const TEXT: &str = "Fiffy AVAWay, déjà vu ─ 0.123";
+
+    const FONTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/shared/src/fonts");
+
+    fn shape(font_system: &mut FontSystem, text: &str, weight: TextWeight) -> GlyphRun {
+        let (_, run) = LineLayout::shape(
+            font_system,
+            text,
+            17.0,
+            weight,
+            Color::BLACK,
+            (0.5, 0.25, 0.0),
+            0.0,
+        );
+        run
+    }
+
+    /// All values of a run that reach the renderer. Fonts are identified by their index in the
+    /// font database.
+    fn run_bytes(font_system: &FontSystem, run: &GlyphRun) -> Vec<u8> {
+        let font_ids: Vec<_> = font_system.db().faces().map(|face| face.id).collect();
+        let mut bytes = Vec::new();
+        for value in [run.translation.x, run.translation.y, run.translation.z] {
+            bytes.extend(value.to_le_bytes());
+        }
+        let metrics = run.metrics;
+        for value in [metrics.max_ascent, metrics.max_descent, metrics.width] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(run.text_weight.0.to_le_bytes());
+        for glyph in &run.glyphs {
+            let key = glyph.key;
+            let font = font_ids.iter().position(|id| *id == key.font_id).unwrap();
+            bytes.extend((font as u32).to_le_bytes());
+            bytes.extend(key.glyph_id.to_le_bytes());
+            bytes.extend(key.font_size_bits.to_le_bytes());
+            bytes.extend([key.x_bin as u8, key.y_bin as u8, key.flags.bits() as u8]);
+            bytes.extend(glyph.hitbox_pos.0.to_le_bytes());
+            bytes.extend(glyph.hitbox_pos.1.to_le_bytes());
+            bytes.extend(glyph.hitbox_width.to_bits().to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn font_files_are_ordered_by_path() {
+        let files = font_files(FONTS).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|source| match source {
+                fontdb::Source::File(path) => path.file_name().unwrap().to_owned(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, ["JetBrainsMono[wght].ttf", "Montserrat-Regular.ttf"]);
+    }
+
+    #[test]
+    fn glyph_runs_are_byte_identical_across_font_systems() {
+        let mut first = deterministic_font_system(font_files(FONTS).unwrap());
+        let run = shape(&mut first, TEXT, TextWeight::NORMAL);
+        let expected = run_bytes(&first, &run);
+        assert!(!expected.is_empty());
+
+        // The caches of the font system do not change the layout.
+        shape(&mut first, "Other text ─ 42", TextWeight::BOLD);
+        let again = shape(&mut first, TEXT, TextWeight::NORMAL);
+        assert_eq!(run_bytes(&first, &again), expected);
+
+        for _ in 0..3 {
+            let mut font_system = deterministic_font_system(font_files(FONTS).unwrap());
+            let run = shape(&mut font_system, TEXT, TextWeight::NORMAL);
+            assert_eq!(run_bytes(&font_system, &run), expected);
+        }
+    }
+
+    #[test]
+    fn hitbox_widths_are_rounded_to_a_64th_pixel() {
+        let mut font_system = deterministic_font_system(font_files(FONTS).unwrap());
+        let run = shape(&mut font_system, TEXT, TextWeight::NORMAL);
+        for glyph in &run.glyphs {
+            let scaled = glyph.hitbox_width * 64.0;
+            assert_eq!(scaled, scaled.round());
+        }
+    }
+}
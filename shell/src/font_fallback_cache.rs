@@ -0,0 +1,355 @@
+//! A persistent record of the faces font fallback resolved characters to.
+//!
+//! The first time cosmic-text shapes a character the requested font does not cover, it loads and
+//! scans the fallback fonts until one covers it. With documents that use many scripts or emojis,
+//! this stalls the first frames. The cache records which face each character ended up with for
+//! the family that was requested, loads these faces at the next start, and requests them directly
+//! when the characters are shaped again, so that fallback is not needed anymore.
+//!
+//! File format: A magic line, followed by one line per character with its code point in hex, the
+//! PostScript name of the face, and the name of the requested family, separated by a space.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use cosmic_text as text;
+use log::{info, warn};
+
+const MAGIC: &str = "MFALLBACK02";
+
+/// Characters beyond this number are not recorded anymore.
+const MAX_ENTRIES: usize = 16384;
+
+#[derive(Debug)]
+pub struct FontFallbackCache {
+    path: PathBuf,
+    /// The PostScript name of the face of each character, by the name of the family that was
+    /// requested for it.
+    faces: BTreeMap<(String, char), String>,
+    /// The family names of the faces that were loaded by [`Self::warm`], by their PostScript name.
+    families: HashMap<String, String>,
+    modified: bool,
+}
+
+impl FontFallbackCache {
+    /// Open the cache at `path` or start a new one if it does not exist.
+    ///
+    /// An unreadable or incompatible cache is ignored and rewritten when the cache is persisted.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let faces = match read_faces(&path) {
+            Ok(faces) => faces,
+            Err(e) => {
+                warn!(
+                    "Ignoring font fallback cache {}, it will be rewritten: {e:?}",
+                    path.display()
+                );
+                BTreeMap::new()
+            }
+        };
+        Self {
+            path,
+            faces,
+            families: HashMap::new(),
+            modified: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the faces recorded in previous runs.
+    ///
+    /// Only faces loaded here are requested by [`Self::apply`]. Characters whose faces are not
+    /// installed anymore are forgotten.
+    pub fn warm(&mut self, font_system: &mut text::FontSystem) {
+        let names: HashSet<&str> = self.faces.values().map(String::as_str).collect();
+        let faces: Vec<_> = font_system
+            .db()
+            .faces()
+            .filter(|face| names.contains(face.post_script_name.as_str()))
+            .filter_map(|face| {
+                let (family, _) = face.families.first()?;
+                Some((face.id, face.post_script_name.clone(), family.clone()))
+            })
+            .collect();
+
+        for (id, name, family) in faces {
+            if font_system.get_font(id).is_some() {
+                self.families.insert(name, family);
+            }
+        }
+
+        let count = self.faces.len();
+        self.faces
+            .retain(|_, name| self.families.contains_key(name.as_str()));
+        self.modified |= self.faces.len() != count;
+        info!(
+            "Loaded {} fallback faces for {} characters",
+            self.families.len(),
+            self.faces.len()
+        );
+    }
+
+    /// Request the recorded faces for the characters of `text` directly, by overriding the family
+    /// of their attributes in `attrs_list`.
+    ///
+    /// Call this before `text` is shaped, and pass the attributes from before this call to
+    /// [`Self::record_line`].
+    pub fn apply(
+        &self,
+        font_system: &text::FontSystem,
+        text: &str,
+        attrs_list: &mut text::AttrsList,
+    ) {
+        if self.families.is_empty() {
+            return;
+        }
+
+        // Consecutive characters with the same face and the same attributes share a span.
+        let mut overrides: Vec<(Range<usize>, &str, text::AttrsOwned)> = Vec::new();
+        for (start, c) in text.char_indices() {
+            if c.is_ascii() {
+                continue;
+            }
+            let attrs = attrs_list.get_span(start);
+            let requested = font_system.db().family_name(&attrs.family).to_string();
+            let Some(family) = self
+                .faces
+                .get(&(requested, c))
+                .and_then(|name| self.families.get(name))
+            else {
+                continue;
+            };
+            let end = start + c.len_utf8();
+            match overrides.last_mut() {
+                Some((range, last_family, last_attrs))
+                    if range.end == start
+                        && *last_family == family.as_str()
+                        && last_attrs.as_attrs() == attrs =>
+                {
+                    range.end = end;
+                }
+                _ => overrides.push((start..end, family.as_str(), text::AttrsOwned::new(attrs))),
+            }
+        }
+
+        for (range, family, attrs) in &overrides {
+            let attrs = attrs.as_attrs().family(text::Family::Name(family));
+            attrs_list.add_span(range.clone(), attrs);
+        }
+    }
+
+    /// Record the faces that font fallback resolved the characters of a shaped line to.
+    ///
+    /// `text` is the text the line was shaped from, and `attrs_list` the attributes it was
+    /// shaped with, before [`Self::apply`] overrode them. ASCII is covered by the requested fonts,
+    /// so only other characters are recorded.
+    pub fn record_line(
+        &mut self,
+        font_system: &text::FontSystem,
+        text: &str,
+        attrs_list: &text::AttrsList,
+        line: &text::LayoutLine,
+    ) {
+        for glyph in &line.glyphs {
+            let Some(c) = text
+                .get(glyph.start..glyph.end)
+                .and_then(|s| s.chars().next())
+            else {
+                continue;
+            };
+            if c.is_ascii() {
+                continue;
+            }
+            let Some(face) = font_system.db().face(glyph.font_id) else {
+                continue;
+            };
+            let attrs = attrs_list.get_span(glyph.start);
+            let requested = font_system.db().family_name(&attrs.family);
+            // Covered by the requested family, no fallback was needed.
+            if face.families.iter().any(|(family, _)| family == requested) {
+                continue;
+            }
+            let key = (requested.to_string(), c);
+            if self.faces.get(&key) == Some(&face.post_script_name) {
+                continue;
+            }
+            if self.faces.len() >= MAX_ENTRIES && !self.faces.contains_key(&key) {
+                continue;
+            }
+            self.faces.insert(key, face.post_script_name.clone());
+            self.modified = true;
+        }
+    }
+
+    /// Write the cache if characters were recorded since it was opened or persisted.
+    pub fn persist(&mut self) -> Result<()> {
+        if !self.modified {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut content = format!("{MAGIC}\n");
+        for ((family, c), name) in &self.faces {
+            content.push_str(&format!("{:x} {name} {family}\n", *c as u32));
+        }
+        fs::write(&self.path, content)?;
+
+        info!(
+            "Persisted {} fallback characters to {}",
+            self.faces.len(),
+            self.path.display()
+        );
+        self.modified = false;
+        Ok(())
+    }
+}
+
+impl Drop for FontFallbackCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.persist() {
+            warn!(
+                "Failed to persist font fallback cache {}: {e:?}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn read_faces(path: &Path) -> Result<BTreeMap<(String, char), String>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+    if lines.next() != Some(MAGIC) {
+        bail!("Invalid magic or version");
+    }
+
+    lines
+        .map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let (Some(code), Some(name), Some(family)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("Invalid entry {line:?}");
+            };
+            let c = u32::from_str_radix(code, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .with_context(|| format!("Invalid character {code:?}"))?;
+            Ok(((family.to_string(), c), name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Montserrat does not cover box drawing characters, JetBrains Mono does.
+    fn font_system() -> text::FontSystem {
+        let mut db = text::fontdb::Database::new();
+        db.load_font_data(
+            include_bytes!("../../examples/shared/src/fonts/Montserrat/Montserrat-Regular.ttf")
+                .to_vec(),
+        );
+        db.load_font_data(
+            include_bytes!(
+                "../../examples/shared/src/fonts/JetBrainsMono-2.304/fonts/variable/JetBrainsMono[wght].ttf"
+            )
+            .to_vec(),
+        );
+        db.set_sans_serif_family("Montserrat");
+        db.set_monospace_family("JetBrains Mono");
+        text::FontSystem::new_with_locale_and_db("en-US".into(), db)
+    }
+
+    fn shape(
+        font_system: &mut text::FontSystem,
+        cache: &mut FontFallbackCache,
+        text: &str,
+    ) -> Vec<text::fontdb::ID> {
+        let requested = text::AttrsList::new(text::Attrs::new().family(text::Family::SansSerif));
+        let mut attrs_list = requested.clone();
+        cache.apply(font_system, text, &mut attrs_list);
+        let mut buffer = text::BufferLine::new(text, attrs_list, text::Shaping::Advanced);
+        let lines = buffer.layout(font_system, 16.0, 1000.0, text::Wrap::None, None);
+        let line = &lines[0];
+        cache.record_line(font_system, text, &requested, line);
+        line.glyphs.iter().map(|glyph| glyph.font_id).collect()
+    }
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "massive-fallback-{name}-{}.cache",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn records_only_fallback_characters() {
+        let path = cache_path("records");
+        let mut font_system = font_system();
+        let mut cache = FontFallbackCache::open(&path);
+        shape(&mut font_system, &mut cache, "é─");
+
+        let faces: Vec<_> = cache.faces.keys().cloned().collect();
+        assert_eq!(faces, [("Montserrat".to_string(), '─')]);
+        cache.modified = false;
+    }
+
+    #[test]
+    fn recorded_faces_are_requested_directly() {
+        let path = cache_path("requested");
+        let mut font_system = font_system();
+        let fallback_ids = {
+            let mut cache = FontFallbackCache::open(&path);
+            let ids = shape(&mut font_system, &mut cache, "a─");
+            cache.persist().unwrap();
+            ids
+        };
+
+        let mut cache = FontFallbackCache::open(&path);
+        cache.warm(&mut font_system);
+        let mut attrs_list =
+            text::AttrsList::new(text::Attrs::new().family(text::Family::SansSerif));
+        cache.apply(&font_system, "a─", &mut attrs_list);
+        assert_eq!(
+            attrs_list.get_span(0).family,
+            text::Family::SansSerif,
+            "ASCII is not overridden"
+        );
+        assert_eq!(
+            attrs_list.get_span(1).family,
+            text::Family::Name("JetBrains Mono")
+        );
+
+        // The same faces as with fallback.
+        assert_eq!(shape(&mut font_system, &mut cache, "a─"), fallback_ids);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn other_formats_are_ignored() {
+        let path = cache_path("format");
+        fs::write(&path, "MFALLBACK01\n2500 JetBrainsMono-Regular\n").unwrap();
+        let cache = FontFallbackCache::open(&path);
+        assert!(cache.faces.is_empty());
+        drop(cache);
+        let _ = fs::remove_file(&path);
+    }
+}
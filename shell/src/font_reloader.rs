@@ -0,0 +1,149 @@
+//! Replacing font data while the application runs, for example while a type designer iterates on
+//! a font.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use cosmic_text::{fontdb, FontSystem};
+use log::{info, warn};
+
+/// Loads font files and reloads them when they change on disk.
+///
+/// Reloading returns the replaced fonts. Pass them to
+/// [`WindowRenderer::replace_fonts`](crate::WindowRenderer::replace_fonts) and call `relayout()`
+/// on the widgets, so that their text is shaped with the new metrics.
+#[derive(Debug, Default)]
+pub struct FontReloader {
+    fonts: HashMap<PathBuf, WatchedFont>,
+}
+
+#[derive(Debug)]
+struct WatchedFont {
+    modified: Option<SystemTime>,
+    faces: Vec<fontdb::ID>,
+}
+
+impl FontReloader {
+    /// Load a font file and watch it for changes.
+    ///
+    /// Returns the ids of the faces in the file.
+    pub fn load(
+        &mut self,
+        font_system: &mut FontSystem,
+        path: impl Into<PathBuf>,
+    ) -> Result<Vec<fontdb::ID>> {
+        let path = path.into();
+        let modified = modified(&path);
+        let data = fs::read(&path).with_context(|| format!("Failed to read font {path:?}"))?;
+        let faces = load_font_data(font_system, data)?;
+        self.fonts.insert(
+            path,
+            WatchedFont {
+                modified,
+                faces: faces.clone(),
+            },
+        );
+        Ok(faces)
+    }
+
+    /// Reload the font files that were modified since they were loaded.
+    ///
+    /// Files that can't be loaded, for example because they are still being written, are retried
+    /// with the next call. Returns the ids of the replaced fonts mapped to their replacements.
+    pub fn reload_modified(
+        &mut self,
+        font_system: &mut FontSystem,
+    ) -> HashMap<fontdb::ID, fontdb::ID> {
+        let mut replacements = HashMap::new();
+        for (path, font) in &mut self.fonts {
+            let modified = modified(path);
+            if modified == font.modified {
+                continue;
+            }
+            let reloaded = fs::read(path)
+                .with_context(|| format!("Failed to read font {path:?}"))
+                .and_then(|data| replace_font_data(font_system, &font.faces, data));
+            match reloaded {
+                Ok((faces, replaced)) => {
+                    info!("Reloaded font {path:?}");
+                    font.modified = modified;
+                    font.faces = faces;
+                    replacements.extend(replaced);
+                }
+                Err(e) => warn!("Failed to reload font {path:?}: {e:?}"),
+            }
+        }
+        replacements
+    }
+
+    /// Replace the data of a loaded font file, without reading the file.
+    pub fn replace(
+        &mut self,
+        font_system: &mut FontSystem,
+        path: &Path,
+        data: Vec<u8>,
+    ) -> Result<HashMap<fontdb::ID, fontdb::ID>> {
+        let Some(font) = self.fonts.get_mut(path) else {
+            bail!("Font {path:?} was not loaded");
+        };
+        let (faces, replacements) = replace_font_data(font_system, &font.faces, data)?;
+        font.faces = faces;
+        Ok(replacements)
+    }
+}
+
+/// Replace the faces `old` with the faces in `data`.
+///
+/// Faces are matched by their PostScript names, and then by their index in the font file. Returns
+/// the ids of the new faces and the ids of the replaced faces mapped to their replacements. If
+/// `data` does not contain any faces, nothing is replaced.
+pub fn replace_font_data(
+    font_system: &mut FontSystem,
+    old: &[fontdb::ID],
+    data: Vec<u8>,
+) -> Result<(Vec<fontdb::ID>, HashMap<fontdb::ID, fontdb::ID>)> {
+    let new = load_font_data(font_system, data)?;
+
+    let db = font_system.db();
+    let replacements = old
+        .iter()
+        .filter_map(|old_id| {
+            let old_face = db.face(*old_id)?;
+            let new_faces = || new.iter().filter_map(|id| db.face(*id));
+            let new_face = new_faces()
+                .find(|face| face.post_script_name == old_face.post_script_name)
+                .or_else(|| new_faces().find(|face| face.index == old_face.index))?;
+            Some((*old_id, new_face.id))
+        })
+        .collect();
+
+    // `db_mut()` also clears the font matching cache of the font system.
+    let db = font_system.db_mut();
+    for id in old {
+        db.remove_face(*id);
+    }
+
+    Ok((new, replacements))
+}
+
+fn load_font_data(font_system: &mut FontSystem, data: Vec<u8>) -> Result<Vec<fontdb::ID>> {
+    let faces: Vec<fontdb::ID> = font_system
+        .db_mut()
+        .load_font_source(fontdb::Source::Binary(Arc::new(data)))
+        .into_iter()
+        .collect();
+    if faces.is_empty() {
+        bail!("No font faces found");
+    }
+    Ok(faces)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,8 +1,43 @@
+mod camera_interpolator;
+mod caret_blink;
+pub mod chart;
+mod deterministic_fonts;
+mod font_fallback_cache;
+mod font_reloader;
+mod native_text;
+mod power_saving;
+mod renderer_options;
+mod screenshot;
+mod semantic_zoom;
 pub mod shell;
+mod shortcuts;
+mod system_fonts;
+mod tile_grid;
+mod transient_shapes;
+mod view_state;
+mod viewport_insets;
+pub mod widgets;
+
+pub use camera_interpolator::*;
+pub use caret_blink::*;
+pub use deterministic_fonts::*;
+pub use font_fallback_cache::*;
+pub use font_reloader::*;
+pub use native_text::*;
+pub use power_saving::*;
+pub use renderer_options::*;
+pub use screenshot::*;
+pub use semantic_zoom::*;
 pub use shell::{ApplicationContext, ShellWindow, WindowRenderer};
+pub use shortcuts::*;
+pub use system_fonts::*;
+pub use tile_grid::*;
+pub use transient_shapes::*;
+pub use view_state::*;
+pub use viewport_insets::*;
 
 pub fn time<T>(name: &str, f: impl FnOnce() -> T) -> T {
-    let start = std::time::Instant::now();
+    let start = web_time::Instant::now();
     let r = f();
     println!("{name}: {:?}", start.elapsed());
     r
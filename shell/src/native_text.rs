@@ -0,0 +1,124 @@
+use massive_renderer::TextRendering;
+
+/// The text rendering that resembles the text of the host platform.
+///
+/// - macOS renders unhinted, slightly darkened stems.
+/// - Windows renders hinted text that is snapped to pixels, like ClearType, but without subpixel
+///   antialiasing.
+/// - On Linux and other Unix systems, fontconfig's hinting settings are used. They are queried
+///   on a background thread that is started by [`crate::shell::run`], or by the first call.
+///   Until they are known, fontconfig's default of slight hinting is assumed.
+///
+/// This is the default of [`crate::RendererOptions::text_rendering`].
+pub fn native_text_rendering() -> TextRendering {
+    platform_text_rendering()
+}
+
+/// Start querying the text rendering of the host platform, if this needs time.
+pub(crate) fn query_native_text_rendering() {
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+    fontconfig::query();
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn platform_text_rendering() -> TextRendering {
+    massive_renderer::TextPreset::Print.settings()
+}
+
+#[cfg(target_os = "windows")]
+fn platform_text_rendering() -> TextRendering {
+    massive_renderer::TextPreset::Crisp.settings()
+}
+
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+fn platform_text_rendering() -> TextRendering {
+    fontconfig::text_rendering()
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn platform_text_rendering() -> TextRendering {
+    TextRendering::default()
+}
+
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+mod fontconfig {
+    use std::{
+        process::Command,
+        sync::{Once, OnceLock},
+        thread,
+    };
+
+    use massive_renderer::{TextPreset, TextRendering};
+
+    /// The hint style of fontconfig, see `FC_HINT_STYLE`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum HintStyle {
+        None,
+        Slight,
+        Medium,
+        Full,
+    }
+
+    static QUERY: Once = Once::new();
+    static TEXT_RENDERING: OnceLock<TextRendering> = OnceLock::new();
+
+    /// The text rendering of fontconfig's settings, or of its default until they are known.
+    pub fn text_rendering() -> TextRendering {
+        query();
+        TEXT_RENDERING
+            .get()
+            .copied()
+            .unwrap_or_else(|| hint_style_text_rendering(HintStyle::Slight))
+    }
+
+    /// Run `fc-match` on a background thread, once.
+    ///
+    /// Starting a process takes too long to wait for it when a window opens.
+    pub fn query() {
+        QUERY.call_once(|| {
+            thread::spawn(|| {
+                let text_rendering = query_fc_match().unwrap_or_default();
+                let _ = TEXT_RENDERING.set(text_rendering);
+            });
+        });
+    }
+
+    /// Query the hinting configuration of the default sans serif font with `fc-match`.
+    ///
+    /// `None` if fontconfig is not available.
+    fn query_fc_match() -> Option<TextRendering> {
+        let output = Command::new("fc-match")
+            .args(["--format=%{hinting}|%{hintstyle}", "sans-serif"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let output = String::from_utf8(output.stdout).ok()?;
+        let (hinting, style) = output.trim().split_once('|')?;
+
+        let style = match style {
+            _ if hinting.eq_ignore_ascii_case("false") => HintStyle::None,
+            "0" => HintStyle::None,
+            "1" => HintStyle::Slight,
+            "2" => HintStyle::Medium,
+            "3" => HintStyle::Full,
+            // Not configured, fontconfig defaults to slight hinting.
+            _ => HintStyle::Slight,
+        };
+        Some(hint_style_text_rendering(style))
+    }
+
+    /// Swash hints fully or not at all, so slight hinting, which keeps the horizontal shapes, is
+    /// closer to unhinted text.
+    fn hint_style_text_rendering(style: HintStyle) -> TextRendering {
+        match style {
+            HintStyle::None | HintStyle::Slight => TextRendering {
+                hinting: false,
+                ..TextPreset::Smooth.settings()
+            },
+            HintStyle::Medium => TextPreset::Smooth.settings(),
+            HintStyle::Full => TextPreset::Crisp.settings(),
+        }
+    }
+}
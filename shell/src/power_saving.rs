@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+/// Settings of the power saving mode, see [`crate::WindowRenderer::set_power_saving`].
+///
+/// Meant for windows that are open all day, like text dashboards on laptops: While the window is
+/// in the background, it is redrawn at a low rate and the renderer's caches are left alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerSaving {
+    /// The highest rate in Hz the window is redrawn at while it is in the background.
+    ///
+    /// Not supported on wasm, browsers throttle pages in the background themselves.
+    pub background_rate: f64,
+    /// A focused window without input for this long is in the background, too. With `None`, only
+    /// unfocused windows are.
+    pub inactive_after: Option<Duration>,
+    /// Present with [`wgpu::PresentMode::Fifo`], which waits for the vertical blank instead of
+    /// rendering frames that are never shown.
+    pub prefer_fifo: bool,
+    /// Don't repack the glyph atlases while the window is in the background, see
+    /// [`massive_renderer::Renderer::set_defer_cache_maintenance`].
+    pub defer_cache_maintenance: bool,
+}
+
+impl Default for PowerSaving {
+    fn default() -> Self {
+        Self {
+            background_rate: 10.0,
+            inactive_after: Some(Duration::from_secs(30)),
+            prefer_fifo: true,
+            defer_cache_maintenance: true,
+        }
+    }
+}
+
+impl PowerSaving {
+    /// The shortest time between two frames while the window is in the background.
+    pub fn background_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.background_rate.max(0.1))
+    }
+}
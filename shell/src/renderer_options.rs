@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Result};
+use log::info;
+use massive_renderer::{MaskAtlasFormat, TextRendering, UploadSubmission};
+use wgpu::{
+    Adapter, CompositeAlphaMode, Instance, PowerPreference, PresentMode, Surface,
+    SurfaceCapabilities, TextureFormat,
+};
+
+use crate::PowerSaving;
+
+/// Options that control how the renderer selects its graphics device.
+#[derive(Debug, Clone, Default)]
+pub struct RendererOptions {
+    /// The power preference passed to the adapter request.
+    ///
+    /// Ignored if an adapter is selected explicitly.
+    pub power_preference: PowerPreference,
+    /// Restrict the backends the renderer may use. `None` uses wgpu's defaults.
+    pub backends: Option<wgpu::Backends>,
+    /// Force a specific adapter. Not supported on wasm.
+    pub adapter: Option<AdapterSelector>,
+    /// Allow a software adapter if no hardware adapter is available.
+    pub allow_software_fallback: bool,
+    /// Use this surface format instead of the automatically selected one.
+    ///
+    /// Must be supported by the surface.
+    pub surface_format: Option<TextureFormat>,
+    /// Use this alpha mode instead of the first one the surface supports.
+    ///
+    /// Must be supported by the surface.
+    pub alpha_mode: Option<CompositeAlphaMode>,
+    /// How text is rendered. `None` matches the host platform, see
+    /// [`crate::native_text_rendering`].
+    pub text_rendering: Option<TextRendering>,
+    /// How the distance fields of glyphs are stored. `None` selects the format that works best
+    /// on the adapter's backend, see [`MaskAtlasFormat::for_backend`].
+    pub mask_atlas_format: Option<MaskAtlasFormat>,
+    /// How the uploads of rasterized glyphs are submitted. `None` selects the submission that
+    /// works best on the adapter's backend, see [`UploadSubmission::for_backend`].
+    pub upload_submission: Option<UploadSubmission>,
+    /// A file to persist rasterized glyphs in, so that they don't need to be rasterized again
+    /// with the next start. Ignored on wasm.
+    pub glyph_cache: Option<PathBuf>,
+    /// Compile the WGSL shaders when the renderer is created, even if the renderer was built with
+    /// the `precompiled-shaders` feature and the adapter could use them.
+    pub compile_shaders: bool,
+    /// Start in the power saving mode, see [`crate::WindowRenderer::set_power_saving`].
+    pub power_saving: Option<PowerSaving>,
+}
+
+/// Selects an adapter by properties reported in its [`wgpu::AdapterInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelector {
+    /// The first adapter that contains this string in its name, case insensitive.
+    Name(String),
+    /// The first adapter with this PCI vendor id.
+    VendorId(u32),
+}
+
+impl AdapterSelector {
+    pub fn matches(&self, info: &wgpu::AdapterInfo) -> bool {
+        match self {
+            AdapterSelector::Name(name) => info.name.to_lowercase().contains(&name.to_lowercase()),
+            AdapterSelector::VendorId(vendor) => info.vendor == *vendor,
+        }
+    }
+}
+
+impl RendererOptions {
+    pub fn instance_descriptor(&self) -> wgpu::InstanceDescriptor {
+        match self.backends {
+            Some(backends) => wgpu::InstanceDescriptor {
+                backends,
+                ..wgpu::InstanceDescriptor::default()
+            },
+            None => wgpu::InstanceDescriptor::default(),
+        }
+    }
+
+    /// Select the surface format.
+    ///
+    /// If no format is configured, formats the renderer can render to directly with the
+    /// `device_features` are preferred, see [`massive_renderer::renders_directly`]. Among them,
+    /// the first non-sRGB format is preferred, because colors are specified in linear rgb space.
+    /// Other formats are rendered through a conversion.
+    pub fn select_surface_format(
+        &self,
+        caps: &SurfaceCapabilities,
+        device_features: wgpu::Features,
+    ) -> Result<TextureFormat> {
+        match self.surface_format {
+            Some(format) if caps.formats.contains(&format) => Ok(format),
+            Some(format) => bail!(
+                "Surface format {format:?} is not supported, supported formats: {:?}",
+                caps.formats
+            ),
+            None => {
+                let direct: Vec<_> = caps
+                    .formats
+                    .iter()
+                    .copied()
+                    .filter(|f| massive_renderer::renders_directly(*f, device_features))
+                    .collect();
+                let candidates = if direct.is_empty() {
+                    &caps.formats
+                } else {
+                    &direct
+                };
+                candidates
+                    .iter()
+                    .copied()
+                    .find(|f| !f.is_srgb())
+                    .or_else(|| candidates.first().copied())
+                    .ok_or_else(|| anyhow!("Surface does not support any format"))
+            }
+        }
+    }
+
+    /// The features to request from the adapter.
+    ///
+    /// The precompiled shaders are used if the adapter supports them, see
+    /// [`massive_renderer::precompiled_shader_features`]. The render passes are measured for
+    /// Tracy if the adapter supports it, see [`massive_renderer::profiling_features`].
+    pub fn required_features(&self, adapter: &Adapter) -> wgpu::Features {
+        let profiling = adapter.features() & massive_renderer::profiling_features();
+        if self.compile_shaders {
+            return profiling;
+        }
+        profiling | (adapter.features() & massive_renderer::precompiled_shader_features())
+    }
+
+    /// Select the alpha mode.
+    ///
+    /// If no alpha mode is configured, the first one the surface supports is used.
+    pub fn select_alpha_mode(&self, caps: &SurfaceCapabilities) -> Result<CompositeAlphaMode> {
+        match self.alpha_mode {
+            Some(mode) if caps.alpha_modes.contains(&mode) => Ok(mode),
+            Some(mode) => bail!(
+                "Alpha mode {mode:?} is not supported, supported alpha modes: {:?}",
+                caps.alpha_modes
+            ),
+            None => caps
+                .alpha_modes
+                .first()
+                .copied()
+                .ok_or_else(|| anyhow!("Surface does not support any alpha mode")),
+        }
+    }
+
+    /// Select the present mode.
+    ///
+    /// [`PresentMode::Fifo`] is selected in the power saving mode if it prefers it, otherwise
+    /// [`PresentMode::Immediate`] if the surface supports it, and the first supported mode if not.
+    pub fn select_present_mode(&self, caps: &SurfaceCapabilities) -> PresentMode {
+        select_present_mode(self.power_saving.as_ref(), caps)
+    }
+
+    /// Select an adapter that is compatible with the surface.
+    pub async fn select_adapter(
+        &self,
+        instance: &Instance,
+        surface: &Surface<'_>,
+    ) -> Result<Adapter> {
+        if let Some(selector) = &self.adapter {
+            return self.select_adapter_explicitly(instance, surface, selector);
+        }
+
+        let request = |force_fallback_adapter| {
+            instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: self.power_preference,
+                // Be sure the adapter can present the surface.
+                compatible_surface: Some(surface),
+                force_fallback_adapter,
+            })
+        };
+
+        if let Some(adapter) = request(false).await {
+            return Ok(adapter);
+        }
+
+        if self.allow_software_fallback {
+            info!("No hardware adapter found, requesting a software fallback adapter");
+            if let Some(adapter) = request(true).await {
+                return Ok(adapter);
+            }
+        }
+
+        bail!(
+            "No adapter found (power preference: {:?}, software fallback allowed: {})",
+            self.power_preference,
+            self.allow_software_fallback
+        )
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn select_adapter_explicitly(
+        &self,
+        instance: &Instance,
+        surface: &Surface<'_>,
+        selector: &AdapterSelector,
+    ) -> Result<Adapter> {
+        let backends = self.backends.unwrap_or(wgpu::Backends::all());
+        let adapters = instance.enumerate_adapters(backends);
+
+        let available: Vec<_> = adapters.iter().map(|a| a.get_info()).collect();
+
+        adapters
+            .into_iter()
+            .filter(|adapter| {
+                self.allow_software_fallback
+                    || adapter.get_info().device_type != wgpu::DeviceType::Cpu
+            })
+            .find(|adapter| {
+                selector.matches(&adapter.get_info()) && adapter.is_surface_supported(surface)
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "No adapter matching {selector:?} can present the surface, available: {:?}",
+                    available
+                )
+            })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn select_adapter_explicitly(
+        &self,
+        _instance: &Instance,
+        _surface: &Surface<'_>,
+        selector: &AdapterSelector,
+    ) -> Result<Adapter> {
+        Err(anyhow!(
+            "Selecting an adapter explicitly ({selector:?}) is not supported on wasm"
+        ))
+    }
+}
+
+/// See [`RendererOptions::select_present_mode`].
+pub(crate) fn select_present_mode(
+    power_saving: Option<&PowerSaving>,
+    caps: &SurfaceCapabilities,
+) -> PresentMode {
+    let preferred = match power_saving {
+        Some(power_saving) if power_saving.prefer_fifo => PresentMode::Fifo,
+        _ => PresentMode::Immediate,
+    };
+    caps.present_modes
+        .iter()
+        .copied()
+        .find(|mode| *mode == preferred)
+        .unwrap_or(caps.present_modes[0])
+}
@@ -0,0 +1,64 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use massive_geometry::Color;
+use massive_renderer::CapturedFrame;
+
+/// A frame captured with [`crate::ApplicationContext::take_screenshot`].
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub frame: CapturedFrame,
+    /// The PNG file the frame was saved to. In the browser, frames are not saved, only passed to
+    /// the application.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub path: PathBuf,
+}
+
+/// How screenshots are taken, see [`crate::WindowRenderer::set_screenshot_options`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenshotOptions {
+    /// The directory the PNG files are saved to. They are named after the time they were taken.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub directory: PathBuf,
+    /// The color the window flashes with when a screenshot was taken, `None` to disable the
+    /// flash.
+    pub flash: Option<Color>,
+    /// How long the flash takes to fade out.
+    pub flash_duration: Duration,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            directory: PathBuf::from("."),
+            flash: Some(Color::new(1.0, 1.0, 1.0, 0.6)),
+            flash_duration: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Save a frame as a PNG file with a timestamped name in `directory`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save_png(frame: &CapturedFrame, directory: &Path) -> anyhow::Result<PathBuf> {
+    use std::{fs, io::BufWriter};
+
+    use anyhow::Context;
+
+    fs::create_dir_all(directory)?;
+    let timestamp = chrono::Local::now().format("%Y-%m-%d %H.%M.%S%.3f");
+    let path = directory.join(format!("Screenshot {timestamp}.png"));
+
+    let file =
+        fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&frame.data)?;
+    writer.finish()?;
+
+    Ok(path)
+}
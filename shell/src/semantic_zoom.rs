@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use massive_geometry::{scalar, Identity, Matrix4};
+use massive_renderer::LayerUniforms;
+use massive_scene::{Director, Handle, Position, PositionedShape};
+
+use crate::WindowRenderer;
+
+/// Provides alternative representations of a document for [`SemanticZoom`], for example the full
+/// text, only the headings, and only the outlines of the blocks.
+pub trait ZoomRepresentations {
+    /// Create the shapes of the representation of `level`, starting with 0 for the most detailed
+    /// one.
+    ///
+    /// All shapes must be placed at `position`, because the representation is faded by the
+    /// uniforms of its layer.
+    fn create(
+        &mut self,
+        level: usize,
+        director: &mut Director,
+        position: &Handle<Position>,
+    ) -> Vec<Handle<PositionedShape>>;
+}
+
+/// Shows one of several representations of a document depending on the zoom scale, and
+/// cross-fades between them when the scale crosses a breakpoint.
+///
+/// Each level has its own position below `parent`. Representations are created when they start
+/// fading in and dropped when they faded out. Fades are measured in [`WindowRenderer::time`].
+#[derive(Debug)]
+pub struct SemanticZoom {
+    parent: Handle<Position>,
+    /// The scales below which the next less detailed level is shown, descending.
+    breakpoints: Vec<scalar>,
+    fade: f32,
+    levels: Vec<Level>,
+    current: Option<usize>,
+    /// The time of the last update.
+    updated: Option<f32>,
+}
+
+#[derive(Debug, Default)]
+struct Level {
+    /// The opacity, linear in time.
+    opacity: f32,
+    shapes: Option<LevelShapes>,
+}
+
+#[derive(Debug)]
+struct LevelShapes {
+    position: Handle<Position>,
+    _shapes: Vec<Handle<PositionedShape>>,
+    /// The opacity the layer uniforms were set to.
+    opacity: f32,
+}
+
+impl SemanticZoom {
+    /// `breakpoints` are the scales, descending, below which the next less detailed level is
+    /// shown. The scale is usually [`WindowRenderer::zoom_scale`]. There is one level more than
+    /// there are breakpoints.
+    pub fn new(parent: Handle<Position>, breakpoints: Vec<scalar>, fade: Duration) -> Self {
+        debug_assert!(breakpoints.windows(2).all(|w| w[0] >= w[1]));
+        let levels = (0..=breakpoints.len()).map(|_| Level::default()).collect();
+        Self {
+            parent,
+            breakpoints,
+            fade: fade.as_secs_f32(),
+            levels,
+            current: None,
+            updated: None,
+        }
+    }
+
+    /// The level shown at `scale`.
+    pub fn level_at(&self, scale: scalar) -> usize {
+        self.breakpoints
+            .iter()
+            .take_while(|breakpoint| scale < **breakpoint)
+            .count()
+    }
+
+    /// The level that is shown or fading in, `None` before the first update.
+    pub fn current_level(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Drop the representation of `level`, for example after the document changed. If it is
+    /// visible, it is created again with the next update.
+    pub fn invalidate(&mut self, level: usize) {
+        if let Some(level) = self.levels.get_mut(level) {
+            level.shapes = None;
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.levels.iter().enumerate().any(|(index, level)| {
+            let target = if Some(index) == self.current {
+                1.0
+            } else {
+                0.0
+            };
+            level.opacity != target
+        })
+    }
+
+    /// Show the level for `scale` and advance the cross-fades.
+    ///
+    /// The first update shows its level without a fade. Send the changes with
+    /// [`Director::action`] before the next redraw. Returns `true` while fading, the window needs
+    /// to be redrawn and updated until then.
+    pub fn update(
+        &mut self,
+        representations: &mut impl ZoomRepresentations,
+        director: &mut Director,
+        renderer: &mut WindowRenderer,
+        scale: scalar,
+    ) -> bool {
+        let now = renderer.time();
+        let elapsed = self.updated.map_or(0.0, |updated| (now - updated).max(0.0));
+        self.updated = Some(now);
+
+        let current = self.level_at(scale);
+        if self.current.is_none() {
+            self.levels[current].opacity = 1.0;
+        }
+        self.current = Some(current);
+
+        let step = if self.fade > 0.0 {
+            elapsed / self.fade
+        } else {
+            1.0
+        };
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            level.opacity = if index == current {
+                (level.opacity + step).min(1.0)
+            } else {
+                (level.opacity - step).max(0.0)
+            };
+
+            if level.opacity == 0.0 {
+                if let Some(shapes) = level.shapes.take() {
+                    // The deletion of the shapes is applied before the next frame.
+                    renderer.remove_layer_uniforms(&shapes.position);
+                }
+                continue;
+            }
+
+            let shapes = level.shapes.get_or_insert_with(|| {
+                let matrix = director.cast(Matrix4::identity());
+                let position = director.cast(Position {
+                    parent: Some(self.parent.clone()),
+                    matrix,
+                    pin: None,
+                    overlay: false,
+                });
+                LevelShapes {
+                    _shapes: representations.create(index, director, &position),
+                    position,
+                    opacity: 1.0,
+                }
+            });
+            let opacity = smoothstep(level.opacity);
+            if shapes.opacity != opacity {
+                shapes.opacity = opacity;
+                if opacity == 1.0 {
+                    renderer.remove_layer_uniforms(&shapes.position);
+                } else {
+                    let uniforms = LayerUniforms {
+                        tint: [1.0, 1.0, 1.0, opacity],
+                        ..LayerUniforms::default()
+                    };
+                    renderer.set_layer_uniforms(&shapes.position, &uniforms);
+                }
+            }
+        }
+
+        self.is_animating()
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
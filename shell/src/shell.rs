@@ -1,16 +1,18 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     future::Future,
     ptr,
     rc::Rc,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use anyhow::{bail, Result};
-use cosmic_text::FontSystem;
+use cosmic_text::{fontdb, FontSystem};
 use futures::{task::ArcWake, FutureExt};
-use log::{error, info};
-use massive_scene::{Director, SceneChange};
+use log::{error, info, warn};
+use massive_scene::{Director, Handle, Id, Position, PositionedShape, SceneChange};
 use tokio::{
     sync::{
         mpsc::{channel, Receiver, Sender},
@@ -18,24 +20,39 @@ use tokio::{
     },
     task::LocalSet,
 };
-use wgpu::{Instance, InstanceDescriptor, PresentMode, Surface, SurfaceTarget, TextureFormat};
+use web_time::Instant;
+use wgpu::{Instance, InstanceDescriptor, Surface, SurfaceTarget, TextureFormat};
 use winit::{
     application::ApplicationHandler,
-    dpi::{self, PhysicalSize},
-    event::WindowEvent,
+    dpi::{self, PhysicalPosition, PhysicalSize},
+    event::{StartCause, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
-    monitor::MonitorHandle,
-    window::{Window, WindowAttributes, WindowId},
+    keyboard::ModifiersState,
+    monitor::{MonitorHandle, VideoModeHandle},
+    window::{Fullscreen, Window, WindowAttributes, WindowId},
 };
 
-use massive_geometry::{scalar, Camera, Matrix4};
-use massive_renderer::Renderer;
+use massive_geometry::{scalar, Bounds3, Camera, Matrix4, UnitSystem};
+use massive_renderer::{
+    DebugMode, FontService, FrameCapture, HighContrast, LayerUniforms, MaskAtlasFormat,
+    PreparationStats, PresentWatchdog, QualityController, QualityPolicy, RenderError, Renderer,
+    RendererConfig, RendererState, ShapeExtension, TextRendering, UploadSubmission, View, Viewport,
+};
+
+use crate::{
+    native_text_rendering, query_native_text_rendering, renderer_options::select_present_mode,
+    CameraInterpolator, CaretBlink, PowerSaving, RendererOptions, Screenshot, ScreenshotOptions,
+    ShellAction, Shortcut, ShortcutMap, TransientShapes, ViewState, ViewportInsets,
+};
 
 const Z_RANGE: (scalar, scalar) = (0.1, 100.0);
 
 pub async fn run<R: Future<Output = Result<()>> + 'static>(
     application: impl FnOnce(ApplicationContext) -> R + 'static,
 ) -> Result<()> {
+    // Usually known by the time the first renderer is created.
+    query_native_text_rendering();
+
     let event_loop = EventLoop::with_user_event().build()?;
 
     // Spawn application.
@@ -106,21 +123,42 @@ impl ShellWindow {
     // DI: Use SizeI to represent initial_size.
     pub async fn new_renderer(
         &self,
-        font_system: Arc<Mutex<FontSystem>>,
+        fonts: impl Into<FontService>,
         camera: Camera,
         // Use a rect here to place the renderer on the window.
         // (But what about resizes then?)
         initial_size: PhysicalSize<u32>,
+    ) -> Result<(WindowRenderer, Director)> {
+        self.new_renderer_with_options(fonts, camera, initial_size, RendererOptions::default())
+            .await
+    }
+
+    /// Create a renderer and select the graphics device according to `options`.
+    ///
+    /// `fonts` is either an `Arc<Mutex<FontSystem>>` the application shares for layout, or a
+    /// [`FontService`].
+    pub async fn new_renderer_with_options(
+        &self,
+        fonts: impl Into<FontService>,
+        camera: Camera,
+        initial_size: PhysicalSize<u32>,
+        options: RendererOptions,
     ) -> Result<(WindowRenderer, Director)> {
         // DI: If we can access the ShellWindow, we don't need a clone of font_system or
         // event_loop_proxy here.
-        WindowRenderer::new(self, font_system, camera, initial_size).await
+        WindowRenderer::new(self, fonts.into(), camera, initial_size, options).await
     }
 
     pub fn scale_factor(&self) -> f64 {
         self.window.scale_factor()
     }
 
+    /// The refresh rate of the monitor the window is on in Hz, `None` if it is unknown.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        let millihertz = self.window.current_monitor()?.refresh_rate_millihertz()?;
+        Some(millihertz as f64 / 1000.0)
+    }
+
     pub fn id(&self) -> WindowId {
         self.window.id()
     }
@@ -132,14 +170,243 @@ impl ShellWindow {
     pub fn inner_size(&self) -> PhysicalSize<u32> {
         self.window.inner_size()
     }
+
+    /// Whether the window has the keyboard focus.
+    pub fn has_focus(&self) -> bool {
+        self.window.has_focus()
+    }
+
+    /// Show the window borderless fullscreen on `monitor`, or on its current monitor with
+    /// `None`.
+    ///
+    /// On the web, this only succeeds while handling a user input event.
+    pub fn enter_borderless_fullscreen(&self, monitor: Option<MonitorHandle>) {
+        self.window
+            .set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+    }
+
+    /// Switch the monitor to the video mode and show the window fullscreen on it.
+    ///
+    /// Use [`Self::video_modes`] or [`Self::select_video_mode`] to find a mode. Not supported on
+    /// the web.
+    pub fn enter_exclusive_fullscreen(&self, mode: VideoModeHandle) {
+        self.window
+            .set_fullscreen(Some(Fullscreen::Exclusive(mode)));
+    }
+
+    /// Leave fullscreen and restore the previous video mode.
+    pub fn exit_fullscreen(&self) {
+        self.window.set_fullscreen(None);
+    }
+
+    pub fn fullscreen(&self) -> Option<Fullscreen> {
+        self.window.fullscreen()
+    }
+
+    /// Leave fullscreen, or show the window borderless fullscreen on its current monitor.
+    pub fn toggle_fullscreen(&self) {
+        if self.fullscreen().is_some() {
+            self.exit_fullscreen();
+        } else {
+            self.enter_borderless_fullscreen(None);
+        }
+    }
+
+    /// The video modes of the monitor the window is on.
+    pub fn video_modes(&self) -> Vec<VideoModeHandle> {
+        self.window
+            .current_monitor()
+            .map(|monitor| monitor.video_modes().collect())
+            .unwrap_or_default()
+    }
+
+    /// The video mode of the window's monitor with the size in physical pixels and the refresh
+    /// rate in Hz closest to `refresh_rate`. The highest bit depth is preferred.
+    ///
+    /// Without a refresh rate, the highest one is selected.
+    pub fn select_video_mode(
+        &self,
+        size: PhysicalSize<u32>,
+        refresh_rate: Option<f64>,
+    ) -> Option<VideoModeHandle> {
+        let distance = |mode: &VideoModeHandle| {
+            let rate = mode.refresh_rate_millihertz() as f64 / 1000.0;
+            match refresh_rate {
+                Some(refresh_rate) => (rate - refresh_rate).abs(),
+                None => -rate,
+            }
+        };
+        self.video_modes()
+            .into_iter()
+            .filter(|mode| mode.size() == size)
+            .min_by(|a, b| {
+                distance(a)
+                    .total_cmp(&distance(b))
+                    .then(b.bit_depth().cmp(&a.bit_depth()))
+            })
+    }
+
+    /// Allow input methods. While allowed, the window receives [`WindowEvent::Ime`] events
+    /// instead of some keyboard events.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed)
+    }
+
+    /// Tell the input method where the text is edited, in physical pixels relative to the window,
+    /// so that its candidate window does not cover it.
+    pub fn set_ime_cursor_area(&self, position: (f64, f64), size: (f64, f64)) {
+        self.window.set_ime_cursor_area(
+            PhysicalPosition::new(position.0, position.1),
+            PhysicalSize::new(size.0, size.1),
+        )
+    }
+
+    /// The high contrast palette the user selected in the system settings, `None` if forced
+    /// colors are off or can't be detected.
+    ///
+    /// On the web, this evaluates the `forced-colors` media query.
+    pub fn forced_colors(&self) -> Option<HighContrast> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let window = web_sys::window()?;
+            let matches = |query| {
+                window
+                    .match_media(query)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|list| list.matches())
+            };
+            if !matches("(forced-colors: active)") {
+                return None;
+            }
+            Some(if matches("(prefers-color-scheme: light)") {
+                HighContrast::LIGHT
+            } else {
+                HighContrast::DARK
+            })
+        }
+        // TODO: winit does not report the high contrast settings of the native platforms.
+        #[cfg(not(target_arch = "wasm32"))]
+        None
+    }
+
+    /// The parts of the window that are covered by a soft keyboard or other system UI.
+    ///
+    /// On the web, the insets are the parts of the layout viewport outside of the visual viewport,
+    /// assuming the canvas fills the page. When the visual viewport changes, a redraw is
+    /// requested, so query the insets when handling [`WindowEvent::RedrawRequested`].
+    pub fn viewport_insets(&self) -> ViewportInsets {
+        #[cfg(target_arch = "wasm32")]
+        {
+            visual_viewport_insets().unwrap_or_default()
+        }
+        // TODO: winit does not report soft keyboard insets on Android and iOS yet.
+        #[cfg(not(target_arch = "wasm32"))]
+        ViewportInsets::default()
+    }
 }
 
+#[cfg(target_arch = "wasm32")]
+fn visual_viewport_insets() -> Option<ViewportInsets> {
+    let window = web_sys::window()?;
+    let viewport = window.visual_viewport()?;
+    let width = window.inner_width().ok()?.as_f64()?;
+    let height = window.inner_height().ok()?.as_f64()?;
+    let scale = window.device_pixel_ratio();
+
+    let (top, left) = (viewport.offset_top(), viewport.offset_left());
+    Some(ViewportInsets {
+        top: top * scale,
+        right: (width - left - viewport.width()).max(0.0) * scale,
+        bottom: (height - top - viewport.height()).max(0.0) * scale,
+        left: left * scale,
+    })
+}
+
+/// Receives the new fullscreen state of the window, see [`WindowRenderer::set_fullscreen_handler`].
+type FullscreenHandler = Box<dyn FnMut(Option<&Fullscreen>)>;
+
+/// Receives every screenshot that was taken, see [`WindowRenderer::set_screenshot_handler`].
+type ScreenshotHandler = Box<dyn FnMut(&Screenshot)>;
+
 pub struct WindowRenderer<'window> {
     window: &'window ShellWindow,
-    font_system: Arc<Mutex<FontSystem>>,
-    camera: Camera,
+    fonts: FontService,
+    camera: CameraInterpolator,
     scene_changes: Rc<RefCell<Vec<SceneChange>>>,
     renderer: Renderer<'window>,
+    // Needed to create new surfaces when the surface is reattached.
+    instance: Instance,
+    // Needed to query the surface capabilities when the surface format is renegotiated.
+    adapter: wgpu::Adapter,
+    quality_controller: Option<QualityController>,
+    /// Detects stalled presentation, see [`Self::set_present_watchdog`].
+    present_watchdog: Option<PresentWatchdog>,
+    /// Whether redraws are skipped if they would present the same frame, see
+    /// [`Self::set_skip_idle_frames`].
+    skip_idle_frames: bool,
+    /// Whether the last redraw was skipped, see [`Self::is_idle`].
+    idle: bool,
+    /// How far ahead of an animated camera glyphs are prefetched, see
+    /// [`Self::set_glyph_prefetch`].
+    glyph_prefetch: Option<Duration>,
+    /// The shapes that are dropped after their time to live, see [`Self::expire`].
+    transient_shapes: TransientShapes,
+    /// The cameras the application defined by name, see [`Self::switch_camera`].
+    cameras: HashMap<String, Camera>,
+    /// The name of the camera the view was switched to last.
+    active_camera: Option<String>,
+    /// View projection matrices for the left and right eye, if stereo rendering is enabled.
+    eye_matrices: Option<[Matrix4; 2]>,
+    /// The views the application renders instead of the camera, see [`Self::set_views`].
+    views: Option<Vec<View>>,
+    /// The time in seconds the renderer's time is measured from.
+    time_origin: f64,
+    /// The time at which the window was minimized. While set, nothing is rendered and the time
+    /// of the renderer stands still.
+    minimized_at: Option<f64>,
+    /// The refresh rate of the window's monitor in Hz.
+    refresh_rate: Option<f64>,
+    /// The rate in Hz the application requested for animations.
+    animation_rate: Option<f64>,
+    /// Receives the recoverable errors of the render path.
+    render_error_handler: Box<dyn FnMut(&RenderError)>,
+    /// The fullscreen state the window had when it was resized last.
+    fullscreen: Option<Fullscreen>,
+    /// Receives the new fullscreen state when it changes.
+    fullscreen_handler: Option<FullscreenHandler>,
+    /// The power saving mode, see [`Self::set_power_saving`].
+    power_saving: Option<PowerSaving>,
+    /// Whether the window has the keyboard focus.
+    focused: bool,
+    /// Receives the focus state when it changes.
+    focus_handler: Option<Box<dyn FnMut(bool)>>,
+    /// The time in seconds of the last input event.
+    last_input: f64,
+    /// The time the last frame was rendered.
+    last_frame: Option<Instant>,
+    /// The time the power saving mode deferred the next redraw to.
+    deferred_redraw: Option<Instant>,
+    /// The keyboard shortcuts, see [`Self::set_shortcuts`].
+    shortcuts: ShortcutMap,
+    /// The modifiers that are held, to match the chords of the shortcuts.
+    modifiers: ModifiersState,
+    /// The shortcut the last window event triggered.
+    triggered_shortcut: Option<Shortcut>,
+    /// How screenshots are taken, see [`Self::set_screenshot_options`].
+    screenshot_options: ScreenshotOptions,
+    /// Receives every screenshot that was taken.
+    screenshot_handler: Option<ScreenshotHandler>,
+    /// How carets blink, see [`Self::set_caret_blink`].
+    caret_blink: Option<CaretBlink>,
+    /// Whether the application shows a caret, see [`Self::set_caret_active`].
+    caret_active: bool,
+    /// The time in seconds the blink phase was restarted with a visible caret.
+    caret_blink_start: f64,
+    /// The time the caret blinks next, at which the window is redrawn.
+    caret_blink_due: Option<Instant>,
+    /// The time the next transient shapes start to fade out, at which the window is redrawn.
+    transient_fade_due: Option<Instant>,
 }
 
 #[must_use]
@@ -151,84 +418,70 @@ pub enum ControlFlow {
 
 impl<'window> WindowRenderer<'window> {
     pub fn font_system(&self) -> &Arc<Mutex<FontSystem>> {
-        &self.font_system
+        self.fonts.font_system()
+    }
+
+    /// The fonts shared with the renderer, see [`FontService`].
+    pub fn fonts(&self) -> &FontService {
+        &self.fonts
     }
 
     async fn new(
         window: &ShellWindow,
-        font_system: Arc<Mutex<FontSystem>>,
+        fonts: FontService,
         camera: Camera,
         // TODO: use a rect here to be able to position the renderer!
         initial_size: PhysicalSize<u32>,
+        options: RendererOptions,
     ) -> Result<(WindowRenderer, Director)> {
         let instance_and_surface = WindowRenderer::create_instance_and_surface(
-            InstanceDescriptor::default(),
-            // Use this for testing webgl:
-            // InstanceDescriptor {
-            //     backends: wgpu::Backends::GL,
-            //     ..InstanceDescriptor::default()
-            // },
+            // Use `RendererOptions::backends` with `wgpu::Backends::GL` for testing webgl.
+            options.instance_descriptor(),
             &window.window,
         );
-        // On wasm, attempt to fall back to webgl
+        // On wasm, attempt to fall back to webgl, if the backends were not restricted.
         #[cfg(target_arch = "wasm32")]
         let instance_and_surface = match instance_and_surface {
-            Ok(_) => instance_and_surface,
-            Err(_) => Self::create_instance_and_surface(
+            Err(_) if options.backends.is_none() => Self::create_instance_and_surface(
                 InstanceDescriptor {
                     backends: wgpu::Backends::GL,
                     ..InstanceDescriptor::default()
                 },
                 &window.window,
             ),
+            _ => instance_and_surface,
         };
         let (instance, surface) = instance_and_surface?;
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::None,
-                // Be sure the adapter can present the surface.
-                compatible_surface: Some(&surface),
-                // software fallback?
-                force_fallback_adapter: false,
-            })
-            .await
-            .expect("Adapter not found");
+        let adapter = options.select_adapter(&instance, &surface).await?;
 
-        info!("Effective WebGPU backend: {:?}", adapter.get_info().backend);
+        let adapter_info = adapter.get_info();
+        info!(
+            "Selected adapter: {:?} ({:?}), effective WebGPU backend: {:?}",
+            adapter_info.name, adapter_info.device_type, adapter_info.backend
+        );
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
+                    required_features: options.required_features(&adapter),
                     // May be wrong, see: <https://github.com/gfx-rs/wgpu/blob/1144b065c4784d769d59da2f58f5aa13212627b0/examples/src/hello_triangle/mod.rs#L33-L34>
                     required_limits: adapter.limits(),
                     label: None,
                 },
                 None, // Trace path
             )
-            .await
-            .unwrap();
+            .await?;
 
         let surface_caps = surface.get_capabilities(&adapter);
 
-        // Don't use srgb now, colors are specified in linear rgb space.
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .find(|f| !f.is_srgb())
-            .unwrap_or(&surface_caps.formats[0]);
+        let surface_format = options.select_surface_format(&surface_caps, device.features())?;
 
         info!("Surface format: {:?}", surface_format);
 
-        let present_mode = surface_caps
-            .present_modes
-            .iter()
-            .copied()
-            .find(|f| *f == PresentMode::Immediate)
-            .unwrap_or(surface_caps.present_modes[0]);
+        let present_mode = options.select_present_mode(&surface_caps);
 
-        let alpha_mode = surface_caps.alpha_modes[0];
+        let alpha_mode = options.select_alpha_mode(&surface_caps)?;
 
         info!(
             "Selecting present mode {:?}, alpha mode: {:?}, initial size: {:?}",
@@ -237,26 +490,76 @@ impl<'window> WindowRenderer<'window> {
 
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: *surface_format,
+            format: surface_format,
             width: initial_size.width,
             height: initial_size.height,
             present_mode,
-            // TODO: Select this explicitly
             alpha_mode,
             view_formats: vec![],
             desired_maximum_frame_latency: DESIRED_MAXIMUM_FRAME_LATENCY,
         };
         surface.configure(&device, &surface_config);
-        let renderer = Renderer::new(device, queue, surface, surface_config);
+        #[allow(unused_mut)]
+        let mut renderer = Renderer::new(device, queue, surface, surface_config);
+        renderer.set_text_rendering(options.text_rendering.unwrap_or_else(native_text_rendering));
+        renderer.set_mask_atlas_format(
+            options
+                .mask_atlas_format
+                .unwrap_or_else(|| MaskAtlasFormat::for_backend(adapter_info.backend)),
+        );
+        renderer.set_upload_submission(
+            options
+                .upload_submission
+                .unwrap_or_else(|| UploadSubmission::for_backend(adapter_info.backend)),
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &options.glyph_cache {
+            renderer.open_glyph_cache(path.clone())?;
+        }
 
         let scene_changes = Rc::new(RefCell::new(Vec::new()));
 
         let window_renderer = WindowRenderer {
             window,
-            font_system,
-            camera,
+            fonts,
+            camera: CameraInterpolator::new(camera),
             scene_changes: scene_changes.clone(),
             renderer,
+            instance,
+            adapter,
+            quality_controller: None,
+            present_watchdog: Some(PresentWatchdog::default()),
+            skip_idle_frames: true,
+            idle: false,
+            glyph_prefetch: None,
+            transient_shapes: TransientShapes::default(),
+            cameras: HashMap::new(),
+            active_camera: None,
+            eye_matrices: None,
+            views: None,
+            time_origin: now_seconds(),
+            minimized_at: None,
+            refresh_rate: window.refresh_rate(),
+            animation_rate: None,
+            render_error_handler: Box::new(|e| error!("{e}")),
+            fullscreen: window.fullscreen(),
+            fullscreen_handler: None,
+            power_saving: options.power_saving,
+            focused: window.has_focus(),
+            focus_handler: None,
+            last_input: now_seconds(),
+            last_frame: None,
+            deferred_redraw: None,
+            shortcuts: ShortcutMap::default(),
+            modifiers: ModifiersState::empty(),
+            triggered_shortcut: None,
+            screenshot_options: ScreenshotOptions::default(),
+            screenshot_handler: None,
+            caret_blink: Some(CaretBlink::default()),
+            caret_active: false,
+            caret_blink_start: now_seconds(),
+            caret_blink_due: None,
+            transient_fade_due: None,
         };
 
         let window = window.window.clone();
@@ -282,24 +585,900 @@ impl<'window> WindowRenderer<'window> {
     // DI: If the renderer does culling, we need to move the camera (or at least the view matrix) into the renderer, and
     // perhaps schedule updates using the director.
     pub fn update_camera(&mut self, camera: Camera) {
-        self.camera = camera;
+        self.camera.set(camera);
+        self.window.request_redraw();
+    }
+
+    /// Animate the camera so that it reaches `camera` at `at`.
+    ///
+    /// The camera is interpolated for every frame until the target is reached, independent of
+    /// how often the application submits new targets. Submitting a target replaces all targets
+    /// scheduled at or after `at`.
+    ///
+    /// Not supported on wasm, because there is no [`Instant`] available.
+    pub fn animate_camera(&mut self, camera: Camera, at: Instant) {
+        self.camera.push_target(Instant::now(), camera, at);
+        self.window.request_redraw();
+    }
+
+    /// Define or replace the camera `name`, for example an overview and a detail camera. If it's
+    /// the active camera, the view moves to it immediately.
+    pub fn define_camera(&mut self, name: impl Into<String>, camera: Camera) {
+        let name = name.into();
+        if self.active_camera.as_ref() == Some(&name) {
+            self.update_camera(camera);
+        }
+        self.cameras.insert(name, camera);
+    }
+
+    /// Remove the camera `name`. The view stays where it is.
+    pub fn remove_camera(&mut self, name: &str) -> Option<Camera> {
+        if self.active_camera.as_deref() == Some(name) {
+            self.active_camera = None;
+        }
+        self.cameras.remove(name)
+    }
+
+    pub fn named_camera(&self, name: &str) -> Option<Camera> {
+        self.cameras.get(name).copied()
+    }
+
+    /// The name of the camera the view was switched to last, `None` if the view was not switched
+    /// or the camera was removed.
+    pub fn active_camera(&self) -> Option<&str> {
+        self.active_camera.as_deref()
+    }
+
+    /// Switch the view to the camera `name` with the next frame. If `animation` is set, the
+    /// camera is animated for that duration instead.
+    ///
+    /// The previously active camera is set to where the view is or is heading to, so that
+    /// switching back returns to where the user left it. Returns `false` if there is no camera
+    /// `name`.
+    pub fn switch_camera(&mut self, name: &str, animation: Option<Duration>) -> bool {
+        let Some(camera) = self.cameras.get(name).copied() else {
+            return false;
+        };
+        let current = self.camera.final_camera();
+        if let Some(active) = self.active_camera.take() {
+            if let Some(active) = self.cameras.get_mut(&active) {
+                *active = current;
+            }
+        }
+        self.active_camera = Some(name.to_string());
+        self.move_camera(camera, animation);
+        true
+    }
+
+    /// The current time in seconds since the renderer was created.
+    ///
+    /// This is the time base of the reveal animations of glyph runs. The renderer is set to it
+    /// with every frame, so use it to compute the start times of reveals.
+    ///
+    /// The time does not advance while the window is minimized.
+    ///
+    /// With an animation rate, the time advances in steps of the animation interval, see
+    /// [`Self::set_animation_rate`].
+    pub fn time(&self) -> f32 {
+        let now = self.minimized_at.unwrap_or_else(now_seconds);
+        let time = now - self.time_origin;
+        match self.animation_interval() {
+            Some(interval) => ((time / interval).floor() * interval) as f32,
+            None => time as f32,
+        }
+    }
+
+    /// The refresh rate of the monitor the window is on in Hz, `None` if it is unknown.
+    ///
+    /// This is updated when the window moves to another monitor.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.refresh_rate
+    }
+
+    /// The duration of one frame of the monitor the window is on.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        self.refresh_rate
+            .map(|refresh_rate| Duration::from_secs_f64(1.0 / refresh_rate))
+    }
+
+    /// Advance the animations of the renderer at a fixed rate in Hz, or continuously with
+    /// `None`.
+    ///
+    /// The rate is aligned to the refresh rate, so that every animation step is visible for the
+    /// same number of frames. For example, on a 144 Hz monitor, a rate of 60 Hz becomes 72 Hz.
+    pub fn set_animation_rate(&mut self, rate: Option<f64>) {
+        self.animation_rate = rate.filter(|rate| *rate > 0.0);
+        self.window.request_redraw();
+    }
+
+    /// The interval in seconds the animations advance in, aligned to the refresh rate.
+    pub fn animation_interval(&self) -> Option<f64> {
+        let rate = self.animation_rate?;
+        Some(match self.refresh_rate {
+            Some(refresh_rate) => {
+                let frames = (refresh_rate / rate).round().max(1.0);
+                frames / refresh_rate
+            }
+            None => 1.0 / rate,
+        })
+    }
+
+    /// Query the refresh rate again, the window may have been moved to another monitor.
+    fn update_refresh_rate(&mut self) {
+        let refresh_rate = self.window.refresh_rate();
+        if refresh_rate != self.refresh_rate {
+            info!("Refresh rate changed: {refresh_rate:?} Hz");
+            self.refresh_rate = refresh_rate;
+            self.window.request_redraw();
+        }
+    }
+
+    /// `true` if the window is minimized or has no area. Then rendering is skipped.
+    pub fn is_minimized(&self) -> bool {
+        self.minimized_at.is_some()
+    }
+
+    /// Update the minimized state and the surface size after the window changed.
+    ///
+    /// The surface is not resized while the window is minimized, so that the prepared scene stays
+    /// valid and is rendered again as soon as the window is restored.
+    fn update_window_state(&mut self) {
+        let size = self.window.inner_size();
+        let minimized =
+            size.width == 0 || size.height == 0 || self.window.window.is_minimized() == Some(true);
+
+        match (self.minimized_at, minimized) {
+            (None, true) => {
+                info!("Window minimized, rendering is paused");
+                self.minimized_at = Some(now_seconds());
+            }
+            (Some(minimized_at), false) => {
+                info!("Window restored, rendering is resumed");
+                // Continue the animations where they stopped.
+                self.time_origin += now_seconds() - minimized_at;
+                self.minimized_at = None;
+            }
+            _ => {}
+        }
+
+        if !minimized {
+            self.renderer.resize_surface((size.width, size.height));
+            self.window.request_redraw();
+        }
+    }
+
+    /// The current camera.
+    pub fn camera(&self) -> Camera {
+        self.camera.camera()
+    }
+
+    /// The physical pixels a world unit covers at the target of the camera, for example to pick
+    /// the level of a [`crate::SemanticZoom`].
+    pub fn zoom_scale(&self) -> scalar {
+        let (_, height) = self.surface_size();
+        self.camera().pixels_per_unit(height as scalar)
+    }
+
+    /// The part of the plane at `z = 0` that is visible through the camera, in world units.
+    ///
+    /// `None` if the plane does not fill the view, for example because it is seen up to the
+    /// horizon.
+    pub fn visible_bounds(&self) -> Option<Bounds3> {
+        self.camera().visible_bounds(0.0, self.aspect_ratio())
+    }
+
+    /// The ids of the visible shapes whose bounds are in view of the camera.
+    ///
+    /// Applies the pending changes first.
+    pub fn shapes_in_view(&mut self) -> Result<Vec<Id>> {
+        self.apply_pending_changes()?;
+        let view_projection_matrix = self
+            .camera()
+            .view_projection_matrix(Z_RANGE, self.surface_size());
+        Ok(self.renderer.shapes_in_view(&view_projection_matrix))
+    }
+
+    /// The state of the view to restore later, with the camera the current animation ends at.
+    ///
+    /// Add the scroll offsets of the application's views with [`ViewState::with_scroll`].
+    pub fn view_state(&self) -> ViewState {
+        ViewState::new(self.camera.final_camera())
+    }
+
+    /// Restore the camera of a view state. If `animation` is set, the camera is animated for
+    /// that duration.
+    ///
+    /// Scroll offsets are restored with [`ViewState::restore_scroll`].
+    pub fn restore_view_state(&mut self, state: &ViewState, animation: Option<Duration>) {
+        self.move_camera(state.camera, animation);
+    }
+
+    /// Move the camera so that all visible shapes of the scene are in view.
+    ///
+    /// `margin` is in world units. If `animation` is set, the camera is animated for that
+    /// duration. Returns `false` and leaves the camera unchanged if the scene is empty.
+    ///
+    /// The bounds are those of the scene the renderer applied last. To include changes that were
+    /// pushed since, invoke [`Self::apply_pending_changes`] before.
+    pub fn fit_scene(&mut self, margin: scalar, animation: Option<Duration>) -> bool {
+        let Some(bounds) = self.renderer.scene_bounds() else {
+            return false;
+        };
+        let camera = self
+            .camera()
+            .fit_bounds(&bounds, self.aspect_ratio(), margin);
+        self.move_camera(camera, animation);
+        true
+    }
+
+    /// Move the camera so that the given shapes are in view.
+    ///
+    /// Shapes that are invisible or empty are ignored. `margin` is in world units. If `animation`
+    /// is set, the camera is animated for that duration. Returns `false` and leaves the camera
+    /// unchanged if there is nothing to fit.
+    ///
+    /// Like [`Self::fit_scene`], this uses the bounds of the scene the renderer applied last.
+    pub fn fit_shapes(
+        &mut self,
+        shapes: &[Id],
+        margin: scalar,
+        animation: Option<Duration>,
+    ) -> bool {
+        let Some(bounds) = shapes
+            .iter()
+            .filter_map(|id| self.renderer.shape_bounds(*id))
+            .reduce(|a, b| a.join(&b))
+        else {
+            return false;
+        };
+        let camera = self
+            .camera()
+            .fit_bounds(&bounds, self.aspect_ratio(), margin);
+        self.move_camera(camera, animation);
+        true
+    }
+
+    /// Move the camera parallel to its view plane so that `bounds` become visible.
+    ///
+    /// `bounds` and `margin` are in world units. If the bounds don't fit, the camera is moved
+    /// back. If `animation` is set, the camera is animated for that duration.
+    pub fn scroll_into_view(
+        &mut self,
+        bounds: &Bounds3,
+        margin: scalar,
+        animation: Option<Duration>,
+    ) {
+        let camera = self
+            .camera()
+            .scroll_into_view(bounds, self.aspect_ratio(), margin);
+        self.move_camera(camera, animation);
+    }
+
+    /// Zoom the camera by `scale` and keep the content under `focal` in place.
+    ///
+    /// `focal` is in physical pixels relative to the window, for example the center of a pinch
+    /// gesture or the cursor position of a [`WindowEvent::PinchGesture`]. A `scale` above 1 zooms
+    /// in.
+    pub fn zoom_at(&mut self, focal: (f64, f64), scale: scalar) {
+        let (width, height) = self.surface_size();
+        let ndc = (
+            focal.0 / width as scalar * 2.0 - 1.0,
+            1.0 - focal.1 / height as scalar * 2.0,
+        );
+        let camera = self.camera().zoom_at(ndc, scale, self.aspect_ratio());
+        self.update_camera(camera);
+    }
+
+    fn move_camera(&mut self, camera: Camera, animation: Option<Duration>) {
+        match animation {
+            Some(duration) => self.animate_camera(camera, Instant::now() + duration),
+            None => self.update_camera(camera),
+        }
+    }
+
+    /// Queue scene changes that were not produced by this renderer's director, for example
+    /// changes received from another process. They are applied with the next redraw.
+    pub fn push_scene_changes(&mut self, changes: impl IntoIterator<Item = SceneChange>) {
+        self.scene_changes.borrow_mut().extend(changes);
+        self.window.request_redraw();
+    }
+
+    /// Show `shapes` for `ttl`, then fade them out over `fade` and drop them, for example for
+    /// toast notifications.
+    ///
+    /// The shapes are faded by the uniforms of the layer of `position`, so `position` must not be
+    /// shared with other shapes, and the application should not keep other handles to the shapes
+    /// or the position. The time to live is measured in [`Self::time`], so it does not run out
+    /// while the window is minimized.
+    ///
+    /// Dropped shapes are removed from the renderer with the next [`Director::action`]. The window
+    /// is redrawn when the shapes start to fade out, and continuously while they fade.
+    pub fn expire(
+        &mut self,
+        position: Handle<Position>,
+        shapes: impl IntoIterator<Item = Handle<PositionedShape>>,
+        ttl: Duration,
+        fade: Duration,
+    ) {
+        let now = self.time();
+        self.transient_shapes.push(position, shapes, now, ttl, fade);
+        self.window.request_redraw();
+    }
+
+    /// Remove everything from the scene, including the changes that were not applied yet.
+    pub fn reset_scene(&mut self) -> Result<()> {
+        self.scene_changes.borrow_mut().clear();
+        self.renderer.bootstrap_changes(&mut self.fonts, [])?;
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Render all glyph runs with the fonts that replace removed fonts.
+    ///
+    /// See [`Renderer::replace_fonts`] and [`crate::FontReloader`].
+    pub fn replace_fonts(&mut self, replacements: &HashMap<fontdb::ID, fontdb::ID>) -> Result<()> {
+        // The replaced fonts are removed from the font database.
+        self.fonts.forget_fonts(replacements.keys());
+        self.renderer.replace_fonts(&mut self.fonts, replacements)?;
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Apply the scene changes that were not rendered yet, so that the renderer's scene is up to
+    /// date.
+    ///
+    /// This happens with every redraw. Invoke it directly to query the bounds of shapes that were
+    /// just pushed, for example before [`Self::fit_scene`].
+    pub fn apply_pending_changes(&mut self) -> Result<(), RenderError> {
+        let changes = self.scene_changes.take();
+        self.transient_shapes.applied(&mut self.renderer, &changes);
+        self.renderer.apply_changes(&mut self.fonts, changes)
+    }
+
+    /// Set the handler that receives the errors of the render path the renderer recovers from.
+    ///
+    /// These errors don't end the event loop, the next frame is rendered as usual. By default,
+    /// they are logged. Errors that are not recoverable are returned from
+    /// [`ApplicationContext::wait_for_event`].
+    ///
+    /// When the device runs out of memory, the handler receives the error before the renderer
+    /// releases its caches and renders the frame again, so that the application can release
+    /// memory, too. If the frame fails again, the error is returned.
+    pub fn set_render_error_handler(&mut self, handler: impl FnMut(&RenderError) + 'static) {
+        self.render_error_handler = Box::new(handler);
+    }
+
+    /// Set the handler that is invoked when the window enters or leaves fullscreen.
+    ///
+    /// This includes changes the user makes with the window manager. The handler is invoked after
+    /// the surface was resized.
+    pub fn set_fullscreen_handler(&mut self, handler: impl FnMut(Option<&Fullscreen>) + 'static) {
+        self.fullscreen_handler = Some(Box::new(handler));
+    }
+
+    /// Set the handler that is invoked when the window gains or loses the keyboard focus.
+    pub fn set_focus_handler(&mut self, handler: impl FnMut(bool) + 'static) {
+        self.focus_handler = Some(Box::new(handler));
+    }
+
+    /// Whether the window has the keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Enable the power saving mode, `None` disables it.
+    ///
+    /// While the window is unfocused or receives no input, it is redrawn at a lower rate and the
+    /// glyph atlases are not repacked. Animations keep their timing, they just advance in larger
+    /// steps. See [`PowerSaving`] for the settings.
+    pub fn set_power_saving(&mut self, power_saving: Option<PowerSaving>) {
+        if let Some(caps) = self.renderer.surface_capabilities(&self.adapter) {
+            let present_mode = select_present_mode(power_saving.as_ref(), &caps);
+            info!("Selecting present mode {present_mode:?}");
+            self.renderer.set_present_mode(present_mode);
+        }
+        self.power_saving = power_saving;
+        self.resume_deferred_redraw();
+    }
+
+    pub fn power_saving(&self) -> Option<&PowerSaving> {
+        self.power_saving.as_ref()
+    }
+
+    /// Whether the power saving mode currently lowers the redraw rate, because the window is
+    /// unfocused or did not receive input for a while.
+    pub fn is_in_background(&self) -> bool {
+        let Some(power_saving) = &self.power_saving else {
+            return false;
+        };
+        !self.focused
+            || power_saving.inactive_after.is_some_and(|inactive_after| {
+                now_seconds() - self.last_input >= inactive_after.as_secs_f64()
+            })
+    }
+
+    /// The time the power saving mode deferred the next redraw to.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn deferred_redraw(&self) -> Option<Instant> {
+        self.deferred_redraw
+    }
+
+    /// Redraw now if the power saving mode deferred a redraw.
+    fn resume_deferred_redraw(&mut self) {
+        if self.deferred_redraw.take().is_some() {
+            self.window.request_redraw();
+        }
+    }
+
+    /// Defer the redraw if the power saving mode limits the redraw rate and the last frame was
+    /// rendered too recently. Returns `true` if the redraw was deferred.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn defer_redraw(&mut self, now: Instant) -> bool {
+        self.deferred_redraw = None;
+        if !self.is_in_background() {
+            return false;
+        }
+        let (Some(power_saving), Some(last_frame)) = (&self.power_saving, self.last_frame) else {
+            return false;
+        };
+        let due = last_frame + power_saving.background_interval();
+        if now >= due {
+            return false;
+        }
+        self.deferred_redraw = Some(due);
+        true
+    }
+
+    /// Replace the keyboard shortcuts. By default, the built-in shell actions are bound, see
+    /// [`ShortcutMap`].
+    ///
+    /// Key events that trigger shell actions the shell performs are not returned from
+    /// [`ApplicationContext::wait_for_event`]. All other key events are, and
+    /// [`Self::triggered_shortcut`] tells which shortcut they triggered.
+    pub fn set_shortcuts(&mut self, shortcuts: ShortcutMap) {
+        self.shortcuts = shortcuts;
+    }
+
+    pub fn shortcuts(&self) -> &ShortcutMap {
+        &self.shortcuts
+    }
+
+    pub fn shortcuts_mut(&mut self) -> &mut ShortcutMap {
+        &mut self.shortcuts
+    }
+
+    /// The shortcut the event [`ApplicationContext::wait_for_event`] returned last triggered.
+    pub fn triggered_shortcut(&self) -> Option<&Shortcut> {
+        self.triggered_shortcut.as_ref()
+    }
+
+    /// Perform a shell action. Returns `false` if the shell does not implement it, so that the
+    /// key event that triggered it is forwarded to the application.
+    fn perform_shell_action(&mut self, action: ShellAction) -> bool {
+        info!("Performing shell action {action:?}");
+        match action {
+            // Handled in `ApplicationContext::wait_for_event`.
+            ShellAction::Quit | ShellAction::Screenshot => true,
+            ShellAction::ToggleFullscreen => {
+                self.window.toggle_fullscreen();
+                true
+            }
+            ShellAction::ToggleStatsOverlay => false,
+        }
+    }
+
+    /// Change how screenshots are taken, see [`ApplicationContext::take_screenshot`].
+    pub fn set_screenshot_options(&mut self, options: ScreenshotOptions) {
+        self.screenshot_options = options;
+    }
+
+    pub fn screenshot_options(&self) -> &ScreenshotOptions {
+        &self.screenshot_options
+    }
+
+    /// Receive the screenshots that are taken, including the ones taken with the screenshot
+    /// shortcut. In the browser, this is the only way to get the frames of the latter.
+    pub fn set_screenshot_handler(&mut self, handler: impl FnMut(&Screenshot) + 'static) {
+        self.screenshot_handler = Some(Box::new(handler));
+    }
+
+    /// Change how carets blink, `None` shows them steadily. By default, they blink as configured
+    /// by [`CaretBlink::default`].
+    pub fn set_caret_blink(&mut self, blink: Option<CaretBlink>) {
+        self.caret_blink = blink;
+        self.restart_caret_blink();
+    }
+
+    pub fn caret_blink(&self) -> Option<&CaretBlink> {
+        self.caret_blink.as_ref()
+    }
+
+    /// Tell the shell whether the application shows a caret, for example while a text field is
+    /// focused. Only then, the window wakes up to redraw when the caret blinks.
+    pub fn set_caret_active(&mut self, active: bool) {
+        if active != self.caret_active {
+            self.caret_active = active;
+            self.restart_caret_blink();
+        }
+    }
+
+    /// Show the caret and restart blinking.
+    ///
+    /// Key presses, mouse buttons, and input method events restart it. Call this when the caret
+    /// is moved in other ways.
+    pub fn restart_caret_blink(&mut self) {
+        self.caret_blink_start = now_seconds();
+    }
+
+    /// Whether the caret is in the visible phase of its blinking.
+    ///
+    /// Query this when updating the shapes after [`ApplicationContext::wait_for_event`] returned,
+    /// which returns [`WindowEvent::RedrawRequested`] whenever the phase changes. Carets that
+    /// don't blink, because they are not active, the window is not focused, blinking stopped, or
+    /// the shell runs in the browser, are always visible.
+    pub fn caret_visible(&self) -> bool {
+        match self.blinking_caret() {
+            Some(blink) => blink.is_visible(self.caret_blink_elapsed()),
+            None => true,
+        }
+    }
+
+    /// How the caret blinks, `None` if it does not blink right now.
+    fn blinking_caret(&self) -> Option<&CaretBlink> {
+        // The browser build has no timer to wake the event loop up with.
+        if cfg!(target_arch = "wasm32") || !self.caret_active || !self.focused {
+            return None;
+        }
+        self.caret_blink.as_ref()
+    }
+
+    fn caret_blink_elapsed(&self) -> Duration {
+        Duration::from_secs_f64((now_seconds() - self.caret_blink_start).max(0.0))
+    }
+
+    /// Schedule a redraw for the next time the caret blinks and return the time.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn schedule_caret_blink(&mut self) -> Option<Instant> {
+        let elapsed = self.caret_blink_elapsed();
+        self.caret_blink_due = self
+            .blinking_caret()
+            .and_then(|blink| blink.next_change(elapsed))
+            .map(|next| Instant::now() + next.saturating_sub(elapsed));
+        self.caret_blink_due
+    }
+
+    /// Redraw if the caret blinked since the redraw was scheduled.
+    fn resume_caret_blink(&mut self) {
+        if self
+            .caret_blink_due
+            .is_some_and(|due| Instant::now() >= due)
+        {
+            self.caret_blink_due = None;
+            self.window.request_redraw();
+        }
+    }
+
+    /// Schedule a redraw for the time the next transient shapes start to fade out and return the
+    /// time.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn schedule_transient_fade(&mut self) -> Option<Instant> {
+        let now = self.time();
+        // The time does not advance while the window is minimized.
+        self.transient_fade_due = match self.minimized_at {
+            Some(_) => None,
+            None => self
+                .transient_shapes
+                .next_fade_start(now)
+                .map(|start| Instant::now() + Duration::from_secs_f32(start - now)),
+        };
+        self.transient_fade_due
+    }
+
+    /// Redraw if transient shapes started to fade out since the redraw was scheduled.
+    fn resume_transient_fade(&mut self) {
+        if self
+            .transient_fade_due
+            .is_some_and(|due| Instant::now() >= due)
+        {
+            self.transient_fade_due = None;
+            self.window.request_redraw();
+        }
+    }
+
+    /// Render the current view into a buffer.
+    fn capture_frame(&mut self) -> Result<FrameCapture> {
+        let views = self.views(self.camera.camera(), self.renderer.surface_size());
+        self.renderer.capture_frame(&views)
+    }
+
+    /// Flash the window and pass the screenshot to the handler.
+    fn screenshot_taken(&mut self, screenshot: &Screenshot) {
+        if let Some(color) = self.screenshot_options.flash {
+            self.renderer
+                .flash(color, self.screenshot_options.flash_duration);
+            self.window.request_redraw();
+        }
+        if let Some(handler) = &mut self.screenshot_handler {
+            handler(screenshot);
+        }
+    }
+
+    /// Detect fullscreen changes. Windows enter and leave fullscreen asynchronously, but are
+    /// always resized then.
+    fn update_fullscreen(&mut self) {
+        let fullscreen = self.window.fullscreen();
+        if fullscreen == self.fullscreen {
+            return;
+        }
+        info!("Fullscreen changed: {fullscreen:?}");
+        self.fullscreen = fullscreen;
+        if let Some(handler) = &mut self.fullscreen_handler {
+            handler(self.fullscreen.as_ref());
+        }
+    }
+
+    fn report_render_error(&mut self, e: RenderError) -> Result<()> {
+        if !e.is_recoverable() {
+            return Err(e.into());
+        }
+        (self.render_error_handler)(&e);
+        Ok(())
+    }
+
+    /// Release the memory the renderer can recreate and render the frame again. Transient out of
+    /// memory conditions are common on integrated GPUs.
+    ///
+    /// If the frame fails again with out of memory, the error ends the application.
+    fn recover_from_out_of_memory(&mut self, e: RenderError, views: &[View]) -> Result<()> {
+        warn!("{e}, releasing memory and rendering the frame again");
+        (self.render_error_handler)(&e);
+        self.renderer.release_memory();
+        if let Err(e) = self.apply_pending_changes() {
+            self.report_render_error(e)?;
+        }
+        if self.renderer.is_preparation_pending() {
+            self.window.request_redraw();
+        }
+        if let Err(e) = self.renderer.render_views_and_present(views) {
+            self.report_render_error(e)?;
+        }
+        Ok(())
+    }
+
+    /// Report the stall and replace the surface with a new one.
+    fn recover_from_present_stall(&mut self, stall: RenderError) -> Result<()> {
+        self.report_render_error(stall)?;
+        // The old surface must be dropped before a new one is created for the same window.
+        self.detach_surface();
+        self.attach_surface()
+    }
+
+    fn aspect_ratio(&self) -> scalar {
+        let (width, height) = self.surface_size();
+        width as scalar / height as scalar
+    }
+
+    /// Enable stereo rendering with externally supplied view projection matrices for the left
+    /// and the right eye.
+    ///
+    /// The eyes are rendered side by side, each into one half of the surface. While stereo
+    /// rendering is enabled, the camera is not used. `None` disables stereo rendering.
+    pub fn set_eye_matrices(&mut self, eye_matrices: Option<[Matrix4; 2]>) {
+        self.eye_matrices = eye_matrices;
+        self.window.request_redraw();
+    }
+
+    /// Render the scene through multiple views, for example one for each pane of a split editor.
+    ///
+    /// Each view is rendered into its viewport with its own view projection matrix, and shows
+    /// the whole scene or only the part below its root. While views are set, the camera is not
+    /// used. Stereo rendering takes precedence. `None` renders through the camera again.
+    pub fn set_views(&mut self, views: Option<Vec<View>>) {
+        self.views = views;
+        self.window.request_redraw();
+    }
+
+    /// The views to render with `camera`, see [`Self::set_views`].
+    fn views(&self, camera: Camera, surface_size: (u32, u32)) -> Vec<View> {
+        match (self.eye_matrices, &self.views) {
+            (Some(eye_matrices), _) => {
+                Self::side_by_side_views(eye_matrices, surface_size).to_vec()
+            }
+            (None, Some(views)) => views.clone(),
+            (None, None) => vec![View::new(
+                camera.view_projection_matrix(Z_RANGE, surface_size),
+            )],
+        }
+    }
+
+    fn side_by_side_views(eye_matrices: [Matrix4; 2], surface_size: (u32, u32)) -> [View; 2] {
+        let (width, height) = surface_size;
+        let half_width = width as f32 / 2.0;
+        let [left, right] = eye_matrices;
+        [
+            View::new(left).with_viewport(Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: half_width,
+                height: height as f32,
+            }),
+            View::new(right).with_viewport(Viewport {
+                x: half_width,
+                y: 0.0,
+                width: half_width,
+                height: height as f32,
+            }),
+        ]
+    }
+
+    /// Renegotiate the surface format and alpha mode.
+    ///
+    /// Only the `surface_format` and `alpha_mode` of the options are used. Unset values are
+    /// selected automatically, just like when the renderer was created.
+    pub fn renegotiate_surface(&mut self, options: &RendererOptions) -> Result<()> {
+        let Some(caps) = self.renderer.surface_capabilities(&self.adapter) else {
+            bail!("No surface attached");
+        };
+        let format = options.select_surface_format(&caps, self.renderer.device.features())?;
+        let alpha_mode = options.select_alpha_mode(&caps)?;
+        info!("Renegotiated surface format: {format:?}, alpha mode: {alpha_mode:?}");
+        self.renderer.reconfigure_surface_format(format, alpha_mode);
         self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Detach the surface from the window.
+    ///
+    /// Use this when the platform destroys the native window's surface, for example when an
+    /// Android application gets suspended. The scene and all other GPU resources are kept and
+    /// scene changes are still applied. Until a surface is attached again, nothing is rendered.
+    pub fn detach_surface(&mut self) {
+        if self.renderer.detach_surface().is_some() {
+            info!("Detached surface");
+        }
+    }
+
+    /// Create a new surface for the window and attach it.
+    ///
+    /// If the current surface format is not supported by the new surface, format and alpha mode
+    /// are renegotiated.
+    pub fn attach_surface(&mut self) -> Result<()> {
+        let surface = self.instance.create_surface(&*self.window.window)?;
+        let caps = surface.get_capabilities(&self.adapter);
+        let size = self.window.inner_size();
+
+        self.renderer.attach_surface(surface);
+        self.renderer.resize_surface((size.width, size.height));
+
+        if !caps.formats.contains(&self.renderer.surface_config.format) {
+            self.renegotiate_surface(&RendererOptions::default())?;
+        }
+
+        info!("Attached surface");
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Enable adaptive quality.
+    ///
+    /// Frame times are measured and the renderer's quality is adjusted according to the policy.
+    /// `None` disables adaptive quality and restores the best quality.
+    ///
+    /// Frame times are not measured on wasm, so there this has no effect.
+    pub fn set_quality_policy(&mut self, policy: Option<QualityPolicy>) {
+        self.quality_controller = policy.map(QualityController::new);
+        let quality = self
+            .quality_controller
+            .as_ref()
+            .map(|controller| controller.quality())
+            .unwrap_or_default();
+        self.renderer.set_quality(quality);
+        self.window.request_redraw();
+    }
+
+    /// Render the scene at the surface size scaled by `scale`, see [`Renderer::set_render_scale`].
+    pub fn set_render_scale(&mut self, scale: f64) {
+        self.renderer.set_render_scale(scale);
+        self.window.request_redraw();
+    }
+
+    pub fn render_scale(&self) -> f64 {
+        self.renderer.render_scale()
+    }
+
+    /// Skip redraws that would present the same frame as the last one, because neither the
+    /// scene, nor the camera, nor the settings changed, and nothing is animating. Enabled by
+    /// default.
+    ///
+    /// Disable this if the platform does not keep the contents of the window between redraws.
+    pub fn set_skip_idle_frames(&mut self, skip: bool) {
+        self.skip_idle_frames = skip;
+        self.window.request_redraw();
+    }
+
+    /// Whether the last redraw was skipped, because it would have presented the same frame, see
+    /// [`Self::set_skip_idle_frames`].
+    ///
+    /// Applications can use this to throttle their own work while nothing changes.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Set the watchdog that detects stalled presentation, `None` disables it.
+    ///
+    /// When a frame takes too long or times out repeatedly, the incident is passed to the render
+    /// error handler and the surface is recreated. By default, [`PresentWatchdog::default`] is
+    /// used.
+    ///
+    /// Frame times are not measured on wasm, so there only timeouts are detected.
+    pub fn set_present_watchdog(&mut self, watchdog: Option<PresentWatchdog>) {
+        self.present_watchdog = watchdog;
+    }
+
+    /// The number of stalled presentations detected so far.
+    pub fn present_stalls(&self) -> usize {
+        self.present_watchdog
+            .as_ref()
+            .map(|watchdog| watchdog.stalls())
+            .unwrap_or_default()
     }
 
     fn handle_window_event(&mut self, window_event: &WindowEvent) -> Result<()> {
+        self.triggered_shortcut = None;
         match window_event {
-            WindowEvent::Resized(physical_size) => {
+            WindowEvent::Resized(_) => {
                 info!("{:?}", window_event);
-                self.renderer
-                    .resize_surface((physical_size.width, physical_size.height));
-                self.window.request_redraw()
+                self.update_window_state();
+                // Exclusive fullscreen may change the refresh rate.
+                self.update_refresh_rate();
+                self.update_fullscreen();
             }
+            // Moving to another monitor may change the scale factor and the refresh rate.
             WindowEvent::ScaleFactorChanged { .. } => {
-                let new_inner_size = self.window.inner_size();
-                self.renderer
-                    .resize_surface((new_inner_size.width, new_inner_size.height));
-                self.window.request_redraw()
+                self.update_window_state();
+                self.update_refresh_rate();
+            }
+            // Some platforms only report minimization as occlusion.
+            WindowEvent::Occluded(_) => {
+                self.update_window_state();
+                // The contents of the surface may be gone when it's visible again.
+                self.renderer.invalidate_frame();
+            }
+            WindowEvent::Moved(_) => {
+                self.update_refresh_rate();
             }
+            WindowEvent::Focused(focused) => {
+                self.focused = *focused;
+                if let Some(handler) = &mut self.focus_handler {
+                    handler(*focused);
+                }
+                if *focused {
+                    self.last_input = now_seconds();
+                    self.resume_deferred_redraw();
+                    self.restart_caret_blink();
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.last_input = now_seconds();
+                self.resume_deferred_redraw();
+                self.restart_caret_blink();
+                self.triggered_shortcut = self.shortcuts.triggered(event, self.modifiers).cloned();
+            }
+            // These may place the caret.
+            WindowEvent::MouseInput { .. } | WindowEvent::Touch(_) | WindowEvent::Ime(_) => {
+                self.last_input = now_seconds();
+                self.resume_deferred_redraw();
+                self.restart_caret_blink();
+            }
+            WindowEvent::MouseWheel { .. } | WindowEvent::CursorMoved { .. } => {
+                self.last_input = now_seconds();
+                self.resume_deferred_redraw();
+            }
+            // Redraws that were requested before the window was minimized.
+            WindowEvent::RedrawRequested if self.is_minimized() => {}
             WindowEvent::RedrawRequested => {
                 self.redraw()?;
             }
@@ -309,30 +1488,112 @@ impl<'window> WindowRenderer<'window> {
         Ok(())
     }
 
+    /// Prefetch the glyphs of the pending shapes the camera is going to show, so that they don't
+    /// need to be rasterized when it arrives.
+    fn prefetch_glyphs(&mut self, lookahead: Duration, surface_size: (u32, u32)) -> Result<()> {
+        if !self.camera.is_animating() || !self.renderer.is_preparation_pending() {
+            return Ok(());
+        }
+        let camera = self.camera.predict(Instant::now() + lookahead);
+        let view_projection_matrix = camera.view_projection_matrix(Z_RANGE, surface_size);
+        self.renderer
+            .prefetch_glyphs(&mut self.fonts, &view_projection_matrix)?;
+        Ok(())
+    }
+
     fn redraw(&mut self) -> Result<()> {
-        let changes = self.scene_changes.take();
+        #[cfg(not(target_arch = "wasm32"))]
+        let frame_start = Instant::now();
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.defer_redraw(frame_start) {
+            return Ok(());
+        }
+        let defer_cache_maintenance = self.is_in_background()
+            && self
+                .power_saving
+                .as_ref()
+                .is_some_and(|power_saving| power_saving.defer_cache_maintenance);
+        self.renderer
+            .set_defer_cache_maintenance(defer_cache_maintenance);
 
         let surface_size = self.renderer.surface_size();
-        let view_projection_matrix = self.camera.view_projection_matrix(Z_RANGE, surface_size);
+        let camera = if self.camera.is_animating() {
+            let camera = self.camera.advance(Instant::now());
+            if self.camera.is_animating() {
+                self.window.request_redraw();
+            }
+            camera
+        } else {
+            self.camera.camera()
+        };
+        let views = self.views(camera, surface_size);
 
+        let time = self.time();
+        self.renderer.set_time(time);
+        self.transient_shapes.advance(&mut self.renderer, time);
+        if let Err(e) = self.apply_pending_changes() {
+            self.report_render_error(e)?;
+        }
+        // Without wake-ups on the web, transient shapes need redraws until they fade out.
+        let transient_redraw = self.transient_shapes.is_fading(time)
+            || (cfg!(target_arch = "wasm32") && self.transient_shapes.is_pending());
+        if self.renderer.is_preparation_pending()
+            || self.renderer.is_animating()
+            || transient_redraw
         {
-            let mut font_system = self.font_system.lock().unwrap();
-            self.renderer.apply_changes(&mut font_system, changes)?;
+            // Continue uploading, revealing glyphs, or fading out transient shapes with the next
+            // frame.
+            self.window.request_redraw();
+        }
+        if let Some(lookahead) = self.glyph_prefetch {
+            self.prefetch_glyphs(lookahead, surface_size)?;
+        }
+
+        if self.skip_idle_frames && self.renderer.is_idle(&views) {
+            self.idle = true;
+            return Ok(());
         }
+        self.idle = false;
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let present_start = Instant::now();
         // TODO: pass primitives as value.
-        match self.renderer.render_and_present(&view_projection_matrix) {
+        let result = self.renderer.render_views_and_present(&views);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.last_frame = Some(present_start);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let present_time = Some(present_start.elapsed());
+        #[cfg(target_arch = "wasm32")]
+        let present_time = None;
+        let stall = self
+            .present_watchdog
+            .as_mut()
+            .and_then(|watchdog| watchdog.record(&result, present_time));
+
+        match result {
             Ok(_) => {}
             // Reconfigure the surface if lost
             // TODO: shouldn't we redraw here? Also, I think the renderer can do this, too.
-            Err(wgpu::SurfaceError::Lost) => {
+            Err(RenderError::Surface(wgpu::SurfaceError::Lost)) => {
                 self.renderer.reconfigure_surface();
             }
-            // The system is out of memory, we should probably quit
-            Err(wgpu::SurfaceError::OutOfMemory) => bail!("Out of memory"),
-            // All other errors (Outdated, Timeout) should be resolved by the next frame
-            Err(e) => {
-                error!("{:?}", e);
+            Err(e) if e.is_out_of_memory() => self.recover_from_out_of_memory(e, &views)?,
+            // All other errors (Outdated, Timeout) should be resolved by the next frame.
+            Err(e) => self.report_render_error(e)?,
+        }
+
+        if let Some(stall) = stall {
+            self.recover_from_present_stall(stall)?;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(controller) = &mut self.quality_controller {
+            if let Some(quality) = controller.record_frame(frame_start.elapsed()) {
+                self.renderer.set_quality(quality);
+                // Prepare the scene again with the new quality.
+                self.window.request_redraw();
             }
         }
 
@@ -340,9 +1601,27 @@ impl<'window> WindowRenderer<'window> {
     }
 }
 
+/// The current time in seconds, relative to an arbitrary origin.
+#[cfg(not(target_arch = "wasm32"))]
+fn now_seconds() -> f64 {
+    use std::time::SystemTime;
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// The current time in seconds, relative to an arbitrary origin.
+#[cfg(target_arch = "wasm32")]
+fn now_seconds() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
 #[derive(Debug)]
 enum ShellEvent {
     WindowEvent(WindowId, WindowEvent),
+    /// The time a redraw was deferred to was reached.
+    ResumeTimeReached,
 }
 
 const DESIRED_MAXIMUM_FRAME_LATENCY: u32 = 1;
@@ -371,17 +1650,164 @@ impl<'window> WindowRenderer<'window> {
         Ok((instance, surface))
     }
 
+    /// Limit the number of glyphs and quads that are uploaded per frame, so that huge scenes
+    /// don't stall the first frame. `None` uploads everything at once.
+    ///
+    /// See [`Renderer::set_upload_budget`].
+    pub fn set_upload_budget(&mut self, budget: Option<usize>) {
+        self.renderer.set_upload_budget(budget);
+    }
+
+    /// Submit the uploads of rasterized glyphs separately from the frames, see
+    /// [`Renderer::set_upload_submission`].
+    pub fn set_upload_submission(&mut self, submission: UploadSubmission) {
+        self.renderer.set_upload_submission(submission);
+    }
+
+    /// Rasterize the glyphs the animated camera will show `lookahead` from now, while the upload
+    /// budget delays the preparation of the scene. `None` disables prefetching.
+    ///
+    /// See [`Renderer::prefetch_glyphs`].
+    pub fn set_glyph_prefetch(&mut self, lookahead: Option<Duration>) {
+        self.glyph_prefetch = lookahead;
+    }
+
+    /// Register a shape extension that renders custom shapes.
+    ///
+    /// `create` receives the renderer to create the pipelines of the extension with its device
+    /// and bind group layouts. See [`Renderer::register_extension`].
+    pub fn register_extension<E: ShapeExtension>(&mut self, create: impl FnOnce(&Renderer) -> E) {
+        let extension = create(&self.renderer);
+        self.renderer.register_extension(extension);
+        self.window.request_redraw();
+    }
+
+    pub fn debug_mode(&self) -> DebugMode {
+        self.renderer.debug_mode()
+    }
+
+    /// Visualize quad outlines, overdraw, or batches. See [`DebugMode`].
+    pub fn set_debug_mode(&mut self, mode: DebugMode) {
+        self.renderer.set_debug_mode(mode);
+    }
+
+    pub fn high_contrast(&self) -> Option<HighContrast> {
+        self.renderer.high_contrast()
+    }
+
+    /// Replace the colors of the scene with a high contrast palette, `None` restores them.
+    ///
+    /// See [`Renderer::set_high_contrast`] and [`ShellWindow::forced_colors`].
+    pub fn set_high_contrast(&mut self, high_contrast: Option<HighContrast>) {
+        self.renderer.set_high_contrast(high_contrast);
+        // Prepare the scene again with the new colors.
+        self.window.request_redraw();
+    }
+
+    pub fn min_contrast(&self) -> Option<f32> {
+        self.renderer.min_contrast()
+    }
+
+    /// Keep text readable on any background, see [`Renderer::set_min_contrast`].
+    pub fn set_min_contrast(&mut self, ratio: Option<f32>) {
+        self.renderer.set_min_contrast(ratio);
+        self.window.request_redraw();
+    }
+
+    /// The retained state of the renderer, as of the last frame.
+    ///
+    /// Scene changes that were not rendered yet are not included.
+    pub fn renderer_state(&self) -> RendererState<'_> {
+        self.renderer.state()
+    }
+
+    /// See [`Renderer::apply_config`].
+    pub fn apply_renderer_config(&mut self, config: &RendererConfig) {
+        self.renderer.apply_config(config);
+    }
+
+    /// Set the shader uniforms of the shapes at `position`, for example to tint or highlight them.
+    ///
+    /// See [`Renderer::set_layer_uniforms`].
+    pub fn set_layer_uniforms(&mut self, position: &Handle<Position>, uniforms: &LayerUniforms) {
+        self.renderer.set_layer_uniforms(position.id(), uniforms);
+    }
+
+    pub fn remove_layer_uniforms(&mut self, position: &Handle<Position>) {
+        self.renderer.remove_layer_uniforms(position.id());
+    }
+
+    /// Cache the world shapes at `position` and its descendants in a texture, see
+    /// [`Renderer::set_layer_cached`].
+    pub fn set_layer_cached(&mut self, position: &Handle<Position>, cached: bool) {
+        self.renderer.set_layer_cached(position.id(), cached);
+    }
+
+    pub fn text_rendering(&self) -> TextRendering {
+        self.renderer.text_rendering()
+    }
+
+    /// Set how text is rendered, see [`Renderer::set_text_rendering`].
+    pub fn set_text_rendering(&mut self, text_rendering: impl Into<TextRendering>) {
+        self.renderer.set_text_rendering(text_rendering.into());
+        self.window.request_redraw();
+    }
+
+    /// Override the text rendering of the shapes at `position`, see
+    /// [`Renderer::set_layer_text_rendering`].
+    pub fn set_layer_text_rendering(
+        &mut self,
+        position: &Handle<Position>,
+        text_rendering: Option<TextRendering>,
+    ) {
+        self.renderer
+            .set_layer_text_rendering(position.id(), text_rendering);
+        self.window.request_redraw();
+    }
+
+    /// Write newly rasterized glyphs to the glyph cache configured in [`RendererOptions`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn persist_glyph_cache(&mut self) -> Result<()> {
+        self.renderer.persist_glyph_cache()
+    }
+
+    /// How the renderer processed the scene changes so far.
+    ///
+    /// Use this to verify that camera and matrix updates don't cause the scene to be prepared
+    /// again.
+    pub fn preparation_stats(&self) -> PreparationStats {
+        self.renderer.preparation_stats()
+    }
+
     /// The format chosen for the swapchain.
     pub fn surface_format(&self) -> TextureFormat {
         self.renderer.surface_config.format
     }
 
+    /// The format the pipelines render to, see [`Renderer::render_format`].
+    pub fn render_format(&self) -> TextureFormat {
+        self.renderer.render_format()
+    }
+
     /// A Matrix that translates from pixels (0,0)-(width,height) to screen space, which is -1.0 to
     /// 1.0 in each axis. Also flips y.
     pub fn pixel_matrix(&self) -> Matrix4 {
         self.renderer.pixel_matrix()
     }
 
+    /// A Matrix that translates from logical pixels to screen space. Also flips y.
+    ///
+    /// Use this to size content independently of the window's scale factor.
+    pub fn logical_pixel_matrix(&self) -> Matrix4 {
+        self.unit_system().logical_pixel_matrix()
+    }
+
+    /// The unit system of the window's surface, for converting between logical pixels, physical
+    /// pixels and world units.
+    pub fn unit_system(&self) -> UnitSystem {
+        self.renderer.unit_system(self.window.scale_factor())
+    }
+
     // Surface size may not match the Window's size, for example if the window's size is 0,0.
     #[allow(unused)]
     fn surface_size(&self) -> (u32, u32) {
@@ -391,7 +1817,7 @@ impl<'window> WindowRenderer<'window> {
 
 #[allow(unused)]
 pub fn time<T>(name: &str, f: impl FnOnce() -> T) -> T {
-    let start = std::time::Instant::now();
+    let start = Instant::now();
     let r = f();
     println!("{name}: {:?}", start.elapsed());
     r
@@ -435,30 +1861,40 @@ impl ApplicationContext {
         _inner_size: impl Into<dpi::Size>,
         canvas_id: Option<&str>,
     ) -> Result<ShellWindow> {
+        use anyhow::{anyhow, Context};
         use wasm_bindgen::JsCast;
         use winit::platform::web::WindowAttributesExtWebSys;
 
-        let canvas_id = canvas_id.expect("Canvas id is needed for wasm targets");
+        let canvas_id = canvas_id.context("Canvas id is needed for wasm targets")?;
 
         let canvas = web_sys::window()
-            .expect("No Window")
+            .context("No Window")?
             .document()
-            .expect("No document")
+            .context("No document")?
             .query_selector(&format!("#{canvas_id}"))
             // what a shit-show here, why is the error not compatible with anyhow.
-            .map_err(|err| anyhow::anyhow!(err.as_string().unwrap()))?
-            .expect("No Canvas with a matching id found");
+            .map_err(|err| anyhow!(err.as_string().unwrap_or_default()))?
+            .context("No Canvas with a matching id found")?;
 
         let canvas: web_sys::HtmlCanvasElement = canvas
             .dyn_into()
-            .map_err(|_| anyhow::anyhow!("Failed to cast to HtmlCanvasElement"))?;
+            .map_err(|_| anyhow!("Failed to cast to HtmlCanvasElement"))?;
 
-        let window =
-            event_loop.create_window(WindowAttributes::default().with_canvas(Some(canvas)))?;
+        let window = Rc::new(
+            event_loop.create_window(WindowAttributes::default().with_canvas(Some(canvas)))?,
+        );
 
-        Ok(ShellWindow {
-            window: Rc::new(window),
-        })
+        // Soft keyboards shrink the visual viewport without resizing the canvas.
+        if let Some(viewport) = web_sys::window().and_then(|w| w.visual_viewport()) {
+            let window = window.clone();
+            let on_resize =
+                wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || window.request_redraw());
+            viewport.set_onresize(Some(on_resize.as_ref().unchecked_ref()));
+            // The handler lives as long as the page.
+            on_resize.forget();
+        }
+
+        Ok(ShellWindow { window })
     }
 
     pub fn primary_monitor(&self) -> Option<MonitorHandle> {
@@ -482,27 +1918,90 @@ impl ApplicationContext {
         &mut self,
         renderer: &mut WindowRenderer<'_>,
     ) -> Result<WindowEvent> {
-        let event = self.event_receiver.recv().await;
-        let Some(event) = event else {
-            // This means that the shell stopped before the application ended, this should not
-            // happen in normal situations.
-            bail!("Internal Error: Shell shut down, no more events")
-        };
-
-        match event {
-            ShellEvent::WindowEvent(window_id, window_event)
-                if window_id == renderer.window.id() =>
+        loop {
+            // Wake up the event loop when the redraw the power saving mode deferred is due, when
+            // the caret blinks, or when transient shapes start to fade out.
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(wake_up) = [
+                renderer.deferred_redraw(),
+                renderer.schedule_caret_blink(),
+                renderer.schedule_transient_fade(),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
             {
-                renderer.handle_window_event(&window_event)?;
-                // We forward _all_ window events to the application (for now)
-                Ok(window_event)
+                self.with_active_event_loop(|event_loop| {
+                    event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(wake_up))
+                });
             }
-            _ => {
-                // TODO: Support this somehow.
-                panic!("Received event from another window")
+
+            let event = self.event_receiver.recv().await;
+            let Some(event) = event else {
+                // This means that the shell stopped before the application ended, this should not
+                // happen in normal situations.
+                bail!("Internal Error: Shell shut down, no more events")
+            };
+
+            match event {
+                ShellEvent::WindowEvent(window_id, window_event)
+                    if window_id == renderer.window.id() =>
+                {
+                    renderer.handle_window_event(&window_event)?;
+                    if let Some(Shortcut::Shell(action)) = renderer.triggered_shortcut {
+                        match action {
+                            ShellAction::Quit => return Ok(WindowEvent::CloseRequested),
+                            ShellAction::Screenshot => {
+                                // A failed screenshot should not end the application.
+                                if let Err(e) = self.take_screenshot(renderer).await {
+                                    error!("Failed to take a screenshot: {e:?}");
+                                }
+                            }
+                            _ => {}
+                        }
+                        if renderer.perform_shell_action(action) {
+                            continue;
+                        }
+                    }
+                    // We forward all other window events to the application.
+                    return Ok(window_event);
+                }
+                ShellEvent::ResumeTimeReached => {
+                    renderer.resume_deferred_redraw();
+                    renderer.resume_caret_blink();
+                    renderer.resume_transient_fade();
+                }
+                _ => {
+                    // TODO: Support this somehow.
+                    bail!("Received event from another window")
+                }
             }
         }
     }
+
+    /// Capture the frame the renderer currently shows.
+    ///
+    /// On native targets, the frame is saved as a PNG file to the directory of the
+    /// [`ScreenshotOptions`]. The window flashes afterwards, and the screenshot is passed to the
+    /// handler, see [`WindowRenderer::set_screenshot_handler`].
+    ///
+    /// This is what the [`ShellAction::Screenshot`] shortcut invokes.
+    pub async fn take_screenshot(&self, renderer: &mut WindowRenderer<'_>) -> Result<Screenshot> {
+        let capture = renderer.capture_frame()?;
+        let frame = capture.wait(&renderer.renderer.device).await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let screenshot = {
+            let path = crate::screenshot::save_png(&frame, &renderer.screenshot_options.directory)?;
+            info!("Saved screenshot to {}", path.display());
+            Screenshot { frame, path }
+        };
+        #[cfg(target_arch = "wasm32")]
+        let screenshot = Screenshot { frame };
+
+        renderer.screenshot_taken(&screenshot);
+        Ok(screenshot)
+    }
 }
 
 struct WinitApplicationHandler {
@@ -515,6 +2014,14 @@ struct WinitApplicationHandler {
 impl ApplicationHandler<Event> for WinitApplicationHandler {
     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
 
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        if let StartCause::ResumeTimeReached { .. } = cause {
+            event_loop.set_control_flow(winit::event_loop::ControlFlow::Wait);
+            // Don't exit if the receiver is gone, the next window event does.
+            let _ = self.event_sender.try_send(ShellEvent::ResumeTimeReached);
+        }
+    }
+
     fn user_event(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, event: Event) {
         match event {
             Event::WakeUpApplication => {
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::{Key, ModifiersState, NamedKey},
+};
+
+/// A key pressed together with modifiers.
+///
+/// Character keys are compared case-insensitively, add [`ModifiersState::SHIFT`] to the modifiers
+/// to bind the shifted key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    key: Key,
+    modifiers: ModifiersState,
+}
+
+impl Chord {
+    pub fn new(key: Key, modifiers: ModifiersState) -> Self {
+        Self {
+            key: normalize(key),
+            modifiers,
+        }
+    }
+
+    /// A key without modifiers.
+    pub fn key(key: Key) -> Self {
+        Self::new(key, ModifiersState::empty())
+    }
+
+    /// A named key without modifiers, like [`NamedKey::Escape`].
+    pub fn named(key: NamedKey) -> Self {
+        Self::key(Key::Named(key))
+    }
+
+    /// A character key with modifiers, like `Chord::character("s", ModifiersState::CONTROL)`.
+    pub fn character(character: &str, modifiers: ModifiersState) -> Self {
+        Self::new(Key::Character(character.into()), modifiers)
+    }
+
+    /// The chord a key event completes, `None` for releases and repeats.
+    pub fn of(event: &KeyEvent, modifiers: ModifiersState) -> Option<Self> {
+        if event.state != ElementState::Pressed || event.repeat {
+            return None;
+        }
+        Some(Self::new(event.logical_key.clone(), modifiers))
+    }
+}
+
+fn normalize(key: Key) -> Key {
+    match key {
+        Key::Character(character) => Key::Character(character.to_lowercase().into()),
+        key => key,
+    }
+}
+
+/// What the shell does itself when a chord is pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShellAction {
+    /// [`crate::ApplicationContext::wait_for_event`] returns
+    /// [`winit::event::WindowEvent::CloseRequested`].
+    Quit,
+    /// Enter or leave borderless fullscreen on the window's monitor.
+    ToggleFullscreen,
+    /// Capture the current frame, see [`crate::ApplicationContext::take_screenshot`].
+    Screenshot,
+    /// Show or hide an overlay with the renderer's statistics.
+    ///
+    /// The shell has no overlay yet, the key event is forwarded to the application.
+    ToggleStatsOverlay,
+}
+
+/// What a chord is bound to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Shortcut {
+    Shell(ShellAction),
+    /// A command of the application, identified by its name. The key event is forwarded, see
+    /// [`crate::WindowRenderer::triggered_shortcut`].
+    Application(String),
+}
+
+impl Shortcut {
+    pub fn application(name: impl Into<String>) -> Self {
+        Self::Application(name.into())
+    }
+}
+
+/// Binds chords to shell actions or application commands.
+///
+/// The default map binds the built-in shell actions: Escape quits, F11 toggles fullscreen, F12
+/// takes a screenshot, and F9 toggles the stats overlay. Use [`Self::unbind`] to disable one of
+/// them, or start with [`Self::empty`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutMap {
+    bindings: HashMap<Chord, Shortcut>,
+}
+
+impl Default for ShortcutMap {
+    fn default() -> Self {
+        let mut map = Self::empty();
+        map.bind(
+            Chord::named(NamedKey::Escape),
+            Shortcut::Shell(ShellAction::Quit),
+        );
+        map.bind(
+            Chord::named(NamedKey::F11),
+            Shortcut::Shell(ShellAction::ToggleFullscreen),
+        );
+        map.bind(
+            Chord::named(NamedKey::F12),
+            Shortcut::Shell(ShellAction::Screenshot),
+        );
+        map.bind(
+            Chord::named(NamedKey::F9),
+            Shortcut::Shell(ShellAction::ToggleStatsOverlay),
+        );
+        map
+    }
+}
+
+impl ShortcutMap {
+    /// A map without any bindings, not even the built-in ones.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Bind a chord, returns what it was bound to before.
+    pub fn bind(&mut self, chord: Chord, shortcut: Shortcut) -> Option<Shortcut> {
+        self.bindings.insert(chord, shortcut)
+    }
+
+    /// Remove the binding of a chord, returns what it was bound to.
+    pub fn unbind(&mut self, chord: &Chord) -> Option<Shortcut> {
+        self.bindings.remove(chord)
+    }
+
+    /// Remove all bindings of a shell action, for example to handle Escape in the application.
+    pub fn unbind_action(&mut self, action: ShellAction) {
+        self.bindings
+            .retain(|_, shortcut| *shortcut != Shortcut::Shell(action));
+    }
+
+    pub fn get(&self, chord: &Chord) -> Option<&Shortcut> {
+        self.bindings.get(chord)
+    }
+
+    /// The shortcut a key event triggers while `modifiers` are held.
+    pub fn triggered(&self, event: &KeyEvent, modifiers: ModifiersState) -> Option<&Shortcut> {
+        self.get(&Chord::of(event, modifiers)?)
+    }
+
+    /// The chords bound to a shortcut.
+    pub fn chords<'a>(&'a self, shortcut: &'a Shortcut) -> impl Iterator<Item = &'a Chord> {
+        self.bindings
+            .iter()
+            .filter(move |(_, bound)| *bound == shortcut)
+            .map(|(chord, _)| chord)
+    }
+}
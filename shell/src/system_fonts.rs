@@ -0,0 +1,188 @@
+//! Discovery of the fonts installed on the host.
+//!
+//! `fontdb` loads the fonts of the standard font directories, but resolves the generic families
+//! (`sans-serif`, `monospace`, ...) to fixed names that may not be installed. These functions also
+//! load the fonts the host's font configuration knows about and resolve the generic families the
+//! way the host does, so that `Family::Monospace` renders with the user's monospace font.
+
+use std::{collections::HashSet, path::PathBuf};
+
+use cosmic_text::{fontdb, FontSystem};
+use log::info;
+
+/// The generic families of CSS that `fontdb` resolves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GenericFamily {
+    Serif,
+    SansSerif,
+    Monospace,
+    Cursive,
+    Fantasy,
+}
+
+impl GenericFamily {
+    const ALL: [GenericFamily; 5] = [
+        GenericFamily::Serif,
+        GenericFamily::SansSerif,
+        GenericFamily::Monospace,
+        GenericFamily::Cursive,
+        GenericFamily::Fantasy,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            GenericFamily::Serif => "serif",
+            GenericFamily::SansSerif => "sans-serif",
+            GenericFamily::Monospace => "monospace",
+            GenericFamily::Cursive => "cursive",
+            GenericFamily::Fantasy => "fantasy",
+        }
+    }
+}
+
+/// A font system with the fonts of the host and the generic families resolved like the host does.
+pub fn system_font_system() -> FontSystem {
+    // `FontSystem::new()` loads the fonts of the standard font directories already.
+    let mut font_system = FontSystem::new();
+    resolve_system_fonts(font_system.db_mut());
+    font_system
+}
+
+/// Load the fonts of the host into `db` and resolve its generic families like the host does.
+pub fn load_system_fonts(db: &mut fontdb::Database) {
+    db.load_system_fonts();
+    resolve_system_fonts(db);
+}
+
+fn resolve_system_fonts(db: &mut fontdb::Database) {
+    let loaded: HashSet<PathBuf> = db
+        .faces()
+        .filter_map(|face| match &face.source {
+            fontdb::Source::File(path) => Some(path.clone()),
+            fontdb::Source::SharedFile(path, _) => Some(path.clone()),
+            fontdb::Source::Binary(_) => None,
+        })
+        .collect();
+
+    for path in platform::font_files() {
+        if loaded.contains(&path) {
+            continue;
+        }
+        // Files fontdb can't parse are ignored by fontconfig, too.
+        let _ = db.load_font_file(path);
+    }
+
+    for generic in GenericFamily::ALL {
+        let Some(family) = platform::family(generic) else {
+            continue;
+        };
+        if !db
+            .faces()
+            .any(|face| face.families.iter().any(|(f, _)| *f == family))
+        {
+            continue;
+        }
+        info!("Resolved {} to {family:?}", generic.name());
+        match generic {
+            GenericFamily::Serif => db.set_serif_family(family),
+            GenericFamily::SansSerif => db.set_sans_serif_family(family),
+            GenericFamily::Monospace => db.set_monospace_family(family),
+            GenericFamily::Cursive => db.set_cursive_family(family),
+            GenericFamily::Fantasy => db.set_fantasy_family(family),
+        }
+    }
+}
+
+/// fontconfig resolves aliases and applies the substitutions of the user's `fonts.conf`.
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+mod platform {
+    use std::{path::PathBuf, process::Command};
+
+    use super::GenericFamily;
+
+    /// The font files fontconfig knows about, including the ones of the user's font directories.
+    pub fn font_files() -> Vec<PathBuf> {
+        fc(&["fc-list", "--format=%{file}\n"])
+            .map(|files| files.lines().map(PathBuf::from).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn family(generic: GenericFamily) -> Option<String> {
+        let family = fc(&["fc-match", "--format=%{family[0]}", generic.name()])?;
+        let family = family.trim();
+        (!family.is_empty()).then(|| family.to_string())
+    }
+
+    /// `None` if fontconfig is not available.
+    fn fc(args: &[&str]) -> Option<String> {
+        let output = Command::new(args[0]).args(&args[1..]).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    }
+}
+
+/// The system fonts of macOS are all in the standard font directories.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod platform {
+    use std::path::PathBuf;
+
+    use super::GenericFamily;
+
+    pub fn font_files() -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    // TODO: Ask Core Text for the user's fonts instead of using the defaults of Safari.
+    pub fn family(generic: GenericFamily) -> Option<String> {
+        let family = match generic {
+            GenericFamily::Serif => "Times",
+            GenericFamily::SansSerif => "Helvetica",
+            GenericFamily::Monospace => "Menlo",
+            GenericFamily::Cursive => "Apple Chancery",
+            GenericFamily::Fantasy => "Papyrus",
+        };
+        Some(family.to_string())
+    }
+}
+
+/// Windows has no aliases for the generic families, these are the defaults of its browsers.
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::path::PathBuf;
+
+    use super::GenericFamily;
+
+    // TODO: Load the fonts installed per user in `%LOCALAPPDATA%\Microsoft\Windows\Fonts`.
+    pub fn font_files() -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    pub fn family(generic: GenericFamily) -> Option<String> {
+        let family = match generic {
+            GenericFamily::Serif => "Times New Roman",
+            GenericFamily::SansSerif => "Segoe UI",
+            GenericFamily::Monospace => "Consolas",
+            GenericFamily::Cursive => "Comic Sans MS",
+            GenericFamily::Fantasy => "Impact",
+        };
+        Some(family.to_string())
+    }
+}
+
+/// No system fonts, for example in the browser.
+#[cfg(not(any(unix, target_os = "windows")))]
+mod platform {
+    use std::path::PathBuf;
+
+    use super::GenericFamily;
+
+    pub fn font_files() -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    pub fn family(_generic: GenericFamily) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use massive_geometry::{scalar, Bounds3, Point3};
+
+/// Divides the plane of an infinite canvas into square tiles, so that its content can be loaded,
+/// created, and dropped by tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileGrid {
+    /// The edge length of a tile in world units.
+    pub tile_size: scalar,
+}
+
+/// A tile of a [`TileGrid`]. Tile `(0, 0)` starts at the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tile {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl TileGrid {
+    pub fn new(tile_size: scalar) -> Self {
+        assert!(tile_size > 0.0, "Tile size must be positive");
+        Self { tile_size }
+    }
+
+    /// The tile that contains the point `(x, y)`.
+    pub fn tile_at(&self, x: scalar, y: scalar) -> Tile {
+        Tile {
+            x: (x / self.tile_size).floor() as i64,
+            y: (y / self.tile_size).floor() as i64,
+        }
+    }
+
+    /// The bounds of a tile, flat at `z = 0`.
+    pub fn tile_bounds(&self, tile: Tile) -> Bounds3 {
+        let size = self.tile_size;
+        Bounds3::new(
+            Point3::new(tile.x as scalar * size, tile.y as scalar * size, 0.0),
+            Point3::new(
+                (tile.x + 1) as scalar * size,
+                (tile.y + 1) as scalar * size,
+                0.0,
+            ),
+        )
+    }
+
+    /// The tiles that overlap `bounds` in x and y, row by row.
+    pub fn tiles_intersecting(&self, bounds: &Bounds3) -> impl Iterator<Item = Tile> {
+        let min = self.tile_at(bounds.min.x, bounds.min.y);
+        let max = self.tile_at(bounds.max.x, bounds.max.y);
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| Tile { x, y }))
+    }
+}
+
+/// The tiles that entered and left the view with an update of [`VisibleTiles`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TileChanges {
+    pub entered: Vec<Tile>,
+    pub left: Vec<Tile>,
+}
+
+/// Tracks the tiles of a [`TileGrid`] in view, for example the visible area from
+/// [`crate::WindowRenderer::visible_bounds`].
+///
+/// Create the content of the tiles that entered the view and drop the content of the tiles that
+/// left it, so that the scene only contains what's near the view, no matter how large the canvas
+/// is.
+#[derive(Debug)]
+pub struct VisibleTiles {
+    grid: TileGrid,
+    /// The number of tiles around the view that are considered visible, so that content is
+    /// created before it scrolls in.
+    margin: i64,
+    /// If the view covers more tiles, no tiles are visible. Protects against views up to the
+    /// horizon or far away cameras.
+    max_tiles: usize,
+    tiles: HashSet<Tile>,
+}
+
+impl VisibleTiles {
+    pub fn new(grid: TileGrid, margin: usize, max_tiles: usize) -> Self {
+        Self {
+            grid,
+            margin: margin as i64,
+            max_tiles,
+            tiles: HashSet::new(),
+        }
+    }
+
+    pub fn grid(&self) -> &TileGrid {
+        &self.grid
+    }
+
+    pub fn is_visible(&self, tile: Tile) -> bool {
+        self.tiles.contains(&tile)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        self.tiles.iter().copied()
+    }
+
+    /// Make the tiles overlapping `view` and the margin around them the visible ones. `None`
+    /// makes no tiles visible.
+    ///
+    /// The changes are ordered by rows.
+    pub fn update(&mut self, view: Option<&Bounds3>) -> TileChanges {
+        let mut visible = HashSet::new();
+        if let Some(view) = view {
+            let margin = self.margin as scalar * self.grid.tile_size;
+            let min = self.grid.tile_at(view.min.x - margin, view.min.y - margin);
+            let max = self.grid.tile_at(view.max.x + margin, view.max.y + margin);
+            let count = (max.x - min.x + 1).saturating_mul(max.y - min.y + 1);
+            if count <= self.max_tiles as i64 {
+                let bounds = Bounds3::new(
+                    Point3::new(view.min.x - margin, view.min.y - margin, 0.0),
+                    Point3::new(view.max.x + margin, view.max.y + margin, 0.0),
+                );
+                visible.extend(self.grid.tiles_intersecting(&bounds));
+            }
+        }
+
+        let mut changes = TileChanges {
+            entered: visible.difference(&self.tiles).copied().collect(),
+            left: self.tiles.difference(&visible).copied().collect(),
+        };
+        changes.entered.sort_by_key(|tile| (tile.y, tile.x));
+        changes.left.sort_by_key(|tile| (tile.y, tile.x));
+        self.tiles = visible;
+        changes
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::HashSet, time::Duration};
+
+use massive_renderer::{LayerUniforms, Renderer};
+use massive_scene::{Change, Handle, Id, Position, PositionedShape, SceneChange};
+
+/// Shapes that live for a limited time, like toast notifications or transient highlights.
+///
+/// After their time to live, the shapes fade out by the tint of their layer and are dropped. The
+/// layer uniforms are reset as soon as the renderer sees the position being deleted, so that a
+/// position reusing its id does not inherit the fade.
+#[derive(Debug, Default)]
+pub struct TransientShapes {
+    entries: Vec<Transient>,
+    /// The positions of the dropped entries, whose deletion was not applied yet.
+    dropped: HashSet<Id>,
+}
+
+#[derive(Debug)]
+struct Transient {
+    position: Handle<Position>,
+    _shapes: Vec<Handle<PositionedShape>>,
+    /// The time the fade out starts, in the time base of the renderer.
+    fade_start: f32,
+    fade: f32,
+}
+
+impl TransientShapes {
+    /// Keep `shapes` at `position` until `now + ttl` and fade them out over `fade` afterwards.
+    pub fn push(
+        &mut self,
+        position: Handle<Position>,
+        shapes: impl IntoIterator<Item = Handle<PositionedShape>>,
+        now: f32,
+        ttl: Duration,
+        fade: Duration,
+    ) {
+        self.entries.push(Transient {
+            position,
+            _shapes: shapes.into_iter().collect(),
+            fade_start: now + ttl.as_secs_f32(),
+            fade: fade.as_secs_f32(),
+        });
+    }
+
+    /// `true` if there are shapes waiting for their time to live to end or fading out.
+    pub fn is_pending(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    /// `true` if shapes are fading out at `now`, which requires a redraw with every frame.
+    pub fn is_fading(&self, now: f32) -> bool {
+        self.entries.iter().any(|entry| now >= entry.fade_start)
+    }
+
+    /// The time after `now` the next shapes start to fade out, `None` if no shapes wait for that.
+    pub fn next_fade_start(&self, now: f32) -> Option<f32> {
+        self.entries
+            .iter()
+            .map(|entry| entry.fade_start)
+            .filter(|fade_start| *fade_start > now)
+            .min_by(f32::total_cmp)
+    }
+
+    /// Fade out the shapes whose time to live ended at `now` and drop the ones that faded out.
+    ///
+    /// The deletions of dropped shapes reach the renderer with the next
+    /// [`massive_scene::Director::action`].
+    pub fn advance(&mut self, renderer: &mut Renderer, now: f32) {
+        let dropped = &mut self.dropped;
+        self.entries.retain(|entry| {
+            if now < entry.fade_start {
+                return true;
+            }
+            let t = if entry.fade > 0.0 {
+                ((now - entry.fade_start) / entry.fade).min(1.0)
+            } else {
+                1.0
+            };
+            let uniforms = LayerUniforms {
+                tint: [1.0, 1.0, 1.0, 1.0 - t],
+                ..LayerUniforms::default()
+            };
+            renderer.set_layer_uniforms(entry.position.id(), &uniforms);
+            if t < 1.0 {
+                return true;
+            }
+            dropped.insert(entry.position.id());
+            false
+        });
+    }
+
+    /// Reset the layer uniforms of the dropped positions that are deleted by `changes`.
+    ///
+    /// Call this with the changes that are applied to the renderer before the next frame.
+    pub fn applied(&mut self, renderer: &mut Renderer, changes: &[SceneChange]) {
+        if self.dropped.is_empty() {
+            return;
+        }
+        for change in changes {
+            if let SceneChange::Position(Change::Delete(id)) = change {
+                if self.dropped.remove(id) {
+                    renderer.remove_layer_uniforms(*id);
+                }
+            }
+        }
+    }
+}
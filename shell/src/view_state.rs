@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use massive_geometry::Camera;
+
+use crate::widgets::KineticScroll;
+
+/// Where the user left off: The camera, which includes the zoom, and the scroll offsets of the
+/// application's scrollable views.
+///
+/// View states are serializable, so that applications can persist them between sessions or keep
+/// them as view bookmarks. They are captured with [`crate::WindowRenderer::view_state`] and
+/// restored with [`crate::WindowRenderer::restore_view_state`] and [`Self::restore_scroll`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewState {
+    pub camera: Camera,
+    /// The scroll offsets by names the application chooses.
+    pub scroll_offsets: BTreeMap<String, f64>,
+}
+
+impl ViewState {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            scroll_offsets: BTreeMap::new(),
+        }
+    }
+
+    /// Record the offset of `scroll` as `name`.
+    ///
+    /// If `scroll` is animating, its target is recorded.
+    pub fn with_scroll(mut self, name: impl Into<String>, scroll: &KineticScroll) -> Self {
+        let offset = scroll.target().unwrap_or_else(|| scroll.offset());
+        self.scroll_offsets.insert(name.into(), offset);
+        self
+    }
+
+    pub fn scroll_offset(&self, name: &str) -> Option<f64> {
+        self.scroll_offsets.get(name).copied()
+    }
+
+    /// Scroll `scroll` to the offset recorded as `name`, smoothly if `animate` is set.
+    ///
+    /// The offset is limited to the current maximum of `scroll`, so restore it after the
+    /// content was set. Returns `false` if there is no offset recorded as `name`.
+    pub fn restore_scroll(&self, name: &str, scroll: &mut KineticScroll, animate: bool) -> bool {
+        let Some(offset) = self.scroll_offset(name) else {
+            return false;
+        };
+        if animate {
+            scroll.scroll_smoothly_to(offset);
+        } else {
+            scroll.set_offset(offset);
+        }
+        true
+    }
+}
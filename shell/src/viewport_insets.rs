@@ -0,0 +1,24 @@
+/// The parts of a window that are covered, for example by a soft keyboard, in physical pixels.
+///
+/// See [`crate::ShellWindow::viewport_insets`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ViewportInsets {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl ViewportInsets {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The size that remains visible of a window of `size`.
+    pub fn visible_size(&self, size: (f64, f64)) -> (f64, f64) {
+        (
+            (size.0 - self.left - self.right).max(0.0),
+            (size.1 - self.top - self.bottom).max(0.0),
+        )
+    }
+}
@@ -0,0 +1,36 @@
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::Caret;
+
+/// The caret of a text widget.
+///
+/// The shape is only updated when the caret changes, the renderer animates the movement.
+#[derive(Debug, Default)]
+pub(crate) struct CaretShape {
+    shape: Option<(Handle<PositionedShape>, Caret)>,
+}
+
+impl CaretShape {
+    /// Show `caret` at `position`, or remove the caret if it's `None`.
+    pub fn update(
+        &mut self,
+        director: &mut Director,
+        position: &Handle<Position>,
+        caret: Option<Caret>,
+    ) {
+        let Some(caret) = caret else {
+            self.shape = None;
+            return;
+        };
+        match &mut self.shape {
+            Some((_, current)) if *current == caret => {}
+            Some((handle, current)) => {
+                handle.update(PositionedShape::new(position.clone(), caret));
+                *current = caret;
+            }
+            None => {
+                let handle = director.cast(PositionedShape::new(position.clone(), caret));
+                self.shape = Some((handle, caret));
+            }
+        }
+    }
+}
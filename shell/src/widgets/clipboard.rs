@@ -0,0 +1,57 @@
+#[cfg(not(target_arch = "wasm32"))]
+use log::warn;
+
+/// Access to the system clipboard.
+///
+/// If the system clipboard is not available, like on wasm, text is only copied inside the
+/// application.
+pub struct Clipboard {
+    #[cfg(not(target_arch = "wasm32"))]
+    system: Option<arboard::Clipboard>,
+    local: String,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Clipboard").finish_non_exhaustive()
+    }
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            system: arboard::Clipboard::new()
+                .map_err(|e| warn!("System clipboard not available: {e:?}"))
+                .ok(),
+            local: String::new(),
+        }
+    }
+
+    pub fn text(&mut self) -> String {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(system) = &mut self.system {
+            match system.get_text() {
+                Ok(text) => return text,
+                Err(e) => warn!("Failed to read from the clipboard: {e:?}"),
+            }
+        }
+        self.local.clone()
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(system) = &mut self.system {
+            if let Err(e) = system.set_text(text) {
+                warn!("Failed to write to the clipboard: {e:?}");
+            }
+        }
+        self.local = text.into();
+    }
+}
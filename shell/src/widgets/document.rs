@@ -0,0 +1,471 @@
+use std::{mem, ops::Range};
+
+use ropey::Rope;
+
+use super::text_edit::is_word_char;
+
+/// The editing state of a multi-line text: The text, the caret, the selection, and the undo
+/// history.
+///
+/// The text is stored in a rope, so edits stay cheap for large documents. All positions are
+/// character indices into the text.
+///
+/// Line breaks are normalized to `\n` and tabs are expanded to spaces when text is inserted.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    text: Rope,
+    caret: usize,
+    /// The other end of the selection. The selection is empty if this is `None` or equal to
+    /// `caret`.
+    anchor: Option<usize>,
+    history: History,
+    /// Increased with every change of the text.
+    revision: u64,
+    /// Lines that changed since the last call to [`Self::take_line_changes`].
+    line_changes: Vec<LineChange>,
+}
+
+/// Lines that were replaced by an edit.
+///
+/// The lines `first..=first + removed` were replaced by the lines `first..=first + inserted`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineChange {
+    pub first: usize,
+    /// The number of line breaks removed.
+    pub removed: usize,
+    /// The number of line breaks inserted.
+    pub inserted: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// The kind of the last edit, if the next edit of the same kind may be merged into it.
+    merge: Option<EditKind>,
+}
+
+/// A reversible replacement of text.
+#[derive(Debug, Clone)]
+struct Edit {
+    start: usize,
+    removed: String,
+    inserted: String,
+    /// Caret and anchor before the edit.
+    before: (usize, Option<usize>),
+    /// Caret and anchor after the edit.
+    after: (usize, Option<usize>),
+}
+
+/// Consecutive edits of the same kind are undone together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    DeleteBackward,
+    DeleteForward,
+    Other,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: Rope::from_str(&normalize(text)),
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> &Rope {
+        &self.text
+    }
+
+    /// Replace the text, move the caret to the start, and clear the undo history.
+    pub fn set_text(&mut self, text: &str) {
+        let removed = self.line_count() - 1;
+        let revision = self.revision;
+        let mut line_changes = mem::take(&mut self.line_changes);
+        *self = Self::new(text);
+        line_changes.push(LineChange {
+            first: 0,
+            removed,
+            inserted: self.line_count() - 1,
+        });
+        self.line_changes = line_changes;
+        self.revision = revision + 1;
+    }
+
+    /// A number that changes whenever the text changes.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// The number of lines. An empty text and a text ending with a line break have an empty last
+    /// line.
+    pub fn line_count(&self) -> usize {
+        self.text.len_lines()
+    }
+
+    /// The text of a line without its line break.
+    pub fn line(&self, line: usize) -> String {
+        let mut text = self.text.line(line).to_string();
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        text
+    }
+
+    /// The index of the first character of a line.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.text.line_to_char(line)
+    }
+
+    /// The index after the last character of a line, excluding the line break.
+    pub fn line_end(&self, line: usize) -> usize {
+        if line + 1 < self.line_count() {
+            self.text.line_to_char(line + 1) - 1
+        } else {
+            self.text.len_chars()
+        }
+    }
+
+    /// The line containing the character at `index`.
+    pub fn line_of(&self, index: usize) -> usize {
+        self.text.char_to_line(index)
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// The selected range, `None` if nothing is selected.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        match anchor.cmp(&self.caret) {
+            std::cmp::Ordering::Less => Some(anchor..self.caret),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(self.caret..anchor),
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection()
+            .map(|range| self.text.slice(range).to_string())
+    }
+
+    /// Return and clear the lines that changed since the last call.
+    ///
+    /// Views use this to update their line layouts incrementally.
+    pub fn take_line_changes(&mut self) -> Vec<LineChange> {
+        mem::take(&mut self.line_changes)
+    }
+
+    /// Insert text at the caret, replacing the selection.
+    pub fn insert(&mut self, text: &str) {
+        let text = normalize(text);
+        // Line breaks end a typing sequence, so that lines are undone one by one.
+        let kind = if text.contains('\n') {
+            EditKind::Other
+        } else {
+            EditKind::Typing
+        };
+        let range = self.selection().unwrap_or(self.caret..self.caret);
+        self.replace(range, &text, kind);
+    }
+
+    /// Delete the selection or the character before the caret.
+    pub fn delete_backward(&mut self, word: bool) {
+        if self.delete_selection() {
+            return;
+        }
+        let start = if word {
+            self.previous_word(self.caret)
+        } else {
+            self.caret.saturating_sub(1)
+        };
+        self.replace(start..self.caret, "", EditKind::DeleteBackward);
+    }
+
+    /// Delete the selection or the character after the caret.
+    pub fn delete_forward(&mut self, word: bool) {
+        if self.delete_selection() {
+            return;
+        }
+        let end = if word {
+            self.next_word(self.caret)
+        } else {
+            (self.caret + 1).min(self.text.len_chars())
+        };
+        self.replace(self.caret..end, "", EditKind::DeleteForward);
+    }
+
+    /// Delete the selected text. Returns `false` if nothing was selected.
+    pub fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+        self.replace(range, "", EditKind::Other);
+        true
+    }
+
+    /// Move the caret one character or word to the left.
+    ///
+    /// Without `extend`, a selection collapses to its start.
+    pub fn move_left(&mut self, word: bool, extend: bool) {
+        let target = match (self.selection(), extend) {
+            (Some(range), false) => range.start,
+            _ if word => self.previous_word(self.caret),
+            _ => self.caret.saturating_sub(1),
+        };
+        self.move_to(target, extend);
+    }
+
+    /// Move the caret one character or word to the right.
+    ///
+    /// Without `extend`, a selection collapses to its end.
+    pub fn move_right(&mut self, word: bool, extend: bool) {
+        let target = match (self.selection(), extend) {
+            (Some(range), false) => range.end,
+            _ if word => self.next_word(self.caret),
+            _ => self.caret + 1,
+        };
+        self.move_to(target, extend);
+    }
+
+    /// Move the caret to the start of its line.
+    pub fn move_line_start(&mut self, extend: bool) {
+        self.move_to(self.line_start(self.line_of(self.caret)), extend);
+    }
+
+    /// Move the caret to the end of its line.
+    pub fn move_line_end(&mut self, extend: bool) {
+        self.move_to(self.line_end(self.line_of(self.caret)), extend);
+    }
+
+    pub fn move_document_start(&mut self, extend: bool) {
+        self.move_to(0, extend);
+    }
+
+    pub fn move_document_end(&mut self, extend: bool) {
+        self.move_to(self.text.len_chars(), extend);
+    }
+
+    /// Move the caret to `index`. If `extend` is set, the selection is extended to it.
+    ///
+    /// `index` is clamped to the text.
+    pub fn move_to(&mut self, index: usize, extend: bool) {
+        if extend {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = index.min(self.text.len_chars());
+        self.history.merge = None;
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.text.len_chars();
+        self.history.merge = None;
+    }
+
+    /// Select the word at `index`.
+    pub fn select_word(&mut self, index: usize) {
+        self.move_to(index, false);
+        let next = (self.caret + 1).min(self.text.len_chars());
+        let start = self.previous_word(next);
+        let end = self.next_word(start);
+        self.anchor = Some(start);
+        self.caret = end;
+    }
+
+    /// Select the line at `index`, including its line break.
+    pub fn select_line(&mut self, index: usize) {
+        let line = self.line_of(index.min(self.text.len_chars()));
+        let end = if line + 1 < self.line_count() {
+            self.line_start(line + 1)
+        } else {
+            self.text.len_chars()
+        };
+        self.move_to(self.line_start(line), false);
+        self.move_to(end, true);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    /// Revert the last edit. Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.history.undo.pop() else {
+            return false;
+        };
+        let inserted = edit.start..edit.start + edit.inserted.chars().count();
+        self.splice(inserted, &edit.removed);
+        (self.caret, self.anchor) = edit.before;
+        self.history.redo.push(edit);
+        self.history.merge = None;
+        true
+    }
+
+    /// Reapply the last undone edit. Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.history.redo.pop() else {
+            return false;
+        };
+        let removed = edit.start..edit.start + edit.removed.chars().count();
+        self.splice(removed, &edit.inserted);
+        (self.caret, self.anchor) = edit.after;
+        self.history.undo.push(edit);
+        self.history.merge = None;
+        true
+    }
+
+    /// Replace `range` with `text`, place the caret after it, and record the edit.
+    fn replace(&mut self, range: Range<usize>, text: &str, kind: EditKind) {
+        if range.is_empty() && text.is_empty() {
+            return;
+        }
+        let before = (self.caret, self.anchor);
+        let start = range.start;
+        let removed = self.splice(range, text);
+        self.caret = start + text.chars().count();
+        self.anchor = None;
+        let after = (self.caret, self.anchor);
+
+        self.history.redo.clear();
+        let merged = match (self.history.undo.last_mut(), self.history.merge) {
+            (Some(last), Some(merge)) if merge == kind && kind != EditKind::Other => {
+                last.merge(start, &removed, text, kind)
+            }
+            _ => false,
+        };
+        match self.history.undo.last_mut() {
+            Some(last) if merged => last.after = after,
+            _ => self.history.undo.push(Edit {
+                start,
+                removed,
+                inserted: text.into(),
+                before,
+                after,
+            }),
+        }
+        self.history.merge = Some(kind);
+    }
+
+    /// Replace `range` with `text` and return the removed text.
+    fn splice(&mut self, range: Range<usize>, text: &str) -> String {
+        let removed = self.text.slice(range.clone()).to_string();
+        let first = self.text.char_to_line(range.start);
+        self.text.remove(range.clone());
+        self.text.insert(range.start, text);
+        self.line_changes.push(LineChange {
+            first,
+            removed: line_breaks(&removed),
+            inserted: line_breaks(text),
+        });
+        self.revision += 1;
+        removed
+    }
+
+    /// The start of the word before `index`, skipping whitespace.
+    fn previous_word(&self, mut index: usize) -> usize {
+        let mut chars = self.text.chars_at(index);
+        let mut c = chars.prev();
+        while c.is_some_and(char::is_whitespace) {
+            index -= 1;
+            c = chars.prev();
+        }
+        match c {
+            Some(c) if is_word_char(c) => {
+                let mut c = Some(c);
+                while c.is_some_and(is_word_char) {
+                    index -= 1;
+                    c = chars.prev();
+                }
+                index
+            }
+            // Punctuation counts as a word of its own.
+            Some(_) => index - 1,
+            None => index,
+        }
+    }
+
+    /// The end of the word after `index`, skipping whitespace.
+    fn next_word(&self, mut index: usize) -> usize {
+        let mut chars = self.text.chars_at(index);
+        let mut c = chars.next();
+        while c.is_some_and(char::is_whitespace) {
+            index += 1;
+            c = chars.next();
+        }
+        match c {
+            Some(c) if is_word_char(c) => {
+                let mut c = Some(c);
+                while c.is_some_and(is_word_char) {
+                    index += 1;
+                    c = chars.next();
+                }
+                index
+            }
+            // Punctuation counts as a word of its own.
+            Some(_) => index + 1,
+            None => index,
+        }
+    }
+}
+
+impl Edit {
+    /// Merge a following edit into this one. Returns `false` if the edits are not adjacent.
+    fn merge(&mut self, start: usize, removed: &str, inserted: &str, kind: EditKind) -> bool {
+        match kind {
+            EditKind::Typing
+                if removed.is_empty()
+                    && start == self.start + self.inserted.chars().count()
+                    // Words are undone one by one.
+                    && (!inserted.starts_with(char::is_whitespace)
+                        || self.inserted.ends_with(char::is_whitespace)) =>
+            {
+                self.inserted.push_str(inserted);
+                true
+            }
+            EditKind::DeleteBackward
+                if self.inserted.is_empty() && start + removed.chars().count() == self.start =>
+            {
+                self.start = start;
+                self.removed.insert_str(0, removed);
+                true
+            }
+            EditKind::DeleteForward if self.inserted.is_empty() && start == self.start => {
+                self.removed.push_str(removed);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The spaces a tab is expanded to.
+pub const TAB: &str = "    ";
+
+/// Normalize line breaks to `\n`, expand tabs, and remove other control characters.
+fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.replace("\r\n", "\n").chars() {
+        match c {
+            '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}' => {
+                normalized.push('\n')
+            }
+            '\t' => normalized.push_str(TAB),
+            c if c.is_control() => {}
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+fn line_breaks(text: &str) -> usize {
+    text.chars().filter(|c| *c == '\n').count()
+}
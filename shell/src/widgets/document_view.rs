@@ -0,0 +1,1537 @@
+use std::{borrow::Cow, collections::BTreeMap, ops::Range};
+
+use cosmic_text as text;
+use web_time::Instant;
+use winit::{
+    event::{ElementState, KeyEvent, MouseScrollDelta, WindowEvent},
+    keyboard::{Key, ModifiersState, NamedKey},
+};
+
+use massive_geometry::{Color, Matrix4, Rect, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::{GlyphRun, Quad, TextWeight};
+
+use crate::FontFallbackCache;
+
+use super::{
+    gutter::GutterRun,
+    line_layout::{rect, run_glyph, run_metrics},
+    scrollbar::scrollbar_matrix,
+    selection_region, Clipboard, Gutter, GutterContent, GutterMarker, GutterSide, KineticScroll,
+    LineLayout, Scrollbar, ScrollbarHit, ScrollbarStyle,
+};
+
+/// The distance arrow keys scroll, in pixels.
+const LINE_SCROLL: f64 = 40.0;
+/// The distance one mouse wheel step scrolls, in pixels.
+const WHEEL_SCROLL: f64 = 120.0;
+/// How far an anchor may be below the top of the viewport to be the current one, in pixels.
+const ANCHOR_TOLERANCE: f64 = 1.0;
+
+/// A paragraph of attributed text.
+#[derive(Debug, Clone, Default)]
+pub struct Paragraph {
+    pub spans: Vec<Span>,
+    pub style: ParagraphStyle,
+    /// Makes the paragraph a heading that can be navigated to.
+    pub anchor: Option<Anchor>,
+    /// Marks the paragraph in the [`GutterContent::Markers`] slots of the gutter, see
+    /// [`DocumentView::set_gutter`].
+    pub marker: Option<GutterMarker>,
+}
+
+/// Identifies a heading in a [`DocumentView`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    pub id: String,
+    /// The heading level, starting with 1 for top level headings.
+    pub level: u8,
+}
+
+/// An entry of a document's outline.
+#[derive(Debug, Clone, Copy)]
+pub struct OutlineEntry<'a> {
+    pub anchor: &'a Anchor,
+    /// The text of the heading.
+    pub title: &'a str,
+    pub paragraph: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParagraphStyle {
+    pub font_size: f32,
+    /// The height of a line relative to the font size.
+    pub line_height: f32,
+    /// The space below the paragraph in pixels.
+    pub spacing: f64,
+}
+
+impl Default for ParagraphStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 18.0,
+            line_height: 1.4,
+            spacing: 12.0,
+        }
+    }
+}
+
+/// A part of a paragraph's text with its own style.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpanStyle {
+    pub color: Color,
+    pub weight: TextWeight,
+    pub italic: bool,
+    pub monospace: bool,
+    /// Tints the span's text on every line it wraps to, for example to mark changes inside a
+    /// line.
+    pub background: Option<Color>,
+    /// Strikes the span's text through in its color, for example to mark deleted text.
+    pub strikethrough: bool,
+}
+
+impl Default for SpanStyle {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            weight: TextWeight::NORMAL,
+            italic: false,
+            monospace: false,
+            background: None,
+            strikethrough: false,
+        }
+    }
+}
+
+impl Paragraph {
+    pub fn new(style: ParagraphStyle) -> Self {
+        Self {
+            spans: Vec::new(),
+            style,
+            anchor: None,
+            marker: None,
+        }
+    }
+
+    /// Make the paragraph a heading with an anchor.
+    pub fn anchor(mut self, id: impl Into<String>, level: u8) -> Self {
+        self.anchor = Some(Anchor {
+            id: id.into(),
+            level,
+        });
+        self
+    }
+
+    /// Mark the paragraph in the gutter.
+    pub fn marker(mut self, marker: GutterMarker) -> Self {
+        self.marker = Some(marker);
+        self
+    }
+
+    /// Append a span.
+    pub fn span(mut self, text: impl Into<String>, style: SpanStyle) -> Self {
+        self.spans.push(Span {
+            text: text.into(),
+            style,
+        });
+        self
+    }
+
+    /// The text of all spans.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn line_height(&self) -> f64 {
+        (self.style.font_size * self.style.line_height) as f64
+    }
+
+    /// The byte range of each span in the paragraph's text.
+    fn span_ranges(&self) -> impl Iterator<Item = (Range<usize>, &Span)> {
+        let mut start = 0;
+        self.spans.iter().map(move |span| {
+            let range = start..start + span.text.len();
+            start = range.end;
+            (range, span)
+        })
+    }
+}
+
+impl SpanStyle {
+    fn attrs(&self) -> text::Attrs<'static> {
+        let style = if self.italic {
+            text::Style::Italic
+        } else {
+            text::Style::Normal
+        };
+        let family = if self.monospace {
+            text::Family::Monospace
+        } else {
+            text::Family::SansSerif
+        };
+        text::Attrs::new()
+            .family(family)
+            .style(style)
+            .weight(text::Weight(self.weight.0))
+    }
+}
+
+/// A position in the text of a [`DocumentView`]: A paragraph and a byte offset into its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextPosition {
+    pub paragraph: usize,
+    pub offset: usize,
+}
+
+/// A read-only view of attributed paragraphs with wrapping, smooth and kinetic scrolling, and
+/// text selection.
+///
+/// Paragraphs are wrapped at the view's width and laid out when they become visible for the
+/// first time. Only visible paragraphs have shapes. Heights of paragraphs that were not laid out
+/// yet are estimated. Ranges of paragraphs can be folded into a placeholder line, see
+/// [`Self::fold`].
+///
+/// The view's shapes are placed at `position`, with the top left corner of the viewport at the
+/// origin. All coordinates the view takes and returns are in pixels relative to that origin.
+///
+/// After handling events, call [`Self::update`] to update the shapes, and send the changes with
+/// [`Director::action`]. As long as `update` returns `true`, the view is scrolling and needs to
+/// be updated for every frame, for example in response to
+/// [`WindowEvent::RedrawRequested`] after [`crate::ShellWindow::request_redraw`].
+#[derive(Debug)]
+pub struct DocumentView {
+    paragraphs: Vec<Entry>,
+    /// The top of each paragraph, followed by the height of the document.
+    tops: Vec<f64>,
+    width: f64,
+    /// The margin beside the text for line numbers and annotations.
+    gutter: Gutter,
+    viewport_height: f64,
+    position: Handle<Position>,
+    selection_color: Color,
+    selection_radius: f64,
+    scroll: KineticScroll,
+    /// Anchor and focus of the selection.
+    selection: Option<(TextPosition, TextPosition)>,
+    selecting: bool,
+    clipboard: Clipboard,
+    modifiers: ModifiersState,
+    /// The paragraphs that may have shapes.
+    shown: Range<usize>,
+    /// The paragraphs with anchors.
+    anchors: Vec<usize>,
+    /// The paragraph of the current anchor.
+    current_anchor: Option<usize>,
+    anchor_changed: bool,
+    /// The paragraph scrolling is animated to, and the scroll target set for it.
+    ///
+    /// Layouts of the paragraphs passed on the way change the paragraph's top, so the target is
+    /// adjusted in every update.
+    navigation: Option<(usize, f64)>,
+    /// The selection needs to be regenerated.
+    dirty: bool,
+    shapes: Option<Shapes>,
+    /// Records the faces fallback resolved characters to while paragraphs are laid out.
+    font_fallback_cache: Option<FontFallbackCache>,
+    /// The style of the placeholders of folded paragraphs.
+    fold_style: SpanStyle,
+    scrollbar_style: Option<ScrollbarStyle>,
+    /// Created with the next update after a scrollbar style was set.
+    scrollbar: Option<ScrollbarShapes>,
+}
+
+#[derive(Debug)]
+struct ScrollbarShapes {
+    matrix: Handle<Matrix4>,
+    /// The width of the view the scrollbar was placed at.
+    width: f64,
+    scrollbar: Scrollbar,
+}
+
+#[derive(Debug)]
+struct Shapes {
+    scroll: Handle<Matrix4>,
+    position: Handle<Position>,
+    selection: Handle<PositionedShape>,
+    scroll_top: f64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    paragraph: Paragraph,
+    text: String,
+    /// `None` if the paragraph was not laid out yet.
+    lines: Option<Vec<VisualLine>>,
+    /// The annotations of the paragraph by gutter slot.
+    annotations: BTreeMap<usize, GutterMarker>,
+    fold: Fold,
+    /// The laid out or estimated height, including the spacing.
+    height: f64,
+    /// The shapes while the paragraph is visible.
+    shapes: Option<ParagraphShapes>,
+}
+
+/// Whether a paragraph is shown or folded, see [`DocumentView::fold`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fold {
+    Open,
+    /// The first paragraph of a fold shows a placeholder for all `len` paragraphs of the fold.
+    Placeholder {
+        len: usize,
+    },
+    /// The other paragraphs of a fold have no lines and no height.
+    Hidden,
+}
+
+#[derive(Debug)]
+struct VisualLine {
+    /// The top relative to the paragraph.
+    top: f64,
+    /// The baseline relative to the paragraph.
+    baseline: f64,
+    layout: LineLayout,
+    /// One run for each span style in the line.
+    runs: Vec<GlyphRun>,
+}
+
+#[derive(Debug)]
+struct ParagraphShapes {
+    /// Places the paragraph at its top.
+    matrix: Handle<Matrix4>,
+    top: f64,
+    _shapes: Vec<Handle<PositionedShape>>,
+}
+
+impl DocumentView {
+    pub fn new(
+        position: Handle<Position>,
+        paragraphs: Vec<Paragraph>,
+        width: f64,
+        viewport_height: f64,
+    ) -> Self {
+        let mut view = Self {
+            paragraphs: Vec::new(),
+            tops: vec![0.0],
+            width,
+            gutter: Gutter::default(),
+            viewport_height,
+            position,
+            selection_color: Color::rgb(0.7, 0.8, 1.0),
+            selection_radius: 0.0,
+            scroll: KineticScroll::default(),
+            selection: None,
+            selecting: false,
+            clipboard: Clipboard::new(),
+            modifiers: ModifiersState::default(),
+            shown: 0..0,
+            anchors: Vec::new(),
+            current_anchor: None,
+            anchor_changed: false,
+            navigation: None,
+            dirty: true,
+            shapes: None,
+            font_fallback_cache: None,
+            fold_style: SpanStyle {
+                color: Color::rgb(0.5, 0.5, 0.5),
+                italic: true,
+                ..SpanStyle::default()
+            },
+            scrollbar_style: None,
+            scrollbar: None,
+        };
+        view.set_paragraphs(paragraphs);
+        view
+    }
+
+    /// Set the cache that records the fallback faces of the laid out text and requests them
+    /// directly the next time. Returns the previous one.
+    pub fn set_font_fallback_cache(
+        &mut self,
+        cache: Option<FontFallbackCache>,
+    ) -> Option<FontFallbackCache> {
+        std::mem::replace(&mut self.font_fallback_cache, cache)
+    }
+
+    pub fn paragraphs(&self) -> impl Iterator<Item = &Paragraph> {
+        self.paragraphs.iter().map(|entry| &entry.paragraph)
+    }
+
+    /// Replace the content, scroll to the top, clear the selection, and unfold all paragraphs.
+    pub fn set_paragraphs(&mut self, paragraphs: Vec<Paragraph>) {
+        let width = self.text_width();
+        self.paragraphs = paragraphs
+            .into_iter()
+            .map(|paragraph| {
+                let text = paragraph.text();
+                let height = estimate_height(&paragraph, &text, width);
+                Entry {
+                    paragraph,
+                    text,
+                    lines: None,
+                    annotations: BTreeMap::new(),
+                    fold: Fold::Open,
+                    height,
+                    shapes: None,
+                }
+            })
+            .collect();
+        self.anchors = (0..self.paragraphs.len())
+            .filter(|index| self.paragraphs[*index].paragraph.anchor.is_some())
+            .collect();
+        self.update_tops();
+        self.scroll.set_offset(0.0);
+        self.selection = None;
+        self.shown = 0..0;
+        self.current_anchor = None;
+        self.anchor_changed = true;
+        self.navigation = None;
+        self.dirty = true;
+    }
+
+    /// The headings with anchors, in document order.
+    pub fn outline(&self) -> impl Iterator<Item = OutlineEntry<'_>> {
+        self.anchors.iter().map(|index| {
+            let entry = &self.paragraphs[*index];
+            OutlineEntry {
+                anchor: entry.paragraph.anchor.as_ref().unwrap(),
+                title: &entry.text,
+                paragraph: *index,
+            }
+        })
+    }
+
+    /// Scroll the heading with the anchor `id` to the top of the viewport. A folded heading is
+    /// scrolled to the placeholder of its fold.
+    ///
+    /// Returns `false` if there is no such anchor.
+    pub fn scroll_to_anchor(&mut self, id: &str, animate: bool) -> bool {
+        let Some(paragraph) = self.anchors.iter().copied().find(|index| {
+            self.paragraphs[*index]
+                .paragraph
+                .anchor
+                .as_ref()
+                .is_some_and(|anchor| anchor.id == id)
+        }) else {
+            return false;
+        };
+        let paragraph = self
+            .folded_range(paragraph)
+            .map_or(paragraph, |range| range.start);
+
+        let top = self.tops[paragraph];
+        if animate {
+            self.scroll.scroll_smoothly_to(top);
+            self.navigation = self.scroll.target().map(|target| (paragraph, target));
+        } else {
+            self.scroll.set_offset(top);
+            self.navigation = None;
+        }
+        true
+    }
+
+    /// The anchor of the section at the top of the viewport, as of the last update.
+    ///
+    /// This is the last anchor at or above the top of the viewport. When the view is scrolled to
+    /// the end, it's the last anchor inside the viewport.
+    pub fn current_anchor(&self) -> Option<&Anchor> {
+        self.paragraphs[self.current_anchor?]
+            .paragraph
+            .anchor
+            .as_ref()
+    }
+
+    /// Returns the current anchor if it changed since the last call, for example to sync a table
+    /// of contents.
+    pub fn take_anchor_change(&mut self) -> Option<Option<&Anchor>> {
+        if !self.anchor_changed {
+            return None;
+        }
+        self.anchor_changed = false;
+        Some(self.current_anchor())
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn set_selection_color(&mut self, color: Color) {
+        self.selection_color = color;
+        self.dirty = true;
+    }
+
+    /// Set the corner radius of the selection, zero for square corners.
+    pub fn set_selection_radius(&mut self, radius: f64) {
+        self.selection_radius = radius;
+        self.dirty = true;
+    }
+
+    /// Show a scrollbar at the right edge of the view, on top of the text. `None` removes it.
+    pub fn set_scrollbar(&mut self, style: Option<ScrollbarStyle>) {
+        self.scrollbar_style = style;
+        match (style, &mut self.scrollbar) {
+            (Some(style), Some(shapes)) => shapes.scrollbar.set_style(style),
+            (None, _) => self.scrollbar = None,
+            (Some(_), None) => {}
+        }
+    }
+
+    /// Set the width of the view. Paragraphs are wrapped at the width without the gutter.
+    ///
+    /// All paragraphs are laid out again. The paragraph at the top of the viewport stays there.
+    pub fn set_width(&mut self, width: f64) {
+        if width != self.width {
+            self.rewrap(|view| view.width = width);
+        }
+    }
+
+    pub fn gutter(&self) -> &Gutter {
+        &self.gutter
+    }
+
+    /// Set the margin beside the text for line numbers and annotations.
+    ///
+    /// If the width of the gutter changes, all paragraphs are laid out again, like with
+    /// [`Self::set_width`].
+    pub fn set_gutter(&mut self, gutter: Gutter) {
+        if gutter == self.gutter {
+            return;
+        }
+        if gutter.width() != self.gutter.width() {
+            self.rewrap(|view| view.gutter = gutter);
+        } else {
+            self.gutter = gutter;
+            self.drop_shapes();
+        }
+    }
+
+    /// Show `annotation` in the gutter `slot` of a paragraph, or remove it with `None`.
+    ///
+    /// The slot needs to show [`GutterContent::Annotations`]. Annotations are kept until the
+    /// paragraphs are replaced.
+    pub fn set_annotation(
+        &mut self,
+        paragraph: usize,
+        slot: usize,
+        annotation: Option<GutterMarker>,
+    ) {
+        let Some(entry) = self.paragraphs.get_mut(paragraph) else {
+            return;
+        };
+        let changed = match annotation {
+            Some(annotation) => {
+                entry.annotations.insert(slot, annotation.clone()) != Some(annotation)
+            }
+            None => entry.annotations.remove(&slot).is_some(),
+        };
+        if changed {
+            // Created again with the next update.
+            entry.shapes = None;
+        }
+    }
+
+    /// The annotation in the gutter `slot` of a paragraph.
+    pub fn annotation(&self, paragraph: usize, slot: usize) -> Option<&GutterMarker> {
+        self.paragraphs.get(paragraph)?.annotations.get(&slot)
+    }
+
+    /// The top of each visual line of a paragraph relative to the top of the document, as of
+    /// the last update. Empty if the paragraph was not laid out yet.
+    ///
+    /// Use this to place content next to wrapped lines.
+    pub fn line_tops(&self, paragraph: usize) -> impl Iterator<Item = f64> + '_ {
+        let top = self.tops.get(paragraph).copied().unwrap_or_default();
+        self.paragraphs
+            .get(paragraph)
+            .and_then(|entry| entry.lines.as_deref())
+            .unwrap_or_default()
+            .iter()
+            .map(move |line| top + line.top)
+    }
+
+    /// Fold the paragraphs in `range` into a single placeholder line, like "… 42 lines".
+    ///
+    /// Folds that overlap `range` are merged into it. The placeholder shows the gutter of the
+    /// first paragraph of the fold, and hit tests to its start. Selections that touch a fold
+    /// highlight its placeholder, and the selected text includes the folded text. The paragraph
+    /// at the top of the viewport stays there, or the fold if it contains that paragraph.
+    ///
+    /// Folds are kept until they are unfolded or the paragraphs are replaced.
+    pub fn fold(&mut self, range: Range<usize>) {
+        let mut range = range.start..range.end.min(self.paragraphs.len());
+        if range.is_empty() {
+            return;
+        }
+        if let Some(folded) = self.folded_range(range.start) {
+            range.start = folded.start;
+        }
+        if let Some(folded) = self.folded_range(range.end - 1) {
+            range.end = folded.end;
+        }
+        let start = range.start;
+        let len = range.len();
+        self.set_folds(range, |index| {
+            if index == start {
+                Fold::Placeholder { len }
+            } else {
+                Fold::Hidden
+            }
+        });
+    }
+
+    /// Unfold the fold that contains `paragraph`. Returns `false` if the paragraph is not
+    /// folded.
+    pub fn unfold(&mut self, paragraph: usize) -> bool {
+        let Some(range) = self.folded_range(paragraph) else {
+            return false;
+        };
+        self.set_folds(range, |_| Fold::Open);
+        true
+    }
+
+    pub fn unfold_all(&mut self) {
+        for range in self.folds().collect::<Vec<_>>() {
+            self.set_folds(range, |_| Fold::Open);
+        }
+    }
+
+    /// The paragraphs of the fold that contains `paragraph`, `None` if it is not folded.
+    ///
+    /// Use this with [`Self::hit_test`] to unfold a placeholder that was clicked.
+    pub fn folded_range(&self, paragraph: usize) -> Option<Range<usize>> {
+        let start = match self.paragraphs.get(paragraph)?.fold {
+            Fold::Open => return None,
+            Fold::Placeholder { .. } => paragraph,
+            Fold::Hidden => self.paragraphs[..paragraph]
+                .iter()
+                .rposition(|entry| matches!(entry.fold, Fold::Placeholder { .. }))?,
+        };
+        let Fold::Placeholder { len } = self.paragraphs[start].fold else {
+            unreachable!()
+        };
+        Some(start..start + len)
+    }
+
+    /// The ranges of folded paragraphs, in document order.
+    pub fn folds(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.paragraphs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry.fold {
+                Fold::Placeholder { len } => Some(index..index + len),
+                _ => None,
+            })
+    }
+
+    /// Set the style of the placeholders that are shown instead of folded paragraphs.
+    pub fn set_fold_style(&mut self, style: SpanStyle) {
+        if style == self.fold_style {
+            return;
+        }
+        self.fold_style = style;
+        for entry in &mut self.paragraphs {
+            if let Fold::Placeholder { .. } = entry.fold {
+                entry.lines = None;
+                entry.shapes = None;
+            }
+        }
+    }
+
+    /// Change the folds of the paragraphs in `range` and lay them out again, keeping the
+    /// content at the top of the viewport in place.
+    fn set_folds(&mut self, range: Range<usize>, fold: impl Fn(usize) -> Fold) {
+        let offset = self.scroll.offset();
+        let mut anchor = self.paragraph_at(offset);
+        if range.contains(&anchor) {
+            anchor = range.start;
+        }
+        let within = (offset - self.tops[anchor]).max(0.0);
+
+        let width = self.text_width();
+        for index in range {
+            let entry = &mut self.paragraphs[index];
+            entry.fold = fold(index);
+            entry.lines = None;
+            entry.shapes = None;
+            entry.height = entry.estimate_height(width);
+        }
+        self.update_tops();
+
+        let height = self
+            .paragraphs
+            .get(anchor)
+            .map_or(0.0, |entry| entry.height);
+        self.scroll
+            .set_offset(self.tops[anchor] + within.min(height));
+        self.dirty = true;
+    }
+
+    /// The width paragraphs are wrapped at.
+    fn text_width(&self) -> f64 {
+        (self.width - self.gutter.width()).max(0.0)
+    }
+
+    /// The left edge of the text.
+    fn text_left(&self) -> f64 {
+        match self.gutter.side {
+            GutterSide::Left => self.gutter.width(),
+            GutterSide::Right => 0.0,
+        }
+    }
+
+    /// Drop the shapes of all paragraphs, they are created again with the next update.
+    fn drop_shapes(&mut self) {
+        for entry in &mut self.paragraphs {
+            entry.shapes = None;
+        }
+    }
+
+    /// Apply a change of the widths and lay out all paragraphs again, keeping the paragraph at
+    /// the top of the viewport in place.
+    fn rewrap(&mut self, change: impl FnOnce(&mut Self)) {
+        let offset = self.scroll.offset();
+        let anchor = self.paragraph_at(offset);
+        let within = self
+            .paragraphs
+            .get(anchor)
+            .map_or(0.0, |entry| (offset - self.tops[anchor]) / entry.height);
+
+        change(self);
+        let width = self.text_width();
+        for entry in &mut self.paragraphs {
+            entry.lines = None;
+            entry.shapes = None;
+            entry.height = entry.estimate_height(width);
+        }
+        self.update_tops();
+
+        let offset = self
+            .paragraphs
+            .get(anchor)
+            .map_or(0.0, |entry| self.tops[anchor] + within * entry.height);
+        self.scroll.set_offset(offset);
+        self.dirty = true;
+    }
+
+    /// Shape all text again with the next update, for example after fonts were reloaded.
+    ///
+    /// Keeps the scroll offset.
+    pub fn relayout(&mut self) {
+        for entry in &mut self.paragraphs {
+            entry.lines = None;
+            entry.shapes = None;
+        }
+        self.dirty = true;
+    }
+
+    pub fn viewport_height(&self) -> f64 {
+        self.viewport_height
+    }
+
+    pub fn set_viewport_height(&mut self, height: f64) {
+        self.viewport_height = height;
+        self.scroll.set_max(self.content_height() - height);
+    }
+
+    /// The height of all paragraphs. Includes estimates for paragraphs that were not laid out
+    /// yet.
+    pub fn content_height(&self) -> f64 {
+        *self.tops.last().unwrap()
+    }
+
+    pub fn scroll_top(&self) -> f64 {
+        self.scroll.offset()
+    }
+
+    /// Control scrolling directly, for example to pan and fling with touch input.
+    pub fn scroll_mut(&mut self) -> &mut KineticScroll {
+        &mut self.scroll
+    }
+
+    /// The selected range, `None` if nothing is selected.
+    pub fn selection(&self) -> Option<Range<TextPosition>> {
+        let (anchor, focus) = self.selection?;
+        match anchor.cmp(&focus) {
+            std::cmp::Ordering::Less => Some(anchor..focus),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(focus..anchor),
+        }
+    }
+
+    /// The selected text. Paragraphs are separated by line breaks.
+    pub fn selected_text(&self) -> Option<String> {
+        let Range { start, end } = self.selection()?;
+        let text = (start.paragraph..=end.paragraph)
+            .map(|index| {
+                let text = &self.paragraphs[index].text;
+                let from = if index == start.paragraph {
+                    start.offset
+                } else {
+                    0
+                };
+                let to = if index == end.paragraph {
+                    end.offset
+                } else {
+                    text.len()
+                };
+                &text[from..to]
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(text)
+    }
+
+    pub fn select_all(&mut self) {
+        let Some(last) = self.paragraphs.len().checked_sub(1) else {
+            return;
+        };
+        self.selection = Some((
+            TextPosition {
+                paragraph: 0,
+                offset: 0,
+            },
+            TextPosition {
+                paragraph: last,
+                offset: self.paragraphs[last].text.len(),
+            },
+        ));
+        self.dirty = true;
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+        self.dirty = true;
+    }
+
+    /// Handle keyboard and mouse wheel events. Returns `true` if the event was handled.
+    ///
+    /// Pointer events need to be transformed to the view's coordinates first and are handled by
+    /// [`Self::pointer_pressed`] and its siblings.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => self
+                        .scroll
+                        .scroll_smoothly_by(-*lines as f64 * WHEEL_SCROLL),
+                    // Touchpads deliver their own momentum.
+                    MouseScrollDelta::PixelDelta(delta) => self.scroll.scroll_by(-delta.y),
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    event @ KeyEvent {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.handle_key(event),
+            _ => false,
+        }
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent) -> bool {
+        let primary = if cfg!(target_os = "macos") {
+            self.modifiers.super_key()
+        } else {
+            self.modifiers.control_key()
+        };
+        let shift = self.modifiers.shift_key();
+        let page = (self.viewport_height * 0.9).max(LINE_SCROLL);
+
+        let scroll = &mut self.scroll;
+        match &event.logical_key {
+            Key::Named(NamedKey::ArrowUp) => scroll.scroll_smoothly_by(-LINE_SCROLL),
+            Key::Named(NamedKey::ArrowDown) => scroll.scroll_smoothly_by(LINE_SCROLL),
+            Key::Named(NamedKey::PageUp) => scroll.scroll_smoothly_by(-page),
+            Key::Named(NamedKey::PageDown) => scroll.scroll_smoothly_by(page),
+            Key::Named(NamedKey::Space) if shift => scroll.scroll_smoothly_by(-page),
+            Key::Named(NamedKey::Space) => scroll.scroll_smoothly_by(page),
+            Key::Named(NamedKey::Home) => scroll.scroll_smoothly_to(0.0),
+            Key::Named(NamedKey::End) => scroll.scroll_smoothly_to(scroll.max()),
+            Key::Character(c) if primary => match c.to_lowercase().as_str() {
+                "a" => self.select_all(),
+                "c" => {
+                    if let Some(text) = self.selected_text() {
+                        self.clipboard.set_text(&text);
+                    }
+                }
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Start selecting at `(x, y)`, or extend the selection to it. Stops scrolling.
+    pub fn pointer_pressed(&mut self, x: f64, y: f64, extend: bool) {
+        if let Some(shapes) = &mut self.scrollbar {
+            let x = x - (self.width - shapes.scrollbar.style().width);
+            if let Some((hit, offset)) = shapes.scrollbar.pointer_pressed(x, y) {
+                if hit == ScrollbarHit::Thumb {
+                    self.scroll.set_offset(self.scroll.offset());
+                } else {
+                    self.scroll.scroll_smoothly_to(offset);
+                }
+                return;
+            }
+        }
+
+        self.scroll.set_offset(self.scroll.offset());
+        let Some(position) = self.hit_test(x, y) else {
+            return;
+        };
+        self.selection = match self.selection {
+            Some((anchor, _)) if extend => Some((anchor, position)),
+            _ => Some((position, position)),
+        };
+        self.selecting = true;
+        self.dirty = true;
+    }
+
+    /// Extend the selection to `(x, y)` while selecting.
+    pub fn pointer_moved(&mut self, x: f64, y: f64) {
+        if let Some(shapes) = &mut self.scrollbar {
+            let x = x - (self.width - shapes.scrollbar.style().width);
+            if let Some(offset) = shapes.scrollbar.pointer_moved(x, y) {
+                self.scroll.set_offset(offset);
+                return;
+            }
+        }
+        if !self.selecting {
+            return;
+        }
+        if let (Some((anchor, _)), Some(position)) = (self.selection, self.hit_test(x, y)) {
+            self.selection = Some((anchor, position));
+            self.dirty = true;
+        }
+    }
+
+    pub fn pointer_released(&mut self) {
+        self.selecting = false;
+        if let Some(shapes) = &mut self.scrollbar {
+            shapes.scrollbar.pointer_released();
+        }
+    }
+
+    /// The text position nearest to `(x, y)`. `None` if the view is empty.
+    ///
+    /// Paragraphs are hit tested with the layout of the last [`Self::update`]. The placeholder
+    /// of a fold hit tests to the start of its first paragraph.
+    pub fn hit_test(&self, x: f64, y: f64) -> Option<TextPosition> {
+        let y = y + self.scroll.offset();
+        let paragraph = self.paragraph_at(y);
+        let entry = self.paragraphs.get(paragraph)?;
+        if y >= self.content_height() {
+            return Some(TextPosition {
+                paragraph,
+                offset: entry.text.len(),
+            });
+        }
+        let offset = match &entry.lines {
+            Some(_) if entry.fold != Fold::Open => 0,
+            Some(lines) => {
+                let local = y - self.tops[paragraph];
+                let line = lines
+                    .iter()
+                    .rev()
+                    .find(|line| line.top <= local)
+                    .or(lines.first());
+                line.map_or(0, |line| {
+                    line.layout.hit_test((x - self.text_left()) as f32)
+                })
+            }
+            None => 0,
+        };
+        Some(TextPosition { paragraph, offset })
+    }
+
+    /// Advance scrolling to `now` and update the shapes of the visible paragraphs and the
+    /// selection.
+    ///
+    /// Returns `true` while scrolling is animated.
+    pub fn update(
+        &mut self,
+        director: &mut Director,
+        font_system: &mut text::FontSystem,
+        now: Instant,
+    ) -> bool {
+        self.scroll.advance(now);
+        let visible = self.layout_visible(font_system);
+        self.follow_navigation();
+        self.update_current_anchor();
+
+        let shapes = self.shapes.get_or_insert_with(|| {
+            let scroll = director.cast(translation(-self.scroll.offset()));
+            let position = director.cast(Position {
+                parent: Some(self.position.clone()),
+                matrix: scroll.clone(),
+                pin: None,
+                overlay: false,
+            });
+            let selection =
+                director.cast(PositionedShape::new(position.clone(), Vec::<Quad>::new()));
+            Shapes {
+                scroll,
+                position,
+                selection,
+                scroll_top: self.scroll.offset(),
+            }
+        });
+        if shapes.scroll_top != self.scroll.offset() {
+            shapes.scroll_top = self.scroll.offset();
+            shapes.scroll.update(translation(-shapes.scroll_top));
+        }
+        let position = shapes.position.clone();
+        let text_left = self.text_left();
+        let width = self.width;
+        let gutter = &self.gutter;
+        let fold_style = self.fold_style;
+
+        // Remove the shapes of paragraphs that left the viewport.
+        for index in self.shown.clone() {
+            if !visible.contains(&index) {
+                if let Some(entry) = self.paragraphs.get_mut(index) {
+                    entry.shapes = None;
+                }
+            }
+        }
+        if visible != self.shown {
+            self.shown = visible.clone();
+            self.dirty = true;
+        }
+
+        for index in visible {
+            let top = self.tops[index];
+            let entry = &mut self.paragraphs[index];
+            match &mut entry.shapes {
+                Some(shapes) => {
+                    if shapes.top != top {
+                        shapes.top = top;
+                        shapes.matrix.update(translation(top));
+                    }
+                }
+                // Hidden paragraphs have no shapes.
+                None if entry.fold == Fold::Hidden => {}
+                None => {
+                    let matrix = director.cast(translation(top));
+                    let paragraph_position = director.cast(Position {
+                        parent: Some(position.clone()),
+                        matrix: matrix.clone(),
+                        pin: None,
+                        overlay: false,
+                    });
+                    let placeholder = match entry.fold {
+                        Fold::Placeholder { len } => {
+                            Some(placeholder(&entry.paragraph, len, fold_style))
+                        }
+                        _ => None,
+                    };
+                    let lines = entry.lines.as_deref().unwrap_or_default();
+                    let decorations = decorations(
+                        placeholder.as_ref().unwrap_or(&entry.paragraph),
+                        entry.annotations.values(),
+                        lines,
+                        text_left,
+                        width,
+                    );
+                    // Backgrounds are drawn before the text in any case, but keep them first.
+                    let mut shapes: Vec<_> = (!decorations.is_empty())
+                        .then(|| {
+                            director.cast(PositionedShape::new(
+                                paragraph_position.clone(),
+                                decorations,
+                            ))
+                        })
+                        .into_iter()
+                        .collect();
+                    shapes.extend(lines.iter().flat_map(|line| &line.runs).map(|run| {
+                        director.cast(PositionedShape::new(
+                            paragraph_position.clone(),
+                            run.clone(),
+                        ))
+                    }));
+                    let paragraph = &entry.paragraph;
+                    for run in gutter_runs(gutter, width, index, entry) {
+                        let run = run.shape(
+                            font_system,
+                            gutter.side,
+                            paragraph.style.font_size,
+                            paragraph.line_height(),
+                        );
+                        shapes.push(
+                            director.cast(PositionedShape::new(paragraph_position.clone(), run)),
+                        );
+                    }
+                    entry.shapes = Some(ParagraphShapes {
+                        matrix,
+                        top,
+                        _shapes: shapes,
+                    });
+                }
+            }
+        }
+
+        if self.dirty {
+            self.dirty = false;
+            let selection = PositionedShape::new(position, self.selection_quads());
+            self.shapes.as_ref().unwrap().selection.update(selection);
+        }
+
+        self.update_scrollbar(director);
+
+        self.scroll.is_animating()
+    }
+
+    fn update_scrollbar(&mut self, director: &mut Director) {
+        let Some(style) = self.scrollbar_style else {
+            return;
+        };
+        let shapes = self.scrollbar.get_or_insert_with(|| {
+            let matrix = director.cast(scrollbar_matrix(self.width, &style));
+            let position = director.cast(Position {
+                parent: Some(self.position.clone()),
+                matrix: matrix.clone(),
+                pin: None,
+                overlay: false,
+            });
+            ScrollbarShapes {
+                matrix,
+                width: self.width,
+                scrollbar: Scrollbar::new(position, self.viewport_height, style),
+            }
+        });
+        if shapes.width != self.width {
+            shapes.width = self.width;
+            shapes.matrix.update(scrollbar_matrix(self.width, &style));
+        }
+        shapes.scrollbar.set_length(self.viewport_height);
+        shapes.scrollbar.sync(&self.scroll, self.viewport_height);
+        shapes.scrollbar.update(director);
+    }
+
+    /// Adjust the scroll target to the top of the paragraph that is navigated to.
+    fn follow_navigation(&mut self) {
+        let Some((paragraph, target)) = self.navigation else {
+            return;
+        };
+        // Stop following when the animation ended or was replaced.
+        if self.scroll.target() != Some(target) {
+            self.navigation = None;
+            return;
+        }
+        let top = self.tops[paragraph];
+        if top != target {
+            self.scroll.scroll_smoothly_to(top);
+            self.navigation = self.scroll.target().map(|target| (paragraph, target));
+        }
+    }
+
+    fn update_current_anchor(&mut self) {
+        let top = self.scroll.offset();
+        let y = if top >= self.scroll.max() {
+            top + self.viewport_height
+        } else {
+            top
+        } + ANCHOR_TOLERANCE;
+        let count = self.anchors.partition_point(|index| self.tops[*index] <= y);
+        let current = count.checked_sub(1).map(|i| self.anchors[i]);
+        if current != self.current_anchor {
+            self.current_anchor = current;
+            self.anchor_changed = true;
+        }
+    }
+
+    /// Lay out the paragraphs inside the viewport and return their range.
+    fn layout_visible(&mut self, font_system: &mut text::FontSystem) -> Range<usize> {
+        let first = self.paragraph_at(self.scroll.offset());
+        let mut index = first;
+        while index < self.paragraphs.len()
+            && self.tops[index] < self.scroll.offset() + self.viewport_height
+        {
+            let delta = self.layout_paragraph(font_system, index);
+            if index == first && self.tops[first] < self.scroll.offset() {
+                // A paragraph scrolled in from above: Keep the content below it in place.
+                self.scroll
+                    .set_max(self.content_height() - self.viewport_height);
+                self.scroll.shift(delta);
+            }
+            index += 1;
+        }
+        self.scroll
+            .set_max(self.content_height() - self.viewport_height);
+        first..index
+    }
+
+    /// Lay out a paragraph if it was not laid out yet. Returns the change of its height.
+    fn layout_paragraph(&mut self, font_system: &mut text::FontSystem, index: usize) -> f64 {
+        let width = self.text_width();
+        let indent = self.text_left();
+        let entry = &mut self.paragraphs[index];
+        if entry.lines.is_some() {
+            return 0.0;
+        }
+
+        let cache = self.font_fallback_cache.as_mut();
+        let lines = match entry.fold {
+            Fold::Open => layout_paragraph(
+                font_system,
+                cache,
+                &entry.paragraph,
+                &entry.text,
+                width,
+                indent,
+            ),
+            Fold::Placeholder { len } => {
+                let placeholder = placeholder(&entry.paragraph, len, self.fold_style);
+                let text = placeholder.text();
+                layout_paragraph(font_system, cache, &placeholder, &text, width, indent)
+            }
+            Fold::Hidden => Vec::new(),
+        };
+        let height = match entry.fold {
+            Fold::Hidden => 0.0,
+            _ => {
+                lines.len().max(1) as f64 * entry.paragraph.line_height()
+                    + entry.paragraph.style.spacing
+            }
+        };
+        let delta = height - entry.height;
+        entry.height = height;
+        entry.lines = Some(lines);
+
+        if delta != 0.0 {
+            for top in &mut self.tops[index + 1..] {
+                *top += delta;
+            }
+        }
+        delta
+    }
+
+    fn update_tops(&mut self) {
+        self.tops.clear();
+        let mut top = 0.0;
+        self.tops.push(top);
+        for entry in &self.paragraphs {
+            top += entry.height;
+            self.tops.push(top);
+        }
+        self.scroll
+            .set_max(self.content_height() - self.viewport_height);
+    }
+
+    /// The paragraph at `y`, clamped to the paragraphs.
+    fn paragraph_at(&self, y: f64) -> usize {
+        let index = self.tops.partition_point(|top| *top <= y).saturating_sub(1);
+        index.min(self.paragraphs.len().saturating_sub(1))
+    }
+
+    fn selection_quads(&self) -> Vec<Quad> {
+        let Some(Range { start, end }) = self.selection() else {
+            return Vec::new();
+        };
+        let mut rows = Vec::new();
+
+        // A selection that starts inside a fold starts at its placeholder.
+        let first = self
+            .folded_range(start.paragraph)
+            .map_or(start.paragraph, |range| range.start);
+        for index in self.shown.start.max(first)..self.shown.end.min(end.paragraph + 1) {
+            let entry = &self.paragraphs[index];
+            let Some(lines) = &entry.lines else {
+                continue;
+            };
+            if entry.fold != Fold::Open {
+                // Folds are selected as a whole.
+                for line in lines {
+                    let top = self.tops[index] + line.top;
+                    rows.push(Rect {
+                        left: self.text_left(),
+                        top,
+                        right: self.text_left() + line.layout.width() as f64,
+                        bottom: top + entry.paragraph.line_height(),
+                    });
+                }
+                continue;
+            }
+            let from = if index == start.paragraph {
+                start.offset
+            } else {
+                0
+            };
+            // The break between paragraphs is selected, too.
+            let (to, selects_break) = if index == end.paragraph {
+                (end.offset, false)
+            } else {
+                (entry.text.len(), true)
+            };
+            let line_height = entry.paragraph.line_height();
+
+            for (i, line) in lines.iter().enumerate() {
+                let last = i + 1 == lines.len();
+                let range = line.layout.range().unwrap_or(0..0);
+                if from > range.end || to < range.start || (from == to && !selects_break) {
+                    continue;
+                }
+                let left = self.text_left()
+                    + if from > range.start {
+                        line.layout.x_at(from) as f64
+                    } else {
+                        0.0
+                    };
+                let mut right = self.text_left()
+                    + if to < range.end {
+                        line.layout.x_at(to) as f64
+                    } else {
+                        line.layout.width() as f64
+                    };
+                if last && selects_break {
+                    right += entry.paragraph.style.font_size as f64 / 3.0;
+                }
+                if right <= left {
+                    continue;
+                }
+                let top = self.tops[index] + line.top;
+                rows.push(Rect {
+                    left,
+                    top,
+                    right,
+                    bottom: top + line_height,
+                });
+            }
+        }
+
+        selection_region(&rows, self.selection_radius, self.selection_color)
+    }
+}
+
+/// Shape and wrap a paragraph, with its lines indented by `indent`.
+fn layout_paragraph(
+    font_system: &mut text::FontSystem,
+    font_fallback_cache: Option<&mut FontFallbackCache>,
+    paragraph: &Paragraph,
+    text: &str,
+    width: f64,
+    indent: f64,
+) -> Vec<VisualLine> {
+    let mut attrs_list = text::AttrsList::new(text::Attrs::new());
+    let mut start = 0;
+    for (index, span) in paragraph.spans.iter().enumerate() {
+        let end = start + span.text.len();
+        if end > start {
+            attrs_list.add_span(start..end, span.style.attrs().metadata(index));
+        }
+        start = end;
+    }
+
+    let font_size = paragraph.style.font_size;
+    let line_height = paragraph.line_height();
+    let requested_attrs = font_fallback_cache.as_ref().map(|cache| {
+        let requested = attrs_list.clone();
+        cache.apply(font_system, text, &mut attrs_list);
+        requested
+    });
+    let mut buffer = text::BufferLine::new(text, attrs_list, text::Shaping::Advanced);
+    let layout_lines = buffer.layout(font_system, font_size, width as f32, text::Wrap::Word, None);
+    if let (Some(cache), Some(requested_attrs)) = (font_fallback_cache, &requested_attrs) {
+        for line in layout_lines {
+            cache.record_line(font_system, text, requested_attrs, line);
+        }
+    }
+    layout_lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let layout = LineLayout::from_layout_line(line, font_size);
+            let top = index as f64 * line_height;
+            // Center the glyphs vertically inside the line.
+            let padding = ((line_height - layout.height() as f64) / 2.0).max(0.0);
+            let translation = Vector3::new(indent, top + padding, 0.0);
+            let baseline = top + padding + line.max_ascent as f64;
+
+            // Consecutive glyphs with the same color and weight share a run.
+            let mut runs: Vec<(SpanStyle, Vec<_>)> = Vec::new();
+            for glyph in &line.glyphs {
+                let style = paragraph
+                    .spans
+                    .get(glyph.metadata)
+                    .map(|span| span.style)
+                    .unwrap_or_default();
+                match runs.last_mut() {
+                    Some((run_style, glyphs))
+                        if run_style.color == style.color && run_style.weight == style.weight =>
+                    {
+                        glyphs.push(run_glyph(glyph))
+                    }
+                    _ => runs.push((style, vec![run_glyph(glyph)])),
+                }
+            }
+            let runs = runs
+                .into_iter()
+                .map(|(style, glyphs)| {
+                    GlyphRun::new(
+                        translation,
+                        run_metrics(line),
+                        style.color,
+                        style.weight,
+                        glyphs,
+                    )
+                })
+                .collect();
+
+            VisualLine {
+                top,
+                baseline,
+                layout,
+                runs,
+            }
+        })
+        .collect()
+}
+
+/// The backgrounds of a laid out paragraph, its gutter markers, and its spans, and the
+/// strikethroughs of its spans, relative to the top of the paragraph.
+///
+/// They are derived from the visual lines, so they follow the text when it wraps differently.
+fn decorations<'a>(
+    paragraph: &'a Paragraph,
+    annotations: impl IntoIterator<Item = &'a GutterMarker>,
+    lines: &[VisualLine],
+    indent: f64,
+    width: f64,
+) -> Vec<Quad> {
+    let line_height = paragraph.line_height();
+    let font_size = paragraph.style.font_size as f64;
+    let mut quads = Vec::new();
+
+    let backgrounds = paragraph
+        .marker
+        .iter()
+        .chain(annotations)
+        .filter_map(|marker| marker.background);
+    for background in backgrounds {
+        let bottom = lines.len().max(1) as f64 * line_height;
+        quads.push(rect(0.0, 0.0, width, bottom, background));
+    }
+
+    for (range, span) in paragraph.span_ranges() {
+        let style = span.style;
+        if range.is_empty() || (style.background.is_none() && !style.strikethrough) {
+            continue;
+        }
+        for line in lines {
+            let Some(line_range) = line.layout.range() else {
+                continue;
+            };
+            let from = range.start.max(line_range.start);
+            let to = range.end.min(line_range.end);
+            if from >= to {
+                continue;
+            }
+            let left = indent + line.layout.x_at(from) as f64;
+            let right = indent + line.layout.x_at(to) as f64;
+            if let Some(background) = style.background {
+                quads.push(rect(
+                    left,
+                    line.top,
+                    right,
+                    line.top + line_height,
+                    background,
+                ));
+            }
+            if style.strikethrough {
+                // Through the middle of lowercase letters. The line is drawn behind the glyphs,
+                // which hides it where they have the same color.
+                let thickness = (font_size / 14.0).round().max(1.0);
+                let top = (line.baseline - font_size * 0.3 - thickness / 2.0).round();
+                quads.push(rect(left, top, right, top + thickness, style.color));
+            }
+        }
+    }
+
+    quads
+}
+
+/// The texts of the gutter slots of a paragraph.
+fn gutter_runs<'a>(
+    gutter: &'a Gutter,
+    width: f64,
+    index: usize,
+    entry: &'a Entry,
+) -> impl Iterator<Item = GutterRun<'a>> + 'a {
+    gutter
+        .slot_lefts(width)
+        .enumerate()
+        .filter_map(move |(slot_index, (left, slot))| {
+            let (text, color, towards_text) = match &slot.content {
+                GutterContent::LineNumbers { first, color } => {
+                    (Cow::Owned((first + index).to_string()), *color, true)
+                }
+                GutterContent::Markers => {
+                    let marker = entry.paragraph.marker.as_ref()?;
+                    (Cow::Borrowed(marker.text.as_str()), marker.color, false)
+                }
+                GutterContent::Annotations => {
+                    let annotation = entry.annotations.get(&slot_index)?;
+                    (
+                        Cow::Borrowed(annotation.text.as_str()),
+                        annotation.color,
+                        false,
+                    )
+                }
+            };
+            Some(GutterRun {
+                text,
+                color,
+                left,
+                width: slot.width,
+                towards_text,
+            })
+        })
+}
+
+/// The paragraph shown instead of the `len` folded paragraphs starting with `first`.
+fn placeholder(first: &Paragraph, len: usize, style: SpanStyle) -> Paragraph {
+    let text = if len == 1 {
+        "… 1 line".to_string()
+    } else {
+        format!("… {len} lines")
+    };
+    Paragraph {
+        marker: first.marker.clone(),
+        ..Paragraph::new(first.style).span(text, style)
+    }
+}
+
+impl Entry {
+    /// Estimate the height of the paragraph, or of its placeholder if it is folded.
+    fn estimate_height(&self, width: f64) -> f64 {
+        match self.fold {
+            Fold::Open => estimate_height(&self.paragraph, &self.text, width),
+            Fold::Placeholder { .. } => self.paragraph.line_height() + self.paragraph.style.spacing,
+            Fold::Hidden => 0.0,
+        }
+    }
+}
+
+/// Estimate the height of a paragraph that was not laid out yet.
+fn estimate_height(paragraph: &Paragraph, text: &str, width: f64) -> f64 {
+    let average_char_width = paragraph.style.font_size as f64 * 0.5;
+    let text_width = text.chars().count() as f64 * average_char_width;
+    let lines = (text_width / width.max(1.0)).ceil().max(1.0);
+    lines * paragraph.line_height() + paragraph.style.spacing
+}
+
+fn translation(y: f64) -> Matrix4 {
+    Matrix4::from_translation(Vector3::new(0.0, y, 0.0))
+}
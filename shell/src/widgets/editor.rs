@@ -0,0 +1,876 @@
+use std::ops::Range;
+
+use cosmic_text as text;
+use winit::{
+    event::{ElementState, Ime, KeyEvent, WindowEvent},
+    keyboard::{Key, ModifiersState, NamedKey},
+};
+
+use massive_geometry::{Bounds, Color, Matrix4, Rect, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::{Caret, GlyphRun, Quad, TextWeight};
+
+use super::{
+    caret_shape::CaretShape, line_layout::rect, selection_region, Clipboard, Document, LineLayout,
+    Preedit, TAB,
+};
+
+/// A multi-line text editor.
+///
+/// The editor combines the rope backed [`Document`] model with shape generation and event
+/// handling. Lines are laid out incrementally, only lines that changed are shaped again. And only
+/// the lines inside the viewport have shapes, so the cost of an update does not grow with the size
+/// of the document.
+///
+/// The editor's shapes are placed at `position`, with the top left corner of the viewport at the
+/// origin. All coordinates the editor takes and returns are in pixels relative to that origin.
+///
+/// After handling events, call [`Self::update`] to update the shapes, and send the changes with
+/// [`Director::action`].
+#[derive(Debug)]
+pub struct Editor {
+    document: Document,
+    style: EditorStyle,
+    position: Handle<Position>,
+    focused: bool,
+    clipboard: Clipboard,
+    modifiers: ModifiersState,
+    dragging: bool,
+    preedit: Option<Preedit>,
+    viewport_height: f64,
+    /// The height at the bottom of the viewport that is covered, for example by a soft keyboard.
+    bottom_inset: f64,
+    /// Scroll the caret above the inset when it grows.
+    reveal_caret_above_inset: bool,
+    scroll_top: f64,
+    /// The x position vertical caret movements try to keep.
+    preferred_x: Option<f32>,
+    /// A vertical caret movement by a number of lines. It's resolved in the next update, because
+    /// it needs the layout of the target line.
+    pending_move: Option<(isize, bool)>,
+    /// Scroll the caret into view in the next update.
+    reveal_caret: bool,
+    /// One entry for each line of the document.
+    lines: Vec<Line>,
+    /// The lines that may have shapes.
+    shown: Range<usize>,
+    /// The decorations need to be regenerated.
+    dirty: bool,
+    shapes: Option<Shapes>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EditorStyle {
+    pub font_size: f32,
+    pub line_height: f64,
+    pub text_color: Color,
+    pub text_weight: TextWeight,
+    pub selection_color: Color,
+    /// The corner radius of the selection, zero for square corners.
+    pub selection_radius: f64,
+    pub caret_color: Color,
+    pub caret_width: f64,
+    /// The time in seconds the caret takes to move to a new position.
+    pub caret_motion: f32,
+    pub caret_trail: bool,
+}
+
+impl Default for EditorStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 20.0,
+            line_height: 28.0,
+            text_color: Color::BLACK,
+            text_weight: TextWeight::NORMAL,
+            selection_color: Color::rgb(0.7, 0.8, 1.0),
+            selection_radius: 0.0,
+            caret_color: Color::BLACK,
+            caret_width: 2.0,
+            caret_motion: 0.1,
+            caret_trail: false,
+        }
+    }
+}
+
+/// How an event was handled by the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorResponse {
+    /// The event was not meant for the editor.
+    Ignored,
+    /// The caret, the selection, or the preedit text changed.
+    Handled,
+    /// The text changed.
+    Changed,
+}
+
+#[derive(Debug)]
+struct Shapes {
+    /// Scrolls the lines and the decorations.
+    scroll: Handle<Matrix4>,
+    position: Handle<Position>,
+    /// Selection and preedit underline.
+    decorations: Handle<PositionedShape>,
+    caret: CaretShape,
+    scroll_top: f64,
+}
+
+#[derive(Debug, Default)]
+struct Line {
+    /// `None` if the line changed since it was laid out.
+    layout: Option<LineShape>,
+    /// The shape of the line while it's inside the viewport.
+    shape: Option<Handle<PositionedShape>>,
+    /// The shape needs to be updated, because the line changed or moved.
+    stale: bool,
+}
+
+#[derive(Debug)]
+struct LineShape {
+    /// The text of the line in the document.
+    text: String,
+    /// The byte range of the preedit text in the displayed text.
+    preedit: Option<Range<usize>>,
+    layout: LineLayout,
+    run: GlyphRun,
+}
+
+impl Editor {
+    pub fn new(
+        position: Handle<Position>,
+        text: &str,
+        style: EditorStyle,
+        viewport_height: f64,
+    ) -> Self {
+        let document = Document::new(text);
+        let lines = (0..document.line_count())
+            .map(|_| Line::default())
+            .collect();
+        Self {
+            document,
+            style,
+            position,
+            focused: false,
+            clipboard: Clipboard::new(),
+            modifiers: ModifiersState::default(),
+            dragging: false,
+            preedit: None,
+            viewport_height,
+            bottom_inset: 0.0,
+            reveal_caret_above_inset: true,
+            scroll_top: 0.0,
+            preferred_x: None,
+            pending_move: None,
+            reveal_caret: false,
+            lines,
+            shown: 0..0,
+            dirty: true,
+            shapes: None,
+        }
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Modify the document directly.
+    pub fn document_mut(&mut self) -> &mut Document {
+        self.dirty = true;
+        &mut self.document
+    }
+
+    pub fn style(&self) -> &EditorStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: EditorStyle) {
+        self.style = style;
+        self.relayout();
+    }
+
+    /// Shape all text again with the next update, for example after fonts were reloaded.
+    pub fn relayout(&mut self) {
+        for line in &mut self.lines {
+            line.layout = None;
+            line.stale = true;
+        }
+        self.dirty = true;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Focus the editor. Only a focused editor shows the caret and handles keyboard input.
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused != self.focused {
+            self.focused = focused;
+            self.dragging = false;
+            self.dirty = true;
+        }
+    }
+
+    pub fn viewport_height(&self) -> f64 {
+        self.viewport_height
+    }
+
+    pub fn set_viewport_height(&mut self, height: f64) {
+        self.viewport_height = height;
+        self.bottom_inset = self.bottom_inset.min(height);
+        self.set_scroll_top(self.scroll_top);
+    }
+
+    pub fn bottom_inset(&self) -> f64 {
+        self.bottom_inset
+    }
+
+    /// Set the height at the bottom of the viewport that is covered, for example by a soft
+    /// keyboard, see [`crate::ShellWindow::viewport_insets`].
+    ///
+    /// The content can be scrolled above the inset. When the inset grows while the editor is
+    /// focused, the caret is scrolled into view in the next update, unless disabled with
+    /// [`Self::set_reveal_caret_above_inset`].
+    pub fn set_bottom_inset(&mut self, inset: f64) {
+        let inset = inset.clamp(0.0, self.viewport_height);
+        if inset == self.bottom_inset {
+            return;
+        }
+        if inset > self.bottom_inset && self.focused && self.reveal_caret_above_inset {
+            self.reveal_caret = true;
+        }
+        self.bottom_inset = inset;
+        self.set_scroll_top(self.scroll_top);
+    }
+
+    pub fn set_reveal_caret_above_inset(&mut self, reveal: bool) {
+        self.reveal_caret_above_inset = reveal;
+    }
+
+    /// The height of all lines.
+    pub fn content_height(&self) -> f64 {
+        self.document.line_count() as f64 * self.style.line_height
+    }
+
+    /// The vertical scroll offset.
+    pub fn scroll_top(&self) -> f64 {
+        self.scroll_top
+    }
+
+    /// Scroll to `top`, clamped to the content.
+    pub fn set_scroll_top(&mut self, top: f64) {
+        let max = (self.content_height() - self.visible_height()).max(0.0);
+        let top = top.clamp(0.0, max);
+        if top != self.scroll_top {
+            self.scroll_top = top;
+            self.dirty = true;
+        }
+    }
+
+    pub fn scroll_by(&mut self, delta: f64) {
+        self.set_scroll_top(self.scroll_top + delta);
+    }
+
+    /// Handle keyboard and IME events.
+    ///
+    /// Pointer events need to be transformed to the editor's coordinates first and are handled
+    /// by [`Self::pointer_pressed`] and its siblings.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> EditorResponse {
+        if let WindowEvent::ModifiersChanged(modifiers) = event {
+            self.modifiers = modifiers.state();
+            return EditorResponse::Ignored;
+        }
+
+        if !self.focused {
+            return EditorResponse::Ignored;
+        }
+
+        let before = self.state();
+        let response = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    event @ KeyEvent {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.handle_key(event),
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+                self.set_preedit(text.clone(), *cursor);
+                EditorResponse::Handled
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => {
+                self.set_preedit(String::new(), None);
+                self.document.insert(text);
+                EditorResponse::Handled
+            }
+            WindowEvent::Ime(Ime::Disabled) => {
+                self.set_preedit(String::new(), None);
+                EditorResponse::Handled
+            }
+            _ => EditorResponse::Ignored,
+        };
+        self.sync_lines();
+
+        let after = self.state();
+        if after == before && self.pending_move.is_none() {
+            return response;
+        }
+        self.dirty = true;
+        self.reveal_caret = true;
+        if after.0 != before.0 {
+            EditorResponse::Changed
+        } else {
+            EditorResponse::Handled
+        }
+    }
+
+    /// Revision, caret, selection and preedit, to detect changes.
+    fn state(&self) -> (u64, usize, Option<Range<usize>>, Option<Preedit>) {
+        (
+            self.document.revision(),
+            self.document.caret(),
+            self.document.selection(),
+            self.preedit.clone(),
+        )
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent) -> EditorResponse {
+        // The modifier for shortcuts and for moving by words.
+        let (primary, word) = if cfg!(target_os = "macos") {
+            (self.modifiers.super_key(), self.modifiers.alt_key())
+        } else {
+            (self.modifiers.control_key(), self.modifiers.control_key())
+        };
+        let shift = self.modifiers.shift_key();
+
+        // While composing, the input method owns the keyboard.
+        if self.preedit.is_some() {
+            return EditorResponse::Ignored;
+        }
+
+        let vertical = matches!(
+            event.logical_key,
+            Key::Named(
+                NamedKey::ArrowUp | NamedKey::ArrowDown | NamedKey::PageUp | NamedKey::PageDown
+            )
+        );
+        if !vertical {
+            self.preferred_x = None;
+        }
+
+        let page = (self.viewport_height / self.style.line_height)
+            .floor()
+            .max(1.0) as isize;
+        let document = &mut self.document;
+        match &event.logical_key {
+            Key::Named(NamedKey::ArrowLeft) => document.move_left(word, shift),
+            Key::Named(NamedKey::ArrowRight) => document.move_right(word, shift),
+            Key::Named(NamedKey::ArrowUp) if primary => document.move_document_start(shift),
+            Key::Named(NamedKey::ArrowDown) if primary => document.move_document_end(shift),
+            Key::Named(NamedKey::ArrowUp) => self.move_vertically(-1, shift),
+            Key::Named(NamedKey::ArrowDown) => self.move_vertically(1, shift),
+            Key::Named(NamedKey::PageUp) => self.move_vertically(-page, shift),
+            Key::Named(NamedKey::PageDown) => self.move_vertically(page, shift),
+            Key::Named(NamedKey::Home) if primary => document.move_document_start(shift),
+            Key::Named(NamedKey::End) if primary => document.move_document_end(shift),
+            Key::Named(NamedKey::Home) => document.move_line_start(shift),
+            Key::Named(NamedKey::End) => document.move_line_end(shift),
+            Key::Named(NamedKey::Backspace) => document.delete_backward(word),
+            Key::Named(NamedKey::Delete) => document.delete_forward(word),
+            Key::Named(NamedKey::Enter) => self.insert_line_break(),
+            Key::Named(NamedKey::Tab) => document.insert(TAB),
+            Key::Character(c) if primary => match c.to_lowercase().as_str() {
+                "a" => document.select_all(),
+                "c" => self.copy(),
+                "x" => {
+                    self.copy();
+                    self.document.delete_selection();
+                }
+                "v" => {
+                    let text = self.clipboard.text();
+                    self.document.insert(&text);
+                }
+                "z" if shift => {
+                    document.redo();
+                }
+                "z" => {
+                    document.undo();
+                }
+                "y" => {
+                    document.redo();
+                }
+                _ => return EditorResponse::Ignored,
+            },
+            _ => match &event.text {
+                Some(text) if !primary && !text.is_empty() => document.insert(text),
+                _ => return EditorResponse::Ignored,
+            },
+        }
+
+        EditorResponse::Handled
+    }
+
+    fn copy(&mut self) {
+        if let Some(text) = self.document.selected_text() {
+            self.clipboard.set_text(&text);
+        }
+    }
+
+    /// Insert a line break and continue the indentation of the current line.
+    fn insert_line_break(&mut self) {
+        let document = &self.document;
+        let line = document.line_of(document.caret());
+        let column = document.caret() - document.line_start(line);
+        let indent: String = document
+            .line(line)
+            .chars()
+            .take(column)
+            .take_while(|c| *c == ' ')
+            .collect();
+        self.document.insert(&format!("\n{indent}"));
+    }
+
+    /// Move the caret by a number of lines in the next update.
+    fn move_vertically(&mut self, lines: isize, extend: bool) {
+        let (pending, _) = self.pending_move.unwrap_or_default();
+        self.pending_move = Some((pending + lines, extend));
+    }
+
+    fn set_preedit(&mut self, text: String, cursor: Option<(usize, usize)>) {
+        let preedit = (!text.is_empty()).then_some(Preedit { text, cursor });
+        if preedit == self.preedit {
+            return;
+        }
+        if preedit.is_some() {
+            // The composed text replaces the selection when it's committed.
+            self.document.delete_selection();
+        }
+        self.preedit = preedit;
+        self.sync_lines();
+        self.invalidate_line(self.document.line_of(self.document.caret()));
+    }
+
+    /// Place the caret at `(x, y)`, or extend the selection to it, and start dragging.
+    pub fn pointer_pressed(&mut self, x: f64, y: f64, extend: bool) {
+        self.set_preedit(String::new(), None);
+        self.sync_lines();
+        let index = self.hit_test(x, y);
+        self.document.move_to(index, extend);
+        self.preferred_x = None;
+        self.dragging = true;
+        self.dirty = true;
+    }
+
+    /// Extend the selection to `(x, y)` while dragging.
+    pub fn pointer_moved(&mut self, x: f64, y: f64) {
+        if self.dragging {
+            self.sync_lines();
+            let index = self.hit_test(x, y);
+            self.document.move_to(index, true);
+            self.reveal_caret = true;
+            self.dirty = true;
+        }
+    }
+
+    pub fn pointer_released(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Select the word at `(x, y)`, for example on a double click.
+    pub fn select_word_at(&mut self, x: f64, y: f64) {
+        self.sync_lines();
+        let index = self.hit_test(x, y);
+        self.document.select_word(index);
+        self.dirty = true;
+    }
+
+    /// Select the line at `(x, y)`, for example on a triple click.
+    pub fn select_line_at(&mut self, x: f64, y: f64) {
+        self.sync_lines();
+        let index = self.hit_test(x, y);
+        self.document.select_line(index);
+        self.dirty = true;
+    }
+
+    /// The character index nearest to `(x, y)`.
+    ///
+    /// Lines are hit tested with the layout of the last [`Self::update`].
+    pub fn hit_test(&self, x: f64, y: f64) -> usize {
+        let line = ((y + self.scroll_top) / self.style.line_height).max(0.0) as usize;
+        if line >= self.document.line_count() {
+            return self.document.text().len_chars();
+        }
+        self.index_at(line, x as f32)
+    }
+
+    /// The top left corner of the caret.
+    pub fn caret_position(&self) -> (f64, f64) {
+        let line = self.document.line_of(self.document.caret());
+        (
+            self.caret_x(line) as f64,
+            self.line_top(line) - self.scroll_top,
+        )
+    }
+
+    /// The area the input method should avoid, as position and size.
+    ///
+    /// Transform it to window coordinates and pass it to
+    /// [`crate::ShellWindow::set_ime_cursor_area`].
+    pub fn ime_cursor_area(&self) -> ((f64, f64), (f64, f64)) {
+        (
+            self.caret_position(),
+            (self.style.caret_width, self.style.line_height),
+        )
+    }
+
+    /// Update the shapes of the lines inside the viewport and the decorations.
+    pub fn update(&mut self, director: &mut Director, font_system: &mut text::FontSystem) {
+        self.sync_lines();
+        if let Some((lines, extend)) = self.pending_move.take() {
+            self.resolve_vertical_move(font_system, lines, extend);
+        }
+        if self.reveal_caret {
+            self.reveal_caret = false;
+            self.reveal(self.document.line_of(self.document.caret()));
+        }
+        // The content may have become shorter.
+        self.set_scroll_top(self.scroll_top);
+
+        let shapes = self.shapes.get_or_insert_with(|| {
+            let scroll = director.cast(scroll_matrix(self.scroll_top));
+            let position = director.cast(Position {
+                parent: Some(self.position.clone()),
+                matrix: scroll.clone(),
+                pin: None,
+                overlay: false,
+            });
+            let decorations =
+                director.cast(PositionedShape::new(position.clone(), Vec::<Quad>::new()));
+            Shapes {
+                scroll,
+                position,
+                decorations,
+                caret: CaretShape::default(),
+                scroll_top: self.scroll_top,
+            }
+        });
+        if shapes.scroll_top != self.scroll_top {
+            shapes.scroll.update(scroll_matrix(self.scroll_top));
+            shapes.scroll_top = self.scroll_top;
+        }
+        let position = shapes.position.clone();
+
+        // Remove the shapes of lines that left the viewport.
+        let visible = self.visible_lines();
+        for line in self.shown.clone() {
+            if !visible.contains(&line) {
+                if let Some(line) = self.lines.get_mut(line) {
+                    line.shape = None;
+                }
+            }
+        }
+        self.shown = visible.clone();
+
+        for index in visible {
+            self.layout_line(font_system, index);
+            let top = self.line_top(index);
+            let line = &mut self.lines[index];
+            if line.shape.is_some() && !line.stale {
+                continue;
+            }
+            line.stale = false;
+            let layout = line.layout.as_ref().unwrap();
+            let mut run = layout.run.clone();
+            // Center the text vertically inside the line.
+            let padding = ((self.style.line_height - layout.layout.height() as f64) / 2.0).max(0.0);
+            run.translation = Vector3::new(0.0, top + padding, 0.0);
+            let shape = PositionedShape::new(position.clone(), run);
+            match &line.shape {
+                Some(handle) => handle.update(shape),
+                None => line.shape = Some(director.cast(shape)),
+            }
+        }
+
+        if self.dirty {
+            self.dirty = false;
+            let decorations = PositionedShape::new(position.clone(), self.decorations());
+            let caret = self.caret();
+            let shapes = self.shapes.as_mut().unwrap();
+            shapes.decorations.update(decorations);
+            shapes.caret.update(director, &position, caret);
+        }
+    }
+
+    /// Apply the line changes of the document to the line layouts.
+    fn sync_lines(&mut self) {
+        for change in self.document.take_line_changes() {
+            let end = (change.first + change.removed + 1).min(self.lines.len());
+            let first = change.first.min(end);
+            self.lines
+                .splice(first..end, (0..=change.inserted).map(|_| Line::default()));
+
+            // The lines below moved.
+            if change.removed != change.inserted {
+                let moved_end = (self.shown.end + change.inserted).min(self.lines.len());
+                for line in self
+                    .lines
+                    .get_mut(first + change.inserted + 1..moved_end)
+                    .unwrap_or_default()
+                {
+                    line.stale = true;
+                }
+            }
+
+            self.shown = self.shown.start.min(first)
+                ..(self.shown.end + change.inserted).min(self.lines.len());
+            self.dirty = true;
+        }
+    }
+
+    fn invalidate_line(&mut self, line: usize) {
+        if let Some(line) = self.lines.get_mut(line) {
+            line.layout = None;
+            line.stale = true;
+        }
+        self.dirty = true;
+    }
+
+    /// Shape a line if it's not laid out yet.
+    fn layout_line(&mut self, font_system: &mut text::FontSystem, index: usize) {
+        if self.lines[index].layout.is_some() {
+            return;
+        }
+
+        let text = self.document.line(index);
+        let caret = self.document.caret();
+        let (display_text, preedit) = match &self.preedit {
+            Some(preedit) if self.document.line_of(caret) == index => {
+                let column = caret - self.document.line_start(index);
+                let offset = byte_offset(&text, column);
+                let mut display_text = text.clone();
+                display_text.insert_str(offset, &preedit.text);
+                (display_text, Some(offset..offset + preedit.text.len()))
+            }
+            _ => (text.clone(), None),
+        };
+
+        let style = &self.style;
+        let (layout, run) = LineLayout::shape(
+            font_system,
+            &display_text,
+            style.font_size,
+            style.text_weight,
+            style.text_color,
+            (0.0, 0.0, 0.0),
+            style.font_size,
+        );
+        let line = &mut self.lines[index];
+        line.layout = Some(LineShape {
+            text,
+            preedit,
+            layout,
+            run,
+        });
+        line.stale = true;
+    }
+
+    fn resolve_vertical_move(
+        &mut self,
+        font_system: &mut text::FontSystem,
+        lines: isize,
+        extend: bool,
+    ) {
+        let line = self.document.line_of(self.document.caret());
+        self.layout_line(font_system, line);
+        let x = self.preferred_x.unwrap_or_else(|| self.caret_x(line));
+        let last = self.document.line_count() - 1;
+        let target = line.saturating_add_signed(lines).min(last);
+
+        if target == line {
+            // Moving beyond the first or last line moves to its start or end.
+            match lines {
+                lines if lines < 0 => self.document.move_document_start(extend),
+                lines if lines > 0 => self.document.move_document_end(extend),
+                _ => {}
+            }
+        } else {
+            self.layout_line(font_system, target);
+            let index = self.index_at(target, x);
+            self.document.move_to(index, extend);
+        }
+        self.preferred_x = Some(x);
+        self.dirty = true;
+    }
+
+    /// Scroll the minimum distance to show a line completely.
+    fn reveal(&mut self, line: usize) {
+        let top = self.line_top(line);
+        let bottom = top + self.style.line_height;
+        if top < self.scroll_top {
+            self.set_scroll_top(top);
+        } else if bottom > self.scroll_top + self.visible_height() {
+            self.set_scroll_top(bottom - self.visible_height());
+        }
+    }
+
+    /// The height of the viewport that is not covered by the bottom inset.
+    fn visible_height(&self) -> f64 {
+        self.viewport_height - self.bottom_inset
+    }
+
+    /// The lines that intersect the viewport.
+    fn visible_lines(&self) -> Range<usize> {
+        let line_height = self.style.line_height;
+        let count = self.document.line_count();
+        let first = (self.scroll_top / line_height).floor() as usize;
+        let end = ((self.scroll_top + self.viewport_height) / line_height).ceil() as usize;
+        first.min(count)..end.min(count)
+    }
+
+    fn line_top(&self, line: usize) -> f64 {
+        line as f64 * self.style.line_height
+    }
+
+    /// The character index nearest to `x` in a line. Lines that are not laid out resolve to their
+    /// start.
+    fn index_at(&self, line: usize, x: f32) -> usize {
+        let start = self.document.line_start(line);
+        match self.lines.get(line).and_then(|line| line.layout.as_ref()) {
+            Some(layout) => start + layout.column_at(x),
+            None => start,
+        }
+    }
+
+    /// The x position of the caret, including the IME cursor.
+    fn caret_x(&self, line: usize) -> f32 {
+        let Some(layout) = self.lines.get(line).and_then(|line| line.layout.as_ref()) else {
+            return 0.0;
+        };
+        let column = self.document.caret() - self.document.line_start(line);
+        match (&layout.preedit, &self.preedit) {
+            (Some(range), Some(preedit)) => {
+                let cursor = preedit.cursor.map_or(range.len(), |(start, _)| start);
+                layout.layout.x_at(range.start + cursor)
+            }
+            _ => layout.x_at(column),
+        }
+    }
+
+    fn decorations(&self) -> Vec<Quad> {
+        let style = &self.style;
+        let line_height = style.line_height;
+        let document = &self.document;
+        let mut quads = Vec::new();
+
+        if let Some(selection) = document.selection() {
+            let first = document.line_of(selection.start);
+            let last = document.line_of(selection.end);
+            let visible = self.visible_lines();
+            let mut rows = Vec::new();
+            for line in first.max(visible.start)..(last + 1).min(visible.end) {
+                let Some(layout) = self.lines[line].layout.as_ref() else {
+                    continue;
+                };
+                let start = document.line_start(line);
+                let left = if line == first {
+                    layout.x_at(selection.start - start)
+                } else {
+                    0.0
+                };
+                let right = if line == last {
+                    layout.x_at(selection.end - start)
+                } else {
+                    // Show the selected line break.
+                    layout.layout.width() + style.font_size / 3.0
+                };
+                let top = self.line_top(line);
+                rows.push(Rect {
+                    left: left as f64,
+                    top,
+                    right: right as f64,
+                    bottom: top + line_height,
+                });
+            }
+            quads.extend(selection_region(
+                &rows,
+                style.selection_radius,
+                style.selection_color,
+            ));
+        }
+
+        let caret_line = document.line_of(document.caret());
+        let top = self.line_top(caret_line);
+
+        if let Some(layout) = self.lines[caret_line].layout.as_ref() {
+            if let Some(preedit) = &layout.preedit {
+                let left = layout.layout.x_at(preedit.start) as f64;
+                let right = layout.layout.x_at(preedit.end) as f64;
+                let thickness = (style.font_size as f64 / 16.0).max(1.0);
+                let bottom = top + line_height;
+                quads.push(rect(
+                    left,
+                    bottom - thickness,
+                    right,
+                    bottom,
+                    style.text_color,
+                ));
+            }
+        }
+
+        quads
+    }
+
+    /// The caret, `None` if the editor is not focused.
+    fn caret(&self) -> Option<Caret> {
+        if !self.focused {
+            return None;
+        }
+        let style = &self.style;
+        let line = self.document.line_of(self.document.caret());
+        let x = self.caret_x(line) as f64;
+        let top = self.line_top(line);
+        let bounds = Bounds::new((x, top), (x + style.caret_width, top + style.line_height));
+        let caret = Caret::new(bounds, style.caret_color).with_motion(style.caret_motion);
+        Some(if style.caret_trail {
+            caret.with_trail()
+        } else {
+            caret
+        })
+    }
+}
+
+impl LineShape {
+    /// The x position of a column.
+    fn x_at(&self, column: usize) -> f32 {
+        self.layout.x_at(self.display_offset(column))
+    }
+
+    /// The column nearest to `x`.
+    fn column_at(&self, x: f32) -> usize {
+        let offset = self.layout.hit_test(x);
+        let offset = match &self.preedit {
+            Some(preedit) if offset >= preedit.end => offset - preedit.len(),
+            Some(preedit) if offset > preedit.start => preedit.start,
+            _ => offset,
+        };
+        self.text[..offset].chars().count()
+    }
+
+    /// Convert a column to a byte offset in the displayed text.
+    fn display_offset(&self, column: usize) -> usize {
+        let offset = byte_offset(&self.text, column);
+        match &self.preedit {
+            Some(preedit) if offset > preedit.start => offset + preedit.len(),
+            _ => offset,
+        }
+    }
+}
+
+fn byte_offset(text: &str, column: usize) -> usize {
+    text.char_indices()
+        .nth(column)
+        .map_or(text.len(), |(offset, _)| offset)
+}
+
+fn scroll_matrix(scroll_top: f64) -> Matrix4 {
+    Matrix4::from_translation(Vector3::new(0.0, -scroll_top, 0.0))
+}
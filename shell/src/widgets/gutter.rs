@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+
+use cosmic_text as text;
+
+use massive_geometry::{Color, Vector3};
+use massive_shapes::{GlyphRun, TextWeight};
+
+use super::LineLayout;
+
+/// A margin beside the text of a [`super::DocumentView`] with slots for per-line annotations,
+/// like line numbers, breakpoints, or diff markers.
+///
+/// The slots are placed side by side, starting at the text. Annotations are shown next to the
+/// first visual line of their paragraph, so they follow the paragraphs when they wrap.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gutter {
+    pub side: GutterSide,
+    pub slots: Vec<GutterSlot>,
+}
+
+/// The side of the text the gutter is placed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GutterSide {
+    #[default]
+    Left,
+    /// For right-to-left text.
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GutterSlot {
+    pub width: f64,
+    pub content: GutterContent,
+}
+
+/// What a [`GutterSlot`] shows for each paragraph.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GutterContent {
+    /// The number of each paragraph, starting with `first` for the first paragraph, aligned
+    /// towards the text.
+    LineNumbers { first: usize, color: Color },
+    /// The [`super::Paragraph::marker`]s.
+    Markers,
+    /// The annotations set with [`super::DocumentView::set_annotation`] for this slot.
+    Annotations,
+}
+
+/// Marks a paragraph in the gutter of a [`super::DocumentView`], for example as inserted or
+/// deleted in a diff, or as having a breakpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GutterMarker {
+    /// Shown centered in its slot.
+    pub text: String,
+    pub color: Color,
+    /// Tints the whole paragraph, including the gutter.
+    pub background: Option<Color>,
+}
+
+impl Gutter {
+    /// A gutter at the left with a single slot for the paragraphs' markers.
+    pub fn markers(width: f64) -> Self {
+        Self {
+            side: GutterSide::Left,
+            slots: vec![GutterSlot {
+                width,
+                content: GutterContent::Markers,
+            }],
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.slots.iter().map(|slot| slot.width).sum()
+    }
+
+    /// The left edge of the gutter in a view of `width`.
+    pub fn left(&self, width: f64) -> f64 {
+        match self.side {
+            GutterSide::Left => 0.0,
+            GutterSide::Right => width - self.width(),
+        }
+    }
+
+    /// The left edge of each slot in a view of `width`. The first slot is next to the text.
+    pub fn slot_lefts(&self, width: f64) -> impl Iterator<Item = (f64, &GutterSlot)> {
+        let side = self.side;
+        let mut edge = match side {
+            GutterSide::Left => self.width(),
+            GutterSide::Right => width - self.width(),
+        };
+        self.slots.iter().map(move |slot| {
+            let left = match side {
+                GutterSide::Left => {
+                    edge -= slot.width;
+                    edge
+                }
+                GutterSide::Right => {
+                    edge += slot.width;
+                    edge - slot.width
+                }
+            };
+            (left, slot)
+        })
+    }
+}
+
+/// The text of a gutter slot, relative to the top of its paragraph.
+pub(crate) struct GutterRun<'a> {
+    pub text: Cow<'a, str>,
+    pub color: Color,
+    pub left: f64,
+    pub width: f64,
+    /// `true` aligns the text towards the text of the paragraph, `false` centers it.
+    pub towards_text: bool,
+}
+
+impl GutterRun<'_> {
+    /// Shape the text and place it in its slot, vertically centered in a line of `line_height`.
+    pub fn shape(
+        &self,
+        font_system: &mut text::FontSystem,
+        side: GutterSide,
+        font_size: f32,
+        line_height: f64,
+    ) -> GlyphRun {
+        let (layout, mut run) = LineLayout::shape(
+            font_system,
+            &self.text,
+            font_size,
+            TextWeight::NORMAL,
+            self.color,
+            (0.0, 0.0, 0.0),
+            font_size,
+        );
+        let free = (self.width - layout.width() as f64).max(0.0);
+        // Keep some distance to the text.
+        let padding = (font_size as f64 / 2.0).min(free);
+        let x = match (self.towards_text, side) {
+            (false, _) => free / 2.0,
+            (true, GutterSide::Left) => free - padding,
+            (true, GutterSide::Right) => padding,
+        };
+        let y = ((line_height - layout.height() as f64) / 2.0).max(0.0);
+        run.translation = Vector3::new((self.left + x).round(), y, 0.0);
+        run
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::VecDeque;
+
+use web_time::Instant;
+
+/// How fast a fling slows down, in 1/s.
+const FRICTION: f64 = 2.5;
+/// Flings stop below this velocity, in pixels per second.
+const MIN_VELOCITY: f64 = 10.0;
+/// How fast smooth scrolling approaches its target, in 1/s.
+const SMOOTHING: f64 = 18.0;
+/// How fast overscroll springs back, in 1/s.
+const BOUNCE: f64 = 12.0;
+/// Smooth scrolling stops when it's closer than this to its target, in pixels.
+const TARGET_TOLERANCE: f64 = 0.5;
+/// The time span of pan movements used to compute the fling velocity, in seconds.
+const VELOCITY_WINDOW: f64 = 0.1;
+
+/// How a [`KineticScroll`] moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollPhysics {
+    /// How fast a fling slows down, in 1/s.
+    pub friction: f64,
+    /// How fast smooth scrolling approaches its target, in 1/s.
+    pub smoothing: f64,
+    /// How far pans and flings may move beyond the edges, in pixels. The further the content is
+    /// pulled, the more it resists, and it bounces back when released. Zero stops at the edges.
+    pub overscroll: f64,
+    /// How fast overscroll springs back, in 1/s.
+    pub bounce: f64,
+}
+
+impl Default for ScrollPhysics {
+    fn default() -> Self {
+        Self {
+            friction: FRICTION,
+            smoothing: SMOOTHING,
+            overscroll: 0.0,
+            bounce: BOUNCE,
+        }
+    }
+}
+
+impl ScrollPhysics {
+    /// Rubber-band overscroll, like scrolling with touches on mobile platforms.
+    pub fn touch() -> Self {
+        Self {
+            overscroll: 120.0,
+            ..Self::default()
+        }
+    }
+
+    /// The overscroll distance for `pulled` pixels of movement beyond an edge.
+    fn rubber_band(&self, pulled: f64) -> f64 {
+        let extent = self.overscroll;
+        if extent <= 0.0 {
+            return 0.0;
+        }
+        extent * pulled / (pulled + extent)
+    }
+
+    /// The movement beyond an edge that results in `distance` pixels of overscroll.
+    fn pulled(&self, distance: f64) -> f64 {
+        let extent = self.overscroll;
+        if extent <= 0.0 {
+            return 0.0;
+        }
+        let distance = distance.min(extent * 0.99);
+        extent * distance / (extent - distance)
+    }
+}
+
+/// A one-dimensional scroll offset with smooth and kinetic scrolling.
+///
+/// Like the camera interpolator, the scroller is driven by the caller: Call [`Self::advance`] for
+/// each frame as long as [`Self::is_animating`] returns `true`.
+///
+/// With [`ScrollPhysics::overscroll`] set, the offset may be temporarily below zero or above the
+/// maximum while panning and bouncing back.
+#[derive(Debug, Default)]
+pub struct KineticScroll {
+    physics: ScrollPhysics,
+    offset: f64,
+    max: f64,
+    motion: Motion,
+    /// The time of the last advance while in motion.
+    last_advance: Option<Instant>,
+    pan: Option<Pan>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Motion {
+    #[default]
+    None,
+    /// Approach an offset.
+    Target(f64),
+    /// Decelerate from a velocity in pixels per second.
+    Fling(f64),
+    /// Spring back from beyond the start or the end. `pulled` is the movement beyond the edge
+    /// before the rubber band is applied and `velocity` is its outward velocity.
+    Bounce {
+        end: bool,
+        pulled: f64,
+        velocity: f64,
+    },
+}
+
+#[derive(Debug)]
+struct Pan {
+    /// The pointer position when the pan started or last moved.
+    position: f64,
+    /// The offset the pointer movements add up to, before the rubber band is applied.
+    pulled_offset: f64,
+    /// Recent offsets, to compute the velocity when the pan ends.
+    samples: VecDeque<(Instant, f64)>,
+}
+
+impl KineticScroll {
+    pub fn new(physics: ScrollPhysics) -> Self {
+        Self {
+            physics,
+            ..Self::default()
+        }
+    }
+
+    pub fn physics(&self) -> &ScrollPhysics {
+        &self.physics
+    }
+
+    pub fn set_physics(&mut self, physics: ScrollPhysics) {
+        self.physics = physics;
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Set the maximum offset. The offset is clamped to it, unless it is beyond the end while
+    /// panning or bouncing.
+    pub fn set_max(&mut self, max: f64) {
+        self.max = max.max(0.0);
+        let pulled = self.pan.is_some() || matches!(self.motion, Motion::Bounce { .. });
+        if !(pulled && self.physics.overscroll > 0.0) {
+            self.offset = self.offset.min(self.max);
+        }
+        if let Motion::Target(target) = &mut self.motion {
+            *target = target.min(self.max);
+        }
+    }
+
+    /// The offset smooth scrolling approaches, `None` if not scrolling smoothly.
+    pub fn target(&self) -> Option<f64> {
+        match self.motion {
+            Motion::Target(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Jump to `offset` and stop all motion.
+    pub fn set_offset(&mut self, offset: f64) {
+        self.offset = offset.clamp(0.0, self.max);
+        self.motion = Motion::None;
+    }
+
+    /// Move the offset and a smooth scrolling target without affecting the motion.
+    ///
+    /// This keeps content in place when the content above it changes its size.
+    pub fn shift(&mut self, delta: f64) {
+        self.offset = (self.offset + delta).clamp(0.0, self.max);
+        if let Motion::Target(target) = &mut self.motion {
+            *target = (*target + delta).clamp(0.0, self.max);
+        }
+    }
+
+    /// Scroll immediately, for example for pixel precise touchpad scrolling.
+    pub fn scroll_by(&mut self, delta: f64) {
+        self.set_offset(self.offset + delta);
+    }
+
+    /// Scroll smoothly, for example for mouse wheel steps or keyboard scrolling.
+    ///
+    /// Repeated calls accumulate.
+    pub fn scroll_smoothly_by(&mut self, delta: f64) {
+        let from = match self.motion {
+            Motion::Target(target) => target,
+            _ => self.offset,
+        };
+        self.scroll_smoothly_to(from + delta);
+    }
+
+    pub fn scroll_smoothly_to(&mut self, offset: f64) {
+        self.start_motion(Motion::Target(offset.clamp(0.0, self.max)));
+    }
+
+    /// Continue scrolling with `velocity` in pixels per second and decelerate.
+    pub fn fling(&mut self, velocity: f64) {
+        if velocity.abs() < MIN_VELOCITY {
+            self.motion = Motion::None;
+            return;
+        }
+        self.start_motion(Motion::Fling(velocity));
+    }
+
+    /// Start to follow a pointer or touch at `position`. Stops all motion.
+    ///
+    /// A bounce is caught where it is.
+    pub fn pan_started(&mut self, position: f64, now: Instant) {
+        self.motion = Motion::None;
+        let pulled_offset = if self.offset < 0.0 {
+            -self.physics.pulled(-self.offset)
+        } else if self.offset > self.max {
+            self.max + self.physics.pulled(self.offset - self.max)
+        } else {
+            self.offset
+        };
+        self.pan = Some(Pan {
+            position,
+            pulled_offset,
+            samples: [(now, self.offset)].into(),
+        });
+    }
+
+    /// Scroll by the movement of the pointer. Content follows the pointer.
+    pub fn pan_moved(&mut self, position: f64, now: Instant) {
+        let Some(pan) = &mut self.pan else {
+            return;
+        };
+        let delta = pan.position - position;
+        pan.position = position;
+        pan.pulled_offset += delta;
+        if self.physics.overscroll > 0.0 {
+            let pulled = pan.pulled_offset;
+            self.offset = if pulled < 0.0 {
+                -self.physics.rubber_band(-pulled)
+            } else if pulled > self.max {
+                self.max + self.physics.rubber_band(pulled - self.max)
+            } else {
+                pulled
+            };
+        } else {
+            pan.pulled_offset = pan.pulled_offset.clamp(0.0, self.max);
+            self.offset = pan.pulled_offset;
+        }
+
+        pan.samples.push_back((now, self.offset));
+        while pan.samples.front().is_some_and(|(time, _)| {
+            now.saturating_duration_since(*time).as_secs_f64() > VELOCITY_WINDOW
+        }) {
+            pan.samples.pop_front();
+        }
+    }
+
+    /// Stop following the pointer and fling with its recent velocity, or bounce back if the
+    /// content was pulled beyond an edge.
+    pub fn pan_ended(&mut self, now: Instant) {
+        let Some(pan) = self.pan.take() else {
+            return;
+        };
+        let velocity = match pan.samples.front() {
+            Some((time, offset)) => {
+                let elapsed = now.saturating_duration_since(*time).as_secs_f64();
+                if elapsed > 0.0 && elapsed <= VELOCITY_WINDOW * 2.0 {
+                    (self.offset - offset) / elapsed
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+
+        if self.physics.overscroll <= 0.0 {
+            self.fling(velocity);
+        } else if pan.pulled_offset < 0.0 {
+            self.start_motion(Motion::Bounce {
+                end: false,
+                pulled: -pan.pulled_offset,
+                velocity: -velocity,
+            });
+        } else if pan.pulled_offset > self.max {
+            self.start_motion(Motion::Bounce {
+                end: true,
+                pulled: pan.pulled_offset - self.max,
+                velocity,
+            });
+        } else {
+            self.fling(velocity);
+        }
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.pan.is_some()
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.motion != Motion::None
+    }
+
+    /// The offset is beyond the start or the end.
+    pub fn is_overscrolling(&self) -> bool {
+        self.offset < 0.0 || self.offset > self.max
+    }
+
+    /// Advance the motion to `now` and return the offset.
+    pub fn advance(&mut self, now: Instant) -> f64 {
+        let dt = self.last_advance.map_or(0.0, |last| {
+            now.saturating_duration_since(last).as_secs_f64()
+        });
+        self.last_advance = Some(now);
+
+        match self.motion {
+            Motion::None => {}
+            Motion::Target(target) => {
+                let smoothing = self.physics.smoothing;
+                let offset = self.offset + (target - self.offset) * (1.0 - (-smoothing * dt).exp());
+                if (target - offset).abs() < TARGET_TOLERANCE {
+                    self.offset = target;
+                    self.motion = Motion::None;
+                } else {
+                    self.offset = offset;
+                }
+            }
+            Motion::Fling(velocity) => {
+                let friction = self.physics.friction;
+                let decay = (-friction * dt).exp();
+                let offset = self.offset + velocity / friction * (1.0 - decay);
+                let velocity = velocity * decay;
+                self.offset = offset.clamp(0.0, self.max);
+                self.motion = if offset != self.offset {
+                    // Hit an edge: Stop or continue into the overscroll and bounce back.
+                    if self.physics.overscroll > 0.0 {
+                        let end = offset > self.max;
+                        Motion::Bounce {
+                            end,
+                            pulled: (offset - self.offset).abs(),
+                            velocity: velocity.abs(),
+                        }
+                    } else {
+                        Motion::None
+                    }
+                } else if velocity.abs() < MIN_VELOCITY {
+                    Motion::None
+                } else {
+                    Motion::Fling(velocity)
+                };
+                self.apply_bounce();
+            }
+            Motion::Bounce {
+                end,
+                pulled,
+                velocity,
+            } => {
+                // A critically damped spring that pulls back to the edge.
+                let w = self.physics.bounce;
+                let b = velocity + w * pulled;
+                let x = pulled + b * dt;
+                let decay = (-w * dt).exp();
+                let pulled = x * decay;
+                let velocity = (b - w * x) * decay;
+                self.motion = if pulled <= 0.0
+                    || (pulled < TARGET_TOLERANCE && velocity.abs() < MIN_VELOCITY)
+                {
+                    Motion::None
+                } else {
+                    Motion::Bounce {
+                        end,
+                        pulled,
+                        velocity,
+                    }
+                };
+                self.offset = if end { self.max } else { 0.0 };
+                self.apply_bounce();
+            }
+        }
+
+        self.offset
+    }
+
+    /// Move the offset beyond the edge while bouncing.
+    fn apply_bounce(&mut self) {
+        if let Motion::Bounce { end, pulled, .. } = self.motion {
+            let distance = self.physics.rubber_band(pulled);
+            self.offset = if end { self.max + distance } else { -distance };
+        }
+    }
+
+    fn start_motion(&mut self, motion: Motion) {
+        if !self.is_animating() {
+            // Don't count the time since the last motion.
+            self.last_advance = None;
+        }
+        self.motion = motion;
+        self.apply_bounce();
+    }
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use cosmic_text as text;
+use massive_geometry::{Color, Vector3};
+use massive_shapes::{GlyphRun, GlyphRunMetrics, Quad, RunGlyph, TextWeight};
+
+/// The layout of a single line of shaped text, used for hit testing and placing carets and
+/// selections.
+///
+/// Offsets are byte offsets into the line's text.
+#[derive(Debug, Clone, Default)]
+pub struct LineLayout {
+    /// Byte range, x position and width of each glyph.
+    glyphs: Vec<(Range<usize>, f32, f32)>,
+    width: f32,
+    height: f32,
+}
+
+impl LineLayout {
+    /// Shape `line` and return its layout and a glyph run at `translation`.
+    ///
+    /// `min_height` is used for empty lines, which have no metrics.
+    pub fn shape(
+        font_system: &mut text::FontSystem,
+        line: &str,
+        font_size: f32,
+        weight: TextWeight,
+        color: Color,
+        translation: impl Into<Vector3>,
+        min_height: f32,
+    ) -> (Self, GlyphRun) {
+        let attrs = text::Attrs::new().weight(text::Weight(weight.0));
+        let mut buffer =
+            text::BufferLine::new(line, text::AttrsList::new(attrs), text::Shaping::Advanced);
+        let line = &buffer.layout(font_system, font_size, f32::MAX, text::Wrap::None, None)[0];
+
+        let layout = Self::from_layout_line(line, min_height);
+        let glyphs = line.glyphs.iter().map(run_glyph).collect();
+        let run = GlyphRun::new(translation, run_metrics(line), color, weight, glyphs);
+        (layout, run)
+    }
+
+    /// The layout of a line shaped by cosmic-text. Offsets are relative to the shaped text, which
+    /// may span multiple wrapped lines.
+    pub(crate) fn from_layout_line(line: &text::LayoutLine, min_height: f32) -> Self {
+        Self {
+            glyphs: line
+                .glyphs
+                .iter()
+                .map(|glyph| (glyph.start..glyph.end, glyph.x, glyph.w))
+                .collect(),
+            width: line.w,
+            height: (line.max_ascent + line.max_descent).max(min_height),
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// The x position of a byte offset.
+    pub fn x_at(&self, offset: usize) -> f32 {
+        for (range, x, width) in &self.glyphs {
+            if offset <= range.start {
+                return *x;
+            }
+            // Inside a ligature.
+            if offset < range.end {
+                let t = (offset - range.start) as f32 / range.len() as f32;
+                return x + width * t;
+            }
+        }
+        self.width
+    }
+
+    /// The byte range the line covers, `None` if it's empty.
+    pub fn range(&self) -> Option<Range<usize>> {
+        let first = self.glyphs.iter().map(|(range, ..)| range.start).min()?;
+        let last = self.glyphs.iter().map(|(range, ..)| range.end).max()?;
+        Some(first..last)
+    }
+
+    /// The byte offset nearest to `x`.
+    pub fn hit_test(&self, x: f32) -> usize {
+        self.glyphs
+            .iter()
+            .find(|(_, glyph_x, width)| x < glyph_x + width / 2.0)
+            .map(|(range, ..)| range.start)
+            .unwrap_or_else(|| self.glyphs.last().map_or(0, |(range, ..)| range.end))
+    }
+}
+
+pub(crate) fn run_metrics(line: &text::LayoutLine) -> GlyphRunMetrics {
+    GlyphRunMetrics {
+        max_ascent: line.max_ascent as u32,
+        max_descent: line.max_descent as u32,
+        width: line.w.ceil() as u32,
+    }
+}
+
+pub(crate) fn run_glyph(glyph: &text::LayoutGlyph) -> RunGlyph {
+    let (key, x, y) = text::CacheKey::new(
+        glyph.font_id,
+        glyph.glyph_id,
+        glyph.font_size,
+        (glyph.x.round(), glyph.y.round()),
+        text::CacheKeyFlags::empty(),
+    );
+    // Rounded, so that tiny differences of the shaped advances do not reach the runs.
+    let width = (glyph.w * 64.0).round() / 64.0;
+    RunGlyph::new(key, (x, y), width)
+}
+
+/// An axis aligned rectangle at z = 0.
+pub(crate) fn rect(left: f64, top: f64, right: f64, bottom: f64, color: Color) -> Quad {
+    Quad::new(
+        [
+            Vector3::new(left, top, 0.0),
+            Vector3::new(right, top, 0.0),
+            Vector3::new(right, bottom, 0.0),
+            Vector3::new(left, bottom, 0.0),
+        ],
+        color,
+    )
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::Range,
+};
+
+use cosmic_text as text;
+use web_time::Instant;
+use winit::event::{MouseScrollDelta, WindowEvent};
+
+use massive_geometry::{Color, Matrix4, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::TextWeight;
+
+use super::{KineticScroll, LineLayout};
+
+/// The distance one mouse wheel step scrolls, in lines.
+const WHEEL_LINES: f64 = 3.0;
+/// How far the view may be above the end to keep following appended lines, in pixels.
+const FOLLOW_TOLERANCE: f64 = 1.0;
+/// The number of lines that share a position.
+const BLOCK_LINES: u64 = 256;
+
+/// An append-only list of lines, for tailing live logs with millions of lines.
+///
+/// Lines are numbered in the order they were appended, and a line keeps its number when older
+/// lines are trimmed. Each line is placed at its number times the line height, so appending a
+/// line or trimming the oldest ones never moves the shapes of other lines: Only the lines that
+/// enter the viewport are shaped and get shapes, and the ones that leave it drop theirs.
+///
+/// Lines are placed relative to a position for each block of lines, so that the coordinates of
+/// their glyphs stay small far down the log.
+///
+/// Lines are retained in a ring buffer. With a retention limit, the oldest lines are trimmed
+/// when new ones are appended.
+///
+/// While the view is at the end, it follows appended lines.
+#[derive(Debug)]
+pub struct LogView {
+    position: Handle<Position>,
+    style: LogStyle,
+    lines: VecDeque<String>,
+    /// The number of the first retained line.
+    first: u64,
+    retention: Option<usize>,
+    /// The offset is relative to the top of the first retained line.
+    scroll: KineticScroll,
+    viewport_height: f64,
+    shapes: Option<Shapes>,
+    /// The positions of the blocks of lines with shapes, by block index.
+    blocks: BTreeMap<u64, Block>,
+    /// The shapes of the lines in the viewport by line number, `None` for empty lines.
+    shown: BTreeMap<u64, Option<Handle<PositionedShape>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LogStyle {
+    pub font_size: f32,
+    /// The height of a line relative to the font size.
+    pub line_height: f32,
+    pub color: Color,
+    pub weight: TextWeight,
+}
+
+impl Default for LogStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            line_height: 1.3,
+            color: Color::BLACK,
+            weight: TextWeight::NORMAL,
+        }
+    }
+}
+
+impl LogStyle {
+    fn line_height(&self) -> f64 {
+        (self.font_size * self.line_height).ceil() as f64
+    }
+}
+
+#[derive(Debug)]
+struct Shapes {
+    /// The translation of the lines by the scroll offset.
+    scroll: Handle<Matrix4>,
+    position: Handle<Position>,
+    scroll_top: f64,
+}
+
+#[derive(Debug)]
+struct Block {
+    _matrix: Handle<Matrix4>,
+    position: Handle<Position>,
+}
+
+impl LogView {
+    pub fn new(position: Handle<Position>, style: LogStyle, viewport_height: f64) -> Self {
+        Self {
+            position,
+            style,
+            lines: VecDeque::new(),
+            first: 0,
+            retention: None,
+            scroll: KineticScroll::default(),
+            viewport_height,
+            shapes: None,
+            blocks: BTreeMap::new(),
+            shown: BTreeMap::new(),
+        }
+    }
+
+    /// Append a line. Line breaks in `line` are not interpreted.
+    pub fn append(&mut self, line: impl Into<String>) {
+        let following = self.is_following();
+        self.lines.push_back(line.into());
+        self.trim();
+        self.update_scroll_max();
+        if following {
+            self.scroll.set_offset(self.scroll.max());
+        }
+    }
+
+    /// Append each line of `text`.
+    pub fn append_lines(&mut self, text: &str) {
+        for line in text.lines() {
+            self.append(line);
+        }
+    }
+
+    /// The number of retained lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The numbers of the retained lines.
+    pub fn line_numbers(&self) -> Range<u64> {
+        self.first..self.first + self.lines.len() as u64
+    }
+
+    /// A retained line by its number.
+    pub fn line(&self, number: u64) -> Option<&str> {
+        let index = usize::try_from(number.checked_sub(self.first)?).ok()?;
+        self.lines.get(index).map(String::as_str)
+    }
+
+    pub fn retention(&self) -> Option<usize> {
+        self.retention
+    }
+
+    /// Keep at most `lines` lines and trim the oldest ones. `None` keeps all lines.
+    pub fn set_retention(&mut self, lines: Option<usize>) {
+        self.retention = lines;
+        self.trim();
+        self.update_scroll_max();
+    }
+
+    /// Drop all lines. Line numbers continue after the last appended line.
+    pub fn clear(&mut self) {
+        self.first += self.lines.len() as u64;
+        self.lines.clear();
+        self.update_scroll_max();
+        self.scroll.set_offset(0.0);
+    }
+
+    pub fn viewport_height(&self) -> f64 {
+        self.viewport_height
+    }
+
+    pub fn set_viewport_height(&mut self, height: f64) {
+        let following = self.is_following();
+        self.viewport_height = height;
+        self.update_scroll_max();
+        if following {
+            self.scroll.set_offset(self.scroll.max());
+        }
+    }
+
+    pub fn scroll_mut(&mut self) -> &mut KineticScroll {
+        &mut self.scroll
+    }
+
+    /// `true` if the view is at the end and follows appended lines.
+    pub fn is_following(&self) -> bool {
+        self.scroll.target().unwrap_or(self.scroll.offset()) >= self.scroll.max() - FOLLOW_TOLERANCE
+    }
+
+    /// Scroll to the end, so that appended lines are followed again.
+    pub fn follow(&mut self) {
+        self.scroll.set_offset(self.scroll.max());
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::MouseWheel { delta, .. } = event else {
+            return false;
+        };
+        match delta {
+            MouseScrollDelta::LineDelta(_, lines) => self
+                .scroll
+                .scroll_smoothly_by(-*lines as f64 * WHEEL_LINES * self.style.line_height()),
+            // Touchpads deliver their own momentum.
+            MouseScrollDelta::PixelDelta(delta) => self.scroll.scroll_by(-delta.y),
+        }
+        true
+    }
+
+    /// Advance scrolling to `now` and update the shapes of the lines in the viewport.
+    ///
+    /// Returns `true` while scrolling is animated.
+    pub fn update(
+        &mut self,
+        director: &mut Director,
+        font_system: &mut text::FontSystem,
+        now: Instant,
+    ) -> bool {
+        self.scroll.advance(now);
+        let line_height = self.style.line_height();
+        // The lines are placed at their numbers, so the scroll offset is made absolute.
+        let scroll_top = self.first as f64 * line_height + self.scroll.offset();
+
+        let shapes = self.shapes.get_or_insert_with(|| {
+            let scroll = director.cast(translation(-scroll_top));
+            let position = director.cast(Position {
+                parent: Some(self.position.clone()),
+                matrix: scroll.clone(),
+                pin: None,
+                overlay: false,
+            });
+            Shapes {
+                scroll,
+                position,
+                scroll_top,
+            }
+        });
+        if shapes.scroll_top != scroll_top {
+            shapes.scroll_top = scroll_top;
+            shapes.scroll.update(translation(-scroll_top));
+        }
+        let position = shapes.position.clone();
+
+        let visible = self.visible_lines(scroll_top);
+        self.shown.retain(|number, _| visible.contains(number));
+        let blocks = visible.start / BLOCK_LINES..visible.end.div_ceil(BLOCK_LINES);
+        self.blocks.retain(|block, _| blocks.contains(block));
+
+        for number in visible {
+            if self.shown.contains_key(&number) {
+                continue;
+            }
+            let line = &self.lines[(number - self.first) as usize];
+            let shape = (!line.is_empty()).then(|| {
+                let block = number / BLOCK_LINES;
+                let block = self.blocks.entry(block).or_insert_with(|| {
+                    let matrix =
+                        director.cast(translation((block * BLOCK_LINES) as f64 * line_height));
+                    Block {
+                        position: director.cast(Position {
+                            parent: Some(position.clone()),
+                            matrix: matrix.clone(),
+                            pin: None,
+                            overlay: false,
+                        }),
+                        _matrix: matrix,
+                    }
+                });
+                let (_, run) = LineLayout::shape(
+                    font_system,
+                    line,
+                    self.style.font_size,
+                    self.style.weight,
+                    self.style.color,
+                    (0.0, (number % BLOCK_LINES) as f64 * line_height, 0.0),
+                    self.style.font_size,
+                );
+                director.cast(PositionedShape::new(block.position.clone(), run))
+            });
+            self.shown.insert(number, shape);
+        }
+
+        self.scroll.is_animating()
+    }
+
+    /// The numbers of the retained lines that intersect the viewport.
+    fn visible_lines(&self, scroll_top: f64) -> Range<u64> {
+        let line_height = self.style.line_height();
+        let lines = self.line_numbers();
+        let first = ((scroll_top / line_height).floor().max(0.0) as u64).max(lines.start);
+        let end = ((scroll_top + self.viewport_height) / line_height)
+            .ceil()
+            .max(0.0) as u64;
+        first..end.min(lines.end).max(first)
+    }
+
+    fn trim(&mut self) {
+        let Some(retention) = self.retention else {
+            return;
+        };
+        let excess = self.lines.len().saturating_sub(retention);
+        if excess == 0 {
+            return;
+        }
+        self.lines.drain(..excess);
+        self.first += excess as u64;
+        // The offset is relative to the first line, keep the remaining lines in place.
+        self.scroll
+            .shift(-(excess as f64) * self.style.line_height());
+    }
+
+    fn update_scroll_max(&mut self) {
+        let content_height = self.lines.len() as f64 * self.style.line_height();
+        self.scroll.set_max(content_height - self.viewport_height);
+    }
+}
+
+fn translation(y: f64) -> Matrix4 {
+    Matrix4::from_translation(Vector3::new(0.0, y, 0.0))
+}
@@ -0,0 +1,46 @@
+//! Reusable interactive components.
+//!
+//! [`TextField`] is the reference implementation for interactive text: It combines the
+//! [`TextEdit`] model, shape generation, and keyboard, IME, clipboard, and pointer handling.
+//! [`Editor`] does the same for multi-line text backed by a rope, the [`Document`], and adds
+//! undo / redo and viewport virtualization for large texts. [`DocumentView`] shows read-only
+//! attributed paragraphs with wrapping, selection, [`KineticScroll`]ing, and an optional
+//! [`Scrollbar`]. Its span backgrounds, strikethroughs, and [`Gutter`] annotations like line
+//! numbers and diff markers follow the text when it wraps, and ranges of paragraphs can be
+//! folded, for code folding or to collapse logs. [`LogView`] tails append-only logs with millions
+//! of lines. [`TerminalGrid`] renders fixed-pitch character cells without shaping, for
+//! full-screen terminals. [`Tooltip`]s are placed next to an anchor in the overlay.
+//! [`RunSelection`] highlights a byte range of any glyph run and shows a blinking caret in it.
+
+mod caret_shape;
+mod clipboard;
+mod document;
+mod document_view;
+mod editor;
+mod gutter;
+mod kinetic_scroll;
+mod line_layout;
+mod log_view;
+mod run_selection;
+mod scrollbar;
+mod selection_region;
+mod terminal_grid;
+mod text_edit;
+mod text_field;
+mod tooltip;
+
+pub use clipboard::*;
+pub use document::*;
+pub use document_view::*;
+pub use editor::*;
+pub use gutter::*;
+pub use kinetic_scroll::*;
+pub use line_layout::LineLayout;
+pub use log_view::*;
+pub use run_selection::*;
+pub use scrollbar::*;
+pub use selection_region::*;
+pub use terminal_grid::*;
+pub use text_edit::*;
+pub use text_field::*;
+pub use tooltip::*;
@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use massive_geometry::{Bounds, Color, Rect};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::{Caret, GlyphRun, Quad};
+
+use super::{caret_shape::CaretShape, selection_region, LineLayout};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunSelectionStyle {
+    pub color: Color,
+    /// The corner radius of the highlight, zero for square corners.
+    pub radius: f64,
+    pub caret_color: Color,
+    pub caret_width: f64,
+    /// The time in seconds the caret takes to move to a new position.
+    pub caret_motion: f32,
+    pub caret_trail: bool,
+}
+
+impl Default for RunSelectionStyle {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(0.7, 0.8, 1.0),
+            radius: 0.0,
+            caret_color: Color::BLACK,
+            caret_width: 2.0,
+            caret_motion: 0.1,
+            caret_trail: false,
+        }
+    }
+}
+
+/// The highlight of the glyphs of `run` in the byte range `range`.
+///
+/// `layout` is the layout [`LineLayout::shape`] returned with the run. The quads are placed like
+/// the run at its translation and cover the height of the line. Quads are rendered before the
+/// text of the same position, so the highlight is underneath the glyphs.
+pub fn selection_highlight(
+    layout: &LineLayout,
+    run: &GlyphRun,
+    range: Range<usize>,
+    radius: f64,
+    color: Color,
+) -> Vec<Quad> {
+    if range.is_empty() {
+        return Vec::new();
+    }
+    selection_region(&[highlight_row(layout, run, range)], radius, color)
+}
+
+fn highlight_row(layout: &LineLayout, run: &GlyphRun, range: Range<usize>) -> Rect {
+    let (x, y) = (run.translation.x, run.translation.y);
+    let left = x + layout.x_at(range.start) as f64;
+    let right = x + layout.x_at(range.end) as f64;
+    Rect {
+        left: left.min(right),
+        top: y,
+        right: left.max(right),
+        bottom: y + layout.height() as f64,
+    }
+}
+
+/// The bounds of a caret of `width` in front of the byte `offset` of `run`.
+///
+/// `layout` is the layout [`LineLayout::shape`] returned with the run.
+pub fn caret_bounds(layout: &LineLayout, run: &GlyphRun, offset: usize, width: f64) -> Bounds {
+    let x = run.translation.x + layout.x_at(offset) as f64;
+    let y = run.translation.y;
+    Bounds::new((x, y), (x + width, y + layout.height() as f64))
+}
+
+/// The shapes of a selection and a caret in a glyph run.
+///
+/// The selection is highlighted underneath the glyphs. The caret blinks: Pass
+/// [`crate::WindowRenderer::caret_visible`] to [`Self::update`] and activate it with
+/// [`crate::WindowRenderer::set_caret_active`] while it is shown.
+#[derive(Debug)]
+pub struct RunSelection {
+    position: Handle<Position>,
+    style: RunSelectionStyle,
+    highlight: Option<(Handle<PositionedShape>, Rect)>,
+    caret: CaretShape,
+}
+
+impl RunSelection {
+    /// A selection placed at `position`, which is usually the position of the run.
+    pub fn new(position: Handle<Position>, style: RunSelectionStyle) -> Self {
+        Self {
+            position,
+            style,
+            highlight: None,
+            caret: CaretShape::default(),
+        }
+    }
+
+    pub fn style(&self) -> &RunSelectionStyle {
+        &self.style
+    }
+
+    /// Change the style, it is applied with the next update.
+    pub fn set_style(&mut self, style: RunSelectionStyle) {
+        self.style = style;
+        // Recreate the highlight.
+        self.highlight = None;
+    }
+
+    /// Highlight `range` of `run` and show the caret in front of the byte `caret`.
+    ///
+    /// `None` removes the caret, `caret_visible` is the phase of its blinking. Only changed shapes
+    /// are sent.
+    pub fn update(
+        &mut self,
+        director: &mut Director,
+        layout: &LineLayout,
+        run: &GlyphRun,
+        range: Range<usize>,
+        caret: Option<usize>,
+        caret_visible: bool,
+    ) {
+        self.update_highlight(director, layout, run, range);
+
+        let style = &self.style;
+        let caret = caret.filter(|_| caret_visible).map(|offset| {
+            let bounds = caret_bounds(layout, run, offset, style.caret_width);
+            let caret = Caret::new(bounds, style.caret_color).with_motion(style.caret_motion);
+            if style.caret_trail {
+                caret.with_trail()
+            } else {
+                caret
+            }
+        });
+        self.caret.update(director, &self.position, caret);
+    }
+
+    fn update_highlight(
+        &mut self,
+        director: &mut Director,
+        layout: &LineLayout,
+        run: &GlyphRun,
+        range: Range<usize>,
+    ) {
+        if range.is_empty() {
+            self.highlight = None;
+            return;
+        }
+        let row = highlight_row(layout, run, range);
+        if matches!(&self.highlight, Some((_, current)) if *current == row) {
+            return;
+        }
+
+        let quads = selection_region(&[row], self.style.radius, self.style.color);
+        let shape = PositionedShape::new(self.position.clone(), quads);
+        match &mut self.highlight {
+            Some((handle, current)) => {
+                handle.update(shape);
+                *current = row;
+            }
+            None => self.highlight = Some((director.cast(shape), row)),
+        }
+    }
+}
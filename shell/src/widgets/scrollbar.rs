@@ -0,0 +1,265 @@
+use std::ops::Range;
+
+use massive_geometry::{Color, Matrix4, Rect, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::Quad;
+
+use super::{selection_region, KineticScroll};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollbarStyle {
+    pub width: f64,
+    /// The thumb does not get shorter than this.
+    pub min_thumb_length: f64,
+    /// The space between the thumb and the edges of the track.
+    pub padding: f64,
+    pub track_color: Color,
+    pub thumb_color: Color,
+    pub thumb_hover_color: Color,
+    pub thumb_drag_color: Color,
+}
+
+impl Default for ScrollbarStyle {
+    fn default() -> Self {
+        Self {
+            width: 12.0,
+            min_thumb_length: 24.0,
+            padding: 2.0,
+            track_color: Color::new(0.0, 0.0, 0.0, 0.05),
+            thumb_color: Color::new(0.0, 0.0, 0.0, 0.3),
+            thumb_hover_color: Color::new(0.0, 0.0, 0.0, 0.45),
+            thumb_drag_color: Color::new(0.0, 0.0, 0.0, 0.6),
+        }
+    }
+}
+
+/// The part of a scrollbar at a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollbarHit {
+    Thumb,
+    /// The track before the thumb.
+    TrackBefore,
+    /// The track after the thumb.
+    TrackAfter,
+}
+
+/// A vertical scrollbar with a track and a draggable thumb.
+///
+/// The scrollbar shows the offset and the extent of a scroll region, see [`Self::sync`], but does
+/// not scroll it. Pointer interactions return the offsets the region should scroll to.
+///
+/// The scrollbar's shapes are placed at `position`, with the top left corner of the track at the
+/// origin. All coordinates the scrollbar takes are in pixels relative to that origin.
+#[derive(Debug)]
+pub struct Scrollbar {
+    style: ScrollbarStyle,
+    position: Handle<Position>,
+    /// The length of the track.
+    length: f64,
+    offset: f64,
+    max: f64,
+    viewport: f64,
+    hovered: bool,
+    /// The distance from the top of the thumb at which it was grabbed.
+    drag: Option<f64>,
+    dirty: bool,
+    shapes: Option<Handle<PositionedShape>>,
+}
+
+impl Scrollbar {
+    pub fn new(position: Handle<Position>, length: f64, style: ScrollbarStyle) -> Self {
+        Self {
+            style,
+            position,
+            length,
+            offset: 0.0,
+            max: 0.0,
+            viewport: length,
+            hovered: false,
+            drag: None,
+            dirty: true,
+            shapes: None,
+        }
+    }
+
+    pub fn style(&self) -> &ScrollbarStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: ScrollbarStyle) {
+        self.style = style;
+        self.dirty = true;
+    }
+
+    pub fn set_length(&mut self, length: f64) {
+        if length != self.length {
+            self.length = length;
+            self.dirty = true;
+        }
+    }
+
+    /// Show the offset and the extent of `scroll` with a viewport of `viewport` pixels.
+    pub fn sync(&mut self, scroll: &KineticScroll, viewport: f64) {
+        let (offset, max) = (scroll.offset(), scroll.max());
+        if (offset, max, viewport) != (self.offset, self.max, self.viewport) {
+            self.offset = offset;
+            self.max = max;
+            self.viewport = viewport;
+            self.dirty = true;
+        }
+    }
+
+    /// Whether there is anything to scroll. Otherwise the scrollbar is hidden.
+    pub fn is_visible(&self) -> bool {
+        self.max > 0.0
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// The range of the track the thumb covers, `None` if the scrollbar is hidden.
+    ///
+    /// While overscrolling, the thumb gets shorter at the edge.
+    pub fn thumb(&self) -> Option<Range<f64>> {
+        if !self.is_visible() {
+            return None;
+        }
+        let content = self.max + self.viewport;
+        let track = self.track();
+        let length = self.thumb_length();
+        let travel = track - length;
+
+        let overscroll = if self.offset < 0.0 {
+            -self.offset
+        } else {
+            (self.offset - self.max).max(0.0)
+        };
+        let shortened = (length - overscroll / content * track).max(self.style.width.min(length));
+        let start = self.style.padding + (self.offset / self.max).clamp(0.0, 1.0) * travel;
+        Some(if self.offset > self.max {
+            start + length - shortened..start + length
+        } else {
+            start..start + shortened
+        })
+    }
+
+    pub fn hit_test(&self, x: f64, y: f64) -> Option<ScrollbarHit> {
+        let thumb = self.thumb()?;
+        if !(0.0..self.style.width).contains(&x) || !(0.0..self.length).contains(&y) {
+            return None;
+        }
+        Some(if y < thumb.start {
+            ScrollbarHit::TrackBefore
+        } else if y < thumb.end {
+            ScrollbarHit::Thumb
+        } else {
+            ScrollbarHit::TrackAfter
+        })
+    }
+
+    /// Start dragging the thumb or page with a click on the track.
+    ///
+    /// Returns the hit and the offset the scroll region should scroll to, `None` if the
+    /// scrollbar was not hit. A page is the viewport minus a tenth, so that some context stays
+    /// visible.
+    pub fn pointer_pressed(&mut self, x: f64, y: f64) -> Option<(ScrollbarHit, f64)> {
+        let hit = self.hit_test(x, y)?;
+        let page = self.viewport * 0.9;
+        let offset = match hit {
+            ScrollbarHit::Thumb => {
+                let thumb = self.thumb()?;
+                self.drag = Some(y - thumb.start);
+                self.dirty = true;
+                self.offset
+            }
+            ScrollbarHit::TrackBefore => (self.offset - page).max(0.0),
+            ScrollbarHit::TrackAfter => (self.offset + page).min(self.max),
+        };
+        Some((hit, offset))
+    }
+
+    /// Update the hover state, and return the offset to scroll to while the thumb is dragged.
+    pub fn pointer_moved(&mut self, x: f64, y: f64) -> Option<f64> {
+        let hovered = self.hit_test(x, y) == Some(ScrollbarHit::Thumb);
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.dirty = true;
+        }
+
+        let grab = self.drag?;
+        let travel = self.track() - self.thumb_length();
+        if travel <= 0.0 {
+            return Some(self.offset);
+        }
+        let t = ((y - grab - self.style.padding) / travel).clamp(0.0, 1.0);
+        Some(t * self.max)
+    }
+
+    pub fn pointer_released(&mut self) {
+        if self.drag.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// The length of the track the thumb moves in.
+    fn track(&self) -> f64 {
+        (self.length - 2.0 * self.style.padding).max(0.0)
+    }
+
+    /// The length of the thumb, proportional to the part of the content that is visible.
+    fn thumb_length(&self) -> f64 {
+        let track = self.track();
+        (self.viewport / (self.max + self.viewport) * track)
+            .max(self.style.min_thumb_length)
+            .min(track)
+    }
+
+    /// Update the shapes of the track and the thumb.
+    pub fn update(&mut self, director: &mut Director) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let shape = PositionedShape::new(self.position.clone(), self.quads());
+        match &self.shapes {
+            Some(shapes) => shapes.update(shape),
+            None => self.shapes = Some(director.cast(shape)),
+        }
+    }
+
+    fn quads(&self) -> Vec<Quad> {
+        let Some(thumb) = self.thumb() else {
+            return Vec::new();
+        };
+        let style = &self.style;
+        let color = if self.drag.is_some() {
+            style.thumb_drag_color
+        } else if self.hovered {
+            style.thumb_hover_color
+        } else {
+            style.thumb_color
+        };
+        let track = Rect {
+            left: 0.0,
+            top: 0.0,
+            right: style.width,
+            bottom: self.length,
+        };
+        let thumb = Rect {
+            left: style.padding,
+            top: thumb.start,
+            right: style.width - style.padding,
+            bottom: thumb.end,
+        };
+        let radius = (style.width / 2.0 - style.padding).max(0.0);
+        let mut quads = selection_region(&[track], style.width / 2.0, style.track_color);
+        quads.extend(selection_region(&[thumb], radius, color));
+        quads
+    }
+}
+
+/// The matrix that places a scrollbar at the right edge of a view of `width`.
+pub(crate) fn scrollbar_matrix(width: f64, style: &ScrollbarStyle) -> Matrix4 {
+    Matrix4::from_translation(Vector3::new(width - style.width, 0.0, 0.0))
+}
@@ -0,0 +1,149 @@
+use std::f64::consts::FRAC_PI_2;
+
+use massive_geometry::{Color, Point, Rect, Vector3};
+use massive_shapes::Quad;
+
+use super::line_layout::rect;
+
+/// The number of segments a rounded corner is approximated with.
+const ARC_SEGMENTS: usize = 6;
+
+/// Rows that are closer than this vertically are merged.
+const TOUCH_EPSILON: f64 = 0.5;
+
+/// Generate the quads of a selection that spans several lines as one merged region.
+///
+/// `rows` are the selected areas of the lines, from top to bottom. Rows that touch vertically
+/// form one region, whose outer corners are rounded with `radius`, and whose inner corners are
+/// filled with fillets of the same radius, like in modern editors. A radius of zero produces one
+/// rectangle per row.
+pub fn selection_region(rows: &[Rect], radius: f64, color: Color) -> Vec<Quad> {
+    let rows: Vec<&Rect> = rows.iter().filter(|row| !row.is_empty()).collect();
+    if radius <= 0.0 {
+        return rows
+            .iter()
+            .map(|r| rect(r.left, r.top, r.right, r.bottom, color))
+            .collect();
+    }
+
+    let mut quads = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let above = i
+            .checked_sub(1)
+            .map(|i| rows[i])
+            .filter(|above| (above.bottom - row.top).abs() < TOUCH_EPSILON);
+        let below = rows
+            .get(i + 1)
+            .copied()
+            .filter(|below| (row.bottom - below.top).abs() < TOUCH_EPSILON);
+
+        let height = row.bottom - row.top;
+        let mut outline = Vec::new();
+        // Clockwise, starting at the top left.
+        let corners = [
+            (row.left, row.top, 1.0, 1.0, above),
+            (row.right, row.top, -1.0, 1.0, above),
+            (row.right, row.bottom, -1.0, -1.0, below),
+            (row.left, row.bottom, 1.0, -1.0, below),
+        ];
+        for (index, (x, y, dx, dy, neighbor)) in corners.into_iter().enumerate() {
+            let corner = Point::new(x, y);
+            match Corner::classify(row, x, dx, neighbor) {
+                Corner::Convex { exposed } => {
+                    let radius = radius.min(exposed / 2.0).min(height / 2.0);
+                    let center = Point::new(x + dx * radius, y + dy * radius);
+                    let mut arc = arc(center, -dx * radius, -dy * radius);
+                    // The arc runs from the horizontal to the vertical edge.
+                    if index % 2 == 0 {
+                        arc.reverse();
+                    }
+                    outline.extend(arc);
+                }
+                Corner::Square => outline.push(corner),
+                Corner::Concave { exposed } => {
+                    outline.push(corner);
+                    let radius = radius.min(exposed / 2.0).min(height / 2.0);
+                    let center = Point::new(x - dx * radius, y + dy * radius);
+                    fan(
+                        corner,
+                        &arc(center, dx * radius, -dy * radius),
+                        color,
+                        &mut quads,
+                    );
+                }
+            }
+        }
+        outline.push(outline[0]);
+
+        let center = Point::new((row.left + row.right) / 2.0, (row.top + row.bottom) / 2.0);
+        fan(center, &outline, color, &mut quads);
+    }
+    quads
+}
+
+/// How a corner of a row meets the row above or below.
+enum Corner {
+    /// An outer corner, `exposed` is the length of the horizontal edge it rounds.
+    Convex { exposed: f64 },
+    /// The neighbor continues the vertical edge.
+    Square,
+    /// The neighbor extends beyond the row, `exposed` is the length of the neighbor's edge that
+    /// the fillet lies on.
+    Concave { exposed: f64 },
+}
+
+impl Corner {
+    /// Classify the corner of `row` at `x`. `dx` points into the row.
+    fn classify(row: &Rect, x: f64, dx: f64, neighbor: Option<&Rect>) -> Self {
+        let width = row.right - row.left;
+        let Some(n) = neighbor else {
+            return Corner::Convex { exposed: width };
+        };
+        // The neighbor's edge that faces the same direction as this corner, and the opposite one.
+        let (near, far) = if dx > 0.0 {
+            (n.left, n.right)
+        } else {
+            (n.right, n.left)
+        };
+        let covered = (x - near) * dx >= 0.0 && (far - x) * dx > 0.0;
+        if !covered {
+            // The neighbor may cover a part of this row's edge.
+            let exposed = if (near - x) * dx > 0.0 && (near - x) * dx < width {
+                (near - x) * dx
+            } else {
+                width
+            };
+            return Corner::Convex { exposed };
+        }
+        if near == x {
+            return Corner::Square;
+        }
+        Corner::Concave {
+            exposed: (x - near) * dx,
+        }
+    }
+}
+
+/// A quarter circle around `center`, from the point at `(0, vy)` to the point at `(vx, 0)`
+/// relative to it.
+fn arc(center: Point, vx: f64, vy: f64) -> Vec<Point> {
+    (0..=ARC_SEGMENTS)
+        .map(|i| {
+            let angle = FRAC_PI_2 * i as f64 / ARC_SEGMENTS as f64;
+            Point::new(center.x + vx * angle.sin(), center.y + vy * angle.cos())
+        })
+        .collect()
+}
+
+/// Fill the triangle fan from `center` through `points`, two triangles per quad.
+fn fan(center: Point, points: &[Point], color: Color, quads: &mut Vec<Quad>) {
+    let vertex = |p: Point| Vector3::new(p.x, p.y, 0.0);
+    for i in (0..points.len().saturating_sub(1)).step_by(2) {
+        let (a, b) = (points[i], points[i + 1]);
+        let c = points.get(i + 2).copied().unwrap_or(b);
+        quads.push(Quad::new(
+            [vertex(center), vertex(a), vertex(b), vertex(c)],
+            color,
+        ));
+    }
+}
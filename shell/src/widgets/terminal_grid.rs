@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+
+use bitflags::bitflags;
+use cosmic_text as text;
+
+use massive_geometry::Color;
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::{GlyphRun, GlyphRunMetrics, Quad, RunGlyph, TextWeight};
+
+use super::line_layout::rect;
+
+/// A fixed-pitch grid of character cells, for example the screen of a terminal emulator.
+///
+/// Instead of shaping text, each cell's character is looked up in a glyph cache and placed at
+/// its cell. Only rows that changed since the last [`Self::update`] get new shapes. All rows
+/// share the grid's position, so the backgrounds of all cells are rendered from a single quad
+/// buffer.
+///
+/// The grid is placed at `position`, with the top left corner of the first cell at the origin.
+#[derive(Debug)]
+pub struct TerminalGrid {
+    position: Handle<Position>,
+    style: TerminalStyle,
+    columns: usize,
+    cells: Vec<Cell>,
+    /// Rows that changed since the last update.
+    damaged: Vec<bool>,
+    cursor: Option<(usize, usize)>,
+    cursor_style: CursorStyle,
+    focused: bool,
+    /// Measured on the first update.
+    metrics: Option<CellMetrics>,
+    glyphs: GlyphCache,
+    rows: Vec<RowShapes>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalStyle {
+    pub font_size: f32,
+    /// The color of [`TerminalColor::Default`] foregrounds.
+    pub foreground: Color,
+    /// The color of [`TerminalColor::Default`] backgrounds.
+    pub background: Color,
+    pub cursor: Color,
+}
+
+impl Default for TerminalStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 16.0,
+            foreground: Color::rgb_u32(0xe5e5e5),
+            background: Color::rgb_u32(0x1e1e1e),
+            cursor: Color::rgb_u32(0xc0c0c0),
+        }
+    }
+}
+
+/// The color of a cell's foreground or background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerminalColor {
+    /// The default color of the [`TerminalStyle`].
+    #[default]
+    Default,
+    /// An entry of the xterm 256 color palette. The first 16 are the ANSI colors.
+    Indexed(u8),
+    /// A true color.
+    Rgb(u8, u8, u8),
+}
+
+impl TerminalColor {
+    /// The color, `default` for [`Self::Default`].
+    pub fn resolve(self, default: Color) -> Color {
+        match self {
+            TerminalColor::Default => default,
+            TerminalColor::Indexed(index) => indexed_color(index),
+            TerminalColor::Rgb(r, g, b) => (r, g, b).into(),
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct CellFlags: u8 {
+        const BOLD = 0x01;
+        const ITALIC = 0x02;
+        const UNDERLINE = 0x04;
+        const STRIKETHROUGH = 0x08;
+        /// Swap foreground and background.
+        const INVERSE = 0x10;
+        /// Render the foreground with half intensity.
+        const DIM = 0x20;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub c: char,
+    pub foreground: TerminalColor,
+    pub background: TerminalColor,
+    pub flags: CellFlags,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            c: ' ',
+            foreground: TerminalColor::Default,
+            background: TerminalColor::Default,
+            flags: CellFlags::empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorStyle {
+    /// Covers the cell and shows its character inverted.
+    #[default]
+    Block,
+    /// The outline of the cell. Used for block cursors while the grid is not focused.
+    HollowBlock,
+    Underline,
+    /// A vertical bar at the left of the cell.
+    Bar,
+}
+
+/// The size of a cell in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellMetrics {
+    pub width: f64,
+    pub height: f64,
+    /// The distance from the top of a cell to the baseline.
+    pub ascent: f64,
+}
+
+impl CellMetrics {
+    /// Measure the cells of the system's monospace font at `font_size`.
+    pub fn measure(font_system: &mut text::FontSystem, font_size: f32) -> Self {
+        let attrs = text::Attrs::new().family(text::Family::Monospace);
+        let mut line =
+            text::BufferLine::new("M", text::AttrsList::new(attrs), text::Shaping::Advanced);
+        let line = &line.layout(font_system, font_size, f32::MAX, text::Wrap::None, None)[0];
+        Self {
+            width: line.w as f64,
+            height: (line.max_ascent + line.max_descent).ceil() as f64,
+            ascent: line.max_ascent.round() as f64,
+        }
+    }
+}
+
+/// The width of underlines, strikethroughs, and the bar and outline cursors.
+const LINE_WIDTH: f64 = 2.0;
+
+#[derive(Debug, Default)]
+struct RowShapes {
+    backgrounds: Option<Handle<PositionedShape>>,
+    /// One glyph run for each color and weight.
+    runs: Vec<Handle<PositionedShape>>,
+}
+
+impl TerminalGrid {
+    pub fn new(
+        position: Handle<Position>,
+        style: TerminalStyle,
+        columns: usize,
+        rows: usize,
+    ) -> Self {
+        Self {
+            position,
+            style,
+            columns,
+            cells: vec![Cell::default(); columns * rows],
+            damaged: vec![true; rows],
+            cursor: None,
+            cursor_style: CursorStyle::default(),
+            focused: true,
+            metrics: None,
+            glyphs: GlyphCache::default(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.damaged.len()
+    }
+
+    pub fn style(&self) -> &TerminalStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: TerminalStyle) {
+        if style.font_size != self.style.font_size {
+            self.metrics = None;
+        }
+        self.style = style;
+        self.damage_all();
+    }
+
+    /// Shape all text again with the next update, for example after fonts were reloaded.
+    pub fn relayout(&mut self) {
+        self.metrics = None;
+        self.glyphs = GlyphCache::default();
+        self.damage_all();
+    }
+
+    /// The cell metrics, `None` before the first update.
+    pub fn metrics(&self) -> Option<&CellMetrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Change the size of the grid. The content is kept at the top left.
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        if columns == self.columns && rows == self.rows() {
+            return;
+        }
+        let mut cells = vec![Cell::default(); columns * rows];
+        let kept_columns = columns.min(self.columns);
+        for row in 0..rows.min(self.rows()) {
+            let from = row * self.columns;
+            cells[row * columns..][..kept_columns]
+                .copy_from_slice(&self.cells[from..from + kept_columns]);
+        }
+        self.cells = cells;
+        self.columns = columns;
+        self.damaged = vec![true; rows];
+        self.rows.truncate(rows);
+        if let Some((row, column)) = self.cursor {
+            if row >= rows || column >= columns {
+                self.cursor = None;
+            }
+        }
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> Option<&Cell> {
+        if column >= self.columns {
+            return None;
+        }
+        self.cells.get(row * self.columns + column)
+    }
+
+    pub fn set_cell(&mut self, row: usize, column: usize, cell: Cell) {
+        if column >= self.columns || row >= self.rows() {
+            return;
+        }
+        let current = &mut self.cells[row * self.columns + column];
+        if *current != cell {
+            *current = cell;
+            self.damaged[row] = true;
+        }
+    }
+
+    /// The cells of a row for modification. Marks the row as damaged.
+    pub fn row_mut(&mut self, row: usize) -> &mut [Cell] {
+        self.damaged[row] = true;
+        &mut self.cells[row * self.columns..][..self.columns]
+    }
+
+    /// Write `text` starting at a cell with the colors and flags of `template`.
+    ///
+    /// Characters beyond the end of the row are dropped. Returns the column after the last
+    /// character written.
+    pub fn write_str(&mut self, row: usize, column: usize, text: &str, template: Cell) -> usize {
+        let mut column = column;
+        for c in text.chars() {
+            if column >= self.columns {
+                break;
+            }
+            self.set_cell(row, column, Cell { c, ..template });
+            column += 1;
+        }
+        column
+    }
+
+    /// Reset all cells.
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::default());
+        self.damage_all();
+    }
+
+    /// Move all rows up by `lines` and clear the rows at the bottom.
+    pub fn scroll_up(&mut self, lines: usize) {
+        let lines = lines.min(self.rows());
+        self.cells.drain(..lines * self.columns);
+        self.cells
+            .resize(self.columns * self.damaged.len(), Cell::default());
+        // OO: Move the row shapes instead of creating them again.
+        self.damage_all();
+    }
+
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    /// Set the row and column of the cursor, `None` hides it.
+    pub fn set_cursor(&mut self, cursor: Option<(usize, usize)>) {
+        if cursor == self.cursor {
+            return;
+        }
+        self.damage_cursor_row();
+        self.cursor = cursor.filter(|(row, column)| *row < self.rows() && *column < self.columns);
+        self.damage_cursor_row();
+    }
+
+    pub fn cursor_style(&self) -> CursorStyle {
+        self.cursor_style
+    }
+
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        if style != self.cursor_style {
+            self.cursor_style = style;
+            self.damage_cursor_row();
+        }
+    }
+
+    /// An unfocused grid shows block cursors hollow.
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused != self.focused {
+            self.focused = focused;
+            self.damage_cursor_row();
+        }
+    }
+
+    /// The cell at a position relative to the grid's origin, `None` before the first update or
+    /// if the position is outside of the grid.
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let metrics = self.metrics.as_ref()?;
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let row = (y / metrics.height) as usize;
+        let column = (x / metrics.width) as usize;
+        (row < self.rows() && column < self.columns).then_some((row, column))
+    }
+
+    /// Update the shapes of the damaged rows.
+    pub fn update(&mut self, director: &mut Director, font_system: &mut text::FontSystem) {
+        let metrics = *self
+            .metrics
+            .get_or_insert_with(|| CellMetrics::measure(font_system, self.style.font_size));
+
+        let rows = self.rows();
+        self.rows.resize_with(rows, RowShapes::default);
+
+        for row in 0..rows {
+            if !self.damaged[row] {
+                continue;
+            }
+            self.damaged[row] = false;
+
+            let (backgrounds, runs) = self.row_contents(font_system, &metrics, row);
+            let shapes = &mut self.rows[row];
+
+            let backgrounds = PositionedShape::new(self.position.clone(), backgrounds);
+            match &shapes.backgrounds {
+                Some(handle) => handle.update(backgrounds),
+                None => shapes.backgrounds = Some(director.cast(backgrounds)),
+            }
+
+            shapes.runs.truncate(runs.len());
+            for (i, run) in runs.into_iter().enumerate() {
+                let run = PositionedShape::new(self.position.clone(), run);
+                match shapes.runs.get(i) {
+                    Some(handle) => handle.update(run),
+                    None => shapes.runs.push(director.cast(run)),
+                }
+            }
+        }
+    }
+
+    /// The background and decoration quads and the glyph runs of a row.
+    fn row_contents(
+        &mut self,
+        font_system: &mut text::FontSystem,
+        metrics: &CellMetrics,
+        row: usize,
+    ) -> (Vec<Quad>, Vec<GlyphRun>) {
+        let style = &self.style;
+        let top = row as f64 * metrics.height;
+        let bottom = top + metrics.height;
+        let cells = &self.cells[row * self.columns..][..self.columns];
+
+        let cursor = self
+            .cursor
+            .filter(|(cursor_row, _)| *cursor_row == row)
+            .map(|(_, column)| column);
+        let cursor_style = match self.cursor_style {
+            CursorStyle::Block if !self.focused => CursorStyle::HollowBlock,
+            style => style,
+        };
+
+        let mut backgrounds: Vec<(usize, usize, Color)> = Vec::new();
+        let mut decorations = Vec::new();
+        let mut runs: Vec<GlyphRun> = Vec::new();
+
+        for (column, cell) in cells.iter().enumerate() {
+            let left = column as f64 * metrics.width;
+            let right = left + metrics.width;
+
+            let mut foreground = cell.foreground.resolve(style.foreground);
+            let mut background = cell.background.resolve(style.background);
+            if cell.flags.contains(CellFlags::INVERSE) {
+                (foreground, background) = (background, foreground);
+            }
+            if cell.flags.contains(CellFlags::DIM) {
+                foreground.alpha *= 0.5;
+            }
+
+            if cursor == Some(column) {
+                match cursor_style {
+                    CursorStyle::Block => {
+                        foreground = background;
+                        background = style.cursor;
+                    }
+                    CursorStyle::HollowBlock => {
+                        let (l, r) = (left + LINE_WIDTH, right - LINE_WIDTH);
+                        decorations.extend([
+                            rect(left, top, right, top + LINE_WIDTH, style.cursor),
+                            rect(left, bottom - LINE_WIDTH, right, bottom, style.cursor),
+                            rect(left, top + LINE_WIDTH, l, bottom - LINE_WIDTH, style.cursor),
+                            rect(
+                                r,
+                                top + LINE_WIDTH,
+                                right,
+                                bottom - LINE_WIDTH,
+                                style.cursor,
+                            ),
+                        ]);
+                    }
+                    CursorStyle::Underline => decorations.push(rect(
+                        left,
+                        bottom - LINE_WIDTH,
+                        right,
+                        bottom,
+                        style.cursor,
+                    )),
+                    CursorStyle::Bar => {
+                        decorations.push(rect(left, top, left + LINE_WIDTH, bottom, style.cursor))
+                    }
+                }
+            }
+
+            // Merge the backgrounds of adjacent cells.
+            match backgrounds.last_mut() {
+                Some((_, end, color)) if *color == background => *end = column + 1,
+                _ => backgrounds.push((column, column + 1, background)),
+            }
+
+            if cell.flags.contains(CellFlags::UNDERLINE) {
+                let y = top + metrics.ascent + LINE_WIDTH;
+                decorations.push(rect(left, y, right, y + LINE_WIDTH, foreground));
+            }
+            if cell.flags.contains(CellFlags::STRIKETHROUGH) {
+                let y = top + (metrics.ascent / 1.5).round();
+                decorations.push(rect(left, y, right, y + LINE_WIDTH, foreground));
+            }
+
+            if cell.c.is_whitespace() || cell.c.is_control() {
+                continue;
+            }
+            let bold = cell.flags.contains(CellFlags::BOLD);
+            let italic = cell.flags.contains(CellFlags::ITALIC);
+            let Some((font_id, glyph_id)) =
+                self.glyphs
+                    .get(font_system, style.font_size, (cell.c, bold, italic))
+            else {
+                continue;
+            };
+            let (key, x, y) = text::CacheKey::new(
+                font_id,
+                glyph_id,
+                style.font_size,
+                (left as f32, 0.0),
+                text::CacheKeyFlags::empty(),
+            );
+            let glyph = RunGlyph::new(key, (x, y), metrics.width as f32);
+
+            let weight = if bold {
+                TextWeight::BOLD
+            } else {
+                TextWeight::NORMAL
+            };
+            let run = match runs
+                .iter_mut()
+                .find(|run| run.text_color == foreground && run.text_weight == weight)
+            {
+                Some(run) => run,
+                None => {
+                    runs.push(GlyphRun::new(
+                        (0.0, top, 0.0),
+                        GlyphRunMetrics {
+                            max_ascent: metrics.ascent as u32,
+                            max_descent: (metrics.height - metrics.ascent) as u32,
+                            width: (metrics.width * self.columns as f64).ceil() as u32,
+                        },
+                        foreground,
+                        weight,
+                        Vec::new(),
+                    ));
+                    runs.last_mut().unwrap()
+                }
+            };
+            run.glyphs.push(glyph);
+        }
+
+        // Decorations are rendered after the backgrounds they are on.
+        let quads = backgrounds
+            .into_iter()
+            .map(|(start, end, color)| {
+                rect(
+                    start as f64 * metrics.width,
+                    top,
+                    end as f64 * metrics.width,
+                    bottom,
+                    color,
+                )
+            })
+            .chain(decorations)
+            .collect();
+
+        (quads, runs)
+    }
+
+    fn damage_all(&mut self) {
+        self.damaged.fill(true);
+    }
+
+    fn damage_cursor_row(&mut self) {
+        if let Some((row, _)) = self.cursor {
+            self.damaged[row] = true;
+        }
+    }
+}
+
+/// Maps characters to the glyphs of the monospace font, or a fallback font.
+#[derive(Debug, Default)]
+struct GlyphCache {
+    /// Glyphs by character, bold, and italic. `None` if there is no font for the character.
+    glyphs: HashMap<(char, bool, bool), Option<(text::fontdb::ID, u16)>>,
+}
+
+impl GlyphCache {
+    fn get(
+        &mut self,
+        font_system: &mut text::FontSystem,
+        font_size: f32,
+        key: (char, bool, bool),
+    ) -> Option<(text::fontdb::ID, u16)> {
+        *self.glyphs.entry(key).or_insert_with(|| {
+            let (c, bold, italic) = key;
+            let mut attrs = text::Attrs::new().family(text::Family::Monospace);
+            if bold {
+                attrs = attrs.weight(text::Weight::BOLD);
+            }
+            if italic {
+                attrs = attrs.style(text::Style::Italic);
+            }
+            let mut line = text::BufferLine::new(
+                c.to_string(),
+                text::AttrsList::new(attrs),
+                text::Shaping::Advanced,
+            );
+            let layout = line.layout(font_system, font_size, f32::MAX, text::Wrap::None, None);
+            let glyph = layout.first()?.glyphs.first()?;
+            Some((glyph.font_id, glyph.glyph_id))
+        })
+    }
+}
+
+/// A color of the xterm 256 color palette.
+pub fn indexed_color(index: u8) -> Color {
+    const ANSI: [u32; 16] = [
+        0x000000, 0xcd0000, 0x00cd00, 0xcdcd00, 0x0000ee, 0xcd00cd, 0x00cdcd, 0xe5e5e5, //
+        0x7f7f7f, 0xff0000, 0x00ff00, 0xffff00, 0x5c5cff, 0xff00ff, 0x00ffff, 0xffffff,
+    ];
+    const CUBE_LEVELS: [u8; 6] = [0, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+    match index {
+        0..=15 => Color::rgb_u32(ANSI[index as usize]),
+        16..=231 => {
+            let i = index as usize - 16;
+            (
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[i / 6 % 6],
+                CUBE_LEVELS[i % 6],
+            )
+                .into()
+        }
+        232..=255 => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level).into()
+        }
+    }
+}
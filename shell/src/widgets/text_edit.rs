@@ -0,0 +1,391 @@
+use std::ops::Range;
+
+/// The editing state of a single line of text: The text, the caret, the selection, and the IME
+/// preedit text.
+///
+/// All positions are byte offsets into the text and always lie on character boundaries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextEdit {
+    text: String,
+    caret: usize,
+    /// The other end of the selection. The selection is empty if this is `None` or equal to
+    /// `caret`.
+    anchor: Option<usize>,
+    preedit: Option<Preedit>,
+}
+
+/// Text that is being composed by an input method and not committed yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    /// The byte range of the IME's cursor inside the preedit text.
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl TextEdit {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text: String = text.into();
+        let caret = text.len();
+        Self {
+            text,
+            caret,
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text and move the caret to the end.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        *self = Self::new(text);
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    pub fn preedit(&self) -> Option<&Preedit> {
+        self.preedit.as_ref()
+    }
+
+    /// The selected range, `None` if nothing is selected.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        match anchor.cmp(&self.caret) {
+            std::cmp::Ordering::Less => Some(anchor..self.caret),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(self.caret..anchor),
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|range| &self.text[range])
+    }
+
+    /// The text as displayed, with the preedit text inserted at the caret.
+    ///
+    /// Returns the displayed text and the range of the preedit text in it.
+    pub fn display_text(&self) -> (String, Option<Range<usize>>) {
+        match &self.preedit {
+            Some(preedit) if !preedit.text.is_empty() => {
+                let mut text = self.text.clone();
+                text.insert_str(self.caret, &preedit.text);
+                (text, Some(self.caret..self.caret + preedit.text.len()))
+            }
+            _ => (self.text.clone(), None),
+        }
+    }
+
+    /// Insert text at the caret, replacing the selection.
+    pub fn insert(&mut self, text: &str) {
+        // A single line does not contain line breaks.
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        let range = self.selection().unwrap_or(self.caret..self.caret);
+        self.text.replace_range(range.clone(), &text);
+        self.caret = range.start + text.len();
+        self.anchor = None;
+    }
+
+    /// Delete the selection or the character before the caret.
+    pub fn delete_backward(&mut self, word: bool) {
+        if self.delete_selection() {
+            return;
+        }
+        let start = if word {
+            self.previous_word(self.caret)
+        } else {
+            self.previous_char(self.caret)
+        };
+        self.text.replace_range(start..self.caret, "");
+        self.caret = start;
+    }
+
+    /// Delete the selection or the character after the caret.
+    pub fn delete_forward(&mut self, word: bool) {
+        if self.delete_selection() {
+            return;
+        }
+        let end = if word {
+            self.next_word(self.caret)
+        } else {
+            self.next_char(self.caret)
+        };
+        self.text.replace_range(self.caret..end, "");
+    }
+
+    /// Delete the selected text. Returns `false` if nothing was selected.
+    pub fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+        self.text.replace_range(range.clone(), "");
+        self.caret = range.start;
+        self.anchor = None;
+        true
+    }
+
+    /// Move the caret one character or word to the left.
+    ///
+    /// Without `extend`, a selection collapses to its start.
+    pub fn move_left(&mut self, word: bool, extend: bool) {
+        let target = match (self.selection(), extend) {
+            (Some(range), false) => range.start,
+            _ if word => self.previous_word(self.caret),
+            _ => self.previous_char(self.caret),
+        };
+        self.move_to(target, extend);
+    }
+
+    /// Move the caret one character or word to the right.
+    ///
+    /// Without `extend`, a selection collapses to its end.
+    pub fn move_right(&mut self, word: bool, extend: bool) {
+        let target = match (self.selection(), extend) {
+            (Some(range), false) => range.end,
+            _ if word => self.next_word(self.caret),
+            _ => self.next_char(self.caret),
+        };
+        self.move_to(target, extend);
+    }
+
+    pub fn move_home(&mut self, extend: bool) {
+        self.move_to(0, extend);
+    }
+
+    pub fn move_end(&mut self, extend: bool) {
+        self.move_to(self.text.len(), extend);
+    }
+
+    /// Move the caret to `offset`. If `extend` is set, the selection is extended to it.
+    ///
+    /// `offset` is clamped to the text and moved back to the previous character boundary.
+    pub fn move_to(&mut self, offset: usize, extend: bool) {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        if extend {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = offset;
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.text.len();
+    }
+
+    /// Select the word at `offset`.
+    pub fn select_word(&mut self, offset: usize) {
+        self.move_to(offset, false);
+        let start = self.previous_word(self.next_char(self.caret));
+        let end = self.next_word(start);
+        self.anchor = Some(start);
+        self.caret = end;
+    }
+
+    /// Set the text an input method is composing. An empty text ends the composition.
+    pub fn set_preedit(&mut self, text: String, cursor: Option<(usize, usize)>) {
+        self.preedit = (!text.is_empty()).then_some(Preedit { text, cursor });
+        if self.preedit.is_some() {
+            // The composed text replaces the selection when it's committed.
+            self.delete_selection();
+        }
+    }
+
+    /// Commit text composed by an input method.
+    pub fn commit(&mut self, text: &str) {
+        self.preedit = None;
+        self.insert(text);
+    }
+
+    fn previous_char(&self, offset: usize) -> usize {
+        self.text[..offset]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn next_char(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .chars()
+            .next()
+            .map(|c| offset + c.len_utf8())
+            .unwrap_or(offset)
+    }
+
+    /// The start of the word before `offset`, skipping whitespace.
+    fn previous_word(&self, offset: usize) -> usize {
+        let before = &self.text[..offset];
+        let end = before.trim_end().len();
+        let start = before[..end]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !is_word_char(*c))
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        // Punctuation counts as a word of its own.
+        if start == end {
+            self.previous_char(end)
+        } else {
+            start
+        }
+    }
+
+    /// The end of the word after `offset`, skipping whitespace.
+    fn next_word(&self, offset: usize) -> usize {
+        let after = &self.text[offset..];
+        let start = offset + (after.len() - after.trim_start().len());
+        let end = self.text[start..]
+            .char_indices()
+            .find(|(_, c)| !is_word_char(*c))
+            .map(|(i, _)| start + i)
+            .unwrap_or(self.text.len());
+        // Punctuation counts as a word of its own.
+        if start == end {
+            self.next_char(start)
+        } else {
+            end
+        }
+    }
+}
+
+pub(super) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_places_the_caret_at_the_end() {
+        let edit = TextEdit::new("héllo");
+        assert_eq!(edit.caret(), 6);
+        assert_eq!(edit.selection(), None);
+    }
+
+    #[test]
+    fn insert_replaces_the_selection() {
+        let mut edit = TextEdit::new("hello world");
+        edit.move_home(false);
+        edit.move_right(true, true);
+        assert_eq!(edit.selected_text(), Some("hello"));
+
+        edit.insert("bye");
+        assert_eq!(edit.text(), "bye world");
+        assert_eq!(edit.caret(), 3);
+        assert_eq!(edit.selection(), None);
+    }
+
+    #[test]
+    fn insert_skips_control_characters() {
+        let mut edit = TextEdit::default();
+        edit.insert("a\nb\tc");
+        assert_eq!(edit.text(), "abc");
+        assert_eq!(edit.caret(), 3);
+    }
+
+    #[test]
+    fn delete_backward_removes_whole_characters() {
+        let mut edit = TextEdit::new("aé");
+        edit.delete_backward(false);
+        assert_eq!(edit.text(), "a");
+        assert_eq!(edit.caret(), 1);
+    }
+
+    #[test]
+    fn delete_backward_removes_words_and_the_whitespace_after_them() {
+        let mut edit = TextEdit::new("hello big  world");
+        edit.delete_backward(true);
+        assert_eq!(edit.text(), "hello big  ");
+        edit.delete_backward(true);
+        assert_eq!(edit.text(), "hello ");
+        assert_eq!(edit.caret(), 6);
+    }
+
+    #[test]
+    fn punctuation_is_a_word_of_its_own() {
+        let mut edit = TextEdit::new("foo, bar");
+        edit.move_home(false);
+        edit.delete_forward(true);
+        assert_eq!(edit.text(), ", bar");
+        edit.delete_forward(true);
+        assert_eq!(edit.text(), " bar");
+        assert_eq!(edit.caret(), 0);
+    }
+
+    #[test]
+    fn moving_without_extending_collapses_the_selection() {
+        let mut edit = TextEdit::new("hello");
+        edit.select_all();
+        edit.move_left(false, false);
+        assert_eq!(edit.caret(), 0);
+        assert_eq!(edit.selection(), None);
+
+        edit.select_all();
+        edit.move_right(false, false);
+        assert_eq!(edit.caret(), 5);
+        assert_eq!(edit.selection(), None);
+    }
+
+    #[test]
+    fn extending_keeps_the_anchor() {
+        let mut edit = TextEdit::new("abc");
+        edit.move_home(false);
+        edit.move_right(false, true);
+        edit.move_right(false, true);
+        assert_eq!(edit.selected_text(), Some("ab"));
+        edit.move_left(false, true);
+        assert_eq!(edit.selection(), Some(0..1));
+    }
+
+    #[test]
+    fn move_to_clamps_to_character_boundaries() {
+        let mut edit = TextEdit::new("aé");
+        edit.move_to(2, false);
+        assert_eq!(edit.caret(), 1);
+        edit.move_to(100, false);
+        assert_eq!(edit.caret(), 3);
+    }
+
+    #[test]
+    fn select_word_selects_the_word_at_the_offset() {
+        let mut edit = TextEdit::new("one two three");
+        edit.select_word(5);
+        assert_eq!(edit.selection(), Some(4..7));
+        assert_eq!(edit.selected_text(), Some("two"));
+    }
+
+    #[test]
+    fn preedit_is_displayed_at_the_caret_until_committed() {
+        let mut edit = TextEdit::new("ab");
+        edit.move_to(1, false);
+        edit.set_preedit("x".into(), Some((1, 1)));
+        assert_eq!(edit.text(), "ab");
+        assert_eq!(edit.display_text(), ("axb".into(), Some(1..2)));
+
+        edit.commit("xy");
+        assert_eq!(edit.text(), "axyb");
+        assert_eq!(edit.caret(), 3);
+        assert_eq!(edit.preedit(), None);
+    }
+
+    #[test]
+    fn preedit_replaces_the_selection() {
+        let mut edit = TextEdit::new("abc");
+        edit.select_all();
+        edit.set_preedit("z".into(), None);
+        assert_eq!(edit.text(), "");
+        assert_eq!(edit.display_text(), ("z".into(), Some(0..1)));
+
+        edit.set_preedit(String::new(), None);
+        assert_eq!(edit.preedit(), None);
+        assert_eq!(edit.display_text(), ("".into(), None));
+    }
+}
@@ -0,0 +1,457 @@
+use std::ops::Range;
+
+use cosmic_text as text;
+use winit::{
+    event::{ElementState, Ime, KeyEvent, WindowEvent},
+    keyboard::{Key, ModifiersState, NamedKey},
+};
+
+use massive_geometry::{Bounds, Color, Rect};
+use massive_scene::{Director, Handle, Position, PositionedShape};
+use massive_shapes::{Caret, Quad, TextWeight};
+
+use super::{
+    caret_shape::CaretShape, line_layout::rect, selection_region, Clipboard, LineLayout, TextEdit,
+};
+
+/// An editable single line of text.
+///
+/// The text field combines the [`TextEdit`] model with shape generation and event handling. Its
+/// shapes are placed at `position`, with the top left corner of the text at the origin. All
+/// coordinates the field takes and returns are in pixels relative to that origin.
+///
+/// After handling events, call [`Self::update`] to regenerate the shapes, and send the changes
+/// with [`Director::action`].
+#[derive(Debug)]
+pub struct TextField {
+    edit: TextEdit,
+    style: TextFieldStyle,
+    position: Handle<Position>,
+    focused: bool,
+    /// The blink phase of the caret.
+    caret_visible: bool,
+    clipboard: Clipboard,
+    modifiers: ModifiersState,
+    dragging: bool,
+    layout: Layout,
+    /// The shapes need to be regenerated.
+    dirty: bool,
+    shapes: Option<Shapes>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextFieldStyle {
+    pub font_size: f32,
+    pub text_color: Color,
+    pub text_weight: TextWeight,
+    pub selection_color: Color,
+    /// The corner radius of the selection, zero for square corners.
+    pub selection_radius: f64,
+    pub caret_color: Color,
+    pub caret_width: f64,
+    /// The time in seconds the caret takes to move to a new position.
+    pub caret_motion: f32,
+    pub caret_trail: bool,
+}
+
+impl Default for TextFieldStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 32.0,
+            text_color: Color::BLACK,
+            text_weight: TextWeight::NORMAL,
+            selection_color: Color::rgb(0.7, 0.8, 1.0),
+            selection_radius: 0.0,
+            caret_color: Color::BLACK,
+            caret_width: 2.0,
+            caret_motion: 0.1,
+            caret_trail: false,
+        }
+    }
+}
+
+/// How an event was handled by the text field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFieldResponse {
+    /// The event was not meant for the text field.
+    Ignored,
+    /// The caret, the selection, or the preedit text changed.
+    Handled,
+    /// The text changed.
+    Changed,
+    /// Enter was pressed.
+    Submitted,
+}
+
+#[derive(Debug)]
+struct Shapes {
+    text: Handle<PositionedShape>,
+    /// Selection and preedit underline.
+    decorations: Handle<PositionedShape>,
+    caret: CaretShape,
+}
+
+/// The layout of the displayed text, used for hit testing and placing the decorations.
+#[derive(Debug, Default)]
+struct Layout {
+    line: LineLayout,
+    preedit: Option<Range<usize>>,
+}
+
+impl TextField {
+    pub fn new(position: Handle<Position>, text: impl Into<String>, style: TextFieldStyle) -> Self {
+        Self {
+            edit: TextEdit::new(text),
+            style,
+            position,
+            focused: false,
+            caret_visible: true,
+            clipboard: Clipboard::new(),
+            modifiers: ModifiersState::default(),
+            dragging: false,
+            layout: Layout::default(),
+            dirty: true,
+            shapes: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.edit.text()
+    }
+
+    pub fn edit(&self) -> &TextEdit {
+        &self.edit
+    }
+
+    /// Modify the editing state directly.
+    pub fn edit_mut(&mut self) -> &mut TextEdit {
+        self.dirty = true;
+        &mut self.edit
+    }
+
+    pub fn style(&self) -> &TextFieldStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: TextFieldStyle) {
+        self.style = style;
+        self.dirty = true;
+    }
+
+    /// Shape all text again with the next update, for example after fonts were reloaded.
+    pub fn relayout(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Focus the text field. Only focused text fields show the caret and handle keyboard input.
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused != self.focused {
+            self.focused = focused;
+            self.dragging = false;
+            self.dirty = true;
+        }
+    }
+
+    /// Show or hide the caret while it blinks, pass [`crate::WindowRenderer::caret_visible`]
+    /// before each update.
+    pub fn set_caret_visible(&mut self, visible: bool) {
+        if visible != self.caret_visible {
+            self.caret_visible = visible;
+            self.dirty = true;
+        }
+    }
+
+    /// The size of the text field in pixels, as of the last [`Self::update`].
+    pub fn size(&self) -> (f64, f64) {
+        (
+            self.layout.line.width() as f64,
+            self.layout.line.height() as f64,
+        )
+    }
+
+    /// Handle keyboard and IME events.
+    ///
+    /// Pointer events need to be transformed to the text field's coordinates first and are
+    /// handled by [`Self::pointer_pressed`] and its siblings.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> TextFieldResponse {
+        if let WindowEvent::ModifiersChanged(modifiers) = event {
+            self.modifiers = modifiers.state();
+            return TextFieldResponse::Ignored;
+        }
+
+        if !self.focused {
+            return TextFieldResponse::Ignored;
+        }
+
+        let before = self.edit.clone();
+        let response = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    event @ KeyEvent {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.handle_key(event),
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+                self.edit.set_preedit(text.clone(), *cursor);
+                TextFieldResponse::Handled
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => {
+                self.edit.commit(text);
+                TextFieldResponse::Handled
+            }
+            WindowEvent::Ime(Ime::Disabled) => {
+                self.edit.set_preedit(String::new(), None);
+                TextFieldResponse::Handled
+            }
+            _ => TextFieldResponse::Ignored,
+        };
+
+        if self.edit == before {
+            return response;
+        }
+        self.dirty = true;
+        if self.edit.text() != before.text() {
+            TextFieldResponse::Changed
+        } else {
+            TextFieldResponse::Handled
+        }
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent) -> TextFieldResponse {
+        // The modifier for shortcuts and for moving by words.
+        let (primary, word) = if cfg!(target_os = "macos") {
+            (self.modifiers.super_key(), self.modifiers.alt_key())
+        } else {
+            (self.modifiers.control_key(), self.modifiers.control_key())
+        };
+        let shift = self.modifiers.shift_key();
+
+        // While composing, the input method owns the keyboard.
+        if self.edit.preedit().is_some() {
+            return TextFieldResponse::Ignored;
+        }
+
+        match &event.logical_key {
+            Key::Named(NamedKey::ArrowLeft) => self.edit.move_left(word, shift),
+            Key::Named(NamedKey::ArrowRight) => self.edit.move_right(word, shift),
+            Key::Named(NamedKey::Home) => self.edit.move_home(shift),
+            Key::Named(NamedKey::End) => self.edit.move_end(shift),
+            Key::Named(NamedKey::Backspace) => self.edit.delete_backward(word),
+            Key::Named(NamedKey::Delete) => self.edit.delete_forward(word),
+            Key::Named(NamedKey::Enter) => return TextFieldResponse::Submitted,
+            Key::Character(c) if primary => match c.to_lowercase().as_str() {
+                "a" => self.edit.select_all(),
+                "c" => self.copy(),
+                "x" => {
+                    self.copy();
+                    self.edit.delete_selection();
+                }
+                "v" => {
+                    let text = self.clipboard.text();
+                    self.edit.insert(&text);
+                }
+                _ => return TextFieldResponse::Ignored,
+            },
+            _ => match &event.text {
+                Some(text) if !primary && !text.is_empty() => self.edit.insert(text),
+                _ => return TextFieldResponse::Ignored,
+            },
+        }
+
+        TextFieldResponse::Handled
+    }
+
+    fn copy(&mut self) {
+        if let Some(text) = self.edit.selected_text() {
+            self.clipboard.set_text(text);
+        }
+    }
+
+    /// Place the caret at `x`, or extend the selection to it, and start dragging.
+    pub fn pointer_pressed(&mut self, x: f64, extend: bool) {
+        let offset = self.hit_test(x);
+        self.edit.move_to(offset, extend);
+        self.dragging = true;
+        self.dirty = true;
+    }
+
+    /// Extend the selection to `x` while dragging.
+    pub fn pointer_moved(&mut self, x: f64) {
+        if self.dragging {
+            let offset = self.hit_test(x);
+            self.edit.move_to(offset, true);
+            self.dirty = true;
+        }
+    }
+
+    pub fn pointer_released(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Select the word at `x`, for example on a double click.
+    pub fn select_word_at(&mut self, x: f64) {
+        let offset = self.hit_test(x);
+        self.edit.select_word(offset);
+        self.dirty = true;
+    }
+
+    /// The text offset nearest to `x`.
+    pub fn hit_test(&self, x: f64) -> usize {
+        let offset = self.layout.line.hit_test(x as f32);
+        self.edit_offset(offset)
+    }
+
+    /// The x position of the caret.
+    pub fn caret_x(&self) -> f64 {
+        self.x_at(self.caret_display_offset()) as f64
+    }
+
+    /// The area the input method should avoid, as position and size.
+    ///
+    /// Transform it to window coordinates and pass it to
+    /// [`crate::ShellWindow::set_ime_cursor_area`].
+    pub fn ime_cursor_area(&self) -> ((f64, f64), (f64, f64)) {
+        (
+            (self.caret_x(), 0.0),
+            (self.style.caret_width, self.layout.line.height() as f64),
+        )
+    }
+
+    /// Regenerate the shapes if anything changed since the last update.
+    pub fn update(&mut self, director: &mut Director, font_system: &mut text::FontSystem) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        let (display_text, preedit) = self.edit.display_text();
+        let style = &self.style;
+        let (line, run) = LineLayout::shape(
+            font_system,
+            &display_text,
+            style.font_size,
+            style.text_weight,
+            style.text_color,
+            (0.0, 0.0, 0.0),
+            // Empty lines have no metrics, but the caret needs a height.
+            style.font_size,
+        );
+        self.layout = Layout { line, preedit };
+        let decorations = self.decorations();
+        let caret = self.caret();
+
+        let text = PositionedShape::new(self.position.clone(), run);
+        let decorations = PositionedShape::new(self.position.clone(), decorations);
+        let shapes = match &mut self.shapes {
+            Some(shapes) => {
+                shapes.text.update(text);
+                shapes.decorations.update(decorations);
+                shapes
+            }
+            None => self.shapes.insert(Shapes {
+                text: director.cast(text),
+                decorations: director.cast(decorations),
+                caret: CaretShape::default(),
+            }),
+        };
+        shapes.caret.update(director, &self.position, caret);
+    }
+
+    fn decorations(&self) -> Vec<Quad> {
+        let height = self.layout.line.height() as f64;
+        let mut quads = Vec::new();
+
+        if let Some(selection) = self.edit.selection() {
+            let left = self.x_at(self.display_offset(selection.start)) as f64;
+            let right = self.x_at(self.display_offset(selection.end)) as f64;
+            let row = Rect {
+                left: left.min(right),
+                top: 0.0,
+                right: left.max(right),
+                bottom: height,
+            };
+            quads.extend(selection_region(
+                &[row],
+                self.style.selection_radius,
+                self.style.selection_color,
+            ));
+        }
+
+        if let Some(preedit) = &self.layout.preedit {
+            let left = self.x_at(preedit.start) as f64;
+            let right = self.x_at(preedit.end) as f64;
+            let thickness = (self.style.font_size as f64 / 16.0).max(1.0);
+            quads.push(rect(
+                left,
+                height - thickness,
+                right,
+                height,
+                self.style.text_color,
+            ));
+        }
+
+        quads
+    }
+
+    /// The caret, `None` if the field is not focused or the caret blinked off.
+    fn caret(&self) -> Option<Caret> {
+        if !self.focused || !self.caret_visible {
+            return None;
+        }
+        let style = &self.style;
+        let x = self.caret_x();
+        let bounds = Bounds::new(
+            (x, 0.0),
+            (x + style.caret_width, self.layout.line.height() as f64),
+        );
+        let caret = Caret::new(bounds, style.caret_color).with_motion(style.caret_motion);
+        Some(if style.caret_trail {
+            caret.with_trail()
+        } else {
+            caret
+        })
+    }
+
+    /// The x position of a byte offset into the displayed text.
+    fn x_at(&self, offset: usize) -> f32 {
+        self.layout.line.x_at(offset)
+    }
+
+    /// The offset of the caret in the displayed text.
+    fn caret_display_offset(&self) -> usize {
+        let caret = self.edit.caret();
+        match self.edit.preedit() {
+            Some(preedit) => {
+                caret
+                    + preedit
+                        .cursor
+                        .map_or(preedit.text.len(), |(start, _)| start)
+            }
+            None => caret,
+        }
+    }
+
+    /// Convert an offset in the edited text to an offset in the displayed text.
+    fn display_offset(&self, offset: usize) -> usize {
+        match self.edit.preedit() {
+            Some(preedit) if offset > self.edit.caret() => offset + preedit.text.len(),
+            _ => offset,
+        }
+    }
+
+    /// Convert an offset in the displayed text to an offset in the edited text.
+    fn edit_offset(&self, offset: usize) -> usize {
+        let caret = self.edit.caret();
+        match self.edit.preedit() {
+            Some(preedit) if offset >= caret + preedit.text.len() => offset - preedit.text.len(),
+            Some(_) if offset > caret => caret,
+            _ => offset,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use cosmic_text as text;
+
+use massive_geometry::{Bounds, Color, Matrix4, Rect, Size, Vector3};
+use massive_scene::{Director, Handle, Position, PositionedShape, Shape};
+use massive_shapes::{Backdrop, RoundedRect, Shadow, TextWeight};
+
+use super::LineLayout;
+
+/// The side of the anchor a tooltip is placed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TooltipDirection {
+    Above,
+    Below,
+    Left,
+    Right,
+}
+
+impl TooltipDirection {
+    pub fn opposite(self) -> Self {
+        match self {
+            TooltipDirection::Above => TooltipDirection::Below,
+            TooltipDirection::Below => TooltipDirection::Above,
+            TooltipDirection::Left => TooltipDirection::Right,
+            TooltipDirection::Right => TooltipDirection::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TooltipStyle {
+    pub font_size: f32,
+    pub text_color: Color,
+    pub text_weight: TextWeight,
+    pub background: Color,
+    /// The blur radius of the content behind the background, zero does not blur it. The
+    /// background color is mixed into the blurred content by its alpha, see [`Backdrop`].
+    pub backdrop_blur: f64,
+    /// The space between the text and the edges of the background.
+    pub padding: f64,
+    /// The corner radius of the background.
+    pub radius: f64,
+    /// The elevation of the background's shadow, see [`Shadow::elevation`]. Zero has no shadow.
+    ///
+    /// Backdrops are drawn before the shadows of the overlay, so tooltips with a blurred backdrop
+    /// have no shadow.
+    pub elevation: f64,
+    /// The distance to the anchor.
+    pub gap: f64,
+    /// The minimum distance to the edges of the screen.
+    pub margin: f64,
+}
+
+impl Default for TooltipStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            text_color: Color::rgb(1.0, 1.0, 1.0),
+            text_weight: TextWeight::NORMAL,
+            background: Color::new(0.15, 0.15, 0.15, 0.9),
+            backdrop_blur: 0.0,
+            padding: 6.0,
+            radius: 4.0,
+            elevation: 4.0,
+            gap: 6.0,
+            margin: 4.0,
+        }
+    }
+}
+
+/// A line of text on a rounded background next to an anchor.
+///
+/// The tooltip is rendered in the overlay, so all coordinates are pixels of the view. It's placed
+/// at the preferred side of the anchor and flips to the opposite side if it does not fit on the
+/// screen there. Dropping the tooltip removes it.
+#[derive(Debug)]
+pub struct Tooltip {
+    style: TooltipStyle,
+    direction: TooltipDirection,
+    size: Size,
+    matrix: Handle<Matrix4>,
+    bounds: Rect,
+    placed: TooltipDirection,
+    _shadow: Option<Handle<PositionedShape>>,
+    _background: Handle<PositionedShape>,
+    _text: Handle<PositionedShape>,
+}
+
+impl Tooltip {
+    /// Show `text` at the preferred `direction` of `anchor` on a screen of `screen` size.
+    pub fn new(
+        director: &mut Director,
+        font_system: &mut text::FontSystem,
+        text: &str,
+        anchor: &Rect,
+        direction: TooltipDirection,
+        screen: Size,
+        style: TooltipStyle,
+    ) -> Self {
+        let padding = style.padding;
+        let (layout, run) = LineLayout::shape(
+            font_system,
+            text,
+            style.font_size,
+            style.text_weight,
+            style.text_color,
+            (padding, padding, 0.0),
+            style.font_size,
+        );
+        let size = Size::new(
+            layout.width() as f64 + 2.0 * padding,
+            layout.height() as f64 + 2.0 * padding,
+        );
+
+        let (bounds, placed) = place_tooltip(size, anchor, direction, screen, &style);
+        let matrix = director.cast(translation(&bounds));
+        let position = director.cast(Position {
+            parent: None,
+            matrix: matrix.clone(),
+            pin: None,
+            overlay: true,
+        });
+
+        let shadow = (style.elevation > 0.0 && style.backdrop_blur <= 0.0).then(|| {
+            let bounds = Bounds::new((0.0, 0.0), (size.width, size.height));
+            let shadow = Shadow::elevation(bounds, style.radius, style.elevation);
+            director.cast(PositionedShape::new(position.clone(), shadow))
+        });
+        let background: Shape = if style.backdrop_blur > 0.0 {
+            let bounds = Bounds::new((0.0, 0.0), (size.width, size.height));
+            Backdrop::new(bounds, style.backdrop_blur, style.background)
+                .with_radius(style.radius)
+                .into()
+        } else {
+            let bounds = Bounds::new((0.0, 0.0), (size.width, size.height));
+            RoundedRect::new(bounds, style.background)
+                .with_radius(style.radius)
+                .into()
+        };
+        let background = director.cast(PositionedShape::new(position.clone(), background));
+        let text = director.cast(PositionedShape::new(position, run));
+
+        Self {
+            style,
+            direction,
+            size,
+            matrix,
+            bounds,
+            placed,
+            _shadow: shadow,
+            _background: background,
+            _text: text,
+        }
+    }
+
+    /// Place the tooltip again, for example when the anchor moved or the window was resized.
+    pub fn set_anchor(&mut self, anchor: &Rect, screen: Size) {
+        let (bounds, placed) =
+            place_tooltip(self.size, anchor, self.direction, screen, &self.style);
+        if bounds != self.bounds {
+            self.matrix.update(translation(&bounds));
+            self.bounds = bounds;
+        }
+        self.placed = placed;
+    }
+
+    /// The bounds of the tooltip in pixels.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// The side of the anchor the tooltip was placed at.
+    pub fn placed(&self) -> TooltipDirection {
+        self.placed
+    }
+}
+
+/// Place a tooltip of `size` next to `anchor`.
+///
+/// Prefers `direction`, flips to the opposite side if the tooltip does not fit there, and keeps
+/// to the side with more space if it fits on neither. Along the side, the tooltip is centered on
+/// the anchor and shifted to stay on the screen.
+///
+/// Returns the bounds and the side the tooltip was placed at.
+pub fn place_tooltip(
+    size: Size,
+    anchor: &Rect,
+    direction: TooltipDirection,
+    screen: Size,
+    style: &TooltipStyle,
+) -> (Rect, TooltipDirection) {
+    let (gap, margin) = (style.gap, style.margin);
+    // The space available at a side.
+    let space = |direction| match direction {
+        TooltipDirection::Above => anchor.top - gap - margin,
+        TooltipDirection::Below => screen.height - margin - anchor.bottom - gap,
+        TooltipDirection::Left => anchor.left - gap - margin,
+        TooltipDirection::Right => screen.width - margin - anchor.right - gap,
+    };
+    let fits = |direction| match direction {
+        TooltipDirection::Above | TooltipDirection::Below => space(direction) >= size.height,
+        TooltipDirection::Left | TooltipDirection::Right => space(direction) >= size.width,
+    };
+
+    let opposite = direction.opposite();
+    let direction = if fits(direction) || (!fits(opposite) && space(direction) >= space(opposite)) {
+        direction
+    } else {
+        opposite
+    };
+
+    let center_x = (anchor.left + anchor.right - size.width) / 2.0;
+    let center_y = (anchor.top + anchor.bottom - size.height) / 2.0;
+    let (left, top) = match direction {
+        TooltipDirection::Above => (center_x, anchor.top - gap - size.height),
+        TooltipDirection::Below => (center_x, anchor.bottom + gap),
+        TooltipDirection::Left => (anchor.left - gap - size.width, center_y),
+        TooltipDirection::Right => (anchor.right + gap, center_y),
+    };
+    // Stay on the screen along the side, but prefer the top left if it's too small.
+    let left = left.min(screen.width - margin - size.width).max(margin);
+    let top = top.min(screen.height - margin - size.height).max(margin);
+
+    let bounds = Rect {
+        left,
+        top,
+        right: left + size.width,
+        bottom: top + size.height,
+    };
+    (bounds, direction)
+}
+
+fn translation(bounds: &Rect) -> Matrix4 {
+    Matrix4::from_translation(Vector3::new(bounds.left, bounds.top, 0.0))
+}
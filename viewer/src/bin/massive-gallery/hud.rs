@@ -0,0 +1,148 @@
+//! The stats HUD of the gallery.
+
+use std::time::{Duration, Instant};
+
+use cosmic_text::FontSystem;
+
+use massive_geometry::{Color, Matrix4, Rect, Vector3};
+use massive_renderer::PreparationStats;
+use massive_scene::{Director, Handle, Matrix, Position, PositionedShape};
+use massive_shapes::TextWeight;
+use massive_shell::widgets::{selection_region, LineLayout};
+
+use crate::scenes::SceneKind;
+
+const FONT_SIZE: f32 = 14.0;
+const LINE_HEIGHT: f64 = 18.0;
+const PADDING: f64 = 8.0;
+const BACKGROUND: Color = Color::new(1.0, 1.0, 1.0, 0.85);
+
+/// How often the text of the HUD changes.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Shows the frame rate and what the renderer did in the top left corner of the window.
+pub struct Hud {
+    _matrix: Handle<Matrix>,
+    position: Handle<Position>,
+    _shapes: Vec<Handle<PositionedShape>>,
+    /// The frames rendered since the text was updated.
+    frames: u32,
+    measuring_since: Option<Instant>,
+    updated: Option<Instant>,
+    fps: f64,
+}
+
+impl Hud {
+    pub fn new(director: &mut Director) -> Self {
+        let matrix = director.cast(Matrix4::from_translation(Vector3::new(10.0, 10.0, 0.0)));
+        let position = director.cast(Position {
+            parent: None,
+            matrix: matrix.clone(),
+            pin: None,
+            overlay: true,
+        });
+        Self {
+            _matrix: matrix,
+            position,
+            _shapes: Vec::new(),
+            frames: 0,
+            measuring_since: None,
+            updated: None,
+            fps: 0.0,
+        }
+    }
+
+    /// Forget the measurements, for example after the scene changed.
+    pub fn reset(&mut self) {
+        self.frames = 0;
+        self.measuring_since = None;
+        self.updated = None;
+        self.fps = 0.0;
+    }
+
+    pub fn frame_rendered(&mut self, now: Instant) {
+        match self.measuring_since {
+            Some(_) => self.frames += 1,
+            None => self.measuring_since = Some(now),
+        }
+    }
+
+    /// Update the text if it's older than the update interval.
+    pub fn update(
+        &mut self,
+        director: &mut Director,
+        font_system: &mut FontSystem,
+        kind: SceneKind,
+        description: &str,
+        stats: PreparationStats,
+        now: Instant,
+    ) {
+        if self
+            .updated
+            .is_some_and(|updated| now - updated < UPDATE_INTERVAL)
+        {
+            return;
+        }
+        self.updated = Some(now);
+
+        if let Some(since) = self.measuring_since {
+            let elapsed = (now - since).as_secs_f64();
+            if self.frames > 0 && elapsed > 0.0 {
+                self.fps = self.frames as f64 / elapsed;
+            }
+            self.frames = 0;
+            self.measuring_since = Some(now);
+        }
+        let frame_time = if self.fps > 0.0 {
+            format!("{:.2} ms", 1000.0 / self.fps)
+        } else {
+            "-".into()
+        };
+
+        let lines = [
+            format!("Scene {} ({description})", kind.name()),
+            format!("{:.1} fps, {frame_time} per frame", self.fps),
+            format!(
+                "Preparations {}, matrix updates {}, skipped {}, upload submissions {}",
+                stats.preparations, stats.matrix_updates, stats.skipped, stats.upload_submissions
+            ),
+            "Keys 1-5 switch the scene".into(),
+        ];
+
+        let mut width: f64 = 0.0;
+        let mut runs = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            let (layout, run) = LineLayout::shape(
+                font_system,
+                line,
+                FONT_SIZE,
+                TextWeight::NORMAL,
+                Color::BLACK,
+                (PADDING, PADDING + i as f64 * LINE_HEIGHT, 0.0),
+                FONT_SIZE,
+            );
+            width = width.max(layout.width() as f64);
+            runs.push(run);
+        }
+
+        let background = selection_region(
+            &[Rect {
+                left: 0.0,
+                top: 0.0,
+                right: width + 2.0 * PADDING,
+                bottom: lines.len() as f64 * LINE_HEIGHT + 2.0 * PADDING,
+            }],
+            4.0,
+            BACKGROUND,
+        );
+
+        // Replacing the handles removes the previous shapes.
+        let mut shapes =
+            vec![director.cast(PositionedShape::new(self.position.clone(), background))];
+        shapes.extend(
+            runs.into_iter()
+                .map(|run| director.cast(PositionedShape::new(self.position.clone(), run))),
+        );
+        self._shapes = shapes;
+    }
+}
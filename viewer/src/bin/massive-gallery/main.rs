@@ -0,0 +1,125 @@
+//! A gallery of demo scenes that stress different parts of the renderer, with a HUD that shows
+//! the frame rate. Run it to validate hardware and to compare performance when reporting issues.
+//!
+//! The window is redrawn continuously, so the frame rate shows how fast the renderer can render
+//! the scene, capped by the present mode.
+//!
+//! Usage: `cargo run --release --features gallery --bin massive-gallery`
+//!
+//! The digit keys switch the scenes, see [`scenes::SceneKind`].
+
+#[cfg(not(target_arch = "wasm32"))]
+mod hud;
+#[cfg(not(target_arch = "wasm32"))]
+mod scenes;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    massive_shell::shell::run(native::application).await
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    panic!("massive-gallery is not supported in the browser");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+
+    use anyhow::Result;
+    use cosmic_text::FontSystem;
+    use log::info;
+    use winit::{
+        dpi::LogicalSize,
+        event::{ElementState, KeyEvent, WindowEvent},
+        keyboard::Key,
+    };
+
+    use massive_geometry::{Camera, UnitSystem};
+    use massive_shell::ApplicationContext;
+
+    use crate::{
+        hud::Hud,
+        scenes::{Scene, SceneKind},
+    };
+
+    pub async fn application(mut ctx: ApplicationContext) -> Result<()> {
+        let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+        let fovy: f64 = 45.0;
+        let camera_distance = UnitSystem::camera_distance(fovy);
+        let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+        let window = ctx.new_window(LogicalSize::new(1280, 800), None)?;
+        let (mut renderer, mut director) = window
+            .new_renderer(font_system.clone(), camera, window.inner_size())
+            .await?;
+        renderer.set_skip_idle_frames(false);
+
+        let mut hud = Hud::new(&mut director);
+        let mut kind = SceneKind::HugeParagraph;
+        let mut scene = Scene::new(
+            kind,
+            &mut director,
+            &mut font_system.lock().unwrap(),
+            window.inner_size(),
+        );
+        director.action()?;
+        scene.place_camera(&mut renderer, camera)?;
+
+        loop {
+            let event = ctx.wait_for_event(&mut renderer).await?;
+
+            match &event {
+                WindowEvent::CloseRequested => return Ok(()),
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: Key::Character(c),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } if SceneKind::from_key(c).is_some() => {
+                    kind = SceneKind::from_key(c).unwrap();
+                    info!("Switching to {}", kind.name());
+                    // Drop the shapes of the previous scene before creating the next one.
+                    drop(scene);
+                    let started = Instant::now();
+                    scene = Scene::new(
+                        kind,
+                        &mut director,
+                        &mut font_system.lock().unwrap(),
+                        window.inner_size(),
+                    );
+                    info!("Created {} in {:?}", kind.name(), started.elapsed());
+                    director.action()?;
+                    scene.place_camera(&mut renderer, camera)?;
+                    hud.reset();
+                }
+                WindowEvent::RedrawRequested => hud.frame_rendered(Instant::now()),
+                _ => scene.handle_event(&event, window.inner_size()),
+            }
+
+            let now = Instant::now();
+            scene.update(&mut director, &mut font_system.lock().unwrap(), now);
+            hud.update(
+                &mut director,
+                &mut font_system.lock().unwrap(),
+                kind,
+                &scene.description(),
+                renderer.preparation_stats(),
+                now,
+            );
+            director.action()?;
+            window.request_redraw();
+        }
+    }
+}
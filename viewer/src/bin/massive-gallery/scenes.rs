@@ -0,0 +1,288 @@
+//! The demo scenes of the gallery.
+
+use std::{f64::consts::TAU, time::Instant};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use shared::origin_matrix;
+use winit::{dpi::PhysicalSize, event::WindowEvent};
+
+use massive_geometry::{Camera, Color, Identity, Matrix4, Vector3};
+use massive_scene::{Director, Handle, Matrix, Position, PositionedShape};
+use massive_shapes::TextWeight;
+use massive_shell::{
+    widgets::{DocumentView, LineLayout, Paragraph, ScrollbarStyle, SpanStyle},
+    WindowRenderer,
+};
+
+/// The distance of the document from the window's edges, in physical pixels.
+const MARGIN: f64 = 40.0;
+
+const SENTENCE: &str = "The quick brown fox jumps over the lazy dog while the renderer keeps \
+    every glyph of this paragraph crisp at any zoom level. ";
+const HUGE_PARAGRAPH_SENTENCES: usize = 5_000;
+
+const LABELS: usize = 100_000;
+const LABEL_COLUMNS: usize = 250;
+
+const EMOJI: &[&str] = &[
+    "😀 😃 😄 😁 😆 😅 🤣 😂 🙂 🙃 😉 😊 😇 🥰 😍 🤩",
+    "👍 👎 👏 🙌 👐 🤲 🤝 🙏 ✍️ 💅 🤳 💪 🦾 🦵 🦿 🦶",
+    "🐶 🐱 🐭 🐹 🐰 🦊 🐻 🐼 🐨 🐯 🦁 🐮 🐷 🐸 🐵 🐔",
+    "🍏 🍎 🍐 🍊 🍋 🍌 🍉 🍇 🍓 🫐 🍈 🍒 🍑 🥭 🍍 🥥",
+    "👨‍👩‍👧‍👦 👩‍💻 🧑‍🚀 🏳️‍🌈 🇩🇪 🇯🇵 🇺🇸 🇧🇷 ❤️‍🔥 🧑🏽‍🍳 👋🏿 👋🏻",
+];
+const EMOJI_REPEAT: usize = 20;
+
+const RTL: &[&str] = &[
+    "שלום עולם, זהו טקסט בעברית",
+    "مرحبا بالعالم، هذا نص باللغة العربية",
+    "Mixed: English, עברית 123, and العربية ٤٥٦ in one line",
+    "سلام دنیا — فارسی",
+];
+const RTL_REPEAT: usize = 100;
+
+const ANIMATED_LABELS: usize = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneKind {
+    /// A single paragraph with thousands of wrapped lines in a `DocumentView`.
+    HugeParagraph,
+    /// 100k static labels.
+    Labels,
+    /// Color glyphs, including sequences and flags.
+    Emoji,
+    /// Right to left and mixed direction text.
+    Rtl,
+    /// Labels with matrices that change every frame.
+    AnimationStress,
+}
+
+impl SceneKind {
+    /// The scene selected with the digit `key`.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Some(match key {
+            "1" => Self::HugeParagraph,
+            "2" => Self::Labels,
+            "3" => Self::Emoji,
+            "4" => Self::Rtl,
+            "5" => Self::AnimationStress,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::HugeParagraph => "1 Huge paragraph",
+            Self::Labels => "2 100k labels",
+            Self::Emoji => "3 Emoji",
+            Self::Rtl => "4 RTL",
+            Self::AnimationStress => "5 Animation stress",
+        }
+    }
+}
+
+pub struct Scene {
+    matrix: Handle<Matrix>,
+    position: Handle<Position>,
+    shapes: Vec<Handle<PositionedShape>>,
+    animated: Vec<AnimatedLabel>,
+    document: Option<DocumentView>,
+    started: Instant,
+}
+
+/// A label with its own matrix, which is updated every frame.
+struct AnimatedLabel {
+    matrix: Handle<Matrix>,
+    _position: Handle<Position>,
+    _shape: Handle<PositionedShape>,
+    center: (f64, f64),
+    phase: f64,
+}
+
+impl Scene {
+    pub fn new(
+        kind: SceneKind,
+        director: &mut Director,
+        font_system: &mut FontSystem,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let matrix = director.cast(Matrix4::identity());
+        let position = director.cast(Position::from(matrix.clone()));
+        let mut scene = Self {
+            matrix,
+            position,
+            shapes: Vec::new(),
+            animated: Vec::new(),
+            document: None,
+            started: Instant::now(),
+        };
+
+        match kind {
+            SceneKind::HugeParagraph => {
+                scene.matrix.update(origin_matrix(size, MARGIN));
+                let paragraph = Paragraph::default().span(
+                    SENTENCE.repeat(HUGE_PARAGRAPH_SENTENCES),
+                    SpanStyle::default(),
+                );
+                let (width, height) = view_size(size);
+                let mut view =
+                    DocumentView::new(scene.position.clone(), vec![paragraph], width, height);
+                view.set_scrollbar(Some(ScrollbarStyle::default()));
+                scene.document = Some(view);
+            }
+            SceneKind::Labels => {
+                for i in 0..LABELS {
+                    let (column, row) = (i % LABEL_COLUMNS, i / LABEL_COLUMNS);
+                    let translation = (column as f64 * 110.0, row as f64 * 24.0, 0.0);
+                    scene.add_line(
+                        director,
+                        font_system,
+                        &format!("Label {i:05}"),
+                        14.0,
+                        translation,
+                    );
+                }
+            }
+            SceneKind::Emoji => {
+                for (i, line) in EMOJI
+                    .iter()
+                    .cycle()
+                    .take(EMOJI.len() * EMOJI_REPEAT)
+                    .enumerate()
+                {
+                    scene.add_line(
+                        director,
+                        font_system,
+                        line,
+                        48.0,
+                        (0.0, i as f64 * 64.0, 0.0),
+                    );
+                }
+            }
+            SceneKind::Rtl => {
+                for (i, line) in RTL.iter().cycle().take(RTL.len() * RTL_REPEAT).enumerate() {
+                    scene.add_line(
+                        director,
+                        font_system,
+                        line,
+                        28.0,
+                        (0.0, i as f64 * 40.0, 0.0),
+                    );
+                }
+            }
+            SceneKind::AnimationStress => {
+                let columns = (ANIMATED_LABELS as f64).sqrt().ceil() as usize;
+                for i in 0..ANIMATED_LABELS {
+                    let center = ((i % columns) as f64 * 80.0, (i / columns) as f64 * 80.0);
+                    let matrix = director.cast(Matrix4::identity());
+                    let position = director.cast(Position {
+                        parent: Some(scene.position.clone()),
+                        matrix: matrix.clone(),
+                        pin: None,
+                        overlay: false,
+                    });
+                    let (_, run) = LineLayout::shape(
+                        font_system,
+                        &format!("{i}"),
+                        16.0,
+                        TextWeight::NORMAL,
+                        Color::BLACK,
+                        (0.0, 0.0, 0.0),
+                        16.0,
+                    );
+                    let shape = director.cast(PositionedShape::new(position.clone(), run));
+                    scene.animated.push(AnimatedLabel {
+                        matrix,
+                        _position: position,
+                        _shape: shape,
+                        center,
+                        phase: i as f64 * 0.1,
+                    });
+                }
+            }
+        }
+
+        scene
+    }
+
+    /// A short description of what the scene contains.
+    pub fn description(&self) -> String {
+        if self.document.is_some() {
+            format!("1 paragraph, {HUGE_PARAGRAPH_SENTENCES} sentences")
+        } else if !self.animated.is_empty() {
+            format!("{} animated labels", self.animated.len())
+        } else {
+            format!("{} glyph runs", self.shapes.len())
+        }
+    }
+
+    /// Show the document with physical pixels 1:1, and fit all other scenes into the window.
+    pub fn place_camera(&self, renderer: &mut WindowRenderer, camera: Camera) -> Result<()> {
+        renderer.update_camera(camera);
+        if self.document.is_none() {
+            renderer.apply_pending_changes()?;
+            renderer.fit_scene(0.0, None);
+        }
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent, size: PhysicalSize<u32>) {
+        let Some(view) = &mut self.document else {
+            return;
+        };
+        if let WindowEvent::Resized(_) = event {
+            self.matrix.update(origin_matrix(size, MARGIN));
+            let (width, height) = view_size(size);
+            view.set_width(width);
+            view.set_viewport_height(height);
+        } else {
+            view.handle_event(event);
+        }
+    }
+
+    pub fn update(&mut self, director: &mut Director, font_system: &mut FontSystem, now: Instant) {
+        if let Some(view) = &mut self.document {
+            view.update(director, font_system, now);
+        }
+
+        let time = (now - self.started).as_secs_f64();
+        for label in &self.animated {
+            let angle = (time * 0.5 + label.phase) % 1.0 * TAU;
+            let (x, y) = label.center;
+            label.matrix.update(
+                Matrix4::from_translation(Vector3::new(x, y, 0.0))
+                    * Matrix4::from_angle_z(cgmath::Rad(angle))
+                    * Matrix4::from_translation(Vector3::new(-10.0, -8.0, 0.0)),
+            );
+        }
+    }
+
+    fn add_line(
+        &mut self,
+        director: &mut Director,
+        font_system: &mut FontSystem,
+        text: &str,
+        font_size: f32,
+        translation: (f64, f64, f64),
+    ) {
+        let (_, run) = LineLayout::shape(
+            font_system,
+            text,
+            font_size,
+            TextWeight::NORMAL,
+            Color::BLACK,
+            translation,
+            font_size,
+        );
+        let shape = director.cast(PositionedShape::new(self.position.clone(), run));
+        self.shapes.push(shape);
+    }
+}
+
+fn view_size(size: PhysicalSize<u32>) -> (f64, f64) {
+    (
+        (size.width as f64 - MARGIN * 2.0).max(1.0),
+        (size.height as f64 - MARGIN * 2.0).max(0.0),
+    )
+}
@@ -0,0 +1,187 @@
+//! Opens a window that shows a file, as a demo and a smoke test of the renderer.
+//!
+//! Usage: `massive-view <path>`
+//!
+//! Markdown files (`.md`, `.markdown`) and other text files are shown in a scrollable
+//! `DocumentView`. Files that are `massive_remote::Snapshot`s are rendered with the window size,
+//! camera, and renderer configuration they were captured with.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod markdown;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    massive_shell::shell::run(native::application).await
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    panic!("massive-view reads files and is not supported in the browser, use massive-viewer");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::{
+        env, fs,
+        path::Path,
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+
+    use anyhow::{bail, Context, Result};
+    use cosmic_text::FontSystem;
+    use log::info;
+    use shared::origin_matrix;
+    use winit::{
+        dpi::{LogicalSize, PhysicalSize},
+        event::{ElementState, MouseButton, TouchPhase, WindowEvent},
+    };
+
+    use massive_geometry::{Camera, UnitSystem};
+    use massive_remote::Snapshot;
+    use massive_scene::{CustomShapeCodecs, Position};
+    use massive_shell::{
+        widgets::{DocumentView, Paragraph, ScrollPhysics, ScrollbarStyle},
+        ApplicationContext,
+    };
+
+    use crate::markdown;
+
+    /// The distance of the document from the window's edges, in physical pixels.
+    const MARGIN: f64 = 40.0;
+
+    pub async fn application(ctx: ApplicationContext) -> Result<()> {
+        let Some(path) = env::args().nth(1) else {
+            bail!("Usage: massive-view <path>");
+        };
+        let path = Path::new(&path);
+
+        let is_markdown = path
+            .extension()
+            .is_some_and(|extension| extension == "md" || extension == "markdown");
+        if is_markdown {
+            let text = read_text(path)?;
+            return show_document(ctx, markdown::paragraphs(&text)).await;
+        }
+
+        // Anything that is not a snapshot is shown as text.
+        match Snapshot::read(path) {
+            Ok(snapshot) => show_snapshot(ctx, snapshot).await,
+            Err(e) => {
+                info!("Showing {path:?} as text: {e:?}");
+                let text = read_text(path)?;
+                show_document(ctx, markdown::plain_paragraphs(&text)).await
+            }
+        }
+    }
+
+    fn read_text(path: &Path) -> Result<String> {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {path:?} as text"))
+    }
+
+    /// Render a snapshot with the window size, camera, and renderer configuration it was
+    /// captured with.
+    async fn show_snapshot(mut ctx: ApplicationContext, snapshot: Snapshot) -> Result<()> {
+        let font_system = Arc::new(Mutex::new(FontSystem::new()));
+        let changes = snapshot.scene_changes(
+            &mut font_system.lock().unwrap(),
+            &CustomShapeCodecs::default(),
+        )?;
+
+        let (width, height) = snapshot.config.surface_size;
+        let window = ctx.new_window(PhysicalSize::new(width, height), None)?;
+        let (mut renderer, _director) = window
+            .new_renderer(font_system, snapshot.camera.into(), window.inner_size())
+            .await?;
+        renderer.apply_renderer_config(&snapshot.config);
+        renderer.push_scene_changes(changes);
+
+        loop {
+            if let WindowEvent::CloseRequested = ctx.wait_for_event(&mut renderer).await? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Show the paragraphs in a document view that fills the window.
+    async fn show_document(mut ctx: ApplicationContext, paragraphs: Vec<Paragraph>) -> Result<()> {
+        let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+        let fovy: f64 = 45.0;
+        let camera_distance = UnitSystem::camera_distance(fovy);
+        let camera = Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0));
+
+        let window = ctx.new_window(LogicalSize::new(1024, 800), None)?;
+        let (mut renderer, mut director) = window
+            .new_renderer(font_system.clone(), camera, window.inner_size())
+            .await?;
+
+        let size = window.inner_size();
+        let matrix = director.cast(origin_matrix(size, MARGIN));
+        let position = director.cast(Position::from(matrix.clone()));
+        let (width, height) = view_size(size);
+        let mut view = DocumentView::new(position, paragraphs, width, height);
+        view.scroll_mut().set_physics(ScrollPhysics::touch());
+        view.set_scrollbar(Some(ScrollbarStyle::default()));
+
+        let mut cursor = (0.0, 0.0);
+
+        loop {
+            let event = ctx.wait_for_event(&mut renderer).await?;
+
+            match &event {
+                WindowEvent::CloseRequested => return Ok(()),
+                WindowEvent::Resized(size) => {
+                    matrix.update(origin_matrix(*size, MARGIN));
+                    let (width, height) = view_size(*size);
+                    view.set_width(width);
+                    view.set_viewport_height(height);
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    cursor = (position.x - MARGIN, position.y - MARGIN);
+                    view.pointer_moved(cursor.0, cursor.1);
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => match state {
+                    ElementState::Pressed => view.pointer_pressed(cursor.0, cursor.1, false),
+                    ElementState::Released => view.pointer_released(),
+                },
+                WindowEvent::Touch(touch) => {
+                    let scroll = view.scroll_mut();
+                    let now = Instant::now();
+                    match touch.phase {
+                        TouchPhase::Started => scroll.pan_started(touch.location.y, now),
+                        TouchPhase::Moved => scroll.pan_moved(touch.location.y, now),
+                        TouchPhase::Ended | TouchPhase::Cancelled => scroll.pan_ended(now),
+                    }
+                }
+                _ => {
+                    view.handle_event(&event);
+                }
+            }
+
+            let animating = view.update(
+                &mut director,
+                &mut font_system.lock().unwrap(),
+                Instant::now(),
+            );
+            director.action()?;
+            if animating {
+                window.request_redraw();
+            }
+        }
+    }
+
+    fn view_size(size: PhysicalSize<u32>) -> (f64, f64) {
+        (
+            (size.width as f64 - MARGIN * 2.0).max(1.0),
+            (size.height as f64 - MARGIN * 2.0).max(0.0),
+        )
+    }
+}
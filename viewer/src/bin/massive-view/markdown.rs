@@ -0,0 +1,161 @@
+//! Converts text files to the paragraphs of a `DocumentView`.
+//!
+//! Only the Markdown that matters for reading is supported: Headings, paragraphs, list items,
+//! fenced code blocks, and inline `**bold**`, `*italic*`, and `` `code` `` spans.
+
+use massive_geometry::Color;
+use massive_shapes::TextWeight;
+use massive_shell::widgets::{Paragraph, ParagraphStyle, SpanStyle};
+
+const CODE_COLOR: Color = Color::rgb(0.6, 0.1, 0.1);
+
+/// The paragraphs of a Markdown document.
+pub fn paragraphs(text: &str) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    // The lines of the paragraph that is not finished yet.
+    let mut pending: Vec<&str> = Vec::new();
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            flush(&mut paragraphs, &mut pending);
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            paragraphs.push(code_line(line));
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut paragraphs, &mut pending);
+            continue;
+        }
+        if let Some((level, title)) = heading(trimmed) {
+            flush(&mut paragraphs, &mut pending);
+            let style = ParagraphStyle {
+                font_size: 34.0 - 4.0 * level as f32,
+                ..ParagraphStyle::default()
+            };
+            let bold = SpanStyle {
+                weight: TextWeight::BOLD,
+                ..SpanStyle::default()
+            };
+            paragraphs.push(
+                Paragraph::new(style)
+                    .span(title, bold)
+                    .anchor(slug(title), level),
+            );
+            continue;
+        }
+        if let Some(item) = list_item(trimmed) {
+            flush(&mut paragraphs, &mut pending);
+            let paragraph = Paragraph::default().span("• ", SpanStyle::default());
+            paragraphs.push(inline_spans(paragraph, item));
+            continue;
+        }
+        pending.push(trimmed);
+    }
+    flush(&mut paragraphs, &mut pending);
+
+    paragraphs
+}
+
+/// The lines of a plain text file, in a monospace font.
+pub fn plain_paragraphs(text: &str) -> Vec<Paragraph> {
+    text.lines().map(code_line).collect()
+}
+
+/// Add the pending lines as one paragraph.
+fn flush(paragraphs: &mut Vec<Paragraph>, pending: &mut Vec<&str>) {
+    if pending.is_empty() {
+        return;
+    }
+    let text = pending.join(" ");
+    pending.clear();
+    paragraphs.push(inline_spans(Paragraph::default(), &text));
+}
+
+fn code_line(line: &str) -> Paragraph {
+    let style = ParagraphStyle {
+        font_size: 15.0,
+        spacing: 0.0,
+        ..ParagraphStyle::default()
+    };
+    let code = SpanStyle {
+        monospace: true,
+        ..SpanStyle::default()
+    };
+    // Empty paragraphs would collapse.
+    let line = if line.is_empty() { " " } else { line };
+    Paragraph::new(style).span(line, code)
+}
+
+/// The level and the title of a heading line.
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let title = line[level..].strip_prefix(' ')?;
+    Some((level as u8, title.trim()))
+}
+
+fn list_item(line: &str) -> Option<&str> {
+    line.strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+}
+
+/// An anchor id for a heading, like the ones GitHub generates.
+fn slug(title: &str) -> String {
+    title
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() => Some(c.to_ascii_lowercase()),
+            ' ' | '-' => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Append the text with its inline markup to the paragraph.
+fn inline_spans(mut paragraph: Paragraph, text: &str) -> Paragraph {
+    let mut style = SpanStyle::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(index) = rest.find(['`', '*']) else {
+            paragraph = paragraph.span(rest, style);
+            break;
+        };
+        if index > 0 {
+            paragraph = paragraph.span(&rest[..index], style);
+        }
+        rest = &rest[index..];
+
+        if let Some(code) = rest.strip_prefix('`') {
+            let Some(end) = code.find('`') else {
+                paragraph = paragraph.span(rest, style);
+                break;
+            };
+            let code_style = SpanStyle {
+                color: CODE_COLOR,
+                monospace: true,
+                ..style
+            };
+            paragraph = paragraph.span(&code[..end], code_style);
+            rest = &code[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("**") {
+            style.weight = if style.weight == TextWeight::BOLD {
+                TextWeight::NORMAL
+            } else {
+                TextWeight::BOLD
+            };
+            rest = after;
+        } else {
+            style.italic = !style.italic;
+            rest = &rest[1..];
+        }
+    }
+    paragraph
+}
@@ -0,0 +1,37 @@
+//! A standalone viewer that renders scenes streamed from other processes.
+//!
+//! Usage: `massive-viewer [unix:<path> | tcp:<address> | snapshot:<path>]`
+//!
+//! Clients connect one at a time. When a client connects, the scene is reset. With `snapshot:`,
+//! the viewer shows a `massive_remote::Snapshot` instead.
+//!
+//! Built for the browser (for example with `trunk serve` in this directory), the viewer connects
+//! to a `massive_remote::WebSocketServer` instead. The server's URL is taken
+//! from the `scene` query parameter of the page and defaults to port 7818 of the page's host.
+
+use anyhow::Result;
+use massive_geometry::{Camera, UnitSystem};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    massive_shell::shell::run(native::application).await
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() -> Result<()> {
+    web::main()
+}
+
+fn initial_camera() -> Camera {
+    let fovy: f64 = 45.0;
+    let camera_distance = UnitSystem::camera_distance(fovy);
+    Camera::new((0.0, 0.0, camera_distance), (0.0, 0.0, 0.0))
+}
@@ -0,0 +1,150 @@
+//! Receives scenes from other processes via [`massive_remote::ipc`], or shows a
+//! [`massive_remote::Snapshot`].
+
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::Result;
+use cosmic_text::FontSystem;
+use log::{error, info};
+use tokio::sync::mpsc;
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::WindowEvent,
+};
+
+use massive_remote::{Endpoint, SceneReceiver, Snapshot};
+use massive_scene::{CustomShapeCodecs, SceneChange};
+use massive_shell::ApplicationContext;
+
+use crate::initial_camera;
+
+#[derive(Debug)]
+enum ViewerEvent {
+    Connected,
+    Changes(Vec<SceneChange>),
+    Disconnected,
+}
+
+pub async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let endpoint = match env::args().nth(1) {
+        Some(arg) => match arg.strip_prefix("snapshot:") {
+            Some(path) => return show_snapshot(ctx, path).await,
+            None => arg.parse()?,
+        },
+        None => Endpoint::default(),
+    };
+
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+
+    let (event_sender, mut event_receiver) = mpsc::channel(16);
+    {
+        let font_system = font_system.clone();
+        let listener = endpoint.listen()?;
+        info!("Waiting for clients at {endpoint}");
+        thread::spawn(move || loop {
+            let stream = match listener.accept() {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("Failed to accept client: {e:?}");
+                    continue;
+                }
+            };
+            if event_sender.blocking_send(ViewerEvent::Connected).is_err() {
+                return;
+            }
+            let mut receiver = SceneReceiver::new(stream, font_system.clone());
+            loop {
+                let event = match receiver.receive() {
+                    Ok(Some(changes)) => ViewerEvent::Changes(changes),
+                    Ok(None) => ViewerEvent::Disconnected,
+                    Err(e) => {
+                        error!("Failed to receive scene changes: {e:?}");
+                        ViewerEvent::Disconnected
+                    }
+                };
+                let disconnected = matches!(event, ViewerEvent::Disconnected);
+                if event_sender.blocking_send(event).is_err() {
+                    return;
+                }
+                if disconnected {
+                    break;
+                }
+            }
+        });
+    }
+
+    let camera = initial_camera();
+
+    let window = ctx.new_window(LogicalSize::new(1280, 800), None)?;
+    let (mut renderer, _director) = window
+        .new_renderer(font_system, camera, window.inner_size())
+        .await?;
+
+    // Fit the camera to the first changes a client sends.
+    let mut fit_pending = false;
+
+    loop {
+        let mut viewer_event = None;
+        let window_event = tokio::select! {
+            window_event = ctx.wait_for_event(&mut renderer) => Some(window_event?),
+            event = event_receiver.recv() => {
+                viewer_event = event;
+                None
+            }
+        };
+
+        if let Some(WindowEvent::CloseRequested) = window_event {
+            return Ok(());
+        }
+
+        match viewer_event {
+            Some(ViewerEvent::Connected) => {
+                info!("Client connected");
+                renderer.reset_scene()?;
+                renderer.update_camera(camera);
+                fit_pending = true;
+            }
+            Some(ViewerEvent::Changes(changes)) => {
+                renderer.push_scene_changes(changes);
+                if fit_pending {
+                    renderer.apply_pending_changes()?;
+                    renderer.fit_scene(0.0, None);
+                    fit_pending = false;
+                }
+            }
+            Some(ViewerEvent::Disconnected) => info!("Client disconnected"),
+            None => {}
+        }
+    }
+}
+
+/// Render a snapshot with the window size, camera, and renderer configuration it was captured
+/// with.
+async fn show_snapshot(mut ctx: ApplicationContext, path: &str) -> Result<()> {
+    let snapshot = Snapshot::read(path)?;
+    info!("Atlases at capture time: {:?}", snapshot.atlases);
+
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+    let changes = snapshot.scene_changes(
+        &mut font_system.lock().unwrap(),
+        &CustomShapeCodecs::default(),
+    )?;
+
+    let (width, height) = snapshot.config.surface_size;
+    let window = ctx.new_window(PhysicalSize::new(width, height), None)?;
+    let (mut renderer, _director) = window
+        .new_renderer(font_system, snapshot.camera.into(), window.inner_size())
+        .await?;
+    renderer.apply_renderer_config(&snapshot.config);
+    renderer.push_scene_changes(changes);
+
+    loop {
+        if let WindowEvent::CloseRequested = ctx.wait_for_event(&mut renderer).await? {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Receives scenes in the browser from a `WebSocketServer`.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use cosmic_text::FontSystem;
+use log::{error, info};
+use web_sys::UrlSearchParams;
+use winit::{dpi::LogicalSize, event::WindowEvent};
+
+use massive_remote::WebSocketReceiver;
+use massive_shell::{shell, ApplicationContext};
+
+use crate::initial_camera;
+
+const CANVAS_ID: &str = "massive-viewer";
+const DEFAULT_PORT: u16 = 7818;
+
+pub fn main() -> Result<()> {
+    console_error_panic_hook::set_once();
+    console_log::init().expect("Could not initialize logger");
+
+    wasm_bindgen_futures::spawn_local(async {
+        if let Err(e) = shell::run(application).await {
+            error!("{e:?}");
+        }
+    });
+
+    Ok(())
+}
+
+async fn application(mut ctx: ApplicationContext) -> Result<()> {
+    let url = server_url()?;
+
+    // Empty, the fonts are embedded in the stream.
+    let font_system = Arc::new(Mutex::new(FontSystem::new()));
+    let mut receiver = WebSocketReceiver::connect(&url, font_system.clone())?;
+    info!("Receiving scene from {url}");
+
+    let camera = initial_camera();
+
+    let window = ctx.new_window(LogicalSize::new(1280, 800), Some(CANVAS_ID))?;
+    let (mut renderer, _director) = window
+        .new_renderer(font_system, camera, window.inner_size())
+        .await?;
+
+    let mut connected = true;
+    let mut fit_pending = true;
+
+    loop {
+        let mut changes = None;
+        let window_event = tokio::select! {
+            window_event = ctx.wait_for_event(&mut renderer) => Some(window_event?),
+            received = receiver.receive(), if connected => {
+                match received {
+                    Some(received) => changes = Some(received),
+                    None => {
+                        info!("Server closed the connection");
+                        connected = false;
+                    }
+                }
+                None
+            }
+        };
+
+        if let Some(WindowEvent::CloseRequested) = window_event {
+            return Ok(());
+        }
+
+        if let Some(changes) = changes {
+            renderer.push_scene_changes(changes);
+            if fit_pending {
+                renderer.apply_pending_changes()?;
+                renderer.fit_scene(0.0, None);
+                fit_pending = false;
+            }
+        }
+    }
+}
+
+/// The URL from the `scene` query parameter, or the default port on the page's host.
+fn server_url() -> Result<String> {
+    let location = web_sys::window()
+        .ok_or_else(|| anyhow!("No window"))?
+        .location();
+    let js_error = |e| anyhow!("{e:?}");
+
+    let search = location.search().map_err(js_error)?;
+    let params = UrlSearchParams::new_with_str(&search).map_err(js_error)?;
+    if let Some(url) = params.get("scene") {
+        return Ok(url);
+    }
+
+    let hostname = location.hostname().map_err(js_error)?;
+    Ok(format!("ws://{hostname}:{DEFAULT_PORT}"))
+}